@@ -10,7 +10,7 @@ fn main() {
         loop {
             match sx.recv() {
                 Ok(frame) => {
-                    println!("frame: {:?}", frame.width);
+                    println!("frame: {:?}", frame.width());
                 }
                 _ => continue,
             }
@@ -0,0 +1,275 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{ActiveTitle, TitleWatcher, Window, XCapResult};
+
+/// A stretch of time during which one window had focus, see [`ActivityTimeline`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivitySpan {
+    /// The application name.
+    pub app_name: String,
+    /// The application process id.
+    pub pid: i32,
+    /// The focused window's title.
+    pub title: String,
+    /// When the window gained focus or its title changed to [`ActivitySpan::title`].
+    pub start: Instant,
+    /// When focus or the title changed again, or the time of the query for the current span.
+    pub end: Instant,
+}
+
+impl ActivitySpan {
+    /// How long the span lasted.
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_duration_since(self.start)
+    }
+}
+
+/// 已经结束的片段和当前的前台窗口
+#[derive(Debug, Default)]
+struct Timeline {
+    spans: Vec<ActivitySpan>,
+    current: Option<(ActiveTitle, Instant)>,
+}
+
+impl Timeline {
+    /// 前台窗口或标题变化，结束当前片段
+    fn focus(&mut self, active: ActiveTitle, now: Instant) {
+        self.close(now);
+        self.current = Some((active, now));
+    }
+
+    fn close(&mut self, now: Instant) {
+        if let Some((active, start)) = self.current.take() {
+            self.spans.push(ActivitySpan {
+                app_name: active.app_name,
+                pid: active.pid,
+                title: active.title,
+                start,
+                end: now,
+            });
+        }
+    }
+
+    /// 所有片段，当前片段在 now 结束
+    fn spans(&self, now: Instant) -> Vec<ActivitySpan> {
+        let mut spans = self.spans.clone();
+        if let Some((active, start)) = &self.current {
+            spans.push(ActivitySpan {
+                app_name: active.app_name.clone(),
+                pid: active.pid,
+                title: active.title.clone(),
+                start: *start,
+                end: now.max(*start),
+            });
+        }
+
+        spans
+    }
+}
+
+/// 和 [from, to) 相交的片段，裁剪到范围内
+fn clip(spans: Vec<ActivitySpan>, from: Instant, to: Instant) -> Vec<ActivitySpan> {
+    spans
+        .into_iter()
+        .filter(|span| span.start < to && span.end > from)
+        .map(|mut span| {
+            span.start = span.start.max(from);
+            span.end = span.end.min(to);
+            span
+        })
+        .collect()
+}
+
+/// 按应用汇总时长，时长最长的在前
+fn app_durations(spans: &[ActivitySpan]) -> Vec<(String, Duration)> {
+    let mut durations: HashMap<&str, Duration> = HashMap::new();
+    for span in spans {
+        *durations.entry(&span.app_name).or_default() += span.duration();
+    }
+
+    let mut durations: Vec<(String, Duration)> = durations
+        .into_iter()
+        .map(|(app_name, duration)| (app_name.to_string(), duration))
+        .collect();
+    durations.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    durations
+}
+
+/// Records which application and window had focus over time, without capturing any pixels,
+/// e.g. for time trackers. Focus and title changes come from
+/// [`Window::watch_active_title`], so their times are accurate to the sampling interval.
+///
+/// Changing the title within the same application, e.g. switching browser tabs, starts a new
+/// [`ActivitySpan`]. Moments without a focused window are attributed to the previous one.
+/// Dropping the timeline stops recording.
+///
+/// ```no_run
+/// use std::{thread, time::Duration};
+///
+/// use xcap::ActivityTimeline;
+///
+/// let timeline = ActivityTimeline::start(Duration::from_millis(500)).unwrap();
+/// thread::sleep(Duration::from_secs(60));
+///
+/// for (app_name, duration) in timeline.app_durations().unwrap() {
+///     println!("{app_name}: {duration:?}");
+/// }
+/// for span in timeline.stop() {
+///     println!("{:?} {}: {}", span.duration(), span.app_name, span.title);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ActivityTimeline {
+    timeline: Arc<Mutex<Timeline>>,
+    title_watcher: Option<TitleWatcher>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ActivityTimeline {
+    /// Start recording, sampling the focused window every `interval`.
+    pub fn start(interval: Duration) -> XCapResult<ActivityTimeline> {
+        let (title_watcher, rx) = Window::watch_active_title(interval)?;
+        let timeline = Arc::new(Mutex::new(Timeline::default()));
+
+        let worker = {
+            let timeline = timeline.clone();
+            // TitleWatcher 停止后通道关闭，线程退出
+            thread::spawn(move || {
+                for active in rx {
+                    let Ok(mut timeline) = timeline.lock() else {
+                        break;
+                    };
+                    timeline.focus(active, Instant::now());
+                }
+            })
+        };
+
+        Ok(ActivityTimeline {
+            timeline,
+            title_watcher: Some(title_watcher),
+            worker: Some(worker),
+        })
+    }
+
+    /// Every span recorded so far, oldest first. The last one is the current focus and ends
+    /// now.
+    pub fn spans(&self) -> XCapResult<Vec<ActivitySpan>> {
+        Ok(self.timeline.lock()?.spans(Instant::now()))
+    }
+
+    /// The spans overlapping `from..to`, clipped to that range.
+    pub fn spans_between(&self, from: Instant, to: Instant) -> XCapResult<Vec<ActivitySpan>> {
+        Ok(clip(self.spans()?, from, to))
+    }
+
+    /// The span that had focus at `instant`, if recording had started by then.
+    pub fn active_at(&self, instant: Instant) -> XCapResult<Option<ActivitySpan>> {
+        Ok(self
+            .spans()?
+            .into_iter()
+            .find(|span| span.start <= instant && instant < span.end))
+    }
+
+    /// The total focus time of each application, longest first.
+    pub fn app_durations(&self) -> XCapResult<Vec<(String, Duration)>> {
+        Ok(app_durations(&self.spans()?))
+    }
+
+    /// The total focus time of each application within `from..to`, longest first.
+    pub fn app_durations_between(
+        &self,
+        from: Instant,
+        to: Instant,
+    ) -> XCapResult<Vec<(String, Duration)>> {
+        Ok(app_durations(&self.spans_between(from, to)?))
+    }
+
+    /// Stop recording and return every span, the last one ending now.
+    pub fn stop(mut self) -> Vec<ActivitySpan> {
+        self.stop_worker();
+
+        match self.timeline.lock() {
+            Ok(mut timeline) => {
+                timeline.close(Instant::now());
+                timeline.spans.clone()
+            }
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn stop_worker(&mut self) {
+        if let Some(title_watcher) = self.title_watcher.take() {
+            title_watcher.stop();
+        }
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for ActivityTimeline {
+    fn drop(&mut self) {
+        self.stop_worker();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn active(app_name: &str, title: &str) -> ActiveTitle {
+        ActiveTitle {
+            app_name: app_name.to_string(),
+            pid: 1,
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_timeline() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let mut timeline = Timeline::default();
+        timeline.focus(active("Browser", "Docs"), at(0));
+        timeline.focus(active("Browser", "Mail"), at(10));
+        timeline.focus(active("Editor", "main.rs"), at(15));
+
+        let spans = timeline.spans(at(45));
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[1].title, "Mail");
+        assert_eq!(spans[2].duration(), Duration::from_secs(30));
+
+        assert_eq!(
+            app_durations(&spans),
+            [
+                ("Editor".to_string(), Duration::from_secs(30)),
+                ("Browser".to_string(), Duration::from_secs(15)),
+            ]
+        );
+
+        // 裁剪到范围内
+        let clipped = clip(spans, at(5), at(20));
+        assert_eq!(clipped.len(), 3);
+        assert_eq!(clipped[0].start, at(5));
+        assert_eq!(clipped[2].end, at(20));
+        assert_eq!(
+            app_durations(&clipped),
+            [
+                ("Browser".to_string(), Duration::from_secs(10)),
+                ("Editor".to_string(), Duration::from_secs(5)),
+            ]
+        );
+
+        timeline.close(at(50));
+        assert_eq!(timeline.spans(at(100)).len(), 3);
+        assert_eq!(timeline.spans[2].end, at(50));
+    }
+}
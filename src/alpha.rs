@@ -0,0 +1,175 @@
+use image::{Rgba, RgbaImage};
+
+/// How the alpha channel of a captured image is stored.
+///
+/// Every backend (ScreenCaptureKit/CGImage, GDI `PrintWindow`, X11 ARGB visuals, PipeWire)
+/// hands out premultiplied pixels, so `Premultiplied` is the default on all platforms and costs
+/// nothing. Opaque pixels are identical in both modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlphaMode {
+    /// Color channels are already multiplied by alpha, as delivered by the platform.
+    #[default]
+    Premultiplied,
+    /// Color channels are independent of alpha, which is what `image` and PNG encoders expect.
+    Straight,
+}
+
+pub(crate) fn apply_alpha_mode(image: &mut RgbaImage, alpha_mode: AlphaMode) {
+    if alpha_mode == AlphaMode::Straight {
+        unpremultiply_rgba(image);
+    }
+}
+
+/// 将预乘 alpha 的 RGBA 数据合成到纯色背景上，背景不透明时结果完全不透明，
+/// 背景半透明时结果的透明度为两者叠加后的透明度
+pub(crate) fn composite_over(buffer: &mut [u8], background: Rgba<u8>) {
+    let background_alpha = background[3] as u32;
+
+    for pixel in buffer.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        if alpha == 255 {
+            continue;
+        }
+
+        let inverse = 255 - alpha;
+        for (channel, background_channel) in pixel[..3].iter_mut().zip(background.0) {
+            // 背景色是直通 alpha，需要先预乘
+            let background_channel = background_channel as u32 * background_alpha / 255;
+            *channel =
+                (*channel as u32 + (background_channel * inverse + 127) / 255).min(255) as u8;
+        }
+        pixel[3] = (alpha + (background_alpha * inverse + 127) / 255).min(255) as u8;
+    }
+}
+
+/// 将预乘 alpha 的 RGBA 数据转换为直通 alpha
+///
+/// 使用 SIMD 一次检查 4 个像素，全部不透明时直接跳过，只有半透明像素才逐个做除法
+pub(crate) fn unpremultiply_rgba(buffer: &mut [u8]) {
+    let simd_len = buffer.len() / 16 * 16;
+    let (head, tail) = buffer.split_at_mut(simd_len);
+
+    for block in head.chunks_exact_mut(16) {
+        if is_opaque_block(block) {
+            continue;
+        }
+
+        for pixel in block.chunks_exact_mut(4) {
+            unpremultiply_pixel(pixel);
+        }
+    }
+
+    for pixel in tail.chunks_exact_mut(4) {
+        unpremultiply_pixel(pixel);
+    }
+}
+
+#[inline]
+fn unpremultiply_pixel(pixel: &mut [u8]) {
+    let alpha = pixel[3] as u32;
+    if alpha == 255 || alpha == 0 {
+        return;
+    }
+
+    for channel in &mut pixel[..3] {
+        *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+    }
+}
+
+/// 判断 16 字节（4 个像素）是否全部不透明
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn is_opaque_block(block: &[u8]) -> bool {
+    use std::arch::x86_64::*;
+
+    // SSE2 是 x86_64 的基础指令集，无需运行时检测
+    unsafe {
+        let data = _mm_loadu_si128(block.as_ptr() as *const __m128i);
+        let alpha_mask = _mm_set1_epi32(0xFF000000u32 as i32);
+        let alpha = _mm_and_si128(data, alpha_mask);
+        let is_opaque = _mm_cmpeq_epi32(alpha, alpha_mask);
+
+        _mm_movemask_epi8(is_opaque) == 0xFFFF
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline]
+fn is_opaque_block(block: &[u8]) -> bool {
+    use std::arch::aarch64::*;
+
+    unsafe {
+        let data = vld1q_u8(block.as_ptr());
+        let alpha_mask = vreinterpretq_u8_u32(vdupq_n_u32(0xFF000000));
+        let alpha = vandq_u8(data, alpha_mask);
+        let is_opaque = vceqq_u8(alpha, alpha_mask);
+
+        vminvq_u8(is_opaque) == 0xFF
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[inline]
+fn is_opaque_block(block: &[u8]) -> bool {
+    block.chunks_exact(4).all(|pixel| pixel[3] == 255)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unpremultiply_rgba() {
+        let mut buffer = vec![
+            10, 20, 30, 255, // 不透明
+            64, 32, 0, 128, // 半透明
+            0, 0, 0, 0, // 全透明
+            50, 50, 50, 255, // 不透明
+            100, 50, 25, 200, // 余数部分
+        ];
+
+        unpremultiply_rgba(&mut buffer);
+
+        assert_eq!(
+            buffer,
+            vec![
+                10, 20, 30, 255, 128, 64, 0, 128, 0, 0, 0, 0, 50, 50, 50, 255, 128, 64, 32, 200
+            ]
+        );
+    }
+
+    #[test]
+    fn test_composite_over() {
+        let mut buffer = vec![10, 20, 30, 255, 64, 32, 0, 128, 0, 0, 0, 0];
+
+        composite_over(&mut buffer, Rgba([255, 255, 255, 255]));
+
+        assert_eq!(
+            buffer,
+            vec![10, 20, 30, 255, 191, 159, 127, 255, 255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn test_composite_over_translucent_background() {
+        let mut buffer = vec![10, 20, 30, 255, 64, 32, 0, 128, 0, 0, 0, 0];
+
+        composite_over(&mut buffer, Rgba([255, 255, 255, 128]));
+
+        // 不透明像素不变，其余像素的透明度为两者叠加，仍然半透明
+        assert_eq!(
+            buffer,
+            vec![10, 20, 30, 255, 128, 96, 64, 192, 128, 128, 128, 128]
+        );
+    }
+
+    #[test]
+    fn test_unpremultiply_rgba_opaque_block() {
+        let mut buffer = [7u8, 8, 9, 255].repeat(8);
+        let expected = buffer.clone();
+
+        unpremultiply_rgba(&mut buffer);
+
+        assert_eq!(buffer, expected);
+    }
+}
@@ -1,9 +1,13 @@
 use crate::{
+    ActiveInfoMode, Backend, BackendInfo, CaptureConfig, MonitorIdentity, PixelEncoding, Point, PowerState, Rect, RecorderConfig, RecorderUpdate, RefreshRateRange, WindowLayer,
     error::{XCapError, XCapResult},
-    video_recorder::Frame,
+    video_recorder::{Frame, RecorderHealth},
 };
 use image::RgbaImage;
-use std::sync::mpsc::Receiver;
+use std::{
+    sync::{Arc, mpsc::Receiver},
+    time::Duration,
+};
 
 #[derive(Debug, Clone)]
 pub struct ImplMonitor;
@@ -49,10 +53,40 @@ impl ImplMonitor {
         Err(XCapError::NotSupported)
     }
 
+    pub fn pixel_ratio(&self) -> XCapResult<f32> {
+        Err(XCapError::NotSupported)
+    }
+
     pub fn frequency(&self) -> XCapResult<f32> {
         Err(XCapError::NotSupported)
     }
 
+    pub fn refresh_rate_range(&self) -> XCapResult<RefreshRateRange> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn identity(&self) -> XCapResult<MonitorIdentity> {
+        Err(XCapError::NotSupported)
+    }
+
+    #[cfg(feature = "ddc")]
+    pub fn ddc_capabilities(&self) -> XCapResult<String> {
+        Err(XCapError::NotSupported)
+    }
+
+    #[cfg(feature = "ddc")]
+    pub fn vcp_feature(&self, _code: u8) -> XCapResult<(u16, u16)> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn bit_depth(&self) -> XCapResult<u32> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn pixel_encoding(&self) -> XCapResult<PixelEncoding> {
+        Err(XCapError::NotSupported)
+    }
+
     pub fn is_primary(&self) -> XCapResult<bool> {
         Err(XCapError::NotSupported)
     }
@@ -61,6 +95,26 @@ impl ImplMonitor {
         Err(XCapError::NotSupported)
     }
 
+    pub fn power_state(&self) -> XCapResult<PowerState> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn brightness(&self) -> XCapResult<f32> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn can_capture(&self) -> XCapResult<bool> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn is_mirrored(&self) -> XCapResult<bool> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn mirror_group(&self) -> XCapResult<Vec<ImplMonitor>> {
+        Err(XCapError::NotSupported)
+    }
+
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
         Err(XCapError::NotSupported)
     }
@@ -69,6 +123,18 @@ impl ImplMonitor {
         Err(XCapError::NotSupported)
     }
 
+    pub fn wait_for_present(&self) -> XCapResult<()> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn capture_thumbnail(&self, _width: u32, _height: u32) -> XCapResult<RgbaImage> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn capture_image_with_config(&self, _config: &CaptureConfig) -> XCapResult<RgbaImage> {
+        Err(XCapError::NotSupported)
+    }
+
     pub fn capture_region(
         &self,
         _x: u32,
@@ -79,7 +145,11 @@ impl ImplMonitor {
         Err(XCapError::NotSupported)
     }
 
-    pub fn video_recorder(&self) -> XCapResult<(ImplVideoRecorder, Receiver<Frame>)> {
+    pub fn video_recorder(
+        &self,
+        _config: &RecorderConfig,
+        _health: Arc<RecorderHealth>,
+    ) -> XCapResult<(ImplVideoRecorder, Receiver<Frame>)> {
         Err(XCapError::NotSupported)
     }
 }
@@ -92,6 +162,14 @@ impl ImplWindow {
         Ok(Vec::new())
     }
 
+    pub fn active_info_mode() -> ActiveInfoMode {
+        ActiveInfoMode::Polling
+    }
+
+    pub fn get_active_title() -> XCapResult<(String, i32, String)> {
+        Err(XCapError::NotSupported)
+    }
+
     pub fn id(&self) -> XCapResult<u32> {
         Err(XCapError::NotSupported)
     }
@@ -112,6 +190,10 @@ impl ImplWindow {
         Err(XCapError::NotSupported)
     }
 
+    pub fn pixel_ratio(&self) -> XCapResult<f32> {
+        Err(XCapError::NotSupported)
+    }
+
     pub fn x(&self) -> XCapResult<i32> {
         Err(XCapError::NotSupported)
     }
@@ -140,13 +222,59 @@ impl ImplWindow {
         Err(XCapError::NotSupported)
     }
 
+    pub fn normal_bounds(&self) -> XCapResult<Rect> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn parent_id(&self) -> XCapResult<Option<u32>> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn focused_id() -> XCapResult<u32> {
+        Err(XCapError::NotSupported)
+    }
+
     pub fn is_focused(&self) -> XCapResult<bool> {
         Err(XCapError::NotSupported)
     }
 
+    pub fn layer(&self) -> XCapResult<WindowLayer> {
+        Err(XCapError::NotSupported)
+    }
+
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
         Err(XCapError::NotSupported)
     }
+
+    pub fn capture_region(
+        &self,
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> XCapResult<RgbaImage> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn scroll(&self, _clicks: u32) -> XCapResult<()> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn can_capture(&self) -> XCapResult<bool> {
+        Err(XCapError::NotSupported)
+    }
+}
+
+pub struct ImplWindowDamage;
+
+impl ImplWindowDamage {
+    pub fn new(_window_id: u32) -> XCapResult<ImplWindowDamage> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn wait(&mut self, _timeout: Duration) -> XCapResult<Vec<Rect>> {
+        Err(XCapError::NotSupported)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -160,16 +288,119 @@ impl ImplVideoRecorder {
     pub fn stop(&self) -> XCapResult<()> {
         Err(XCapError::NotSupported)
     }
+
+    pub fn reconfigure(&self, _update: &RecorderUpdate) -> XCapResult<()> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn shutdown(&self) -> XCapResult<()> {
+        Err(XCapError::NotSupported)
+    }
+}
+
+pub mod external_encoder_surface {
+    use crate::Frame;
+
+    /// The GPU surface a recorder frame was captured into, see [`Frame::encoder_surface`].
+    /// Recording is not supported on Android, so frames never carry a surface.
+    #[derive(Debug, Clone)]
+    pub struct ExternalEncoderSurface {
+        _private: (),
+    }
+
+    pub(crate) fn encoder_surface(_frame: &Frame) -> Option<ExternalEncoderSurface> {
+        None
+    }
 }
 
 pub mod impl_monitor {
     pub use super::ImplMonitor;
 }
 
+#[cfg(feature = "selector")]
+pub mod impl_selector {
+    use crate::{
+        error::{XCapError, XCapResult},
+        geometry::Rect,
+        selector::{Outcome, Selection},
+    };
+
+    pub(crate) fn run_selector(
+        _bounds: &[Rect],
+        _selection: Selection,
+    ) -> XCapResult<Option<Outcome>> {
+        Err(XCapError::NotSupported)
+    }
+}
+
 pub mod impl_video_recorder {
     pub use super::ImplVideoRecorder;
 }
 
+#[cfg(feature = "virtual-camera")]
+pub mod impl_virtual_camera {
+    use std::path::Path;
+
+    use crate::error::{XCapError, XCapResult};
+
+    #[derive(Debug)]
+    pub struct ImplVirtualCamera;
+
+    impl ImplVirtualCamera {
+        pub fn open(_device: &Path, _width: u32, _height: u32) -> XCapResult<ImplVirtualCamera> {
+            Err(XCapError::NotSupported)
+        }
+
+        pub fn write(&mut self, _yuyv: &[u8]) -> XCapResult<()> {
+            Err(XCapError::NotSupported)
+        }
+    }
+}
+
+#[cfg(feature = "virtual-display")]
+pub mod impl_virtual_display {
+    use super::ImplMonitor;
+    use crate::error::{XCapError, XCapResult};
+
+    #[derive(Debug)]
+    pub struct ImplVirtualDisplay;
+
+    impl ImplVirtualDisplay {
+        pub fn new(_width: u32, _height: u32) -> XCapResult<ImplVirtualDisplay> {
+            Err(XCapError::NotSupported)
+        }
+
+        pub fn monitor(&self) -> XCapResult<ImplMonitor> {
+            Err(XCapError::NotSupported)
+        }
+    }
+}
+
+pub mod impl_wake_lock {
+    use crate::error::{XCapError, XCapResult};
+
+    #[derive(Debug)]
+    pub struct ImplWakeLock;
+
+    impl ImplWakeLock {
+        pub fn new() -> XCapResult<ImplWakeLock> {
+            Err(XCapError::NotSupported)
+        }
+    }
+}
+
 pub mod impl_window {
-    pub use super::ImplWindow;
+    pub use super::{ImplWindow, ImplWindowDamage};
+}
+
+pub(crate) fn shutdown() -> XCapResult<()> {
+    Ok(())
+}
+
+pub(crate) fn backend_info() -> BackendInfo {
+    BackendInfo::new(Backend::Auto, false)
+}
+
+pub(crate) fn cursor_position() -> XCapResult<Point> {
+    Err(XCapError::NotSupported)
 }
@@ -0,0 +1,108 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::XCapResult;
+
+// 没有帧取走标注时最多保留的数量，超出时丢弃最早的
+const MAX_PENDING: usize = 1024;
+
+/// A timestamped marker added with [`VideoRecorder::annotate`](crate::VideoRecorder::annotate),
+/// e.g. a click or key press from an input hook, delivered with the first frame captured at or
+/// after it. See [`Frame::annotations`](crate::Frame::annotations).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    timestamp: Instant,
+    payload: Arc<[u8]>,
+}
+
+impl Annotation {
+    /// When the annotated event happened, on the same timeline as
+    /// [`Frame::timestamp`](crate::Frame::timestamp).
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+    /// The bytes passed to [`VideoRecorder::annotate`](crate::VideoRecorder::annotate).
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+}
+
+/// 一个帧流等待附加到帧上的标注，按时间戳排序
+#[derive(Debug, Default)]
+pub(crate) struct AnnotationQueue {
+    pending: Mutex<VecDeque<Annotation>>,
+}
+
+impl AnnotationQueue {
+    pub fn push(&self, timestamp: Instant, payload: Arc<[u8]>) -> XCapResult<()> {
+        let mut pending = self.pending.lock()?;
+        insert(&mut pending, Annotation { timestamp, payload });
+
+        Ok(())
+    }
+    /// 取出时间戳不晚于 timestamp 的标注
+    pub fn take(&self, timestamp: Instant) -> Vec<Annotation> {
+        let Ok(mut pending) = self.pending.lock() else {
+            return Vec::new();
+        };
+
+        let len = pending.partition_point(|annotation| annotation.timestamp <= timestamp);
+        pending.drain(..len).collect()
+    }
+    /// 帧没有送达时把它的标注放回，附加到下一帧
+    pub fn restore(&self, annotations: Vec<Annotation>) {
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+
+        for annotation in annotations {
+            insert(&mut pending, annotation);
+        }
+    }
+}
+
+/// 时间戳相同的标注保持添加顺序
+fn insert(pending: &mut VecDeque<Annotation>, annotation: Annotation) {
+    let index = pending.partition_point(|pending| pending.timestamp <= annotation.timestamp);
+    pending.insert(index, annotation);
+
+    if pending.len() > MAX_PENDING {
+        pending.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_annotation_queue() {
+        let queue = AnnotationQueue::default();
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        queue.push(at(20), Arc::from(&b"b"[..])).unwrap();
+        queue.push(at(10), Arc::from(&b"a"[..])).unwrap();
+        queue.push(at(30), Arc::from(&b"c"[..])).unwrap();
+
+        // 只取出帧之前的标注，按时间排序
+        let taken = queue.take(at(25));
+        let payloads: Vec<&[u8]> = taken.iter().map(Annotation::payload).collect();
+        assert_eq!(payloads, [b"a", b"b"]);
+
+        // 放回的标注附加到下一帧
+        queue.restore(taken);
+        let payloads: Vec<Vec<u8>> = queue
+            .take(at(30))
+            .iter()
+            .map(|annotation| annotation.payload().to_vec())
+            .collect();
+        assert_eq!(payloads, [b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+        assert!(queue.take(at(100)).is_empty());
+    }
+}
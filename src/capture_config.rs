@@ -0,0 +1,243 @@
+use std::{thread, time::Duration};
+
+use image::{Rgba, RgbaImage};
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+use crate::WindowLayer;
+#[cfg(target_os = "macos")]
+use crate::platform::capture_config_ext::StreamOptions;
+use crate::{
+    AlphaMode, Backend, Redactor, XCapResult,
+    alpha::{apply_alpha_mode, composite_over},
+    capture_report::{self, CaptureReport},
+};
+
+/// Options applied to a single capture call.
+///
+/// ```no_run
+/// use xcap::{AlphaMode, CaptureConfig, Window};
+///
+/// let config = CaptureConfig::new().alpha_mode(AlphaMode::Straight);
+/// let window = Window::all().unwrap().remove(0);
+/// let image = window.capture_image_with_config(&config).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct CaptureConfig {
+    pub(crate) alpha_mode: AlphaMode,
+    pub(crate) background_color: Option<Rgba<u8>>,
+    pub(crate) exclude_menu_bar: bool,
+    pub(crate) exclude_dock: bool,
+    pub(crate) exclude_desktop_icons: bool,
+    pub(crate) retries: u32,
+    pub(crate) backoff: Duration,
+    pub(crate) redactor: Option<Redactor>,
+    pub(crate) backend: Option<Backend>,
+    #[cfg(target_os = "macos")]
+    pub(crate) stream_options: StreamOptions,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig {
+            alpha_mode: AlphaMode::default(),
+            background_color: None,
+            exclude_menu_bar: false,
+            exclude_dock: false,
+            exclude_desktop_icons: false,
+            retries: 0,
+            backoff: Duration::from_millis(100),
+            redactor: None,
+            backend: None,
+            #[cfg(target_os = "macos")]
+            stream_options: StreamOptions::default(),
+        }
+    }
+}
+
+impl CaptureConfig {
+    pub fn new() -> CaptureConfig {
+        CaptureConfig::default()
+    }
+
+    /// How alpha is stored in the returned image, defaults to [`AlphaMode::Premultiplied`].
+    pub fn alpha_mode(mut self, alpha_mode: AlphaMode) -> CaptureConfig {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    /// Composite translucent and rounded-corner pixels over a solid color. The image is fully
+    /// opaque when the color is opaque; a translucent color leaves those pixels translucent.
+    /// By default transparency is preserved.
+    pub fn background_color(mut self, background_color: Rgba<u8>) -> CaptureConfig {
+        self.background_color = Some(background_color);
+        self
+    }
+
+    /// Keep the captured transparency as-is (the default).
+    pub fn preserve_transparency(mut self) -> CaptureConfig {
+        self.background_color = None;
+        self
+    }
+
+    /// Leave the menu bar and status items out of monitor captures. On Windows this covers the
+    /// notification area overflow.
+    /// Supported on macOS, Windows and X11, Wayland returns
+    /// [`XCapError::NotSupported`](crate::XCapError::NotSupported).
+    pub fn exclude_menu_bar(mut self, exclude_menu_bar: bool) -> CaptureConfig {
+        self.exclude_menu_bar = exclude_menu_bar;
+        self
+    }
+
+    /// Leave the Dock, or the taskbar on Windows and dock windows such as panels on X11, out of
+    /// monitor captures.
+    /// Supported on macOS, Windows and X11, Wayland returns
+    /// [`XCapError::NotSupported`](crate::XCapError::NotSupported).
+    pub fn exclude_dock(mut self, exclude_dock: bool) -> CaptureConfig {
+        self.exclude_dock = exclude_dock;
+        self
+    }
+
+    /// Leave desktop icons out of monitor captures, showing the wallpaper instead.
+    /// Supported on macOS, Windows and X11, Wayland returns
+    /// [`XCapError::NotSupported`](crate::XCapError::NotSupported).
+    pub fn exclude_desktop_icons(mut self, exclude_desktop_icons: bool) -> CaptureConfig {
+        self.exclude_desktop_icons = exclude_desktop_icons;
+        self
+    }
+
+    /// Retry a failed capture up to `retries` times, for transient failures such as a
+    /// ScreenCaptureKit timeout, a lost desktop duplication or a busy portal. Other errors,
+    /// e.g. a denied permission, are returned immediately, see
+    /// [`XCapError::is_transient`](crate::XCapError::is_transient).
+    /// Defaults to no retries.
+    pub fn retries(mut self, retries: u32) -> CaptureConfig {
+        self.retries = retries;
+        self
+    }
+
+    /// Delay before the first retry, doubled after every further attempt. Defaults to 100ms.
+    pub fn backoff(mut self, backoff: Duration) -> CaptureConfig {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Hide the regions of `redactor` in monitor captures, see [`Redactor`]. Window captures
+    /// are not redacted.
+    pub fn redactor(mut self, redactor: Redactor) -> CaptureConfig {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// Require `backend` for this capture instead of the process-wide
+    /// [`ConfigBuilder::backend`](crate::ConfigBuilder::backend). A required backend never falls
+    /// back: when it fails, its error is returned. [`Backend::Auto`] allows falling back, and
+    /// backends of other platforms are treated as [`Backend::Auto`].
+    pub fn backend(mut self, backend: Backend) -> CaptureConfig {
+        self.backend = Some(backend);
+        self
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+    pub(crate) fn excludes_system_windows(&self) -> bool {
+        self.exclude_menu_bar || self.exclude_dock || self.exclude_desktop_icons
+    }
+
+    /// 窗口层级是否被排除，菜单栏包括状态栏图标
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    pub(crate) fn excludes_layer(&self, layer: WindowLayer) -> bool {
+        match layer {
+            WindowLayer::MenuBar | WindowLayer::StatusItem => self.exclude_menu_bar,
+            WindowLayer::Dock => self.exclude_dock,
+            WindowLayer::Desktop => self.exclude_desktop_icons,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn apply(&self, image: &mut RgbaImage) {
+        // 合成背景必须在去预乘之前进行，合成后的像素都是不透明的
+        if let Some(background_color) = self.background_color {
+            composite_over(image, background_color);
+        }
+        apply_alpha_mode(image, self.alpha_mode);
+    }
+
+    /// 按配置的后端和重试执行截图，同时返回截图报告
+    pub(crate) fn capture<T, F>(&self, mut capture: F) -> (XCapResult<T>, CaptureReport)
+    where
+        F: FnMut() -> XCapResult<T>,
+    {
+        capture_report::scope(self.backend, || {
+            self.retry(|| {
+                capture_report::start_attempt();
+                capture()
+            })
+        })
+    }
+
+    /// 按重试配置执行截图，失败后按指数退避等待再重试
+    pub(crate) fn retry<T, F>(&self, mut capture: F) -> XCapResult<T>
+    where
+        F: FnMut() -> XCapResult<T>,
+    {
+        let mut backoff = self.backoff;
+        let mut attempt = 0;
+
+        loop {
+            match capture() {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt < self.retries && err.is_transient() => {
+                    log::warn!("Capture failed, retrying in {backoff:?}: {err:?}");
+                    thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2);
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::XCapError;
+
+    use super::*;
+
+    #[test]
+    fn test_retry() {
+        let config = CaptureConfig::new().retries(2).backoff(Duration::ZERO);
+
+        let mut attempts = 0;
+        let result = config.retry(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(XCapError::transient("timeout"))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut attempts = 0;
+        let result: XCapResult<()> = config.retry(|| {
+            attempts += 1;
+            Err(XCapError::NotSupported)
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_retry_permanent_error() {
+        let config = CaptureConfig::new().retries(3).backoff(Duration::ZERO);
+
+        // 权限被拒绝等错误不是暂时的，只尝试一次
+        let mut attempts = 0;
+        let result: XCapResult<()> = config.retry(|| {
+            attempts += 1;
+            Err(XCapError::new("Screen recording permission denied"))
+        });
+        assert!(matches!(result, Err(XCapError::Error(_))));
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,147 @@
+use std::cell::RefCell;
+
+#[cfg(any(target_os = "macos", test))]
+use crate::XCapError;
+#[cfg(any(target_os = "macos", target_os = "linux", test))]
+use crate::config::Config;
+use crate::{Backend, XCapResult};
+
+/// How a capture was taken, returned by
+/// [`Monitor::capture_image_with_report`](crate::Monitor::capture_image_with_report) and
+/// [`Window::capture_image_with_report`](crate::Window::capture_image_with_report).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CaptureReport {
+    backend: Backend,
+    fallback: Option<(Backend, String)>,
+    attempts: u32,
+}
+
+impl CaptureReport {
+    /// The backend that produced the image, [`Backend::Auto`] on platforms that have only one.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+    /// Whether a preferred backend failed and the capture fell back to
+    /// [`CaptureReport::backend`], e.g. ScreenCaptureKit timing out on macOS.
+    pub fn fell_back(&self) -> bool {
+        self.fallback.is_some()
+    }
+    /// The backend that failed before the fallback and its error.
+    pub fn fallback(&self) -> Option<(Backend, &str)> {
+        self.fallback
+            .as_ref()
+            .map(|(backend, error)| (*backend, error.as_str()))
+    }
+    /// How many times the capture ran, more than 1 when
+    /// [`CaptureConfig::retries`](crate::CaptureConfig::retries) retried it.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+/// 当前线程正在进行的截图：调用方要求的后端和记录的报告
+#[derive(Debug)]
+struct Scope {
+    #[cfg(any(target_os = "macos", target_os = "linux", test))]
+    backend: Option<Backend>,
+    report: CaptureReport,
+}
+
+thread_local! {
+    static SCOPE: RefCell<Option<Scope>> = const { RefCell::new(None) };
+}
+
+/// 在 f 执行期间用 backend 覆盖全局配置的后端，并收集平台代码记录的报告。
+/// 平台代码在同一个线程上完成截图，嵌套调用时外层的报告保持不变
+pub(crate) fn scope<T, F>(backend: Option<Backend>, f: F) -> (XCapResult<T>, CaptureReport)
+where
+    F: FnOnce() -> XCapResult<T>,
+{
+    // 只有 macOS 和 Linux 有多个可选的后端
+    #[cfg(not(any(target_os = "macos", target_os = "linux", test)))]
+    let _ = backend;
+
+    let previous = SCOPE.with(|scope| {
+        scope.borrow_mut().replace(Scope {
+            #[cfg(any(target_os = "macos", target_os = "linux", test))]
+            backend,
+            report: CaptureReport::default(),
+        })
+    });
+
+    let result = f();
+
+    let report = SCOPE.with(|scope| {
+        let current = scope.replace(previous);
+        current.map(|current| current.report).unwrap_or_default()
+    });
+
+    (result, report)
+}
+
+/// 每次尝试截图前调用，清除上一次尝试记录的后端和回退
+pub(crate) fn start_attempt() {
+    update(|report| {
+        report.backend = Backend::Auto;
+        report.fallback = None;
+        report.attempts += 1;
+    });
+}
+
+/// 本次截图应该使用的后端：调用方要求的后端，没有要求时使用全局配置
+#[cfg(any(target_os = "macos", target_os = "linux", test))]
+pub(crate) fn requested_backend() -> Backend {
+    SCOPE
+        .with(|scope| scope.borrow().as_ref().and_then(|scope| scope.backend))
+        .unwrap_or_else(|| Config::get().backend())
+}
+
+/// 记录实际产生图像的后端
+#[cfg(any(target_os = "macos", target_os = "linux", test))]
+pub(crate) fn record_backend(backend: Backend) {
+    update(|report| report.backend = backend);
+}
+
+/// 记录失败后被回退的后端和它的错误
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn record_fallback(backend: Backend, error: &XCapError) {
+    log::debug!("{backend:?} capture failed, falling back: {error:?}");
+    update(|report| report.fallback = Some((backend, format!("{error}"))));
+}
+
+fn update<F: FnOnce(&mut CaptureReport)>(f: F) {
+    SCOPE.with(|scope| {
+        if let Some(scope) = scope.borrow_mut().as_mut() {
+            f(&mut scope.report);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope() {
+        let (result, report) = scope(Some(Backend::CoreGraphics), || {
+            assert_eq!(requested_backend(), Backend::CoreGraphics);
+
+            start_attempt();
+            record_fallback(Backend::ScreenCaptureKit, &XCapError::new("timeout"));
+            start_attempt();
+            record_fallback(Backend::ScreenCaptureKit, &XCapError::new("timeout"));
+            record_backend(Backend::CoreGraphics);
+
+            Ok(())
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(report.backend(), Backend::CoreGraphics);
+        assert_eq!(report.attempts(), 2);
+        assert!(report.fell_back());
+        assert_eq!(
+            report.fallback().map(|(backend, _)| backend),
+            Some(Backend::ScreenCaptureKit)
+        );
+    }
+}
@@ -0,0 +1,475 @@
+use std::{
+    sync::{
+        Arc, LazyLock, Mutex, Weak,
+        mpsc::{self, Receiver, Sender, SyncSender, TrySendError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    FramePacing, PowerProfile, RecorderConfig, RecorderUpdate, RecoveryPolicy,
+    annotation::AnnotationQueue,
+    error::{XCapError, XCapResult, catch_panics},
+    geometry::Rect,
+    platform::{impl_monitor::ImplMonitor, impl_video_recorder::ImplVideoRecorder},
+    video_recorder::{Frame, RecorderEvent, RecorderHealth},
+};
+
+// 进程内所有可以共用的平台流，不同线程创建的录制器也共用同一个流，流被释放后在下一次查找时清理
+static SESSIONS: LazyLock<Mutex<Vec<Weak<CaptureSession>>>> =
+    LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// 查找 key 相同的流，没有时创建。key 为 None 的流不共用，也不加入 sessions
+fn find_or_create<T, K, M, C>(
+    sessions: &Mutex<Vec<Weak<T>>>,
+    key: Option<&K>,
+    matches: M,
+    create: C,
+) -> XCapResult<Arc<T>>
+where
+    M: Fn(&T, &K) -> bool,
+    C: FnOnce() -> XCapResult<T>,
+{
+    // 创建期间持有锁，避免两个线程同时为同一个显示器创建流
+    let mut sessions = sessions.lock()?;
+    sessions.retain(|session| session.strong_count() > 0);
+
+    let existing = key.and_then(|key| {
+        sessions
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|session| matches(session, key))
+    });
+
+    if let Some(session) = existing {
+        return Ok(session);
+    }
+
+    let session = Arc::new(create()?);
+    if key.is_some() {
+        sessions.push(Arc::downgrade(&session));
+    }
+
+    Ok(session)
+}
+
+type RecorderJob = Box<dyn FnOnce(&ImplVideoRecorder) + Send>;
+
+fn recorder_thread_error() -> XCapError {
+    XCapError::new("Recorder thread exited")
+}
+
+/// 平台录制器所在的线程
+///
+/// macOS 和 Windows 的平台录制器不能跨线程传递，在这个线程上创建、调用和释放，
+/// 其他线程的录制器通过它共用平台流。发送端释放后线程释放平台录制器并退出
+#[derive(Debug)]
+struct RecorderThread {
+    jobs: Sender<RecorderJob>,
+}
+
+impl RecorderThread {
+    fn spawn(
+        monitor_id: u32,
+        config: &RecorderConfig,
+        health: Arc<RecorderHealth>,
+    ) -> XCapResult<(RecorderThread, Receiver<Frame>)> {
+        let (jobs, jobs_rx) = mpsc::channel::<RecorderJob>();
+        let (created_tx, created_rx) = mpsc::sync_channel(1);
+        let config = config.clone();
+
+        thread::Builder::new()
+            .name("xcap-recorder".to_string())
+            .spawn(move || {
+                // 显示器句柄不能跨线程传递，按 id 在这个线程上重新查找
+                let created = catch_panics(|| {
+                    let impl_monitor = ImplMonitor::all()?
+                        .into_iter()
+                        .find(|impl_monitor| impl_monitor.id().is_ok_and(|id| id == monitor_id))
+                        .ok_or(XCapError::new("Monitor not found"))?;
+
+                    impl_monitor.video_recorder(&config, health)
+                });
+
+                let impl_video_recorder = match created {
+                    Ok((impl_video_recorder, rx)) => {
+                        let _ = created_tx.send(Ok(rx));
+                        impl_video_recorder
+                    }
+                    Err(err) => {
+                        let _ = created_tx.send(Err(err));
+                        return;
+                    }
+                };
+
+                for job in jobs_rx {
+                    job(&impl_video_recorder);
+                }
+            })
+            .map_err(|err| XCapError::new(format!("Failed to spawn recorder thread: {err}")))?;
+
+        let rx = created_rx.recv().map_err(|_| recorder_thread_error())??;
+
+        Ok((RecorderThread { jobs }, rx))
+    }
+
+    /// 在录制器线程上执行 f 并等待结果
+    fn call<T, F>(&self, f: F) -> XCapResult<T>
+    where
+        F: FnOnce(&ImplVideoRecorder) -> XCapResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let job: RecorderJob = Box::new(move |impl_video_recorder| {
+            let _ = tx.send(catch_panics(|| f(impl_video_recorder)));
+        });
+        self.jobs.send(job).map_err(|_| recorder_thread_error())?;
+
+        rx.recv().map_err(|_| recorder_thread_error())?
+    }
+}
+
+/// 决定平台流行为的配置，相同时多个录制器共用一个平台流
+#[derive(Debug, Clone, PartialEq)]
+struct SessionKey {
+    monitor_id: u32,
+    pacing: FramePacing,
+    recovery: RecoveryPolicy,
+    pause_when_idle: bool,
+    power_profile: PowerProfile,
+    max_fps: Option<f32>,
+    timelapse: Option<Duration>,
+    scale: f32,
+    show_cursor: Option<bool>,
+    follow_window: Option<u32>,
+    region: Option<Rect>,
+    encoder_surface: bool,
+}
+
+impl SessionKey {
+    /// frame_hook 和 redactor 在平台线程中修改帧，设置了它们的录制器单独使用平台流
+    fn new(monitor_id: u32, config: &RecorderConfig) -> Option<SessionKey> {
+        if config.frame_hook.is_some() || config.redactor.is_some() {
+            return None;
+        }
+
+        Some(SessionKey {
+            monitor_id,
+            pacing: config.pacing,
+            recovery: config.recovery,
+            pause_when_idle: config.pause_when_idle,
+            power_profile: config.power_profile,
+            max_fps: config.max_fps,
+            timelapse: config.timelapse,
+            scale: config.scale,
+            show_cursor: config.show_cursor,
+            follow_window: config.follow_window,
+            region: config.region,
+            encoder_surface: config.encoder_surface,
+        })
+    }
+}
+
+/// 共用平台流的一个录制器，统计信息和事件由它自己的 health 上报
+#[derive(Debug)]
+struct Consumer {
+    id: u64,
+    tx: SyncSender<Frame>,
+    health: Arc<RecorderHealth>,
+    annotations: Arc<AnnotationQueue>,
+    running: bool,
+}
+
+impl Consumer {
+    /// 附加这一帧之前的标注后发送，帧被丢弃时标注留给下一帧
+    fn send(&self, mut frame: Frame) -> bool {
+        frame.annotations = self.annotations.take(frame.timestamp());
+
+        match self.tx.try_send(frame) {
+            Ok(()) => true,
+            Err(TrySendError::Full(frame) | TrySendError::Disconnected(frame)) => {
+                self.annotations.restore(frame.annotations);
+                false
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Consumers {
+    next_id: u64,
+    list: Vec<Consumer>,
+}
+
+impl Consumers {
+    fn is_running(&self) -> bool {
+        self.list.iter().any(|consumer| consumer.running)
+    }
+
+    fn set_running(&mut self, id: u64, running: bool) {
+        if let Some(consumer) = self.list.iter_mut().find(|consumer| consumer.id == id) {
+            consumer.running = running;
+        }
+    }
+}
+
+/// 把平台流的帧分发给正在录制的录制器，只有一个接收者时不复制帧
+fn tee_frames(rx: Receiver<Frame>, consumers: Arc<Mutex<Consumers>>) {
+    thread::spawn(move || {
+        for frame in rx {
+            let Ok(consumers) = consumers.lock() else {
+                break;
+            };
+
+            let running: Vec<&Consumer> = consumers
+                .list
+                .iter()
+                .filter(|consumer| consumer.running)
+                .collect();
+            let Some((last, others)) = running.split_last() else {
+                continue;
+            };
+
+            // 接收端没有及时取走时只丢弃这个录制器的帧，不阻塞其他录制器
+            for consumer in others {
+                let frame = frame.clone();
+                consumer.health.deliver(|| consumer.send(frame));
+            }
+            last.health.deliver(|| last.send(frame));
+        }
+    });
+}
+
+/// 转发平台流的事件，统计信息由每个录制器分别计算
+fn forward_events(events: Receiver<RecorderEvent>, consumers: Arc<Mutex<Consumers>>) {
+    thread::spawn(move || {
+        for event in events {
+            if matches!(event, RecorderEvent::Stats { .. }) {
+                continue;
+            }
+
+            let Ok(consumers) = consumers.lock() else {
+                break;
+            };
+            for consumer in &consumers.list {
+                consumer.health.emit(event.clone());
+            }
+        }
+    });
+}
+
+/// 一个平台流和共用它的录制器
+#[derive(Debug)]
+struct CaptureSession {
+    // reconfigure 之后配置和 key 不再一致，不再共用
+    key: Mutex<Option<SessionKey>>,
+    recorder_thread: RecorderThread,
+    consumers: Arc<Mutex<Consumers>>,
+    // 串行化启动和停止，分发帧的线程只使用 consumers 的锁，避免 stop 等待帧送达时死锁
+    control: Mutex<()>,
+}
+
+impl CaptureSession {
+    fn new(
+        monitor_id: u32,
+        config: &RecorderConfig,
+        key: Option<SessionKey>,
+    ) -> XCapResult<CaptureSession> {
+        let health = Arc::new(RecorderHealth::new(config.stats_interval));
+        let events = health.subscribe()?;
+        let (recorder_thread, rx) = RecorderThread::spawn(monitor_id, config, health)?;
+
+        let consumers = Arc::new(Mutex::new(Consumers::default()));
+        tee_frames(rx, consumers.clone());
+        forward_events(events, consumers.clone());
+
+        Ok(CaptureSession {
+            key: Mutex::new(key),
+            recorder_thread,
+            consumers,
+            control: Mutex::new(()),
+        })
+    }
+
+    fn matches(&self, key: &SessionKey) -> bool {
+        self.key
+            .lock()
+            .is_ok_and(|session_key| session_key.as_ref() == Some(key))
+    }
+}
+
+impl Drop for CaptureSession {
+    fn drop(&mut self) {
+        if let Err(err) = self.recorder_thread.call(ImplVideoRecorder::shutdown) {
+            log::error!("Failed to shut down capture session: {err:?}");
+        }
+    }
+}
+
+/// 录制器在共用平台流中的句柄，接口和 ImplVideoRecorder 一致
+///
+/// 同一个显示器上配置相同的录制器共用一个平台流，避免 ScreenCaptureKit 和 DXGI Desktop Duplication
+/// 为同一个显示器创建多个流时失败或者互相抢占。第一个录制器启动时启动平台流，最后一个停止时停止
+#[derive(Debug)]
+pub(crate) struct SharedRecorder {
+    session: Arc<CaptureSession>,
+    id: u64,
+    annotations: Arc<AnnotationQueue>,
+}
+
+impl SharedRecorder {
+    pub fn new(
+        impl_monitor: &ImplMonitor,
+        config: &RecorderConfig,
+        health: Arc<RecorderHealth>,
+    ) -> XCapResult<(SharedRecorder, Receiver<Frame>)> {
+        let monitor_id = impl_monitor.id()?;
+        let key = SessionKey::new(monitor_id, config);
+
+        let session = find_or_create(&SESSIONS, key.as_ref(), CaptureSession::matches, || {
+            CaptureSession::new(monitor_id, config, key.clone())
+        })?;
+
+        // 缓存一帧，其他录制器的接收端不会因此阻塞
+        let (tx, rx) = mpsc::sync_channel(1);
+        let annotations = Arc::new(AnnotationQueue::default());
+        let id = {
+            let mut consumers = session.consumers.lock()?;
+            let id = consumers.next_id;
+            consumers.next_id += 1;
+            consumers.list.push(Consumer {
+                id,
+                tx,
+                health,
+                annotations: annotations.clone(),
+                running: false,
+            });
+
+            id
+        };
+
+        Ok((
+            SharedRecorder {
+                session,
+                id,
+                annotations,
+            },
+            rx,
+        ))
+    }
+
+    pub fn start(&self) -> XCapResult<()> {
+        let _control = self.session.control.lock()?;
+
+        if !self.session.consumers.lock()?.is_running() {
+            self.session
+                .recorder_thread
+                .call(ImplVideoRecorder::start)?;
+        }
+        self.session.consumers.lock()?.set_running(self.id, true);
+
+        Ok(())
+    }
+
+    pub fn stop(&self) -> XCapResult<()> {
+        let _control = self.session.control.lock()?;
+
+        let is_running = {
+            let mut consumers = self.session.consumers.lock()?;
+            consumers.set_running(self.id, false);
+            consumers.is_running()
+        };
+
+        if is_running {
+            return Ok(());
+        }
+
+        self.session.recorder_thread.call(ImplVideoRecorder::stop)
+    }
+
+    pub fn reconfigure(&self, update: &RecorderUpdate) -> XCapResult<()> {
+        let _control = self.session.control.lock()?;
+
+        if self.session.consumers.lock()?.list.len() > 1 {
+            return Err(XCapError::new(
+                "Cannot reconfigure a stream shared with other recorders",
+            ));
+        }
+
+        let update = *update;
+        self.session
+            .recorder_thread
+            .call(move |impl_video_recorder| impl_video_recorder.reconfigure(&update))?;
+        *self.session.key.lock()? = None;
+
+        Ok(())
+    }
+
+    /// 标注只附加到这个录制器的帧上，不影响共用平台流的其他录制器
+    pub fn annotate(&self, timestamp: Instant, payload: Arc<[u8]>) -> XCapResult<()> {
+        self.annotations.push(timestamp, payload)
+    }
+
+    /// 从平台流中移除，最后一个录制器被释放时由 CaptureSession 关闭平台流
+    pub fn shutdown(&self) -> XCapResult<()> {
+        let _control = self.session.control.lock()?;
+
+        let (was_running, is_running) = {
+            let mut consumers = self.session.consumers.lock()?;
+            let was_running = consumers.is_running();
+            consumers.list.retain(|consumer| consumer.id != self.id);
+            (was_running, consumers.is_running())
+        };
+
+        if was_running && !is_running {
+            return self.session.recorder_thread.call(ImplVideoRecorder::stop);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct TestSession(Option<u32>);
+
+    #[test]
+    fn test_find_or_create_across_threads() {
+        static TEST_SESSIONS: LazyLock<Mutex<Vec<Weak<TestSession>>>> =
+            LazyLock::new(|| Mutex::new(Vec::new()));
+
+        let open = |key: Option<u32>| {
+            thread::spawn(move || {
+                find_or_create(
+                    &TEST_SESSIONS,
+                    key.as_ref(),
+                    |session, key| session.0 == Some(*key),
+                    || Ok(TestSession(key)),
+                )
+                .unwrap()
+            })
+            .join()
+            .unwrap()
+        };
+
+        // 不同线程打开同一个显示器时共用一个流
+        let first = open(Some(1));
+        let second = open(Some(1));
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(&first, &open(Some(2))));
+
+        // 不共用的流不加入 sessions
+        let unshared = open(None);
+        assert!(!Arc::ptr_eq(&unshared, &open(None)));
+
+        // 释放后重新创建
+        drop((first, second));
+        let third = open(Some(1));
+        assert_eq!(TEST_SESSIONS.lock().unwrap().len(), 1);
+        assert_eq!(third.0, Some(1));
+    }
+}
@@ -0,0 +1,294 @@
+use std::sync::mpsc::Receiver;
+
+use image::RgbaImage;
+
+use crate::{
+    CaptureConfig, Monitor, MonitorPlacement, RecorderConfig, VideoRecorder, Window,
+    error::{XCapError, XCapResult},
+    geometry::Rect,
+    video_recorder::{Frame, FrameView},
+};
+
+/// What to capture, accepted by [`capture`] and [`record`] so the same code handles
+/// monitors, windows and regions.
+///
+/// ```no_run
+/// use xcap::{CaptureConfig, CaptureTarget, Monitor, Rect};
+///
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let targets = [
+///     CaptureTarget::Monitor(monitor.clone()),
+///     CaptureTarget::MonitorRegion {
+///         monitor,
+///         rect: Rect::new(0, 0, 640, 480),
+///     },
+///     CaptureTarget::VirtualRegion(Rect::new(-100, 0, 200, 200)),
+/// ];
+///
+/// for target in &targets {
+///     let image = xcap::capture(target, &CaptureConfig::new()).unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub enum CaptureTarget {
+    /// A whole monitor.
+    Monitor(Monitor),
+    /// A window, see [`Window::capture_image`].
+    Window(Window),
+    /// A region in coordinates relative to the monitor's top-left corner.
+    MonitorRegion { monitor: Monitor, rect: Rect },
+    /// A region in global coordinates, which may span several monitors, see
+    /// [`Monitor::capture_screen_region`].
+    VirtualRegion(Rect),
+}
+
+impl From<Monitor> for CaptureTarget {
+    fn from(monitor: Monitor) -> CaptureTarget {
+        CaptureTarget::Monitor(monitor)
+    }
+}
+
+impl From<Window> for CaptureTarget {
+    fn from(window: Window) -> CaptureTarget {
+        CaptureTarget::Window(window)
+    }
+}
+
+impl CaptureTarget {
+    /// The target's bounds in global coordinates.
+    pub fn bounds(&self) -> XCapResult<Rect> {
+        match self {
+            CaptureTarget::Monitor(monitor) => monitor.bounds(),
+            CaptureTarget::Window(window) => Ok(window.coordinate_space()?.bounds()),
+            CaptureTarget::MonitorRegion { monitor, rect } => {
+                Ok(monitor.coordinate_space()?.rect_to_global(*rect))
+            }
+            CaptureTarget::VirtualRegion(rect) => Ok(*rect),
+        }
+    }
+
+    /// Capture the target, same as [`capture`] with the default [`CaptureConfig`].
+    pub fn capture_image(&self) -> XCapResult<RgbaImage> {
+        capture(self, &CaptureConfig::default())
+    }
+
+    /// Stable identifiers for the target, to save and [`resolve`](SavedTarget::resolve) on the
+    /// next run.
+    pub fn to_saved(&self) -> XCapResult<SavedTarget> {
+        match self {
+            CaptureTarget::Monitor(monitor) => {
+                Ok(SavedTarget::Monitor(SavedMonitor::new(monitor)?))
+            }
+            CaptureTarget::Window(window) => Ok(SavedTarget::Window(SavedWindow {
+                app_name: window.app_name()?,
+                title_pattern: window.title()?,
+            })),
+            CaptureTarget::MonitorRegion { monitor, rect } => Ok(SavedTarget::MonitorRegion {
+                monitor: SavedMonitor::new(monitor)?,
+                rect: *rect,
+            }),
+            CaptureTarget::VirtualRegion(rect) => Ok(SavedTarget::VirtualRegion(*rect)),
+        }
+    }
+}
+
+/// A monitor saved by its [`Monitor::unique_key`] and [`Monitor::placement`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SavedMonitor {
+    pub unique_key: String,
+    /// Used to find a replacement of the same model, see [`Monitor::find_by_unique_key`].
+    pub placement: Option<MonitorPlacement>,
+}
+
+impl SavedMonitor {
+    fn new(monitor: &Monitor) -> XCapResult<SavedMonitor> {
+        Ok(SavedMonitor {
+            unique_key: monitor.unique_key()?,
+            placement: monitor.placement().ok(),
+        })
+    }
+
+    fn resolve(&self) -> XCapResult<Monitor> {
+        let (monitor, _) = Monitor::find_by_unique_key(&self.unique_key, self.placement.as_ref())?;
+
+        Ok(monitor)
+    }
+}
+
+/// A window saved by its app and title. Window ids don't survive a restart of the app.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SavedWindow {
+    /// See [`Window::app_name`].
+    pub app_name: String,
+    /// The title to match, where `*` matches any text, e.g. `"* - Visual Studio Code"` for a
+    /// window whose title changes with the open document. The exact title when saved.
+    pub title_pattern: String,
+}
+
+impl SavedWindow {
+    /// Whether `window` matches the saved app and title pattern.
+    pub fn matches(&self, window: &Window) -> bool {
+        window
+            .app_name()
+            .is_ok_and(|app_name| app_name == self.app_name)
+            && window
+                .title()
+                .is_ok_and(|title| match_pattern(&self.title_pattern, &title))
+    }
+
+    fn resolve(&self) -> XCapResult<Window> {
+        // Window::all 按 z 顺序返回，多个窗口匹配时使用最上层的
+        Window::all()?
+            .into_iter()
+            .find(|window| self.matches(window))
+            .ok_or_else(|| {
+                XCapError::new(format!(
+                    "Window of '{}' with title '{}' not found",
+                    self.app_name, self.title_pattern
+                ))
+            })
+    }
+}
+
+/// A [`CaptureTarget`] saved by stable identifiers, so that "record the same window as last
+/// time" works across restarts. With the `serde` feature it can be serialized.
+///
+/// ```no_run
+/// use xcap::{CaptureTarget, Monitor, SavedTarget};
+///
+/// let target = CaptureTarget::Monitor(Monitor::all().unwrap().remove(0));
+/// let saved: SavedTarget = target.to_saved().unwrap();
+/// // ...next run
+/// let target = saved.resolve().unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SavedTarget {
+    Monitor(SavedMonitor),
+    Window(SavedWindow),
+    MonitorRegion { monitor: SavedMonitor, rect: Rect },
+    VirtualRegion(Rect),
+}
+
+impl SavedTarget {
+    /// Find the live monitor or window, failing when it's no longer there.
+    pub fn resolve(&self) -> XCapResult<CaptureTarget> {
+        match self {
+            SavedTarget::Monitor(monitor) => Ok(CaptureTarget::Monitor(monitor.resolve()?)),
+            SavedTarget::Window(window) => Ok(CaptureTarget::Window(window.resolve()?)),
+            SavedTarget::MonitorRegion { monitor, rect } => Ok(CaptureTarget::MonitorRegion {
+                monitor: monitor.resolve()?,
+                rect: *rect,
+            }),
+            SavedTarget::VirtualRegion(rect) => Ok(CaptureTarget::VirtualRegion(*rect)),
+        }
+    }
+}
+
+/// 匹配标题，`*` 匹配任意文本，其他字符按原样匹配
+fn match_pattern(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // split 至少返回一个元素
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // 没有 `*`，需要完全相等
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+/// Capture `target`, applying the options in `config`. Region targets are redacted and retried
+/// like monitor captures.
+pub fn capture(target: &CaptureTarget, config: &CaptureConfig) -> XCapResult<RgbaImage> {
+    let (image, bounds) = match target {
+        CaptureTarget::Monitor(monitor) => return monitor.capture_image_with_config(config),
+        CaptureTarget::Window(window) => return window.capture_image_with_config(config),
+        CaptureTarget::MonitorRegion { monitor, rect } => {
+            let (image, _) = config.capture(|| {
+                monitor.capture_region(rect.x as u32, rect.y as u32, rect.width, rect.height)
+            });
+            (image?, target.bounds()?)
+        }
+        CaptureTarget::VirtualRegion(rect) => {
+            let (image, _) = config.capture(|| Monitor::capture_screen_region(*rect));
+            (image?, *rect)
+        }
+    };
+
+    let mut image = image;
+    if let Some(redactor) = &config.redactor {
+        redactor.redact(&mut FrameView::from_image(&mut image), bounds);
+    }
+    config.apply(&mut image);
+
+    Ok(image)
+}
+
+/// Create a video recorder for `target`, applying the options in `config`.
+///
+/// The monitor under the target is recorded and each frame is cropped to it: a window is
+/// followed as with [`RecorderConfig::follow_window`], on the monitor it is on when recording
+/// starts. A [`CaptureTarget::VirtualRegion`] must lie on a single monitor.
+pub fn record(
+    target: &CaptureTarget,
+    config: &RecorderConfig,
+) -> XCapResult<(VideoRecorder, Receiver<Frame>)> {
+    match target {
+        CaptureTarget::Monitor(monitor) => monitor.video_recorder_with_config(config),
+        CaptureTarget::Window(window) => window
+            .current_monitor()?
+            .video_recorder_with_config(&config.clone().follow_window(window.id()?)),
+        CaptureTarget::MonitorRegion { monitor, rect } => {
+            monitor.video_recorder_with_config(&config.clone().with_region(*rect))
+        }
+        CaptureTarget::VirtualRegion(rect) => {
+            let monitor = Monitor::all()?
+                .into_iter()
+                .find(|monitor| {
+                    monitor
+                        .bounds()
+                        .is_ok_and(|bounds| bounds.contains_rect(*rect))
+                })
+                .ok_or_else(|| {
+                    XCapError::InvalidCaptureRegion(format!(
+                        "Region {rect:?} must lie on a single monitor to be recorded"
+                    ))
+                })?;
+            let rect = monitor.coordinate_space()?.rect_to_local(*rect);
+
+            monitor.video_recorder_with_config(&config.clone().with_region(rect))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_pattern() {
+        assert!(match_pattern("Notes", "Notes"));
+        assert!(!match_pattern("Notes", "Notes 2"));
+        assert!(match_pattern("* - Editor", "a.txt - Editor"));
+        assert!(!match_pattern("* - Editor", "a.txt - Viewer"));
+        assert!(match_pattern("a*b*c", "abc"));
+        assert!(match_pattern("a*b*c", "a-b-b-c"));
+        assert!(!match_pattern("ab*ba", "aba"));
+        assert!(match_pattern("*", ""));
+    }
+}
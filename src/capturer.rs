@@ -0,0 +1,95 @@
+use image::RgbaImage;
+
+use crate::{CaptureConfig, Monitor, Window, error::XCapResult, video_recorder::ChangeDetector};
+
+#[derive(Debug, Clone)]
+enum Target {
+    Monitor(Monitor),
+    Window(Window),
+}
+
+/// Captures one monitor or window repeatedly, remembering the previous screenshot so
+/// unchanged screenshots can be skipped, e.g. in products that take a screenshot every few
+/// seconds and would otherwise encode and store identical images.
+///
+/// ```no_run
+/// use std::{thread, time::Duration};
+///
+/// use xcap::{Capturer, Monitor};
+///
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let mut capturer = Capturer::monitor(monitor);
+///
+/// loop {
+///     if let Some(image) = capturer.capture_dedup().unwrap() {
+///         image.save("latest.png").unwrap();
+///     }
+///     thread::sleep(Duration::from_secs(5));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Capturer {
+    target: Target,
+    config: CaptureConfig,
+    change_detector: ChangeDetector,
+}
+
+impl Capturer {
+    /// Capture `monitor`.
+    pub fn monitor(monitor: Monitor) -> Capturer {
+        Capturer::new(Target::Monitor(monitor))
+    }
+
+    /// Capture `window`.
+    pub fn window(window: Window) -> Capturer {
+        Capturer::new(Target::Window(window))
+    }
+
+    fn new(target: Target) -> Capturer {
+        Capturer {
+            target,
+            config: CaptureConfig::default(),
+            change_detector: ChangeDetector::default(),
+        }
+    }
+
+    /// Capture with the options in `config`, see [`Monitor::capture_image_with_config`] and
+    /// [`Window::capture_image_with_config`].
+    pub fn with_config(mut self, config: CaptureConfig) -> Capturer {
+        self.config = config;
+        self
+    }
+
+    /// Capture a screenshot, whether or not it changed.
+    pub fn capture(&mut self) -> XCapResult<RgbaImage> {
+        let image = self.capture_image()?;
+        self.change_detector.is_changed(image.as_raw());
+
+        Ok(image)
+    }
+
+    /// Capture a screenshot, returning `None` when it is identical to the previous screenshot
+    /// taken by [`capture`](Capturer::capture) or `capture_dedup`. Screenshots are compared
+    /// by a hash of their pixels, so only the hash of the previous screenshot is kept.
+    pub fn capture_dedup(&mut self) -> XCapResult<Option<RgbaImage>> {
+        let image = self.capture_image()?;
+
+        if !self.change_detector.is_changed(image.as_raw()) {
+            return Ok(None);
+        }
+
+        Ok(Some(image))
+    }
+
+    /// Forget the previous screenshot, so the next `capture_dedup` returns an image.
+    pub fn reset(&mut self) {
+        self.change_detector = ChangeDetector::default();
+    }
+
+    fn capture_image(&self) -> XCapResult<RgbaImage> {
+        match &self.target {
+            Target::Monitor(monitor) => monitor.capture_image_with_config(&self.config),
+            Target::Window(window) => window.capture_image_with_config(&self.config),
+        }
+    }
+}
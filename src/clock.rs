@@ -0,0 +1,188 @@
+//! Map platform frame timestamps onto a single monotonic timeline.
+//!
+//! Every backend stamps frames with its own clock (`mach_absolute_time`/`CMTime` host time on
+//! macOS, QPC on Windows, `CLOCK_MONOTONIC` on Linux). The helpers here convert them to
+//! [`Instant`], and [`to_nanos`]/[`from_nanos`] express an [`Instant`] as nanoseconds since a
+//! process-wide epoch, so captures can be correlated with input logs or audio on every OS.
+//!
+//! ```
+//! use std::time::Instant;
+//!
+//! let epoch = xcap::clock::epoch();
+//! let now = Instant::now();
+//! let nanos = xcap::clock::to_nanos(now);
+//! assert_eq!(xcap::clock::from_nanos(nanos), now);
+//! assert!(epoch <= now);
+//! ```
+
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+/// The start of the nanosecond timeline, fixed the first time it is requested.
+pub fn epoch() -> Instant {
+    *EPOCH.get_or_init(Instant::now)
+}
+
+/// Nanoseconds from [`epoch`] to `instant`, saturating at zero for earlier instants.
+pub fn to_nanos(instant: Instant) -> u64 {
+    instant.saturating_duration_since(epoch()).as_nanos() as u64
+}
+
+/// The instant `nanos` nanoseconds after [`epoch`].
+pub fn from_nanos(nanos: u64) -> Instant {
+    epoch() + Duration::from_nanos(nanos)
+}
+
+/// 根据同一时刻采样的平台时钟和 Instant，把平台时间戳换算为 Instant
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+fn instant_from_clock(now: Instant, now_nanos: u64, timestamp_nanos: u64) -> Instant {
+    if timestamp_nanos <= now_nanos {
+        let elapsed = Duration::from_nanos(now_nanos - timestamp_nanos);
+        now.checked_sub(elapsed).unwrap_or(now)
+    } else {
+        now + Duration::from_nanos(timestamp_nanos - now_nanos)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod mach {
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct MachTimebaseInfo {
+        pub numer: u32,
+        pub denom: u32,
+    }
+
+    unsafe extern "C" {
+        pub fn mach_absolute_time() -> u64;
+        pub fn mach_timebase_info(info: *mut MachTimebaseInfo) -> i32;
+    }
+
+    /// mach_absolute_time 的单位在 Apple Silicon 上不是纳秒，需要按 timebase 换算
+    pub fn ticks_to_nanos(ticks: u64) -> u64 {
+        let mut info = MachTimebaseInfo::default();
+        unsafe { mach_timebase_info(&mut info) };
+
+        if info.denom == 0 {
+            return ticks;
+        }
+
+        (ticks as u128 * info.numer as u128 / info.denom as u128) as u64
+    }
+}
+
+/// Convert a `mach_absolute_time` value to an [`Instant`].
+#[cfg(target_os = "macos")]
+pub fn from_mach_absolute_time(ticks: u64) -> Instant {
+    let now = Instant::now();
+    let now_ticks = unsafe { mach::mach_absolute_time() };
+
+    instant_from_clock(
+        now,
+        mach::ticks_to_nanos(now_ticks),
+        mach::ticks_to_nanos(ticks),
+    )
+}
+
+/// Convert a host-time `CMTime` (e.g. a `CMSampleBuffer` presentation timestamp) to an
+/// [`Instant`]. Returns `None` for invalid or non-numeric times.
+#[cfg(target_os = "macos")]
+pub fn from_cm_time(time: objc2_core_media::CMTime) -> Option<Instant> {
+    use objc2_core_media::CMTimeFlags;
+
+    if !time.flags.contains(CMTimeFlags::Valid) || time.timescale <= 0 || time.value < 0 {
+        return None;
+    }
+
+    let nanos = time.value as u128 * 1_000_000_000 / time.timescale as u128;
+
+    let now = Instant::now();
+    let now_nanos = mach::ticks_to_nanos(unsafe { mach::mach_absolute_time() });
+
+    Some(instant_from_clock(now, now_nanos, nanos as u64))
+}
+
+/// Convert a `QueryPerformanceCounter` value to an [`Instant`].
+#[cfg(target_os = "windows")]
+pub fn from_qpc(ticks: i64) -> Instant {
+    use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+
+    let now = Instant::now();
+    let mut now_ticks = 0;
+    let mut frequency = 0;
+    unsafe {
+        let _ = QueryPerformanceCounter(&mut now_ticks);
+        let _ = QueryPerformanceFrequency(&mut frequency);
+    }
+
+    if frequency <= 0 {
+        return now;
+    }
+
+    let to_nanos = |ticks: i64| (ticks.max(0) as u128 * 1_000_000_000 / frequency as u128) as u64;
+
+    instant_from_clock(now, to_nanos(now_ticks), to_nanos(ticks))
+}
+
+/// Convert a `CLOCK_MONOTONIC` timestamp in nanoseconds (e.g. a PipeWire buffer pts) to an
+/// [`Instant`].
+#[cfg(target_os = "linux")]
+pub fn from_clock_monotonic(nanos: u64) -> Instant {
+    use std::ffi::{c_int, c_long};
+
+    #[repr(C)]
+    struct Timespec {
+        tv_sec: c_long,
+        tv_nsec: c_long,
+    }
+
+    const CLOCK_MONOTONIC: c_int = 1;
+
+    unsafe extern "C" {
+        fn clock_gettime(clock_id: c_int, tp: *mut Timespec) -> c_int;
+    }
+
+    let now = Instant::now();
+    let mut timespec = Timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { clock_gettime(CLOCK_MONOTONIC, &mut timespec) } != 0 {
+        return now;
+    }
+
+    let now_nanos = timespec.tv_sec as u64 * 1_000_000_000 + timespec.tv_nsec as u64;
+
+    instant_from_clock(now, now_nanos, nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instant_from_clock() {
+        let now = Instant::now() + Duration::from_secs(10);
+
+        assert_eq!(
+            instant_from_clock(now, 5_000, 2_000),
+            now - Duration::from_nanos(3_000)
+        );
+        assert_eq!(
+            instant_from_clock(now, 5_000, 7_000),
+            now + Duration::from_nanos(2_000)
+        );
+    }
+
+    #[test]
+    fn test_nanos_round_trip() {
+        let instant = epoch() + Duration::from_millis(1500);
+
+        assert_eq!(to_nanos(instant), 1_500_000_000);
+        assert_eq!(from_nanos(to_nanos(instant)), instant);
+    }
+}
@@ -0,0 +1,180 @@
+//! Color statistics over captured images and frames, e.g. for ambient (bias) lighting.
+//!
+//! ```
+//! use xcap::image::{Rgba, RgbaImage};
+//!
+//! let image = RgbaImage::from_pixel(4, 4, Rgba([200, 100, 0, 255]));
+//! assert_eq!(xcap::color::average_color(&image), Rgba([200, 100, 0, 255]));
+//! assert_eq!(xcap::color::dominant_colors(&image, 3), vec![Rgba([200, 100, 0, 255])]);
+//! ```
+
+use std::collections::HashMap;
+
+use image::{Rgba, RgbaImage};
+
+use crate::{PixelFormat, video_recorder::Frame};
+
+// 每个通道先用 u32 累加，最多 BLOCK_PIXELS 个像素（255 * 2^16 < 2^32）后再合并到 u64，
+// 内层循环没有分支和溢出检查，编译器可以自动向量化
+const BLOCK_PIXELS: usize = 1 << 16;
+
+// 统计主色时每个通道保留的高位数，4 位即 4096 个颜色桶
+const QUANTIZE_BITS: u8 = 4;
+
+/// 按行遍历像素数据，跳过每行末尾的填充字节
+fn rows(data: &[u8], width: u32, height: u32, stride: usize) -> impl Iterator<Item = &[u8]> {
+    let row_len = width as usize * 4;
+
+    data.chunks(stride.max(row_len).max(1))
+        .take(height as usize)
+        .map(move |row| &row[..row_len.min(row.len())])
+}
+
+fn accumulate(pixels: &[u8], sums: &mut [u64; 4]) {
+    for block in pixels.chunks(BLOCK_PIXELS * 4) {
+        let mut block_sums = [0u32; 4];
+        for pixel in block.chunks_exact(4) {
+            block_sums[0] += pixel[0] as u32;
+            block_sums[1] += pixel[1] as u32;
+            block_sums[2] += pixel[2] as u32;
+            block_sums[3] += pixel[3] as u32;
+        }
+
+        for (sum, block_sum) in sums.iter_mut().zip(block_sums) {
+            *sum += block_sum as u64;
+        }
+    }
+}
+
+fn average<'a>(rows: impl Iterator<Item = &'a [u8]>) -> Rgba<u8> {
+    let mut sums = [0u64; 4];
+    let mut count = 0u64;
+
+    for row in rows {
+        accumulate(row, &mut sums);
+        count += (row.len() / 4) as u64;
+    }
+
+    if count == 0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+
+    Rgba(sums.map(|sum| ((sum + count / 2) / count) as u8))
+}
+
+fn dominant<'a>(rows: impl Iterator<Item = &'a [u8]>, n: usize) -> Vec<Rgba<u8>> {
+    let shift = 8 - QUANTIZE_BITS;
+    // 颜色桶 -> (像素数, 各通道之和)，返回桶内的平均色而不是桶的中心色
+    let mut buckets: HashMap<u16, (u64, [u64; 3])> = HashMap::new();
+
+    for row in rows {
+        for pixel in row.chunks_exact(4) {
+            // 完全透明的像素没有颜色
+            if pixel[3] == 0 {
+                continue;
+            }
+
+            let key = ((pixel[0] >> shift) as u16) << (QUANTIZE_BITS * 2)
+                | ((pixel[1] >> shift) as u16) << QUANTIZE_BITS
+                | (pixel[2] >> shift) as u16;
+            let (count, sums) = buckets.entry(key).or_default();
+            *count += 1;
+            sums[0] += pixel[0] as u64;
+            sums[1] += pixel[1] as u64;
+            sums[2] += pixel[2] as u64;
+        }
+    }
+
+    let mut buckets: Vec<(u16, (u64, [u64; 3]))> = buckets.into_iter().collect();
+    // 像素数相同时按颜色桶排序，保证结果稳定
+    buckets.sort_unstable_by(|(a_key, (a_count, _)), (b_key, (b_count, _))| {
+        b_count.cmp(a_count).then(a_key.cmp(b_key))
+    });
+
+    buckets
+        .into_iter()
+        .take(n)
+        .map(|(_, (count, sums))| {
+            let [r, g, b] = sums.map(|sum| ((sum + count / 2) / count) as u8);
+            Rgba([r, g, b, 255])
+        })
+        .collect()
+}
+
+/// The mean of every pixel in `image`, per channel.
+pub fn average_color(image: &RgbaImage) -> Rgba<u8> {
+    average(rows(image.as_raw(), image.width(), image.height(), 0))
+}
+
+/// Up to `n` of the most common colors in `image`, most common first. Similar colors are
+/// grouped together and fully transparent pixels are ignored.
+pub fn dominant_colors(image: &RgbaImage, n: usize) -> Vec<Rgba<u8>> {
+    dominant(rows(image.as_raw(), image.width(), image.height(), 0), n)
+}
+
+impl Frame {
+    /// The mean of every pixel in the frame, see [`average_color`].
+    pub fn average_color(&self) -> Rgba<u8> {
+        match self.format() {
+            PixelFormat::Rgba8 => average(rows(
+                self.data(),
+                self.width(),
+                self.height(),
+                self.stride(),
+            )),
+        }
+    }
+
+    /// Up to `n` of the most common colors in the frame, see [`dominant_colors`].
+    pub fn dominant_colors(&self, n: usize) -> Vec<Rgba<u8>> {
+        match self.format() {
+            PixelFormat::Rgba8 => dominant(
+                rows(self.data(), self.width(), self.height(), self.stride()),
+                n,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn test_average_color() {
+        let mut image = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]));
+        image.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        image.put_pixel(1, 0, Rgba([255, 255, 255, 255]));
+
+        assert_eq!(average_color(&image), Rgba([128, 128, 128, 255]));
+        assert_eq!(average_color(&RgbaImage::new(0, 0)), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_dominant_colors() {
+        let mut image = RgbaImage::from_pixel(4, 1, Rgba([10, 200, 10, 255]));
+        image.put_pixel(0, 0, Rgba([250, 0, 0, 255]));
+        image.put_pixel(1, 0, Rgba([0, 0, 0, 0]));
+
+        assert_eq!(
+            dominant_colors(&image, 5),
+            vec![Rgba([10, 200, 10, 255]), Rgba([250, 0, 0, 255])]
+        );
+        assert_eq!(dominant_colors(&image, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_frame_colors_skip_padding() {
+        // 每行末尾的填充字节不参与统计
+        let data = vec![
+            100, 100, 100, 255, 255, 255, 255, 255, //
+            100, 100, 100, 255, 255, 255, 255, 255,
+        ];
+        let frame = Frame::with_stride(1, 2, 8, data, Instant::now());
+
+        assert_eq!(frame.average_color(), Rgba([100, 100, 100, 255]));
+        assert_eq!(frame.dominant_colors(2), vec![Rgba([100, 100, 100, 255])]);
+    }
+}
@@ -0,0 +1,350 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use image::{
+    RgbaImage,
+    imageops::{self, FilterType},
+};
+
+use crate::{
+    Window,
+    error::{XCapError, XCapResult},
+    geometry::Rect,
+    video_recorder::{Frame, FrameView},
+};
+
+// 等待底层帧的超时时间，超时后检查合成器是否已经停止
+const FRAME_WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+enum Source {
+    Frames(Receiver<Frame>),
+    // Windows 上 HWND 不能跨线程传递，在线程中按 id 重新查找窗口
+    Window(u32),
+}
+
+/// Where the pixels of a [`Compositor`] overlay come from.
+#[derive(Debug)]
+pub struct OverlaySource(Source);
+
+impl OverlaySource {
+    /// Frames from another recorder, e.g. a second monitor or a camera. The latest frame is
+    /// drawn on each base frame.
+    pub fn frames(rx: Receiver<Frame>) -> OverlaySource {
+        OverlaySource(Source::Frames(rx))
+    }
+
+    /// A window, captured again for each base frame.
+    pub fn window(window: &Window) -> XCapResult<OverlaySource> {
+        Ok(OverlaySource(Source::Window(window.id()?)))
+    }
+}
+
+/// Where and how an overlay is drawn on the base frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Inset {
+    rect: Rect,
+    opacity: f32,
+}
+
+impl Inset {
+    /// Draw the overlay scaled to `rect`, in base frame pixels. Parts outside the frame are
+    /// clipped.
+    pub fn new(rect: Rect) -> Inset {
+        Inset { rect, opacity: 1.0 }
+    }
+
+    /// Opacity from 0.0 to 1.0, multiplied with the overlay's own alpha. Defaults to 1.0.
+    pub fn opacity(mut self, opacity: f32) -> Inset {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
+}
+
+/// 一个叠加层的状态，保存最近一次取到的图像
+struct Layer {
+    source: Source,
+    inset: Inset,
+    window: Option<Window>,
+    latest: Option<RgbaImage>,
+}
+
+impl Layer {
+    /// 取最新的图像并缩放到目标大小，取不到新图像时沿用上一次的
+    fn update(&mut self) {
+        let image = match &self.source {
+            Source::Frames(rx) => rx.try_iter().last().and_then(|frame| frame.to_rgba_image()),
+            Source::Window(id) => {
+                if self.window.is_none() {
+                    self.window = Window::from_id(*id).ok();
+                }
+
+                match self.window.as_ref().map(Window::capture_image) {
+                    Some(Ok(image)) => Some(image),
+                    Some(Err(err)) => {
+                        log::debug!("capture overlay window failed: {err:?}");
+                        // 窗口可能已经关闭，下次重新查找
+                        self.window = None;
+                        None
+                    }
+                    None => None,
+                }
+            }
+        };
+
+        if let Some(image) = image {
+            let (width, height) = (self.inset.rect.width, self.inset.rect.height);
+            self.latest = Some(if image.dimensions() == (width, height) {
+                image
+            } else {
+                imageops::resize(&image, width, height, FilterType::Triangle)
+            });
+        }
+    }
+}
+
+/// 把 overlay 按 opacity 混合到帧上，左上角位于 (x, y)，超出帧的部分被裁剪
+fn blend(view: &mut FrameView, overlay: &RgbaImage, x: i32, y: i32, opacity: f32) {
+    let alpha_scale = (opacity * 255.0).round() as u32;
+    if alpha_scale == 0 {
+        return;
+    }
+
+    let (frame_width, frame_height) = (view.width() as i64, view.height() as i64);
+    let x_start = (x as i64).clamp(0, frame_width);
+    let x_end = (x as i64 + overlay.width() as i64).clamp(0, frame_width);
+    let y_start = (y as i64).clamp(0, frame_height);
+    let y_end = (y as i64 + overlay.height() as i64).clamp(0, frame_height);
+
+    for frame_y in y_start..y_end {
+        let Some(row) = view.row_mut(frame_y as u32) else {
+            continue;
+        };
+        let overlay_y = (frame_y - y as i64) as u32;
+
+        for frame_x in x_start..x_end {
+            let overlay_x = (frame_x - x as i64) as u32;
+            let src = overlay.get_pixel(overlay_x, overlay_y).0;
+            // 0..=255*255，乘以 opacity 之后的不透明度
+            let alpha = src[3] as u32 * alpha_scale;
+            let dst = &mut row[frame_x as usize * 4..frame_x as usize * 4 + 4];
+
+            for channel in 0..3 {
+                let blended =
+                    src[channel] as u32 * alpha + dst[channel] as u32 * (255 * 255 - alpha);
+                dst[channel] = ((blended + 255 * 255 / 2) / (255 * 255)) as u8;
+            }
+            let dst_alpha = alpha + dst[3] as u32 * (255 * 255 - alpha) / 255;
+            dst[3] = ((dst_alpha + 255 / 2) / 255).min(255) as u8;
+        }
+    }
+}
+
+/// 发送合成后的帧，接收端没有及时取走时定期检查是否已经停止
+fn send_frame(tx: &SyncSender<Frame>, frame: Frame, stopped: &AtomicBool) -> bool {
+    let mut frame = frame;
+    loop {
+        match tx.try_send(frame) {
+            Ok(()) => return true,
+            Err(TrySendError::Disconnected(_)) => return false,
+            Err(TrySendError::Full(f)) => {
+                if stopped.load(Ordering::Relaxed) {
+                    return false;
+                }
+
+                frame = f;
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+}
+
+/// Builds a [`Compositor`], see [`Compositor::builder`].
+#[derive(Debug)]
+pub struct CompositorBuilder {
+    base: Receiver<Frame>,
+    layers: Vec<(OverlaySource, Inset)>,
+}
+
+impl CompositorBuilder {
+    /// Add an overlay, drawn above the base frames and the overlays added before it.
+    pub fn overlay(mut self, source: OverlaySource, inset: Inset) -> CompositorBuilder {
+        self.layers.push((source, inset));
+        self
+    }
+
+    /// Start compositing, returning the compositor and the receiver of the composited frames.
+    pub fn build(self) -> XCapResult<(Compositor, Receiver<Frame>)> {
+        if self
+            .layers
+            .iter()
+            .any(|(_, inset)| inset.rect.width == 0 || inset.rect.height == 0)
+        {
+            return Err(XCapError::new("Overlay inset must not be empty"));
+        }
+
+        let base = self.base;
+        let sources: Vec<(Source, Inset)> = self
+            .layers
+            .into_iter()
+            .map(|(OverlaySource(source), inset)| (source, inset))
+            .collect();
+
+        // 不缓存帧，和录制器一样接收端没有取走时等待
+        let (tx, rx) = mpsc::sync_channel(0);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let stopped = stopped.clone();
+            thread::spawn(move || {
+                // Window 不能跨线程传递，在线程中创建
+                let mut layers: Vec<Layer> = sources
+                    .into_iter()
+                    .map(|(source, inset)| Layer {
+                        source,
+                        inset,
+                        window: None,
+                        latest: None,
+                    })
+                    .collect();
+
+                while !stopped.load(Ordering::Relaxed) {
+                    let mut frame = match base.recv_timeout(FRAME_WAIT_TIMEOUT) {
+                        Ok(frame) => frame,
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    };
+
+                    let mut view = frame.view_mut();
+                    for layer in &mut layers {
+                        layer.update();
+                        if let Some(latest) = &layer.latest {
+                            let Inset { rect, opacity } = layer.inset;
+                            blend(&mut view, latest, rect.x, rect.y, opacity);
+                        }
+                    }
+
+                    if !send_frame(&tx, frame, &stopped) {
+                        break;
+                    }
+                }
+            })
+        };
+
+        Ok((
+            Compositor {
+                stopped,
+                worker: Some(worker),
+            },
+            rx,
+        ))
+    }
+}
+
+/// Draws overlays, such as a window inset, onto the frames of a recorder and sends the result
+/// as a single frame stream.
+///
+/// Dropping the compositor stops its thread.
+#[derive(Debug)]
+pub struct Compositor {
+    stopped: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Compositor {
+    /// Composite onto the frames from `base`, typically a monitor recorder's receiver. Frames
+    /// are sent on once all overlays are drawn; overlays that have no image yet are skipped.
+    ///
+    /// ```no_run
+    /// use xcap::{Compositor, Inset, Monitor, OverlaySource, Rect, Window};
+    ///
+    /// let monitor = Monitor::all().unwrap().remove(0);
+    /// let window = Window::all().unwrap().remove(0);
+    ///
+    /// let (recorder, base) = monitor.video_recorder().unwrap();
+    /// let (_compositor, rx) = Compositor::builder(base)
+    ///     .overlay(
+    ///         OverlaySource::window(&window).unwrap(),
+    ///         Inset::new(Rect::new(20, 20, 480, 270)).opacity(0.9),
+    ///     )
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// recorder.start().unwrap();
+    /// let frame = rx.recv().unwrap();
+    /// ```
+    pub fn builder(base: Receiver<Frame>) -> CompositorBuilder {
+        CompositorBuilder {
+            base,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Stop compositing and wait for the compositor thread to exit.
+    pub fn stop(mut self) {
+        self.stop_worker();
+    }
+
+    fn stop_worker(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for Compositor {
+    fn drop(&mut self) {
+        self.stop_worker();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use image::Rgba;
+
+    use super::*;
+
+    fn black_frame(width: u32, height: u32) -> Frame {
+        let raw = [0, 0, 0, 255].repeat((width * height) as usize);
+        Frame::with_stride(width, height, width as usize * 4, raw, Instant::now())
+    }
+
+    #[test]
+    fn test_blend_opacity() {
+        let mut frame = black_frame(3, 3);
+        let overlay = RgbaImage::from_pixel(2, 2, Rgba([255, 255, 255, 255]));
+
+        blend(&mut frame.view_mut(), &overlay, 1, 1, 0.5);
+
+        let image = frame.to_rgba_image().unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 1).0, [128, 128, 128, 255]);
+        assert_eq!(image.get_pixel(2, 2).0, [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn test_blend_clips() {
+        let mut frame = black_frame(2, 2);
+        let mut overlay = RgbaImage::from_pixel(3, 3, Rgba([255, 0, 0, 255]));
+        // 透明像素不覆盖底层
+        overlay.put_pixel(2, 1, Rgba([0, 255, 0, 0]));
+
+        blend(&mut frame.view_mut(), &overlay, -1, 0, 1.0);
+
+        let image = frame.to_rgba_image().unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(0, 1).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 1).0, [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{PixelFormat, XCapError, XCapResult, clock, video_recorder::Frame};
+
+// 压缩数据的头部：魔数、版本、编码、像素格式、宽、高、stride、时间戳、原始长度
+const MAGIC: &[u8; 4] = b"XCFZ";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 1 + 1 + 1 + 4 + 4 + 4 + 8 + 8;
+
+// zstd 的压缩级别，低级别在屏幕内容上已经有很好的压缩率，速度快很多
+const ZSTD_LEVEL: i32 = 3;
+
+/// The compression codec used by [`Frame::compress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codec {
+    /// Better ratio, for bandwidth-limited links.
+    Zstd,
+    /// Faster, for low-latency links.
+    Lz4,
+}
+
+impl Codec {
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            Codec::Zstd => 0,
+            Codec::Lz4 => 1,
+        }
+    }
+
+    pub(crate) fn from_byte(byte: u8) -> XCapResult<Codec> {
+        match byte {
+            0 => Ok(Codec::Zstd),
+            1 => Ok(Codec::Lz4),
+            _ => Err(XCapError::new(format!("Unknown codec {byte}"))),
+        }
+    }
+}
+
+fn format_to_byte(format: PixelFormat) -> u8 {
+    match format {
+        PixelFormat::Rgba8 => 0,
+    }
+}
+
+fn format_from_byte(byte: u8) -> XCapResult<PixelFormat> {
+    match byte {
+        0 => Ok(PixelFormat::Rgba8),
+        _ => Err(XCapError::new(format!("Unknown pixel format {byte}"))),
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap_or_default())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap_or_default())
+}
+
+/// 按 codec 压缩，不带头部
+pub(crate) fn compress_bytes(codec: Codec, data: &[u8]) -> XCapResult<Vec<u8>> {
+    match codec {
+        Codec::Zstd => zstd::bulk::compress(data, ZSTD_LEVEL)
+            .map_err(|err| XCapError::new(format!("Zstd compress failed: {err}"))),
+        Codec::Lz4 => Ok(lz4_flex::block::compress(data)),
+    }
+}
+
+/// 解压 compress_bytes 的结果，len 是原始数据的长度
+pub(crate) fn decompress_bytes(codec: Codec, compressed: &[u8], len: usize) -> XCapResult<Vec<u8>> {
+    match codec {
+        Codec::Zstd => zstd::bulk::decompress(compressed, len)
+            .map_err(|err| XCapError::new(format!("Zstd decompress failed: {err}"))),
+        Codec::Lz4 => lz4_flex::block::decompress(compressed, len)
+            .map_err(|err| XCapError::new(format!("Lz4 decompress failed: {err}"))),
+    }
+}
+
+impl Frame {
+    /// Compress the frame into a self-describing payload for transport. The payload carries the
+    /// codec, pixel format, dimensions, stride and timestamp, so [`Frame::decompress`] needs no
+    /// other information.
+    pub fn compress(&self, codec: Codec) -> XCapResult<Vec<u8>> {
+        let data = self.data();
+        let compressed = compress_bytes(codec, data)?;
+
+        let mut payload = Vec::with_capacity(HEADER_LEN + compressed.len());
+        payload.extend_from_slice(MAGIC);
+        payload.push(VERSION);
+        payload.push(codec.to_byte());
+        payload.push(format_to_byte(self.format()));
+        payload.extend_from_slice(&self.width().to_le_bytes());
+        payload.extend_from_slice(&self.height().to_le_bytes());
+        payload.extend_from_slice(&(self.stride() as u32).to_le_bytes());
+        payload.extend_from_slice(&clock::to_nanos(self.timestamp()).to_le_bytes());
+        payload.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        payload.extend_from_slice(&compressed);
+
+        Ok(payload)
+    }
+
+    /// Restore a frame from a payload produced by [`Frame::compress`]. The timestamp is only
+    /// meaningful when both ends share the same [`clock::epoch`].
+    pub fn decompress(payload: &[u8]) -> XCapResult<Frame> {
+        if payload.len() < HEADER_LEN || &payload[..4] != MAGIC {
+            return Err(XCapError::new("Invalid compressed frame"));
+        }
+        if payload[4] != VERSION {
+            return Err(XCapError::new(format!(
+                "Unsupported compressed frame version {}",
+                payload[4]
+            )));
+        }
+
+        let codec = Codec::from_byte(payload[5])?;
+        let format = format_from_byte(payload[6])?;
+        let width = read_u32(payload, 7);
+        let height = read_u32(payload, 11);
+        let stride = read_u32(payload, 15) as usize;
+        let timestamp = clock::from_nanos(read_u64(payload, 19));
+        let len = read_u64(payload, 27) as usize;
+
+        let compressed = &payload[HEADER_LEN..];
+        let data = decompress_bytes(codec, compressed, len)?;
+
+        if data.len() != len || data.len() < stride * height as usize {
+            return Err(XCapError::new("Corrupted compressed frame"));
+        }
+
+        match format {
+            PixelFormat::Rgba8 => Ok(Frame::with_stride(width, height, stride, data, timestamp)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_round_trip() {
+        // 时间戳在 epoch 之前会被截断为 0
+        clock::epoch();
+        let frame = Frame::new(4, 2, (0..32).collect());
+
+        for codec in [Codec::Zstd, Codec::Lz4] {
+            let payload = frame.compress(codec).unwrap();
+            let restored = Frame::decompress(&payload).unwrap();
+
+            assert_eq!(restored.width(), 4);
+            assert_eq!(restored.height(), 2);
+            assert_eq!(restored.stride(), 16);
+            assert_eq!(restored.data(), frame.data());
+            assert_eq!(restored.timestamp(), frame.timestamp());
+        }
+
+        assert!(Frame::decompress(b"XCFZ").is_err());
+    }
+}
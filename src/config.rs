@@ -0,0 +1,327 @@
+use std::{sync::RwLock, time::Duration};
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+use crate::metrics::Stage;
+use crate::{
+    error::XCapResult,
+    metrics::{MetricsSink, SharedMetricsSink},
+};
+
+static CONFIG: RwLock<Config> = RwLock::new(Config::DEFAULT);
+
+/// The capture backend to use on platforms that have more than one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Backend {
+    /// Pick the backend automatically.
+    #[default]
+    Auto,
+    /// macOS: ScreenCaptureKit only, without falling back to `CGWindowListCreateImage`.
+    ScreenCaptureKit,
+    /// macOS: `CGWindowListCreateImage` only.
+    CoreGraphics,
+    /// Linux: the Wayland backends, whatever the session type.
+    Wayland,
+    /// Linux: the X11 backends, whatever the session type.
+    Xorg,
+}
+
+/// The capture backend in use, as decided by [`backend_info`](crate::backend_info).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BackendInfo {
+    backend: Backend,
+    xwayland: bool,
+}
+
+impl BackendInfo {
+    pub(crate) fn new(backend: Backend, xwayland: bool) -> BackendInfo {
+        BackendInfo { backend, xwayland }
+    }
+
+    /// The backend captures go through, [`Backend::Auto`] on platforms that have only one.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+    /// Linux: the X server is XWayland. The X11 backends only see XWayland clients there, so
+    /// captures are routed through the Wayland backends unless [`Backend::Xorg`] is forced.
+    pub fn xwayland(&self) -> bool {
+        self.xwayland
+    }
+}
+
+/// Process-wide settings, applied with [`Config::apply`].
+///
+/// Per-capture options live in [`CaptureConfig`](crate::CaptureConfig) and
+/// [`RecorderConfig`](crate::RecorderConfig).
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use xcap::{Backend, Config};
+///
+/// Config::builder()
+///     .capture_timeout(Duration::from_millis(500))
+///     .backend(Backend::Auto)
+///     .build()
+///     .apply()
+///     .unwrap();
+/// assert_eq!(Config::current().unwrap().capture_timeout(), Duration::from_millis(500));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub(crate) capture_timeout: Duration,
+    pub(crate) portal_timeout: Duration,
+    pub(crate) portal_idle_timeout: Duration,
+    pub(crate) portal_output: Option<String>,
+    pub(crate) portal_restore_token: Option<String>,
+    pub(crate) main_thread_timeout: Duration,
+    pub(crate) backend: Backend,
+    pub(crate) show_cursor: bool,
+    pub(crate) window_cache_ttl: Duration,
+    pub(crate) log_timings: bool,
+    pub(crate) catch_panics: bool,
+    pub(crate) metrics_sink: Option<SharedMetricsSink>,
+}
+
+impl Config {
+    const DEFAULT: Config = Config {
+        capture_timeout: Duration::from_millis(200),
+        portal_timeout: Duration::from_secs(5),
+        portal_idle_timeout: Duration::from_secs(30),
+        portal_output: None,
+        portal_restore_token: None,
+        main_thread_timeout: Duration::from_secs(2),
+        backend: Backend::Auto,
+        show_cursor: true,
+        window_cache_ttl: Duration::from_millis(100),
+        log_timings: true,
+        catch_panics: false,
+        metrics_sink: None,
+    };
+
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// The settings currently in effect.
+    pub fn current() -> XCapResult<Config> {
+        Ok(CONFIG.read()?.clone())
+    }
+
+    /// Make these settings the process-wide settings. Captures and recorders that are already
+    /// running keep the settings they started with.
+    pub fn apply(self) -> XCapResult<()> {
+        *CONFIG.write()? = self;
+        Ok(())
+    }
+
+    /// See [`ConfigBuilder::capture_timeout`].
+    pub fn capture_timeout(&self) -> Duration {
+        self.capture_timeout
+    }
+    /// See [`ConfigBuilder::portal_timeout`].
+    pub fn portal_timeout(&self) -> Duration {
+        self.portal_timeout
+    }
+    /// See [`ConfigBuilder::portal_idle_timeout`].
+    pub fn portal_idle_timeout(&self) -> Duration {
+        self.portal_idle_timeout
+    }
+    /// See [`ConfigBuilder::portal_output`].
+    pub fn portal_output(&self) -> Option<&str> {
+        self.portal_output.as_deref()
+    }
+    /// See [`ConfigBuilder::portal_restore_token`].
+    pub fn portal_restore_token(&self) -> Option<&str> {
+        self.portal_restore_token.as_deref()
+    }
+    /// See [`ConfigBuilder::main_thread_timeout`].
+    pub fn main_thread_timeout(&self) -> Duration {
+        self.main_thread_timeout
+    }
+    /// See [`ConfigBuilder::backend`].
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+    /// See [`ConfigBuilder::show_cursor`].
+    pub fn show_cursor(&self) -> bool {
+        self.show_cursor
+    }
+    /// See [`ConfigBuilder::window_cache_ttl`].
+    pub fn window_cache_ttl(&self) -> Duration {
+        self.window_cache_ttl
+    }
+    /// See [`ConfigBuilder::log_timings`].
+    pub fn log_timings(&self) -> bool {
+        self.log_timings
+    }
+    /// See [`ConfigBuilder::catch_panics`].
+    pub fn catch_panics(&self) -> bool {
+        self.catch_panics
+    }
+
+    /// 把一个阶段的耗时报告给 MetricsSink
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+    pub(crate) fn record_stage(&self, stage: Stage, duration: Duration) {
+        if let Some(metrics_sink) = &self.metrics_sink {
+            metrics_sink.record(stage, duration);
+        }
+    }
+
+    /// 读取当前配置，锁中毒时使用默认配置，配置读取失败不应该导致截图失败
+    pub(crate) fn get() -> Config {
+        CONFIG
+            .read()
+            .map(|config| config.clone())
+            .unwrap_or(Config::DEFAULT)
+    }
+
+    /// 修改当前配置中的一项
+    #[cfg(target_os = "macos")]
+    pub(crate) fn update<F>(update: F) -> XCapResult<()>
+    where
+        F: FnOnce(&mut Config),
+    {
+        update(&mut *CONFIG.write()?);
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::DEFAULT
+    }
+}
+
+/// Builds a [`Config`], starting from the defaults.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// How long a single ScreenCaptureKit step (fetching shareable content, starting the stream,
+    /// waiting for a frame) may take on macOS before falling back or failing. Defaults to 200ms.
+    pub fn capture_timeout(mut self, capture_timeout: Duration) -> ConfigBuilder {
+        self.config.capture_timeout = capture_timeout;
+        self
+    }
+
+    /// How long to wait for the first frame of an xdg-desktop-portal screencast on Wayland.
+    /// Defaults to 5 seconds.
+    pub fn portal_timeout(mut self, portal_timeout: Duration) -> ConfigBuilder {
+        self.config.portal_timeout = portal_timeout;
+        self
+    }
+
+    /// How long the screencast portal session used for Wayland screenshots is kept open after
+    /// the last capture. Each monitor's stream stops once it has been idle this long, and the
+    /// session is closed when no stream is left, so repeated captures skip the portal
+    /// negotiation. Defaults to 30 seconds.
+    pub fn portal_idle_timeout(mut self, portal_idle_timeout: Duration) -> ConfigBuilder {
+        self.config.portal_idle_timeout = portal_idle_timeout;
+        self
+    }
+
+    /// Share only this output, given by name (e.g. `DP-1`) or serial number, when Wayland
+    /// screenshots go through the screencast portal. The portal is asked for a single source and
+    /// captures of other monitors fail. Portals that can select sources without asking, such as
+    /// xdg-desktop-portal-hyprland or xdg-desktop-portal-wlr configured with a fixed output,
+    /// then run without any user interaction, e.g. on kiosks.
+    pub fn portal_output<S>(mut self, portal_output: S) -> ConfigBuilder
+    where
+        S: Into<String>,
+    {
+        self.config.portal_output = Some(portal_output.into());
+        self
+    }
+
+    /// A restore token for the screencast portal, used instead of the one saved from the last
+    /// session. Portals that accept it, such as KDE and GNOME, restore the earlier source
+    /// selection without showing the picker. Applies to Wayland screenshots and recordings.
+    pub fn portal_restore_token<S>(mut self, portal_restore_token: S) -> ConfigBuilder
+    where
+        S: Into<String>,
+    {
+        self.config.portal_restore_token = Some(portal_restore_token.into());
+        self
+    }
+
+    /// How long to wait for the main thread to run main-thread-only work on macOS. Defaults to
+    /// 2 seconds.
+    pub fn main_thread_timeout(mut self, main_thread_timeout: Duration) -> ConfigBuilder {
+        self.config.main_thread_timeout = main_thread_timeout;
+        self
+    }
+
+    /// The capture backend to use, defaults to [`Backend::Auto`]. Backends of other platforms
+    /// are treated as [`Backend::Auto`].
+    pub fn backend(mut self, backend: Backend) -> ConfigBuilder {
+        self.config.backend = backend;
+        self
+    }
+
+    /// Whether captures and recordings include the mouse cursor where the backend can choose
+    /// (ScreenCaptureKit and AVFoundation on macOS, the screencast portal on Wayland). Enabled
+    /// by default.
+    pub fn show_cursor(mut self, show_cursor: bool) -> ConfigBuilder {
+        self.config.show_cursor = show_cursor;
+        self
+    }
+
+    /// How long window information is cached on macOS, 0 disables the cache. Defaults to 100ms.
+    pub fn window_cache_ttl(mut self, window_cache_ttl: Duration) -> ConfigBuilder {
+        self.config.window_cache_ttl = window_cache_ttl;
+        self
+    }
+
+    /// Log the time taken by each capture stage at debug level. Enabled by default.
+    pub fn log_timings(mut self, log_timings: bool) -> ConfigBuilder {
+        self.config.log_timings = log_timings;
+        self
+    }
+
+    /// Turn panics inside the main entry points ([`Monitor::all`](crate::Monitor::all),
+    /// [`Window::all`](crate::Window::all), the capture methods and
+    /// [`Monitor::video_recorder_with_config`](crate::Monitor::video_recorder_with_config))
+    /// into [`XCapError::Panicked`](crate::XCapError::Panicked), for hosts that call xcap
+    /// through FFI, where unwinding across the boundary aborts the process. The panic hook
+    /// still runs. Disabled by default; has no effect when built with `panic = "abort"`.
+    pub fn catch_panics(mut self, catch_panics: bool) -> ConfigBuilder {
+        self.config.catch_panics = catch_panics;
+        self
+    }
+
+    /// Report the time taken by each capture stage to `sink`, see [`MetricsSink`].
+    pub fn metrics_sink<S>(mut self, sink: S) -> ConfigBuilder
+    where
+        S: MetricsSink + 'static,
+    {
+        self.config.metrics_sink = Some(SharedMetricsSink::new(sink));
+        self
+    }
+
+    pub fn build(self) -> Config {
+        self.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_defaults() {
+        assert_eq!(Config::builder().build(), Config::default());
+
+        let config = Config::builder()
+            .backend(Backend::Xorg)
+            .show_cursor(false)
+            .window_cache_ttl(Duration::ZERO)
+            .build();
+        assert_eq!(config.backend(), Backend::Xorg);
+        assert!(!config.show_cursor());
+        assert_eq!(config.window_cache_ttl(), Duration::ZERO);
+        assert_eq!(config.capture_timeout(), Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,454 @@
+//! BGRA to RGBA pixel conversion, tuned for the running CPU.
+//!
+//! Most backends receive BGRA pixels and swap them to RGBA for every capture. There are
+//! scalar, SSE4.1, AVX2, AVX-512 and NEON kernels for the swap, and large frames can be split
+//! across threads. Which is fastest depends on the CPU and its memory bandwidth, so the first
+//! conversion runs a short benchmark (a few tens of milliseconds) and keeps the winner. Call
+//! [`tune`] up front to move that cost out of the first capture, and [`strategy`] to see what
+//! was picked.
+//!
+//! ```
+//! let strategy = xcap::conversion::tune();
+//! println!(
+//!     "{:?} kernel, {} threads from {:?} pixels",
+//!     strategy.kernel, strategy.threads, strategy.parallel_min_pixels
+//! );
+//! assert!(xcap::conversion::ConversionKernel::available().contains(&strategy.kernel));
+//! ```
+
+use std::{
+    mem::MaybeUninit,
+    sync::{
+        Once,
+        atomic::{AtomicU8, AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// 测量时使用的帧大小：一个小窗口和一块 1080p 的显示器
+const BENCH_PIXELS: [usize; 2] = [256 * 256, 1920 * 1080];
+// 每种方案测量的次数，取最快的一次，排除缺页和调度的影响
+const BENCH_RUNS: usize = 3;
+// 更多线程只会争抢内存带宽
+const MAX_THREADS: usize = 8;
+
+// 每个像素 B 和 R 交换位置，pshufb 和 tbl 只使用每 16 字节中的低 4 位索引，所以各种宽度的寄存器都能用
+const SHUFFLE_MASK: [u8; 64] = {
+    let mut mask = [0u8; 64];
+    let mut i = 0;
+    while i < 64 {
+        mask[i] = (i - i % 4 + [2, 1, 0, 3][i % 4]) as u8;
+        i += 1;
+    }
+    mask
+};
+
+static TUNED: Once = Once::new();
+// 0 表示还没有测量
+static KERNEL: AtomicU8 = AtomicU8::new(0);
+static THREADS: AtomicUsize = AtomicUsize::new(1);
+// usize::MAX 表示不使用多线程
+static PARALLEL_MIN_PIXELS: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// A BGRA to RGBA conversion kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConversionKernel {
+    /// One pixel at a time, available everywhere.
+    Scalar,
+    /// 4 pixels at a time on x86_64.
+    Sse41,
+    /// 8 pixels at a time on x86_64.
+    Avx2,
+    /// 16 pixels at a time on x86_64 with AVX-512BW.
+    Avx512,
+    /// 4 pixels at a time on aarch64.
+    Neon,
+}
+
+impl ConversionKernel {
+    /// The kernels the running CPU supports, from the fewest to the most pixels at a time.
+    pub fn available() -> Vec<ConversionKernel> {
+        let mut kernels = vec![ConversionKernel::Scalar];
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            if std::arch::is_x86_feature_detected!("sse4.1") {
+                kernels.push(ConversionKernel::Sse41);
+            }
+            if std::arch::is_x86_feature_detected!("avx2") {
+                kernels.push(ConversionKernel::Avx2);
+            }
+            if std::arch::is_x86_feature_detected!("avx512f")
+                && std::arch::is_x86_feature_detected!("avx512bw")
+            {
+                kernels.push(ConversionKernel::Avx512);
+            }
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        kernels.push(ConversionKernel::Neon);
+
+        kernels
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            ConversionKernel::Scalar => 1,
+            ConversionKernel::Sse41 => 2,
+            ConversionKernel::Avx2 => 3,
+            ConversionKernel::Avx512 => 4,
+            ConversionKernel::Neon => 5,
+        }
+    }
+
+    fn from_u8(value: u8) -> ConversionKernel {
+        match value {
+            2 => ConversionKernel::Sse41,
+            3 => ConversionKernel::Avx2,
+            4 => ConversionKernel::Avx512,
+            5 => ConversionKernel::Neon,
+            _ => ConversionKernel::Scalar,
+        }
+    }
+}
+
+/// How frames are converted, picked by [`tune`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConversionStrategy {
+    /// The fastest kernel the CPU supports.
+    pub kernel: ConversionKernel,
+    /// The number of threads a large frame is split across.
+    pub threads: usize,
+    /// Frames with at least this many pixels are split across [`threads`](Self::threads),
+    /// `None` when a single thread was always faster.
+    pub parallel_min_pixels: Option<usize>,
+}
+
+/// Benchmark the conversion kernels and threading on this CPU and use the fastest from now on.
+/// Runs automatically before the first conversion; calling it again measures again.
+pub fn tune() -> ConversionStrategy {
+    let strategy = measure();
+    store(strategy);
+    TUNED.call_once(|| {});
+
+    log::debug!("conversion strategy: {strategy:?}");
+
+    strategy
+}
+
+/// The strategy conversions use, running [`tune`] first if it has not run yet.
+pub fn strategy() -> ConversionStrategy {
+    TUNED.call_once(|| store(measure()));
+
+    // 各个字段分别读取，和并发的 tune 交错时也都是可用的值
+    ConversionStrategy {
+        kernel: ConversionKernel::from_u8(KERNEL.load(Ordering::Relaxed)),
+        threads: THREADS.load(Ordering::Relaxed),
+        parallel_min_pixels: Some(PARALLEL_MIN_PIXELS.load(Ordering::Relaxed))
+            .filter(|&pixels| pixels != usize::MAX),
+    }
+}
+
+fn store(strategy: ConversionStrategy) {
+    KERNEL.store(strategy.kernel.to_u8(), Ordering::Relaxed);
+    THREADS.store(strategy.threads, Ordering::Relaxed);
+    PARALLEL_MIN_PIXELS.store(
+        strategy.parallel_min_pixels.unwrap_or(usize::MAX),
+        Ordering::Relaxed,
+    );
+}
+
+/// 多次运行取最短耗时
+fn best_time<F: FnMut()>(mut f: F) -> Duration {
+    (0..BENCH_RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// 先在小帧上选出最快的内核，再找出多线程开始变快的帧大小
+fn measure() -> ConversionStrategy {
+    let max_pixels = BENCH_PIXELS[BENCH_PIXELS.len() - 1];
+    let src = vec![0x5a; max_pixels * 4];
+    let mut dst = vec![MaybeUninit::uninit(); max_pixels * 4];
+
+    let small = BENCH_PIXELS[0] * 4;
+    let kernel = ConversionKernel::available()
+        .into_iter()
+        .min_by_key(|&kernel| best_time(|| convert(kernel, 1, &src[..small], &mut dst[..small])))
+        .unwrap_or(ConversionKernel::Scalar);
+
+    let threads = thread::available_parallelism()
+        .map_or(1, |threads| threads.get())
+        .min(MAX_THREADS);
+    let parallel_min_pixels = (threads > 1)
+        .then(|| {
+            BENCH_PIXELS.into_iter().find(|&pixels| {
+                let (src, dst) = (&src[..pixels * 4], &mut dst[..pixels * 4]);
+                let single = best_time(|| convert(kernel, 1, src, dst));
+                best_time(|| convert(kernel, threads, src, dst)) < single
+            })
+        })
+        .flatten();
+
+    ConversionStrategy {
+        kernel,
+        threads,
+        parallel_min_pixels,
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+fn threads_for(strategy: &ConversionStrategy, pixel_count: usize) -> usize {
+    match strategy.parallel_min_pixels {
+        Some(min_pixels) if pixel_count >= min_pixels => strategy.threads,
+        _ => 1,
+    }
+}
+
+/// 按线程数把帧切成若干块，每块在单独的线程中转换
+fn convert(kernel: ConversionKernel, threads: usize, src: &[u8], dst: &mut [MaybeUninit<u8>]) {
+    let chunk_len = (src.len() / 4).div_ceil(threads.max(1)).max(1) * 4;
+    if threads <= 1 || chunk_len >= src.len() {
+        unsafe { convert_pixels(kernel, src.as_ptr(), dst.as_mut_ptr().cast(), src.len() / 4) };
+        return;
+    }
+
+    thread::scope(|scope| {
+        for (src, dst) in src.chunks(chunk_len).zip(dst.chunks_mut(chunk_len)) {
+            scope.spawn(move || unsafe {
+                convert_pixels(kernel, src.as_ptr(), dst.as_mut_ptr().cast(), src.len() / 4)
+            });
+        }
+    });
+}
+
+/// 把 BGRA 像素转换为新的 RGBA 缓冲区，多余的不足一个像素的字节会被丢弃
+#[cfg(any(target_os = "macos", target_os = "linux", test))]
+pub(crate) fn bgra_to_rgba(src: &[u8]) -> Vec<u8> {
+    let strategy = strategy();
+    let len = src.len() / 4 * 4;
+    let mut buffer = Vec::with_capacity(len);
+
+    convert(
+        strategy.kernel,
+        threads_for(&strategy, len / 4),
+        &src[..len],
+        &mut buffer.spare_capacity_mut()[..len],
+    );
+    unsafe { buffer.set_len(len) };
+
+    buffer
+}
+
+/// 原地把 BGRA 像素转换为 RGBA
+#[cfg(any(target_os = "macos", target_os = "windows", test))]
+pub(crate) fn bgra_to_rgba_in_place(buffer: &mut [u8]) {
+    let strategy = strategy();
+    let threads = threads_for(&strategy, buffer.len() / 4);
+    let chunk_len = (buffer.len() / 4).div_ceil(threads).max(1) * 4;
+
+    thread::scope(|scope| {
+        let mut chunks = buffer.chunks_mut(chunk_len);
+        // 最后一块在当前线程中转换，单线程时不创建线程
+        let last = chunks.next_back();
+        for chunk in chunks {
+            scope.spawn(move || convert_chunk_in_place(strategy.kernel, chunk));
+        }
+        if let Some(chunk) = last {
+            convert_chunk_in_place(strategy.kernel, chunk);
+        }
+    });
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", test))]
+fn convert_chunk_in_place(kernel: ConversionKernel, chunk: &mut [u8]) {
+    let pixels = chunk.as_mut_ptr();
+    unsafe { convert_pixels(kernel, pixels, pixels, chunk.len() / 4) };
+}
+
+/// 转换一行像素，用于按行拷贝的场景，不使用多线程
+///
+/// # Safety
+///
+/// src 必须可读 pixel_count * 4 字节，dst 必须可写 pixel_count * 4 字节，两者可以是同一块内存
+#[cfg(target_os = "macos")]
+pub(crate) unsafe fn bgra_to_rgba_row(src: *const u8, dst: *mut u8, pixel_count: usize) {
+    unsafe { convert_pixels(strategy().kernel, src, dst, pixel_count) };
+}
+
+/// kernel 必须来自 ConversionKernel::available
+unsafe fn convert_pixels(
+    kernel: ConversionKernel,
+    src: *const u8,
+    dst: *mut u8,
+    pixel_count: usize,
+) {
+    unsafe {
+        match kernel {
+            #[cfg(target_arch = "x86_64")]
+            ConversionKernel::Sse41 => convert_sse41(src, dst, pixel_count),
+            #[cfg(target_arch = "x86_64")]
+            ConversionKernel::Avx2 => convert_avx2(src, dst, pixel_count),
+            #[cfg(target_arch = "x86_64")]
+            ConversionKernel::Avx512 => convert_avx512(src, dst, pixel_count),
+            #[cfg(target_arch = "aarch64")]
+            ConversionKernel::Neon => convert_neon(src, dst, pixel_count),
+            _ => convert_scalar(src, dst, pixel_count),
+        }
+    }
+}
+
+/// 逐像素交换 B 和 R，先读出整个像素再写入，所以 src 和 dst 可以是同一块内存
+unsafe fn convert_scalar(src: *const u8, dst: *mut u8, pixel_count: usize) {
+    for i in 0..pixel_count {
+        unsafe {
+            let [b, g, r, a] = src.add(i * 4).cast::<[u8; 4]>().read();
+            dst.add(i * 4).cast::<[u8; 4]>().write([r, g, b, a]);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+unsafe fn convert_sse41(src: *const u8, dst: *mut u8, pixel_count: usize) {
+    use std::arch::x86_64::*;
+
+    let simd_count = pixel_count / 4;
+    unsafe {
+        let mask = _mm_loadu_si128(SHUFFLE_MASK.as_ptr().cast());
+        for i in 0..simd_count {
+            let offset = i * 16;
+            let data = _mm_loadu_si128(src.add(offset).cast());
+            _mm_storeu_si128(dst.add(offset).cast(), _mm_shuffle_epi8(data, mask));
+        }
+
+        convert_scalar(
+            src.add(simd_count * 16),
+            dst.add(simd_count * 16),
+            pixel_count % 4,
+        );
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn convert_avx2(src: *const u8, dst: *mut u8, pixel_count: usize) {
+    use std::arch::x86_64::*;
+
+    // vpshufb 在每个 128 位通道内分别重排，像素不会跨通道，所以可以直接处理 8 个像素
+    let simd_count = pixel_count / 8;
+    unsafe {
+        let mask = _mm256_loadu_si256(SHUFFLE_MASK.as_ptr().cast());
+        for i in 0..simd_count {
+            let offset = i * 32;
+            let data = _mm256_loadu_si256(src.add(offset).cast());
+            _mm256_storeu_si256(dst.add(offset).cast(), _mm256_shuffle_epi8(data, mask));
+        }
+
+        convert_scalar(
+            src.add(simd_count * 32),
+            dst.add(simd_count * 32),
+            pixel_count % 8,
+        );
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512bw")]
+unsafe fn convert_avx512(src: *const u8, dst: *mut u8, pixel_count: usize) {
+    use std::arch::x86_64::*;
+
+    let simd_count = pixel_count / 16;
+    unsafe {
+        let mask = _mm512_loadu_si512(SHUFFLE_MASK.as_ptr().cast());
+        for i in 0..simd_count {
+            let offset = i * 64;
+            let data = _mm512_loadu_si512(src.add(offset).cast());
+            _mm512_storeu_si512(dst.add(offset).cast(), _mm512_shuffle_epi8(data, mask));
+        }
+
+        convert_scalar(
+            src.add(simd_count * 64),
+            dst.add(simd_count * 64),
+            pixel_count % 16,
+        );
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn convert_neon(src: *const u8, dst: *mut u8, pixel_count: usize) {
+    use std::arch::aarch64::*;
+
+    let simd_count = pixel_count / 4;
+    unsafe {
+        let mask = vld1q_u8(SHUFFLE_MASK.as_ptr());
+        for i in 0..simd_count {
+            let offset = i * 16;
+            let data = vld1q_u8(src.add(offset));
+            vst1q_u8(dst.add(offset), vqtbl1q_u8(data, mask));
+        }
+
+        convert_scalar(
+            src.add(simd_count * 16),
+            dst.add(simd_count * 16),
+            pixel_count % 4,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bgra(pixel_count: usize) -> Vec<u8> {
+        (0..pixel_count * 4).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    fn expected(src: &[u8]) -> Vec<u8> {
+        src.chunks_exact(4)
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+            .collect()
+    }
+
+    #[test]
+    fn test_kernels() {
+        // 不是任何 SIMD 宽度整数倍的像素数，覆盖剩余像素的处理
+        let src = bgra(67);
+        for kernel in ConversionKernel::available() {
+            for threads in [1, 3] {
+                let mut dst = vec![MaybeUninit::uninit(); src.len()];
+                convert(kernel, threads, &src, &mut dst);
+                let dst: Vec<u8> = dst
+                    .iter()
+                    .map(|byte| unsafe { byte.assume_init() })
+                    .collect();
+                assert_eq!(dst, expected(&src), "{kernel:?} on {threads} threads");
+            }
+
+            let mut buffer = src.clone();
+            convert_chunk_in_place(kernel, &mut buffer);
+            assert_eq!(buffer, expected(&src), "{kernel:?} in place");
+        }
+    }
+
+    #[test]
+    fn test_bgra_to_rgba() {
+        let src = bgra(1000);
+        assert_eq!(bgra_to_rgba(&src), expected(&src));
+
+        let mut buffer = src.clone();
+        bgra_to_rgba_in_place(&mut buffer);
+        assert_eq!(buffer, expected(&src));
+
+        assert!(ConversionKernel::available().contains(&strategy().kernel));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::geometry::{Point, Rect, map_rect};
+
+/// Converts between the three coordinate systems of a monitor or window, returned by
+/// [`Monitor::coordinate_space`](crate::Monitor::coordinate_space) and
+/// [`Window::coordinate_space`](crate::Window::coordinate_space):
+///
+/// - global: the virtual desktop, as used by [`Monitor::bounds`](crate::Monitor::bounds),
+///   [`Monitor::from_point`](crate::Monitor::from_point) and window positions.
+/// - local: relative to the top-left corner, in the same units, as taken by `capture_region`.
+/// - physical: pixels of the captured image, which differ from local coordinates on macOS
+///   Retina displays and scaled Linux desktops.
+///
+/// ```
+/// use xcap::{CoordinateSpace, Point, Rect};
+///
+/// // a 2x display left of the primary one
+/// let space = CoordinateSpace::new(Rect::new(-1440, 0, 1440, 900), 2.0);
+///
+/// assert_eq!(space.to_local(Point::new(-1430, 20)), Point::new(10, 20));
+/// assert_eq!(space.to_physical(Point::new(10, 20)), Point::new(20, 40));
+/// assert_eq!(space.from_physical(Point::new(21, 41)), Point::new(10, 20));
+/// assert_eq!(
+///     space.rect_to_physical(Rect::new(10, 20, 100, 50)),
+///     Rect::new(20, 40, 200, 100)
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordinateSpace {
+    bounds: Rect,
+    pixel_ratio: f64,
+}
+
+impl CoordinateSpace {
+    /// A space for `bounds`, in global coordinates, whose captures have `pixel_ratio` physical
+    /// pixels per unit. A ratio that is not positive is treated as 1.
+    pub fn new(bounds: Rect, pixel_ratio: f64) -> CoordinateSpace {
+        let pixel_ratio = if pixel_ratio.is_finite() && pixel_ratio > 0.0 {
+            pixel_ratio
+        } else {
+            1.0
+        };
+
+        CoordinateSpace {
+            bounds,
+            pixel_ratio,
+        }
+    }
+
+    /// The bounds in global coordinates.
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+    /// Physical pixels per local unit.
+    pub fn pixel_ratio(&self) -> f64 {
+        self.pixel_ratio
+    }
+    /// The size of a full capture in physical pixels.
+    pub fn physical_size(&self) -> (u32, u32) {
+        let rect = self.rect_to_physical(Rect::new(0, 0, self.bounds.width, self.bounds.height));
+        (rect.width, rect.height)
+    }
+
+    /// Converts a global point to local coordinates. The result is outside the local bounds
+    /// when the point is not in this space.
+    pub fn to_local(&self, point: Point) -> Point {
+        Point::new(
+            (point.x as i64 - self.bounds.x as i64) as i32,
+            (point.y as i64 - self.bounds.y as i64) as i32,
+        )
+    }
+    /// Converts a local point to global coordinates.
+    pub fn to_global(&self, point: Point) -> Point {
+        Point::new(
+            (point.x as i64 + self.bounds.x as i64) as i32,
+            (point.y as i64 + self.bounds.y as i64) as i32,
+        )
+    }
+    /// Converts a local point to the top-left physical pixel it covers.
+    pub fn to_physical(&self, point: Point) -> Point {
+        let scale = |value: i32| floor(value as f64 * self.pixel_ratio) as i32;
+        Point::new(scale(point.x), scale(point.y))
+    }
+    /// Converts a physical pixel to the local point that covers it.
+    pub fn from_physical(&self, point: Point) -> Point {
+        let scale = |value: i32| floor(value as f64 / self.pixel_ratio) as i32;
+        Point::new(scale(point.x), scale(point.y))
+    }
+
+    /// Converts a global rectangle to local coordinates.
+    pub fn rect_to_local(&self, rect: Rect) -> Rect {
+        let origin = self.to_local(Point::new(rect.x, rect.y));
+        Rect::new(origin.x, origin.y, rect.width, rect.height)
+    }
+    /// Converts a local rectangle to global coordinates.
+    pub fn rect_to_global(&self, rect: Rect) -> Rect {
+        let origin = self.to_global(Point::new(rect.x, rect.y));
+        Rect::new(origin.x, origin.y, rect.width, rect.height)
+    }
+    /// Converts a local rectangle to the physical pixels it covers, rounding outwards.
+    pub fn rect_to_physical(&self, rect: Rect) -> Rect {
+        map_rect(rect, (self.pixel_ratio, self.pixel_ratio), (0.0, 0.0))
+    }
+    /// Converts a physical rectangle to the local rectangle that covers it, rounding outwards.
+    pub fn rect_from_physical(&self, rect: Rect) -> Rect {
+        let scale = 1.0 / self.pixel_ratio;
+        map_rect(rect, (scale, scale), (0.0, 0.0))
+    }
+}
+
+// 消除浮点误差，避免整数结果被向下多取一个单位
+fn floor(value: f64) -> f64 {
+    (value + 1e-6).floor()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coordinate_space() {
+        let space = CoordinateSpace::new(Rect::new(1920, -100, 1280, 720), 1.5);
+
+        let global = Point::new(1921, -99);
+        let local = space.to_local(global);
+        assert_eq!(local, Point::new(1, 1));
+        assert_eq!(space.to_global(local), global);
+
+        // 1.5 倍时逻辑像素 1 覆盖物理像素 1.5 到 3，取左上角
+        assert_eq!(space.to_physical(local), Point::new(1, 1));
+        assert_eq!(space.to_physical(Point::new(3, 3)), Point::new(4, 4));
+        assert_eq!(space.from_physical(Point::new(4, 5)), Point::new(2, 3));
+        assert_eq!(space.physical_size(), (1920, 1080));
+
+        let rect = Rect::new(1, 1, 3, 3);
+        assert_eq!(space.rect_to_physical(rect), Rect::new(1, 1, 5, 5));
+        assert_eq!(
+            space.rect_from_physical(Rect::new(3, 3, 3, 3)),
+            Rect::new(2, 2, 2, 2)
+        );
+        assert_eq!(space.rect_to_global(rect), Rect::new(1921, -99, 3, 3));
+        assert_eq!(space.rect_to_local(space.rect_to_global(rect)), rect);
+
+        assert_eq!(
+            CoordinateSpace::new(Rect::default(), 0.0).pixel_ratio(),
+            1.0
+        );
+        assert_eq!(
+            CoordinateSpace::new(Rect::default(), f64::NAN).pixel_ratio(),
+            1.0
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use crate::{
+    Monitor, RecorderConfig, VideoRecorder,
+    error::{XCapError, XCapResult},
+    geometry::{Point, Rect},
+    platform,
+    video_recorder::Frame,
+    window_crop::crop_frame,
+};
+
+/// 录制区域在帧中的像素位置：以指针为中心、大小固定，靠近显示器边缘时移回显示器内，
+/// 帧小于区域时使用整个帧。monitor 和 cursor 是全局坐标
+fn cursor_region(
+    monitor: Rect,
+    cursor: Point,
+    frame_width: u32,
+    frame_height: u32,
+    width: u32,
+    height: u32,
+) -> Rect {
+    let width = width.min(frame_width);
+    let height = height.min(frame_height);
+
+    let center = |offset: i32, size: u32, frame_size: u32| {
+        (offset as f64 * frame_size as f64 / size.max(1) as f64).round() as i64
+    };
+    let center_x = center(cursor.x - monitor.x, monitor.width, frame_width);
+    let center_y = center(cursor.y - monitor.y, monitor.height, frame_height);
+
+    let left = (center_x - width as i64 / 2).clamp(0, (frame_width - width) as i64);
+    let top = (center_y - height as i64 / 2).clamp(0, (frame_height - height) as i64);
+
+    Rect::new(left as i32, top as i32, width, height)
+}
+
+/// 转发 monitor 的帧，指针不在这个显示器上时丢弃
+fn forward_cursor_frames(
+    monitor: Rect,
+    rx: Receiver<Frame>,
+    tx: mpsc::SyncSender<Frame>,
+    width: u32,
+    height: u32,
+) {
+    thread::spawn(move || {
+        for mut frame in rx {
+            let cursor = match platform::cursor_position() {
+                Ok(cursor) if monitor.contains_point(cursor.x, cursor.y) => cursor,
+                Ok(_) => continue,
+                Err(err) => {
+                    log::debug!("get cursor position failed: {err:?}");
+                    continue;
+                }
+            };
+
+            let rect = cursor_region(
+                monitor,
+                cursor,
+                frame.width(),
+                frame.height(),
+                width,
+                height,
+            );
+            let Some(mut cropped) = crop_frame(&frame, rect) else {
+                continue;
+            };
+            cropped.annotations = std::mem::take(&mut frame.annotations);
+
+            if tx.send(cropped).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+impl VideoRecorder {
+    /// Record a `width` x `height` pixel region centered on the mouse cursor, for zoom and
+    /// spotlight style tools. Near the edge of a monitor the region stays inside it.
+    ///
+    /// All monitors are recorded and each frame is taken from the monitor under the cursor, so
+    /// moving to another display doesn't wait for its stream to start. The region is in the
+    /// pixels of that monitor's frames, so it covers less of a high-DPI monitor. Not supported
+    /// on Wayland, where the cursor position can't be read.
+    ///
+    /// ```no_run
+    /// use xcap::{RecorderConfig, VideoRecorder};
+    ///
+    /// let (recorder, rx) =
+    ///     VideoRecorder::follow_cursor(640, 360, &RecorderConfig::default()).unwrap();
+    /// recorder.start().unwrap();
+    ///
+    /// for frame in rx.iter().take(30) {
+    ///     println!("{}x{}", frame.width(), frame.height());
+    /// }
+    /// ```
+    pub fn follow_cursor(
+        width: u32,
+        height: u32,
+        config: &RecorderConfig,
+    ) -> XCapResult<(VideoRecorder, Receiver<Frame>)> {
+        if width == 0 || height == 0 {
+            return Err(XCapError::InvalidCaptureRegion(format!(
+                "Region size {width}x{height} must not be empty"
+            )));
+        }
+        platform::cursor_position()?;
+
+        let monitors = Monitor::all()?;
+        let bounds = monitors
+            .iter()
+            .map(Monitor::bounds)
+            .collect::<XCapResult<Vec<Rect>>>()?;
+        let (recorder, receivers) = VideoRecorder::multi_with_config(&monitors, config)?;
+
+        // 不缓存帧，接收端没有取走时和单显示器录制一样丢帧
+        let (tx, rx) = mpsc::sync_channel(0);
+        for (monitor, monitor_rx) in bounds.into_iter().zip(receivers) {
+            forward_cursor_frames(monitor, monitor_rx, tx.clone(), width, height);
+        }
+
+        Ok((recorder, rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_region() {
+        let monitor = Rect::new(-100, 0, 100, 50);
+
+        // 2 倍缩放的显示器，指针在中间
+        assert_eq!(
+            cursor_region(monitor, Point::new(-50, 25), 200, 100, 40, 20),
+            Rect::new(80, 40, 40, 20)
+        );
+        // 靠近边缘时移回显示器内
+        assert_eq!(
+            cursor_region(monitor, Point::new(-100, 49), 200, 100, 40, 20),
+            Rect::new(0, 80, 40, 20)
+        );
+        // 区域大于帧
+        assert_eq!(
+            cursor_region(monitor, Point::new(-1, 0), 200, 100, 400, 20),
+            Rect::new(0, 0, 200, 20)
+        );
+    }
+}
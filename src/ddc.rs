@@ -0,0 +1,142 @@
+/// The current and maximum value of a VCP feature, see
+/// [`Monitor::vcp_feature`](crate::Monitor::vcp_feature).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VcpValue {
+    /// The current value.
+    pub current: u16,
+    /// The largest value the monitor accepts, for continuous features.
+    pub maximum: u16,
+}
+
+/// The MCCS capabilities string a monitor reports over DDC/CI, see
+/// [`Monitor::ddc_capabilities`](crate::Monitor::ddc_capabilities).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DdcCapabilities {
+    /// The string as received, e.g. `(prot(monitor)type(lcd)model(U2720Q)vcp(10 12 D6)mccs_ver(2.1))`.
+    pub raw: String,
+    /// The `model` field, which names the model even when the EDID does not.
+    pub model: Option<String>,
+    /// The `type` field, usually `lcd` or `crt`.
+    pub display_type: Option<String>,
+    /// The `mccs_ver` field, e.g. `2.1`.
+    pub mccs_version: Option<String>,
+    /// The VCP codes listed in the `vcp` field, without their allowed values.
+    pub vcp_codes: Vec<u8>,
+}
+
+impl DdcCapabilities {
+    /// 解析能力字符串，格式为括号嵌套的 key(value) 列表，字段缺失或格式错误时跳过
+    pub(crate) fn parse(raw: String) -> DdcCapabilities {
+        let fields = top_level_fields(&raw);
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        let vcp_codes = field("vcp")
+            .map(|vcp| parse_vcp_codes(&vcp))
+            .unwrap_or_default();
+
+        DdcCapabilities {
+            model: field("model"),
+            display_type: field("type"),
+            mccs_version: field("mccs_ver"),
+            vcp_codes,
+            raw,
+        }
+    }
+}
+
+/// 最外层括号中的 key(value) 字段，value 中可能还有括号
+fn top_level_fields(raw: &str) -> Vec<(&str, &str)> {
+    let inner = raw.trim().trim_end_matches('\0');
+    let inner = inner
+        .strip_prefix('(')
+        .and_then(|inner| inner.strip_suffix(')'))
+        .unwrap_or(inner);
+
+    let mut fields = Vec::new();
+    let mut depth = 0;
+    let mut key_start = 0;
+    let mut value_start = 0;
+
+    for (index, char) in inner.char_indices() {
+        match char {
+            '(' => {
+                if depth == 0 {
+                    value_start = index + 1;
+                }
+                depth += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let key = inner[key_start..value_start - 1].trim();
+                    fields.push((key, &inner[value_start..index]));
+                    key_start = index + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fields
+}
+
+/// vcp 字段是空格分隔的十六进制代码，代码后面可以用括号列出允许的值，如 14(05 08)
+fn parse_vcp_codes(vcp: &str) -> Vec<u8> {
+    let mut codes = Vec::new();
+    let mut depth = 0u32;
+    let mut token = String::new();
+
+    for char in vcp.chars().chain([' ']) {
+        if depth == 0 {
+            if char.is_ascii_hexdigit() {
+                token.push(char);
+                continue;
+            }
+
+            if let Ok(code) = u8::from_str_radix(&token, 16) {
+                codes.push(code);
+            }
+            token.clear();
+        }
+
+        match char {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    codes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_capabilities() {
+        let capabilities = DdcCapabilities::parse(
+            "(prot(monitor)type(LCD)model(U2720Q)cmds(01 02 03 07 0C E3 F3)\
+             vcp(02 04 10 12 14(05 08 0B) 60( 0F 11 1B) D6(01 04 05) DF)mccs_ver(2.1))"
+                .to_string(),
+        );
+
+        assert_eq!(capabilities.model.as_deref(), Some("U2720Q"));
+        assert_eq!(capabilities.display_type.as_deref(), Some("LCD"));
+        assert_eq!(capabilities.mccs_version.as_deref(), Some("2.1"));
+        assert_eq!(
+            capabilities.vcp_codes,
+            [0x02, 0x04, 0x10, 0x12, 0x14, 0x60, 0xD6, 0xDF]
+        );
+
+        let capabilities = DdcCapabilities::parse("garbage".to_string());
+        assert_eq!(capabilities.model, None);
+        assert!(capabilities.vcp_codes.is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use image::RgbaImage;
+
+use crate::{
+    error::XCapResult,
+    geometry::Rect,
+    platform::impl_window::{ImplWindow, ImplWindowDamage},
+};
+
+// 轮询焦点窗口的间隔
+const FOCUS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+// 焦点切换后需要保持不变的时长，避免在切换动画过程中截图
+const FOCUS_SETTLE_DURATION: Duration = Duration::from_millis(250);
+// 等待内容稳定时截图的间隔
+const STABLE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+// 内容需要保持不变的时长，覆盖动画中短暂停顿的几帧
+const STABLE_DURATION: Duration = Duration::from_millis(200);
+// 刷新率未知时按 60Hz 计算
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+const DEFAULT_FREQUENCY: f32 = 60.0;
+
+/// 睡眠到指定时间点，thread::sleep 可能被提前唤醒，所以循环直到真正到达
+pub(crate) fn sleep_until(deadline: Instant) {
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+
+        thread::sleep(deadline - now);
+    }
+}
+
+/// 没有合成同步接口时的近似：等待两个刷新周期，第一个周期合成器取到新的内容，第二个周期显示出来
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+pub(crate) fn wait_refresh_periods(frequency: f32) {
+    let frequency = if frequency > 0.0 {
+        frequency
+    } else {
+        DEFAULT_FREQUENCY
+    };
+
+    sleep_until(Instant::now() + Duration::from_secs_f32(2.0 / frequency));
+}
+
+/// 直接查询系统的焦点窗口，轮询时不枚举所有窗口
+fn get_focused_window_id() -> Option<u32> {
+    ImplWindow::focused_id().ok()
+}
+
+/// 等待焦点窗口发生变化并稳定下来，超时后直接返回
+pub(crate) fn wait_for_focus_change(timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    let initial_window_id = get_focused_window_id();
+
+    let mut changed_window: Option<(Option<u32>, Instant)> = None;
+
+    while Instant::now() < deadline {
+        sleep_until((Instant::now() + FOCUS_POLL_INTERVAL).min(deadline));
+
+        let window_id = get_focused_window_id();
+        match changed_window {
+            Some((changed_window_id, changed_at)) if changed_window_id == window_id => {
+                if changed_at.elapsed() >= FOCUS_SETTLE_DURATION {
+                    return;
+                }
+            }
+            _ if window_id != initial_window_id => {
+                changed_window = Some((window_id, Instant::now()));
+            }
+            _ => changed_window = None,
+        }
+    }
+}
+
+pub(crate) fn capture_after<T, F>(
+    delay: Duration,
+    focus_change_timeout: Option<Duration>,
+    capture: F,
+) -> XCapResult<T>
+where
+    F: FnOnce() -> XCapResult<T>,
+{
+    sleep_until(Instant::now() + delay);
+
+    if let Some(timeout) = focus_change_timeout {
+        wait_for_focus_change(timeout);
+    }
+
+    capture()
+}
+
+/// 等待窗口内容在 STABLE_DURATION 内保持不变后截图，超时后返回最后一次截图。
+/// 平台能报告窗口的变化区域时（X11 Damage、DXGI 脏矩形）只在稳定后截图一次，否则反复截图比较
+pub(crate) fn capture_when_stable<F>(
+    window_id: u32,
+    timeout: Duration,
+    capture: F,
+) -> XCapResult<RgbaImage>
+where
+    F: FnMut() -> XCapResult<RgbaImage>,
+{
+    let deadline = Instant::now() + timeout;
+
+    match ImplWindowDamage::new(window_id) {
+        Ok(mut damage) => wait_until_undamaged(deadline, |timeout| damage.wait(timeout), capture),
+        Err(err) => {
+            log::debug!("window damage is not available, comparing captures: {err:?}");
+            capture_until_unchanged(deadline, capture)
+        }
+    }
+}
+
+/// 等待变化通知，STABLE_DURATION 内没有变化时截图，等待失败时改为比较截图
+fn wait_until_undamaged<W, F>(
+    deadline: Instant,
+    mut wait: W,
+    mut capture: F,
+) -> XCapResult<RgbaImage>
+where
+    W: FnMut(Duration) -> XCapResult<Vec<Rect>>,
+    F: FnMut() -> XCapResult<RgbaImage>,
+{
+    let mut stable_since = Instant::now();
+
+    loop {
+        let now = Instant::now();
+        let stable_at = stable_since + STABLE_DURATION;
+        if now >= deadline || now >= stable_at {
+            return capture();
+        }
+
+        match wait(stable_at.min(deadline) - now) {
+            Ok(rects) if !rects.is_empty() => stable_since = Instant::now(),
+            Ok(_) => {}
+            Err(err) => {
+                log::debug!("wait for window damage failed, comparing captures: {err:?}");
+                return capture_until_unchanged(deadline, capture);
+            }
+        }
+    }
+}
+
+/// 反复截图直到内容在 STABLE_DURATION 内保持不变，超时后返回最后一次截图
+fn capture_until_unchanged<F>(deadline: Instant, mut capture: F) -> XCapResult<RgbaImage>
+where
+    F: FnMut() -> XCapResult<RgbaImage>,
+{
+    let mut image = capture()?;
+    let mut stable_since = Instant::now();
+
+    loop {
+        let now = Instant::now();
+        if now >= deadline || now - stable_since >= STABLE_DURATION {
+            return Ok(image);
+        }
+
+        sleep_until((now + STABLE_POLL_INTERVAL).min(deadline));
+
+        let current = capture()?;
+        if current != image {
+            image = current;
+            stable_since = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_until_unchanged() {
+        // 前 3 次截图内容都在变化，之后保持不变
+        let mut count = 0u8;
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let image = capture_until_unchanged(deadline, || {
+            count = (count + 1).min(4);
+            Ok(RgbaImage::from_pixel(2, 2, image::Rgba([count; 4])))
+        })
+        .unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [4; 4]);
+
+        // 一直变化时在超时后返回最后一次截图
+        let mut count = 0u8;
+        let start = Instant::now();
+        let image = capture_until_unchanged(start + Duration::from_millis(120), || {
+            count += 1;
+            Ok(RgbaImage::from_pixel(2, 2, image::Rgba([count; 4])))
+        })
+        .unwrap();
+        assert!(start.elapsed() < STABLE_DURATION);
+        assert_eq!(image.get_pixel(0, 0).0, [count; 4]);
+    }
+
+    #[test]
+    fn test_wait_until_undamaged() {
+        // 前 2 次等待都报告了变化，之后没有变化，只在稳定后截图一次
+        let mut waits = 0;
+        let mut captures = 0;
+        let start = Instant::now();
+        let image = wait_until_undamaged(
+            start + Duration::from_secs(5),
+            |timeout| {
+                waits += 1;
+                if waits > 2 {
+                    thread::sleep(timeout);
+                    return Ok(Vec::new());
+                }
+                Ok(vec![Rect::new(0, 0, 1, 1)])
+            },
+            || {
+                captures += 1;
+                Ok(RgbaImage::from_pixel(2, 2, image::Rgba([captures; 4])))
+            },
+        )
+        .unwrap();
+        assert_eq!(captures, 1);
+        assert_eq!(image.get_pixel(0, 0).0, [1; 4]);
+        assert!(start.elapsed() >= STABLE_DURATION);
+    }
+
+    #[test]
+    fn test_wait_until_undamaged_fallback() {
+        // 等待变化失败时改为比较截图
+        let mut captures = 0u8;
+        let image = wait_until_undamaged(
+            Instant::now() + Duration::from_secs(5),
+            |_| Err(crate::XCapError::NotSupported),
+            || {
+                captures = (captures + 1).min(2);
+                Ok(RgbaImage::from_pixel(2, 2, image::Rgba([captures; 4])))
+            },
+        )
+        .unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [2; 4]);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{geometry::Rect, video_recorder::Frame};
+
+// 比较的最小单位，32x32 像素的块在精度和开销之间比较均衡
+const TILE_SIZE: u32 = 32;
+
+/// 判断两帧中的一个块是否有变化，按行比较切片，切片比较会被编译为 memcmp，由 libc 做向量化
+fn is_tile_changed(a: &Frame, b: &Frame, tile: Rect) -> bool {
+    let start = tile.x as usize * 4;
+    let end = start + tile.width as usize * 4;
+
+    (tile.y as usize..tile.y as usize + tile.height as usize).any(|y| {
+        let row_a = a.data().get(y * a.stride() + start..y * a.stride() + end);
+        let row_b = b.data().get(y * b.stride() + start..y * b.stride() + end);
+
+        row_a != row_b
+    })
+}
+
+/// The rectangles that differ between two frames of the same monitor, in frame pixel coordinates.
+///
+/// Frames are compared in 32x32 tiles and changed tiles are merged into larger rectangles, so
+/// encoders and sync tools can send only the deltas on backends without native dirty rects. If
+/// the frames differ in size or pixel format the whole of `b` is reported as changed.
+pub fn diff(a: &Frame, b: &Frame) -> Vec<Rect> {
+    let (width, height) = (b.width(), b.height());
+
+    if a.width() != width || a.height() != height || a.format() != b.format() {
+        return vec![Rect::new(0, 0, width, height)];
+    }
+
+    let mut rects: Vec<Rect> = Vec::new();
+    // 上一行块中合并出来的矩形，用于和当前行相同跨度的矩形在垂直方向合并
+    let mut previous_row: Vec<usize> = Vec::new();
+
+    for tile_y in (0..height).step_by(TILE_SIZE as usize) {
+        let tile_height = TILE_SIZE.min(height - tile_y);
+        let mut current_row = Vec::new();
+        let mut run: Option<(u32, u32)> = None;
+
+        for tile_x in (0..width).step_by(TILE_SIZE as usize) {
+            let tile_width = TILE_SIZE.min(width - tile_x);
+            let tile = Rect::new(tile_x as i32, tile_y as i32, tile_width, tile_height);
+
+            if is_tile_changed(a, b, tile) {
+                // 水平方向连续变化的块合并为一段
+                run = Some(match run {
+                    Some((start, run_width)) => (start, run_width + tile_width),
+                    None => (tile_x, tile_width),
+                });
+                continue;
+            }
+
+            if let Some(span) = run.take() {
+                current_row.push(span);
+            }
+        }
+        if let Some(span) = run.take() {
+            current_row.push(span);
+        }
+
+        let mut merged_row = Vec::with_capacity(current_row.len());
+        for (start, run_width) in current_row {
+            // 和上一行跨度完全相同的矩形向下延伸
+            let extended = previous_row.iter().copied().find(|&index| {
+                let rect = rects[index];
+                rect.x == start as i32
+                    && rect.width == run_width
+                    && rect.y as i64 + rect.height as i64 == tile_y as i64
+            });
+
+            match extended {
+                Some(index) => {
+                    rects[index].height += tile_height;
+                    merged_row.push(index);
+                }
+                None => {
+                    rects.push(Rect::new(
+                        start as i32,
+                        tile_y as i32,
+                        run_width,
+                        tile_height,
+                    ));
+                    merged_row.push(rects.len() - 1);
+                }
+            }
+        }
+        previous_row = merged_row;
+    }
+
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn frame(width: u32, height: u32, changed: &[(u32, u32)]) -> Frame {
+        let mut raw = vec![0; (width * height * 4) as usize];
+        for &(x, y) in changed {
+            raw[((y * width + x) * 4) as usize] = 255;
+        }
+
+        Frame::new(width, height, raw)
+    }
+
+    #[test]
+    fn test_diff_identical() {
+        assert!(diff(&frame(100, 100, &[]), &frame(100, 100, &[])).is_empty());
+    }
+
+    #[test]
+    fn test_diff_merges_tiles() {
+        let a = frame(100, 100, &[]);
+        // (0,0) 和 (40,0) 在相邻的两个块中，(40,40) 在它们下方
+        let b = frame(100, 100, &[(0, 0), (40, 0), (5, 40), (40, 40), (99, 99)]);
+
+        assert_eq!(
+            diff(&a, &b),
+            vec![Rect::new(0, 0, 64, 64), Rect::new(96, 96, 4, 4)]
+        );
+    }
+
+    #[test]
+    fn test_diff_with_stride() {
+        let a = frame(2, 2, &[]);
+        // 填充字节不同不算变化
+        let b = Frame::with_stride(
+            2,
+            2,
+            12,
+            [0, 0, 0, 0, 0, 0, 0, 0, 9, 9, 9, 9].repeat(2),
+            Instant::now(),
+        );
+
+        assert!(diff(&a, &b).is_empty());
+        assert_eq!(diff(&a, &frame(3, 2, &[])), vec![Rect::new(0, 0, 3, 2)]);
+    }
+}
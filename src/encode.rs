@@ -0,0 +1,83 @@
+use image::{
+    ExtendedColorType, GenericImageView, ImageEncoder, Rgb, RgbaImage,
+    codecs::{jpeg::JpegEncoder, png::PngEncoder},
+};
+
+use crate::error::{XCapError, XCapResult};
+
+/// 直接从截图的像素缓冲区编码为 PNG，不经过 DynamicImage 转换
+pub(crate) fn encode_png(image: &RgbaImage) -> XCapResult<Vec<u8>> {
+    // 屏幕内容的 PNG 大约是原始数据的 1/4 到 1/10
+    let mut png = Vec::with_capacity(image.as_raw().len() / 4);
+
+    PngEncoder::new(&mut png)
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            ExtendedColorType::Rgba8,
+        )
+        .map_err(|err| XCapError::new(format!("Encode png failed: {err}")))?;
+
+    Ok(png)
+}
+
+/// JPEG 不支持透明通道，编码器按 8x8 块逐个读取像素，读取时直接丢弃透明通道，
+/// 不需要先复制出一份 RGB 数据
+struct RgbView<'a>(&'a RgbaImage);
+
+impl GenericImageView for RgbView<'_> {
+    type Pixel = Rgb<u8>;
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.0.dimensions()
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> Rgb<u8> {
+        let [r, g, b, _] = self.0.get_pixel(x, y).0;
+        Rgb([r, g, b])
+    }
+}
+
+/// 按 quality（1 到 100）编码为 JPEG
+pub(crate) fn encode_jpeg(image: &RgbaImage, quality: u8) -> XCapResult<Vec<u8>> {
+    let mut jpeg = Vec::new();
+
+    JpegEncoder::new_with_quality(&mut jpeg, quality.clamp(1, 100))
+        .encode_image(&RgbView(image))
+        .map_err(|err| XCapError::new(format!("Encode jpeg failed: {err}")))?;
+
+    Ok(jpeg)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn test_encode_png() {
+        let image = RgbaImage::from_pixel(3, 2, Rgba([1, 2, 3, 4]));
+        let png = encode_png(&image).unwrap();
+
+        let decoded = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(decoded, image);
+    }
+
+    #[test]
+    fn test_encode_jpeg() {
+        let image = RgbaImage::from_pixel(16, 8, Rgba([200, 100, 50, 128]));
+        let jpeg = encode_jpeg(&image, 100).unwrap();
+
+        // JPEG 有损且没有透明通道，解码后颜色接近原图，透明度为 255
+        let decoded = image::load_from_memory(&jpeg).unwrap().to_rgba8();
+        assert_eq!(decoded.dimensions(), image.dimensions());
+        for (decoded, original) in decoded.pixels().zip(image.pixels()) {
+            for channel in 0..3 {
+                assert!(decoded[channel].abs_diff(original[channel]) <= 4);
+            }
+            assert_eq!(decoded[3], 255);
+        }
+    }
+}
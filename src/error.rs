@@ -1,7 +1,14 @@
-use std::sync::PoisonError;
+use std::{
+    any::Any,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::PoisonError,
+};
 
 use thiserror::Error;
 
+use crate::config::Config;
+
 #[derive(Debug, Error)]
 pub enum XCapError {
     #[error("Not supported")]
@@ -12,6 +19,21 @@ pub enum XCapError {
     StdSyncPoisonError(String),
     #[error("Invalid capture region: {0}")]
     InvalidCaptureRegion(String),
+    /// A temporary failure that a later attempt is likely to get past, e.g. a ScreenCaptureKit
+    /// stream timing out. See [`XCapError::is_transient`].
+    #[error("{0}")]
+    Transient(String),
+    /// A panic was caught at the API boundary, see
+    /// [`ConfigBuilder::catch_panics`](crate::ConfigBuilder::catch_panics).
+    #[error("Panicked: {0}")]
+    Panicked(String),
+    /// An operating system API failed, the [`PlatformError`] is the error's source.
+    #[error("{message}")]
+    Platform {
+        message: String,
+        #[source]
+        source: PlatformError,
+    },
 
     #[cfg(target_os = "linux")]
     #[error(transparent)]
@@ -48,7 +70,7 @@ pub enum XCapError {
     PipewireError(#[from] pipewire::Error),
 
     #[cfg(target_os = "macos")]
-    #[error("Objc2CoreGraphicsCGError {:?}", 0)]
+    #[error("Objc2CoreGraphicsCGError {0:?}")]
     Objc2CoreGraphicsCGError(objc2_core_graphics::CGError),
 
     #[cfg(target_os = "windows")]
@@ -63,12 +85,191 @@ impl XCapError {
     pub fn new<S: ToString>(err: S) -> Self {
         XCapError::Error(err.to_string())
     }
+
+    /// 重试后可能成功的错误，例如等待系统返回帧超时
+    #[cfg(any(target_os = "macos", target_os = "linux", test))]
+    pub(crate) fn transient<S: ToString>(err: S) -> Self {
+        XCapError::Transient(err.to_string())
+    }
+
+    /// Whether the failure is temporary, so that retrying the capture is likely to succeed:
+    /// [`XCapError::Transient`], a lost Desktop Duplication (`DXGI_ERROR_ACCESS_LOST`) on
+    /// Windows, or a busy XDG desktop portal on Linux. Everything else, including a denied
+    /// permission or a closed window, is permanent. Only transient failures are retried, see
+    /// [`CaptureConfig::retries`](crate::CaptureConfig::retries).
+    pub fn is_transient(&self) -> bool {
+        match self {
+            XCapError::Transient(_) => true,
+            // 切换桌面、UAC 提示等会打断桌面复制，重新创建后可以继续
+            #[cfg(target_os = "windows")]
+            XCapError::WindowsCoreError(err) => {
+                err.code() == windows::Win32::Graphics::Dxgi::DXGI_ERROR_ACCESS_LOST
+            }
+            #[cfg(target_os = "linux")]
+            XCapError::ZbusError(err) => is_portal_busy(err),
+            _ => false,
+        }
+    }
+
+    /// 操作系统接口返回的错误，message 说明失败的操作
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+    pub(crate) fn platform<S: ToString>(message: S, source: PlatformError) -> Self {
+        XCapError::Platform {
+            message: message.to_string(),
+            source,
+        }
+    }
+
+    /// The operating system error behind [`XCapError::Platform`].
+    pub fn platform_error(&self) -> Option<&PlatformError> {
+        match self {
+            XCapError::Platform { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// An error reported by an operating system API, with the code to search for in its
+/// documentation: an `NSError` domain and code on macOS, an `HRESULT` on Windows, an `errno`
+/// or an XDG desktop portal response code on Linux.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlatformError {
+    domain: String,
+    code: i64,
+    message: String,
+}
+
+impl PlatformError {
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+    pub(crate) fn new<D: ToString, M: ToString>(domain: D, code: i64, message: M) -> Self {
+        PlatformError {
+            domain: domain.to_string(),
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    /// Where the code comes from, e.g. `SCStreamErrorDomain`, `HRESULT`, `errno` or
+    /// `org.freedesktop.portal.Request`.
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+    /// The error code within [`PlatformError::domain`].
+    pub fn code(&self) -> i64 {
+        self.code
+    }
+    /// The description the operating system gave, possibly localized.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for PlatformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // HRESULT 习惯用十六进制表示
+        if self.domain == "HRESULT" {
+            write!(
+                f,
+                "{} {:#010x}: {}",
+                self.domain, self.code as u32, self.message
+            )
+        } else {
+            write!(f, "{} {}: {}", self.domain, self.code, self.message)
+        }
+    }
 }
 
+impl std::error::Error for PlatformError {}
+
 pub type XCapResult<T> = Result<T, XCapError>;
 
+/// 门户正在处理其他请求，或者没有及时响应
+#[cfg(target_os = "linux")]
+fn is_portal_busy(err: &zbus::Error) -> bool {
+    match err {
+        zbus::Error::MethodError(name, _, _) => matches!(
+            name.as_str(),
+            "org.freedesktop.DBus.Error.LimitsExceeded" | "org.freedesktop.DBus.Error.NoReply"
+        ),
+        zbus::Error::FDO(err) => matches!(
+            **err,
+            zbus::fdo::Error::LimitsExceeded(_) | zbus::fdo::Error::NoReply(_)
+        ),
+        _ => false,
+    }
+}
+
+/// 开启 catch_panics 时把 f 中的 panic 转换为错误，避免 panic 穿过 FFI 边界导致宿主进程退出
+pub(crate) fn catch_panics<T, F>(f: F) -> XCapResult<T>
+where
+    F: FnOnce() -> XCapResult<T>,
+{
+    if !Config::get().catch_panics {
+        return f();
+    }
+
+    // panic 之后只返回错误，不会再使用 f 中被中断的状态
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|payload| Err(XCapError::Panicked(panic_message(payload.as_ref()))))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
 impl<T> From<PoisonError<T>> for XCapError {
     fn from(value: PoisonError<T>) -> Self {
         XCapError::StdSyncPoisonError(value.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn test_platform_error() {
+        let err = XCapError::platform(
+            "Start stream failed",
+            PlatformError::new(
+                "HRESULT",
+                0x8007_0005_u32 as i32 as i64,
+                "Access is denied.",
+            ),
+        );
+
+        assert_eq!(err.to_string(), "Start stream failed");
+        assert_eq!(
+            err.source().map(|source| source.to_string()),
+            Some("HRESULT 0x80070005: Access is denied.".to_string())
+        );
+        assert_eq!(
+            err.platform_error().map(PlatformError::code),
+            Some(-2147024891)
+        );
+
+        let source = PlatformError::new("SCStreamErrorDomain", -3801, "The user declined.");
+        assert_eq!(
+            source.to_string(),
+            "SCStreamErrorDomain -3801: The user declined."
+        );
+        assert!(XCapError::NotSupported.platform_error().is_none());
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = panic::catch_unwind(|| panic!("index {} out of range", 3)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "index 3 out of range");
+
+        let payload = panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "static");
+    }
+}
@@ -0,0 +1,18 @@
+//! Entry points for the fuzz targets in `fuzz/`, enabled by the `fuzzing` feature. Not part of
+//! the public API.
+
+use crate::monitor_identity::{edid_name, parse_edid, wmi_string};
+
+/// Parses `data` as an EDID the way every platform does, both with and without the header check.
+pub fn edid(data: &[u8]) {
+    if let Ok(edid) = parse_edid(data) {
+        // 头部有效时两种方式读到的名称必须一致
+        assert_eq!(edid.identity.name, edid_name(data));
+    }
+    let _ = edid_name(data);
+}
+
+/// Decodes `data` as a WMI uint8 string property, e.g. `WmiMonitorID.SerialNumberID`.
+pub fn wmi(data: &[u8]) {
+    let _ = wmi_string(data);
+}
@@ -0,0 +1,433 @@
+/// A point in screen coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+}
+
+/// A rectangle in global screen coordinates. The origin is the top-left corner and may be
+/// negative on multi-monitor layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // 用 i64 计算右下角，避免 x + width 溢出
+    fn right(&self) -> i64 {
+        self.x as i64 + self.width as i64
+    }
+
+    fn bottom(&self) -> i64 {
+        self.y as i64 + self.height as i64
+    }
+
+    /// Whether the point lies inside the rectangle. The right and bottom edges are exclusive.
+    pub fn contains_point(&self, x: i32, y: i32) -> bool {
+        x >= self.x && (x as i64) < self.right() && y >= self.y && (y as i64) < self.bottom()
+    }
+
+    /// Whether `rect` lies entirely inside the rectangle.
+    pub fn contains_rect(&self, rect: Rect) -> bool {
+        rect.x >= self.x
+            && rect.y >= self.y
+            && rect.right() <= self.right()
+            && rect.bottom() <= self.bottom()
+    }
+
+    /// Whether the two rectangles overlap. Rectangles that only share an edge do not overlap.
+    pub fn intersects(&self, rect: Rect) -> bool {
+        self.intersection(rect).is_some()
+    }
+
+    /// The overlapping part of the two rectangles, or `None` if they do not overlap.
+    pub fn intersection(&self, rect: Rect) -> Option<Rect> {
+        let left = self.x.max(rect.x);
+        let top = self.y.max(rect.y);
+        let right = self.right().min(rect.right());
+        let bottom = self.bottom().min(rect.bottom());
+
+        if right <= left as i64 || bottom <= top as i64 {
+            return None;
+        }
+
+        Some(Rect::new(
+            left,
+            top,
+            (right - left as i64) as u32,
+            (bottom - top as i64) as u32,
+        ))
+    }
+}
+
+/// A side of a monitor, in screen coordinates where y grows downwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Where a monitor lies relative to another, see
+/// [`Monitor::relative_position`](crate::Monitor::relative_position).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RelativePosition {
+    /// The other monitor's origin minus this monitor's origin, e.g. to place both in one
+    /// stitched image.
+    pub offset: Point,
+    /// The side of this monitor the other one lies beyond, `None` when the two overlap, e.g.
+    /// mirrored monitors. A diagonal neighbor counts as left or right unless it is further
+    /// away vertically than horizontally.
+    pub direction: Option<Direction>,
+    /// Whether the monitors share part of an edge, so the cursor can move straight from one
+    /// to the other. Monitors touching only at a corner are not adjacent.
+    pub is_adjacent: bool,
+}
+
+/// 两个矩形在水平和垂直方向上的间距，重叠时为负数，只共用边时为 0
+fn gaps(from: Rect, to: Rect) -> (i64, i64) {
+    let horizontal = (to.x as i64 - from.right()).max(from.x as i64 - to.right());
+    let vertical = (to.y as i64 - from.bottom()).max(from.y as i64 - to.bottom());
+
+    (horizontal, vertical)
+}
+
+/// to 相对于 from 的位置
+pub(crate) fn relative_position(from: Rect, to: Rect) -> RelativePosition {
+    let offset = Point::new(
+        (to.x as i64 - from.x as i64) as i32,
+        (to.y as i64 - from.y as i64) as i32,
+    );
+    let (horizontal, vertical) = gaps(from, to);
+
+    let direction = if horizontal < 0 && vertical < 0 {
+        None
+    } else if horizontal >= vertical {
+        Some(if to.x >= from.x {
+            Direction::Right
+        } else {
+            Direction::Left
+        })
+    } else {
+        Some(if to.y >= from.y {
+            Direction::Down
+        } else {
+            Direction::Up
+        })
+    };
+
+    RelativePosition {
+        offset,
+        direction,
+        is_adjacent: (horizontal == 0 && vertical < 0) || (vertical == 0 && horizontal < 0),
+    }
+}
+
+/// candidates 中位于 from 的 direction 一侧的最近的矩形，优先选择相邻的，
+/// 其次间距最小的，再次在垂直于方向的轴上重叠最多的
+pub(crate) fn neighbor(from: Rect, candidates: &[Rect], direction: Direction) -> Option<usize> {
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, candidate)| relative_position(from, **candidate).direction == Some(direction))
+        .min_by_key(|(_, candidate)| {
+            let position = relative_position(from, **candidate);
+            let (horizontal, vertical) = gaps(from, **candidate);
+            let (gap, overlap) = match direction {
+                Direction::Left | Direction::Right => (horizontal, -vertical),
+                Direction::Up | Direction::Down => (vertical, -horizontal),
+            };
+
+            (!position.is_adjacent, gap, -overlap)
+        })
+        .map(|(index, _)| index)
+}
+
+/// 包含所有点的最小矩形，没有点时返回 None
+pub(crate) fn bounding_rect(points: &[Point]) -> Option<Rect> {
+    let first = points.first()?;
+    let (mut left, mut top, mut right, mut bottom) = (first.x, first.y, first.x, first.y);
+
+    for point in points {
+        left = left.min(point.x);
+        top = top.min(point.y);
+        right = right.max(point.x);
+        bottom = bottom.max(point.y);
+    }
+
+    Some(Rect::new(
+        left,
+        top,
+        (right as i64 - left as i64 + 1) as u32,
+        (bottom as i64 - top as i64 + 1) as u32,
+    ))
+}
+
+/// 包含所有矩形的最小矩形，没有矩形时返回 None
+pub(crate) fn union_rect(rects: &[Rect]) -> Option<Rect> {
+    let first = rects.first()?;
+    let (mut left, mut top, mut right, mut bottom) =
+        (first.x, first.y, first.right(), first.bottom());
+
+    for rect in rects {
+        left = left.min(rect.x);
+        top = top.min(rect.y);
+        right = right.max(rect.right());
+        bottom = bottom.max(rect.bottom());
+    }
+
+    Some(Rect::new(
+        left,
+        top,
+        (right - left as i64) as u32,
+        (bottom - top as i64) as u32,
+    ))
+}
+
+/// 将全局坐标的区域按显示器拆分
+///
+/// 返回：(显示器下标, 显示器内的相对区域, 在拼接结果中的偏移)
+pub(crate) fn split_region(region: Rect, monitors: &[Rect]) -> Vec<(usize, Rect, (u32, u32))> {
+    monitors
+        .iter()
+        .enumerate()
+        .filter_map(|(index, bounds)| {
+            let intersection = bounds.intersection(region)?;
+            let local = Rect::new(
+                intersection.x - bounds.x,
+                intersection.y - bounds.y,
+                intersection.width,
+                intersection.height,
+            );
+            let offset = (
+                (intersection.x as i64 - region.x as i64) as u32,
+                (intersection.y as i64 - region.y as i64) as u32,
+            );
+
+            Some((index, local, offset))
+        })
+        .collect()
+}
+
+/// 先按 scale 缩放再按 offset 平移，把区域映射到另一个坐标系，例如逻辑坐标到帧缓冲像素
+///
+/// 结果向外取整，保证覆盖原区域；scale 为负数时区域翻转
+pub(crate) fn map_rect(rect: Rect, scale: (f64, f64), offset: (f64, f64)) -> Rect {
+    let map = |value: i64, scale: f64, offset: f64| value as f64 * scale + offset;
+
+    let (x1, x2) = (
+        map(rect.x as i64, scale.0, offset.0),
+        map(rect.right(), scale.0, offset.0),
+    );
+    let (y1, y2) = (
+        map(rect.y as i64, scale.1, offset.1),
+        map(rect.bottom(), scale.1, offset.1),
+    );
+
+    // 先消除浮点误差，避免整数边界被向外多取一个像素
+    let floor = |value: f64| (value + 1e-6).floor();
+    let ceil = |value: f64| (value - 1e-6).ceil();
+    let left = floor(x1.min(x2));
+    let top = floor(y1.min(y2));
+    let right = ceil(x1.max(x2));
+    let bottom = ceil(y1.max(y2));
+
+    Rect::new(
+        left as i32,
+        top as i32,
+        (right - left) as u32,
+        (bottom - top) as u32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_point() {
+        let rect = Rect::new(-1920, 0, 1920, 1080);
+
+        assert!(rect.contains_point(-1920, 0));
+        assert!(rect.contains_point(-1, 1079));
+        assert!(!rect.contains_point(0, 0));
+        assert!(!rect.contains_point(-1920, 1080));
+    }
+
+    #[test]
+    fn test_relative_position() {
+        let main = Rect::new(0, 0, 1920, 1080);
+
+        // 右侧相邻，垂直方向有偏移
+        let right = Rect::new(1920, -200, 1920, 1080);
+        assert_eq!(
+            relative_position(main, right),
+            RelativePosition {
+                offset: Point::new(1920, -200),
+                direction: Some(Direction::Right),
+                is_adjacent: true,
+            }
+        );
+        assert_eq!(
+            relative_position(right, main).direction,
+            Some(Direction::Left)
+        );
+
+        // 只在角上接触
+        let corner = Rect::new(1920, 1080, 1920, 1080);
+        let position = relative_position(main, corner);
+        assert_eq!(position.direction, Some(Direction::Right));
+        assert!(!position.is_adjacent);
+
+        let above = Rect::new(200, -1440, 2560, 1440);
+        assert_eq!(
+            relative_position(main, above).direction,
+            Some(Direction::Up)
+        );
+        assert_eq!(relative_position(main, main).direction, None);
+    }
+
+    #[test]
+    fn test_neighbor() {
+        let main = Rect::new(0, 0, 1920, 1080);
+        let candidates = [
+            Rect::new(3840, 0, 1920, 1080),
+            Rect::new(1920, 0, 1920, 1080),
+            Rect::new(0, 1080, 1920, 1080),
+            Rect::new(1920, 1080, 1920, 1080),
+        ];
+
+        assert_eq!(neighbor(main, &candidates, Direction::Right), Some(1));
+        assert_eq!(neighbor(main, &candidates, Direction::Down), Some(2));
+        assert_eq!(neighbor(main, &candidates, Direction::Left), None);
+        assert_eq!(
+            neighbor(candidates[0], &candidates, Direction::Left),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_intersection() {
+        let rect = Rect::new(0, 0, 1920, 1080);
+
+        assert_eq!(
+            rect.intersection(Rect::new(1800, 1000, 400, 400)),
+            Some(Rect::new(1800, 1000, 120, 80))
+        );
+        assert_eq!(
+            rect.intersection(Rect::new(-100, -100, 200, 200)),
+            Some(Rect::new(0, 0, 100, 100))
+        );
+        assert_eq!(rect.intersection(Rect::new(1920, 0, 100, 100)), None);
+        assert!(!rect.intersects(Rect::new(0, 1080, 100, 100)));
+        assert!(rect.contains_rect(Rect::new(0, 0, 1920, 1080)));
+        assert!(!rect.contains_rect(Rect::new(1, 0, 1920, 1080)));
+        assert!(!rect.contains_rect(Rect::new(i32::MAX, 0, u32::MAX, 1)));
+    }
+
+    #[test]
+    fn test_bounding_rect() {
+        let points = [Point::new(10, -5), Point::new(3, 7), Point::new(12, 0)];
+
+        assert_eq!(bounding_rect(&points), Some(Rect::new(3, -5, 10, 13)));
+        assert_eq!(
+            bounding_rect(&[Point::new(1, 1)]),
+            Some(Rect::new(1, 1, 1, 1))
+        );
+        assert_eq!(bounding_rect(&[]), None);
+    }
+
+    #[test]
+    fn test_union_rect() {
+        let rects = [Rect::new(-100, 10, 200, 50), Rect::new(50, -20, 30, 30)];
+
+        assert_eq!(union_rect(&rects), Some(Rect::new(-100, -20, 200, 80)));
+        assert_eq!(union_rect(&[]), None);
+    }
+
+    #[test]
+    fn test_split_region_left_of_primary() {
+        let monitors = [
+            Rect::new(0, 0, 1920, 1080),
+            Rect::new(-2560, -360, 2560, 1440),
+        ];
+
+        let parts = split_region(Rect::new(-100, 10, 200, 50), &monitors);
+
+        assert_eq!(
+            parts,
+            vec![
+                (0, Rect::new(0, 10, 100, 50), (100, 0)),
+                (1, Rect::new(2460, 370, 100, 50), (0, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_region_above_primary() {
+        let monitors = [
+            Rect::new(0, 0, 1920, 1080),
+            Rect::new(200, -1200, 1600, 1200),
+        ];
+
+        let parts = split_region(Rect::new(100, -50, 400, 100), &monitors);
+
+        assert_eq!(
+            parts,
+            vec![
+                (0, Rect::new(100, 0, 400, 50), (0, 50)),
+                (1, Rect::new(0, 1150, 300, 50), (100, 0)),
+            ]
+        );
+        assert!(split_region(Rect::new(-500, -500, 100, 100), &monitors).is_empty());
+    }
+
+    #[test]
+    fn test_map_rect() {
+        // 逻辑坐标按 1.5 倍缩放到帧缓冲，再加上显示器原点，小数边界向外取整
+        assert_eq!(
+            map_rect(Rect::new(10, 10, 101, 21), (1.5, 1.5), (-1920.0, 0.0)),
+            Rect::new(-1905, 15, 152, 32)
+        );
+        assert_eq!(
+            map_rect(Rect::new(0, 0, 100, 100), (1.0, 1.0), (0.0, 0.0)),
+            Rect::new(0, 0, 100, 100)
+        );
+        // 水平翻转
+        assert_eq!(
+            map_rect(Rect::new(0, 0, 100, 50), (-1.0, 1.0), (1920.0, 0.0)),
+            Rect::new(1820, 0, 100, 50)
+        );
+        assert_eq!(
+            map_rect(
+                Rect::new(30, 30, 30, 30),
+                (1.0 / 3.0, 1.0 / 3.0),
+                (0.0, 0.0)
+            ),
+            Rect::new(10, 10, 10, 10)
+        );
+    }
+}
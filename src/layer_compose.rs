@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use image::RgbaImage;
+
+use crate::{
+    XCapResult,
+    geometry::{Point, Rect},
+};
+
+// 没有窗口覆盖的像素
+const NO_WINDOW: u16 = u16::MAX;
+
+/// 参与合成的窗口，坐标都相对显示器左上角
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct LayerWindow {
+    // 窗口可见的区域
+    pub rect: Rect,
+    // 窗口截图左上角的位置，截图可能包含不可见的边框
+    pub image_origin: Point,
+    pub excluded: bool,
+}
+
+/// 每个像素最上层的窗口
+fn topmost_windows<'a, I>(width: u32, height: u32, windows: I) -> Vec<u16>
+where
+    I: DoubleEndedIterator<Item = (usize, &'a LayerWindow)>,
+{
+    let mut owners = vec![NO_WINDOW; (width * height) as usize];
+    let bounds = Rect::new(0, 0, width, height);
+
+    // 从下往上绘制，上层的窗口覆盖下层
+    for (index, window) in windows.rev() {
+        let Some(visible) = bounds.intersection(window.rect) else {
+            continue;
+        };
+
+        for y in visible.y..visible.y + visible.height as i32 {
+            let start = (y as u32 * width + visible.x as u32) as usize;
+            owners[start..start + visible.width as usize].fill(index as u16);
+        }
+    }
+
+    owners
+}
+
+/// 从截图中去掉被排除的窗口
+///
+/// windows 按从上到下的顺序排列。最上层窗口被排除的像素改用它下面第一个没有被排除的窗口的内容，
+/// 没有这样的窗口时使用 background（桌面背景）。没有窗口覆盖的像素属于桌面，exclude_desktop 时
+/// 同样使用 background。capture_window 和 background 只在需要时调用一次，窗口截图失败时使用背景
+pub(crate) fn compose_without<W, B>(
+    image: &mut RgbaImage,
+    windows: &[LayerWindow],
+    exclude_desktop: bool,
+    mut capture_window: W,
+    background: B,
+) -> XCapResult<()>
+where
+    W: FnMut(usize) -> XCapResult<RgbaImage>,
+    B: FnOnce() -> XCapResult<RgbaImage>,
+{
+    // 超出索引范围的窗口在最下层，几乎不会露出来
+    let windows = &windows[..windows.len().min(NO_WINDOW as usize)];
+    if !exclude_desktop && !windows.iter().any(|window| window.excluded) {
+        return Ok(());
+    }
+
+    let (width, height) = image.dimensions();
+    let owners = topmost_windows(width, height, windows.iter().enumerate());
+    let is_excluded = |owner: u16| match owner {
+        NO_WINDOW => exclude_desktop,
+        owner => windows[owner as usize].excluded,
+    };
+    if !owners.iter().any(|&owner| is_excluded(owner)) {
+        return Ok(());
+    }
+
+    let fallbacks = topmost_windows(
+        width,
+        height,
+        windows
+            .iter()
+            .enumerate()
+            .filter(|(_, window)| !window.excluded),
+    );
+
+    let mut window_images: HashMap<u16, Option<RgbaImage>> = HashMap::new();
+    let background = background()?;
+
+    for (index, owner) in owners.into_iter().enumerate() {
+        if !is_excluded(owner) {
+            continue;
+        }
+
+        let x = index as u32 % width;
+        let y = index as u32 / width;
+        let fallback = fallbacks[index];
+
+        let pixel = (fallback != NO_WINDOW)
+            .then(|| {
+                let window_image = window_images
+                    .entry(fallback)
+                    .or_insert_with(|| capture_window(fallback as usize).ok())
+                    .as_ref()?;
+                let origin = windows[fallback as usize].image_origin;
+                let window_x = (x as i32 - origin.x) as u32;
+                let window_y = (y as i32 - origin.y) as u32;
+
+                // 窗口截图的尺寸可能和窗口位置不一致
+                (window_x < window_image.width() && window_y < window_image.height())
+                    .then(|| *window_image.get_pixel(window_x, window_y))
+            })
+            .flatten()
+            .or_else(|| background.get_pixel_checked(x, y).copied());
+
+        if let Some(pixel) = pixel {
+            image.put_pixel(x, y, pixel);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    const SCREEN: Rgba<u8> = Rgba([1, 1, 1, 255]);
+    const WALLPAPER: Rgba<u8> = Rgba([2, 2, 2, 255]);
+    const WINDOW: Rgba<u8> = Rgba([3, 3, 3, 255]);
+
+    fn layer_window(rect: Rect, excluded: bool) -> LayerWindow {
+        LayerWindow {
+            rect,
+            image_origin: Point::new(rect.x, rect.y),
+            excluded,
+        }
+    }
+
+    fn compose(windows: &[LayerWindow], exclude_desktop: bool) -> RgbaImage {
+        let mut image = RgbaImage::from_pixel(4, 4, SCREEN);
+        compose_without(
+            &mut image,
+            windows,
+            exclude_desktop,
+            |index| {
+                let rect = windows[index].rect;
+                Ok(RgbaImage::from_pixel(rect.width, rect.height, WINDOW))
+            },
+            || Ok(RgbaImage::from_pixel(4, 4, WALLPAPER)),
+        )
+        .unwrap();
+
+        image
+    }
+
+    #[test]
+    fn test_compose_without_dock() {
+        let dock = layer_window(Rect::new(0, 3, 4, 1), true);
+        let window = layer_window(Rect::new(2, 2, 4, 4), false);
+
+        let image = compose(&[dock, window], false);
+        // 任务栏下面有窗口的部分使用窗口内容，其余使用桌面背景
+        assert_eq!(*image.get_pixel(0, 3), WALLPAPER);
+        assert_eq!(*image.get_pixel(3, 3), WINDOW);
+        // 其他像素保持截图内容
+        assert_eq!(*image.get_pixel(0, 0), SCREEN);
+        assert_eq!(*image.get_pixel(3, 2), SCREEN);
+    }
+
+    #[test]
+    fn test_compose_without_desktop() {
+        let window = layer_window(Rect::new(0, 0, 2, 2), false);
+
+        let image = compose(&[window], true);
+        assert_eq!(*image.get_pixel(0, 0), SCREEN);
+        assert_eq!(*image.get_pixel(3, 3), WALLPAPER);
+
+        // 没有排除时不修改截图
+        let image = compose(&[window], false);
+        assert!(image.pixels().all(|&pixel| pixel == SCREEN));
+    }
+}
@@ -1,7 +1,68 @@
+mod activity_timeline;
+mod alpha;
+mod annotation;
+mod capture_config;
+mod capture_report;
+mod capture_session;
+mod capture_target;
+mod capturer;
+mod compositor;
+mod coordinate_space;
+mod cursor_follow;
+#[cfg(feature = "compression")]
+mod compression;
+mod config;
+#[cfg(feature = "ddc")]
+mod ddc;
+mod delayed_capture;
+mod diff;
+mod encode;
 mod error;
+mod geometry;
+#[cfg(any(target_os = "linux", target_os = "windows", test))]
+mod layer_compose;
+mod magnifier;
+mod metrics;
 mod monitor;
+mod monitor_identity;
+mod monitor_layout;
+mod monitor_watcher;
+mod network;
+#[cfg(any(target_os = "linux", target_os = "macos", test))]
+mod normal_bounds;
+mod recorder_config;
+mod redaction;
+mod region_watcher;
+mod replay_buffer;
+#[cfg(feature = "remote-view")]
+mod remote_view;
+mod scroll_capture;
+#[cfg(feature = "selector")]
+mod selector;
+mod sidecar;
+mod snapshot;
+mod thumbnail_stream;
+mod title_watcher;
 mod video_recorder;
+#[cfg(feature = "virtual-camera")]
+mod virtual_camera;
+#[cfg(feature = "virtual-display")]
+mod virtual_display;
+#[cfg(feature = "webm")]
+mod webm;
+#[cfg(feature = "webm")]
+mod webm_segments;
 mod window;
+mod window_crop;
+mod window_watcher;
+
+pub mod clock;
+pub mod color;
+pub mod conversion;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+pub mod testing;
 
 #[cfg(target_os = "macos")]
 #[path = "macos/mod.rs"]
@@ -21,9 +82,93 @@ mod platform;
 
 pub use image;
 
-pub use error::{XCapError, XCapResult};
-pub use monitor::Monitor;
-pub use window::Window;
+pub use activity_timeline::{ActivitySpan, ActivityTimeline};
+pub use alpha::AlphaMode;
+pub use annotation::Annotation;
+pub use capture_config::CaptureConfig;
+pub use capture_report::CaptureReport;
+pub use capture_target::{
+    CaptureTarget, SavedMonitor, SavedTarget, SavedWindow, capture, record,
+};
+pub use capturer::Capturer;
+pub use compositor::{Compositor, CompositorBuilder, Inset, OverlaySource};
+pub use coordinate_space::CoordinateSpace;
+#[cfg(target_os = "macos")]
+pub use platform::capture_config_ext::CaptureConfigExt;
+#[cfg(target_os = "macos")]
+pub use platform::frame_ext::FrameExt;
+#[cfg(all(target_os = "linux", feature = "egl"))]
+pub use platform::frame_ext::{DmaBuf, FrameExt};
+#[cfg(target_os = "windows")]
+pub use platform::window_preview::{WindowExt, WindowPreview};
+pub use config::{Backend, BackendInfo, Config, ConfigBuilder};
+#[cfg(feature = "ddc")]
+pub use ddc::{DdcCapabilities, VcpValue};
+pub use diff::diff;
+#[cfg(feature = "compression")]
+pub use compression::Codec;
+pub use error::{PlatformError, XCapError, XCapResult};
+pub use platform::external_encoder_surface::ExternalEncoderSurface;
+pub use geometry::{Direction, Point, Rect, RelativePosition};
+pub use magnifier::Magnifier;
+pub use metrics::{MetricsSink, Stage};
+pub use network::{NetworkFrameSender, TcpFrameReceiver, TcpFrameSender};
+pub use monitor::{
+    Monitor, MonitorPlacement, PixelEncoding, PowerState, RefreshRateRange, RegionMode,
+    UniqueKeyMatch,
+};
+pub use monitor_identity::{ManufactureDate, MonitorIdentity};
+pub use monitor_layout::{LayoutChange, LayoutMonitor, MonitorLayout};
+pub use monitor_watcher::MonitorWatcher;
+pub use recorder_config::{
+    FramePacing, PowerProfile, RecorderConfig, RecorderUpdate, RecoveryPolicy,
+};
+pub use redaction::{RedactionId, RedactionStyle, Redactor};
+pub use region_watcher::RegionWatcher;
+pub use sidecar::{FrameSidecar, SidecarFormat};
+pub use snapshot::{MonitorInfo, Snapshot, WindowInfo, snapshot};
+pub use thumbnail_stream::{Thumbnail, ThumbnailSource, ThumbnailStream, ThumbnailStreamBuilder};
+pub use title_watcher::{ActiveTitle, TitleWatcher};
+pub use window::{ActiveInfoMode, Window, WindowLayer};
+pub use window_watcher::WindowWatcher;
 
 pub use video_recorder::Frame;
+pub use video_recorder::FrameView;
+pub use video_recorder::PixelFormat;
+pub use video_recorder::Plane;
+pub use video_recorder::RecorderEvent;
 pub use video_recorder::VideoRecorder;
+pub use replay_buffer::ReplayBuffer;
+#[cfg(feature = "remote-view")]
+pub use remote_view::{RemoteViewClient, RemoteViewServer};
+#[cfg(feature = "selector")]
+pub use selector::Selector;
+#[cfg(feature = "virtual-camera")]
+pub use virtual_camera::{CameraSink, VirtualCamera};
+#[cfg(feature = "virtual-display")]
+pub use virtual_display::VirtualDisplay;
+#[cfg(feature = "webm")]
+pub use webm::{WebmEncoder, WebmEncoderBuilder};
+#[cfg(feature = "webm")]
+pub use webm_segments::{SegmentedWebmEncoder, SegmentedWebmEncoderBuilder};
+
+/// Release the resources the crate keeps between calls, so it can be used from plugins and
+/// dynamic libraries that get unloaded. On macOS this removes the app activation observer, stops
+/// the cached ScreenCaptureKit streams and the internal thread that owns them, and clears the
+/// window cache. On Linux this closes the screencast portal session used for Wayland
+/// screenshots. Later calls recreate whatever they need. Video recorders must be stopped
+/// separately.
+pub fn shutdown() -> XCapResult<()> {
+    platform::shutdown()
+}
+
+/// The capture backend picked for the current session and [`Config`], e.g. whether Linux
+/// captures go through X11 or the Wayland backends.
+///
+/// ```no_run
+/// let info = xcap::backend_info();
+/// println!("backend: {:?}, xwayland: {}", info.backend(), info.xwayland());
+/// ```
+pub fn backend_info() -> BackendInfo {
+    platform::backend_info()
+}
@@ -1,19 +1,28 @@
-use image::RgbaImage;
+use image::{Rgba, RgbaImage};
+use xcb::{
+    XidNew,
+    x::{ATOM_PIXMAP, Drawable, Pixmap},
+};
 
-use crate::error::XCapResult;
+use crate::{
+    Backend, capture_report,
+    error::{XCapError, XCapResult},
+    geometry::{Rect, map_rect},
+};
 
 use super::{
     impl_monitor::ImplMonitor,
-    impl_window::ImplWindow,
-    utils::{get_current_screen_buf, get_monitor_info_buf, wayland_detect},
+    impl_window::{ImplWindow, get_window_property},
+    utils::{get_atom, get_current_screen_buf, get_monitor_info_buf, wayland_detect},
     wayland_capture::wayland_capture,
-    xorg_capture::xorg_capture,
+    xorg_capture::{xorg_capture, xorg_capture_drawable},
 };
 
 pub fn capture_monitor(impl_monitor: &ImplMonitor) -> XCapResult<RgbaImage> {
     let monitor_info_buf = get_monitor_info_buf(impl_monitor.output)?;
 
     if wayland_detect() {
+        capture_report::record_backend(Backend::Wayland);
         wayland_capture(
             monitor_info_buf.x() as i32,
             monitor_info_buf.y() as i32,
@@ -21,6 +30,7 @@ pub fn capture_monitor(impl_monitor: &ImplMonitor) -> XCapResult<RgbaImage> {
             monitor_info_buf.height() as i32,
         )
     } else {
+        capture_report::record_backend(Backend::Xorg);
         let screen_buf = get_current_screen_buf()?;
 
         xorg_capture(
@@ -41,18 +51,47 @@ pub fn capture_region(
     height: u32,
 ) -> XCapResult<RgbaImage> {
     let monitor_info_buf = get_monitor_info_buf(impl_monitor.output)?;
+    let scale_factor = impl_monitor.scale_factor()? as f64;
+
+    // 区域是显示器内的逻辑坐标，GetMonitors 返回的是帧缓冲中的像素坐标。
+    // RandR 的缩放、平移（panning）和翻转在扫描输出时才生效，
+    // 帧缓冲中 CRTC 的位置和大小已经是变换后的结果，这里只需要换算缩放比例
+    let bounds = Rect::new(
+        monitor_info_buf.x() as i32,
+        monitor_info_buf.y() as i32,
+        monitor_info_buf.width() as u32,
+        monitor_info_buf.height() as u32,
+    );
+    // 两种后端都使用全局坐标，主显示器左侧或上方的显示器原点为负数
+    let region = map_rect(
+        Rect::new(x as i32, y as i32, width, height),
+        (scale_factor, scale_factor),
+        (bounds.x as f64, bounds.y as f64),
+    );
+    let region = bounds
+        .intersection(region)
+        .ok_or(XCapError::InvalidCaptureRegion(format!(
+            "Region ({x}, {y}, {width}, {height}) is outside monitor bounds"
+        )))?;
 
     if wayland_detect() {
-        wayland_capture(x as i32, y as i32, width as i32, height as i32)
+        capture_report::record_backend(Backend::Wayland);
+        wayland_capture(
+            region.x,
+            region.y,
+            region.width as i32,
+            region.height as i32,
+        )
     } else {
+        capture_report::record_backend(Backend::Xorg);
         let screen_buf = get_current_screen_buf()?;
 
         xorg_capture(
             screen_buf.root(),
-            monitor_info_buf.x() as i32 + x as i32,
-            monitor_info_buf.y() as i32 + y as i32,
-            width,
-            height,
+            region.x,
+            region.y,
+            region.width,
+            region.height,
         )
     }
 }
@@ -61,5 +100,34 @@ pub fn capture_window(impl_window: &ImplWindow) -> XCapResult<RgbaImage> {
     let width = impl_window.width()?;
     let height = impl_window.height()?;
 
+    capture_report::record_backend(Backend::Xorg);
     xorg_capture(impl_window.window, 0, 0, width, height)
 }
+
+pub fn capture_window_region(
+    impl_window: &ImplWindow,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> XCapResult<RgbaImage> {
+    // GetImage 直接读取窗口中的区域，不需要截取整个窗口
+    capture_report::record_backend(Backend::Xorg);
+    xorg_capture(impl_window.window, x as i32, y as i32, width, height)
+}
+
+/// 截取根窗口的壁纸，壁纸程序按照约定把壁纸 pixmap 保存在根窗口的 _XROOTPMAP_ID 属性中
+///
+/// 没有设置这个属性或者 pixmap 已经失效时，根窗口的背景是黑色
+pub fn capture_wallpaper(x: i32, y: i32, width: u32, height: u32) -> XCapResult<RgbaImage> {
+    let screen_buf = get_current_screen_buf()?;
+    let root_pixmap_atom = get_atom("_XROOTPMAP_ID")?;
+    let reply = get_window_property(screen_buf.root(), root_pixmap_atom, ATOM_PIXMAP, 0, 1)?;
+
+    let wallpaper = reply.value::<u32>().first().and_then(|&pixmap| {
+        let pixmap = unsafe { Pixmap::new(pixmap) };
+        xorg_capture_drawable(Drawable::Pixmap(pixmap), x, y, width, height).ok()
+    });
+
+    Ok(wallpaper.unwrap_or_else(|| RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]))))
+}
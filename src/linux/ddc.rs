@@ -0,0 +1,161 @@
+use std::{
+    ffi::{c_int, c_ulong},
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    os::fd::AsRawFd,
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
+
+use crate::error::{PlatformError, XCapError, XCapResult};
+
+const DRM_DIR: &str = "/sys/class/drm";
+// linux/i2c-dev.h
+const I2C_SLAVE: c_ulong = 0x0703;
+// DDC/CI 的 7 位 I2C 地址
+const DDC_CI_ADDRESS: c_int = 0x37;
+// 主机和显示器的 8 位地址，参与校验和计算
+const HOST_ADDRESS: u8 = 0x51;
+const DISPLAY_ADDRESS: u8 = 0x6E;
+const REPLY_CHECKSUM_SEED: u8 = 0x50;
+const VCP_REQUEST: u8 = 0x01;
+const VCP_REPLY: u8 = 0x02;
+const CAPABILITIES_REQUEST: u8 = 0xF3;
+const CAPABILITIES_REPLY: u8 = 0xE3;
+// 每个能力字符串片段最多 32 字节，加上长度、操作码、偏移和校验和
+const CAPABILITIES_REPLY_LEN: usize = 38;
+const VCP_REPLY_LEN: usize = 11;
+// 能力字符串一般只有几百字节，防止异常的显示器一直返回数据
+const MAX_CAPABILITIES_LEN: usize = 4096;
+// MCCS 规定发送请求后等待显示器准备回复的时间
+const REPLY_DELAY: Duration = Duration::from_millis(50);
+
+unsafe extern "C" {
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+}
+
+/// drm 连接器中 EDID 和 RandR 输出相同的那个的 DDC 总线
+fn find_i2c_device(edid: &[u8]) -> XCapResult<PathBuf> {
+    let edid = edid.get(..128).ok_or(XCapError::NotSupported)?;
+
+    let entries = fs::read_dir(DRM_DIR).map_err(|_| XCapError::NotSupported)?;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let is_same_edid = fs::read(path.join("edid"))
+            .is_ok_and(|connector_edid| connector_edid.get(..128) == Some(edid));
+        if !is_same_edid {
+            continue;
+        }
+
+        // ddc 是指向 i2c-N 设备的符号链接
+        let ddc = fs::read_link(path.join("ddc"))?;
+        let name = ddc.file_name().ok_or(XCapError::new("Invalid DDC link"))?;
+
+        return Ok(PathBuf::from("/dev").join(name));
+    }
+
+    Err(XCapError::NotSupported)
+}
+
+/// 通过 i2c-dev 和显示器进行 DDC/CI 通信，需要 /dev/i2c-* 的读写权限
+#[derive(Debug)]
+pub(crate) struct DdcDevice {
+    file: File,
+}
+
+impl DdcDevice {
+    pub fn open(edid: &[u8]) -> XCapResult<DdcDevice> {
+        let path = find_i2c_device(edid)?;
+        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+
+        if unsafe { ioctl(file.as_raw_fd(), I2C_SLAVE, DDC_CI_ADDRESS) } < 0 {
+            let err = io::Error::last_os_error();
+            return Err(XCapError::platform(
+                format!("Set DDC/CI address on {path:?} failed"),
+                PlatformError::new("errno", err.raw_os_error().unwrap_or(0) as i64, err),
+            ));
+        }
+
+        Ok(DdcDevice { file })
+    }
+
+    /// 发送一条消息：源地址、长度、内容和校验和，校验和从目标地址开始异或
+    fn write(&mut self, payload: &[u8]) -> XCapResult<()> {
+        let mut message = vec![HOST_ADDRESS, 0x80 | payload.len() as u8];
+        message.extend_from_slice(payload);
+        let checksum = message
+            .iter()
+            .fold(DISPLAY_ADDRESS, |checksum, byte| checksum ^ byte);
+        message.push(checksum);
+
+        self.file.write_all(&message)?;
+        thread::sleep(REPLY_DELAY);
+
+        Ok(())
+    }
+
+    /// 读取回复并校验，返回回复的内容
+    fn read(&mut self, len: usize) -> XCapResult<Vec<u8>> {
+        let mut reply = vec![0; len];
+        self.file.read_exact(&mut reply)?;
+
+        let payload_len = (reply[1] & 0x7F) as usize;
+        if reply[0] != DISPLAY_ADDRESS || payload_len + 3 > len {
+            return Err(XCapError::new("Invalid DDC/CI reply"));
+        }
+
+        let checksum = reply[..payload_len + 2]
+            .iter()
+            .fold(REPLY_CHECKSUM_SEED, |checksum, byte| checksum ^ byte);
+        if checksum != reply[payload_len + 2] {
+            return Err(XCapError::new("DDC/CI reply checksum mismatch"));
+        }
+
+        Ok(reply[2..payload_len + 2].to_vec())
+    }
+
+    /// 当前值和最大值
+    pub fn vcp_feature(&mut self, code: u8) -> XCapResult<(u16, u16)> {
+        self.write(&[VCP_REQUEST, code])?;
+        let payload = self.read(VCP_REPLY_LEN)?;
+
+        // 操作码、结果、VCP 代码、类型、最大值和当前值
+        if payload.len() < 8 || payload[0] != VCP_REPLY || payload[2] != code {
+            return Err(XCapError::new("Invalid VCP reply"));
+        }
+        if payload[1] != 0 {
+            return Err(XCapError::new(format!("Unsupported VCP code {code:#04x}")));
+        }
+
+        Ok((
+            u16::from_be_bytes([payload[6], payload[7]]),
+            u16::from_be_bytes([payload[4], payload[5]]),
+        ))
+    }
+
+    /// 按偏移分段读取能力字符串，显示器返回空片段时结束
+    pub fn capabilities(&mut self) -> XCapResult<String> {
+        let mut raw = Vec::new();
+
+        while raw.len() < MAX_CAPABILITIES_LEN {
+            let offset = (raw.len() as u16).to_be_bytes();
+            self.write(&[CAPABILITIES_REQUEST, offset[0], offset[1]])?;
+            let payload = self.read(CAPABILITIES_REPLY_LEN)?;
+
+            if payload.len() < 3 || payload[0] != CAPABILITIES_REPLY || payload[1..3] != offset {
+                return Err(XCapError::new("Invalid capabilities reply"));
+            }
+
+            let fragment = &payload[3..];
+            if fragment.is_empty() {
+                break;
+            }
+            raw.extend_from_slice(fragment);
+        }
+
+        Ok(String::from_utf8_lossy(&raw)
+            .trim_end_matches('\0')
+            .to_string())
+    }
+}
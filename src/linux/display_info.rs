@@ -1,6 +1,6 @@
 //! Linux 显示器信息获取工具
 //!
-//! 本模块提供了 Linux 平台的显示器 UUID 和序列号获取功能。
+//! 本模块提供了 Linux 平台的显示器 UUID、序列号和厂商型号信息获取功能。
 //! 使用 XCB RandR 扩展获取 EDID 信息。
 //!
 //! # 兼容性
@@ -16,55 +16,13 @@ use xcb::{
     Xid,
 };
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    error::{XCapError, XCapResult},
+    monitor_identity::{parse_edid, MonitorIdentity},
+};
 
 use super::utils::{get_atom, get_xcb_connection_and_index};
 
-/// EDID 数据结构
-#[derive(Debug)]
-struct EdidInfo {
-    manufacturer_id: String,
-    product_code: u16,
-    serial_number: u32,
-}
-
-/// 解析 EDID 数据
-/// EDID 格式参考: https://en.wikipedia.org/wiki/Extended_Display_Identification_Data
-fn parse_edid(edid_data: &[u8]) -> XCapResult<EdidInfo> {
-    if edid_data.len() < 128 {
-        return Err(XCapError::new("EDID data too short"));
-    }
-
-    // 验证 EDID 头部 (00 FF FF FF FF FF FF 00)
-    if &edid_data[0..8] != &[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00] {
-        return Err(XCapError::new("Invalid EDID header"));
-    }
-
-    // 提取制造商 ID (字节 8-9)
-    let manufacturer_bytes = u16::from_be_bytes([edid_data[8], edid_data[9]]);
-    let char1 = (((manufacturer_bytes >> 10) & 0x1F) + 64) as u8 as char;
-    let char2 = (((manufacturer_bytes >> 5) & 0x1F) + 64) as u8 as char;
-    let char3 = ((manufacturer_bytes & 0x1F) + 64) as u8 as char;
-    let manufacturer_id = format!("{}{}{}", char1, char2, char3);
-
-    // 提取产品代码 (字节 10-11, 小端序)
-    let product_code = u16::from_le_bytes([edid_data[10], edid_data[11]]);
-
-    // 提取序列号 (字节 12-15, 小端序)
-    let serial_number = u32::from_le_bytes([
-        edid_data[12],
-        edid_data[13],
-        edid_data[14],
-        edid_data[15],
-    ]);
-
-    Ok(EdidInfo {
-        manufacturer_id,
-        product_code,
-        serial_number,
-    })
-}
-
 /// 获取显示器的 EDID 数据
 fn get_edid_data(output: Output) -> XCapResult<Vec<u8>> {
     let (conn, _) = get_xcb_connection_and_index()?;
@@ -102,7 +60,9 @@ pub fn get_display_uuid(output: Output) -> XCapResult<String> {
                 // 使用制造商ID、产品代码和序列号生成 UUID 格式的字符串
                 let uuid = format!(
                     "{}-{:04X}-{:08X}",
-                    edid_info.manufacturer_id, edid_info.product_code, edid_info.serial_number
+                    edid_info.identity.vendor,
+                    edid_info.identity.product,
+                    edid_info.serial_number
                 );
                 return Ok(uuid);
             }
@@ -144,42 +104,15 @@ pub fn get_display_serial_number(output: Output) -> XCapResult<String> {
                     return Ok(edid_info.serial_number.to_string());
                 }
 
-                // 有些显示器序列号为 0，尝试提取描述符中的序列号字符串
-                // EDID 字节 54-125 包含 4 个 18 字节的描述符块
-                for i in 0..4 {
-                    let offset = 54 + i * 18;
-                    if offset + 18 <= edid_data.len() {
-                        let descriptor = &edid_data[offset..offset + 18];
-                        // 检查是否为序列号描述符 (tag = 0xFF)
-                        if descriptor[0] == 0x00
-                            && descriptor[1] == 0x00
-                            && descriptor[2] == 0x00
-                            && descriptor[3] == 0xFF
-                        {
-                            // 提取序列号字符串 (字节 5-17)
-                            let serial_bytes = &descriptor[5..18];
-                            let serial_str: String = serial_bytes
-                                .iter()
-                                .filter(|&&b| b >= 0x20 && b <= 0x7E) // 可打印 ASCII
-                                .map(|&b| b as char)
-                                .collect();
-
-                            if !serial_str.is_empty() {
-                                return Ok(serial_str.trim().to_string());
-                            }
-                        }
-                    }
-                }
-
-                // 如果找不到字符串序列号，返回数字序列号
-                if edid_info.serial_number != 0 {
-                    return Ok(edid_info.serial_number.to_string());
+                // 有些显示器序列号为 0，使用描述符中的序列号字符串
+                if let Some(serial) = edid_info.identity.serial {
+                    return Ok(serial);
                 }
 
                 // 返回制造商和产品代码组合作为标识
                 return Ok(format!(
                     "{}-{:04X}",
-                    edid_info.manufacturer_id, edid_info.product_code
+                    edid_info.identity.vendor, edid_info.identity.product
                 ));
             }
         }
@@ -192,3 +125,10 @@ pub fn get_display_serial_number(output: Output) -> XCapResult<String> {
     ))
 }
 
+
+/// 获取显示器的厂商、型号、序列号等信息
+pub fn get_display_identity(output: Output) -> XCapResult<MonitorIdentity> {
+    let edid_data = get_edid_data(output)?;
+
+    Ok(parse_edid(&edid_data)?.identity)
+}
@@ -0,0 +1,15 @@
+use crate::Frame;
+
+/// The GPU surface a recorder frame was captured into, see [`Frame::encoder_surface`].
+///
+/// Linux recorders deliver frames in system memory, so frames never carry a surface. With the
+/// `egl` feature, Wayland recorders expose the DMA-BUF frames came in through
+/// [`FrameExt`](crate::FrameExt) instead.
+#[derive(Debug, Clone)]
+pub struct ExternalEncoderSurface {
+    _private: (),
+}
+
+pub(crate) fn encoder_surface(_frame: &Frame) -> Option<ExternalEncoderSurface> {
+    None
+}
@@ -0,0 +1,210 @@
+use std::{
+    os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd},
+    sync::Arc,
+};
+
+use khronos_egl as egl;
+use pipewire::spa::{
+    buffer::{Data, DataType},
+    param::video::{VideoFormat, VideoInfoRaw},
+};
+
+use crate::{
+    Frame,
+    error::{PlatformError, XCapError, XCapResult},
+};
+
+pub(crate) const DRM_FORMAT_MOD_LINEAR: u64 = 0;
+
+// EGL_EXT_image_dma_buf_import 和 EGL_EXT_image_dma_buf_import_modifiers 中的常量
+const EGL_LINUX_DMA_BUF_EXT: egl::Enum = 0x3270;
+const EGL_LINUX_DRM_FOURCC_EXT: egl::Attrib = 0x3271;
+const EGL_DMA_BUF_PLANE0_FD_EXT: egl::Attrib = 0x3272;
+const EGL_DMA_BUF_PLANE0_OFFSET_EXT: egl::Attrib = 0x3273;
+const EGL_DMA_BUF_PLANE0_PITCH_EXT: egl::Attrib = 0x3274;
+const EGL_DMA_BUF_PLANE0_MODIFIER_LO_EXT: egl::Attrib = 0x3443;
+const EGL_DMA_BUF_PLANE0_MODIFIER_HI_EXT: egl::Attrib = 0x3444;
+
+const fn fourcc(code: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*code)
+}
+
+/// PipeWire 的格式按字节顺序命名，DRM 的格式按小端序的 32 位值命名
+fn drm_fourcc(format: VideoFormat) -> Option<u32> {
+    match format {
+        VideoFormat::RGBA => Some(fourcc(b"AB24")),
+        VideoFormat::RGBx => Some(fourcc(b"XB24")),
+        VideoFormat::BGRx => Some(fourcc(b"XR24")),
+        VideoFormat::RGB => Some(fourcc(b"BG24")),
+        _ => None,
+    }
+}
+
+/// A single plane DMA-BUF a recorder [`Frame`] was captured into, see [`FrameExt::dma_buf`].
+#[derive(Debug, Clone)]
+pub struct DmaBuf {
+    fd: Arc<OwnedFd>,
+    width: u32,
+    height: u32,
+    fourcc: u32,
+    modifier: u64,
+    offset: u32,
+    stride: u32,
+}
+
+impl DmaBuf {
+    /// PipeWire 在回调返回后回收缓冲区，复制文件描述符让 DMA-BUF 跟随帧存活
+    pub(crate) fn new(data: &Data, format: VideoInfoRaw) -> Option<DmaBuf> {
+        if data.type_() != DataType::DmaBuf {
+            return None;
+        }
+
+        let size = format.size();
+        let chunk = data.chunk();
+        let stride = match chunk.stride() {
+            stride if stride > 0 => stride as u32,
+            _ => size.width * 4,
+        };
+        let fd = unsafe { BorrowedFd::borrow_raw(data.fd()) }
+            .try_clone_to_owned()
+            .map_err(|err| log::debug!("dup DMA-BUF fd failed: {err:?}"))
+            .ok()?;
+
+        Some(DmaBuf {
+            fd: Arc::new(fd),
+            width: size.width,
+            height: size.height,
+            fourcc: drm_fourcc(format.format())?,
+            modifier: format.modifier(),
+            offset: chunk.offset(),
+            stride,
+        })
+    }
+
+    /// The DMA-BUF file descriptor, valid while this value is alive.
+    pub fn fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The DRM fourcc code of the pixel format, e.g. `XR24` for `DRM_FORMAT_XRGB8888`.
+    pub fn fourcc(&self) -> u32 {
+        self.fourcc
+    }
+
+    /// The DRM format modifier, `DRM_FORMAT_MOD_LINEAR` unless the compositor chose another.
+    pub fn modifier(&self) -> u64 {
+        self.modifier
+    }
+
+    /// The byte offset of the first pixel.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// The number of bytes per row.
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// Import the buffer into an `EGLImage` on `display`, without copying the pixels. The
+    /// display must support `EGL_EXT_image_dma_buf_import` and
+    /// `EGL_EXT_image_dma_buf_import_modifiers`.
+    ///
+    /// Bind the image to a GL texture with `glEGLImageTargetTexture2DOES`, and destroy it with
+    /// [`khronos_egl::Instance::destroy_image`] when done. The image keeps the buffer alive on its own.
+    pub fn egl_image<T: egl::api::EGL1_5>(
+        &self,
+        instance: &egl::Instance<T>,
+        display: egl::Display,
+    ) -> XCapResult<egl::Image> {
+        let attributes = [
+            egl::WIDTH as egl::Attrib,
+            self.width as egl::Attrib,
+            egl::HEIGHT as egl::Attrib,
+            self.height as egl::Attrib,
+            EGL_LINUX_DRM_FOURCC_EXT,
+            self.fourcc as egl::Attrib,
+            EGL_DMA_BUF_PLANE0_FD_EXT,
+            self.fd.as_raw_fd() as egl::Attrib,
+            EGL_DMA_BUF_PLANE0_OFFSET_EXT,
+            self.offset as egl::Attrib,
+            EGL_DMA_BUF_PLANE0_PITCH_EXT,
+            self.stride as egl::Attrib,
+            EGL_DMA_BUF_PLANE0_MODIFIER_LO_EXT,
+            (self.modifier & 0xffff_ffff) as egl::Attrib,
+            EGL_DMA_BUF_PLANE0_MODIFIER_HI_EXT,
+            (self.modifier >> 32) as egl::Attrib,
+            egl::ATTRIB_NONE,
+        ];
+
+        // EGL_LINUX_DMA_BUF_EXT 要求 context 为 EGL_NO_CONTEXT，buffer 为 NULL
+        instance
+            .create_image(
+                display,
+                unsafe { egl::Context::from_ptr(egl::NO_CONTEXT) },
+                EGL_LINUX_DMA_BUF_EXT,
+                unsafe { egl::ClientBuffer::from_ptr(std::ptr::null_mut()) },
+                &attributes,
+            )
+            .map_err(|err| {
+                XCapError::platform(
+                    "eglCreateImage failed",
+                    PlatformError::new("EGL", err.native() as i64, err),
+                )
+            })
+    }
+}
+
+/// Linux-only access to the DMA-BUF a recorder [`Frame`] was captured into, so apps rendering
+/// with GL can import it as a texture instead of uploading [`Frame::data`].
+///
+/// Only Wayland recorders with the `egl` feature ask the compositor for DMA-BUF frames, and
+/// fall back to shared memory when it doesn't support them. The buffer holds the frame as
+/// delivered by the compositor, before any
+/// [`RecorderConfig::frame_hook`](crate::RecorderConfig::frame_hook) edits, and is reused by
+/// it once later frames arrive, so import and draw it promptly. Frames that were not produced
+/// by a recorder, that were scaled, redacted, or cropped with
+/// [`RecorderConfig::follow_window`](crate::RecorderConfig::follow_window), have no buffer.
+///
+/// ```no_run
+/// use xcap::{FrameExt, Monitor};
+///
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let (recorder, rx) = monitor.video_recorder().unwrap();
+/// recorder.start().unwrap();
+///
+/// let frame = rx.recv().unwrap();
+/// if let Some(dma_buf) = frame.dma_buf() {
+///     println!("DMA-BUF {}x{} fourcc {:#x}", dma_buf.width(), dma_buf.height(), dma_buf.fourcc());
+/// }
+/// ```
+pub trait FrameExt {
+    /// The DMA-BUF the frame was captured into.
+    fn dma_buf(&self) -> Option<&DmaBuf>;
+}
+
+impl FrameExt for Frame {
+    fn dma_buf(&self) -> Option<&DmaBuf> {
+        self.dma_buf.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drm_fourcc() {
+        // DRM_FORMAT_XRGB8888
+        assert_eq!(drm_fourcc(VideoFormat::BGRx), Some(0x3432_5258));
+        assert_eq!(drm_fourcc(VideoFormat::I420), None);
+    }
+}
@@ -1,27 +1,41 @@
-use std::{ffi::CStr, sync::mpsc::Receiver};
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, mpsc::Receiver},
+};
 
-use image::RgbaImage;
+use image::{RgbaImage, imageops};
 use xcb::{
     Xid,
+    dpms::{self, DpmsMode},
     randr::{
         GetCrtcInfo, GetMonitors, GetOutputInfo, GetOutputProperty, GetScreenResources, Mode,
         ModeFlag, ModeInfo, Output, Rotation,
     },
-    x::{ATOM_ANY, ATOM_RESOURCE_MANAGER, ATOM_STRING, CURRENT_TIME, GetProperty},
+    x::{
+        ATOM_ANY, ATOM_RESOURCE_MANAGER, ATOM_STRING, Atom, CURRENT_TIME, GetAtomName, GetProperty,
+    },
 };
 
 use crate::{
+    CaptureConfig, MonitorIdentity, PixelEncoding, PowerState, RecorderConfig, RefreshRateRange,
+    delayed_capture::wait_refresh_periods,
     error::{XCapError, XCapResult},
-    video_recorder::Frame,
+    geometry::{Point, Rect},
+    layer_compose::{LayerWindow, compose_without},
+    monitor_identity::edid_name,
+    video_recorder::{Frame, RecorderHealth},
 };
 
 use super::{
-    capture::{capture_monitor, capture_region},
+    capture::{capture_monitor, capture_region, capture_wallpaper},
     impl_video_recorder::ImplVideoRecorder,
+    impl_window::ImplWindow,
     utils::{
         get_atom, get_current_screen_buf, get_monitor_info_buf, get_xcb_connection_and_index,
         wayland_detect,
     },
+    wayland_capture::is_wayland_capture_available,
 };
 
 #[derive(Debug, Clone)]
@@ -54,7 +68,7 @@ fn get_current_frequency(mode_infos: Vec<ModeInfo>, mode: Mode) -> f32 {
     }
 }
 
-fn get_scale_factor() -> XCapResult<f32> {
+pub(super) fn get_scale_factor() -> XCapResult<f32> {
     if wayland_detect() {
         // for wayland we can get all the outputs, and get the maximum scaling of them.
         let wayshot_conn = libwayshot_xcap::WayshotConnection::new()?;
@@ -134,6 +148,31 @@ fn get_rotation_frequency(mode_infos: Vec<ModeInfo>, output: &Output) -> XCapRes
     Ok((rotation, frequency))
 }
 
+// 输出所在 CRTC 的区域，复制模式下的输出共用 CRTC 或者使用位置、大小相同的 CRTC
+fn get_crtc_rect(output: Output) -> XCapResult<Rect> {
+    let (conn, _) = get_xcb_connection_and_index()?;
+    let get_output_info_cookie = conn.send_request(&GetOutputInfo {
+        output,
+        config_timestamp: CURRENT_TIME,
+    });
+
+    let get_output_info_reply = conn.wait_for_reply(get_output_info_cookie)?;
+
+    let get_crtc_info_cookie = conn.send_request(&GetCrtcInfo {
+        crtc: get_output_info_reply.crtc(),
+        config_timestamp: CURRENT_TIME,
+    });
+
+    let get_crtc_info_reply = conn.wait_for_reply(get_crtc_info_cookie)?;
+
+    Ok(Rect::new(
+        get_crtc_info_reply.x() as i32,
+        get_crtc_info_reply.y() as i32,
+        get_crtc_info_reply.width() as u32,
+        get_crtc_info_reply.height() as u32,
+    ))
+}
+
 fn get_mode_infos() -> XCapResult<Vec<ModeInfo>> {
     let (conn, _) = get_xcb_connection_and_index()?;
 
@@ -170,28 +209,129 @@ fn get_output_edid(output: Output) -> XCapResult<Vec<u8>> {
     Ok(edid)
 }
 
+/// 获取输出的 Colorspace 属性，例如 "Default"、"BT2020_RGB"、"BT2020_YCC"，
+/// 驱动不支持时返回 None
+fn get_output_colorspace(output: Output) -> XCapResult<Option<String>> {
+    let (conn, _) = get_xcb_connection_and_index()?;
+    let Ok(atom) = get_atom("Colorspace") else {
+        return Ok(None);
+    };
+
+    let get_output_property_cookie = conn.send_request(&GetOutputProperty {
+        output,
+        property: atom,
+        r#type: ATOM_ANY,
+        long_offset: 0,
+        long_length: 1,
+        delete: false,
+        pending: false,
+    });
+    let get_output_property_reply = conn.wait_for_reply(get_output_property_cookie)?;
+
+    let Some(&value) = get_output_property_reply.data::<Atom>().first() else {
+        return Ok(None);
+    };
+
+    let get_atom_name_cookie = conn.send_request(&GetAtomName { atom: value });
+    let get_atom_name_reply = conn.wait_for_reply(get_atom_name_cookie)?;
+
+    Ok(Some(get_atom_name_reply.name().to_utf8().into_owned()))
+}
+
+/// 型号名称中包含 Internal 的是内置屏幕
 fn is_builtin_edid(edid: &[u8]) -> bool {
+    edid_name(edid).is_some_and(|name| name.contains("Internal"))
+}
+
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+/// 读取一个背光设备的亮度，actual_brightness 是硬件当前的亮度
+fn read_backlight(path: &Path) -> Option<f32> {
+    let read = |name: &str| -> Option<u32> {
+        fs::read_to_string(path.join(name))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+
+    let brightness = read("actual_brightness").or_else(|| read("brightness"))?;
+    let max_brightness = read("max_brightness").filter(|max_brightness| *max_brightness > 0)?;
+
+    Some((brightness as f32 / max_brightness as f32).clamp(0.0, 1.0))
+}
+
+/// 内置屏幕的背光亮度，按内核文档的建议优先使用 firmware，其次 platform，最后 raw 类型的设备
+fn get_backlight_brightness() -> XCapResult<f32> {
+    let entries = fs::read_dir(BACKLIGHT_DIR).map_err(|_| XCapError::NotSupported)?;
+
+    let mut devices: Vec<(u8, _)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let path = entry.path();
+            let priority = match fs::read_to_string(path.join("type"))
+                .as_deref()
+                .map(str::trim)
+            {
+                Ok("firmware") => 0,
+                Ok("platform") => 1,
+                _ => 2,
+            };
+
+            (priority, path)
+        })
+        .collect();
+    devices.sort();
+
+    devices
+        .iter()
+        .find_map(|(_, path)| read_backlight(path))
+        .ok_or(XCapError::NotSupported)
+}
+
+/// 输出是否支持可变刷新率，由 modesetting 和 amdgpu 驱动的 vrr_capable 属性提供
+fn is_output_vrr_capable(output: Output) -> XCapResult<bool> {
+    let (conn, _) = get_xcb_connection_and_index()?;
+    let Ok(atom) = get_atom("vrr_capable") else {
+        return Ok(false);
+    };
+
+    let get_output_property_cookie = conn.send_request(&GetOutputProperty {
+        output,
+        property: atom,
+        r#type: ATOM_ANY,
+        long_offset: 0,
+        long_length: 1,
+        delete: false,
+        pending: false,
+    });
+    let get_output_property_reply = conn.wait_for_reply(get_output_property_cookie)?;
+
+    Ok(get_output_property_reply.data::<u32>().first() == Some(&1))
+}
+
+/// 从 EDID 的显示范围限制描述符 (0xFD) 中读取垂直刷新率的范围
+fn get_edid_refresh_range(edid: &[u8]) -> Option<(f32, f32)> {
     const DESCRIPTOR_OFFSET: usize = 0x36;
 
-    // 遍历 EDID 描述符块
     for i in 0..4 {
         let offset = DESCRIPTOR_OFFSET + i * 18;
-        if offset + 5 >= edid.len() {
+        let Some(descriptor) = edid.get(offset..offset + 18) else {
             break;
-        }
+        };
 
-        // 检查描述符类型 (0xFC 为显示器名称)
-        if edid[offset] == 0xFC {
-            let text = &edid[offset + 5..offset + 18];
-            if let Ok(name) = CStr::from_bytes_until_nul(text) {
-                if name.to_string_lossy().contains("Internal") {
-                    return true;
-                }
-            }
+        if descriptor[..3] != [0, 0, 0] || descriptor[3] != 0xFD {
+            continue;
         }
+
+        // EDID 1.4 中第 4 字节的低两位表示最小、最大刷新率需要加上 255
+        let min = descriptor[5] as u32 + if descriptor[4] & 0x01 != 0 { 255 } else { 0 };
+        let max = descriptor[6] as u32 + if descriptor[4] & 0x02 != 0 { 255 } else { 0 };
+
+        return (min > 0 && min <= max).then_some((min as f32, max as f32));
     }
 
-    false
+    None
 }
 
 impl ImplMonitor {
@@ -258,38 +398,6 @@ impl ImplMonitor {
 
         Err(XCapError::new("Not found monitor"))
     }
-
-    pub fn from_unique_key(unique_key: String) -> XCapResult<ImplMonitor> {
-        let monitors = ImplMonitor::all()?;
-
-        for monitor in monitors {
-            // 1. 优先检查序列号（硬件属性，最可靠）
-            if let Ok(serial) = monitor.serial_number() {
-                if !serial.is_empty() && serial == unique_key {
-                    return Ok(monitor);
-                }
-            }
-
-            // 2. 检查 UUID
-            if let Ok(uuid) = monitor.uuid() {
-                if uuid == unique_key {
-                    return Ok(monitor);
-                }
-            }
-
-            // 3. 最后检查显示器 ID
-            if let Ok(id) = monitor.id() {
-                if id.to_string() == unique_key {
-                    return Ok(monitor);
-                }
-            }
-        }
-
-        Err(XCapError::new(format!(
-            "Monitor with unique_key '{}' not found",
-            unique_key
-        )))
-    }
 }
 
 impl ImplMonitor {
@@ -350,18 +458,69 @@ impl ImplMonitor {
         Ok(scale_factor)
     }
 
+    /// 坐标是帧缓冲像素除以缩放比例，截图是帧缓冲像素
+    pub fn pixel_ratio(&self) -> XCapResult<f32> {
+        self.scale_factor()
+    }
+
     pub fn frequency(&self) -> XCapResult<f32> {
         let mode_infos = get_mode_infos()?;
         let (_, frequency) = get_rotation_frequency(mode_infos, &self.output).unwrap_or((0.0, 0.0));
         Ok(frequency)
     }
 
+    pub fn refresh_rate_range(&self) -> XCapResult<RefreshRateRange> {
+        let frequency = self.frequency()?;
+        let fixed = RefreshRateRange {
+            min: frequency,
+            max: frequency,
+        };
+
+        if !is_output_vrr_capable(self.output)? {
+            return Ok(fixed);
+        }
+
+        // 可变刷新率的上限是当前模式的刷新率，EDID 中的上限可能属于其他模式
+        let edid = get_output_edid(self.output)?;
+        let range = match get_edid_refresh_range(&edid) {
+            Some((min, _)) if min < frequency => RefreshRateRange {
+                min,
+                max: frequency,
+            },
+            _ => fixed,
+        };
+
+        Ok(range)
+    }
+
     pub fn is_primary(&self) -> XCapResult<bool> {
         let primary = get_monitor_info_buf(self.output)?.primary();
 
         Ok(primary)
     }
 
+    pub fn bit_depth(&self) -> XCapResult<u32> {
+        // X11 的位深是整个屏幕的，24 位色的根窗口 visual 每个通道 8 位，30 位色每个通道 10 位
+        let screen_buf = get_current_screen_buf()?;
+        let root_visual = screen_buf.root_visual();
+
+        screen_buf
+            .allowed_depths()
+            .flat_map(|depth| depth.visuals())
+            .find(|visual| visual.visual_id() == root_visual)
+            .map(|visual| visual.bits_per_rgb_value() as u32)
+            .ok_or(XCapError::new("Get root visual failed"))
+    }
+
+    pub fn pixel_encoding(&self) -> XCapResult<PixelEncoding> {
+        let pixel_encoding = match get_output_colorspace(self.output)? {
+            Some(colorspace) if colorspace.contains("YCC") => PixelEncoding::YCbCr,
+            _ => PixelEncoding::Rgb,
+        };
+
+        Ok(pixel_encoding)
+    }
+
     pub fn is_builtin(&self) -> XCapResult<bool> {
         let name = self.name()?;
 
@@ -374,6 +533,66 @@ impl ImplMonitor {
         Ok(is_builtin_edid(&edid))
     }
 
+    pub fn can_capture(&self) -> XCapResult<bool> {
+        if wayland_detect() {
+            return Ok(is_wayland_capture_available());
+        }
+
+        Ok(get_xcb_connection_and_index().is_ok())
+    }
+
+    pub fn power_state(&self) -> XCapResult<PowerState> {
+        // Wayland 下 XWayland 的 DPMS 状态与实际显示器无关
+        if wayland_detect() {
+            return Err(XCapError::NotSupported);
+        }
+
+        let (conn, _) = get_xcb_connection_and_index()?;
+        let get_info_cookie = conn.send_request(&dpms::Info {});
+        let get_info_reply = conn.wait_for_reply(get_info_cookie)?;
+
+        // DPMS 被禁用时显示器始终保持开启
+        if !get_info_reply.state() {
+            return Ok(PowerState::On);
+        }
+
+        let power_state = match get_info_reply.power_level() {
+            DpmsMode::Standby => PowerState::Standby,
+            DpmsMode::Suspend => PowerState::Suspend,
+            DpmsMode::Off => PowerState::Off,
+            _ => PowerState::On,
+        };
+
+        Ok(power_state)
+    }
+
+    pub fn brightness(&self) -> XCapResult<f32> {
+        // 外接显示器的亮度只能通过 DDC/CI 读取，需要 i2c 设备的权限
+        if !self.is_builtin()? {
+            return Err(XCapError::NotSupported);
+        }
+
+        get_backlight_brightness()
+    }
+
+    pub fn is_mirrored(&self) -> XCapResult<bool> {
+        Ok(self.mirror_group()?.len() > 1)
+    }
+
+    pub fn mirror_group(&self) -> XCapResult<Vec<ImplMonitor>> {
+        let crtc_rect = get_crtc_rect(self.output)?;
+
+        let impl_monitors = ImplMonitor::all()?
+            .into_iter()
+            .filter(|impl_monitor| {
+                impl_monitor.output == self.output
+                    || get_crtc_rect(impl_monitor.output).is_ok_and(|rect| rect == crtc_rect)
+            })
+            .collect();
+
+        Ok(impl_monitors)
+    }
+
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
         capture_monitor(self)
     }
@@ -382,6 +601,75 @@ impl ImplMonitor {
         self.capture_image()
     }
 
+    pub fn wait_for_present(&self) -> XCapResult<()> {
+        // X11 和 Wayland 都没有不创建窗口就能等待合成器提交的接口，Wayland 下 RandR 可能取不到刷新率
+        wait_refresh_periods(self.frequency().unwrap_or(0.0));
+
+        Ok(())
+    }
+
+    pub fn capture_thumbnail(&self, width: u32, height: u32) -> XCapResult<RgbaImage> {
+        // X11 和 portal 都只能截取原始尺寸
+        let image = self.capture_image()?;
+
+        Ok(imageops::thumbnail(&image, width, height))
+    }
+
+    pub fn capture_image_with_config(&self, config: &CaptureConfig) -> XCapResult<RgbaImage> {
+        if !config.excludes_system_windows() {
+            return self.capture_image();
+        }
+        // 门户截图拿不到其他窗口的位置和内容，无法按窗口层级排除系统窗口
+        if wayland_detect() {
+            return Err(XCapError::NotSupported);
+        }
+
+        // GetImage 不能按窗口过滤，被排除的窗口用下面的窗口和壁纸重新合成，坐标都是帧缓冲中的像素
+        let mut image = self.capture_image()?;
+        let monitor_info_buf = get_monitor_info_buf(self.output)?;
+        let x = monitor_info_buf.x() as i32;
+        let y = monitor_info_buf.y() as i32;
+
+        let mut impl_windows = Vec::new();
+        let mut layer_windows = Vec::new();
+        // ImplWindow::all 按 Z 顺序从最顶层的窗口开始
+        for impl_window in ImplWindow::all()? {
+            if impl_window.is_minimized()? {
+                continue;
+            }
+
+            let rect = Rect::new(
+                impl_window.x()? - x,
+                impl_window.y()? - y,
+                impl_window.width()?,
+                impl_window.height()?,
+            );
+            layer_windows.push(LayerWindow {
+                rect,
+                image_origin: Point::new(rect.x, rect.y),
+                excluded: config.excludes_layer(impl_window.layer()?),
+            });
+            impl_windows.push(impl_window);
+        }
+
+        compose_without(
+            &mut image,
+            &layer_windows,
+            config.exclude_desktop_icons,
+            |index| impl_windows[index].capture_image(),
+            || {
+                capture_wallpaper(
+                    x,
+                    y,
+                    monitor_info_buf.width() as u32,
+                    monitor_info_buf.height() as u32,
+                )
+            },
+        )?;
+
+        Ok(image)
+    }
+
     pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
         // Validate region bounds
         let monitor_x = self.x()?;
@@ -389,11 +677,8 @@ impl ImplMonitor {
         let monitor_width = self.width()?;
         let monitor_height = self.height()?;
 
-        if width > monitor_width
-            || height > monitor_height
-            || x + width > monitor_width
-            || y + height > monitor_height
-        {
+        let region = Rect::new(x as i32, y as i32, width, height);
+        if !Rect::new(0, 0, monitor_width, monitor_height).contains_rect(region) {
             return Err(XCapError::InvalidCaptureRegion(format!(
                 "Region ({x}, {y}, {width}, {height}) is outside monitor bounds ({monitor_x}, {monitor_y}, {monitor_width}, {monitor_height})"
             )));
@@ -401,8 +686,12 @@ impl ImplMonitor {
         capture_region(self, x, y, width, height)
     }
 
-    pub fn video_recorder(&self) -> XCapResult<(ImplVideoRecorder, Receiver<Frame>)> {
-        ImplVideoRecorder::new(self.clone())
+    pub fn video_recorder(
+        &self,
+        config: &RecorderConfig,
+        health: Arc<RecorderHealth>,
+    ) -> XCapResult<(ImplVideoRecorder, Receiver<Frame>)> {
+        ImplVideoRecorder::new(self.clone(), config, health)
     }
 
     /// 获取显示器的 UUID
@@ -416,4 +705,24 @@ impl ImplMonitor {
     pub fn serial_number(&self) -> XCapResult<String> {
         super::display_info::get_display_serial_number(self.output)
     }
+
+    /// 获取显示器的厂商、型号和序列号
+    /// 通过 XRandR 读取并解析 EDID
+    pub fn identity(&self) -> XCapResult<MonitorIdentity> {
+        super::display_info::get_display_identity(self.output)
+    }
+
+    #[cfg(feature = "ddc")]
+    pub fn ddc_capabilities(&self) -> XCapResult<String> {
+        let edid = get_output_edid(self.output)?;
+
+        super::ddc::DdcDevice::open(&edid)?.capabilities()
+    }
+
+    #[cfg(feature = "ddc")]
+    pub fn vcp_feature(&self, code: u8) -> XCapResult<(u16, u16)> {
+        let edid = get_output_edid(self.output)?;
+
+        super::ddc::DdcDevice::open(&edid)?.vcp_feature(code)
+    }
 }
@@ -0,0 +1,251 @@
+use scopeguard::defer;
+use xcb::{
+    Connection, Xid,
+    x::{
+        ATOM_CARDINAL, CURRENT_TIME, ChangeGc, ChangeProperty, ClearArea, CloseFont, CreateGc,
+        CreateGlyphCursor, CreateWindow, Cursor, Cw, DestroyWindow, Drawable, Event, EventMask,
+        Font, FreeCursor, FreeGc, Gc, Gcontext, GetKeyboardMapping, GrabKeyboard, GrabMode,
+        GrabPointer, GrabStatus, Keycode, MapWindow, OpenFont, PolyFillRectangle, PolyRectangle,
+        PropMode, Rectangle, UngrabKeyboard, UngrabPointer, Window, WindowClass,
+    },
+};
+
+use crate::{
+    error::{XCapError, XCapResult},
+    geometry::{Point, Rect},
+    selector::{Outcome, Selection},
+};
+
+use super::{
+    impl_monitor::get_scale_factor,
+    utils::{get_atom, get_x_display_name, wayland_detect},
+};
+
+// X11 cursor 字体中的十字光标
+const XC_CROSSHAIR: u16 = 34;
+const XK_ESCAPE: u32 = 0xFF1B;
+// _NET_WM_WINDOW_OPACITY 的取值范围是 0 到 0xFFFFFFFF，由合成器实现，没有合成器时窗口不透明
+const OVERLAY_OPACITY: u32 = 0x6000_0000;
+const SELECTION_PIXEL: u32 = 0xFF_FFFF;
+const BORDER_PIXEL: u32 = 0x00_78D7;
+
+/// 创建覆盖整个屏幕的 override-redirect 窗口，抓取鼠标和键盘直到选择结束。
+/// 使用独立的连接，事件不会被其他功能读取
+pub(crate) fn run_selector(
+    _bounds: &[Rect],
+    mut selection: Selection,
+) -> XCapResult<Option<Outcome>> {
+    if wayland_detect() {
+        return Err(XCapError::NotSupported);
+    }
+
+    let scale_factor = get_scale_factor().unwrap_or(1.0) as f64;
+    let (conn, index) = Connection::connect(get_x_display_name().as_deref())?;
+    let setup = conn.get_setup();
+    let screen = setup
+        .roots()
+        .nth(index as usize)
+        .ok_or_else(|| XCapError::new("Not found screen"))?;
+
+    let font: Font = conn.generate_id();
+    conn.send_request(&OpenFont {
+        fid: font,
+        name: b"cursor",
+    });
+    let cursor: Cursor = conn.generate_id();
+    conn.send_request(&CreateGlyphCursor {
+        cid: cursor,
+        source_font: font,
+        mask_font: font,
+        source_char: XC_CROSSHAIR,
+        mask_char: XC_CROSSHAIR + 1,
+        fore_red: 0xFFFF,
+        fore_green: 0xFFFF,
+        fore_blue: 0xFFFF,
+        back_red: 0,
+        back_green: 0,
+        back_blue: 0,
+    });
+
+    let window: Window = conn.generate_id();
+    conn.send_request(&CreateWindow {
+        depth: xcb::x::COPY_FROM_PARENT as u8,
+        wid: window,
+        parent: screen.root(),
+        x: 0,
+        y: 0,
+        width: screen.width_in_pixels(),
+        height: screen.height_in_pixels(),
+        border_width: 0,
+        class: WindowClass::InputOutput,
+        visual: screen.root_visual(),
+        value_list: &[
+            Cw::BackPixel(screen.black_pixel()),
+            Cw::OverrideRedirect(true),
+            Cw::EventMask(
+                EventMask::EXPOSURE
+                    | EventMask::BUTTON_PRESS
+                    | EventMask::BUTTON_RELEASE
+                    | EventMask::POINTER_MOTION
+                    | EventMask::KEY_PRESS,
+            ),
+            Cw::Cursor(cursor),
+        ],
+    });
+    defer! {
+        conn.send_request(&UngrabPointer { time: CURRENT_TIME });
+        conn.send_request(&UngrabKeyboard { time: CURRENT_TIME });
+        conn.send_request(&DestroyWindow { window });
+        conn.send_request(&FreeCursor { cursor });
+        conn.send_request(&CloseFont { font });
+        if let Err(err) = conn.flush() {
+            log::error!("Flush selector requests failed: {err:?}");
+        }
+    }
+
+    if let Ok(opacity) = get_atom("_NET_WM_WINDOW_OPACITY") {
+        conn.send_request(&ChangeProperty {
+            mode: PropMode::Replace,
+            window,
+            property: opacity,
+            r#type: ATOM_CARDINAL,
+            data: &[OVERLAY_OPACITY],
+        });
+    }
+
+    let gc: Gcontext = conn.generate_id();
+    conn.send_request(&CreateGc {
+        cid: gc,
+        drawable: Drawable::Window(window),
+        value_list: &[Gc::Foreground(SELECTION_PIXEL), Gc::LineWidth(2)],
+    });
+    defer! {
+        conn.send_request(&FreeGc { gc });
+    }
+
+    conn.send_request(&MapWindow { window });
+
+    let pointer = conn.wait_for_reply(conn.send_request(&GrabPointer {
+        owner_events: false,
+        grab_window: window,
+        event_mask: EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::POINTER_MOTION,
+        pointer_mode: GrabMode::Async,
+        keyboard_mode: GrabMode::Async,
+        confine_to: Window::none(),
+        cursor,
+        time: CURRENT_TIME,
+    }))?;
+    let keyboard = conn.wait_for_reply(conn.send_request(&GrabKeyboard {
+        owner_events: false,
+        grab_window: window,
+        time: CURRENT_TIME,
+        pointer_mode: GrabMode::Async,
+        keyboard_mode: GrabMode::Async,
+    }))?;
+    if pointer.status() != GrabStatus::Success || keyboard.status() != GrabStatus::Success {
+        return Err(XCapError::new("Grab pointer and keyboard failed"));
+    }
+
+    let escape = escape_keycode(&conn)?;
+    // 事件中是帧缓冲像素，显示器坐标是除以缩放比例后的逻辑坐标
+    let to_point = |x: i16, y: i16| {
+        Point::new(
+            (x as f64 / scale_factor).floor() as i32,
+            (y as f64 / scale_factor).floor() as i32,
+        )
+    };
+
+    loop {
+        match conn.wait_for_event()? {
+            xcb::Event::X(Event::ButtonPress(event)) => match event.detail() {
+                1 => selection.press(to_point(event.root_x(), event.root_y())),
+                3 => return Ok(None),
+                _ => {}
+            },
+            xcb::Event::X(Event::MotionNotify(event)) => {
+                selection.drag(to_point(event.root_x(), event.root_y()));
+                draw(&conn, window, gc, &selection, scale_factor)?;
+            }
+            xcb::Event::X(Event::ButtonRelease(event)) if event.detail() == 1 => {
+                if let Some(outcome) = selection.release(to_point(event.root_x(), event.root_y())) {
+                    return Ok(Some(outcome));
+                }
+                draw(&conn, window, gc, &selection, scale_factor)?;
+            }
+            xcb::Event::X(Event::KeyPress(event)) if Some(event.detail()) == escape => {
+                return Ok(None);
+            }
+            xcb::Event::X(Event::Expose(_)) => draw(&conn, window, gc, &selection, scale_factor)?,
+            _ => {}
+        }
+    }
+}
+
+/// 查找 Escape 对应的键码，不同的键盘布局键码可能不同
+fn escape_keycode(conn: &Connection) -> XCapResult<Option<Keycode>> {
+    let setup = conn.get_setup();
+    let reply = conn.wait_for_reply(conn.send_request(&GetKeyboardMapping {
+        first_keycode: setup.min_keycode(),
+        count: setup.max_keycode() - setup.min_keycode() + 1,
+    }))?;
+
+    let per_keycode = reply.keysyms_per_keycode().max(1) as usize;
+    let keycode = reply
+        .keysyms()
+        .chunks(per_keycode)
+        .position(|keysyms| keysyms.contains(&XK_ESCAPE))
+        .map(|offset| setup.min_keycode() + offset as u8);
+
+    Ok(keycode)
+}
+
+/// 清空窗口后用白色填充选区并绘制边框，覆盖层半透明时选区比周围更亮
+fn draw(
+    conn: &Connection,
+    window: Window,
+    gc: Gcontext,
+    selection: &Selection,
+    scale_factor: f64,
+) -> XCapResult<()> {
+    conn.send_request(&ClearArea {
+        exposures: false,
+        window,
+        x: 0,
+        y: 0,
+        width: 0,
+        height: 0,
+    });
+
+    if let Some(rect) = selection.rect() {
+        let rectangle = Rectangle {
+            x: (rect.x as f64 * scale_factor) as i16,
+            y: (rect.y as f64 * scale_factor) as i16,
+            width: (rect.width as f64 * scale_factor).ceil() as u16,
+            height: (rect.height as f64 * scale_factor).ceil() as u16,
+        };
+        let drawable = Drawable::Window(window);
+
+        conn.send_request(&ChangeGc {
+            gc,
+            value_list: &[Gc::Foreground(SELECTION_PIXEL)],
+        });
+        conn.send_request(&PolyFillRectangle {
+            drawable,
+            gc,
+            rectangles: &[rectangle],
+        });
+        conn.send_request(&ChangeGc {
+            gc,
+            value_list: &[Gc::Foreground(BORDER_PIXEL)],
+        });
+        conn.send_request(&PolyRectangle {
+            drawable,
+            gc,
+            rectangles: &[rectangle],
+        });
+    }
+
+    conn.flush()?;
+
+    Ok(())
+}
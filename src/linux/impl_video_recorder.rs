@@ -1,6 +1,9 @@
-use std::sync::mpsc::Receiver;
+use std::sync::{Arc, mpsc::Receiver};
 
-use crate::{XCapResult, video_recorder::Frame};
+use crate::{
+    RecorderConfig, RecorderUpdate, XCapResult,
+    video_recorder::{Frame, RecorderHealth},
+};
 
 use super::{
     impl_monitor::ImplMonitor, utils::wayland_detect, wayland_video_recorder::WaylandVideoRecorder,
@@ -14,12 +17,16 @@ pub enum ImplVideoRecorder {
 }
 
 impl ImplVideoRecorder {
-    pub fn new(monitor: ImplMonitor) -> XCapResult<(Self, Receiver<Frame>)> {
+    pub fn new(
+        monitor: ImplMonitor,
+        config: &RecorderConfig,
+        health: Arc<RecorderHealth>,
+    ) -> XCapResult<(Self, Receiver<Frame>)> {
         if wayland_detect() {
-            let (recorder, receiver) = WaylandVideoRecorder::new(monitor)?;
+            let (recorder, receiver) = WaylandVideoRecorder::new(monitor, config, health)?;
             Ok((ImplVideoRecorder::Wayland(recorder), receiver))
         } else {
-            let (recorder, receiver) = XorgVideoRecorder::new(monitor)?;
+            let (recorder, receiver) = XorgVideoRecorder::new(monitor, config, health)?;
             Ok((ImplVideoRecorder::Xorg(recorder), receiver))
         }
     }
@@ -37,4 +44,18 @@ impl ImplVideoRecorder {
             ImplVideoRecorder::Wayland(recorder) => recorder.stop(),
         }
     }
+
+    pub fn reconfigure(&self, update: &RecorderUpdate) -> XCapResult<()> {
+        match self {
+            ImplVideoRecorder::Xorg(recorder) => recorder.reconfigure(update),
+            ImplVideoRecorder::Wayland(recorder) => recorder.reconfigure(update),
+        }
+    }
+
+    pub fn shutdown(&self) -> XCapResult<()> {
+        match self {
+            ImplVideoRecorder::Xorg(recorder) => recorder.shutdown(),
+            ImplVideoRecorder::Wayland(recorder) => recorder.shutdown(),
+        }
+    }
 }
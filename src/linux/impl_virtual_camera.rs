@@ -0,0 +1,120 @@
+use std::{
+    ffi::{c_int, c_ulong, c_void},
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    mem,
+    os::fd::AsRawFd,
+    path::Path,
+};
+
+use crate::error::{PlatformError, XCapError, XCapResult};
+
+// linux/videodev2.h
+const V4L2_BUF_TYPE_VIDEO_OUTPUT: u32 = 2;
+const V4L2_FIELD_NONE: u32 = 1;
+const V4L2_COLORSPACE_REC709: u32 = 3;
+const V4L2_YCBCR_ENC_709: u32 = 2;
+const V4L2_QUANTIZATION_LIM_RANGE: u32 = 2;
+const V4L2_PIX_FMT_YUYV: u32 = u32::from_le_bytes(*b"YUYV");
+const VIDIOC_S_FMT: c_ulong = iowr(b'V', 5, mem::size_of::<V4l2Format>());
+
+unsafe extern "C" {
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+}
+
+/// asm-generic/ioctl.h 中的 _IOWR
+const fn iowr(kind: u8, nr: u8, size: usize) -> c_ulong {
+    (3 << 30) | ((size as c_ulong) << 16) | ((kind as c_ulong) << 8) | nr as c_ulong
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct V4l2PixFormat {
+    width: u32,
+    height: u32,
+    pixelformat: u32,
+    field: u32,
+    bytesperline: u32,
+    sizeimage: u32,
+    colorspace: u32,
+    private: u32,
+    flags: u32,
+    ycbcr_enc: u32,
+    quantization: u32,
+    xfer_func: u32,
+}
+
+// 内核中的联合体包含指针，按指针对齐
+#[repr(C)]
+#[derive(Clone, Copy)]
+union V4l2FormatData {
+    pix: V4l2PixFormat,
+    raw_data: [u8; 200],
+    _align: [*mut c_void; 0],
+}
+
+#[repr(C)]
+struct V4l2Format {
+    kind: u32,
+    fmt: V4l2FormatData,
+}
+
+/// 以输出设备打开 v4l2loopback，写入的每一帧都是完整的 YUYV 图像
+#[derive(Debug)]
+pub(crate) struct ImplVirtualCamera {
+    file: File,
+}
+
+impl ImplVirtualCamera {
+    pub fn open(device: &Path, width: u32, height: u32) -> XCapResult<ImplVirtualCamera> {
+        let file = OpenOptions::new().write(true).open(device)?;
+
+        let pix = V4l2PixFormat {
+            width,
+            height,
+            pixelformat: V4L2_PIX_FMT_YUYV,
+            field: V4L2_FIELD_NONE,
+            bytesperline: width * 2,
+            sizeimage: width * height * 2,
+            colorspace: V4L2_COLORSPACE_REC709,
+            private: 0,
+            flags: 0,
+            ycbcr_enc: V4L2_YCBCR_ENC_709,
+            quantization: V4L2_QUANTIZATION_LIM_RANGE,
+            xfer_func: 0,
+        };
+        let mut format = V4l2Format {
+            kind: V4L2_BUF_TYPE_VIDEO_OUTPUT,
+            fmt: V4l2FormatData { raw_data: [0; 200] },
+        };
+        format.fmt.pix = pix;
+
+        if unsafe { ioctl(file.as_raw_fd(), VIDIOC_S_FMT, &mut format) } < 0 {
+            let err = io::Error::last_os_error();
+            return Err(XCapError::platform(
+                format!("Set the format of {device:?} failed, is it a v4l2loopback device?"),
+                PlatformError::new("errno", err.raw_os_error().unwrap_or(0) as i64, err),
+            ));
+        }
+
+        // 驱动可能调整格式，例如设备已经被其他进程以其他尺寸打开
+        let applied = unsafe { format.fmt.pix };
+        if applied.width != width
+            || applied.height != height
+            || applied.pixelformat != V4L2_PIX_FMT_YUYV
+        {
+            return Err(XCapError::new(format!(
+                "{device:?} does not accept {width}x{height} YUYV frames, it uses {}x{}",
+                applied.width, applied.height
+            )));
+        }
+
+        Ok(ImplVirtualCamera { file })
+    }
+
+    pub fn write(&mut self, yuyv: &[u8]) -> XCapResult<()> {
+        self.file.write_all(yuyv)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    path::Path,
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::error::{XCapError, XCapResult};
+
+use super::{
+    impl_monitor::ImplMonitor,
+    utils::{is_xcb_connected, set_x_display_name, wayland_detect},
+};
+
+// 等待 Xvfb 启动的超时时间
+const XVFB_START_TIMEOUT: Duration = Duration::from_secs(10);
+// 从 :99 开始查找空闲的 display，避免和桌面会话冲突
+const FIRST_DISPLAY_NUMBER: u32 = 99;
+const LAST_DISPLAY_NUMBER: u32 = 199;
+
+/// X server 的锁文件和 socket 都不存在时 display 是空闲的
+fn find_free_display_number() -> XCapResult<u32> {
+    (FIRST_DISPLAY_NUMBER..=LAST_DISPLAY_NUMBER)
+        .find(|number| {
+            !Path::new(&format!("/tmp/.X{number}-lock")).exists()
+                && !Path::new(&format!("/tmp/.X11-unix/X{number}")).exists()
+        })
+        .ok_or_else(|| XCapError::new("No free X display number for Xvfb"))
+}
+
+/// 启动 Xvfb 并等待它创建 socket
+fn start_xvfb(number: u32, width: u32, height: u32) -> XCapResult<Child> {
+    let mut xvfb = Command::new("Xvfb")
+        .arg(format!(":{number}"))
+        .args(["-screen", "0", &format!("{width}x{height}x24")])
+        .args(["-nolisten", "tcp"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| XCapError::new(format!("Start Xvfb failed: {err}")))?;
+
+    let socket = format!("/tmp/.X11-unix/X{number}");
+    let start = Instant::now();
+    while !Path::new(&socket).exists() {
+        if let Some(status) = xvfb.try_wait()? {
+            return Err(XCapError::new(format!("Xvfb exited with {status}")));
+        }
+
+        if start.elapsed() > XVFB_START_TIMEOUT {
+            let _ = xvfb.kill();
+            let _ = xvfb.wait();
+            return Err(XCapError::new("Xvfb did not start in time"));
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    Ok(xvfb)
+}
+
+#[derive(Debug)]
+pub struct ImplVirtualDisplay {
+    xvfb: Child,
+}
+
+impl ImplVirtualDisplay {
+    pub fn new(width: u32, height: u32) -> XCapResult<ImplVirtualDisplay> {
+        // 共享的 XCB 连接创建后不能再切换 display
+        if is_xcb_connected() {
+            return Err(XCapError::new(
+                "Already connected to the X server, create the virtual display first",
+            ));
+        }
+
+        let number = find_free_display_number()?;
+        let mut xvfb = start_xvfb(number, width, height)?;
+
+        let result = set_x_display_name(format!(":{number}")).and_then(|_| {
+            if wayland_detect() {
+                return Err(XCapError::new(
+                    "Virtual displays need the Xorg backend, set it with ConfigBuilder::backend",
+                ));
+            }
+
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            let _ = xvfb.kill();
+            let _ = xvfb.wait();
+            return Err(err);
+        }
+
+        Ok(ImplVirtualDisplay { xvfb })
+    }
+
+    pub fn monitor(&self) -> XCapResult<ImplMonitor> {
+        // Xvfb 只有一个屏幕
+        ImplMonitor::all()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| XCapError::new("Virtual display has no monitor"))
+    }
+}
+
+impl Drop for ImplVirtualDisplay {
+    fn drop(&mut self) {
+        if let Err(err) = self.xvfb.kill() {
+            log::error!("kill Xvfb failed: {err:?}");
+        }
+
+        let _ = self.xvfb.wait();
+    }
+}
@@ -0,0 +1,45 @@
+use zbus::blocking::Proxy;
+
+use crate::error::XCapResult;
+
+use super::utils::get_zbus_connection;
+
+const APPLICATION_NAME: &str = "xcap";
+const REASON: &str = "Recording the screen";
+
+fn get_screen_saver_proxy() -> XCapResult<Proxy<'static>> {
+    let proxy = Proxy::new(
+        get_zbus_connection()?,
+        "org.freedesktop.ScreenSaver",
+        "/org/freedesktop/ScreenSaver",
+        "org.freedesktop.ScreenSaver",
+    )?;
+
+    Ok(proxy)
+}
+
+/// 通过 org.freedesktop.ScreenSaver 阻止屏保和息屏，X11 和 Wayland 的桌面环境都实现了该接口
+#[derive(Debug)]
+pub(crate) struct ImplWakeLock {
+    cookie: u32,
+}
+
+impl ImplWakeLock {
+    pub fn new() -> XCapResult<ImplWakeLock> {
+        let cookie: u32 = get_screen_saver_proxy()?.call("Inhibit", &(APPLICATION_NAME, REASON))?;
+
+        Ok(ImplWakeLock { cookie })
+    }
+}
+
+impl Drop for ImplWakeLock {
+    fn drop(&mut self) {
+        // 共享的 D-Bus 连接不会关闭，需要主动解除
+        let result = get_screen_saver_proxy()
+            .and_then(|proxy| Ok(proxy.call::<_, _, ()>("UnInhibit", &(self.cookie,))?));
+
+        if let Err(err) = result {
+            log::error!("UnInhibit({}) failed: {:?}", self.cookie, err);
+        }
+    }
+}
@@ -1,19 +1,34 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
 use image::RgbaImage;
 use xcb::{
-    Xid, XidNew,
+    Connection, Extension, Xid, XidNew,
+    damage::{
+        Create, Damage, Destroy, Event as DamageEvent, QueryVersion, ReportLevel, Subtract,
+    },
     x::{
-        ATOM_ATOM, ATOM_CARDINAL, ATOM_NONE, ATOM_STRING, ATOM_WM_CLASS, ATOM_WM_NAME, Atom,
-        Drawable, GetGeometry, GetProperty, GetPropertyReply, QueryPointer, TranslateCoordinates,
-        Window,
+        ATOM_ATOM, ATOM_CARDINAL, ATOM_NONE, ATOM_STRING, ATOM_WINDOW, ATOM_WM_CLASS, ATOM_WM_NAME,
+        ATOM_WM_TRANSIENT_FOR, Atom, CURRENT_TIME, Drawable, GetGeometry, GetProperty,
+        GetPropertyReply, GetWindowAttributes, MapState, QueryPointer, TranslateCoordinates,
+        WarpPointer, Window,
     },
+    xfixes::Region,
+    xtest::FakeInput,
 };
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    ActiveInfoMode, Rect, WindowLayer,
+    error::{XCapError, XCapResult},
+    normal_bounds::track_normal_bounds,
+};
 
 use super::{
-    capture::capture_window,
+    capture::{capture_window, capture_window_region},
     impl_monitor::ImplMonitor,
-    utils::{get_atom, get_xcb_connection_and_index},
+    utils::{get_atom, get_x_display_name, get_xcb_connection_and_index, wayland_detect},
 };
 
 #[derive(Debug, Clone)]
@@ -21,7 +36,7 @@ pub(crate) struct ImplWindow {
     pub window: Window,
 }
 
-fn get_window_property(
+pub(super) fn get_window_property(
     window: Window,
     property: Atom,
     r#type: Atom,
@@ -118,6 +133,54 @@ fn get_window_state(window: &Window) -> XCapResult<(bool, bool)> {
     ))
 }
 
+/// 窗口被窗口管理器最大化（包括单方向最大化）或全屏时返回 true
+fn is_window_resized_by_wm(window: &Window) -> XCapResult<bool> {
+    let wm_state_atom = get_atom("_NET_WM_STATE")?;
+    let resized_atoms = [
+        get_atom("_NET_WM_STATE_MAXIMIZED_VERT")?,
+        get_atom("_NET_WM_STATE_MAXIMIZED_HORZ")?,
+        get_atom("_NET_WM_STATE_FULLSCREEN")?,
+    ];
+
+    let wm_state_reply = get_window_property(*window, wm_state_atom, ATOM_ATOM, 0, 12)?;
+    let wm_state = wm_state_reply.value::<Atom>();
+
+    Ok(wm_state.iter().any(|atom| resized_atoms.contains(atom)))
+}
+
+fn get_window_layer(window: &Window) -> XCapResult<WindowLayer> {
+    // https://specifications.freedesktop.org/wm-spec/1.3/ar01s05.html#id-1.6.7
+    let wm_window_type_atom = get_atom("_NET_WM_WINDOW_TYPE")?;
+
+    let wm_window_type_reply = get_window_property(*window, wm_window_type_atom, ATOM_ATOM, 0, 12)?;
+
+    // 属性可能包含多个类型，按优先级取第一个能识别的类型
+    for &window_type in wm_window_type_reply.value::<Atom>() {
+        let layers = [
+            ("_NET_WM_WINDOW_TYPE_DESKTOP", WindowLayer::Desktop),
+            ("_NET_WM_WINDOW_TYPE_DOCK", WindowLayer::Dock),
+            ("_NET_WM_WINDOW_TYPE_TOOLTIP", WindowLayer::Tooltip),
+            ("_NET_WM_WINDOW_TYPE_DROPDOWN_MENU", WindowLayer::Popup),
+            ("_NET_WM_WINDOW_TYPE_POPUP_MENU", WindowLayer::Popup),
+            ("_NET_WM_WINDOW_TYPE_COMBO", WindowLayer::Popup),
+            ("_NET_WM_WINDOW_TYPE_MENU", WindowLayer::Popup),
+            ("_NET_WM_WINDOW_TYPE_NOTIFICATION", WindowLayer::Overlay),
+            ("_NET_WM_WINDOW_TYPE_DND", WindowLayer::Overlay),
+            ("_NET_WM_WINDOW_TYPE_SPLASH", WindowLayer::Overlay),
+        ];
+
+        for (name, layer) in layers {
+            // 窗口管理器不支持的类型原子不存在，直接跳过
+            if get_atom(name).is_ok_and(|atom| atom == window_type) {
+                return Ok(layer);
+            }
+        }
+    }
+
+    // 没有设置类型的窗口按照规范视为普通窗口
+    Ok(WindowLayer::Normal)
+}
+
 impl ImplWindow {
     fn new(window: Window) -> ImplWindow {
         ImplWindow { window }
@@ -201,6 +264,27 @@ impl ImplWindow {
 
         Ok((app_name, pid, display_serial))
     }
+
+    pub fn active_info_mode() -> ActiveInfoMode {
+        ActiveInfoMode::Polling
+    }
+
+    /// 获取活动窗口的标题，只读取 _NET_ACTIVE_WINDOW 指向的窗口，不枚举所有窗口
+    ///
+    /// 返回：(应用名称, 进程 ID, 窗口标题)
+    pub fn get_active_title() -> XCapResult<(String, i32, String)> {
+        let active_window_id = get_active_window_id()?;
+        let active_window = unsafe { Window::new(active_window_id) };
+        let impl_window = ImplWindow::new(active_window);
+
+        let app_name = impl_window
+            .app_name()
+            .unwrap_or_else(|_| "Unknown".to_string());
+        let pid = impl_window.pid().unwrap_or(0) as i32;
+        let title = impl_window.title()?;
+
+        Ok((app_name, pid, title))
+    }
 }
 
 impl ImplWindow {
@@ -303,6 +387,11 @@ impl ImplWindow {
         Ok(find_result)
     }
 
+    /// 窗口坐标和截图都是 X 服务器中的像素
+    pub fn pixel_ratio(&self) -> XCapResult<f32> {
+        Ok(1.0)
+    }
+
     pub fn x(&self) -> XCapResult<i32> {
         let (x, _, _, _) = get_position_and_size(&self.window)?;
 
@@ -352,13 +441,199 @@ impl ImplWindow {
         Ok(is_minimized)
     }
 
+    pub fn normal_bounds(&self) -> XCapResult<Rect> {
+        // EWMH 没有还原位置，隐藏的窗口保留原来的几何信息，只有最大化和全屏会改变窗口大小
+        let (x, y, width, height) = get_position_and_size(&self.window)?;
+        let bounds = Rect::new(x, y, width, height);
+        let is_normal = !is_window_resized_by_wm(&self.window)?;
+
+        Ok(track_normal_bounds(self.id()?, bounds, is_normal).unwrap_or(bounds))
+    }
+
+    pub fn focused_id() -> XCapResult<u32> {
+        get_active_window_id()
+    }
+
     pub fn is_focused(&self) -> XCapResult<bool> {
         let active_window_id = get_active_window_id()?;
 
         Ok(active_window_id == self.id()?)
     }
 
+    pub fn layer(&self) -> XCapResult<WindowLayer> {
+        get_window_layer(&self.window)
+    }
+
+    pub fn parent_id(&self) -> XCapResult<Option<u32>> {
+        // https://x.org/releases/X11R7.6/doc/xorg-docs/specs/ICCCM/icccm.html#WM_TRANSIENT_FOR_Property
+        let reply = get_window_property(self.window, ATOM_WM_TRANSIENT_FOR, ATOM_WINDOW, 0, 1)?;
+        let Some(&transient_for) = reply.value::<Window>().first() else {
+            return Ok(None);
+        };
+
+        // 有些程序把 transient-for 设置为根窗口，表示属于整个程序组而不是某个窗口
+        let (conn, _) = get_xcb_connection_and_index()?;
+        let is_root = conn
+            .get_setup()
+            .roots()
+            .any(|screen| screen.root() == transient_for);
+
+        if transient_for.is_none() || is_root {
+            return Ok(None);
+        }
+
+        Ok(Some(transient_for.resource_id()))
+    }
+
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
         capture_window(self)
     }
+
+    pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
+        capture_window_region(self, x, y, width, height)
+    }
+
+    pub fn can_capture(&self) -> XCapResult<bool> {
+        let Ok((conn, _)) = get_xcb_connection_and_index() else {
+            return Ok(false);
+        };
+
+        // 窗口已经销毁或者没有映射到屏幕上时 GetImage 会失败
+        let get_window_attributes_cookie = conn.send_request(&GetWindowAttributes {
+            window: self.window,
+        });
+        let is_viewable = conn
+            .wait_for_reply(get_window_attributes_cookie)
+            .is_ok_and(|reply| reply.map_state() == MapState::Viewable);
+
+        Ok(is_viewable)
+    }
+
+    pub fn scroll(&self, clicks: u32) -> XCapResult<()> {
+        // Wayland 不允许普通客户端注入输入事件
+        if wayland_detect() {
+            return Err(XCapError::NotSupported);
+        }
+
+        let (conn, _) = get_xcb_connection_and_index()?;
+        let (_, _, width, height) = get_position_and_size(&self.window)?;
+
+        // 滚轮事件发送给指针下的窗口，先把指针移动到窗口中心
+        conn.send_request(&WarpPointer {
+            src_window: Window::none(),
+            dst_window: self.window,
+            src_x: 0,
+            src_y: 0,
+            src_width: 0,
+            src_height: 0,
+            dst_x: (width / 2) as i16,
+            dst_y: (height / 2) as i16,
+        });
+
+        // 滚轮向下是 5 号按键，XTest 的事件类型与核心协议一致：ButtonPress = 4，ButtonRelease = 5
+        for _ in 0..clicks {
+            for r#type in [4, 5] {
+                conn.send_and_check_request(&FakeInput {
+                    r#type,
+                    detail: 5,
+                    time: CURRENT_TIME,
+                    root: Window::none(),
+                    root_x: 0,
+                    root_y: 0,
+                    deviceid: 0,
+                })
+                .map_err(xcb::Error::Protocol)?;
+            }
+        }
+        conn.flush()?;
+
+        Ok(())
+    }
+}
+
+// 轮询 Damage 事件的间隔
+const DAMAGE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 使用 X11 Damage 扩展监听窗口内容的变化，使用独立的连接，避免和其他线程争抢事件
+pub(crate) struct ImplWindowDamage {
+    conn: Connection,
+    damage: Damage,
+}
+
+impl ImplWindowDamage {
+    pub fn new(window_id: u32) -> XCapResult<ImplWindowDamage> {
+        if wayland_detect() {
+            return Err(XCapError::NotSupported);
+        }
+
+        let (conn, _) = Connection::connect_with_extensions(
+            get_x_display_name().as_deref(),
+            &[Extension::Damage],
+            &[],
+        )?;
+
+        // 使用扩展之前必须先协商版本
+        let query_version_cookie = conn.send_request(&QueryVersion {
+            client_major_version: 1,
+            client_minor_version: 1,
+        });
+        conn.wait_for_reply(query_version_cookie)?;
+
+        let damage = conn.generate_id();
+        conn.send_and_check_request(&Create {
+            damage,
+            drawable: Drawable::Window(unsafe { Window::new(window_id) }),
+            level: ReportLevel::BoundingBox,
+        })
+        .map_err(xcb::Error::Protocol)?;
+
+        Ok(ImplWindowDamage { conn, damage })
+    }
+
+    /// 等待窗口内容变化，返回变化的区域，超时没有变化时返回空列表
+    pub fn wait(&mut self, timeout: Duration) -> XCapResult<Vec<Rect>> {
+        let deadline = Instant::now() + timeout;
+        let mut rects = Vec::new();
+
+        loop {
+            while let Some(event) = self.conn.poll_for_event()? {
+                if let xcb::Event::Damage(DamageEvent::Notify(notify)) = event {
+                    let area = notify.area();
+                    rects.push(Rect::new(
+                        area.x as i32,
+                        area.y as i32,
+                        area.width as u32,
+                        area.height as u32,
+                    ));
+                }
+            }
+
+            if !rects.is_empty() {
+                // 清空已经报告的区域，BoundingBox 模式下之后的变化才会再次通知
+                self.conn.send_request(&Subtract {
+                    damage: self.damage,
+                    repair: Region::none(),
+                    parts: Region::none(),
+                });
+                self.conn.flush()?;
+
+                return Ok(rects);
+            }
+
+            if Instant::now() >= deadline {
+                return Ok(rects);
+            }
+
+            thread::sleep(DAMAGE_POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for ImplWindowDamage {
+    fn drop(&mut self) {
+        self.conn.send_request(&Destroy {
+            damage: self.damage,
+        });
+        let _ = self.conn.flush();
+    }
 }
@@ -1,5 +1,10 @@
 mod capture;
+#[cfg(feature = "ddc")]
+mod ddc;
 mod display_info;
+pub mod external_encoder_surface;
+#[cfg(feature = "egl")]
+pub mod frame_ext;
 mod screencast_capture;
 pub mod utils;
 mod wayland_capture;
@@ -8,5 +13,61 @@ pub mod xorg_capture;
 mod xorg_video_recorder;
 
 pub mod impl_monitor;
+#[cfg(feature = "selector")]
+pub mod impl_selector;
 pub mod impl_video_recorder;
+#[cfg(feature = "virtual-camera")]
+pub mod impl_virtual_camera;
+#[cfg(feature = "virtual-display")]
+pub mod impl_virtual_display;
+pub mod impl_wake_lock;
 pub mod impl_window;
+
+use xcb::x::QueryPointer;
+
+use crate::{
+    Backend, BackendInfo,
+    error::{XCapError, XCapResult},
+    geometry::Point,
+};
+
+/// 关闭缓存的 ScreenCast 会话，XCB 和 D-Bus 连接保存在 lazy_static 中，无法释放，
+/// 录制线程随 VideoRecorder 一起停止
+pub(crate) fn shutdown() -> XCapResult<()> {
+    screencast_capture::close_screencast_session()
+}
+
+pub(crate) fn backend_info() -> BackendInfo {
+    let backend = if utils::wayland_detect() {
+        Backend::Wayland
+    } else {
+        Backend::Xorg
+    };
+
+    BackendInfo::new(backend, utils::is_xwayland())
+}
+
+/// 鼠标指针的全局坐标，X11 返回帧缓冲像素，除以缩放比例后与显示器坐标一致。
+/// Wayland 不允许客户端读取指针位置
+pub(crate) fn cursor_position() -> XCapResult<Point> {
+    if utils::wayland_detect() {
+        return Err(XCapError::NotSupported);
+    }
+
+    let (conn, index) = utils::get_xcb_connection_and_index()?;
+    let screen = conn
+        .get_setup()
+        .roots()
+        .nth(*index as usize)
+        .ok_or_else(|| XCapError::new("Not found screen"))?;
+    let reply = conn.wait_for_reply(conn.send_request(&QueryPointer {
+        window: screen.root(),
+    }))?;
+
+    let scale_factor = impl_monitor::get_scale_factor().unwrap_or(1.0) as f64;
+
+    Ok(Point::new(
+        (reply.root_x() as f64 / scale_factor).floor() as i32,
+        (reply.root_y() as f64 / scale_factor).floor() as i32,
+    ))
+}
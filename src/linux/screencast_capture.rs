@@ -3,15 +3,16 @@ use std::{
     io::Cursor,
     sync::{
         Arc, Condvar, Mutex,
-        atomic::{AtomicBool, AtomicU8, Ordering},
+        atomic::{AtomicU8, Ordering},
     },
-    thread,
-    time::Duration,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use image::RgbaImage;
 use lazy_static::lazy_static;
 use pipewire::{
+    channel,
     context::ContextRc,
     keys::{MEDIA_CATEGORY, MEDIA_ROLE, MEDIA_TYPE},
     main_loop::MainLoopRc,
@@ -28,27 +29,82 @@ use pipewire::{
     },
     stream::{StreamFlags, StreamRc},
 };
-use zbus::zvariant::Value;
+use zbus::zvariant::{OwnedObjectPath, Value};
 
-use crate::{XCapError, XCapResult};
+use crate::{Config, Stage, XCapError, XCapResult, conversion};
 
 use super::{
-    utils::get_zbus_connection,
+    impl_monitor::ImplMonitor,
+    utils::{get_zbus_connection, portal_response_error},
     wayland_video_recorder::ScreenCast,
 };
 
+// 检查空闲流的间隔
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+type LatestFrame = Arc<(Mutex<Option<RgbaImage>>, Condvar)>;
+
+/// 读取一个流的 PipeWire 线程
+struct StreamWorker {
+    quit_sender: channel::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl StreamWorker {
+    fn spawn(node_id: u32, latest_frame: LatestFrame) -> StreamWorker {
+        let (quit_sender, quit_receiver) = channel::channel();
+        let handle = thread::spawn(move || {
+            if let Err(e) = run_pipewire_capture(node_id, latest_frame, quit_receiver) {
+                log::error!("ScreenCast PipeWire thread for stream {node_id} failed: {e}");
+            }
+        });
+
+        StreamWorker {
+            quit_sender,
+            handle,
+        }
+    }
+
+    fn stop(self) {
+        // 主循环已经退出时发送会失败，此时线程也已经结束
+        let _ = self.quit_sender.send(());
+        let _ = self.handle.join();
+    }
+}
+
 struct StreamInfo {
+    node_id: u32,
     source_x: i32,
     source_y: i32,
     source_w: i32,
     source_h: i32,
-    latest_frame: Arc<(Mutex<Option<RgbaImage>>, Condvar)>,
+    latest_frame: LatestFrame,
+    // 截取这个显示器时才启动，空闲超时后停止，门户会话保持打开，重新连接不需要再次协商
+    worker: Option<StreamWorker>,
+    last_used: Instant,
 }
 
 struct ScreenCastCaptureInner {
+    session: OwnedObjectPath,
     streams: Vec<StreamInfo>,
 }
 
+impl ScreenCastCaptureInner {
+    fn close(self) {
+        for stream in self.streams {
+            if let Some(worker) = stream.worker {
+                worker.stop();
+            }
+        }
+
+        let result =
+            ScreenCast::new().and_then(|screen_cast| screen_cast.close_session(&self.session));
+        if let Err(e) = result {
+            log::debug!("ScreenCast: failed to close session: {e}");
+        }
+    }
+}
+
 // 0 = not initialized, 1 = active, 2 = permanently failed
 static SCREENCAST_STATE: AtomicU8 = AtomicU8::new(0);
 
@@ -56,7 +112,28 @@ lazy_static! {
     static ref SCREENCAST_INSTANCE: Mutex<Option<ScreenCastCaptureInner>> = Mutex::new(None);
 }
 
+/// 配置的门户输出（名称或者序列号）对应的显示器位置
+fn portal_output_position(output: &str) -> XCapResult<(i32, i32)> {
+    let monitor = ImplMonitor::all()?
+        .into_iter()
+        .find(|monitor| {
+            monitor.name().is_ok_and(|name| name == output)
+                || monitor
+                    .serial_number()
+                    .is_ok_and(|serial| !serial.is_empty() && serial == output)
+        })
+        .ok_or_else(|| XCapError::new(format!("ScreenCast: output {output} not found")))?;
+
+    Ok((monitor.x()?, monitor.y()?))
+}
+
 fn init_screencast() -> XCapResult<ScreenCastCaptureInner> {
+    let config = Config::get();
+    let output_position = config
+        .portal_output()
+        .map(portal_output_position)
+        .transpose()?;
+
     let screen_cast = ScreenCast::new()?;
     let session = screen_cast.create_session()?;
 
@@ -74,10 +151,25 @@ fn init_screencast() -> XCapResult<ScreenCastCaptureInner> {
     let mut options: HashMap<&str, Value> = HashMap::new();
     options.insert("handle_token", Value::from(&handle_token));
     options.insert("types", Value::from(1_u32));
-    options.insert("multiple", Value::from(true));
+    // 指定了输出时只请求一个源，支持预先配置的门户可以不弹出选择界面
+    options.insert("multiple", Value::from(output_position.is_none()));
     options.insert("persist_mode", Value::from(2_u32));
 
-    if let Some(token) = load_restore_token() {
+    // cursor_mode: 1 隐藏，2 嵌入画面，门户不支持嵌入时只能隐藏
+    let available_cursor_modes = proxy
+        .get_property::<u32>("AvailableCursorModes")
+        .unwrap_or(0);
+    if config.show_cursor() && available_cursor_modes & 2 != 0 {
+        options.insert("cursor_mode", Value::from(2_u32));
+    } else if available_cursor_modes & 1 != 0 {
+        options.insert("cursor_mode", Value::from(1_u32));
+    }
+
+    let restore_token = config
+        .portal_restore_token()
+        .map(str::to_string)
+        .or_else(load_restore_token);
+    if let Some(token) = restore_token {
         options.insert("restore_token", Value::from(token));
     }
 
@@ -89,11 +181,8 @@ fn init_screencast() -> XCapResult<ScreenCastCaptureInner> {
         let body = msg.body();
         let result: Result<(u32, zbus::zvariant::Value), _> = body.deserialize();
         if let Ok((code, _)) = result {
-            if code == 1 {
-                return Err(XCapError::new("ScreenCast: user cancelled source selection"));
-            }
             if code != 0 {
-                return Err(XCapError::new(format!("ScreenCast: SelectSources failed with code {code}")));
+                return Err(portal_response_error("ScreenCast: SelectSources failed", code));
             }
         }
     }
@@ -118,36 +207,80 @@ fn init_screencast() -> XCapResult<ScreenCastCaptureInner> {
         let (src_x, src_y) = stream_meta.position.unwrap_or((0, 0));
         let (src_w, src_h) = stream_meta.size.unwrap_or((0, 0));
 
-        let latest_frame: Arc<(Mutex<Option<RgbaImage>>, Condvar)> =
-            Arc::new((Mutex::new(None), Condvar::new()));
-        let initialized = Arc::new(AtomicBool::new(false));
-
-        let frame_ref = latest_frame.clone();
-        let init_ref = initialized.clone();
-        let sid = *stream_id;
-
-        thread::spawn(move || {
-            if let Err(e) = run_pipewire_capture(sid, frame_ref, init_ref) {
-                log::error!("ScreenCast PipeWire thread for stream {sid} failed: {e}");
-            }
-        });
+        // 只保留指定输出的流，门户没有返回位置时无法判断，只能保留
+        if let Some(position) = output_position
+            && stream_meta.position.is_some_and(|stream_position| stream_position != position)
+        {
+            continue;
+        }
 
         streams.push(StreamInfo {
+            node_id: *stream_id,
             source_x: src_x,
             source_y: src_y,
             source_w: src_w,
             source_h: src_h,
-            latest_frame,
+            latest_frame: Arc::new((Mutex::new(None), Condvar::new())),
+            worker: None,
+            last_used: Instant::now(),
         });
     }
 
-    Ok(ScreenCastCaptureInner { streams })
+    if streams.is_empty() {
+        return Err(XCapError::new(format!(
+            "ScreenCast: portal did not share output {}",
+            config.portal_output().unwrap_or_default()
+        )));
+    }
+
+    Ok(ScreenCastCaptureInner { session, streams })
+}
+
+/// 定期停止空闲超时的流，所有流都停止后关闭门户会话，下次截图时使用 restore_token 重新建立会话
+fn spawn_idle_reaper() {
+    thread::spawn(|| loop {
+        thread::sleep(IDLE_CHECK_INTERVAL);
+
+        let idle_timeout = Config::get().portal_idle_timeout();
+        let Ok(mut instance_guard) = SCREENCAST_INSTANCE.lock() else {
+            return;
+        };
+        // 会话已经被关闭，新的会话有自己的检查线程
+        let Some(inner) = instance_guard.as_mut() else {
+            return;
+        };
+
+        for stream in &mut inner.streams {
+            if stream.last_used.elapsed() >= idle_timeout
+                && let Some(worker) = stream.worker.take()
+            {
+                worker.stop();
+            }
+        }
+
+        if inner.streams.iter().all(|stream| stream.worker.is_none()) {
+            log::debug!("ScreenCast: closing idle session");
+            if let Some(inner) = instance_guard.take() {
+                inner.close();
+            }
+            return;
+        }
+    });
+}
+
+/// 关闭缓存的 ScreenCast 会话，下次截图时重新建立
+pub fn close_screencast_session() -> XCapResult<()> {
+    if let Some(inner) = SCREENCAST_INSTANCE.lock()?.take() {
+        inner.close();
+    }
+
+    Ok(())
 }
 
 fn run_pipewire_capture(
     stream_id: u32,
-    latest_frame: Arc<(Mutex<Option<RgbaImage>>, Condvar)>,
-    initialized: Arc<AtomicBool>,
+    latest_frame: LatestFrame,
+    quit_receiver: channel::Receiver<()>,
 ) -> XCapResult<()> {
     pipewire::init();
 
@@ -155,6 +288,11 @@ fn run_pipewire_capture(
     let context = ContextRc::new(&main_loop, None)?;
     let core = context.connect_rc(None)?;
 
+    let _attached = quit_receiver.attach(main_loop.loop_(), {
+        let main_loop = main_loop.clone();
+        move |_| main_loop.quit()
+    });
+
     let user_data = VideoInfoRaw::default();
 
     let stream = StreamRc::new(
@@ -228,11 +366,7 @@ fn run_pipewire_capture(
                         if frame_data.len() < expected_pixels * 4 {
                             return;
                         }
-                        let mut buf = frame_data[..expected_pixels * 4].to_vec();
-                        for chunk in buf.chunks_exact_mut(4) {
-                            chunk.swap(0, 2);
-                        }
-                        buf
+                        conversion::bgra_to_rgba(&frame_data[..expected_pixels * 4])
                     }
                     _ => {
                         log::error!("ScreenCast: unsupported format: {:?}", user_data.format());
@@ -244,7 +378,6 @@ fn run_pipewire_capture(
                     let (lock, cvar) = &*latest_frame;
                     if let Ok(mut guard) = lock.lock() {
                         *guard = Some(image);
-                        initialized.store(true, Ordering::Release);
                         cvar.notify_all();
                     }
                 }
@@ -339,6 +472,11 @@ fn find_matching_stream(streams: &[StreamInfo], x: i32, y: i32, width: i32, heig
     None
 }
 
+/// ScreenCast 门户没有永久失败（用户拒绝授权、门户不可用）
+pub fn is_screencast_available() -> bool {
+    SCREENCAST_STATE.load(Ordering::Relaxed) != 2
+}
+
 pub fn screencast_capture(x: i32, y: i32, width: i32, height: i32) -> XCapResult<RgbaImage> {
     // Fast path: permanently failed, don't retry
     if SCREENCAST_STATE.load(Ordering::Relaxed) == 2 {
@@ -355,10 +493,13 @@ pub fn screencast_capture(x: i32, y: i32, width: i32, height: i32) -> XCapResult
                 return Err(XCapError::new("ScreenCast: previously failed, not retrying"));
             }
             log::info!("Initializing ScreenCast capture session (one-time permission prompt)");
+            let started_at = Instant::now();
             match init_screencast() {
                 Ok(inner) => {
+                    Config::get().record_stage(Stage::StreamStart, started_at.elapsed());
                     *instance_guard = Some(inner);
                     SCREENCAST_STATE.store(1, Ordering::Relaxed);
+                    spawn_idle_reaper();
                 }
                 Err(e) => {
                     SCREENCAST_STATE.store(2, Ordering::Relaxed);
@@ -367,27 +508,41 @@ pub fn screencast_capture(x: i32, y: i32, width: i32, height: i32) -> XCapResult
             }
         }
 
-        let inner = instance_guard.as_ref().unwrap();
+        let inner = instance_guard
+            .as_mut()
+            .ok_or(XCapError::new("ScreenCast: session not started"))?;
 
         let stream_idx = find_matching_stream(&inner.streams, x, y, width, height)
             .ok_or(XCapError::new("ScreenCast: no stream covers the requested region"))?;
 
-        let stream = &inner.streams[stream_idx];
+        let stream = &mut inner.streams[stream_idx];
+        stream.last_used = Instant::now();
+        if stream.worker.as_ref().is_none_or(|worker| worker.handle.is_finished()) {
+            // 丢弃停止前的最后一帧，等待重新连接后的新画面
+            if let Ok(mut frame) = stream.latest_frame.0.lock() {
+                *frame = None;
+            }
+            stream.worker = Some(StreamWorker::spawn(stream.node_id, stream.latest_frame.clone()));
+        }
+
         (stream.latest_frame.clone(), stream.source_x, stream.source_y)
         // instance_guard drops here — other capture threads can proceed
     };
 
     // Wait for a frame on the matching stream (without holding instance lock)
+    let started_at = Instant::now();
     let full_image = {
         let (lock, cvar) = &*frame_arc;
 
         let guard = lock.lock()?;
         let result = cvar
-            .wait_timeout_while(guard, Duration::from_secs(5), |frame| frame.is_none())
+            .wait_timeout_while(guard, Config::get().portal_timeout(), |frame| {
+                frame.is_none()
+            })
             .map_err(|e| XCapError::new(format!("ScreenCast: condvar wait failed: {e}")))?;
 
         if result.1.timed_out() {
-            return Err(XCapError::new("ScreenCast: timed out waiting for first frame"));
+            return Err(XCapError::transient("ScreenCast: timed out waiting for first frame"));
         }
 
         // Clone the image and release the frame lock immediately so PipeWire can update
@@ -398,6 +553,7 @@ pub fn screencast_capture(x: i32, y: i32, width: i32, height: i32) -> XCapResult
             .clone()
         // MutexGuard drops here — PipeWire process callback unblocked
     };
+    Config::get().record_stage(Stage::FirstFrame, started_at.elapsed());
 
     // Crop using stream-relative coordinates (no locks held)
     let rel_x = x - source_x;
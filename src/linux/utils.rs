@@ -1,6 +1,10 @@
 use std::{
     env::{self, var_os},
     path::{Path, PathBuf},
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
 };
 
 use image::{RgbaImage, open};
@@ -11,7 +15,7 @@ use url::Url;
 use xcb::{
     ConnResult, Connection as XcbConnection, Xid,
     randr::{GetMonitors, MonitorInfoBuf, Output},
-    x::{Atom, InternAtom, ScreenBuf},
+    x::{Atom, InternAtom, QueryExtension, ScreenBuf},
 };
 use zbus::{
     Result as ZBusResult,
@@ -19,27 +23,81 @@ use zbus::{
     zvariant::Type,
 };
 
-use crate::{XCapError, error::XCapResult};
+use crate::{
+    Backend, XCapError, capture_report,
+    error::{PlatformError, XCapResult},
+};
+
+// 虚拟显示器的 X display，需要在第一次连接 X server 之前设置
+static X_DISPLAY_NAME: OnceLock<String> = OnceLock::new();
+// 共享的 XCB 连接是否已经创建
+static XCB_CONNECTED: AtomicBool = AtomicBool::new(false);
 
 lazy_static! {
     static ref XCB_CONNECTION_AND_INDEX: ConnResult<(XcbConnection, i32)> = {
-        let display_name = env::var("DISPLAY").unwrap_or("DISPLAY:1".to_string());
+        XCB_CONNECTED.store(true, Ordering::SeqCst);
+        let display_name = get_x_display_name().unwrap_or("DISPLAY:1".to_string());
         XcbConnection::connect(Some(display_name.as_str()))
     };
     static ref ZBUS_CONNECTION: ZBusResult<ZBusConnection> = ZBusConnection::session();
+    // XWayland 会注册 XWAYLAND 扩展，X server 在进程运行期间不会变化
+    static ref XWAYLAND: bool = get_xcb_connection_and_index().is_ok_and(|(conn, _)| {
+        let cookie = conn.send_request(&QueryExtension { name: b"XWAYLAND" });
+        conn.wait_for_reply(cookie).is_ok_and(|reply| reply.present())
+    });
 }
 
 pub fn get_xcb_connection_and_index() -> XCapResult<&'static (XcbConnection, i32)> {
     XCB_CONNECTION_AND_INDEX.as_ref().map_err(XCapError::new)
 }
 
+/// 要连接的 X display，创建了虚拟显示器时为虚拟显示器，否则为 DISPLAY 环境变量
+pub fn get_x_display_name() -> Option<String> {
+    X_DISPLAY_NAME
+        .get()
+        .cloned()
+        .or_else(|| env::var("DISPLAY").ok())
+}
+
+/// 让之后的 X11 连接都使用 display_name，只能在第一次连接 X server 之前设置一次
+#[cfg(feature = "virtual-display")]
+pub fn set_x_display_name(display_name: String) -> XCapResult<()> {
+    if XCB_CONNECTED.load(Ordering::SeqCst) {
+        return Err(XCapError::new(
+            "Already connected to the X server, create the virtual display first",
+        ));
+    }
+
+    X_DISPLAY_NAME
+        .set(display_name)
+        .map_err(|_| XCapError::new("Only one virtual display can be created per process"))
+}
+
+/// 共享的 XCB 连接是否已经创建
+#[cfg(feature = "virtual-display")]
+pub fn is_xcb_connected() -> bool {
+    XCB_CONNECTED.load(Ordering::SeqCst)
+}
+
 pub fn get_zbus_connection() -> XCapResult<&'static ZBusConnection> {
     ZBUS_CONNECTION
         .as_ref()
         .map_err(|err| XCapError::ZbusError(err.clone()))
 }
 
+/// X server 是否为 XWayland
+pub fn is_xwayland() -> bool {
+    *XWAYLAND
+}
+
 pub fn wayland_detect() -> bool {
+    // 截图时优先使用 CaptureConfig 要求的后端
+    match capture_report::requested_backend() {
+        Backend::Wayland => return true,
+        Backend::Xorg => return false,
+        _ => {}
+    }
+
     let xdg_session_type = var_os("XDG_SESSION_TYPE")
         .unwrap_or_default()
         .to_string_lossy()
@@ -50,7 +108,44 @@ pub fn wayland_detect() -> bool {
         .to_string_lossy()
         .to_string();
 
-    xdg_session_type.eq("wayland") || wayland_display.to_lowercase().contains("wayland")
+    // 环境变量被清除时（例如通过 sudo 或者 systemd 启动）也可能运行在 XWayland 上，
+    // 此时 X11 只能截取到 XWayland 窗口，原生 Wayland 窗口都是黑色
+    xdg_session_type.eq("wayland")
+        || wayland_display.to_lowercase().contains("wayland")
+        || is_xwayland()
+}
+
+/// 屏保正在运行或者会话已锁定时返回 true，桌面环境没有实现 org.freedesktop.ScreenSaver 时返回 false
+pub fn is_display_idle() -> bool {
+    let is_active = || -> XCapResult<bool> {
+        let proxy = Proxy::new(
+            get_zbus_connection()?,
+            "org.freedesktop.ScreenSaver",
+            "/org/freedesktop/ScreenSaver",
+            "org.freedesktop.ScreenSaver",
+        )?;
+
+        Ok(proxy.call("GetActive", &())?)
+    };
+
+    is_active().unwrap_or(false)
+}
+
+/// 通过 UPower 判断是否使用电池供电，UPower 不可用时返回 false
+pub fn is_on_battery() -> bool {
+    let on_battery = || -> XCapResult<bool> {
+        let conn = ZBusConnection::system()?;
+        let proxy = Proxy::new(
+            &conn,
+            "org.freedesktop.UPower",
+            "/org/freedesktop/UPower",
+            "org.freedesktop.UPower",
+        )?;
+
+        Ok(proxy.get_property("OnBattery")?)
+    };
+
+    on_battery().unwrap_or(false)
 }
 
 pub fn get_current_screen_buf() -> XCapResult<ScreenBuf> {
@@ -179,9 +274,18 @@ where
         return Ok(body);
     }
 
-    if code == 1 {
-        return Err(XCapError::new("Z-Bus canceled"));
-    }
+    Err(portal_response_error("Portal request failed", code))
+}
+
+/// XDG 桌面门户 Request 的非零响应码：1 表示用户取消，2 表示其他原因结束
+pub(super) fn portal_response_error(message: &str, code: u32) -> XCapError {
+    let reason = match code {
+        1 => "cancelled by the user",
+        _ => "ended in some other way",
+    };
 
-    Err(XCapError::new(format!("Response code is {code}")))
+    XCapError::platform(
+        message,
+        PlatformError::new("org.freedesktop.portal.Request", code as i64, reason),
+    )
 }
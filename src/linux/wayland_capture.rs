@@ -7,9 +7,9 @@ use image::RgbaImage;
 use scopeguard::defer;
 use zbus::blocking::{Connection, Proxy};
 
-use crate::error::XCapResult;
+use crate::error::{XCapError, XCapResult};
 
-use super::screencast_capture::screencast_capture;
+use super::screencast_capture::{is_screencast_available, screencast_capture};
 use super::utils::{get_zbus_connection, png_to_rgba_image};
 
 static GNOME_SHELL_AVAILABLE: AtomicBool = AtomicBool::new(true);
@@ -79,11 +79,18 @@ fn wlroots_screenshot(
         rgba_image.height(),
         rgba_image.to_rgba8().into_vec(),
     )
-    .expect("Conversion of PNG -> Raw -> PNG does not fail");
+    .ok_or(XCapError::new("Convert libwayshot image failed"))?;
 
     Ok(image)
 }
 
+/// 是否有可用的 Wayland 截图方式，按 wayland_capture 的尝试顺序检查
+pub fn is_wayland_capture_available() -> bool {
+    GNOME_SHELL_AVAILABLE.load(Ordering::Relaxed)
+        || is_screencast_available()
+        || libwayshot_xcap::WayshotConnection::new().is_ok()
+}
+
 pub fn wayland_capture(x: i32, y: i32, width: i32, height: i32) -> XCapResult<RgbaImage> {
     // Try GNOME Shell Screenshot first (only if not already known to be unavailable)
     if GNOME_SHELL_AVAILABLE.load(Ordering::Relaxed) {
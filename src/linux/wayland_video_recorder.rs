@@ -1,13 +1,16 @@
 use std::{
+    cell::{Cell, RefCell},
     collections::HashMap,
     fmt,
     io::Cursor,
+    rc::Rc,
     sync::{
-        Arc,
+        Arc, Mutex,
         atomic::{AtomicBool, Ordering},
         mpsc::{self, Receiver, Sender},
     },
-    thread,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use pipewire::{
@@ -26,18 +29,32 @@ use pipewire::{
         pod::{self, Pod, serialize::PodSerializer},
         utils::{Direction, Fraction, Rectangle, SpaTypes},
     },
-    stream::{StreamRc, StreamFlags},
+    stream::{StreamFlags, StreamRc, StreamState},
 };
 use zbus::{
     blocking::Proxy,
     zvariant::{DeserializeDict, OwnedFd, OwnedObjectPath, Type, Value},
 };
 
-use crate::{XCapError, XCapResult, video_recorder::Frame};
+use crate::{
+    Config, FramePacing, Monitor, RecorderConfig, RecorderUpdate, RecoveryPolicy, XCapError,
+    XCapResult, conversion,
+    redaction::Redaction,
+    video_recorder::{
+        ChangeDetector, Frame, FrameHook, FrameThrottle, IdleGate, LiveConfig, RecorderEvent,
+        RecorderHealth, join_worker, scale_frame, vsync_frequency,
+    },
+    window_crop::WindowCrop,
+};
 
+#[cfg(feature = "egl")]
+use super::frame_ext::DmaBuf;
 use super::{
     impl_monitor::ImplMonitor,
-    utils::{get_zbus_connection, get_zbus_portal_request, wait_zbus_response},
+    utils::{
+        get_zbus_connection, get_zbus_portal_request, is_display_idle, is_on_battery,
+        wait_zbus_response,
+    },
 };
 
 #[allow(dead_code)]
@@ -117,7 +134,7 @@ impl ScreenCast<'_> {
         Ok(session)
     }
 
-    pub fn select_sources(&self, session: &OwnedObjectPath) -> XCapResult<()> {
+    pub fn select_sources(&self, session: &OwnedObjectPath, show_cursor: bool) -> XCapResult<()> {
         let conn = get_zbus_connection()?;
 
         let mut options = HashMap::new();
@@ -129,6 +146,21 @@ impl ScreenCast<'_> {
         options.insert("types", Value::from(1_u32));
         options.insert("multiple", Value::from(false));
 
+        // cursor_mode: 1 隐藏，2 嵌入画面，只能在创建会话时选择
+        let available_cursor_modes = self
+            .proxy
+            .get_property::<u32>("AvailableCursorModes")
+            .unwrap_or(0);
+        if show_cursor && available_cursor_modes & 2 != 0 {
+            options.insert("cursor_mode", Value::from(2_u32));
+        } else if available_cursor_modes & 1 != 0 {
+            options.insert("cursor_mode", Value::from(1_u32));
+        }
+
+        if let Some(token) = Config::get().portal_restore_token() {
+            options.insert("restore_token", Value::from(token.to_string()));
+        }
+
         self.proxy
             .call_method("SelectSources", &(session, options))?;
 
@@ -152,6 +184,20 @@ impl ScreenCast<'_> {
         wait_zbus_response(&portal_request)
     }
 
+    /// https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Session.html
+    pub fn close_session(&self, session: &OwnedObjectPath) -> XCapResult<()> {
+        let conn = get_zbus_connection()?;
+        let proxy = Proxy::new(
+            conn,
+            "org.freedesktop.portal.Desktop",
+            session,
+            "org.freedesktop.portal.Session",
+        )?;
+        proxy.call_method("Close", &())?;
+
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn open_pipe_wire_remote(&self, session: &OwnedObjectPath) -> XCapResult<OwnedFd> {
         let options: HashMap<&str, Value<'_>> = HashMap::new();
@@ -161,13 +207,132 @@ impl ScreenCast<'_> {
     }
 }
 
+// 发送给 PipeWire 主循环的控制消息
+#[derive(Debug, Clone, Copy)]
+enum StreamControl {
+    Active(bool),
+    // 设置被修改，重新协商帧率
+    Reconfigure,
+    Shutdown,
+}
+
+/// 协商的帧率 (分子, 分母)，None 表示不限制
+fn negotiated_framerate(
+    frame_interval: Option<Duration>,
+    vsync_framerate: Option<(u32, u32)>,
+) -> Option<(u32, u32)> {
+    frame_interval
+        .map(|frame_interval| (1000, frame_interval.as_millis().max(1) as u32))
+        .or(vsync_framerate)
+}
+
+/// 流支持的格式参数，按优先级排列
+fn format_params(framerate: Option<(u32, u32)>) -> XCapResult<Vec<Vec<u8>>> {
+    let (default_framerate, max_framerate) = match framerate {
+        Some(framerate) => (framerate, framerate),
+        None => ((24, 1), (1000, 1)),
+    };
+
+    let obj = pod::object!(
+        SpaTypes::ObjectParamFormat,
+        ParamType::EnumFormat,
+        pod::property!(FormatProperties::MediaType, Id, MediaType::Video),
+        pod::property!(FormatProperties::MediaSubtype, Id, MediaSubtype::Raw),
+        pod::property!(
+            FormatProperties::VideoFormat,
+            Choice,
+            Enum,
+            Id,
+            VideoFormat::RGB,
+            VideoFormat::RGBA,
+            VideoFormat::RGBx,
+            VideoFormat::BGRx,
+            // VideoFormat::YUY2,
+            // VideoFormat::I420,
+        ),
+        pod::property!(
+            FormatProperties::VideoSize,
+            Choice,
+            Range,
+            Rectangle,
+            Rectangle {
+                width: 128,
+                height: 128
+            },
+            Rectangle {
+                width: 1,
+                height: 1
+            },
+            Rectangle {
+                width: 4096,
+                height: 4096
+            }
+        ),
+        pod::property!(
+            FormatProperties::VideoFramerate,
+            Choice,
+            Range,
+            Fraction,
+            Fraction {
+                num: default_framerate.0,
+                denom: default_framerate.1
+            },
+            Fraction { num: 0, denom: 1 },
+            Fraction {
+                num: max_framerate.0,
+                denom: max_framerate.1
+            }
+        ),
+    );
+    let serialize = |obj: pod::Object| -> XCapResult<Vec<u8>> {
+        let values = PodSerializer::serialize(Cursor::new(Vec::new()), &pod::Value::Object(obj))
+            .map_err(XCapError::new)?
+            .0
+            .into_inner();
+        Ok(values)
+    };
+
+    let mut params = Vec::new();
+    // 优先使用线性布局的 DMA-BUF，GL 可以直接导入，CPU 也可以映射读取，合成器不支持时使用共享内存
+    #[cfg(feature = "egl")]
+    {
+        let mut obj = obj.clone();
+        obj.properties.push(pod::Property {
+            key: FormatProperties::VideoModifier.as_raw(),
+            flags: pod::PropertyFlags::MANDATORY,
+            value: pod::Value::Long(super::frame_ext::DRM_FORMAT_MOD_LINEAR as i64),
+        });
+        params.push(serialize(obj)?);
+    }
+    params.push(serialize(obj)?);
+
+    Ok(params)
+}
+
+fn as_pods(params: &[Vec<u8>]) -> XCapResult<Vec<&Pod>> {
+    params
+        .iter()
+        .map(|values| Pod::from_bytes(values).ok_or(XCapError::new("Failed to create Pod")))
+        .collect()
+}
+
 #[derive(Clone)]
 pub struct WaylandVideoRecorder {
     #[allow(dead_code)]
     monitor: ImplMonitor,
+    session: OwnedObjectPath,
+    pause_when_idle: bool,
+    low_power: bool,
+    live_config: Arc<LiveConfig>,
+    window_crop: Option<Arc<WindowCrop>>,
+    redaction: Option<Redaction>,
+    vsync_framerate: Option<(u32, u32)>,
+    frame_hook: Option<FrameHook>,
     sender: Sender<Frame>,
     is_running: Arc<AtomicBool>,
-    active_sender: channel::Sender<bool>,
+    control_sender: channel::Sender<StreamControl>,
+    health: Arc<RecorderHealth>,
+    worker: Arc<Mutex<Option<JoinHandle<XCapResult<()>>>>>,
 }
 
 impl fmt::Debug for WaylandVideoRecorder {
@@ -188,13 +353,17 @@ struct ListenerUserData {
 }
 
 impl WaylandVideoRecorder {
-    pub fn new(monitor: ImplMonitor) -> XCapResult<(Self, Receiver<Frame>)> {
+    pub fn new(
+        monitor: ImplMonitor,
+        config: &RecorderConfig,
+        health: Arc<RecorderHealth>,
+    ) -> XCapResult<(Self, Receiver<Frame>)> {
         let (sender, receiver) = mpsc::channel();
-        let (active_sender, active_receiver) = channel::channel();
+        let (control_sender, control_receiver) = channel::channel();
 
         let screen_cast = ScreenCast::new()?;
         let session = screen_cast.create_session()?;
-        screen_cast.select_sources(&session)?;
+        screen_cast.select_sources(&session, config.shows_cursor())?;
         let response = screen_cast.start(&session)?;
 
         // 获取流节点ID
@@ -205,14 +374,39 @@ impl WaylandVideoRecorder {
             .ok_or(XCapError::new("Stream ID not found"))?
             .0;
 
+        // 按 vsync 输出时，让 PipeWire 按显示器刷新率协商帧率，由合成器的帧时钟驱动，
+        // 可变刷新率的显示器按最高刷新率协商，刷新间隔变长时合成器只是更晚送出帧
+        // 低功耗模式和限制帧率时直接协商较低的帧率，合成器不会产生多余的帧
+        let low_power = config.power_profile.is_low_power(is_on_battery);
+        let vsync_framerate = match config.pacing {
+            FramePacing::Vsync => {
+                let frequency = vsync_frequency(&Monitor::new(monitor.clone()))?;
+                Some((frequency.round().max(1.0) as u32, 1))
+            }
+            FramePacing::FreeRunning => None,
+        };
+
+        let window_crop = WindowCrop::from_config(config, &Monitor::new(monitor.clone()))?;
+        let redaction = Redaction::from_config(config, &Monitor::new(monitor.clone()))?;
+
         let recorder = Self {
             monitor,
+            session,
+            pause_when_idle: config.pause_when_idle,
+            low_power,
+            live_config: Arc::new(LiveConfig::new(config.clone())),
+            window_crop: window_crop.map(Arc::new),
+            redaction,
+            vsync_framerate,
+            frame_hook: config.frame_hook.clone(),
             sender,
             is_running: Arc::new(AtomicBool::new(false)),
-            active_sender,
+            control_sender,
+            health,
+            worker: Arc::default(),
         };
 
-        recorder.pipewire_capturer(stream_id, active_receiver)?;
+        recorder.pipewire_capturer(stream_id, config.recovery, control_receiver)?;
 
         Ok((recorder, receiver))
     }
@@ -220,209 +414,342 @@ impl WaylandVideoRecorder {
     pub fn pipewire_capturer(
         &self,
         stream_id: u32,
-        active_receiver: channel::Receiver<bool>,
+        recovery: RecoveryPolicy,
+        control_receiver: channel::Receiver<StreamControl>,
     ) -> XCapResult<()> {
         let sender = self.sender.clone();
         let is_running = self.is_running.clone();
-
-        thread::spawn(move || {
+        let health = self.health.clone();
+        let pause_when_idle = self.pause_when_idle;
+        let low_power = self.low_power;
+        let live_config = self.live_config.clone();
+        let window_crop = self.window_crop.clone();
+        let redaction = self.redaction.clone();
+        let vsync_framerate = self.vsync_framerate;
+        let frame_hook = self.frame_hook.clone();
+
+        let worker = thread::spawn(move || {
             pipewire::init();
 
+            let idle_gate = Rc::new(IdleGate::new(pause_when_idle));
+
             let main_loop = MainLoopRc::new(None)?;
             let context = ContextRc::new(&main_loop, None)?;
             let core = context.connect_rc(None)?;
 
-            let user_data = ListenerUserData {
-                format: Default::default(),
-            };
-
-            let stream = StreamRc::new(
-                core,
-                "XCap",
-                properties::properties! {
-                    *MEDIA_TYPE => "Video",
-                    *MEDIA_CATEGORY => "Capture",
-                    *MEDIA_ROLE => "Screen",
-                },
-            )?;
-
-            let _listener = stream
-                .add_local_listener_with_user_data(user_data)
-                .param_changed(|_, user_data, id, param| {
-                    let Some(param) = param else {
+            // Used to pause/resume the stream
+            let current_stream: Rc<RefCell<Option<StreamRc>>> = Rc::default();
+            let is_shutdown = Rc::new(Cell::new(false));
+            let _attached = control_receiver.attach(main_loop.loop_(), {
+                let current_stream = current_stream.clone();
+                let main_loop = main_loop.clone();
+                let is_shutdown = is_shutdown.clone();
+                let live_config = live_config.clone();
+                move |control| {
+                    let current_stream = current_stream.borrow();
+                    let Some(stream) = current_stream.as_ref() else {
                         return;
                     };
 
-                    if id != ParamType::Format.as_raw() {
-                        return;
+                    match control {
+                        StreamControl::Active(active) => {
+                            if let Err(e) = stream.set_active(active) {
+                                log::error!("Failed to set stream active={active}: {e:?}");
+                            }
+                            if !active {
+                                if let Err(e) = stream.flush(true) {
+                                    log::error!("Failed to flush: {e:?}");
+                                }
+                            }
+                        }
+                        StreamControl::Reconfigure => {
+                            // 更新支持的格式后 PipeWire 会重新协商，不需要重建流
+                            let framerate = live_config.get().map(|(_, config)| {
+                                negotiated_framerate(
+                                    config.frame_interval(low_power),
+                                    vsync_framerate,
+                                )
+                            });
+                            let result = framerate.and_then(format_params).and_then(|params| {
+                                stream.update_params(&mut as_pods(&params)?)?;
+                                Ok(())
+                            });
+                            if let Err(e) = result {
+                                log::error!("Failed to update stream params: {e:?}");
+                            }
+                        }
+                        StreamControl::Shutdown => {
+                            // 处理完已经排队的缓冲区后断开流，再退出主循环
+                            if let Err(e) = stream.flush(true) {
+                                log::error!("Failed to flush: {e:?}");
+                            }
+                            if let Err(e) = stream.disconnect() {
+                                log::error!("Failed to disconnect stream: {e:?}");
+                            }
+                            is_shutdown.set(true);
+                            main_loop.quit();
+                        }
                     }
+                }
+            });
 
-                    let (media_type, media_subtype) = match format_utils::parse_format(param) {
-                        Ok(v) => v,
-                        Err(err) => {
-                            log::error!("Failed to parse format: {err:?}");
-                            return;
+            let stream_failed = Rc::new(Cell::new(false));
+
+            loop {
+                let user_data = ListenerUserData {
+                    format: Default::default(),
+                };
+
+                let stream = StreamRc::new(
+                    core.clone(),
+                    "XCap",
+                    properties::properties! {
+                        *MEDIA_TYPE => "Video",
+                        *MEDIA_CATEGORY => "Capture",
+                        *MEDIA_ROLE => "Screen",
+                    },
+                )?;
+
+                let state_main_loop = main_loop.clone();
+                let state_stream_failed = stream_failed.clone();
+                let state_health = health.clone();
+                let param_health = health.clone();
+                let sender = sender.clone();
+                let is_running = is_running.clone();
+                let process_health = health.clone();
+                let idle_gate = idle_gate.clone();
+                let frame_hook = frame_hook.clone();
+                let mut change_detector = ChangeDetector::default();
+                let (mut version, config) = live_config.get()?;
+                let frame_interval = config.frame_interval(low_power);
+                let params = format_params(negotiated_framerate(frame_interval, vsync_framerate))?;
+                // 合成器不一定遵守协商的帧率，多出的帧在复制之前丢弃
+                let mut frame_throttle = frame_interval.map(FrameThrottle::new);
+                let mut scale = config.scale;
+                let process_live_config = live_config.clone();
+                let window_crop = window_crop.clone();
+                let redaction = redaction.clone();
+
+                let _listener = stream
+                    .add_local_listener_with_user_data(user_data)
+                    .state_changed(move |_, _, _, state| {
+                        if let StreamState::Error(err) = state {
+                            state_health.emit(RecorderEvent::Error(err));
+                            // 退出主循环，由外层决定是否重建流
+                            state_stream_failed.set(true);
+                            state_main_loop.quit();
                         }
-                    };
+                    })
+                    .param_changed(move |_, user_data, id, param| {
+                        let Some(param) = param else {
+                            return;
+                        };
 
-                    if media_type != MediaType::Video || media_subtype != MediaSubtype::Raw {
-                        return;
-                    }
+                        if id != ParamType::Format.as_raw() {
+                            return;
+                        }
 
-                    if let Err(err) = user_data.format.parse(param) {
-                        log::error!("Failed to parse format: {err:?}");
-                    }
-                })
-                .process(move |stream, user_data| {
-                    let state = is_running.load(Ordering::Relaxed);
-                    match stream.dequeue_buffer() {
-                        None => log::info!("stream.dequeue_buffer() returned None"),
-                        Some(mut buffer) => {
-                            let datas = buffer.datas_mut();
-                            if datas.is_empty() {
+                        let (media_type, media_subtype) = match format_utils::parse_format(param) {
+                            Ok(v) => v,
+                            Err(err) => {
+                                log::error!("Failed to parse format: {err:?}");
                                 return;
                             }
-                            let size = user_data.format.size();
-                            if let Some(frame_data) = datas[0].data() {
-                                let buffer = match user_data.format.format() {
-                                    VideoFormat::RGB => {
-                                        let mut buf =
-                                            vec![0; (size.width * size.height * 4) as usize];
-                                        for (src, dst) in
-                                            frame_data.chunks_exact(3).zip(buf.chunks_exact_mut(4))
-                                        {
-                                            dst[0] = src[0];
-                                            dst[1] = src[1];
-                                            dst[2] = src[2];
-                                            dst[3] = 255;
-                                        }
+                        };
+
+                        if media_type != MediaType::Video || media_subtype != MediaSubtype::Raw {
+                            return;
+                        }
+
+                        let previous_size = user_data.format.size();
+                        if let Err(err) = user_data.format.parse(param) {
+                            log::error!("Failed to parse format: {err:?}");
+                        }
 
-                                        buf
+                        // 重新协商出不同的尺寸说明显示器分辨率发生了变化
+                        let size = user_data.format.size();
+                        if previous_size.width != 0
+                            && (previous_size.width != size.width
+                                || previous_size.height != size.height)
+                        {
+                            param_health.emit(RecorderEvent::DisplayReconfigured);
+                        }
+                    })
+                    .process(move |stream, user_data| {
+                        let state = is_running.load(Ordering::Relaxed);
+                        match stream.dequeue_buffer() {
+                            None => log::info!("stream.dequeue_buffer() returned None"),
+                            Some(mut buffer) => {
+                                let datas = buffer.datas_mut();
+                                if datas.is_empty() {
+                                    return;
+                                }
+                                // 屏保运行或锁屏时丢弃合成器发来的帧
+                                if idle_gate.poll(is_display_idle, &process_health) {
+                                    return;
+                                }
+                                // 设置被修改后从这一帧开始生效
+                                if let Some(config) = process_live_config.poll(&mut version) {
+                                    frame_throttle =
+                                        config.frame_interval(low_power).map(FrameThrottle::new);
+                                    scale = config.scale;
+                                }
+                                let timestamp = Instant::now();
+                                if let Some(frame_throttle) = frame_throttle.as_mut()
+                                    && !frame_throttle.accept(timestamp)
+                                {
+                                    return;
+                                }
+                                let size = user_data.format.size();
+                                // 合成器可能在每行末尾填充字节
+                                let chunk_stride = datas[0].chunk().stride();
+                                let stride = if chunk_stride > 0 {
+                                    chunk_stride as usize
+                                } else {
+                                    size.width as usize * 4
+                                };
+                                // 原始缓冲区中是没有遮挡、没有裁剪的画面，设置了 redactor 或跟随窗口时不附带
+                                #[cfg(feature = "egl")]
+                                let dma_buf = match (&redaction, &window_crop) {
+                                    (None, None) => DmaBuf::new(&datas[0], user_data.format),
+                                    _ => None,
+                                };
+                                if let Some(frame_data) = datas[0].data() {
+                                    let (stride, buffer) = match user_data.format.format() {
+                                        VideoFormat::RGB => {
+                                            let mut buf =
+                                                vec![0; (size.width * size.height * 4) as usize];
+                                            for (src, dst) in frame_data
+                                                .chunks_exact(3)
+                                                .zip(buf.chunks_exact_mut(4))
+                                            {
+                                                dst[0] = src[0];
+                                                dst[1] = src[1];
+                                                dst[2] = src[2];
+                                                dst[3] = 255;
+                                            }
+
+                                            (size.width as usize * 4, buf)
+                                        }
+                                        VideoFormat::RGBA => (stride, frame_data.to_vec()),
+                                        VideoFormat::RGBx => (stride, frame_data.to_vec()),
+                                        VideoFormat::BGRx => {
+                                            (stride, conversion::bgra_to_rgba(frame_data))
+                                        }
+                                        _ => {
+                                            log::error!(
+                                                "Unsupported format: {:?}",
+                                                user_data.format.format()
+                                            );
+                                            return;
+                                        }
+                                    };
+
+                                    let mut frame = Frame::with_stride(
+                                        size.width,
+                                        size.height,
+                                        stride,
+                                        buffer,
+                                        timestamp,
+                                    );
+                                    #[cfg(feature = "egl")]
+                                    {
+                                        frame.dma_buf = dma_buf;
                                     }
-                                    VideoFormat::RGBA => frame_data.to_vec(),
-                                    VideoFormat::RGBx => frame_data.to_vec(),
-                                    VideoFormat::BGRx => {
-                                        let mut buf = frame_data.to_vec();
-                                        for src in buf.chunks_exact_mut(4) {
-                                            src.swap(0, 2);
+                                    if let Some(redaction) = &redaction {
+                                        redaction.apply(&mut frame);
+                                    }
+                                    // 跟随的窗口不在显示器上时不发送
+                                    if let Some(window_crop) = &window_crop {
+                                        match window_crop.crop(&frame) {
+                                            Some(cropped) => frame = cropped,
+                                            None => return,
                                         }
-
-                                        buf
                                     }
-                                    _ => {
-                                        log::error!(
-                                            "Unsupported format: {:?}",
-                                            user_data.format.format()
-                                        );
+
+                                    // 低功耗模式下画面没有变化时不发送
+                                    if low_power && !change_detector.is_changed(frame.data()) {
                                         return;
                                     }
-                                };
 
-                                if state {
-                                    let _ =
-                                        sender.send(Frame::new(size.width, size.height, buffer));
+                                    if state {
+                                        if scale < 1.0 {
+                                            frame = scale_frame(frame, scale);
+                                        }
+                                        if let Some(frame_hook) = &frame_hook {
+                                            frame_hook.apply(&mut frame);
+                                        }
+                                        process_live_config
+                                            .emit_marker(frame.timestamp(), &process_health);
+                                        process_health.deliver(|| sender.send(frame).is_ok());
+                                    }
                                 }
                             }
                         }
-                    }
-                })
-                .register()?;
-
-            let obj = pod::object!(
-                SpaTypes::ObjectParamFormat,
-                ParamType::EnumFormat,
-                pod::property!(FormatProperties::MediaType, Id, MediaType::Video),
-                pod::property!(FormatProperties::MediaSubtype, Id, MediaSubtype::Raw),
-                pod::property!(
-                    FormatProperties::VideoFormat,
-                    Choice,
-                    Enum,
-                    Id,
-                    VideoFormat::RGB,
-                    VideoFormat::RGBA,
-                    VideoFormat::RGBx,
-                    VideoFormat::BGRx,
-                    // VideoFormat::YUY2,
-                    // VideoFormat::I420,
-                ),
-                pod::property!(
-                    FormatProperties::VideoSize,
-                    Choice,
-                    Range,
-                    Rectangle,
-                    Rectangle {
-                        width: 128,
-                        height: 128
-                    },
-                    Rectangle {
-                        width: 1,
-                        height: 1
-                    },
-                    Rectangle {
-                        width: 4096,
-                        height: 4096
-                    }
-                ),
-                pod::property!(
-                    FormatProperties::VideoFramerate,
-                    Choice,
-                    Range,
-                    Fraction,
-                    Fraction { num: 24, denom: 1 },
-                    Fraction { num: 0, denom: 1 },
-                    Fraction {
-                        num: 1000,
-                        denom: 1
-                    }
-                ),
-            );
-            let values =
-                PodSerializer::serialize(Cursor::new(Vec::new()), &pod::Value::Object(obj))
-                    .map_err(XCapError::new)?
-                    .0
-                    .into_inner();
-
-            let mut params =
-                [Pod::from_bytes(&values).ok_or(XCapError::new("Failed to create Pod"))?];
-
-            stream.connect(
-                Direction::Input,
-                Some(stream_id),
-                StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS,
-                &mut params,
-            )?;
-
-            // Used to pause/resume the stream
-            let _attached = active_receiver.attach(main_loop.loop_(), {
-                move |active| {
-                    if let Err(e) = stream.set_active(active) {
-                        log::error!("Failed to set stream active={active}: {e:?}");
-                    }
-                    if !active {
-                        if let Err(e) = stream.flush(true) {
-                            log::error!("Failed to flush: {e:?}");
-                        }
-                    }
+                    })
+                    .register()?;
+
+                stream.connect(
+                    Direction::Input,
+                    Some(stream_id),
+                    StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS,
+                    &mut as_pods(&params)?,
+                )?;
+                *current_stream.borrow_mut() = Some(stream);
+
+                main_loop.run();
+
+                current_stream.borrow_mut().take();
+
+                if is_shutdown.get()
+                    || !stream_failed.replace(false)
+                    || recovery == RecoveryPolicy::Stop
+                {
+                    break;
                 }
-            });
 
-            main_loop.run();
+                // 重新连接同一个 PipeWire 节点
+                log::warn!("PipeWire stream failed, restarting");
+                health.emit(RecorderEvent::StreamRestarted);
+            }
 
             Result::<(), XCapError>::Ok(())
         });
+        *self.worker.lock()? = Some(worker);
 
         Ok(())
     }
 
     pub fn start(&self) -> XCapResult<()> {
         self.is_running.store(true, Ordering::Relaxed);
-        let _ = self.active_sender.send(true);
+        let _ = self.control_sender.send(StreamControl::Active(true));
         Ok(())
     }
 
     pub fn stop(&self) -> XCapResult<()> {
         self.is_running.store(false, Ordering::Relaxed);
-        let _ = self.active_sender.send(false);
+        let _ = self.control_sender.send(StreamControl::Active(false));
+        Ok(())
+    }
+
+    /// 门户会话的鼠标指针模式只能在创建时选择，忽略 show_cursor
+    pub fn reconfigure(&self, update: &RecorderUpdate) -> XCapResult<()> {
+        self.live_config.update(update)?;
+        let _ = self.control_sender.send(StreamControl::Reconfigure);
+
         Ok(())
     }
+
+    pub fn shutdown(&self) -> XCapResult<()> {
+        self.is_running.store(false, Ordering::Relaxed);
+
+        // 主循环已经退出时发送会失败，此时线程也已经结束
+        let _ = self.control_sender.send(StreamControl::Shutdown);
+        let result = join_worker(&self.worker);
+
+        // 关闭门户会话，合成器会停止共享屏幕并移除屏幕共享指示
+        ScreenCast::new()?.close_session(&self.session)?;
+
+        result
+    }
 }
@@ -1,10 +1,20 @@
+use std::time::Instant;
+
 use image::RgbaImage;
 use xcb::{
     Connection,
-    x::{Drawable, GetImage, ImageFormat, ImageOrder, Window},
+    x::{
+        Drawable, GetImage, GetImageReply, ImageFormat, ImageOrder, Setup, VisualClass, Visualid,
+        Window,
+    },
+};
+
+use crate::{
+    Config, Stage,
+    error::{XCapError, XCapResult},
 };
 
-use crate::error::{XCapError, XCapResult};
+use super::utils::get_x_display_name;
 
 fn get_pixel8_rgba(
     bytes: &[u8],
@@ -69,6 +79,143 @@ fn get_pixel24_32_rgba(
     }
 }
 
+/// visual 的颜色掩码，用于解析 ARGB32、30 位深等非 24 位 BGRX 的像素格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PixelMasks {
+    red: u32,
+    green: u32,
+    blue: u32,
+    alpha: u32,
+}
+
+fn find_pixel_masks(setup: &Setup, visual_id: Visualid, depth: u8) -> Option<PixelMasks> {
+    for screen in setup.roots() {
+        for allowed_depth in screen.allowed_depths() {
+            if allowed_depth.depth() != depth {
+                continue;
+            }
+
+            let Some(visual) = allowed_depth
+                .visuals()
+                .iter()
+                .find(|visual| visual.visual_id() == visual_id)
+            else {
+                continue;
+            };
+
+            // 只有 TrueColor/DirectColor 的像素值直接由掩码描述，其他 visual 需要 colormap
+            if visual.class() != VisualClass::TrueColor
+                && visual.class() != VisualClass::DirectColor
+            {
+                return None;
+            }
+
+            return Some(pixel_masks(
+                visual.red_mask(),
+                visual.green_mask(),
+                visual.blue_mask(),
+                depth,
+            ));
+        }
+    }
+
+    None
+}
+
+impl PixelMasks {
+    /// 常见的 24 位 BGRX 格式，可以直接按字节读取，不需要逐个分量移位缩放
+    fn is_bgrx(&self) -> bool {
+        self.red == 0x00FF0000
+            && self.green == 0x0000FF00
+            && self.blue == 0x000000FF
+            && self.alpha == 0
+    }
+}
+
+fn pixel_masks(red: u32, green: u32, blue: u32, depth: u8) -> PixelMasks {
+    let depth_mask = if depth >= 32 {
+        u32::MAX
+    } else {
+        (1u32 << depth) - 1
+    };
+
+    // ARGB32 visual 中 RGB 掩码之外的位即为 alpha 通道，24/30 位深没有多余的位
+    let alpha = depth_mask & !(red | green | blue);
+
+    PixelMasks {
+        red,
+        green,
+        blue,
+        alpha,
+    }
+}
+
+/// 将掩码对应的分量缩放到 8 位
+fn extract_channel(pixel: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 255;
+    }
+
+    let shift = mask.trailing_zeros();
+    let bits = (mask >> shift).count_ones();
+    let value = ((pixel & mask) >> shift) as u64;
+    let max = (1u64 << bits) - 1;
+
+    ((value * 255 + max / 2) / max) as u8
+}
+
+fn read_pixel(bytes: &[u8], index: usize, bytes_per_pixel: usize, byte_order: ImageOrder) -> u32 {
+    let mut pixel = 0u32;
+    for i in 0..bytes_per_pixel {
+        let byte = bytes[index + i] as u32;
+        if byte_order == ImageOrder::LsbFirst {
+            pixel |= byte << (i * 8);
+        } else {
+            pixel = (pixel << 8) | byte;
+        }
+    }
+
+    pixel
+}
+
+fn convert_with_masks(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    bits_per_pixel: u32,
+    scanline_pad: u32,
+    byte_order: ImageOrder,
+    masks: PixelMasks,
+) -> XCapResult<Vec<u8>> {
+    let bytes_per_pixel = (bits_per_pixel / 8) as usize;
+    let scanline_pad = scanline_pad.max(8);
+    let stride = ((width * bits_per_pixel).div_ceil(scanline_pad) * scanline_pad / 8) as usize;
+
+    if bytes.len() < stride * height as usize {
+        return Err(XCapError::new("GetImage returned truncated image data"));
+    }
+
+    let mut rgba = vec![0u8; (width * height * 4) as usize];
+    for (y, row) in rgba.chunks_exact_mut((width * 4) as usize).enumerate() {
+        let row_offset = y * stride;
+        for (x, dst) in row.chunks_exact_mut(4).enumerate() {
+            let pixel = read_pixel(
+                bytes,
+                row_offset + x * bytes_per_pixel,
+                bytes_per_pixel,
+                byte_order,
+            );
+
+            dst[0] = extract_channel(pixel, masks.red);
+            dst[1] = extract_channel(pixel, masks.green);
+            dst[2] = extract_channel(pixel, masks.blue);
+            dst[3] = extract_channel(pixel, masks.alpha);
+        }
+    }
+
+    Ok(rgba)
+}
+
 pub fn xorg_capture(
     window: Window,
     x: i32,
@@ -76,13 +223,24 @@ pub fn xorg_capture(
     width: u32,
     height: u32,
 ) -> XCapResult<RgbaImage> {
-    let (conn, _) = Connection::connect(None)?;
+    xorg_capture_drawable(Drawable::Window(window), x, y, width, height)
+}
+
+/// 读取窗口或 pixmap 中的区域，pixmap 没有 visual，按深度解析像素
+pub fn xorg_capture_drawable(
+    drawable: Drawable,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+) -> XCapResult<RgbaImage> {
+    let (conn, _) = Connection::connect(get_x_display_name().as_deref())?;
 
     let setup = conn.get_setup();
 
     let get_image_cookie = conn.send_request(&GetImage {
         format: ImageFormat::ZPixmap,
-        drawable: Drawable::Window(window),
+        drawable,
         x: x as i16,
         y: y as i16,
         width: width as u16,
@@ -90,7 +248,24 @@ pub fn xorg_capture(
         plane_mask: u32::MAX,
     });
 
+    let started_at = Instant::now();
     let get_image_reply = conn.wait_for_reply(get_image_cookie)?;
+    Config::get().record_stage(Stage::FirstFrame, started_at.elapsed());
+
+    let started_at = Instant::now();
+    let image = to_rgba_image(setup, &get_image_reply, width, height);
+    Config::get().record_stage(Stage::Conversion, started_at.elapsed());
+
+    image
+}
+
+/// 把 GetImage 返回的像素数据转换为 RgbaImage
+fn to_rgba_image(
+    setup: &Setup,
+    get_image_reply: &GetImageReply,
+    width: u32,
+    height: u32,
+) -> XCapResult<RgbaImage> {
     let bytes = get_image_reply.data();
     let depth = get_image_reply.depth();
 
@@ -101,6 +276,27 @@ pub fn xorg_capture(
         .ok_or(XCapError::new("Not found pixmap format"))?;
 
     let bits_per_pixel = pixmap_format.bits_per_pixel() as u32;
+
+    // 根据窗口 visual 的掩码解析 ARGB32 与 30 位深等格式，BGRX 使用下面按字节读取的快速路径
+    if let Some(masks) = find_pixel_masks(setup, get_image_reply.visual(), depth)
+        && !masks.is_bgrx()
+        && bits_per_pixel % 8 == 0
+        && bits_per_pixel <= 32
+    {
+        let rgba = convert_with_masks(
+            bytes,
+            width,
+            height,
+            bits_per_pixel,
+            pixmap_format.scanline_pad() as u32,
+            setup.image_byte_order(),
+            masks,
+        )?;
+
+        return RgbaImage::from_raw(width, height, rgba)
+            .ok_or_else(|| XCapError::new("RgbaImage::from_raw failed"));
+    }
+
     let bit_order = setup.bitmap_format_bit_order();
 
     let get_pixel_rgba = match depth {
@@ -127,3 +323,46 @@ pub fn xorg_capture(
     RgbaImage::from_raw(width, height, rgba)
         .ok_or_else(|| XCapError::new("RgbaImage::from_raw failed"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixel_masks_argb32() {
+        let masks = pixel_masks(0x00FF0000, 0x0000FF00, 0x000000FF, 32);
+        assert_eq!(masks.alpha, 0xFF000000);
+
+        let masks = pixel_masks(0x00FF0000, 0x0000FF00, 0x000000FF, 24);
+        assert_eq!(masks.alpha, 0);
+    }
+
+    #[test]
+    fn test_pixel_masks_is_bgrx() {
+        assert!(pixel_masks(0x00FF0000, 0x0000FF00, 0x000000FF, 24).is_bgrx());
+        // ARGB32 需要读取 alpha，30 位深需要缩放分量
+        assert!(!pixel_masks(0x00FF0000, 0x0000FF00, 0x000000FF, 32).is_bgrx());
+        assert!(!pixel_masks(0x3FF00000, 0x000FFC00, 0x000003FF, 30).is_bgrx());
+        assert!(!pixel_masks(0x000000FF, 0x0000FF00, 0x00FF0000, 24).is_bgrx());
+    }
+
+    #[test]
+    fn test_convert_with_masks_30bit() {
+        let masks = pixel_masks(0x3FF00000, 0x000FFC00, 0x000003FF, 30);
+        // r = 1023, g = 0, b = 512
+        let pixel: u32 = (1023 << 20) | 512;
+        let bytes = pixel.to_le_bytes();
+
+        let rgba = convert_with_masks(&bytes, 1, 1, 32, 32, ImageOrder::LsbFirst, masks).unwrap();
+        assert_eq!(rgba, vec![255, 0, 128, 255]);
+    }
+
+    #[test]
+    fn test_convert_with_masks_argb32() {
+        let masks = pixel_masks(0x00FF0000, 0x0000FF00, 0x000000FF, 32);
+        let bytes = [0x30, 0x20, 0x10, 0x80];
+
+        let rgba = convert_with_masks(&bytes, 1, 1, 32, 32, ImageOrder::LsbFirst, masks).unwrap();
+        assert_eq!(rgba, vec![0x10, 0x20, 0x30, 0x80]);
+    }
+}
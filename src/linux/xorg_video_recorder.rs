@@ -1,27 +1,61 @@
 use super::impl_monitor::ImplMonitor;
+use super::utils::{is_display_idle, is_on_battery};
 use crate::error::{XCapError, XCapResult};
-use crate::video_recorder::{Frame, RecorderWaker};
+use crate::redaction::Redaction;
+use crate::video_recorder::{
+    ChangeDetector, Frame, FrameHook, FramePacer, IdleGate, LiveConfig, RecorderEvent,
+    RecorderHealth, RecorderWaker, WorkerGuard, join_worker, scale_frame, vsync_frequency,
+};
+use crate::window_crop::WindowCrop;
+use crate::{FramePacing, Monitor, RecorderConfig, RecorderUpdate, RecoveryPolicy};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
-use std::thread;
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct XorgVideoRecorder {
     monitor: ImplMonitor,
+    pacing: FramePacing,
+    low_power: bool,
+    live_config: Arc<LiveConfig>,
+    window_crop: Option<Arc<WindowCrop>>,
+    redaction: Option<Redaction>,
+    recovery: RecoveryPolicy,
+    pause_when_idle: bool,
+    frame_hook: Option<FrameHook>,
     sender: Sender<Frame>,
     running: Arc<Mutex<bool>>,
     recorder_waker: Arc<RecorderWaker>,
+    health: Arc<RecorderHealth>,
+    worker: Arc<Mutex<Option<JoinHandle<XCapResult<()>>>>>,
 }
 
 impl XorgVideoRecorder {
-    pub fn new(monitor: ImplMonitor) -> XCapResult<(Self, Receiver<Frame>)> {
+    pub fn new(
+        monitor: ImplMonitor,
+        config: &RecorderConfig,
+        health: Arc<RecorderHealth>,
+    ) -> XCapResult<(Self, Receiver<Frame>)> {
         let (sender, receiver) = mpsc::channel();
+        let low_power = config.power_profile.is_low_power(is_on_battery);
+        let window_crop = WindowCrop::from_config(config, &Monitor::new(monitor.clone()))?;
+        let redaction = Redaction::from_config(config, &Monitor::new(monitor.clone()))?;
         let recorder = Self {
             monitor,
+            pacing: config.pacing,
+            low_power,
+            live_config: Arc::new(LiveConfig::new(config.clone())),
+            window_crop: window_crop.map(Arc::new),
+            redaction,
+            recovery: config.recovery,
+            pause_when_idle: config.pause_when_idle,
+            frame_hook: config.frame_hook.clone(),
             sender,
             running: Arc::new(Mutex::new(false)),
             recorder_waker: Arc::new(RecorderWaker::new()),
+            health,
+            worker: Arc::default(),
         };
 
         recorder.on_frame()?;
@@ -31,15 +65,49 @@ impl XorgVideoRecorder {
 
     pub fn on_frame(&self) -> XCapResult<()> {
         let monitor = self.monitor.clone();
+        // X11 没有 vsync 回调，按显示器刷新率计时，可变刷新率的显示器按最高刷新率计时
+        // 限制帧率时在截图之前等待，跳过的帧不会被截取
+        let vsync_frequency = match self.pacing {
+            FramePacing::Vsync => Some(vsync_frequency(&Monitor::new(monitor.clone()))?),
+            FramePacing::FreeRunning => None,
+        };
+        let low_power = self.low_power;
+        let new_frame_pacer = move |config: &RecorderConfig| match (
+            config.frame_interval(low_power),
+            vsync_frequency,
+        ) {
+            (Some(frame_interval), _) => Some(FramePacer::with_interval(frame_interval)),
+            (None, Some(frequency)) => Some(FramePacer::new(frequency)),
+            (None, None) => None,
+        };
+        let (mut version, config) = self.live_config.get()?;
+        let mut frame_pacer = new_frame_pacer(&config);
+        let mut scale = config.scale;
+        let live_config = self.live_config.clone();
+        let window_crop = self.window_crop.clone();
+        let redaction = self.redaction.clone();
+        let recovery = self.recovery;
+        let idle_gate = IdleGate::new(self.pause_when_idle);
+        let frame_hook = self.frame_hook.clone();
         let sender = self.sender.clone();
         let running_flag = self.running.clone();
         let recorder_waker = self.recorder_waker.clone();
+        let health = self.health.clone();
+
+        let worker = thread::spawn(move || {
+            let _guard = WorkerGuard(recorder_waker.clone());
+            let mut frame_size = None;
+            let mut capture_failed = false;
+            let mut change_detector = ChangeDetector::default();
 
-        thread::spawn(move || {
             loop {
-                if let Err(err) = recorder_waker.wait() {
-                    log::error!("Recorder waker error: {err:?}");
-                    break Err(err);
+                match recorder_waker.wait() {
+                    Ok(true) => {}
+                    Ok(false) => break Ok(()),
+                    Err(err) => {
+                        log::error!("Recorder waker error: {err:?}");
+                        break Err(err);
+                    }
                 }
 
                 let is_running = match running_flag.lock() {
@@ -50,8 +118,33 @@ impl XorgVideoRecorder {
                     }
                 };
 
+                // stop 和 wake 之间的竞争，回到 wait 中等待下一次 start
                 if !is_running {
-                    break Ok(());
+                    continue;
+                }
+
+                // 设置被修改后重建节拍器，从下一帧开始生效
+                if let Some(config) = live_config.poll(&mut version) {
+                    frame_pacer = new_frame_pacer(&config);
+                    scale = config.scale;
+                }
+
+                // 显示器休眠或屏保运行时截到的都是黑屏
+                if idle_gate.poll(is_display_idle, &health) {
+                    thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+
+                if let Some(frame_pacer) = frame_pacer.as_mut() {
+                    match frame_pacer.wait(&recorder_waker) {
+                        Ok(true) => {}
+                        // 等待期间被暂停，回到 wait 中等待下一次 start
+                        Ok(false) => continue,
+                        Err(err) => {
+                            log::error!("Frame pacer error: {err:?}");
+                            break Err(err);
+                        }
+                    }
                 }
 
                 match monitor.capture_image() {
@@ -60,22 +153,68 @@ impl XorgVideoRecorder {
                         let height = image.height();
                         let raw = image.into_raw();
 
-                        let frame = Frame::new(width, height, raw);
-                        if let Err(e) = sender.send(frame) {
-                            log::error!("Failed to send frame: {e:?}");
-                            break Err(XCapError::new(format!("Failed to send frame: {e}")));
+                        // 截图尺寸变化说明显示器分辨率或布局发生了变化
+                        if frame_size.is_some_and(|size| size != (width, height)) {
+                            health.emit(RecorderEvent::DisplayReconfigured);
+                        }
+                        frame_size = Some((width, height));
+
+                        if capture_failed {
+                            capture_failed = false;
+                            health.emit(RecorderEvent::StreamRestarted);
+                        }
+
+                        let mut frame = Frame::new(width, height, raw);
+                        if let Some(redaction) = &redaction {
+                            redaction.apply(&mut frame);
+                        }
+                        // 跟随的窗口不在显示器上时不发送
+                        if let Some(window_crop) = &window_crop {
+                            match window_crop.crop(&frame) {
+                                Some(cropped) => frame = cropped,
+                                None => continue,
+                            }
+                        }
+
+                        // 低功耗模式下画面没有变化时不发送
+                        if low_power && !change_detector.is_changed(frame.data()) {
+                            continue;
+                        }
+
+                        if scale < 1.0 {
+                            frame = scale_frame(frame, scale);
+                        }
+                        if let Some(frame_hook) = &frame_hook {
+                            frame_hook.apply(&mut frame);
+                        }
+                        live_config.emit_marker(frame.timestamp(), &health);
+                        if !health.deliver(|| sender.send(frame).is_ok()) {
+                            log::error!("Failed to send frame: receiver disconnected");
+                            break Err(XCapError::new("Failed to send frame"));
                         }
                     }
                     Err(e) => {
                         log::error!("Failed to capture frame: {e:?}");
+                        health.dropped(1);
+                        health.emit(RecorderEvent::Error(e.to_string()));
+
+                        if recovery == RecoveryPolicy::Stop {
+                            break Err(e);
+                        }
+
+                        // 显示器断开或切换模式时截图会持续失败，恢复后继续录制
+                        capture_failed = true;
                         thread::sleep(Duration::from_millis(10));
                         continue;
                     }
                 }
 
-                thread::sleep(Duration::from_millis(1));
+                if frame_pacer.is_none() {
+                    thread::sleep(Duration::from_millis(1));
+                }
             }
         });
+        *self.worker.lock().map_err(XCapError::from)? = Some(worker);
 
         Ok(())
     }
@@ -97,7 +236,21 @@ impl XorgVideoRecorder {
         *running = false;
 
         self.recorder_waker.sleep()?;
+        self.recorder_waker.wait_idle()?;
+
+        Ok(())
+    }
+
+    /// 由工作线程在下一次截图之前应用，XGetImage 的截图不包含鼠标指针，忽略 show_cursor
+    pub fn reconfigure(&self, update: &RecorderUpdate) -> XCapResult<()> {
+        self.live_config.update(update)?;
 
         Ok(())
     }
+
+    pub fn shutdown(&self) -> XCapResult<()> {
+        self.recorder_waker.shutdown()?;
+
+        join_worker(&self.worker)
+    }
 }
@@ -1,4 +1,9 @@
-use std::{collections::HashMap, slice, sync::mpsc, time::Instant};
+use std::{
+    collections::HashMap,
+    slice,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
 
 use block2::StackBlock;
 use dispatch2::{DispatchQueue, DispatchQueueAttr};
@@ -17,17 +22,31 @@ use objc2_core_video::{
     CVPixelBufferLockBaseAddress, CVPixelBufferLockFlags, CVPixelBufferUnlockBaseAddress,
     kCVPixelFormatType_32BGRA,
 };
-use objc2_foundation::{NSError, NSObject, NSObjectProtocol, NSProcessInfo};
+use objc2_foundation::{NSArray, NSError, NSObject, NSObjectProtocol, NSProcessInfo};
 use objc2_screen_capture_kit::{
     SCContentFilter, SCShareableContent, SCStream, SCStreamConfiguration, SCStreamOutput,
-    SCStreamOutputType,
+    SCStreamOutputType, SCWindow,
 };
 use scopeguard::defer;
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    Backend, CaptureConfig, Config, Stage, capture_report, conversion,
+    error::{PlatformError, XCapError, XCapResult},
+    video_recorder::Watchdog,
+};
 
-use super::bgra_to_rgba;
+use super::capture_thread::{run_on_capture_thread, shutdown_capture_thread};
 use super::capture_compatible;
+use super::capture_config_ext::StreamOptions;
+
+// 按配置输出各阶段耗时
+macro_rules! timing {
+    ($enabled:expr, $($arg:tt)*) => {
+        if $enabled {
+            debug!($($arg)*);
+        }
+    };
+}
 
 // 流缓存结构：复用 SCStream 以避免重复创建和启动
 // 注意：display_id, width, height 虽然现在由 HashMap 的键管理，但保留它们有助于调试
@@ -38,29 +57,55 @@ struct StreamCache {
     width: usize,
     height: usize,
     is_started: bool,
+    // 复用的流连续等不到帧时停止并重新创建，避免卡住的流一直留在缓存中
+    watchdog: Watchdog,
+}
+
+// 复用的流连续这么多次等待帧超时后判定为卡住
+const STALL_TIMEOUTS: u32 = 3;
+
+// CGWindowLevel 常量，参见 CGWindowLevel.h
+pub(super) const DOCK_WINDOW_LEVEL: isize = 20;
+pub(super) const MAIN_MENU_WINDOW_LEVEL: isize = 24;
+pub(super) const STATUS_WINDOW_LEVEL: isize = 25;
+pub(super) const POP_UP_MENU_WINDOW_LEVEL: isize = 101;
+pub(super) const HELP_WINDOW_LEVEL: isize = 200;
+// kCGDesktopIconWindowLevel = kCGMinimumWindowLevel + 20 + 20
+pub(super) const DESKTOP_ICON_WINDOW_LEVEL: isize = i32::MIN as isize + 1 + 20 + 20;
+
+// 需要从显示器截图中排除的系统窗口，同时作为流缓存键的一部分
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+struct ExcludedSystemWindows {
+    menu_bar: bool,
+    dock: bool,
+    desktop_icons: bool,
+}
+
+impl From<&CaptureConfig> for ExcludedSystemWindows {
+    fn from(config: &CaptureConfig) -> Self {
+        ExcludedSystemWindows {
+            menu_bar: config.exclude_menu_bar,
+            dock: config.exclude_dock,
+            desktop_icons: config.exclude_desktop_icons,
+        }
+    }
 }
 
 // 缓存 shareable_content 以减少重复获取的开销
 //
-// 注意：
-// 1. 由于 Retained<SCShareableContent> 不是 Send + Sync，无法跨线程共享
-// 2. 使用 thread_local! 实现线程本地缓存，每个线程有独立的缓存
-// 3. 如果使用 spawn_blocking 或其他方式创建新线程，新线程第一次调用需要重新获取（~85ms）
-// 4. 但在同一线程内的后续调用都能享受缓存（~3-6µs）
-// 5. 缓存会一直存在直到线程结束（thread_local 会自动清理）
-//
-// 性能影响：
-// - 单线程应用：完美，第一次后所有调用都很快
-// - 多线程但每个线程只调用一次：第一次有开销，可接受
-// - 多线程且每个线程多次调用：每个线程都有自己的缓存，性能仍然很好
+// Retained<SCShareableContent> 不是 Send + Sync，无法跨线程共享，所以用 thread_local! 缓存。
+// ScreenCaptureKit 的调用都在截图线程上执行（见 capture_thread），缓存只有一份：
+// 第一次获取约 85ms，之后约 3-6µs，与调用方所在的线程无关
 thread_local! {
     static SHAREABLE_CONTENT_CACHE: std::cell::RefCell<Option<(Retained<SCShareableContent>, bool)>> = std::cell::RefCell::new(None);
 }
 
-// 线程本地流缓存，按 display_id 和尺寸缓存多个显示器的流
+// 截图线程上的流缓存，按 display_id、尺寸、排除的系统窗口和流配置缓存多个显示器的流
 // 使用 HashMap 支持在同一线程中缓存多个显示器的流
+type StreamCacheKey = (CGDirectDisplayID, usize, usize, ExcludedSystemWindows, StreamOptions);
+
 thread_local! {
-    static STREAM_CACHE: std::cell::RefCell<HashMap<(CGDirectDisplayID, usize, usize), StreamCache>> = std::cell::RefCell::new(HashMap::new());
+    static STREAM_CACHE: std::cell::RefCell<HashMap<StreamCacheKey, StreamCache>> = std::cell::RefCell::new(HashMap::new());
 }
 
 // 缓存 macOS 版本检查结果，避免重复调用
@@ -68,6 +113,46 @@ thread_local! {
     static SCKIT_AVAILABLE_CACHE: std::cell::Cell<Option<bool>> = std::cell::Cell::new(None);
 }
 
+/// 停止缓存的所有 SCStream，清空可共享内容缓存，然后结束截图线程
+pub(super) fn clear_stream_caches() -> XCapResult<()> {
+    shutdown_capture_thread(clear_caches)
+}
+
+fn clear_caches() {
+    STREAM_CACHE.with(|cache| {
+        for (_, cached) in cache.borrow_mut().drain() {
+            if cached.is_started {
+                unsafe { cached.stream.stopCaptureWithCompletionHandler(None) };
+            }
+        }
+    });
+
+    SHAREABLE_CONTENT_CACHE.with(|cache| *cache.borrow_mut() = None);
+}
+
+/// 记录一次等待帧超时，流卡住时停止它并移出缓存，下次截图会重新创建
+fn evict_stalled_stream(cache_key: &StreamCacheKey) {
+    STREAM_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let Some(idle) = cache
+            .get_mut(cache_key)
+            .and_then(|cached| cached.watchdog.miss())
+        else {
+            return;
+        };
+
+        if let Some(cached) = cache.remove(cache_key) {
+            log::warn!(
+                "ScreenCaptureKit stream of display {} delivered no frames for {idle:?}, restarting it",
+                cached.display_id
+            );
+            if cached.is_started {
+                unsafe { cached.stream.stopCaptureWithCompletionHandler(None) };
+            }
+        }
+    });
+}
+
 pub fn capture(
     cg_rect: CGRect,
     list_option: CGWindowListOption,
@@ -84,26 +169,86 @@ pub fn capture_with_scale(
     display_id: Option<CGDirectDisplayID>,
     scale: f32,
 ) -> XCapResult<RgbaImage> {
+    let backend = capture_report::requested_backend();
+
     // 优先使用 ScreenCaptureKit（如果可用）
     // 深度优化：快速失败，如果 ScreenCaptureKit 超时或失败，立即回退
-    if is_screencapturekit_available() {
+    if backend != Backend::CoreGraphics && is_screencapturekit_available() {
         // 尝试使用 ScreenCaptureKit，但设置较短的超时以便快速回退
-        match capture_with_screencapturekit(cg_rect, list_option, window_id, display_id, scale) {
-            Ok(image) => return Ok(image),
-            Err(_) => {
-                // ScreenCaptureKit 不可用或失败，快速回退到 CGWindowListCreateImage
-                // 注意：这里不打印错误，因为 ScreenCaptureKit 可能还未完全实现或超时
+        let result = capture_with_screencapturekit(
+            cg_rect,
+            list_option,
+            window_id,
+            display_id,
+            scale,
+            ExcludedSystemWindows::default(),
+            StreamOptions::default(),
+        );
+        match result {
+            Ok(image) => {
+                capture_report::record_backend(Backend::ScreenCaptureKit);
+                return Ok(image);
+            }
+            // 指定了 ScreenCaptureKit 时不回退
+            Err(err) if backend == Backend::ScreenCaptureKit => return Err(err),
+            Err(err) => {
+                // ScreenCaptureKit 不可用或失败，快速回退到 CGWindowListCreateImage，
+                // 错误记录在 CaptureReport 中
+                capture_report::record_fallback(Backend::ScreenCaptureKit, &err);
             }
         }
     }
+    if backend == Backend::ScreenCaptureKit {
+        return Err(XCapError::NotSupported);
+    }
+
     // 回退到传统的 CGWindowListCreateImage 方法（通常更快但已废弃）
     // CGWindowListCreateImage 始终返回物理像素，无需 scale 参数
+    capture_report::record_backend(Backend::CoreGraphics);
     capture_compatible::capture_with_cgwindowlist(cg_rect, list_option, window_id)
 }
 
+pub fn capture_with_config(
+    cg_rect: CGRect,
+    list_option: CGWindowListOption,
+    window_id: CGWindowID,
+    display_id: Option<CGDirectDisplayID>,
+    config: &CaptureConfig,
+) -> XCapResult<RgbaImage> {
+    if !config.excludes_system_windows() && config.stream_options == StreamOptions::default() {
+        return capture(cg_rect, list_option, window_id, display_id);
+    }
+
+    // CGWindowListCreateImage 无法排除指定窗口，也没有流配置，不能回退
+    if capture_report::requested_backend() == Backend::CoreGraphics
+        || !is_screencapturekit_available()
+    {
+        return Err(XCapError::NotSupported);
+    }
+
+    capture_report::record_backend(Backend::ScreenCaptureKit);
+    capture_with_screencapturekit(
+        cg_rect,
+        list_option,
+        window_id,
+        display_id,
+        1.0,
+        ExcludedSystemWindows::from(config),
+        config.stream_options,
+    )
+}
+
+/// 保留 NSError 的域和错误码，用户反馈问题时可以据此定位原因
+fn ns_error(message: &str, err: &NSError) -> XCapError {
+    XCapError::platform(
+        message,
+        PlatformError::new(err.domain(), err.code() as i64, err.localizedDescription()),
+    )
+}
+
 /// 检查 macOS 版本是否 >= 12.3 (ScreenCaptureKit 可用)
 /// 使用线程本地缓存避免重复检查
-fn is_screencapturekit_available() -> bool {
+pub(super) fn is_screencapturekit_available() -> bool {
     SCKIT_AVAILABLE_CACHE.with(|cache| {
         if let Some(cached) = cache.get() {
             return cached;
@@ -151,19 +296,17 @@ fn pixel_buffer_to_rgba_image(pixel_buffer: &CVPixelBuffer) -> XCapResult<RgbaIm
         // 优化：如果 bytes_per_row == width * 4，可以直接使用，无需逐行拷贝
         let expected_row_size = width * 4;
         let total_size = width * height * 4;
-        let mut buffer = Vec::with_capacity(total_size);
 
-        if bytes_per_row == expected_row_size {
+        let buffer = if bytes_per_row == expected_row_size {
             // 最优情况：行对齐，直接拷贝并转换
             let data = slice::from_raw_parts(base_address as *const u8, total_size);
-            // 优化：reserve_exact 已经在 with_capacity 中完成，这里不需要再次调用
 
-            // SIMD 优化：使用 SIMD 指令批量转换 BGRA -> RGBA
-            bgra_to_rgba::convert_bgra_to_rgba_simd(data, &mut buffer);
+            // 按测量结果选择 SIMD 内核，大帧分到多个线程中转换
+            conversion::bgra_to_rgba(data)
         } else {
             // 需要处理行对齐的情况（较少见，但也可以使用 SIMD 优化）
             let data = slice::from_raw_parts(base_address as *const u8, bytes_per_row * height);
-            // 优化：reserve_exact 已经在 with_capacity 中完成，这里不需要再次调用
+            let mut buffer = Vec::with_capacity(total_size);
 
             // 逐行处理，每行使用 SIMD 优化
             let mut dst_offset = 0;
@@ -175,12 +318,13 @@ fn pixel_buffer_to_rgba_image(pixel_buffer: &CVPixelBuffer) -> XCapResult<RgbaIm
                 let row_pixel_count = width;
                 let dst_ptr = buffer.as_mut_ptr().add(dst_offset);
                 let src_ptr = row_data.as_ptr();
-                bgra_to_rgba::convert_bgra_to_rgba_row(src_ptr, dst_ptr, row_pixel_count);
+                conversion::bgra_to_rgba_row(src_ptr, dst_ptr, row_pixel_count);
 
                 dst_offset += expected_row_size;
             }
             buffer.set_len(width * height * 4);
-        }
+            buffer
+        };
 
         RgbaImage::from_raw(width as u32, height as u32, buffer)
             .ok_or_else(|| XCapError::new("RgbaImage::from_raw failed"))
@@ -270,6 +414,7 @@ impl SCStreamOutputDelegate {
 
 fn fetch_shareable_content(
     excluding_desktop_windows: bool,
+    timeout: Duration,
 ) -> XCapResult<Retained<SCShareableContent>> {
     // 优化：检查线程本地缓存，如果缓存有效且参数匹配，直接返回
     // 缓存会一直存在直到程序结束，最大化性能提升
@@ -314,11 +459,11 @@ fn fetch_shareable_content(
         );
     }
 
-    // 同步等待：使用阻塞接收，设置超时（优化：缩短到 200ms，通常系统响应很快）
-    let content = match rx.recv_timeout(std::time::Duration::from_millis(200)) {
+    // 同步等待：使用阻塞接收，超时默认 200ms，通常系统响应很快
+    let content = match rx.recv_timeout(timeout) {
         Ok(result) => result,
         Err(mpsc::RecvTimeoutError::Timeout) => {
-            return Err(XCapError::new(
+            return Err(XCapError::transient(
                 "Timed out while fetching ScreenCaptureKit shareable content",
             ));
         }
@@ -329,11 +474,13 @@ fn fetch_shareable_content(
 
     let content = match content {
         Ok(content) => content,
-        Err(Some(err)) => return Err(XCapError::new(err.localizedDescription().to_string())),
+        Err(Some(err)) => {
+            return Err(ns_error("Fetch ScreenCaptureKit shareable content failed", &err));
+        }
         Err(None) => return Err(XCapError::new("ScreenCaptureKit returned no content")),
     };
 
-    // 更新线程本地缓存（长期存在，直到程序结束）
+    // 更新缓存（长期存在，直到截图线程结束）
     SHAREABLE_CONTENT_CACHE.with(|cache| {
         *cache.borrow_mut() = Some((content.clone(), excluding_desktop_windows));
     });
@@ -341,21 +488,85 @@ fn fetch_shareable_content(
     Ok(content)
 }
 
-/// 使用 ScreenCaptureKit 进行屏幕捕获
+/// 从可共享内容中找出需要排除的菜单栏、Dock 和桌面图标窗口
+unsafe fn get_excluded_windows(
+    shareable_content: &SCShareableContent,
+    excluded: ExcludedSystemWindows,
+) -> Retained<NSArray<SCWindow>> {
+    if excluded == ExcludedSystemWindows::default() {
+        return NSArray::new();
+    }
+
+    let mut excluded_windows = Vec::new();
+    for window in unsafe { shareable_content.windows() }.iter() {
+        let layer = unsafe { window.windowLayer() };
+        // 菜单栏属于 WindowServer，没有 owningApplication
+        let bundle_identifier = unsafe { window.owningApplication() }
+            .map(|app| unsafe { app.bundleIdentifier() }.to_string())
+            .unwrap_or_default();
+
+        let is_excluded = (excluded.menu_bar
+            && (layer == MAIN_MENU_WINDOW_LEVEL || layer == STATUS_WINDOW_LEVEL))
+            || (excluded.dock
+                && layer == DOCK_WINDOW_LEVEL
+                && bundle_identifier == "com.apple.dock")
+            || (excluded.desktop_icons
+                && layer == DESKTOP_ICON_WINDOW_LEVEL
+                && bundle_identifier == "com.apple.finder");
+
+        if is_excluded {
+            excluded_windows.push(window);
+        }
+    }
+
+    NSArray::from_retained_slice(&excluded_windows)
+}
+
+/// 使用 ScreenCaptureKit 进行屏幕捕获，在截图线程上执行
 fn capture_with_screencapturekit(
+    cg_rect: CGRect,
+    list_option: CGWindowListOption,
+    window_id: CGWindowID,
+    display_id: Option<CGDirectDisplayID>,
+    scale: f32,
+    excluded: ExcludedSystemWindows,
+    stream_options: StreamOptions,
+) -> XCapResult<RgbaImage> {
+    run_on_capture_thread(move || {
+        capture_on_capture_thread(
+            cg_rect,
+            list_option,
+            window_id,
+            display_id,
+            scale,
+            excluded,
+            stream_options,
+        )
+    })
+}
+
+fn capture_on_capture_thread(
     cg_rect: CGRect,
     _list_option: CGWindowListOption,
     _window_id: CGWindowID,
     display_id: Option<CGDirectDisplayID>,
     scale: f32,
+    excluded: ExcludedSystemWindows,
+    stream_options: StreamOptions,
 ) -> XCapResult<RgbaImage> {
+    let config = Config::get();
+    let log_timings = config.log_timings();
+    let capture_timeout = config.capture_timeout();
+    let stream_options = stream_options.with_shows_cursor(config.show_cursor());
+
     unsafe {
         let total_start = Instant::now();
 
         // 1. 获取可共享内容
         let t1 = Instant::now();
-        let shareable_content = fetch_shareable_content(false)?;
-        debug!("[性能] 1. 获取可共享内容: {:?}", t1.elapsed());
+        let shareable_content = fetch_shareable_content(false, capture_timeout)?;
+        config.record_stage(Stage::ContentFetch, t1.elapsed());
+        timing!(log_timings, "[性能] 1. 获取可共享内容: {:?}", t1.elapsed());
 
         // 2. 获取显示器列表
         let t2 = Instant::now();
@@ -363,7 +574,7 @@ fn capture_with_screencapturekit(
         if displays.count() == 0 {
             return Err(XCapError::new("No displays found"));
         }
-        debug!("[性能] 2. 获取显示器列表: {:?}", t2.elapsed());
+        timing!(log_timings, "[性能] 2. 获取显示器列表: {:?}", t2.elapsed());
 
         // 3. 找到对应的显示器（优化：如果提供了 display_id，直接使用；否则通过 rect 查找）
         let t3 = Instant::now();
@@ -373,7 +584,7 @@ fn capture_with_screencapturekit(
                 CGMainDisplayID()
             })
         });
-        debug!("[性能] 3. 找到对应的显示器: {:?}", t3.elapsed());
+        timing!(log_timings, "[性能] 3. 找到对应的显示器: {:?}", t3.elapsed());
 
         // 优化：直接查找目标显示器，避免不必要的遍历
         let t4 = Instant::now();
@@ -387,20 +598,18 @@ fn capture_with_screencapturekit(
             }
         }
         let display = display.ok_or_else(|| XCapError::new("Target display not found"))?;
-        debug!("[性能] 4. 查找目标显示器: {:?}", t4.elapsed());
+        timing!(log_timings, "[性能] 4. 查找目标显示器: {:?}", t4.elapsed());
 
         // 4. 创建内容过滤器
         let t5 = Instant::now();
-        let excluding_windows_filter: Retained<
-            objc2_foundation::NSArray<objc2_screen_capture_kit::SCWindow>,
-        > = objc2_foundation::NSArray::new();
+        let excluding_windows_filter = get_excluded_windows(&shareable_content, excluded);
 
         let content_filter = SCContentFilter::initWithDisplay_excludingWindows(
             SCContentFilter::alloc(),
             display.as_ref(),
             excluding_windows_filter.as_ref(),
         );
-        debug!("[性能] 5. 创建内容过滤器: {:?}", t5.elapsed());
+        timing!(log_timings, "[性能] 5. 创建内容过滤器: {:?}", t5.elapsed());
 
         // 5. 创建流配置
         // 优化：使用 cg_rect 的尺寸，避免重复调用 CGDisplayBounds
@@ -420,7 +629,7 @@ fn capture_with_screencapturekit(
                 (bounds.size.height * scale as f64).round() as usize,
             )
         };
-        debug!("[性能] 6. 创建流配置: {:?}", t6.elapsed());
+        timing!(log_timings, "[性能] 6. 创建流配置: {:?}", t6.elapsed());
 
         // 6. 创建输出处理器（每次都需要新的，因为需要新的 channel）
         let t7 = Instant::now();
@@ -428,11 +637,11 @@ fn capture_with_screencapturekit(
         let output_delegate = SCStreamOutputDelegate::new(frame_tx);
         let output_delegate_protocol =
             ProtocolObject::<dyn SCStreamOutput>::from_ref(&*output_delegate);
-        debug!("[性能] 7. 创建输出处理器: {:?}", t7.elapsed());
+        timing!(log_timings, "[性能] 7. 创建输出处理器: {:?}", t7.elapsed());
 
         // 7-9. 复用流或创建新流
         let t8 = Instant::now();
-        let cache_key = (target_display_id, width, height);
+        let cache_key = (target_display_id, width, height, excluded, stream_options);
         let (stream, need_start): (Retained<SCStream>, bool) =
             STREAM_CACHE.with(|cache| -> XCapResult<(Retained<SCStream>, bool)> {
                 let mut cache_ref = cache.borrow_mut();
@@ -449,10 +658,7 @@ fn capture_with_screencapturekit(
                 stream_config.setWidth(width.max(1));
                 stream_config.setHeight(height.max(1));
                 stream_config.setPixelFormat(kCVPixelFormatType_32BGRA);
-                stream_config.setQueueDepth(1);
-                // scale > 1.0 时输出尺寸可能大于物理像素，默认 scalesToFit=false
-                // 只会缩小不会放大，导致内容只占据部分画面
-                unsafe { stream_config.setScalesToFit(true) };
+                stream_options.apply(&stream_config);
 
                 let stream = SCStream::initWithFilter_configuration_delegate(
                     SCStream::alloc(),
@@ -470,12 +676,13 @@ fn capture_with_screencapturekit(
                         width,
                         height,
                         is_started: false, // 稍后会启动
+                        watchdog: Watchdog::new(capture_timeout, STALL_TIMEOUTS),
                     },
                 );
 
                 Ok((stream, true))
             })?;
-        debug!("[性能] 8. 复用流或创建新流: {:?}", t8.elapsed());
+        timing!(log_timings, "[性能] 8. 复用流或创建新流: {:?}", t8.elapsed());
 
         // 8. 添加输出（每次都需要新的 output_delegate 和队列）
         let t9 = Instant::now();
@@ -487,8 +694,8 @@ fn capture_with_screencapturekit(
                 SCStreamOutputType::Screen,
                 Some(output_queue_ref),
             )
-            .map_err(|err| XCapError::new(err.localizedDescription().to_string()))?;
-        debug!("[性能] 9. 添加输出: {:?}", t9.elapsed());
+            .map_err(|err| ns_error("Add ScreenCaptureKit stream output failed", &err))?;
+        timing!(log_timings, "[性能] 9. 添加输出: {:?}", t9.elapsed());
 
         // 9. 启动流（如果需要）
         let t10 = Instant::now();
@@ -505,8 +712,8 @@ fn capture_with_screencapturekit(
 
             stream.startCaptureWithCompletionHandler(Some(&start_block));
 
-            // 同步等待：使用阻塞接收，设置超时（优化：缩短到 200ms）
-            match start_rx.recv_timeout(std::time::Duration::from_millis(200)) {
+            // 同步等待：使用阻塞接收，超时默认 200ms
+            match start_rx.recv_timeout(capture_timeout) {
                 Ok(Ok(())) => {
                     // 更新缓存状态为已启动
                     STREAM_CACHE.with(|cache| {
@@ -516,10 +723,10 @@ fn capture_with_screencapturekit(
                     });
                 }
                 Ok(Err(err)) => {
-                    return Err(XCapError::new(err.localizedDescription().to_string()));
+                    return Err(ns_error("Start ScreenCaptureKit stream failed", &err));
                 }
                 Err(mpsc::RecvTimeoutError::Timeout) => {
-                    return Err(XCapError::new(
+                    return Err(XCapError::transient(
                         "Timed out while starting ScreenCaptureKit stream",
                     ));
                 }
@@ -527,15 +734,21 @@ fn capture_with_screencapturekit(
                     return Err(XCapError::new("Channel disconnected"));
                 }
             }
+            config.record_stage(Stage::StreamStart, t10.elapsed());
         }
-        debug!("[性能] 10. 启动流: {:?}", t10.elapsed());
+        timing!(log_timings, "[性能] 10. 启动流: {:?}", t10.elapsed());
 
-        // 10. 等待一帧数据（同步等待：使用阻塞接收，设置超时（优化：缩短到 200ms））
+        // 10. 等待一帧数据（同步等待：使用阻塞接收，超时默认 200ms）
         let t11 = Instant::now();
-        let frame_result = match frame_rx.recv_timeout(std::time::Duration::from_millis(200)) {
+        let frame_result = match frame_rx.recv_timeout(capture_timeout) {
             Ok(result) => result,
             Err(mpsc::RecvTimeoutError::Timeout) => {
-                return Err(XCapError::new("Timeout waiting for ScreenCaptureKit frame"));
+                let _ = stream.removeStreamOutput_type_error(
+                    output_delegate_protocol.as_ref(),
+                    SCStreamOutputType::Screen,
+                );
+                evict_stalled_stream(&cache_key);
+                return Err(XCapError::transient("Timeout waiting for ScreenCaptureKit frame"));
             }
             Err(mpsc::RecvTimeoutError::Disconnected) => {
                 return Err(XCapError::new("Channel disconnected"));
@@ -545,10 +758,16 @@ fn capture_with_screencapturekit(
         let pixel_buffer = match frame_result {
             Ok(buffer) => buffer,
             Err(err) => {
-                return Err(XCapError::new(err.localizedDescription().to_string()));
+                return Err(ns_error("Capture ScreenCaptureKit frame failed", &err));
             }
         };
-        debug!("[性能] 11. 等待一帧数据: {:?}", t11.elapsed());
+        config.record_stage(Stage::FirstFrame, t11.elapsed());
+        STREAM_CACHE.with(|cache| {
+            if let Some(cached) = cache.borrow_mut().get_mut(&cache_key) {
+                cached.watchdog.feed();
+            }
+        });
+        timing!(log_timings, "[性能] 11. 等待一帧数据: {:?}", t11.elapsed());
 
         // 优化：不停止流，保持运行状态以便下次复用
         // 只移除当前的 output，流继续运行
@@ -557,14 +776,15 @@ fn capture_with_screencapturekit(
             output_delegate_protocol.as_ref(),
             SCStreamOutputType::Screen,
         );
-        debug!("[性能] 12. 移除输出: {:?}", t12.elapsed());
+        timing!(log_timings, "[性能] 12. 移除输出: {:?}", t12.elapsed());
 
         // 12. 转换为 RgbaImage
         let t13 = Instant::now();
         let result = pixel_buffer_to_rgba_image(pixel_buffer.as_ref());
-        debug!("[性能] 13. 转换为 RgbaImage: {:?}", t13.elapsed());
+        config.record_stage(Stage::Conversion, t13.elapsed());
+        timing!(log_timings, "[性能] 13. 转换为 RgbaImage: {:?}", t13.elapsed());
 
-        debug!("[性能] 总耗时: {:?}", total_start.elapsed());
+        timing!(log_timings, "[性能] 总耗时: {:?}", total_start.elapsed());
         result
     }
 }
@@ -10,7 +10,7 @@ use objc2_core_graphics::{
 
 use crate::error::{XCapError, XCapResult};
 
-use super::bgra_to_rgba::convert_bgra_to_rgba_row;
+use crate::conversion::bgra_to_rgba_row;
 
 /// 使用 CGWindowListCreateImage 进行屏幕捕获（传统方法，已废弃）
 /// 作为 ScreenCaptureKit 的回退方案
@@ -64,7 +64,7 @@ fn capture_with_cgwindowlist_sync(
             let src_ptr = row_data.as_ptr();
             let dst_row_ptr = dst_ptr.add(dst_offset);
             // 使用 SIMD 优化的行转换函数
-            convert_bgra_to_rgba_row(src_ptr, dst_row_ptr, width);
+            bgra_to_rgba_row(src_ptr, dst_row_ptr, width);
             dst_offset += width * 4;
         }
         buffer.set_len(width * height * 4);
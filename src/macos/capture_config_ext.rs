@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use objc2_core_media::{CMTime, CMTimeFlags};
+use objc2_screen_capture_kit::SCStreamConfiguration;
+
+use crate::CaptureConfig;
+
+// SCStream 允许的队列深度范围
+const MIN_QUEUE_DEPTH: usize = 1;
+const MAX_QUEUE_DEPTH: usize = 8;
+
+/// ScreenCaptureKit 流的配置，同时作为流缓存键的一部分，默认值与之前固定的配置一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct StreamOptions {
+    queue_depth: usize,
+    minimum_frame_interval: Option<Duration>,
+    captures_audio: bool,
+    scales_to_fit: bool,
+    shows_cursor: bool,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        StreamOptions {
+            // 截图只需要最新的一帧
+            queue_depth: 1,
+            minimum_frame_interval: None,
+            captures_audio: false,
+            // scale > 1.0 时输出尺寸可能大于物理像素，默认 scalesToFit=false
+            // 只会缩小不会放大，导致内容只占据部分画面
+            scales_to_fit: true,
+            shows_cursor: true,
+        }
+    }
+}
+
+impl StreamOptions {
+    /// 是否显示鼠标指针由全局配置决定，见 [`crate::ConfigBuilder::show_cursor`]
+    pub(super) fn with_shows_cursor(self, shows_cursor: bool) -> StreamOptions {
+        StreamOptions {
+            shows_cursor,
+            ..self
+        }
+    }
+
+    pub(super) fn apply(&self, stream_config: &SCStreamConfiguration) {
+        unsafe {
+            stream_config.setQueueDepth(self.queue_depth as isize);
+            stream_config.setScalesToFit(self.scales_to_fit);
+            stream_config.setCapturesAudio(self.captures_audio);
+            stream_config.setShowsCursor(self.shows_cursor);
+
+            if let Some(minimum_frame_interval) = self.minimum_frame_interval {
+                stream_config.setMinimumFrameInterval(CMTime {
+                    value: minimum_frame_interval.as_micros() as i64,
+                    timescale: 1_000_000,
+                    flags: CMTimeFlags::Valid,
+                    epoch: 0,
+                });
+            }
+        }
+    }
+}
+
+/// macOS-only ScreenCaptureKit settings for [`CaptureConfig`], to trade latency for smoothness.
+/// They apply to captures that go through ScreenCaptureKit on macOS 12.3 and later.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use xcap::{CaptureConfig, CaptureConfigExt, Monitor};
+///
+/// let config = CaptureConfig::new()
+///     .queue_depth(3)
+///     .minimum_frame_interval(Duration::from_millis(16));
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let image = monitor.capture_image_with_config(&config).unwrap();
+/// ```
+pub trait CaptureConfigExt {
+    /// Frames the stream keeps in flight, from 1 to 8, defaults to 1. Deeper queues smooth out
+    /// slow consumers at the cost of latency.
+    fn queue_depth(self, queue_depth: usize) -> Self;
+
+    /// Shortest time between frames, by default the display's refresh rate.
+    fn minimum_frame_interval(self, minimum_frame_interval: Duration) -> Self;
+
+    /// Capture system audio along with the screen (macOS 13 and later), disabled by default.
+    fn captures_audio(self, captures_audio: bool) -> Self;
+
+    /// Scale the content to fill the output size, enabled by default.
+    fn scales_to_fit(self, scales_to_fit: bool) -> Self;
+}
+
+impl CaptureConfigExt for CaptureConfig {
+    fn queue_depth(mut self, queue_depth: usize) -> Self {
+        self.stream_options.queue_depth = queue_depth.clamp(MIN_QUEUE_DEPTH, MAX_QUEUE_DEPTH);
+        self
+    }
+
+    fn minimum_frame_interval(mut self, minimum_frame_interval: Duration) -> Self {
+        self.stream_options.minimum_frame_interval = Some(minimum_frame_interval);
+        self
+    }
+
+    fn captures_audio(mut self, captures_audio: bool) -> Self {
+        self.stream_options.captures_audio = captures_audio;
+        self
+    }
+
+    fn scales_to_fit(mut self, scales_to_fit: bool) -> Self {
+        self.stream_options.scales_to_fit = scales_to_fit;
+        self
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    cell::Cell,
+    sync::{
+        Mutex,
+        mpsc::{self, SendError, Sender},
+    },
+    thread,
+};
+
+use crate::error::{XCapError, XCapResult, catch_panics};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// 执行 ScreenCaptureKit 调用的线程，SCStream 和 SCShareableContent 只在这个线程上创建、缓存和释放
+static CAPTURE_THREAD: Mutex<Option<Sender<Job>>> = Mutex::new(None);
+
+thread_local! {
+    static IS_CAPTURE_THREAD: Cell<bool> = const { Cell::new(false) };
+}
+
+fn capture_thread_error() -> XCapError {
+    XCapError::new("Capture thread exited")
+}
+
+fn spawn() -> XCapResult<Sender<Job>> {
+    let (tx, rx) = mpsc::channel::<Job>();
+    thread::Builder::new()
+        .name("xcap-capture".to_string())
+        .spawn(move || {
+            IS_CAPTURE_THREAD.set(true);
+            // 所有发送端释放后退出，线程本地的缓存随线程一起释放
+            for job in rx {
+                job();
+            }
+        })?;
+
+    Ok(tx)
+}
+
+/// 在截图线程上执行 f 并等待结果，截图线程在第一次调用时创建
+///
+/// 缓存都在同一个线程上，性能不再取决于调用方（例如 tokio 的工作线程）所在的线程，
+/// 调用方线程上也不会留下 Retained 对象。已经在截图线程上时直接执行
+pub(super) fn run_on_capture_thread<T, F>(f: F) -> XCapResult<T>
+where
+    F: FnOnce() -> XCapResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    if IS_CAPTURE_THREAD.get() {
+        return f();
+    }
+
+    let (tx, rx) = mpsc::sync_channel(1);
+    let job: Job = Box::new(move || {
+        let _ = tx.send(catch_panics(f));
+    });
+
+    {
+        let mut capture_thread = CAPTURE_THREAD.lock()?;
+        let job = match capture_thread.as_ref() {
+            Some(sender) => match sender.send(job) {
+                Ok(()) => None,
+                // 线程因为 panic 退出了，重新创建
+                Err(SendError(job)) => Some(job),
+            },
+            None => Some(job),
+        };
+
+        if let Some(job) = job {
+            let sender = spawn()?;
+            sender.send(job).map_err(|_| capture_thread_error())?;
+            *capture_thread = Some(sender);
+        }
+    }
+
+    // 任务中的 ScreenCaptureKit 调用都有自己的超时，这里不需要再设置超时
+    rx.recv().map_err(|_| capture_thread_error())?
+}
+
+/// 在截图线程上执行 f 后让线程退出，截图线程没有创建时什么都不做
+pub(super) fn shutdown_capture_thread<F>(f: F) -> XCapResult<()>
+where
+    F: FnOnce() + Send + 'static,
+{
+    let Some(sender) = CAPTURE_THREAD.lock()?.take() else {
+        return Ok(());
+    };
+
+    let (tx, rx) = mpsc::sync_channel(1);
+    let job: Job = Box::new(move || {
+        f();
+        let _ = tx.send(());
+    });
+    if sender.send(job).is_err() {
+        return Ok(());
+    }
+    drop(sender);
+
+    rx.recv().map_err(|_| capture_thread_error())
+}
@@ -1,6 +1,6 @@
 //! macOS 显示器信息获取工具
 //!
-//! 本模块提供了跨 macOS 版本的显示器 UUID 和序列号获取功能。
+//! 本模块提供了跨 macOS 版本的显示器 UUID、序列号和厂商型号信息获取功能。
 //! 使用多层后备方案确保在 macOS 10.6 及以上版本都能正常工作。
 //!
 //! # 兼容性
@@ -10,13 +10,13 @@
 //! - ✅ 无需特殊权限，沙盒环境兼容
 
 use core::ffi::c_void;
-use objc2::MainThreadMarker;
-use objc2_app_kit::NSScreen;
-use objc2_core_foundation::{CFString, CFUUID, CFDictionary};
+use objc2_core_foundation::{CFData, CFDictionary, CFString, CFType, CFUUID};
 use objc2_core_graphics::CGDirectDisplayID;
-use objc2_foundation::{NSNumber, NSString};
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    error::{XCapError, XCapResult},
+    monitor_identity::{parse_edid, pnp_vendor_id, MonitorIdentity},
+};
 
 // IOKit 类型定义
 #[repr(C)]
@@ -33,6 +33,8 @@ const kIODisplayUUIDKey: &str = "IODisplayUUID";
 const kIODisplaySerialNumberKey: &str = "IODisplaySerialNumber";
 #[allow(non_upper_case_globals)]
 const kIODisplaySerialNumber: &str = "IODisplaySerialNumber";
+#[allow(non_upper_case_globals)]
+const kIODisplayEDIDKey: &str = "IODisplayEDID";
 
 // CoreGraphics 函数声明
 #[link(name = "CoreGraphics", kind = "framework")]
@@ -89,29 +91,7 @@ fn get_display_io_service(display_id: CGDirectDisplayID) -> XCapResult<io_servic
         }
     }
 
-    // 方法2：通过 NSScreen 获取设备描述，然后查找对应的 IOKit 服务
-    // 这在新版本的 macOS 上可能更可靠
-    let screens = NSScreen::screens(unsafe { MainThreadMarker::new_unchecked() });
-    for screen in screens {
-        let device_description = screen.deviceDescription();
-        let screen_number = match device_description.objectForKey(&NSString::from_str("NSScreenNumber")) {
-            Some(num) => num,
-            None => continue,
-        };
-
-        let screen_id = match screen_number.downcast::<NSNumber>() {
-            Ok(num) => num.unsignedIntValue(),
-            Err(_) => continue,
-        };
-
-        if screen_id == display_id {
-            // 尝试从 deviceDescription 获取 IODisplayLocation 或其他标识符
-            // 然后通过 IORegistry 查找对应的服务
-            // 这是一个备用方案
-        }
-    }
-
-    // 方法3：尝试使用 IODisplayForFramebuffer（仅在 display_id 较小时尝试，避免崩溃）
+    // 方法2：尝试使用 IODisplayForFramebuffer（仅在 display_id 较小时尝试，避免崩溃）
     unsafe {
         // framebuffer index 通常是较小的数字
         if display_id > 0 && display_id < 16 {
@@ -288,12 +268,14 @@ pub fn get_display_serial_number(display_id: CGDirectDisplayID) -> XCapResult<St
             let serial_key = CFString::from_str(key);
             let serial_key_ref = serial_key.as_ref() as *const CFString;
 
-            let serial_value = (*info_dict).value(serial_key_ref.cast());
+            let serial_value = (*info_dict).value(serial_key_ref.cast()) as *const CFType;
 
-            if !serial_value.is_null() {
-                // 尝试转换为字符串
-                let serial_ref = serial_value as *const CFString;
-                let serial_string = (*serial_ref).to_string();
+            // 有些显示器的序列号是 CFNumber，类型不是 CFString 时跳过，不能直接转换
+            if let Some(serial_ref) = serial_value
+                .as_ref()
+                .and_then(|value| value.downcast_ref::<CFString>())
+            {
+                let serial_string = serial_ref.to_string();
 
                 if !serial_string.is_empty() {
                     return Ok(serial_string);
@@ -309,3 +291,85 @@ pub fn get_display_serial_number(display_id: CGDirectDisplayID) -> XCapResult<St
     }
 }
 
+
+/// 从 IOKit 的显示器信息字典中读取 EDID
+/// Apple Silicon 上 CGDisplayIOServicePort 不可用，这时会返回错误
+fn get_display_edid(display_id: CGDirectDisplayID) -> XCapResult<Vec<u8>> {
+    unsafe {
+        let service = get_display_io_service(display_id)?;
+
+        // 使用 scopeguard 确保释放服务
+        let _guard = scopeguard::guard(service, |s| {
+            IOObjectRelease(s.0);
+        });
+
+        let info_dict_ptr = IODisplayCreateInfoDictionary(service, kIODisplayOnlyPreferredName);
+        if info_dict_ptr.is_null() {
+            return Err(XCapError::new(format!(
+                "Failed to create info dictionary for display {}",
+                display_id
+            )));
+        }
+
+        // 使用 scopeguard 确保释放字典
+        let _info_dict_guard = scopeguard::guard((), |_| {
+            CFRelease(info_dict_ptr.cast());
+        });
+
+        let info_dict = info_dict_ptr as *const CFDictionary;
+        let edid_key = CFString::from_str(kIODisplayEDIDKey);
+        let edid_key_ref = edid_key.as_ref() as *const CFString;
+
+        let edid_value = (*info_dict).value(edid_key_ref.cast()) as *const CFType;
+        let Some(edid_ref) = edid_value
+            .as_ref()
+            .and_then(|value| value.downcast_ref::<CFData>())
+        else {
+            return Err(XCapError::new(format!(
+                "Display {} does not provide EDID",
+                display_id
+            )));
+        };
+
+        Ok(edid_ref.to_vec())
+    }
+}
+
+/// 获取显示器的厂商、型号和序列号
+/// 优先解析 IOKit 中的 EDID，读取失败时使用 CoreGraphics 提供的厂商、型号和序列号，
+/// name 为 NSScreen 的本地化名称，EDID 中没有型号名称时使用
+pub fn get_display_identity(
+    display_id: CGDirectDisplayID,
+    name: Option<String>,
+) -> XCapResult<MonitorIdentity> {
+    match get_display_edid(display_id).and_then(|edid| parse_edid(&edid)) {
+        Ok(edid) => {
+            let mut identity = edid.identity;
+            identity.name = identity.name.or(name);
+            return Ok(identity);
+        }
+        Err(err) => log::debug!("read display {} EDID failed: {:?}", display_id, err),
+    }
+
+    unsafe {
+        let vendor = CGDisplayVendorNumber(display_id);
+        let model = CGDisplayModelNumber(display_id);
+        let serial_num = CGDisplaySerialNumber(display_id);
+
+        if vendor == 0 && model == 0 {
+            return Err(XCapError::new(format!(
+                "Display {} does not provide vendor information",
+                display_id
+            )));
+        }
+
+        Ok(MonitorIdentity {
+            // CoreGraphics 返回的厂商 ID 和 EDID 中的制造商 ID 编码相同
+            vendor: pnp_vendor_id(vendor as u16),
+            product: model as u16,
+            serial: (serial_num != 0).then(|| serial_num.to_string()),
+            name,
+            manufacture_date: None,
+        })
+    }
+}
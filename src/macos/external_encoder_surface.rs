@@ -0,0 +1,115 @@
+use std::{ffi::c_void, ptr::NonNull, sync::Arc};
+
+use objc2_core_foundation::CFRetained;
+use objc2_core_video::CVPixelBuffer;
+
+use crate::{Frame, FrameExt};
+
+#[link(name = "CoreVideo", kind = "framework")]
+unsafe extern "C" {
+    fn CVPixelBufferGetIOSurface(pixel_buffer: *const CVPixelBuffer) -> *mut c_void;
+}
+
+#[link(name = "IOSurface", kind = "framework")]
+unsafe extern "C" {
+    fn IOSurfaceGetID(surface: *mut c_void) -> u32;
+    fn IOSurfaceGetWidth(surface: *mut c_void) -> usize;
+    fn IOSurfaceGetHeight(surface: *mut c_void) -> usize;
+    fn IOSurfaceIncrementUseCount(surface: *mut c_void);
+    fn IOSurfaceDecrementUseCount(surface: *mut c_void);
+}
+
+/// 增加 IOSurface 的使用计数，释放时减少，其他进程通过 IOSurfaceIsInUse 可以知道表面仍在使用
+#[derive(Debug)]
+struct SurfaceUse {
+    // 持有像素缓冲区，IOSurface 的生命周期不会短于它
+    pixel_buffer: CFRetained<CVPixelBuffer>,
+    io_surface: NonNull<c_void>,
+}
+
+impl Drop for SurfaceUse {
+    fn drop(&mut self) {
+        unsafe { IOSurfaceDecrementUseCount(self.io_surface.as_ptr()) };
+    }
+}
+
+// SAFETY: SurfaceUse 放在 Arc 中随帧发送到其他线程，也可能在其他线程中最后一次释放
+//
+// 1. CVPixelBuffer 是 CF 对象，CFRetain/CFRelease 在任意线程调用都是安全的
+//    （Threading Programming Guide 的 Thread Safety Summary）
+// 2. IOSurface 是为跨线程、跨进程共享设计的，IOSurfaceIncrementUseCount/IOSurfaceDecrementUseCount
+//    是原子计数，可以在任意线程调用，Drop 中减少计数不要求在增加计数的线程中进行
+// 3. io_surface 指针只用于 IOSurfaceGetID、IOSurfaceGetWidth 等只读查询，表面内容按照
+//    ExternalEncoderSurface 的同步约定只读，CPU 读取时通过 IOSurfaceLock 的只读锁同步
+unsafe impl Send for SurfaceUse {}
+unsafe impl Sync for SurfaceUse {}
+
+/// The IOSurface a recorder frame was captured into, so an external encoder, e.g.
+/// VideoToolbox or a Metal pipeline, can read it without a copy to system memory. See
+/// [`Frame::encoder_surface`].
+///
+/// Synchronization contract:
+///
+/// 1. ScreenCaptureKit only delivers a surface once the system has finished writing it, so no
+///    fence or semaphore is needed before reading it, e.g. with
+///    `MTLDevice::newTextureWithDescriptor:iosurface:plane:` or with
+///    `VTCompressionSessionEncodeFrame` on the
+///    [`pixel_buffer`](ExternalEncoderSurface::pixel_buffer).
+/// 2. Keep the surface, or the frame it came from, alive until the encoder's reads have
+///    completed, e.g. in a Metal command buffer's completion handler. While it is alive the
+///    surface is retained and its use count is incremented, so the capture session does not
+///    write to it.
+/// 3. Never write to the surface. Lock it with `kIOSurfaceLockReadOnly` for CPU reads.
+///
+/// The surface holds the whole frame in BGRA as delivered by the system, before
+/// [`RecorderConfig::frame_hook`](crate::RecorderConfig::frame_hook). Frames cropped with
+/// [`RecorderConfig::follow_window`](crate::RecorderConfig::follow_window) have no surface.
+/// Surfaces come from a pool shared with the capture session, so drop them promptly.
+#[derive(Debug, Clone)]
+pub struct ExternalEncoderSurface {
+    surface_use: Arc<SurfaceUse>,
+}
+
+impl ExternalEncoderSurface {
+    fn new(pixel_buffer: &CVPixelBuffer) -> Option<ExternalEncoderSurface> {
+        let io_surface = NonNull::new(unsafe { CVPixelBufferGetIOSurface(pixel_buffer) })?;
+        let pixel_buffer = unsafe { CFRetained::retain(NonNull::from(pixel_buffer)) };
+        unsafe { IOSurfaceIncrementUseCount(io_surface.as_ptr()) };
+
+        Some(ExternalEncoderSurface {
+            surface_use: Arc::new(SurfaceUse {
+                pixel_buffer,
+                io_surface,
+            }),
+        })
+    }
+
+    /// The `IOSurfaceRef`, valid while the surface is alive.
+    pub fn io_surface(&self) -> *mut c_void {
+        self.surface_use.io_surface.as_ptr()
+    }
+
+    /// The global id of the surface, see `IOSurfaceGetID`.
+    pub fn io_surface_id(&self) -> u32 {
+        unsafe { IOSurfaceGetID(self.io_surface()) }
+    }
+
+    /// The pixel buffer backed by the surface, for VideoToolbox.
+    pub fn pixel_buffer(&self) -> &CVPixelBuffer {
+        &self.surface_use.pixel_buffer
+    }
+
+    /// The surface width in pixels.
+    pub fn width(&self) -> u32 {
+        unsafe { IOSurfaceGetWidth(self.io_surface()) as u32 }
+    }
+
+    /// The surface height in pixels.
+    pub fn height(&self) -> u32 {
+        unsafe { IOSurfaceGetHeight(self.io_surface()) as u32 }
+    }
+}
+
+pub(crate) fn encoder_surface(frame: &Frame) -> Option<ExternalEncoderSurface> {
+    frame.pixel_buffer().and_then(ExternalEncoderSurface::new)
+}
@@ -0,0 +1,74 @@
+use objc2_core_foundation::CFRetained;
+use objc2_core_media::CMSampleBuffer;
+use objc2_core_video::CVPixelBuffer;
+
+use crate::Frame;
+
+/// 录制回调中收到的原始缓冲区，随帧一起传递，不做任何复制
+#[derive(Debug, Clone)]
+pub(crate) struct NativeBuffers {
+    sample_buffer: CFRetained<CMSampleBuffer>,
+    pixel_buffer: CFRetained<CVPixelBuffer>,
+}
+
+// SAFETY: 帧需要通过 channel 发送到其他线程，并且和其他平台一样可以在多个线程中共享
+//
+// 1. CMSampleBuffer 和 CVPixelBuffer 都是 CF 对象，CFRetain/CFRelease 在任意线程调用都是安全的
+//    （Threading Programming Guide 的 Thread Safety Summary），CFRetained 的 clone 和 drop 只做这两件事
+// 2. AVCaptureVideoDataOutput 的回调文档允许 CFRetain 样本缓冲区后在回调之外、其他队列中使用，
+//    交给回调时缓冲区的数据已经准备好，系统不会再修改它，像素缓冲区在被持有期间也不会回到池中被复用
+// 3. 这里只提供共享引用。CoreVideo 读取像素需要先调用 CVPixelBufferLockBaseAddress，锁有计数，
+//    多个线程可以同时持有 kCVPixelBufferLock_ReadOnly 锁；xcap 自身不会修改这两个缓冲区
+unsafe impl Send for NativeBuffers {}
+unsafe impl Sync for NativeBuffers {}
+
+impl NativeBuffers {
+    pub(crate) fn new(
+        sample_buffer: CFRetained<CMSampleBuffer>,
+        pixel_buffer: CFRetained<CVPixelBuffer>,
+    ) -> NativeBuffers {
+        NativeBuffers {
+            sample_buffer,
+            pixel_buffer,
+        }
+    }
+}
+
+/// macOS-only access to the buffers a recorder [`Frame`] was read from, so AVFoundation and
+/// VideoToolbox encoders can consume them without any conversion.
+///
+/// The buffers hold the frame as delivered by the system in BGRA, before any
+/// [`RecorderConfig::frame_hook`](crate::RecorderConfig::frame_hook) edits. They come from a
+/// pool shared with the capture session, so drop frames promptly to avoid stalling it. Frames
+/// that were not produced by a recorder, that were decompressed, or that were cropped with
+/// [`RecorderConfig::follow_window`](crate::RecorderConfig::follow_window), have no buffers.
+///
+/// ```no_run
+/// use xcap::{FrameExt, Monitor};
+///
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let (recorder, rx) = monitor.video_recorder().unwrap();
+/// recorder.start().unwrap();
+///
+/// let frame = rx.recv().unwrap();
+/// if let Some(pixel_buffer) = frame.pixel_buffer() {
+///     println!("pixel buffer: {pixel_buffer:?}");
+/// }
+/// ```
+pub trait FrameExt {
+    /// The sample buffer the frame was delivered in, with its timing and attachments.
+    fn sample_buffer(&self) -> Option<&CMSampleBuffer>;
+
+    /// The pixel buffer holding the frame's image.
+    fn pixel_buffer(&self) -> Option<&CVPixelBuffer>;
+}
+
+impl FrameExt for Frame {
+    fn sample_buffer(&self) -> Option<&CMSampleBuffer> {
+        self.native.as_ref().map(|native| &*native.sample_buffer)
+    }
+
+    fn pixel_buffer(&self) -> Option<&CVPixelBuffer> {
+        self.native.as_ref().map(|native| &*native.pixel_buffer)
+    }
+}
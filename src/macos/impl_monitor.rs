@@ -1,50 +1,159 @@
-use std::sync::mpsc::Receiver;
+use std::{
+    ffi::{CStr, c_char, c_int, c_void},
+    mem,
+    sync::{Arc, mpsc::Receiver},
+};
 
-use image::RgbaImage;
-use objc2::MainThreadMarker;
+use image::{imageops, RgbaImage};
 use objc2_app_kit::NSScreen;
 use objc2_core_foundation::CGPoint;
 use objc2_core_graphics::{
     CGDirectDisplayID, CGDisplayBounds, CGDisplayCopyDisplayMode, CGDisplayIsActive,
-    CGDisplayIsBuiltin, CGDisplayIsMain, CGDisplayMode, CGDisplayRotation, CGError,
-    CGGetActiveDisplayList, CGGetDisplaysWithPoint, CGWindowListOption,
+    CGDisplayIsAsleep, CGDisplayIsBuiltin, CGDisplayIsInMirrorSet, CGDisplayIsMain,
+    CGDisplayMirrorsDisplay, CGDisplayMode, CGDisplayModeCopyPixelEncoding,
+    CGDisplayPrimaryDisplay, CGDisplayRotation, CGError, CGGetActiveDisplayList,
+    CGGetDisplaysWithPoint, CGGetOnlineDisplayList, CGWindowListOption,
 };
 use objc2_foundation::{NSNumber, NSString};
 
 use crate::{
+    CaptureConfig, FramePacing, Monitor, MonitorIdentity, PixelEncoding, PowerState,
+    RecorderConfig, RefreshRateRange,
+    delayed_capture::wait_refresh_periods,
     error::{XCapError, XCapResult},
-    video_recorder::Frame,
+    geometry::{Rect, map_rect},
+    video_recorder::{Frame, RecorderHealth, vsync_frequency},
 };
 
-use super::{capture::capture, capture::capture_with_scale, display_info, impl_video_recorder::ImplVideoRecorder};
+use super::{capture::capture, capture::capture_with_config, capture::capture_with_scale, display_info, has_screen_capture_access, impl_video_recorder::ImplVideoRecorder, main_thread::run_on_main};
 
 #[derive(Debug, Clone)]
 pub(crate) struct ImplMonitor {
     pub cg_direct_display_id: CGDirectDisplayID,
 }
 
+fn get_screen_id(screen: &NSScreen) -> XCapResult<CGDirectDisplayID> {
+    let device_description = screen.deviceDescription();
+    let screen_number = device_description
+        .objectForKey(&NSString::from_str("NSScreenNumber"))
+        .ok_or(XCapError::new("Get NSScreenNumber failed"))?;
+
+    let screen_id = screen_number
+        .downcast::<NSNumber>()
+        .map_err(|err| XCapError::new(format!("{:?}", err)))?
+        .unsignedIntValue();
+
+    Ok(screen_id)
+}
+
 fn get_display_friendly_name(display_id: CGDirectDisplayID) -> XCapResult<String> {
-    let screens = NSScreen::screens(unsafe { MainThreadMarker::new_unchecked() });
-    for screen in screens {
-        let device_description = screen.deviceDescription();
-        let screen_number = device_description
-            .objectForKey(&NSString::from_str("NSScreenNumber"))
-            .ok_or(XCapError::new("Get NSScreenNumber failed"))?;
-
-        let screen_id = screen_number
-            .downcast::<NSNumber>()
-            .map_err(|err| XCapError::new(format!("{:?}", err)))?
-            .unsignedIntValue();
-
-        if screen_id == display_id {
-            unsafe { return Ok(screen.localizedName().to_string()) };
+    run_on_main(move |mtm| {
+        let screens = NSScreen::screens(mtm);
+        for screen in screens {
+            if get_screen_id(&screen)? == display_id {
+                unsafe { return Ok(screen.localizedName().to_string()) };
+            }
         }
+
+        Err(XCapError::new(format!(
+            "Get display {} friendly name failed",
+            display_id
+        )))
+    })?
+}
+
+// DisplayServices 是私有框架，CoreDisplay 的亮度函数没有公开，都在运行时加载
+unsafe extern "C" {
+    fn dlopen(path: *const c_char, mode: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+}
+
+const RTLD_LAZY: c_int = 0x1;
+
+type DisplayServicesGetBrightness = unsafe extern "C" fn(CGDirectDisplayID, *mut f32) -> c_int;
+type CoreDisplayGetUserBrightness = unsafe extern "C" fn(CGDirectDisplayID) -> f64;
+
+fn load_symbol(path: &CStr, symbol: &CStr) -> Option<*mut c_void> {
+    let handle = unsafe { dlopen(path.as_ptr(), RTLD_LAZY) };
+    if handle.is_null() {
+        return None;
     }
 
-    Err(XCapError::new(format!(
-        "Get display {} friendly name failed",
-        display_id
-    )))
+    let symbol = unsafe { dlsym(handle, symbol.as_ptr()) };
+
+    (!symbol.is_null()).then_some(symbol)
+}
+
+/// 内置屏幕和 Apple 显示器的亮度，其他外接显示器不支持
+fn get_display_brightness(display_id: CGDirectDisplayID) -> XCapResult<f32> {
+    if let Some(symbol) = load_symbol(
+        c"/System/Library/PrivateFrameworks/DisplayServices.framework/DisplayServices",
+        c"DisplayServicesGetBrightness",
+    ) {
+        let get_brightness: DisplayServicesGetBrightness = unsafe { mem::transmute(symbol) };
+        let mut brightness: f32 = 0.0;
+        if unsafe { get_brightness(display_id, &mut brightness) } == 0 {
+            return Ok(brightness.clamp(0.0, 1.0));
+        }
+    }
+
+    // 旧版本系统没有 DisplayServices 时使用 CoreDisplay
+    if let Some(symbol) = load_symbol(
+        c"/System/Library/Frameworks/CoreDisplay.framework/CoreDisplay",
+        c"CoreDisplay_Display_GetUserBrightness",
+    ) {
+        let get_brightness: CoreDisplayGetUserBrightness = unsafe { mem::transmute(symbol) };
+        let brightness = unsafe { get_brightness(display_id) };
+        if (0.0..=1.0).contains(&brightness) {
+            return Ok(brightness as f32);
+        }
+    }
+
+    Err(XCapError::NotSupported)
+}
+
+/// 获取显示器的最短和最长刷新间隔，ProMotion 显示器的刷新间隔在这个范围内变化
+fn get_display_refresh_intervals(display_id: CGDirectDisplayID) -> XCapResult<(f64, f64)> {
+    run_on_main(move |mtm| {
+        let screens = NSScreen::screens(mtm);
+        for screen in screens {
+            if get_screen_id(&screen)? == display_id {
+                unsafe {
+                    return Ok((
+                        screen.minimumRefreshInterval(),
+                        screen.maximumRefreshInterval(),
+                    ));
+                };
+            }
+        }
+
+        Err(XCapError::new(format!(
+            "Get display {} refresh intervals failed",
+            display_id
+        )))
+    })?
+}
+
+/// 解析 IOKit 的像素编码字符串，返回每个通道的位数和编码，
+/// 例如 "--------RRRRRRRRGGGGGGGGBBBBBBBB" 是 8 位，"-16FR16FG16FB16" 是 16 位浮点
+fn parse_io_pixel_encoding(encoding: &str) -> Option<(u32, PixelEncoding)> {
+    let encoding = encoding.trim_start_matches('-');
+    let digits_len = encoding.chars().take_while(char::is_ascii_digit).count();
+
+    // 按位展开的形式，每个 R 表示红色通道的一位
+    if digits_len == 0 {
+        let bits = encoding.chars().filter(|&c| c == 'R').count() as u32;
+        return (bits > 0).then_some((bits, PixelEncoding::Rgb));
+    }
+
+    let bits = encoding[..digits_len].parse().ok()?;
+    let pixel_encoding = if encoding[digits_len..].starts_with('F') {
+        PixelEncoding::RgbFloat
+    } else {
+        PixelEncoding::Rgb
+    };
+
+    Some((bits, pixel_encoding))
 }
 
 impl ImplMonitor {
@@ -123,38 +232,6 @@ impl ImplMonitor {
             Err(XCapError::new("Monitor not found"))
         }
     }
-
-    pub fn from_unique_key(unique_key: String) -> XCapResult<ImplMonitor> {
-        let monitors = ImplMonitor::all()?;
-
-        for monitor in monitors {
-            // 1. 优先检查序列号（硬件属性，最可靠）
-            if let Ok(serial) = monitor.serial_number() {
-                if !serial.is_empty() && serial == unique_key {
-                    return Ok(monitor);
-                }
-            }
-
-            // 2. 检查 UUID
-            if let Ok(uuid) = monitor.uuid() {
-                if uuid == unique_key {
-                    return Ok(monitor);
-                }
-            }
-
-            // 3. 最后检查显示器 ID
-            if let Ok(id) = monitor.id() {
-                if id.to_string() == unique_key {
-                    return Ok(monitor);
-                }
-            }
-        }
-
-        Err(XCapError::new(format!(
-            "Monitor with unique_key '{}' not found",
-            unique_key
-        )))
-    }
 }
 
 impl ImplMonitor {
@@ -207,6 +284,11 @@ impl ImplMonitor {
         Ok(pixel_width as f32 / width as f32)
     }
 
+    /// 坐标是逻辑点，截图是物理像素
+    pub fn pixel_ratio(&self) -> XCapResult<f32> {
+        self.scale_factor()
+    }
+
     pub fn frequency(&self) -> XCapResult<f32> {
         let frequency = unsafe {
             let display_mode = CGDisplayCopyDisplayMode(self.cg_direct_display_id);
@@ -216,6 +298,45 @@ impl ImplMonitor {
         Ok(frequency as f32)
     }
 
+    fn pixel_encoding_info(&self) -> XCapResult<(u32, PixelEncoding)> {
+        // CGDisplayModeCopyPixelEncoding 已经废弃，但仍然是获取显示模式位深的唯一接口
+        #[allow(deprecated)]
+        let encoding = unsafe {
+            let display_mode = CGDisplayCopyDisplayMode(self.cg_direct_display_id);
+            CGDisplayModeCopyPixelEncoding(display_mode.as_deref())
+        }
+        .ok_or(XCapError::new("Get display pixel encoding failed"))?;
+
+        let encoding = encoding.to_string();
+        parse_io_pixel_encoding(&encoding)
+            .ok_or_else(|| XCapError::new(format!("Unknown pixel encoding {encoding}")))
+    }
+
+    pub fn bit_depth(&self) -> XCapResult<u32> {
+        let (bit_depth, _) = self.pixel_encoding_info()?;
+
+        Ok(bit_depth)
+    }
+
+    pub fn pixel_encoding(&self) -> XCapResult<PixelEncoding> {
+        let (_, pixel_encoding) = self.pixel_encoding_info()?;
+
+        Ok(pixel_encoding)
+    }
+
+    pub fn refresh_rate_range(&self) -> XCapResult<RefreshRateRange> {
+        let (min_interval, max_interval) =
+            get_display_refresh_intervals(self.cg_direct_display_id)?;
+        if min_interval <= 0.0 || max_interval <= 0.0 {
+            return Err(XCapError::new("Get display refresh rate range failed"));
+        }
+
+        Ok(RefreshRateRange {
+            min: (1.0 / max_interval) as f32,
+            max: (1.0 / min_interval) as f32,
+        })
+    }
+
     pub fn is_primary(&self) -> XCapResult<bool> {
         let is_primary = unsafe { CGDisplayIsMain(self.cg_direct_display_id) };
 
@@ -228,6 +349,68 @@ impl ImplMonitor {
         Ok(is_builtin)
     }
 
+    pub fn can_capture(&self) -> XCapResult<bool> {
+        Ok(has_screen_capture_access())
+    }
+
+    pub fn power_state(&self) -> XCapResult<PowerState> {
+        let is_asleep = unsafe { CGDisplayIsAsleep(self.cg_direct_display_id) };
+
+        if is_asleep {
+            Ok(PowerState::Off)
+        } else {
+            Ok(PowerState::On)
+        }
+    }
+
+    pub fn brightness(&self) -> XCapResult<f32> {
+        get_display_brightness(self.cg_direct_display_id)
+    }
+
+    pub fn is_mirrored(&self) -> XCapResult<bool> {
+        let is_mirrored = unsafe { CGDisplayIsInMirrorSet(self.cg_direct_display_id) };
+
+        Ok(is_mirrored)
+    }
+
+    pub fn mirror_group(&self) -> XCapResult<Vec<ImplMonitor>> {
+        if !self.is_mirrored()? {
+            return Ok(vec![self.clone()]);
+        }
+
+        let max_displays: u32 = 16;
+        let mut online_displays: Vec<CGDirectDisplayID> = vec![0; max_displays as usize];
+        let mut display_count: u32 = 0;
+
+        // 硬件镜像时只有主显示器在活动列表中，需要从在线列表中查找镜像组的其他显示器
+        let cg_error = unsafe {
+            CGGetOnlineDisplayList(
+                max_displays,
+                online_displays.as_mut_ptr(),
+                &mut display_count,
+            )
+        };
+
+        if cg_error != CGError::Success {
+            return Err(XCapError::new(format!(
+                "CGGetOnlineDisplayList failed: {:?}",
+                cg_error
+            )));
+        }
+
+        online_displays.truncate(display_count as usize);
+
+        // 同一镜像组中的显示器有相同的主显示器
+        let primary_display = unsafe { CGDisplayPrimaryDisplay(self.cg_direct_display_id) };
+        let impl_monitors = online_displays
+            .into_iter()
+            .filter(|&display| unsafe { CGDisplayPrimaryDisplay(display) } == primary_display)
+            .map(ImplMonitor::new)
+            .collect();
+
+        Ok(impl_monitors)
+    }
+
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
         let cg_rect = unsafe { CGDisplayBounds(self.cg_direct_display_id) };
 
@@ -241,6 +424,32 @@ impl ImplMonitor {
         capture_with_scale(cg_rect, CGWindowListOption::OptionAll, 0, Some(self.cg_direct_display_id), scale)
     }
 
+    pub fn wait_for_present(&self) -> XCapResult<()> {
+        // WindowServer 在 vsync 时合成，按当前刷新率等待，ProMotion 显示器降频时周期更长
+        wait_refresh_periods(self.frequency()?);
+
+        Ok(())
+    }
+
+    pub fn capture_thumbnail(&self, width: u32, height: u32) -> XCapResult<RgbaImage> {
+        let cg_rect = unsafe { CGDisplayBounds(self.cg_direct_display_id) };
+        // 复用缓存的缩放 SCStream，由 ScreenCaptureKit 完成缩放
+        let scale = width as f64 / cg_rect.size.width;
+
+        let image = capture_with_scale(cg_rect, CGWindowListOption::OptionAll, 0, Some(self.cg_direct_display_id), scale as f32)?;
+        if image.dimensions() == (width, height) {
+            return Ok(image);
+        }
+
+        Ok(imageops::thumbnail(&image, width, height))
+    }
+
+    pub fn capture_image_with_config(&self, config: &CaptureConfig) -> XCapResult<RgbaImage> {
+        let cg_rect = unsafe { CGDisplayBounds(self.cg_direct_display_id) };
+
+        capture_with_config(cg_rect, CGWindowListOption::OptionAll, 0, Some(self.cg_direct_display_id), config)
+    }
+
     pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
         // Validate region bounds
         let monitor_x = self.x()?;
@@ -248,35 +457,94 @@ impl ImplMonitor {
         let monitor_width = self.width()?;
         let monitor_height = self.height()?;
 
-        if width > monitor_width
-            || height > monitor_height
-            || x + width > monitor_width
-            || y + height > monitor_height
-        {
+        let region = Rect::new(x as i32, y as i32, width, height);
+        if !Rect::new(0, 0, monitor_width, monitor_height).contains_rect(region) {
             return Err(XCapError::InvalidCaptureRegion(format!(
                 "Region ({}, {}, {}, {}) is outside monitor bounds ({}, {}, {}, {})",
                 x, y, width, height, monitor_x, monitor_y, monitor_width, monitor_height
             )));
         }
 
+        let (display_id, global_region) = self.mirror_source_region(region)?;
+
         // Create a CGRect for the region to capture
         let cg_rect = objc2_core_foundation::CGRect {
             origin: objc2_core_foundation::CGPoint {
-                x: (monitor_x + x as i32) as f64,
-                y: (monitor_y + y as i32) as f64,
+                x: global_region.x as f64,
+                y: global_region.y as f64,
             },
             size: objc2_core_foundation::CGSize {
-                width: width as f64,
-                height: height as f64,
+                width: global_region.width as f64,
+                height: global_region.height as f64,
             },
         };
 
         // 优化：直接传递 display_id，避免在 capture 函数中重复查找显示器
-        capture(cg_rect, CGWindowListOption::OptionAll, 0, Some(self.cg_direct_display_id))
+        capture(cg_rect, CGWindowListOption::OptionAll, 0, Some(display_id))
+    }
+
+    /// 把显示器内的区域换算为实际截取的显示器和全局坐标
+    ///
+    /// 镜像显示器显示的是主显示器缩放后的画面，分辨率不同时按比例缩放并居中（留黑边），
+    /// 截图只能从主显示器读取，所以需要把区域映射回主显示器的坐标
+    fn mirror_source_region(&self, region: Rect) -> XCapResult<(CGDirectDisplayID, Rect)> {
+        let bounds = unsafe { CGDisplayBounds(self.cg_direct_display_id) };
+        let source_id = unsafe { CGDisplayMirrorsDisplay(self.cg_direct_display_id) };
+
+        // kCGNullDirectDisplay 表示没有镜像其他显示器
+        if source_id == 0 {
+            let offset = (bounds.origin.x, bounds.origin.y);
+            return Ok((self.cg_direct_display_id, map_rect(region, (1.0, 1.0), offset)));
+        }
+
+        let source_bounds = unsafe { CGDisplayBounds(source_id) };
+        if source_bounds.size.width <= 0.0 || source_bounds.size.height <= 0.0 {
+            return Err(XCapError::new("Get mirror source display bounds failed"));
+        }
+
+        let scale = (bounds.size.width / source_bounds.size.width)
+            .min(bounds.size.height / source_bounds.size.height);
+        let padding_x = (bounds.size.width - source_bounds.size.width * scale) / 2.0;
+        let padding_y = (bounds.size.height - source_bounds.size.height * scale) / 2.0;
+
+        let source_region = map_rect(
+            region,
+            (1.0 / scale, 1.0 / scale),
+            (
+                source_bounds.origin.x - padding_x / scale,
+                source_bounds.origin.y - padding_y / scale,
+            ),
+        );
+        let source_rect = Rect::new(
+            source_bounds.origin.x as i32,
+            source_bounds.origin.y as i32,
+            source_bounds.size.width as u32,
+            source_bounds.size.height as u32,
+        );
+        // 区域完全落在黑边中时没有内容可截
+        let source_region = source_rect.intersection(source_region).ok_or(
+            XCapError::InvalidCaptureRegion(format!(
+                "Region ({}, {}, {}, {}) only covers the letterbox of a mirrored display",
+                region.x, region.y, region.width, region.height
+            )),
+        )?;
+
+        Ok((source_id, source_region))
     }
 
-    pub fn video_recorder(&self) -> XCapResult<(ImplVideoRecorder, Receiver<Frame>)> {
-        ImplVideoRecorder::new(self.cg_direct_display_id)
+    pub fn video_recorder(
+        &self,
+        config: &RecorderConfig,
+        health: Arc<RecorderHealth>,
+    ) -> XCapResult<(ImplVideoRecorder, Receiver<Frame>)> {
+        // 内建屏幕的显示模式刷新率为 0，ProMotion 屏幕按最高刷新率对齐 vsync，
+        // 只有按 vsync 输出时才需要从主线程读取 NSScreen
+        let frequency = match config.pacing {
+            FramePacing::Vsync => vsync_frequency(&Monitor::new(self.clone()))?,
+            FramePacing::FreeRunning => self.frequency()?,
+        };
+
+        ImplVideoRecorder::new(self.cg_direct_display_id, frequency, config, health)
     }
 
     /// 获取显示器的 UUID（持久化唯一标识符）
@@ -290,4 +558,22 @@ impl ImplMonitor {
     pub fn serial_number(&self) -> XCapResult<String> {
         display_info::get_display_serial_number(self.cg_direct_display_id)
     }
+
+    /// 获取显示器的厂商、型号和序列号
+    /// 内置显示器通常没有 EDID，使用 CoreGraphics 提供的信息
+    pub fn identity(&self) -> XCapResult<MonitorIdentity> {
+        let name = get_display_friendly_name(self.cg_direct_display_id).ok();
+
+        display_info::get_display_identity(self.cg_direct_display_id, name)
+    }
+
+    #[cfg(feature = "ddc")]
+    pub fn ddc_capabilities(&self) -> XCapResult<String> {
+        Err(XCapError::NotSupported)
+    }
+
+    #[cfg(feature = "ddc")]
+    pub fn vcp_feature(&self, _code: u8) -> XCapResult<(u16, u16)> {
+        Err(XCapError::NotSupported)
+    }
 }
@@ -0,0 +1,141 @@
+use objc2::{MainThreadMarker, MainThreadOnly, rc::Retained};
+use objc2_app_kit::{
+    NSApplication, NSBackingStoreType, NSColor, NSCursor, NSEvent, NSEventMask, NSEventType,
+    NSWindow, NSWindowStyleMask,
+};
+use objc2_core_foundation::{CGPoint, CGRect, CGSize};
+use objc2_core_graphics::{CGDisplayBounds, CGMainDisplayID};
+use objc2_foundation::{NSDate, NSDefaultRunLoopMode};
+use scopeguard::defer;
+
+use crate::{
+    error::{XCapError, XCapResult},
+    geometry::{Point, Rect},
+    selector::{Outcome, Selection},
+};
+
+// kCGScreenSaverWindowLevel，在菜单栏和 Dock 之上
+const OVERLAY_WINDOW_LEVEL: isize = 1000;
+// Escape 的虚拟键码 kVK_Escape
+const ESCAPE_KEY_CODE: u16 = 53;
+const OVERLAY_ALPHA: f64 = 0.35;
+const SELECTION_ALPHA: f64 = 0.25;
+
+/// 每个显示器上显示一个半透明的无边框窗口，选区是覆盖层之上的白色半透明窗口。
+/// 在主线程上自己取出事件处理，不需要自定义 NSView
+pub(crate) fn run_selector(
+    bounds: &[Rect],
+    mut selection: Selection,
+) -> XCapResult<Option<Outcome>> {
+    let mtm = MainThreadMarker::new()
+        .ok_or_else(|| XCapError::new("Selector must be used on the main thread"))?;
+
+    // CoreGraphics 坐标的原点在主显示器左上角，AppKit 坐标的原点在主显示器左下角
+    let primary_height = unsafe { CGDisplayBounds(CGMainDisplayID()).size.height };
+    let to_cocoa_rect = |rect: Rect| {
+        CGRect::new(
+            CGPoint::new(
+                rect.x as f64,
+                primary_height - rect.y as f64 - rect.height as f64,
+            ),
+            CGSize::new(rect.width as f64, rect.height as f64),
+        )
+    };
+
+    let overlays = bounds
+        .iter()
+        .map(|rect| {
+            let window = create_window(mtm, to_cocoa_rect(*rect), OVERLAY_ALPHA, 0.0);
+            window.makeKeyAndOrderFront(None);
+            window
+        })
+        .collect::<Vec<_>>();
+    let highlight = create_window(
+        mtm,
+        CGRect::new(CGPoint::ZERO, CGSize::ZERO),
+        SELECTION_ALPHA,
+        1.0,
+    );
+
+    let app = NSApplication::sharedApplication(mtm);
+    #[allow(deprecated)]
+    app.activateIgnoringOtherApps(true);
+    NSCursor::crosshairCursor().push();
+    defer! {
+        NSCursor::pop();
+        highlight.close();
+        for overlay in &overlays {
+            overlay.close();
+        }
+    }
+
+    let event_point = || {
+        let location = NSEvent::mouseLocation();
+        Point::new(
+            location.x.floor() as i32,
+            (primary_height - location.y).floor() as i32,
+        )
+    };
+
+    loop {
+        let event = unsafe {
+            app.nextEventMatchingMask_untilDate_inMode_dequeue(
+                NSEventMask::Any,
+                Some(&NSDate::distantFuture()),
+                NSDefaultRunLoopMode,
+                true,
+            )
+        };
+        let Some(event) = event else {
+            continue;
+        };
+
+        match event.r#type() {
+            NSEventType::LeftMouseDown => selection.press(event_point()),
+            NSEventType::LeftMouseDragged => selection.drag(event_point()),
+            NSEventType::LeftMouseUp => {
+                if let Some(outcome) = selection.release(event_point()) {
+                    return Ok(Some(outcome));
+                }
+            }
+            NSEventType::RightMouseDown => return Ok(None),
+            NSEventType::KeyDown if event.keyCode() == ESCAPE_KEY_CODE => return Ok(None),
+            // 其他事件交给应用处理，按键不转发，避免传给宿主程序的窗口
+            NSEventType::KeyDown | NSEventType::KeyUp => {}
+            _ => app.sendEvent(&event),
+        }
+
+        match selection.rect() {
+            Some(rect) => {
+                highlight.setFrame_display(to_cocoa_rect(rect), true);
+                highlight.orderFrontRegardless();
+            }
+            None => highlight.orderOut(None),
+        }
+    }
+}
+
+fn create_window(
+    mtm: MainThreadMarker,
+    frame: CGRect,
+    alpha: f64,
+    white: f64,
+) -> Retained<NSWindow> {
+    unsafe {
+        let window = NSWindow::initWithContentRect_styleMask_backing_defer(
+            NSWindow::alloc(mtm),
+            frame,
+            NSWindowStyleMask::Borderless,
+            NSBackingStoreType::Buffered,
+            false,
+        );
+        // 窗口由 Retained 管理，关闭时不能再释放一次
+        window.setReleasedWhenClosed(false);
+        window.setOpaque(false);
+        window.setHasShadow(false);
+        window.setLevel(OVERLAY_WINDOW_LEVEL);
+        window.setBackgroundColor(Some(&NSColor::colorWithCalibratedWhite_alpha(white, alpha)));
+
+        window
+    }
+}
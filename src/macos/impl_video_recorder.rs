@@ -1,32 +1,426 @@
 use std::{
+    ffi::c_void,
+    ptr::{self, NonNull},
     slice,
-    sync::mpsc::{Receiver, SyncSender, sync_channel},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{Receiver, SyncSender, sync_channel},
+    },
+    time::Instant,
 };
 
+use block2::RcBlock;
 use dispatch2::{DispatchQueue, DispatchQueueAttr};
 use objc2::{
-    AllocAnyThread, DefinedClass, define_class, msg_send, rc::Retained, runtime::ProtocolObject,
+    AllocAnyThread, DefinedClass, define_class, msg_send,
+    rc::Retained,
+    runtime::{AnyObject, ProtocolObject},
 };
 use objc2_av_foundation::{
     AVCaptureConnection, AVCaptureOutput, AVCaptureScreenInput, AVCaptureSession,
-    AVCaptureVideoDataOutput, AVCaptureVideoDataOutputSampleBufferDelegate,
+    AVCaptureSessionRuntimeErrorNotification, AVCaptureVideoDataOutput,
+    AVCaptureVideoDataOutputSampleBufferDelegate,
 };
-use objc2_core_graphics::CGDirectDisplayID;
-use objc2_core_media::CMSampleBuffer;
+use objc2_core_foundation::{CFString, Type};
+use objc2_core_graphics::{CGDirectDisplayID, CGDisplayIsAsleep};
+use objc2_core_media::{CMSampleBuffer, CMTime, CMTimeFlags};
 use objc2_core_video::{
     CVPixelBufferGetBaseAddress, CVPixelBufferGetBytesPerRow, CVPixelBufferGetDataSize,
     CVPixelBufferGetHeight, CVPixelBufferGetPixelFormatType, CVPixelBufferGetWidth,
     CVPixelBufferLockBaseAddress, CVPixelBufferLockFlags, CVPixelBufferUnlockBaseAddress,
     kCVPixelBufferPixelFormatTypeKey, kCVPixelFormatType_32BGRA,
 };
-use objc2_foundation::{NSDictionary, NSNumber, NSObject, NSObjectProtocol, NSString};
+use objc2_foundation::{
+    NSDictionary, NSNotification, NSNotificationCenter, NSNumber, NSObject, NSObjectProtocol,
+    NSString,
+};
 use scopeguard::defer;
 
-use crate::{XCapError, XCapResult, video_recorder::Frame};
+use crate::{
+    FramePacing, Monitor, RecorderConfig, RecorderUpdate, RecoveryPolicy, XCapError, XCapResult,
+    clock, conversion,
+    redaction::Redaction,
+    video_recorder::{
+        ChangeDetector, Frame, FrameHook, IdleGate, LiveConfig, RecorderEvent, RecorderHealth,
+        RecorderWaker,
+    },
+    window_crop::WindowCrop,
+};
+
+use super::{frame_ext::NativeBuffers, impl_monitor::ImplMonitor};
+
+// IOKit 电源管理函数声明
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {
+    fn IOPSCopyPowerSourcesInfo() -> *const c_void;
+    fn IOPSGetProvidingPowerSourceType(snapshot: *const c_void) -> *const CFString;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    fn CFRelease(cf: *const c_void);
+}
+
+type DisplayReconfigurationCallback =
+    unsafe extern "C" fn(display: CGDirectDisplayID, flags: u32, user_info: *mut c_void);
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGDisplayRegisterReconfigurationCallback(
+        callback: DisplayReconfigurationCallback,
+        user_info: *mut c_void,
+    ) -> i32;
+    fn CGDisplayRemoveReconfigurationCallback(
+        callback: DisplayReconfigurationCallback,
+        user_info: *mut c_void,
+    ) -> i32;
+}
+
+type CVDisplayLinkRef = *mut c_void;
+
+type DisplayLinkOutputCallback = unsafe extern "C" fn(
+    display_link: CVDisplayLinkRef,
+    now: *const c_void,
+    output_time: *const c_void,
+    flags_in: u64,
+    flags_out: *mut u64,
+    context: *mut c_void,
+) -> i32;
+
+#[link(name = "CoreVideo", kind = "framework")]
+unsafe extern "C" {
+    fn CVDisplayLinkCreateWithCGDisplay(
+        display_id: CGDirectDisplayID,
+        display_link_out: *mut CVDisplayLinkRef,
+    ) -> i32;
+    fn CVDisplayLinkSetOutputCallback(
+        display_link: CVDisplayLinkRef,
+        callback: DisplayLinkOutputCallback,
+        user_info: *mut c_void,
+    ) -> i32;
+    fn CVDisplayLinkStart(display_link: CVDisplayLinkRef) -> i32;
+    fn CVDisplayLinkStop(display_link: CVDisplayLinkRef) -> i32;
+    fn CVDisplayLinkRelease(display_link: CVDisplayLinkRef);
+}
+
+// CGDisplayChangeSummaryFlags，配置开始时的回调不处理，只在配置完成后重建
+const DISPLAY_BEGIN_CONFIGURATION_FLAG: u32 = 1 << 0;
+const DISPLAY_RECONFIGURED_FLAGS: u32 = (1 << 3) | (1 << 4) | (1 << 5) | (1 << 8) | (1 << 9);
+
+fn is_on_battery() -> bool {
+    unsafe {
+        let snapshot = IOPSCopyPowerSourcesInfo();
+        if snapshot.is_null() {
+            return false;
+        }
+        defer! {
+            CFRelease(snapshot);
+        };
+
+        // 返回值属于 snapshot，不需要释放
+        IOPSGetProvidingPowerSourceType(snapshot)
+            .as_ref()
+            .is_some_and(|power_source_type| power_source_type.to_string() == "Battery Power")
+    }
+}
+
+/// AVCaptureScreenInput 的最小帧间隔，None 表示不限制
+fn min_frame_duration(config: &RecorderConfig, low_power: bool, frequency: f32) -> Option<CMTime> {
+    // 低功耗模式和限制帧率时由 AVCaptureScreenInput 直接降低帧率，不会产生多余的帧
+    if let Some(frame_interval) = config.frame_interval(low_power) {
+        return Some(CMTime {
+            value: frame_interval.as_micros() as i64,
+            timescale: 1_000_000,
+            flags: CMTimeFlags::Valid,
+            epoch: 0,
+        });
+    }
+
+    if config.pacing != FramePacing::Vsync {
+        return None;
+    }
+
+    // 帧间隔由 AVCaptureScreenInput 自己计时，和 vblank 之间会漂移，设为略短于一个刷新周期，
+    // 避免有的刷新周期没有帧，同一周期内多余的帧由 DisplayLinkGate 丢弃
+    // 部分显示器（如内建屏幕）返回的刷新率为 0，按 60Hz 处理
+    let frequency = if frequency > 0.0 { frequency } else { 60.0 };
+    Some(CMTime {
+        value: 750,
+        timescale: (frequency * 1000.0).round() as i32,
+        flags: CMTimeFlags::Valid,
+        epoch: 0,
+    })
+}
+
+/// 按 vsync 输出时由 CVDisplayLink 计数显示器的刷新，每个刷新周期只发送第一帧
+#[derive(Debug)]
+struct DisplayLinkGate {
+    // CVDisplayLinkRef，CVDisplayLink 可以在任意线程上启停和释放
+    display_link: usize,
+    // 交给回调的 ticks 引用，释放 display link 之后收回
+    context: usize,
+    ticks: Arc<AtomicU64>,
+    last_tick: AtomicU64,
+}
+
+unsafe extern "C" fn display_link_tick(
+    _display_link: CVDisplayLinkRef,
+    _now: *const c_void,
+    _output_time: *const c_void,
+    _flags_in: u64,
+    _flags_out: *mut u64,
+    context: *mut c_void,
+) -> i32 {
+    // context 是 DisplayLinkGate::new 中交出的引用，Drop 中释放 display link 之后才收回
+    let ticks = unsafe { &*(context as *const AtomicU64) };
+    ticks.fetch_add(1, Ordering::Release);
+
+    0
+}
+
+impl DisplayLinkGate {
+    fn new(cg_direct_display_id: CGDirectDisplayID) -> XCapResult<DisplayLinkGate> {
+        unsafe {
+            let mut display_link: CVDisplayLinkRef = ptr::null_mut();
+            if CVDisplayLinkCreateWithCGDisplay(cg_direct_display_id, &mut display_link) != 0
+                || display_link.is_null()
+            {
+                return Err(XCapError::new("CVDisplayLinkCreateWithCGDisplay failed"));
+            }
+
+            let ticks = Arc::new(AtomicU64::new(0));
+            let gate = DisplayLinkGate {
+                display_link: display_link as usize,
+                context: Arc::into_raw(ticks.clone()) as usize,
+                ticks,
+                last_tick: AtomicU64::new(u64::MAX),
+            };
+
+            if CVDisplayLinkSetOutputCallback(
+                display_link,
+                display_link_tick,
+                gate.context as *mut c_void,
+            ) != 0
+                || CVDisplayLinkStart(display_link) != 0
+            {
+                return Err(XCapError::new("Start CVDisplayLink failed"));
+            }
+
+            Ok(gate)
+        }
+    }
+
+    /// 当前刷新周期还没有发送过帧时返回 true
+    fn accept(&self) -> bool {
+        let tick = self.ticks.load(Ordering::Acquire);
+
+        self.last_tick.swap(tick, Ordering::AcqRel) != tick
+    }
+}
+
+impl Drop for DisplayLinkGate {
+    fn drop(&mut self) {
+        unsafe {
+            let display_link = self.display_link as CVDisplayLinkRef;
+            CVDisplayLinkStop(display_link);
+            CVDisplayLinkRelease(display_link);
+            drop(Arc::from_raw(self.context as *const AtomicU64));
+        }
+    }
+}
+
+/// 按录制设置创建 AVCaptureScreenInput
+fn create_input(
+    cg_direct_display_id: CGDirectDisplayID,
+    config: &RecorderConfig,
+    low_power: bool,
+    frequency: f32,
+) -> XCapResult<Retained<AVCaptureScreenInput>> {
+    unsafe {
+        let input = AVCaptureScreenInput::initWithDisplayID(
+            AVCaptureScreenInput::alloc(),
+            cg_direct_display_id,
+        )
+        .ok_or(XCapError::new(
+            "AVCaptureScreenInput::initWithDisplayID failed",
+        ))?;
+        input.setCapturesCursor(config.shows_cursor());
+        input.setCapturesMouseClicks(true);
+        // 由 AVCaptureScreenInput 输出缩小后的画面，不需要再缩放
+        input.setScaleFactor(config.scale as f64);
+
+        if let Some(min_frame_duration) = min_frame_duration(config, low_power, frequency) {
+            input.setMinFrameDuration(min_frame_duration);
+        }
+
+        Ok(input)
+    }
+}
+
+/// 会话出错或显示器配置变化后重建 AVCaptureScreenInput，对应 Windows 上重建桌面复制
+///
+/// AVCaptureSession 的配置和启停可以在任意线程上调用，错误通知在 AVFoundation 的线程上，
+/// 显示器配置回调在主线程上，输入设备的替换由 input 的锁串行化
+#[derive(Debug)]
+struct StreamRecovery {
+    session: Retained<AVCaptureSession>,
+    input: Mutex<Retained<AVCaptureScreenInput>>,
+    cg_direct_display_id: CGDirectDisplayID,
+    live_config: Arc<LiveConfig>,
+    low_power: bool,
+    frequency: f32,
+    recovery: RecoveryPolicy,
+    health: Arc<RecorderHealth>,
+    recorder_waker: Arc<RecorderWaker>,
+    // 错误通知的观察者令牌和回调，shutdown 时移除
+    observer: Mutex<
+        Option<(
+            Retained<ProtocolObject<dyn NSObjectProtocol>>,
+            ObserverBlock,
+        )>,
+    >,
+    // 注册显示器配置回调时交出的引用，shutdown 时收回
+    callback_user_info: Mutex<Option<*const StreamRecovery>>,
+}
+
+type ObserverBlock = RcBlock<dyn Fn(NonNull<NSNotification>)>;
+
+impl StreamRecovery {
+    /// 注册错误通知和显示器配置回调，回调中各持有一个引用，直到 unregister
+    fn register(self: &Arc<Self>) -> XCapResult<()> {
+        let recovery = self.clone();
+        let block: ObserverBlock = RcBlock::new(move |notification: NonNull<NSNotification>| {
+            let user_info = unsafe { notification.as_ref().userInfo() };
+            recovery.recover(RecorderEvent::Error(format!(
+                "AVCaptureSession runtime error: {user_info:?}"
+            )));
+        });
+
+        unsafe {
+            let token = NSNotificationCenter::defaultCenter()
+                .addObserverForName_object_queue_usingBlock(
+                    Some(AVCaptureSessionRuntimeErrorNotification),
+                    Some(self.session.as_ref()),
+                    None,
+                    &block,
+                );
+            *self.observer.lock()? = Some((token, block));
+
+            let user_info = Arc::into_raw(self.clone());
+            if CGDisplayRegisterReconfigurationCallback(display_reconfigured, user_info as *mut _)
+                != 0
+            {
+                drop(Arc::from_raw(user_info));
+                return Err(XCapError::new(
+                    "CGDisplayRegisterReconfigurationCallback failed",
+                ));
+            }
+            *self.callback_user_info.lock()? = Some(user_info);
+        }
+
+        Ok(())
+    }
+
+    fn unregister(&self) -> XCapResult<()> {
+        if let Some((token, _block)) = self.observer.lock()?.take() {
+            let observer: &AnyObject = token.as_ref();
+            unsafe { NSNotificationCenter::defaultCenter().removeObserver(observer) };
+        }
+
+        if let Some(user_info) = self.callback_user_info.lock()?.take() {
+            unsafe {
+                CGDisplayRemoveReconfigurationCallback(display_reconfigured, user_info as *mut _);
+                drop(Arc::from_raw(user_info));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn recover(&self, event: RecorderEvent) {
+        self.health.emit(event);
+
+        if self.recovery == RecoveryPolicy::Stop {
+            unsafe { self.session.stopRunning() };
+            return;
+        }
+
+        match self.restart() {
+            Ok(()) => self.health.emit(RecorderEvent::StreamRestarted),
+            Err(err) => {
+                log::error!("Restart AVCaptureSession failed: {err:?}");
+                self.health.emit(RecorderEvent::Error(err.to_string()));
+            }
+        }
+    }
+
+    /// 替换会话的输入设备，显示器断开时创建会失败，重新连接后的配置回调中会再次尝试
+    fn restart(&self) -> XCapResult<()> {
+        let (_, config) = self.live_config.get()?;
+        let new_input = create_input(
+            self.cg_direct_display_id,
+            &config,
+            self.low_power,
+            self.frequency,
+        )?;
+
+        let mut input = self.input.lock()?;
+        unsafe {
+            self.session.beginConfiguration();
+            self.session.removeInput(&input);
+            let can_add_input = self.session.canAddInput(&new_input);
+            self.session
+                .addInput(if can_add_input { &new_input } else { &input });
+            self.session.commitConfiguration();
+
+            if !can_add_input {
+                return Err(XCapError::new("AVCaptureSession::canAddInput failed"));
+            }
+            *input = new_input;
+
+            // 出错后会话已经停止，暂停中的录制保持暂停
+            if !self.recorder_waker.is_parking() && !self.session.isRunning() {
+                self.session.startRunning();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+unsafe extern "C" fn display_reconfigured(
+    display: CGDirectDisplayID,
+    flags: u32,
+    user_info: *mut c_void,
+) {
+    // user_info 是 register 中交出的引用，unregister 移除回调之后才释放
+    let recovery = unsafe { &*(user_info as *const StreamRecovery) };
+    if display != recovery.cg_direct_display_id
+        || flags & DISPLAY_BEGIN_CONFIGURATION_FLAG != 0
+        || flags & DISPLAY_RECONFIGURED_FLAGS == 0
+    {
+        return;
+    }
+
+    recovery.recover(RecorderEvent::DisplayReconfigured);
+}
 
 #[derive(Debug, Clone)]
 struct DataOutputSampleBufferDelegateVars {
     tx: SyncSender<Frame>,
+    health: Arc<RecorderHealth>,
+    recorder_waker: Arc<RecorderWaker>,
+    cg_direct_display_id: CGDirectDisplayID,
+    idle_gate: Arc<IdleGate>,
+    // 按 vsync 输出时用于丢弃同一刷新周期内的多余帧
+    vsync_gate: Option<Arc<DisplayLinkGate>>,
+    // 低功耗模式下用于跳过没有变化的帧
+    change_detector: Option<Arc<Mutex<ChangeDetector>>>,
+    frame_hook: Option<FrameHook>,
+    live_config: Arc<LiveConfig>,
+    window_crop: Option<Arc<WindowCrop>>,
+    redaction: Option<Redaction>,
 }
 
 impl DataOutputSampleBufferDelegateVars {
@@ -37,6 +431,21 @@ impl DataOutputSampleBufferDelegateVars {
         _connection: &AVCaptureConnection,
     ) {
         unsafe {
+            // 显示器休眠时 AVCaptureScreenInput 仍会输出黑帧
+            let cg_direct_display_id = self.cg_direct_display_id;
+            if self
+                .idle_gate
+                .poll(|| CGDisplayIsAsleep(cg_direct_display_id), &self.health)
+            {
+                return;
+            }
+
+            if let Some(vsync_gate) = &self.vsync_gate
+                && !vsync_gate.accept()
+            {
+                return;
+            }
+
             let pixel_buffer = match CMSampleBuffer::image_buffer(sample_buffer) {
                 Some(pixel_buffer) => pixel_buffer,
                 None => return,
@@ -66,15 +475,47 @@ impl DataOutputSampleBufferDelegateVars {
                 buffer.extend_from_slice(&row[..width * 4]);
             }
 
-            for bgra in buffer.chunks_exact_mut(4) {
-                bgra.swap(0, 2);
+            conversion::bgra_to_rgba_in_place(&mut buffer);
+
+            // 展示时间戳是 host time，换算到统一的时间线上
+            let timestamp =
+                clock::from_cm_time(CMSampleBuffer::presentation_time_stamp(sample_buffer))
+                    .unwrap_or_else(Instant::now);
+            let mut frame =
+                Frame::with_stride(width as u32, height as u32, width * 4, buffer, timestamp);
+            if let Some(redaction) = &self.redaction {
+                redaction.apply(&mut frame);
+            }
+            match &self.window_crop {
+                // 跟随的窗口不在显示器上时不发送，裁剪后的帧和原始缓冲区不一致，不附带缓冲区
+                Some(window_crop) => match window_crop.crop(&frame) {
+                    Some(cropped) => frame = cropped,
+                    None => return,
+                },
+                // 原始缓冲区中是没有遮挡的画面，设置了 redactor 时也不附带
+                None if self.redaction.is_none() => {
+                    frame.native = Some(NativeBuffers::new(
+                        sample_buffer.retain(),
+                        pixel_buffer.clone(),
+                    ))
+                }
+                None => {}
             }
 
-            let _ = self.tx.send(Frame {
-                width: width as u32,
-                height: height as u32,
-                raw: buffer,
-            });
+            if let Some(change_detector) = self.change_detector.as_ref()
+                && let Ok(mut change_detector) = change_detector.lock()
+                && !change_detector.is_changed(frame.data())
+            {
+                return;
+            }
+
+            if let Some(frame_hook) = &self.frame_hook {
+                frame_hook.apply(&mut frame);
+            }
+            self.live_config.emit_marker(timestamp, &self.health);
+            // 停止时 stopRunning 会等待回调返回，send 只等待分发线程取走帧，不会一直阻塞
+            self.health
+                .deliver(|| self.recorder_waker.send(&self.tx, frame));
         }
     }
 }
@@ -96,14 +537,25 @@ define_class!(
         ) {
             self.ivars().capture(output, sample_buffer, connection);
         }
+
+        // setAlwaysDiscardsLateVideoFrames(true) 时，来不及处理的帧会在这里回调
+        #[unsafe(method(captureOutput:didDropSampleBuffer:fromConnection:))]
+        unsafe fn capture_output_did_drop_sample_buffer_from_connection(
+            &self,
+            _output: &AVCaptureOutput,
+            _sample_buffer: &CMSampleBuffer,
+            _connection: &AVCaptureConnection,
+        ) {
+            self.ivars().health.dropped(1);
+        }
     }
 );
 
 unsafe impl NSObjectProtocol for DataOutputSampleBufferDelegate {}
 
 impl DataOutputSampleBufferDelegate {
-    fn new(tx: SyncSender<Frame>) -> Retained<Self> {
-        let this = Self::alloc().set_ivars(DataOutputSampleBufferDelegateVars { tx });
+    fn new(ivars: DataOutputSampleBufferDelegateVars) -> Retained<Self> {
+        let this = Self::alloc().set_ivars(ivars);
         unsafe { msg_send![super(this), init] }
     }
 }
@@ -111,24 +563,26 @@ impl DataOutputSampleBufferDelegate {
 #[derive(Debug, Clone)]
 pub struct ImplVideoRecorder {
     session: Retained<AVCaptureSession>,
-    _input: Retained<AVCaptureScreenInput>,
-    _output: Retained<AVCaptureVideoDataOutput>,
+    output: Retained<AVCaptureVideoDataOutput>,
     _delegate: Retained<DataOutputSampleBufferDelegate>,
+    recovery: Arc<StreamRecovery>,
+    recorder_waker: Arc<RecorderWaker>,
+    live_config: Arc<LiveConfig>,
+    low_power: bool,
+    frequency: f32,
 }
 
 impl ImplVideoRecorder {
-    pub fn new(cg_direct_display_id: CGDirectDisplayID) -> XCapResult<(Self, Receiver<Frame>)> {
+    pub fn new(
+        cg_direct_display_id: CGDirectDisplayID,
+        frequency: f32,
+        config: &RecorderConfig,
+        health: Arc<RecorderHealth>,
+    ) -> XCapResult<(Self, Receiver<Frame>)> {
         unsafe {
             let session = AVCaptureSession::new();
-            let input = AVCaptureScreenInput::initWithDisplayID(
-                AVCaptureScreenInput::alloc(),
-                cg_direct_display_id,
-            )
-            .ok_or(XCapError::new(
-                "AVCaptureScreenInput::initWithDisplayID failed",
-            ))?;
-            input.setCapturesCursor(true);
-            input.setCapturesMouseClicks(true);
+            let low_power = config.power_profile.is_low_power(is_on_battery);
+            let input = create_input(cg_direct_display_id, config, low_power, frequency)?;
 
             if session.canAddInput(&input) {
                 session.addInput(&input);
@@ -164,7 +618,29 @@ impl ImplVideoRecorder {
 
             let (tx, rx) = sync_channel(0);
 
-            let delegate = DataOutputSampleBufferDelegate::new(tx.clone());
+            let recorder_waker = Arc::new(RecorderWaker::new());
+            let live_config = Arc::new(LiveConfig::new(config.clone()));
+            let monitor = Monitor::new(ImplMonitor::new(cg_direct_display_id));
+            let window_crop = WindowCrop::from_config(config, &monitor)?;
+            let redaction = Redaction::from_config(config, &monitor)?;
+            let vsync_gate = match config.pacing {
+                FramePacing::Vsync => Some(Arc::new(DisplayLinkGate::new(cg_direct_display_id)?)),
+                FramePacing::FreeRunning => None,
+            };
+            let delegate =
+                DataOutputSampleBufferDelegate::new(DataOutputSampleBufferDelegateVars {
+                    tx: tx.clone(),
+                    health: health.clone(),
+                    recorder_waker: recorder_waker.clone(),
+                    cg_direct_display_id,
+                    idle_gate: Arc::new(IdleGate::new(config.pause_when_idle)),
+                    vsync_gate,
+                    change_detector: low_power.then(Arc::default),
+                    frame_hook: config.frame_hook.clone(),
+                    live_config: live_config.clone(),
+                    window_crop: window_crop.map(Arc::new),
+                    redaction,
+                });
 
             let sample_buffer_delegate = ProtocolObject::<
                 dyn AVCaptureVideoDataOutputSampleBufferDelegate,
@@ -178,12 +654,31 @@ impl ImplVideoRecorder {
             let _: () =
                 msg_send![&output, setSampleBufferDelegate: sample_buffer_delegate, queue: queue];
 
+            let recovery = Arc::new(StreamRecovery {
+                session: session.clone(),
+                input: Mutex::new(input),
+                cg_direct_display_id,
+                live_config: live_config.clone(),
+                low_power,
+                frequency,
+                recovery: config.recovery,
+                health,
+                recorder_waker: recorder_waker.clone(),
+                observer: Mutex::default(),
+                callback_user_info: Mutex::default(),
+            });
+            recovery.register()?;
+
             Ok((
                 ImplVideoRecorder {
                     session,
-                    _output: output,
-                    _input: input,
+                    output,
                     _delegate: delegate,
+                    recovery,
+                    recorder_waker,
+                    live_config,
+                    low_power,
+                    frequency,
                 },
                 rx,
             ))
@@ -191,12 +686,64 @@ impl ImplVideoRecorder {
     }
 
     pub fn start(&self) -> XCapResult<()> {
+        self.recorder_waker.wake()?;
         unsafe { self.session.startRunning() };
         Ok(())
     }
 
+    /// stopRunning 是同步的，返回时委托队列里的回调已经执行完
     pub fn stop(&self) -> XCapResult<()> {
+        self.recorder_waker.sleep()?;
         unsafe { self.session.stopRunning() };
         Ok(())
     }
+
+    /// 在会话的配置事务中修改 AVCaptureScreenInput，不需要重建会话
+    pub fn reconfigure(&self, update: &RecorderUpdate) -> XCapResult<()> {
+        let config = self.live_config.update(update)?;
+
+        let input = self.recovery.input.lock()?;
+        unsafe {
+            self.session.beginConfiguration();
+            // 无效的 CMTime 恢复默认的帧间隔
+            let min_frame_duration = min_frame_duration(&config, self.low_power, self.frequency)
+                .unwrap_or(CMTime {
+                    value: 0,
+                    timescale: 0,
+                    flags: CMTimeFlags::empty(),
+                    epoch: 0,
+                });
+            input.setMinFrameDuration(min_frame_duration);
+            input.setScaleFactor(config.scale as f64);
+            input.setCapturesCursor(config.shows_cursor());
+            self.session.commitConfiguration();
+        }
+        drop(input);
+
+        // 提交之前捕获的帧可能还在委托队列中，按展示时间判断
+        self.live_config.mark_switch(Some(Instant::now()));
+
+        Ok(())
+    }
+
+    pub fn shutdown(&self) -> XCapResult<()> {
+        self.recorder_waker.shutdown()?;
+        // 先移除回调，避免停止之后又被重新启动
+        self.recovery.unregister()?;
+
+        unsafe {
+            self.session.stopRunning();
+
+            // 解除委托，避免会话释放前还有回调进来
+            let _: () = msg_send![
+                &self.output,
+                setSampleBufferDelegate: std::ptr::null::<AnyObject>(),
+                queue: std::ptr::null::<DispatchQueue>()
+            ];
+            self.session.removeOutput(&self.output);
+            self.session.removeInput(&self.recovery.input.lock()?);
+        }
+
+        Ok(())
+    }
 }
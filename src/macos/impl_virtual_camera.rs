@@ -0,0 +1,17 @@
+use std::path::Path;
+
+use crate::error::{XCapError, XCapResult};
+
+/// 系统没有 v4l2loopback 这样的设备，虚拟摄像头需要应用自己提供的扩展，见 CameraSink
+#[derive(Debug)]
+pub(crate) struct ImplVirtualCamera;
+
+impl ImplVirtualCamera {
+    pub fn open(_device: &Path, _width: u32, _height: u32) -> XCapResult<ImplVirtualCamera> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn write(&mut self, _yuyv: &[u8]) -> XCapResult<()> {
+        Err(XCapError::NotSupported)
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    ffi::CStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use dispatch2::DispatchQueue;
+use objc2::{
+    msg_send,
+    rc::{Allocated, Retained},
+    runtime::{AnyClass, AnyObject},
+};
+use objc2_core_foundation::CGSize;
+use objc2_core_graphics::CGDirectDisplayID;
+use objc2_foundation::{NSArray, NSString};
+
+use crate::error::{XCapError, XCapResult};
+
+use super::impl_monitor::ImplMonitor;
+
+// 等待虚拟显示器上线的超时时间
+const DISPLAY_WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+// 虚拟显示器的 EDID 信息，厂商 ID 使用未分配的值
+const VENDOR_ID: u32 = 0xF0F0;
+const PRODUCT_ID: u32 = 0x0001;
+const REFRESH_RATE: f64 = 60.0;
+// 按 96 DPI 计算物理尺寸
+const PIXELS_PER_MILLIMETER: f64 = 96.0 / 25.4;
+
+/// CGVirtualDisplay 系列类是 CoreGraphics 的私有 API，运行时查找
+fn get_class(name: &CStr) -> XCapResult<&'static AnyClass> {
+    AnyClass::get(name).ok_or_else(|| {
+        XCapError::new(format!(
+            "{} is not available on this macOS version",
+            name.to_string_lossy()
+        ))
+    })
+}
+
+fn get_online_display_ids() -> XCapResult<Vec<CGDirectDisplayID>> {
+    ImplMonitor::all()?
+        .iter()
+        .map(|monitor| monitor.id())
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct ImplVirtualDisplay {
+    // 释放 CGVirtualDisplay 时系统移除显示器
+    _display: Retained<AnyObject>,
+    display_id: CGDirectDisplayID,
+}
+
+impl ImplVirtualDisplay {
+    pub fn new(width: u32, height: u32) -> XCapResult<ImplVirtualDisplay> {
+        let descriptor_class = get_class(c"CGVirtualDisplayDescriptor")?;
+        let display_class = get_class(c"CGVirtualDisplay")?;
+        let settings_class = get_class(c"CGVirtualDisplaySettings")?;
+        let mode_class = get_class(c"CGVirtualDisplayMode")?;
+
+        let (display, display_id) = unsafe {
+            let descriptor: Retained<AnyObject> = msg_send![descriptor_class, new];
+            let name = NSString::from_str("XCap Virtual Display");
+            let size = CGSize::new(
+                width as f64 / PIXELS_PER_MILLIMETER,
+                height as f64 / PIXELS_PER_MILLIMETER,
+            );
+            let queue: &DispatchQueue = DispatchQueue::main();
+            let _: () = msg_send![&descriptor, setName: &*name];
+            let _: () = msg_send![&descriptor, setMaxPixelsWide: width];
+            let _: () = msg_send![&descriptor, setMaxPixelsHigh: height];
+            let _: () = msg_send![&descriptor, setSizeInMillimeters: size];
+            let _: () = msg_send![&descriptor, setVendorID: VENDOR_ID];
+            let _: () = msg_send![&descriptor, setProductID: PRODUCT_ID];
+            let _: () = msg_send![&descriptor, setSerialNum: std::process::id()];
+            let _: () = msg_send![&descriptor, setQueue: queue];
+
+            let display: Allocated<AnyObject> = msg_send![display_class, alloc];
+            let display: Option<Retained<AnyObject>> =
+                msg_send![display, initWithDescriptor: &*descriptor];
+            let display = display.ok_or_else(|| XCapError::new("Create virtual display failed"))?;
+
+            let mode: Allocated<AnyObject> = msg_send![mode_class, alloc];
+            let mode: Retained<AnyObject> = msg_send![
+                mode,
+                initWithWidth: width as usize,
+                height: height as usize,
+                refreshRate: REFRESH_RATE
+            ];
+            let modes = NSArray::from_retained_slice(&[mode]);
+
+            let settings: Retained<AnyObject> = msg_send![settings_class, new];
+            let _: () = msg_send![&settings, setModes: &*modes];
+            let _: () = msg_send![&settings, setHiDPI: 0u32];
+
+            let applied: bool = msg_send![&display, applySettings: &*settings];
+            if !applied {
+                return Err(XCapError::new("Apply virtual display settings failed"));
+            }
+
+            let display_id: CGDirectDisplayID = msg_send![&display, displayID];
+
+            (display, display_id)
+        };
+
+        // 显示器异步上线
+        let start = Instant::now();
+        while !get_online_display_ids()?.contains(&display_id) {
+            if start.elapsed() > DISPLAY_WAIT_TIMEOUT {
+                return Err(XCapError::new(
+                    "Virtual display did not come online in time",
+                ));
+            }
+
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        Ok(ImplVirtualDisplay {
+            _display: display,
+            display_id,
+        })
+    }
+
+    pub fn monitor(&self) -> XCapResult<ImplMonitor> {
+        Ok(ImplMonitor::new(self.display_id))
+    }
+}
@@ -0,0 +1,68 @@
+use objc2_core_foundation::CFString;
+
+use crate::error::{XCapError, XCapResult};
+
+// IOPMLib 常量（保持与 Apple API 一致的命名）
+#[allow(non_upper_case_globals)]
+const kIOPMAssertionTypePreventUserIdleDisplaySleep: &str = "PreventUserIdleDisplaySleep";
+#[allow(non_upper_case_globals)]
+const kIOPMAssertionLevelOn: u32 = 255;
+#[allow(non_upper_case_globals)]
+const kIOReturnSuccess: i32 = 0;
+
+const REASON: &str = "Recording the screen";
+
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {
+    fn IOPMAssertionCreateWithName(
+        assertion_type: *const CFString,
+        assertion_level: u32,
+        assertion_name: *const CFString,
+        assertion_id: *mut u32,
+    ) -> i32;
+    fn IOPMAssertionRelease(assertion_id: u32) -> i32;
+}
+
+/// 持有 IOPMAssertion 阻止显示器因空闲而休眠，释放后恢复
+#[derive(Debug)]
+pub(crate) struct ImplWakeLock {
+    assertion_id: u32,
+}
+
+impl ImplWakeLock {
+    pub fn new() -> XCapResult<ImplWakeLock> {
+        let assertion_type = CFString::from_str(kIOPMAssertionTypePreventUserIdleDisplaySleep);
+        let reason = CFString::from_str(REASON);
+        let mut assertion_id = 0;
+
+        let result = unsafe {
+            IOPMAssertionCreateWithName(
+                &*assertion_type,
+                kIOPMAssertionLevelOn,
+                &*reason,
+                &mut assertion_id,
+            )
+        };
+
+        if result != kIOReturnSuccess {
+            return Err(XCapError::new(format!(
+                "IOPMAssertionCreateWithName failed: {result:#x}"
+            )));
+        }
+
+        Ok(ImplWakeLock { assertion_id })
+    }
+}
+
+impl Drop for ImplWakeLock {
+    fn drop(&mut self) {
+        let result = unsafe { IOPMAssertionRelease(self.assertion_id) };
+        if result != kIOReturnSuccess {
+            log::error!(
+                "IOPMAssertionRelease({}) failed: {:#x}",
+                self.assertion_id,
+                result
+            );
+        }
+    }
+}
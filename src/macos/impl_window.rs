@@ -1,40 +1,67 @@
 use std::{
+    cell::RefCell,
     ffi::c_void,
     ptr::NonNull,
     sync::Arc,
+    time::Duration,
 };
 
-use tokio::sync::{oneshot, Mutex, RwLock};
+use tokio::sync::{Mutex, RwLock};
 
 use block2::RcBlock;
-use dispatch2::DispatchQueue;
 use image::RgbaImage;
-use objc2::{MainThreadMarker, rc::Retained, runtime::ProtocolObject};
+use objc2::{
+    MainThreadMarker,
+    rc::Retained,
+    runtime::{AnyObject, ProtocolObject},
+};
 use objc2_app_kit::{NSWorkspace, NSWorkspaceDidActivateApplicationNotification};
 use objc2_core_foundation::{
-    CFBoolean, CFDictionary, CFNumber, CFNumberType, CFRetained, CFString, CGPoint, CGRect,
+    CFBoolean, CFDictionary, CFNumber, CFNumberType, CFRetained, CFString, CFType, CGPoint,
+    CGRect, CGSize, ConcreteType,
 };
 use objc2_core_graphics::{
-    CGDisplayBounds, CGMainDisplayID, CGRectContainsPoint, CGRectIntersectsRect,
-    CGRectMakeWithDictionaryRepresentation, CGWindowListCopyWindowInfo, CGWindowListOption,
+    CGDisplayBounds, CGEvent, CGEventTapLocation, CGMainDisplayID, CGRectContainsPoint,
+    CGRectIntersectsRect, CGRectMakeWithDictionaryRepresentation, CGScrollEventUnit,
+    CGWindowListCopyWindowInfo, CGWindowListOption,
 };
 
 use objc2_foundation::{NSNotification, NSObjectProtocol};
 
-use crate::{XCapError, error::XCapResult};
+use crate::{
+    ActiveInfoMode, Rect, WindowLayer, XCapError, error::XCapResult,
+    normal_bounds::track_normal_bounds,
+};
 
-use super::{capture::capture, impl_monitor::ImplMonitor};
+use super::{
+    capture::{
+        DOCK_WINDOW_LEVEL, HELP_WINDOW_LEVEL, MAIN_MENU_WINDOW_LEVEL, POP_UP_MENU_WINDOW_LEVEL,
+        STATUS_WINDOW_LEVEL, capture,
+    },
+    has_screen_capture_access,
+    impl_monitor::ImplMonitor,
+    main_thread::{
+        is_main_run_loop_observed, mark_main_run_loop_observed, probe_main_run_loop, run_on_main,
+    },
+    window_cache,
+};
+
+pub use super::window_cache::{invalidate_window_cache, set_window_cache_ttl};
 
 static ACTIVE_APP_TRACKER: Mutex<Option<Arc<ActiveAppTracker>>> = Mutex::const_new(None);
 static ACTIVE_APP_TRACKER_INIT_LOCK: Mutex<()> = Mutex::const_new(());
 
+thread_local! {
+    /// 活动应用通知的观察者令牌和回调，不满足 Send，只保存在主线程上，
+    /// 由主线程创建和释放，ActiveAppTracker 中只保留可以跨线程共享的数据
+    static ACTIVE_APP_OBSERVER: RefCell<Option<ActiveAppObserver>> = const { RefCell::new(None) };
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct ImplWindow {
     pub window_id: u32,
 }
 
-unsafe impl Send for ImplWindow {}
-
 /// 活动应用信息
 ///
 /// 存储当前处于前台的活动应用的名称、进程 ID 和所在显示器的序列号
@@ -52,24 +79,22 @@ struct ActiveAppInfo {
 ///
 /// 用于跟踪当前处于前台的活动应用信息（名称、pid、显示序列号）。通过监听 macOS 的 NSWorkspaceDidActivateApplicationNotification
 /// 通知，当用户切换应用时自动更新当前应用信息。
+///
+/// 只包含可以跨线程共享的数据，通知观察者保存在主线程的 ACTIVE_APP_OBSERVER 中
 struct ActiveAppTracker {
     /// 当前活动应用的信息，使用 Arc<RwLock<>> 包装以支持多线程读取
     /// - Arc: 允许多个线程共享同一个信息的引用
     /// - RwLock: 允许多个线程同时读取，但写入时需要独占锁（使用 tokio 异步锁）
     current_info: Arc<RwLock<ActiveAppInfo>>,
-    /// 通知观察者的令牌，用于在对象销毁时自动移除观察者
-    /// 使用下划线前缀表示这是一个仅用于保持生命周期的字段
-    _observer_token: Retained<ProtocolObject<dyn NSObjectProtocol>>,
 }
 
-/// 为 ActiveAppTracker 实现 Send 和 Sync trait
-///
-/// 虽然 ActiveAppTracker 内部包含的 observer_token 不满足 Send/Sync，
-/// 但该 tracker 只在主线程上创建和使用，observer_token 也只在主线程上访问。
-/// 通过 Arc 在线程间共享时，其他线程只能读取 current_info，不会访问 observer_token，
-/// 因此可以安全地标记为 Send + Sync。
-unsafe impl Send for ActiveAppTracker {}
-unsafe impl Sync for ActiveAppTracker {}
+/// 注册在 NSWorkspace 通知中心上的观察者，只在主线程上创建、访问和释放
+struct ActiveAppObserver {
+    /// 通知观察者的令牌，shutdown 时用于移除观察者
+    token: Retained<ProtocolObject<dyn NSObjectProtocol>>,
+    /// 通知回调，移除观察者之后才释放
+    _block: RcBlock<dyn Fn(NonNull<NSNotification>)>,
+}
 
 /// 确保活动应用跟踪器已被初始化（单例模式）
 ///
@@ -102,49 +127,37 @@ async fn ensure_active_app_tracker() -> XCapResult<Arc<ActiveAppTracker>> {
         }
 
         // 初始化 tracker
-        let tracker = init_active_app_tracker().await?;
+        let tracker = init_active_app_tracker()?;
         let tracker_clone = tracker.clone();
         *tracker_guard = Some(tracker);
         Ok(tracker_clone)
     }
 }
 
+/// 移除活动应用跟踪器的通知观察者并释放 tracker，之后调用 get_active_info 会重新创建
+pub(super) fn shutdown_active_app_tracker() -> XCapResult<()> {
+    // shutdown 是同步函数，可能在 tokio 运行时中调用，不能阻塞等待异步锁
+    let tracker = ACTIVE_APP_TRACKER
+        .try_lock()
+        .map_err(|_| XCapError::new("Active app tracker is being initialized"))?
+        .take();
+
+    if let Some(tracker) = tracker {
+        tracker.shutdown()?;
+    }
+
+    Ok(())
+}
+
 /// 初始化活动应用跟踪器
 ///
-/// 这个函数负责创建 ActiveAppTracker 实例。由于 macOS 的通知观察者必须在主线程上注册，
-/// 所以需要根据当前线程情况选择不同的初始化方式：
-///
-/// 1. 如果当前已经在主线程上：直接在主线程上创建 tracker
-/// 2. 如果当前不在主线程上：通过 DispatchQueue 切换到主线程创建 tracker
-///
-/// 使用 tokio::sync::oneshot::channel 来异步等待主线程上的初始化完成。
-async fn init_active_app_tracker() -> XCapResult<Arc<ActiveAppTracker>> {
-    // 检查当前是否在主线程上
-    if MainThreadMarker::new().is_some() {
-        // 已经在主线程上，直接创建 tracker
-        return ActiveAppTracker::new_observer_on_main_thread().map(Arc::new);
-    }
-
-    // 不在主线程上，需要切换到主线程
-    // 创建一个一次性通道用于接收主线程上的初始化结果
-    let (tx, rx) = oneshot::channel();
-
-    // 在主线程上异步执行初始化任务
-    DispatchQueue::main().exec_async(move || {
-        // 在主线程上创建 tracker
-        let tracker = ActiveAppTracker::new_observer_on_main_thread().map(Arc::new);
-        // 将结果发送回原线程（忽略发送失败的情况）
-        let _ = tx.send(tracker);
-    });
-
-    // 异步等待主线程完成初始化并返回结果
-    // 使用 ?? 是因为 rx.await 返回 Result<Result<Arc<ActiveAppTracker>, XCapError>, RecvError>
-    // 第一个 ? 处理通道接收错误，第二个 ? 处理 tracker 创建错误
-    let tracker = rx
-        .await
-        .map_err(|_| XCapError::new("Failed to initialize active app tracker"))??;
-
-    Ok(tracker)
+/// macOS 的通知观察者必须在主线程上注册，所以通过 run_on_main 在主线程上创建 tracker。
+/// 不在主线程上调用时，需要宿主程序运行主线程的 RunLoop，否则返回错误。
+fn init_active_app_tracker() -> XCapResult<Arc<ActiveAppTracker>> {
+    // 在主线程上直接创建时无法知道 RunLoop 是否在运行，派发一个任务来检测
+    probe_main_run_loop();
+
+    run_on_main(|_| ActiveAppTracker::new_observer_on_main_thread().map(Arc::new))?
 }
 
 impl ActiveAppTracker {
@@ -187,6 +200,12 @@ impl ActiveAppTracker {
         // 创建通知回调闭包
         // 当用户切换应用时，macOS 会调用这个闭包
         let observer_block = RcBlock::new(move |_notification: NonNull<NSNotification>| {
+            // 能收到通知说明主线程 RunLoop 在运行
+            mark_main_run_loop_observed();
+
+            // 切换应用后窗口层级发生变化，缓存的窗口信息不再可靠
+            let _ = invalidate_window_cache();
+
             // 获取新的前台应用信息
             if let Ok((name, pid)) = ImplWindow::get_app_name_pid() {
                 // 获取显示序列号，如果获取失败则使用默认值
@@ -232,24 +251,34 @@ impl ActiveAppTracker {
                 &observer_block,
             );
 
-            // 重要：使用 std::mem::forget 防止 observer_block 被释放
-            //
-            // 原因：通知中心会持有 observer_block 的引用，但 Rust 的借用检查器
-            // 不知道这一点。如果不使用 forget，observer_block 会在函数结束时被释放，
-            // 导致通知中心持有悬垂指针，程序崩溃。
-            //
-            // 使用 forget 后，observer_block 会一直存在直到程序结束或观察者被移除。
-            // observer_token 会在 ActiveAppTracker 被销毁时自动移除观察者。
-            std::mem::forget(observer_block);
+            // observer_block 保存在主线程上，直到 shutdown 移除观察者后才释放，
+            // 不会出现通知中心调用已释放的回调的情况
+            let previous = ACTIVE_APP_OBSERVER.with(|observer| {
+                observer.borrow_mut().replace(ActiveAppObserver {
+                    token: observer_token,
+                    _block: observer_block,
+                })
+            });
+            if let Some(previous) = previous {
+                previous.remove();
+            }
 
             // 返回初始化好的 tracker
-            Ok(Self {
-                current_info,
-                _observer_token: observer_token,
-            })
+            Ok(Self { current_info })
         }
     }
 
+    /// 在主线程上移除通知观察者，之后不再接收应用切换通知
+    ///
+    /// 主线程 RunLoop 没有运行时，移除操作在 RunLoop 恢复后执行
+    fn shutdown(&self) -> XCapResult<()> {
+        run_on_main(|_| {
+            if let Some(observer) = ACTIVE_APP_OBSERVER.with(|observer| observer.take()) {
+                observer.remove();
+            }
+        })
+    }
+
     /// 读取当前活动应用的信息
     ///
     /// 这个函数是线程安全的，可以在任何线程上调用。
@@ -264,34 +293,41 @@ impl ActiveAppTracker {
     }
 }
 
-fn get_cf_dictionary_get_value(
-    cf_dictionary: &CFDictionary,
+impl ActiveAppObserver {
+    fn remove(self) {
+        unsafe {
+            let notification_center = NSWorkspace::sharedWorkspace().notificationCenter();
+            let observer: &AnyObject = self.token.as_ref();
+            notification_center.removeObserver(observer);
+        }
+    }
+}
+
+/// 读取字典中的值并检查类型，值不存在或类型不符时返回错误，不能直接转换指针
+fn get_cf_dictionary_get_value<'a, T: ConcreteType>(
+    cf_dictionary: &'a CFDictionary,
     key: &str,
-) -> XCapResult<*const c_void> {
+) -> XCapResult<&'a T> {
     unsafe {
         let cf_dictionary_key = CFString::from_str(key);
         let cf_dictionary_key_ref = cf_dictionary_key.as_ref() as *const CFString;
 
-        let value = cf_dictionary.value(cf_dictionary_key_ref.cast());
-
-        if value.is_null() {
-            return Err(XCapError::new(format!(
-                "Get CFDictionary {} value failed",
-                key
-            )));
-        }
+        let value = cf_dictionary.value(cf_dictionary_key_ref.cast()) as *const CFType;
 
-        Ok(value)
+        value
+            .as_ref()
+            .and_then(|value| value.downcast_ref::<T>())
+            .ok_or_else(|| XCapError::new(format!("Get CFDictionary {} value failed", key)))
     }
 }
 
 fn get_cf_number_i32_value(cf_dictionary: &CFDictionary, key: &str) -> XCapResult<i32> {
     unsafe {
-        let cf_number = get_cf_dictionary_get_value(cf_dictionary, key)? as *const CFNumber;
+        let cf_number = get_cf_dictionary_get_value::<CFNumber>(cf_dictionary, key)?;
 
         let mut value: i32 = 0;
         let is_success =
-            (*cf_number).value(CFNumberType::IntType, &mut value as *mut _ as *mut c_void);
+            cf_number.value(CFNumberType::IntType, &mut value as *mut _ as *mut c_void);
 
         if !is_success {
             return Err(XCapError::new(format!(
@@ -305,26 +341,24 @@ fn get_cf_number_i32_value(cf_dictionary: &CFDictionary, key: &str) -> XCapResul
 }
 
 fn get_cf_string_value(cf_dictionary: &CFDictionary, key: &str) -> XCapResult<String> {
-    let value_ref = get_cf_dictionary_get_value(cf_dictionary, key)? as *const CFString;
-    let value = unsafe { (*value_ref).to_string() };
-    Ok(value)
+    let value_ref = get_cf_dictionary_get_value::<CFString>(cf_dictionary, key)?;
+    Ok(value_ref.to_string())
 }
 
 fn get_cf_bool_value(cf_dictionary: &CFDictionary, key: &str) -> XCapResult<bool> {
-    let value_ref = get_cf_dictionary_get_value(cf_dictionary, key)? as *const CFBoolean;
+    let value_ref = get_cf_dictionary_get_value::<CFBoolean>(cf_dictionary, key)?;
 
-    Ok(unsafe { (*value_ref).value() })
+    Ok(value_ref.value())
 }
 
 fn get_window_cg_rect(window_cf_dictionary: &CFDictionary) -> XCapResult<CGRect> {
     unsafe {
-        let window_bounds = get_cf_dictionary_get_value(window_cf_dictionary, "kCGWindowBounds")?
-            as *const CFDictionary;
+        let window_bounds =
+            get_cf_dictionary_get_value::<CFDictionary>(window_cf_dictionary, "kCGWindowBounds")?;
 
         let mut cg_rect = CGRect::default();
 
-        let is_success =
-            CGRectMakeWithDictionaryRepresentation(Some(&*window_bounds), &mut cg_rect);
+        let is_success = CGRectMakeWithDictionaryRepresentation(Some(window_bounds), &mut cg_rect);
 
         if !is_success {
             return Err(XCapError::new(
@@ -336,7 +370,7 @@ fn get_window_cg_rect(window_cf_dictionary: &CFDictionary) -> XCapResult<CGRect>
     }
 }
 
-fn get_window_id(window_cf_dictionary: &CFDictionary) -> XCapResult<u32> {
+pub(super) fn get_window_id(window_cf_dictionary: &CFDictionary) -> XCapResult<u32> {
     let window_name = get_cf_string_value(window_cf_dictionary, "kCGWindowName")?;
 
     let window_owner_name = get_cf_string_value(window_cf_dictionary, "kCGWindowOwnerName")?;
@@ -357,43 +391,28 @@ fn get_window_id(window_cf_dictionary: &CFDictionary) -> XCapResult<u32> {
     Ok(window_id as u32)
 }
 
-pub fn get_window_cf_dictionary(window_id: u32) -> XCapResult<CFRetained<CFDictionary>> {
-    unsafe {
-        // CGWindowListCopyWindowInfo 返回窗口顺序为从顶层到最底层
-        // 即在前面的窗口在数组前面
-        let cf_array = match CGWindowListCopyWindowInfo(
-            CGWindowListOption::OptionOnScreenOnly | CGWindowListOption::ExcludeDesktopElements,
-            0,
-        ) {
-            Some(cf_array) => cf_array,
-            None => return Err(XCapError::new("Get window info failed")),
-        };
-
-        let windows_count = cf_array.count();
-
-        for i in 0..windows_count {
-            let window_cf_dictionary_ref = cf_array.value_at_index(i) as *const CFDictionary;
-
-            if window_cf_dictionary_ref.is_null() {
-                continue;
-            }
-            let window_cf_dictionary = &*window_cf_dictionary_ref;
-
-            let current_window_id = match get_window_id(window_cf_dictionary) {
-                Ok(val) => val,
-                Err(_) => continue,
-            };
-
-            if current_window_id == window_id {
-                let s = CFDictionary::new_copy(None, Some(window_cf_dictionary)).unwrap();
-                return Ok(s);
-            }
-        }
-
-        Err(XCapError::new("Window not found"))
+/// 根据 CGWindowLevel 判断窗口层级，参见 CGWindowLevel.h
+fn window_layer_from_level(level: isize) -> WindowLayer {
+    match level {
+        // kCGNormalWindowLevel 和 kCGModalPanelWindowLevel
+        0 | 8 => WindowLayer::Normal,
+        DOCK_WINDOW_LEVEL => WindowLayer::Dock,
+        MAIN_MENU_WINDOW_LEVEL => WindowLayer::MenuBar,
+        STATUS_WINDOW_LEVEL => WindowLayer::StatusItem,
+        POP_UP_MENU_WINDOW_LEVEL => WindowLayer::Popup,
+        HELP_WINDOW_LEVEL => WindowLayer::Tooltip,
+        // 桌面背景、桌面图标等
+        level if level < 0 => WindowLayer::Desktop,
+        // 浮动窗口、工具面板、遮罩层、屏保等
+        _ => WindowLayer::Overlay,
     }
 }
 
+/// 获取窗口信息，短时间内的重复查询命中缓存，见 [`set_window_cache_ttl`]
+pub fn get_window_cf_dictionary(window_id: u32) -> XCapResult<CFRetained<CFDictionary>> {
+    window_cache::get_window_cf_dictionary(window_id)
+}
+
 impl ImplWindow {
     pub fn new(window_id: u32) -> ImplWindow {
         ImplWindow { window_id }
@@ -439,8 +458,98 @@ impl ImplWindow {
     ///
     /// 返回：(应用名称, 进程 ID, 显示序列号)
     pub async fn get_active_info() -> XCapResult<(String, i32, String)> {
-        let tracker = ensure_active_app_tracker().await?;
-        tracker.read_active_info().await
+        // 通知只有在主线程 RunLoop 运行时才会送达，没有观察到 RunLoop 时 tracker 中的数据可能是旧的，
+        // tracker 创建失败也说明主线程没有响应，这两种情况都改为直接查询
+        match ensure_active_app_tracker().await {
+            Ok(tracker) if is_main_run_loop_observed() => tracker.read_active_info().await,
+            _ => {
+                let (name, pid, _) = ImplWindow::get_frontmost_window()?;
+                let display_serial = ImplWindow::get_display_serial_by_pid(pid)
+                    .unwrap_or_else(|_| "Unknown".to_string());
+
+                Ok((name, pid, display_serial))
+            }
+        }
+    }
+
+    pub fn active_info_mode() -> ActiveInfoMode {
+        if is_main_run_loop_observed() {
+            ActiveInfoMode::Notification
+        } else {
+            ActiveInfoMode::Polling
+        }
+    }
+
+    /// 获取前台窗口的标题
+    ///
+    /// 标题变化没有对应的通知，需要轮询，这里复用不依赖 RunLoop 的前台窗口查询
+    ///
+    /// 返回：(应用名称, 进程 ID, 窗口标题)
+    pub fn get_active_title() -> XCapResult<(String, i32, String)> {
+        ImplWindow::get_frontmost_window()
+    }
+
+    /// 前台应用最上层的普通窗口的 id，和 get_frontmost_window 一样不依赖 RunLoop
+    pub fn focused_id() -> XCapResult<u32> {
+        ImplWindow::with_frontmost_window(|window_cf_dictionary, _| get_window_id(window_cf_dictionary))
+    }
+
+    /// 不依赖 RunLoop 获取前台应用及其最前面的窗口标题
+    ///
+    /// NSWorkspace 的 frontmostApplication 也是在主线程 RunLoop 中更新的，没有 RunLoop 时同样是旧数据，
+    /// 所以从窗口列表中取最前面的普通窗口（layer 0）的所有者，找到后即停止，不读取其余窗口
+    fn get_frontmost_window() -> XCapResult<(String, i32, String)> {
+        ImplWindow::with_frontmost_window(|window_cf_dictionary, pid| {
+            let app_name = get_cf_string_value(window_cf_dictionary, "kCGWindowOwnerName")
+                .unwrap_or_else(|_| "Unknown".to_string());
+            // 没有屏幕录制权限时窗口列表中不包含 kCGWindowName
+            let title =
+                get_cf_string_value(window_cf_dictionary, "kCGWindowName").unwrap_or_default();
+
+            Ok((app_name, pid, title))
+        })
+    }
+
+    /// 找到窗口列表中最前面的普通窗口（layer 0），把它的信息和所有者进程 ID 交给 f
+    fn with_frontmost_window<T, F>(f: F) -> XCapResult<T>
+    where
+        F: FnOnce(&CFDictionary, i32) -> XCapResult<T>,
+    {
+        unsafe {
+            // 获取窗口列表（按 z-order 排序，最前面的窗口在数组最前）
+            let cf_array = CGWindowListCopyWindowInfo(
+                CGWindowListOption::OptionOnScreenOnly | CGWindowListOption::ExcludeDesktopElements,
+                0,
+            )
+            .ok_or_else(|| XCapError::new("Failed to get window list"))?;
+
+            for i in 0..cf_array.count() {
+                let window_cf_dictionary_ref = cf_array.value_at_index(i) as *const CFDictionary;
+
+                if window_cf_dictionary_ref.is_null() {
+                    continue;
+                }
+
+                let window_cf_dictionary = &*window_cf_dictionary_ref;
+
+                // 菜单栏、Dock、状态栏图标等系统窗口不在 layer 0
+                if !matches!(
+                    get_cf_number_i32_value(window_cf_dictionary, "kCGWindowLayer"),
+                    Ok(0)
+                ) {
+                    continue;
+                }
+
+                let pid = match get_cf_number_i32_value(window_cf_dictionary, "kCGWindowOwnerPID") {
+                    Ok(pid) => pid,
+                    Err(_) => continue,
+                };
+
+                return f(window_cf_dictionary, pid);
+            }
+
+            Err(XCapError::new("Failed to get frontmost application"))
+        }
     }
 
     /// 根据进程 ID 获取该进程窗口所在显示器的序列号
@@ -607,6 +716,11 @@ impl ImplWindow {
         Ok(impl_monitor.to_owned())
     }
 
+    /// 窗口坐标是逻辑点，截图按窗口所在显示器的比例输出物理像素
+    pub fn pixel_ratio(&self) -> XCapResult<f32> {
+        self.current_monitor()?.scale_factor()
+    }
+
     pub fn x(&self) -> XCapResult<i32> {
         let window_cf_dictionary = get_window_cf_dictionary(self.window_id)?;
 
@@ -702,6 +816,23 @@ impl ImplWindow {
         Ok(is_maximized)
     }
 
+    pub fn normal_bounds(&self) -> XCapResult<Rect> {
+        // CoreGraphics 和 AX 都没有提供窗口缩放前的位置，最小化的窗口保留原来的位置，
+        // 只有铺满显示器时需要使用之前记录的位置
+        let window_cf_dictionary = get_window_cf_dictionary(self.window_id)?;
+
+        let cg_rect = get_window_cg_rect(window_cf_dictionary.as_ref())?;
+        let bounds = Rect::new(
+            cg_rect.origin.x as i32,
+            cg_rect.origin.y as i32,
+            cg_rect.size.width as u32,
+            cg_rect.size.height as u32,
+        );
+        let is_normal = !self.is_maximized()?;
+
+        Ok(track_normal_bounds(self.window_id, bounds, is_normal).unwrap_or(bounds))
+    }
+
     pub fn is_focused(&self) -> XCapResult<bool> {
         unsafe {
             let workspace = NSWorkspace::sharedWorkspace();
@@ -722,6 +853,47 @@ impl ImplWindow {
         }
     }
 
+    pub fn layer(&self) -> XCapResult<WindowLayer> {
+        let window_cf_dictionary = get_window_cf_dictionary(self.window_id)?;
+
+        let level = get_cf_number_i32_value(window_cf_dictionary.as_ref(), "kCGWindowLayer")?;
+
+        Ok(window_layer_from_level(level as isize))
+    }
+
+    pub fn parent_id(&self) -> XCapResult<Option<u32>> {
+        // CGWindow 没有窗口之间的所属关系，把高于普通层级的面板、菜单等窗口
+        // 归到同一个程序最前面的普通窗口
+        let window_cf_dictionary = get_window_cf_dictionary(self.window_id)?;
+        let level = get_cf_number_i32_value(window_cf_dictionary.as_ref(), "kCGWindowLayer")?;
+        if level <= 0 {
+            return Ok(None);
+        }
+
+        let pid = self.pid()?;
+        for impl_window in ImplWindow::all()? {
+            if impl_window.window_id == self.window_id {
+                continue;
+            }
+
+            let Ok(window_cf_dictionary) = get_window_cf_dictionary(impl_window.window_id) else {
+                continue;
+            };
+            let is_same_app =
+                get_cf_number_i32_value(window_cf_dictionary.as_ref(), "kCGWindowOwnerPID")
+                    .is_ok_and(|owner_pid| owner_pid as u32 == pid);
+            let is_normal =
+                get_cf_number_i32_value(window_cf_dictionary.as_ref(), "kCGWindowLayer")
+                    .is_ok_and(|level| level == 0);
+
+            if is_same_app && is_normal {
+                return Ok(Some(impl_window.window_id));
+            }
+        }
+
+        Ok(None)
+    }
+
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
         let window_cf_dictionary = get_window_cf_dictionary(self.window_id)?;
 
@@ -734,4 +906,87 @@ impl ImplWindow {
             None, // 窗口捕获不需要 display_id
         )
     }
+
+    pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
+        let window_cf_dictionary = get_window_cf_dictionary(self.window_id)?;
+
+        let window_cg_rect = get_window_cg_rect(window_cf_dictionary.as_ref())?;
+
+        // 只截取窗口中的区域，CGWindowListCreateImage 和 ScreenCaptureKit 都只读取这部分像素
+        let cg_rect = CGRect {
+            origin: CGPoint {
+                x: window_cg_rect.origin.x + x as f64,
+                y: window_cg_rect.origin.y + y as f64,
+            },
+            size: CGSize {
+                width: width as f64,
+                height: height as f64,
+            },
+        };
+
+        capture(
+            cg_rect,
+            CGWindowListOption::OptionIncludingWindow,
+            self.window_id,
+            None,
+        )
+    }
+
+    pub fn can_capture(&self) -> XCapResult<bool> {
+        if !has_screen_capture_access() {
+            return Ok(false);
+        }
+
+        // 窗口已经关闭
+        let Ok(window_cf_dictionary) = get_window_cf_dictionary(self.window_id) else {
+            return Ok(false);
+        };
+
+        // kCGWindowSharingNone 的窗口截图是空白的
+        let window_sharing_state =
+            get_cf_number_i32_value(window_cf_dictionary.as_ref(), "kCGWindowSharingState")?;
+
+        Ok(window_sharing_state != 0)
+    }
+
+    pub fn scroll(&self, clicks: u32) -> XCapResult<()> {
+        let window_cf_dictionary = get_window_cf_dictionary(self.window_id)?;
+
+        let cg_rect = get_window_cg_rect(window_cf_dictionary.as_ref())?;
+
+        // 滚轮事件发送给指针所在位置的窗口，事件位置设为窗口中心，负数表示向下滚动
+        let event = CGEvent::new_scroll_wheel_event2(
+            None,
+            CGScrollEventUnit::Line,
+            1,
+            -(clicks.min(i32::MAX as u32) as i32),
+            0,
+            0,
+        )
+        .ok_or_else(|| XCapError::new("CGEventCreateScrollWheelEvent2 failed"))?;
+
+        CGEvent::set_location(
+            Some(&event),
+            CGPoint {
+                x: cg_rect.origin.x + cg_rect.size.width / 2.0,
+                y: cg_rect.origin.y + cg_rect.size.height / 2.0,
+            },
+        );
+        CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&event));
+
+        Ok(())
+    }
+}
+
+/// 窗口内容变化的原生通知，还没有实现，由调用方轮询截图比较
+pub(crate) struct ImplWindowDamage;
+
+impl ImplWindowDamage {
+    pub fn new(_window_id: u32) -> XCapResult<ImplWindowDamage> {
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn wait(&mut self, _timeout: Duration) -> XCapResult<Vec<Rect>> {
+        Err(XCapError::NotSupported)
+    }
 }
@@ -0,0 +1,76 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc,
+};
+
+use dispatch2::DispatchQueue;
+use objc2::MainThreadMarker;
+
+use crate::{
+    Config,
+    error::{XCapError, XCapResult},
+};
+
+// 主队列上的任务执行过，说明宿主程序在运行主线程的 RunLoop
+static MAIN_RUN_LOOP_OBSERVED: AtomicBool = AtomicBool::new(false);
+// 上一次派发到主队列的任务超时未执行
+static MAIN_RUN_LOOP_STALLED: AtomicBool = AtomicBool::new(false);
+
+fn main_run_loop_error() -> XCapError {
+    XCapError::new(
+        "Main thread is not responding, the host application must run the main RunLoop \
+         (NSApplication, CFRunLoopRun or dispatch_main) to use this API from other threads",
+    )
+}
+
+/// 是否观察到主线程的 RunLoop 在运行
+pub(super) fn is_main_run_loop_observed() -> bool {
+    MAIN_RUN_LOOP_OBSERVED.load(Ordering::Relaxed)
+}
+
+/// 在主线程上执行了由 RunLoop 驱动的回调（主队列任务、通知等）时调用
+pub(super) fn mark_main_run_loop_observed() {
+    MAIN_RUN_LOOP_OBSERVED.store(true, Ordering::Relaxed);
+    MAIN_RUN_LOOP_STALLED.store(false, Ordering::Relaxed);
+}
+
+/// 向主队列派发一个空任务，主线程 RunLoop 在运行时会很快执行并记录下来
+pub(super) fn probe_main_run_loop() {
+    DispatchQueue::main().exec_async(mark_main_run_loop_observed);
+}
+
+/// 在主线程上执行闭包并等待结果，NSScreen、NSWorkspace 通知等只能在主线程上使用的 API 都要通过它调用
+///
+/// 已经在主线程上时直接执行。否则派发到主队列，这要求宿主程序运行主线程的 RunLoop
+/// （NSApplication、CFRunLoopRun 或 dispatch_main），超时后返回错误。
+/// 超时之后的调用不再等待，直接返回错误，直到主队列重新响应。
+pub(super) fn run_on_main<T, F>(f: F) -> XCapResult<T>
+where
+    F: FnOnce(MainThreadMarker) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    if let Some(mtm) = MainThreadMarker::new() {
+        return Ok(f(mtm));
+    }
+
+    let (tx, rx) = mpsc::sync_channel(1);
+    DispatchQueue::main().exec_async(move || {
+        mark_main_run_loop_observed();
+
+        // 主队列上的任务总是在主线程上执行
+        let mtm = unsafe { MainThreadMarker::new_unchecked() };
+        let _ = tx.send(f(mtm));
+    });
+
+    // 主队列之前没有响应，派发的任务会在 RunLoop 恢复后执行并清除标记
+    if MAIN_RUN_LOOP_STALLED.load(Ordering::Relaxed) {
+        return Err(main_run_loop_error());
+    }
+
+    // 等待主线程执行任务的超时时间，默认 2 秒
+    rx.recv_timeout(Config::get().main_thread_timeout())
+        .map_err(|_| {
+            MAIN_RUN_LOOP_STALLED.store(true, Ordering::Relaxed);
+            main_run_loop_error()
+        })
+}
@@ -1,8 +1,62 @@
 pub mod bgra_to_rgba;
 mod capture;
+pub mod capture_config_ext;
 mod capture_compatible;
+mod capture_thread;
 mod display_info;
+pub mod external_encoder_surface;
+pub mod frame_ext;
+mod main_thread;
+mod window_cache;
 
 pub mod impl_monitor;
+#[cfg(feature = "selector")]
+pub mod impl_selector;
 pub mod impl_video_recorder;
+#[cfg(feature = "virtual-camera")]
+pub mod impl_virtual_camera;
+#[cfg(feature = "virtual-display")]
+pub mod impl_virtual_display;
+pub mod impl_wake_lock;
 pub mod impl_window;
+
+use objc2_core_graphics::{CGEvent, CGPreflightScreenCaptureAccess};
+
+use crate::{
+    Backend, BackendInfo, Config,
+    error::{XCapError, XCapResult},
+    geometry::Point,
+};
+
+/// 是否已经授予屏幕录制权限，不会弹出授权提示
+pub(crate) fn has_screen_capture_access() -> bool {
+    unsafe { CGPreflightScreenCaptureAccess() }
+}
+
+/// 释放全局资源：移除通知观察者、停止缓存的 SCStream 并结束截图线程、清空缓存
+pub(crate) fn shutdown() -> XCapResult<()> {
+    impl_window::shutdown_active_app_tracker()?;
+    capture::clear_stream_caches()?;
+    window_cache::invalidate_window_cache()
+}
+
+/// 配置为 CoreGraphics 或者系统不支持 ScreenCaptureKit 时使用 CGWindowListCreateImage
+pub(crate) fn backend_info() -> BackendInfo {
+    let backend = if Config::get().backend() == Backend::CoreGraphics
+        || !capture::is_screencapturekit_available()
+    {
+        Backend::CoreGraphics
+    } else {
+        Backend::ScreenCaptureKit
+    };
+
+    BackendInfo::new(backend, false)
+}
+
+/// 鼠标指针的全局坐标，CoreGraphics 坐标的原点在主显示器左上角，单位是点
+pub(crate) fn cursor_position() -> XCapResult<Point> {
+    let event = CGEvent::new(None).ok_or_else(|| XCapError::new("CGEventCreate failed"))?;
+    let location = CGEvent::location(Some(&event));
+
+    Ok(Point::new(location.x.floor() as i32, location.y.floor() as i32))
+}
@@ -0,0 +1,114 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use objc2_core_foundation::{CFDictionary, CFRetained};
+use objc2_core_graphics::{CGWindowListCopyWindowInfo, CGWindowListOption};
+
+use crate::{
+    Config,
+    error::{XCapError, XCapResult},
+};
+
+use super::impl_window::get_window_id;
+
+// 每次清空缓存时递增，各线程的缓存代数落后时失效
+static WINDOW_CACHE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    // CFDictionary 没有实现 Send，缓存保存在查询它的线程中，不跨线程共享
+    static WINDOW_CACHE: RefCell<Option<WindowCache>> = const { RefCell::new(None) };
+}
+
+/// 窗口列表的一次快照，按窗口 id 索引
+struct WindowCache {
+    generation: u64,
+    updated_at: Instant,
+    windows: HashMap<u32, CFRetained<CFDictionary>>,
+}
+
+impl WindowCache {
+    fn load(generation: u64) -> XCapResult<WindowCache> {
+        unsafe {
+            let cf_array = CGWindowListCopyWindowInfo(
+                CGWindowListOption::OptionOnScreenOnly | CGWindowListOption::ExcludeDesktopElements,
+                0,
+            )
+            .ok_or(XCapError::new("Get window info failed"))?;
+
+            let windows_count = cf_array.count();
+            let mut windows = HashMap::with_capacity(windows_count as usize);
+
+            for i in 0..windows_count {
+                let window_cf_dictionary_ref = cf_array.value_at_index(i) as *const CFDictionary;
+
+                if window_cf_dictionary_ref.is_null() {
+                    continue;
+                }
+                let window_cf_dictionary = &*window_cf_dictionary_ref;
+
+                let window_id = match get_window_id(window_cf_dictionary) {
+                    Ok(val) => val,
+                    Err(_) => continue,
+                };
+
+                if let Some(copy) = CFDictionary::new_copy(None, Some(window_cf_dictionary)) {
+                    windows.insert(window_id, copy);
+                }
+            }
+
+            Ok(WindowCache {
+                generation,
+                updated_at: Instant::now(),
+                windows,
+            })
+        }
+    }
+
+    fn get(&self, window_id: u32) -> Option<CFRetained<CFDictionary>> {
+        self.windows.get(&window_id).cloned()
+    }
+}
+
+/// 设置窗口信息缓存的有效时间，设为 0 时禁用缓存，每次查询都重新读取窗口列表
+pub fn set_window_cache_ttl(ttl: Duration) -> XCapResult<()> {
+    Config::update(|config| config.window_cache_ttl = ttl)?;
+    invalidate_window_cache()
+}
+
+/// 清空窗口信息缓存，窗口发生变化（应用切换、窗口移动等）后下一次查询会重新读取窗口列表
+///
+/// 所有线程的缓存都会失效
+pub fn invalidate_window_cache() -> XCapResult<()> {
+    WINDOW_CACHE_GENERATION.fetch_add(1, Ordering::AcqRel);
+    Ok(())
+}
+
+/// 获取窗口信息，缓存未过期时直接返回缓存中的字典
+///
+/// 缓存未命中时读取整个窗口列表并缓存所有窗口，连续查询多个窗口的属性也只需要读取一次
+pub(super) fn get_window_cf_dictionary(window_id: u32) -> XCapResult<CFRetained<CFDictionary>> {
+    // 缓存时间默认 100ms，覆盖一次连续查询窗口属性（位置、大小、标题等）的时间即可
+    let ttl = Config::get().window_cache_ttl();
+    let generation = WINDOW_CACHE_GENERATION.load(Ordering::Acquire);
+
+    WINDOW_CACHE.with_borrow_mut(|window_cache| {
+        if let Some(cache) = window_cache.as_ref()
+            && cache.generation == generation
+            && cache.updated_at.elapsed() < ttl
+            && let Some(window_cf_dictionary) = cache.get(window_id)
+        {
+            return Ok(window_cf_dictionary);
+        }
+
+        // 缓存过期或者是新创建的窗口，重新读取窗口列表
+        let cache = WindowCache::load(generation)?;
+        let window_cf_dictionary = cache.get(window_id);
+        *window_cache = (!ttl.is_zero()).then_some(cache);
+
+        window_cf_dictionary.ok_or(XCapError::new("Window not found"))
+    })
+}
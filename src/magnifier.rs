@@ -0,0 +1,189 @@
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use image::Rgba;
+
+use crate::{
+    RecorderConfig, VideoRecorder,
+    error::{XCapError, XCapResult},
+    video_recorder::Frame,
+};
+
+// 每个像素放大到至少这么多个像素时才绘制网格，否则网格会盖住画面
+const MIN_GRID_ZOOM: f32 = 4.0;
+
+/// Live, scaled-up frames of the area around the mouse cursor, for color pickers and
+/// accessibility tools. Built on [`VideoRecorder::follow_cursor`], so it has the same platform
+/// support.
+///
+/// Pixels are enlarged without smoothing and the pixel under the cursor is in the center of the
+/// frame.
+///
+/// ```no_run
+/// use image::Rgba;
+/// use xcap::{Magnifier, RecorderConfig};
+///
+/// let (recorder, rx) = Magnifier::new(240, 240)
+///     .zoom(8.0)
+///     .grid(Some(Rgba([128, 128, 128, 255])))
+///     .start(&RecorderConfig::default())
+///     .unwrap();
+/// recorder.start().unwrap();
+///
+/// let frame = rx.recv().unwrap();
+/// let center = frame.to_rgba_image().unwrap().get_pixel(120, 120).0;
+/// println!("color under the cursor: {center:?}");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Magnifier {
+    width: u32,
+    height: u32,
+    zoom: f32,
+    grid: Option<Rgba<u8>>,
+}
+
+impl Magnifier {
+    /// A magnifier producing `width` x `height` pixel frames at 4x zoom without a grid.
+    pub fn new(width: u32, height: u32) -> Magnifier {
+        Magnifier {
+            width,
+            height,
+            zoom: 4.0,
+            grid: None,
+        }
+    }
+
+    /// How many times each captured pixel is enlarged. Values below 1 are treated as 1.
+    pub fn zoom(mut self, zoom: f32) -> Magnifier {
+        self.zoom = if zoom.is_finite() { zoom.max(1.0) } else { 1.0 };
+        self
+    }
+
+    /// Draw lines of this color between the enlarged pixels, from 4x zoom on. Defaults to none.
+    pub fn grid(mut self, grid: Option<Rgba<u8>>) -> Magnifier {
+        self.grid = grid;
+        self
+    }
+
+    /// The size of the captured region around the cursor, in pixels.
+    fn source_size(&self) -> (u32, u32) {
+        (
+            (self.width as f32 / self.zoom).ceil().max(1.0) as u32,
+            (self.height as f32 / self.zoom).ceil().max(1.0) as u32,
+        )
+    }
+
+    /// Start recording the area around the cursor, returning the recorder and the receiver of
+    /// the magnified frames. Call [`VideoRecorder::start`] to begin.
+    pub fn start(&self, config: &RecorderConfig) -> XCapResult<(VideoRecorder, Receiver<Frame>)> {
+        if self.width == 0 || self.height == 0 {
+            return Err(XCapError::InvalidCaptureRegion(format!(
+                "Magnifier size {}x{} must not be empty",
+                self.width, self.height
+            )));
+        }
+
+        let (source_width, source_height) = self.source_size();
+        let (recorder, source_rx) =
+            VideoRecorder::follow_cursor(source_width, source_height, config)?;
+
+        // 不缓存帧，接收端没有取走时和单显示器录制一样丢帧
+        let (tx, rx) = mpsc::sync_channel(0);
+        let magnifier = *self;
+        thread::spawn(move || {
+            for frame in source_rx {
+                if tx.send(magnifier.magnify(frame)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok((recorder, rx))
+    }
+
+    /// 按最近邻放大，帧比预期小时（显示器比区域小）边缘的像素会重复
+    fn magnify(&self, mut frame: Frame) -> Frame {
+        let (source_width, source_height) = self.source_size();
+        // 区域在帧中居中，帧和区域一样大时偏移为 0
+        let offset_x = (source_width as i64 - frame.width() as i64) / 2;
+        let offset_y = (source_height as i64 - frame.height() as i64) / 2;
+        let max_x = frame.width().saturating_sub(1) as i64;
+        let max_y = frame.height().saturating_sub(1) as i64;
+        let grid = self.grid.filter(|_| self.zoom >= MIN_GRID_ZOOM);
+
+        // 输出坐标对应的区域坐标，以及是否是一个放大像素的第一行或第一列
+        let source = |output: u32| {
+            let source = (output as f32 / self.zoom) as i64;
+            let is_edge = output > 0 && ((output - 1) as f32 / self.zoom) as i64 != source;
+            (source, is_edge)
+        };
+        let columns: Vec<(i64, bool)> = (0..self.width).map(source).collect();
+
+        let stride = self.width as usize * 4;
+        let mut raw = vec![0; stride * self.height as usize];
+        for (y, row) in raw.chunks_exact_mut(stride).enumerate() {
+            let (source_y, is_edge_y) = source(y as u32);
+            let source_y = (source_y - offset_y).clamp(0, max_y) as usize;
+            let source_row = source_y * frame.stride();
+
+            for (pixel, &(source_x, is_edge_x)) in row.chunks_exact_mut(4).zip(&columns) {
+                let color = match grid {
+                    Some(grid) if is_edge_x || is_edge_y => grid.0,
+                    _ => {
+                        let source_x = (source_x - offset_x).clamp(0, max_x) as usize;
+                        let start = source_row + source_x * 4;
+                        match frame.data().get(start..start + 4) {
+                            Some(color) => [color[0], color[1], color[2], color[3]],
+                            None => [0, 0, 0, 0],
+                        }
+                    }
+                };
+                pixel.copy_from_slice(&color);
+            }
+        }
+
+        let mut magnified =
+            Frame::with_stride(self.width, self.height, stride, raw, frame.timestamp());
+        magnified.annotations = std::mem::take(&mut frame.annotations);
+
+        magnified
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn test_magnify() {
+        // 2x2 像素，每个像素的 4 个通道相同
+        let data = vec![1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4];
+        let frame = Frame::with_stride(2, 2, 8, data, Instant::now());
+
+        let image = Magnifier::new(4, 4)
+            .zoom(2.0)
+            .magnify(frame.clone())
+            .to_rgba_image()
+            .unwrap();
+        assert_eq!(image.get_pixel(1, 1).0, [1, 1, 1, 1]);
+        assert_eq!(image.get_pixel(2, 1).0, [2, 2, 2, 2]);
+        assert_eq!(image.get_pixel(3, 3).0, [4, 4, 4, 4]);
+
+        // 放大 4 倍时在每个放大像素的第一行和第一列绘制网格
+        let grid = Rgba([9, 9, 9, 9]);
+        let image = Magnifier::new(8, 8)
+            .zoom(4.0)
+            .grid(Some(grid))
+            .magnify(frame)
+            .to_rgba_image()
+            .unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [1, 1, 1, 1]);
+        assert_eq!(image.get_pixel(4, 1).0, grid.0);
+        assert_eq!(image.get_pixel(5, 4).0, grid.0);
+        assert_eq!(image.get_pixel(5, 5).0, [4, 4, 4, 4]);
+    }
+}
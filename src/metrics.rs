@@ -0,0 +1,80 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+/// A stage of the capture pipeline, reported to a [`MetricsSink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Stage {
+    /// Fetching the capturable content: ScreenCaptureKit shareable content on macOS.
+    ContentFetch,
+    /// Starting a capture stream: a ScreenCaptureKit stream on macOS, the screencast portal
+    /// session on Wayland. Reused streams are not reported.
+    StreamStart,
+    /// Waiting for the pixels of the capture to arrive.
+    FirstFrame,
+    /// Converting the captured pixels into an [`RgbaImage`](image::RgbaImage).
+    Conversion,
+}
+
+impl Stage {
+    /// A stable snake_case name, for use as a metric label.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::ContentFetch => "content_fetch",
+            Stage::StreamStart => "stream_start",
+            Stage::FirstFrame => "first_frame",
+            Stage::Conversion => "conversion",
+        }
+    }
+}
+
+/// Receives the time taken by each stage of a capture, so it can be exported to Prometheus,
+/// OpenTelemetry and the like. Install one with
+/// [`ConfigBuilder::metrics_sink`](crate::ConfigBuilder::metrics_sink).
+///
+/// The sink is called on the capturing thread, so it should return quickly.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use xcap::{Config, MetricsSink, Stage};
+///
+/// struct LogSink;
+///
+/// impl MetricsSink for LogSink {
+///     fn record(&self, stage: Stage, duration: Duration) {
+///         println!("xcap_{}_seconds {}", stage.name(), duration.as_secs_f64());
+///     }
+/// }
+///
+/// Config::builder().metrics_sink(LogSink).build().apply().unwrap();
+/// ```
+pub trait MetricsSink: Send + Sync {
+    fn record(&self, stage: Stage, duration: Duration);
+}
+
+/// 保存在 Config 中的 MetricsSink，同一个 sink 视为相等
+#[derive(Clone)]
+pub(crate) struct SharedMetricsSink(Arc<dyn MetricsSink>);
+
+impl SharedMetricsSink {
+    pub(crate) fn new<S: MetricsSink + 'static>(sink: S) -> Self {
+        SharedMetricsSink(Arc::new(sink))
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+    pub(crate) fn record(&self, stage: Stage, duration: Duration) {
+        self.0.record(stage, duration);
+    }
+}
+
+impl fmt::Debug for SharedMetricsSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedMetricsSink").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for SharedMetricsSink {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
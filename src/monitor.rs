@@ -1,11 +1,187 @@
-use std::sync::mpsc::Receiver;
+use std::{
+    sync::{Arc, mpsc::Receiver},
+    time::Duration,
+};
+
+#[cfg(feature = "ddc")]
+use crate::{DdcCapabilities, VcpValue};
 
-use image::RgbaImage;
+use image::{
+    Rgba, RgbaImage,
+    imageops::{self, FilterType},
+};
 
 use crate::{
-    VideoRecorder, error::XCapResult, platform::impl_monitor::ImplMonitor, video_recorder::Frame,
+    CaptureConfig, CaptureReport, CoordinateSpace, MonitorIdentity, RecorderConfig, VideoRecorder,
+    capture_session::SharedRecorder,
+    delayed_capture::capture_after,
+    encode::{encode_jpeg, encode_png},
+    error::{XCapError, XCapResult, catch_panics},
+    geometry::{
+        Direction, Point, Rect, RelativePosition, bounding_rect, neighbor, relative_position,
+        split_region,
+    },
+    monitor_watcher::MonitorWatcher,
+    platform::impl_monitor::ImplMonitor,
+    thumbnail_stream::thumbnail_size,
+    video_recorder::{Frame, FrameView, RecorderHealth},
 };
 
+// 采样点的包围矩形不超过这个面积时只截一次图
+const SAMPLE_BATCH_AREA: u64 = 64 * 64;
+
+/// 每个镜像组只保留第一个显示器，返回保留的下标，groups 为每个显示器所在镜像组的显示器 ID
+fn first_of_mirror_groups(groups: &[Vec<u32>]) -> Vec<usize> {
+    let mut seen = Vec::new();
+    let mut indices = Vec::new();
+
+    for (index, group) in groups.iter().enumerate() {
+        if group.iter().any(|id| seen.contains(id)) {
+            continue;
+        }
+
+        seen.extend_from_slice(group);
+        indices.push(index);
+    }
+
+    indices
+}
+
+/// 按 unique_key 查找显示器时比较的键，序列号和 UUID 可能需要查询 WMI 或 IOKit，
+/// 每个显示器只查询一次
+#[derive(Debug, Default)]
+struct MonitorKeys {
+    serial: Option<String>,
+    uuid: Option<String>,
+    id: Option<u32>,
+}
+
+impl MonitorKeys {
+    fn new(monitor: &Monitor) -> MonitorKeys {
+        MonitorKeys {
+            serial: monitor
+                .serial_number()
+                .ok()
+                .filter(|serial| !serial.is_empty()),
+            uuid: monitor.uuid().ok(),
+            id: monitor.id().ok(),
+        }
+    }
+
+    /// 组合键，格式和 UUID_VS_SERIAL.md 中的一致，序列号和 UUID 都没有时返回 None
+    fn composite_key(&self) -> Option<String> {
+        if self.serial.is_none() && self.uuid.is_none() {
+            return None;
+        }
+
+        Some(format!(
+            "{}-{}",
+            self.serial.as_deref().unwrap_or("NO_SERIAL"),
+            self.uuid.as_deref().unwrap_or("NO_UUID")
+        ))
+    }
+
+    fn matches(&self, unique_key: &str, level: UniqueKeyMatch) -> bool {
+        match level {
+            UniqueKeyMatch::Composite => self.composite_key().as_deref() == Some(unique_key),
+            UniqueKeyMatch::Serial => self.serial.as_deref() == Some(unique_key),
+            UniqueKeyMatch::Uuid => self.uuid.as_deref() == Some(unique_key),
+            UniqueKeyMatch::Id => self.id.is_some_and(|id| id.to_string() == unique_key),
+            UniqueKeyMatch::ModelAndPosition => false,
+        }
+    }
+}
+
+/// 从精确到宽松逐级查找 unique_key 对应的显示器，返回下标和匹配的级别
+fn match_unique_key(keys: &[MonitorKeys], unique_key: &str) -> Option<(usize, UniqueKeyMatch)> {
+    [
+        UniqueKeyMatch::Composite,
+        UniqueKeyMatch::Serial,
+        UniqueKeyMatch::Uuid,
+        UniqueKeyMatch::Id,
+    ]
+    .into_iter()
+    .find_map(|level| {
+        keys.iter()
+            .position(|key| key.matches(unique_key, level))
+            .map(|index| (index, level))
+    })
+}
+
+/// How [`Monitor::capture_region_with_mode`] handles a region that extends past the monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RegionMode {
+    /// Fail with [`XCapError::InvalidCaptureRegion`].
+    #[default]
+    Strict,
+    /// Capture the part of the region that lies on the monitor. Fails only when the region does
+    /// not overlap the monitor at all.
+    Clamp,
+}
+
+/// The power state of a display, following the DPMS levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerState {
+    /// The display is on.
+    On,
+    /// The display is blanked and wakes up quickly.
+    Standby,
+    /// The display is in a deeper power saving state.
+    Suspend,
+    /// The display is off or asleep.
+    Off,
+}
+
+/// How a display encodes its pixels, see [`Monitor::pixel_encoding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelEncoding {
+    /// RGB with integer channels.
+    Rgb,
+    /// RGB with floating point channels, used on macOS for extended dynamic range.
+    RgbFloat,
+    /// YCbCr, used by some TVs and HDMI connections.
+    YCbCr,
+}
+
+/// The range a display's refresh rate varies in, see [`Monitor::refresh_rate_range`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RefreshRateRange {
+    /// The lowest refresh rate in Hz.
+    pub min: f32,
+    /// The highest refresh rate in Hz.
+    pub max: f32,
+}
+
+/// Which key [`Monitor::find_by_unique_key`] matched a monitor by, from the most to the least
+/// exact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UniqueKeyMatch {
+    /// The `{serial}-{uuid}` composite key, with `NO_SERIAL` or `NO_UUID` for a missing part.
+    Composite,
+    /// The serial number.
+    Serial,
+    /// The UUID.
+    Uuid,
+    /// The monitor id.
+    Id,
+    /// No key matched, the monitor has the model and position of the fallback placement.
+    ModelAndPosition,
+}
+
+/// The model and position of a monitor, see [`Monitor::placement`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorPlacement {
+    /// The PNP vendor id, see [`MonitorIdentity::vendor`].
+    pub vendor: String,
+    /// The vendor's product code, see [`MonitorIdentity::product`].
+    pub product: u16,
+    /// The x coordinate of the monitor.
+    pub x: i32,
+    /// The y coordinate of the monitor.
+    pub y: i32,
+}
+
 #[derive(Debug, Clone)]
 pub struct Monitor {
     pub(crate) impl_monitor: ImplMonitor,
@@ -19,21 +195,93 @@ impl Monitor {
 
 impl Monitor {
     pub fn all() -> XCapResult<Vec<Monitor>> {
-        let monitors = ImplMonitor::all()?
+        let monitors = catch_panics(ImplMonitor::all)?
             .iter()
             .map(|impl_monitor| Monitor::new(impl_monitor.clone()))
             .collect();
 
         Ok(monitors)
     }
+
+    /// Like [`Monitor::all`], but lists each group of mirrored displays once, since capturing
+    /// every display of a mirror captures the same content. The first display of each group in
+    /// [`Monitor::all`] order is kept.
+    pub fn all_without_mirrors() -> XCapResult<Vec<Monitor>> {
+        let monitors = Monitor::all()?;
+
+        let mut groups = Vec::with_capacity(monitors.len());
+        for monitor in &monitors {
+            let mut group = vec![monitor.id()?];
+            for impl_monitor in monitor.impl_monitor.mirror_group()? {
+                group.push(impl_monitor.id()?);
+            }
+            groups.push(group);
+        }
+
+        let indices = first_of_mirror_groups(&groups);
+        let monitors = monitors
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| indices.contains(index))
+            .map(|(_, monitor)| monitor)
+            .collect();
+
+        Ok(monitors)
+    }
+    /// Find the monitor with a key saved from [`Monitor::unique_key`]. A serial number, a UUID,
+    /// an id string or the `{serial}-{uuid}` composite key are accepted as well.
     pub fn from_unique_key(unique_key: String) -> XCapResult<Monitor> {
-        let impl_monitor = ImplMonitor::from_unique_key(unique_key)?;
+        let (monitor, _) = Monitor::find_by_unique_key(&unique_key, None)?;
 
-        Ok(Monitor::new(impl_monitor))
+        Ok(monitor)
+    }
+
+    /// Like [`Monitor::from_unique_key`], also returning which key matched. When no key matches
+    /// and `fallback` is given, for example because a monitor was swapped for another unit of
+    /// the same model, the monitor of the same model at the same position is returned.
+    ///
+    /// ```no_run
+    /// use xcap::Monitor;
+    ///
+    /// let monitor = Monitor::all().unwrap().remove(0);
+    /// let unique_key = monitor.unique_key().unwrap();
+    /// let placement = monitor.placement().ok();
+    ///
+    /// let (monitor, matched) =
+    ///     Monitor::find_by_unique_key(&unique_key, placement.as_ref()).unwrap();
+    /// println!("found {:?} by {:?}", monitor.name(), matched);
+    /// ```
+    pub fn find_by_unique_key(
+        unique_key: &str,
+        fallback: Option<&MonitorPlacement>,
+    ) -> XCapResult<(Monitor, UniqueKeyMatch)> {
+        let mut monitors = Monitor::all()?;
+        let keys: Vec<MonitorKeys> = monitors.iter().map(MonitorKeys::new).collect();
+
+        if let Some((index, level)) = match_unique_key(&keys, unique_key) {
+            return Ok((monitors.swap_remove(index), level));
+        }
+
+        let index = fallback.and_then(|fallback| {
+            monitors
+                .iter()
+                .position(|monitor| monitor.placement().ok().as_ref() == Some(fallback))
+        });
+
+        match index {
+            Some(index) => Ok((
+                monitors.swap_remove(index),
+                UniqueKeyMatch::ModelAndPosition,
+            )),
+            None => Err(XCapError::new(format!(
+                "Monitor with unique_key '{}' not found",
+                unique_key
+            ))),
+        }
     }
 
     pub fn from_point(x: i32, y: i32) -> XCapResult<Monitor> {
-        let impl_monitor = ImplMonitor::from_point(x, y)?;
+        let impl_monitor = catch_panics(|| ImplMonitor::from_point(x, y))?;
 
         Ok(Monitor::new(impl_monitor))
     }
@@ -44,6 +292,8 @@ impl Monitor {
     pub fn id(&self) -> XCapResult<u32> {
         self.impl_monitor.id()
     }
+    /// A key that identifies the monitor across restarts, for [`Monitor::from_unique_key`]. The
+    /// serial number when available, otherwise the UUID, otherwise the id.
     pub fn unique_key(&self) -> XCapResult<String> {
         // 1. 优先使用序列号（硬件属性，最可靠）
         if let Ok(serial) = self.serial_number() {
@@ -61,6 +311,10 @@ impl Monitor {
         return self.id().map(|id| id.to_string());
     }
     /// Unique identifier associated with the screen.
+    ///
+    /// On macOS the friendly name comes from `NSScreen`, which is only available on the main
+    /// thread. When called from another thread the host must run the main RunLoop, otherwise a
+    /// generic name is returned.
     pub fn name(&self) -> XCapResult<String> {
         self.impl_monitor.name()
     }
@@ -92,6 +346,31 @@ impl Monitor {
     pub fn frequency(&self) -> XCapResult<f32> {
         self.impl_monitor.frequency()
     }
+    /// The range the screen's refresh rate varies in with variable refresh rate (VRR, ProMotion),
+    /// or the current refresh rate at both ends for fixed-rate screens. macOS reads NSScreen's
+    /// refresh intervals and X11 the EDID range limits of outputs with the `vrr_capable` RandR
+    /// property. Windows returns [`XCapError::NotSupported`].
+    pub fn refresh_rate_range(&self) -> XCapResult<RefreshRateRange> {
+        self.impl_monitor.refresh_rate_range()
+    }
+    /// Whether the screen uses a variable refresh rate, see [`Monitor::refresh_rate_range`].
+    pub fn is_variable_refresh_rate(&self) -> XCapResult<bool> {
+        let range = self.refresh_rate_range()?;
+
+        Ok(range.min < range.max)
+    }
+    /// Bits per color channel the screen is driven at, e.g. 8 or 10. Read from the display
+    /// mode's pixel encoding on macOS, the DXGI output description on Windows and the root visual
+    /// on X11.
+    pub fn bit_depth(&self) -> XCapResult<u32> {
+        self.impl_monitor.bit_depth()
+    }
+    /// How the screen encodes its pixels, so capture pipelines can pick a matching output
+    /// format. Windows reads the DXGI output color space and X11 the RandR `Colorspace` output
+    /// property; macOS only reports RGB encodings.
+    pub fn pixel_encoding(&self) -> XCapResult<PixelEncoding> {
+        self.impl_monitor.pixel_encoding()
+    }
     /// Whether the screen is the main screen
     pub fn is_primary(&self) -> XCapResult<bool> {
         self.impl_monitor.is_primary()
@@ -102,6 +381,121 @@ impl Monitor {
         self.impl_monitor.is_builtin()
     }
 
+    /// Call `callback` with a fresh handle to this screen whenever its position, size, scale
+    /// factor, rotation or refresh rate changes. The screen is checked every 500ms and is tracked
+    /// by [`Monitor::unique_key`], so the handle stays valid after the change. Video recorders
+    /// for the screen restart their stream by themselves and emit
+    /// [`RecorderEvent::DisplayReconfigured`](crate::RecorderEvent::DisplayReconfigured).
+    ///
+    /// ```no_run
+    /// use xcap::Monitor;
+    ///
+    /// let monitor = Monitor::from_point(100, 100).unwrap();
+    /// let _watcher = monitor
+    ///     .on_change(|monitor| {
+    ///         println!("monitor changed: {:?}", monitor.bounds());
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn on_change<F>(&self, callback: F) -> XCapResult<MonitorWatcher>
+    where
+        F: FnMut(&Monitor) + Send + 'static,
+    {
+        MonitorWatcher::new(self, callback)
+    }
+
+    /// The power state of the screen, so captures from a sleeping display can be skipped instead
+    /// of producing black frames.
+    ///
+    /// - macOS reports [`PowerState::On`] or [`PowerState::Off`].
+    /// - Windows reads the monitor's power mode over DDC/CI, which fails for monitors that do not
+    ///   support it.
+    /// - X11 reports the DPMS level, which applies to all monitors of the screen. Wayland returns
+    ///   [`XCapError::NotSupported`].
+    pub fn power_state(&self) -> XCapResult<PowerState> {
+        self.impl_monitor.power_state()
+    }
+
+    /// The screen's current brightness from 0.0 to 1.0, e.g. to normalize the exposure of
+    /// captured frames.
+    ///
+    /// - macOS reads it with DisplayServices, or CoreDisplay on older systems, which supports
+    ///   built-in and Apple displays.
+    /// - Windows reads the luminance over DDC/CI, falling back to WMI for built-in screens.
+    /// - Linux reads the sysfs backlight of built-in screens.
+    ///
+    /// Returns [`XCapError::NotSupported`] where the brightness cannot be read, e.g. for most
+    /// external monitors on macOS and Linux.
+    pub fn brightness(&self) -> XCapResult<f32> {
+        self.impl_monitor.brightness()
+    }
+
+    /// Whether the screen is in any power saving state, see [`Monitor::power_state`].
+    pub fn is_asleep(&self) -> XCapResult<bool> {
+        Ok(self.power_state()? != PowerState::On)
+    }
+
+    /// Cheaply check whether the monitor can be captured right now, without capturing, so UIs
+    /// can disable capture buttons up front. Checks the screen recording permission on macOS,
+    /// whether the input desktop is accessible (not the lock screen or a UAC prompt) on Windows,
+    /// and whether an X11 connection or a Wayland capture backend is available on Linux.
+    pub fn can_capture(&self) -> XCapResult<bool> {
+        self.impl_monitor.can_capture()
+    }
+
+    /// Whether the screen mirrors or is mirrored by another display.
+    pub fn is_mirrored(&self) -> XCapResult<bool> {
+        self.impl_monitor.is_mirrored()
+    }
+
+    /// The displays showing the same content as this screen, including itself. On Windows
+    /// duplicated displays share a single monitor, so the group only contains this monitor even
+    /// when [`Monitor::is_mirrored`] is `true`.
+    pub fn mirror_group(&self) -> XCapResult<Vec<Monitor>> {
+        let monitors = self
+            .impl_monitor
+            .mirror_group()?
+            .into_iter()
+            .map(Monitor::new)
+            .collect();
+
+        Ok(monitors)
+    }
+
+    /// The vendor, product, serial number, model name and manufacture date of the display,
+    /// parsed from its EDID. Linux reads the EDID from RandR and Windows from the monitor's
+    /// registry key; on macOS displays without an EDID in IOKit, such as Apple Silicon built-in
+    /// screens, fall back to the CoreGraphics vendor, model and serial numbers.
+    ///
+    /// With the `ddc` feature, a missing model name is read from the monitor itself, see
+    /// [`Monitor::ddc_capabilities`].
+    pub fn identity(&self) -> XCapResult<MonitorIdentity> {
+        let identity = self.impl_monitor.identity()?;
+
+        #[cfg(feature = "ddc")]
+        let identity = MonitorIdentity {
+            name: identity
+                .name
+                .or_else(|| self.ddc_capabilities().ok()?.model),
+            ..identity
+        };
+
+        Ok(identity)
+    }
+
+    /// The model and position of the monitor. Save it next to [`Monitor::unique_key`] to find a
+    /// replacement of the same model with [`Monitor::find_by_unique_key`].
+    pub fn placement(&self) -> XCapResult<MonitorPlacement> {
+        let identity = self.identity()?;
+
+        Ok(MonitorPlacement {
+            vendor: identity.vendor,
+            product: identity.product,
+            x: self.x()?,
+            y: self.y()?,
+        })
+    }
+
     /// Get the display UUID (persistent unique identifier)
     /// This UUID remains constant across system restarts and display reconnections.
     /// Currently only supported on macOS.
@@ -151,10 +545,95 @@ impl Monitor {
     }
 }
 
+#[cfg(feature = "ddc")]
+impl Monitor {
+    /// The capabilities string the monitor reports over DDC/CI, including its model name, for
+    /// monitors whose vendor zeroed or left out the EDID fields. Requires the `ddc` feature.
+    ///
+    /// Windows uses the DDC/CI functions of the Monitor Configuration API. Linux talks to the
+    /// monitor through `/dev/i2c-*`, which needs the `i2c-dev` module and read-write access to
+    /// the device, usually by membership of the `i2c` group. Built-in screens, most docks and
+    /// macOS return an error. Each request takes tens to hundreds of milliseconds.
+    pub fn ddc_capabilities(&self) -> XCapResult<DdcCapabilities> {
+        Ok(DdcCapabilities::parse(
+            self.impl_monitor.ddc_capabilities()?,
+        ))
+    }
+
+    /// Read a VCP feature over DDC/CI, e.g. `0xC9` for the firmware version or `0x10` for the
+    /// brightness. See [`Monitor::ddc_capabilities`] for platform support.
+    pub fn vcp_feature(&self, code: u8) -> XCapResult<VcpValue> {
+        let (current, maximum) = self.impl_monitor.vcp_feature(code)?;
+
+        Ok(VcpValue { current, maximum })
+    }
+}
+
+impl Monitor {
+    /// The screen bounds in global coordinates.
+    pub fn bounds(&self) -> XCapResult<Rect> {
+        Ok(Rect::new(
+            self.x()?,
+            self.y()?,
+            self.width()?,
+            self.height()?,
+        ))
+    }
+    /// Whether the point, in global coordinates, lies on the screen.
+    pub fn contains_point(&self, x: i32, y: i32) -> XCapResult<bool> {
+        Ok(self.bounds()?.contains_point(x, y))
+    }
+    /// Whether `rect`, in global coordinates, overlaps the screen.
+    pub fn intersects_rect(&self, rect: Rect) -> XCapResult<bool> {
+        Ok(self.bounds()?.intersects(rect))
+    }
+    /// The part of `rect`, in global coordinates, that lies on the screen.
+    pub fn intersection(&self, rect: Rect) -> XCapResult<Option<Rect>> {
+        Ok(self.bounds()?.intersection(rect))
+    }
+    /// The closest monitor beyond the `direction` side of the screen in the virtual desktop,
+    /// preferring one that shares an edge with it, then the nearest, then the one overlapping
+    /// it most along that edge. `None` when there is no monitor on that side.
+    pub fn neighbor(&self, direction: Direction) -> XCapResult<Option<Monitor>> {
+        let mut monitors = Monitor::all()?;
+        let rects = monitors
+            .iter()
+            .map(Monitor::bounds)
+            .collect::<XCapResult<Vec<Rect>>>()?;
+
+        Ok(neighbor(self.bounds()?, &rects, direction).map(|index| monitors.swap_remove(index)))
+    }
+    /// Where `other` lies relative to this monitor in the virtual desktop, see
+    /// [`RelativePosition`].
+    pub fn relative_position(&self, other: &Monitor) -> XCapResult<RelativePosition> {
+        Ok(relative_position(self.bounds()?, other.bounds()?))
+    }
+    /// Converters between global, monitor-local and captured-image coordinates, see
+    /// [`CoordinateSpace`]. The result is a snapshot and does not follow later display changes.
+    pub fn coordinate_space(&self) -> XCapResult<CoordinateSpace> {
+        Ok(CoordinateSpace::new(
+            self.bounds()?,
+            self.impl_monitor.pixel_ratio()? as f64,
+        ))
+    }
+}
+
 impl Monitor {
     /// Capture image of the monitor
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
-        self.impl_monitor.capture_image()
+        catch_panics(|| self.impl_monitor.capture_image())
+    }
+
+    /// Capture the monitor encoded as PNG, e.g. for an HTTP screenshot endpoint. The pixels are
+    /// encoded straight from the capture buffer.
+    pub fn capture_png(&self) -> XCapResult<Vec<u8>> {
+        encode_png(&self.impl_monitor.capture_image()?)
+    }
+
+    /// Capture the monitor encoded as JPEG with `quality` from 1 to 100. The alpha channel is
+    /// dropped while encoding, without copying the pixels.
+    pub fn capture_jpeg(&self, quality: u8) -> XCapResult<Vec<u8>> {
+        encode_jpeg(&self.impl_monitor.capture_image()?, quality)
     }
 
     /// Capture image of the monitor with a custom scale factor.
@@ -165,23 +644,257 @@ impl Monitor {
         self.impl_monitor.capture_image_with_scale(scale)
     }
 
+    /// Capture a downscaled image of the monitor that fits within `max_size` x `max_size`,
+    /// keeping the aspect ratio. Cheaper than [`capture_image`](Monitor::capture_image) on
+    /// Windows and macOS, where the image is scaled by the system.
+    pub fn capture_thumbnail(&self, max_size: u32) -> XCapResult<RgbaImage> {
+        let (width, height) = thumbnail_size(self.width()?, self.height()?, max_size);
+
+        self.impl_monitor.capture_thumbnail(width, height)
+    }
+
+    /// Capture image of the monitor, applying the options in `config`.
+    pub fn capture_image_with_config(&self, config: &CaptureConfig) -> XCapResult<RgbaImage> {
+        let (image, _) = self.capture_image_with_report(config)?;
+
+        Ok(image)
+    }
+
+    /// Capture image of the monitor like
+    /// [`capture_image_with_config`](Monitor::capture_image_with_config), also returning which backend took it and whether it fell back from another one.
+    pub fn capture_image_with_report(
+        &self,
+        config: &CaptureConfig,
+    ) -> XCapResult<(RgbaImage, CaptureReport)> {
+        let (image, report) =
+            config.capture(|| catch_panics(|| self.impl_monitor.capture_image_with_config(config)));
+        let mut image = image?;
+        if let Some(redactor) = &config.redactor {
+            redactor.redact(&mut FrameView::from_image(&mut image), self.bounds()?);
+        }
+        config.apply(&mut image);
+
+        Ok((image, report))
+    }
+
+    /// Capture image of the monitor after `delay`, blocking the calling thread.
+    pub fn capture_after(&self, delay: Duration) -> XCapResult<RgbaImage> {
+        capture_after(delay, None, || self.capture_image())
+    }
+
+    /// Capture image of the monitor once the display has presented its next frame, so UI changed
+    /// programmatically right before the call is captured fully rendered. On Windows this waits
+    /// for the next DWM composition, elsewhere for two refresh periods of the monitor.
+    pub fn capture_on_next_present(&self) -> XCapResult<RgbaImage> {
+        self.impl_monitor.wait_for_present()?;
+
+        self.capture_image()
+    }
+
+    /// Capture image of the monitor after `delay`, then wait for the focused window to change
+    /// and settle before capturing. If focus does not change within `timeout` the capture is
+    /// taken anyway.
+    pub fn capture_after_focus_change(
+        &self,
+        delay: Duration,
+        timeout: Duration,
+    ) -> XCapResult<RgbaImage> {
+        capture_after(delay, Some(timeout), || self.capture_image())
+    }
+
+    /// Capture `region`, in global coordinates, stitching together every monitor it overlaps.
+    /// Monitors left of or above the primary monitor have negative coordinates. Parts of the
+    /// region that are not on any monitor are transparent.
+    pub fn capture_screen_region(region: Rect) -> XCapResult<RgbaImage> {
+        let monitors = Monitor::all()?;
+        let bounds = monitors
+            .iter()
+            .map(Monitor::bounds)
+            .collect::<XCapResult<Vec<Rect>>>()?;
+
+        let parts = split_region(region, &bounds);
+        if parts.is_empty() {
+            return Err(XCapError::InvalidCaptureRegion(format!(
+                "Region {region:?} does not overlap any monitor"
+            )));
+        }
+
+        let mut image = RgbaImage::new(region.width, region.height);
+        for (index, local, (offset_x, offset_y)) in parts {
+            let mut part = monitors[index].impl_monitor.capture_region(
+                local.x as u32,
+                local.y as u32,
+                local.width,
+                local.height,
+            )?;
+
+            // 高分屏上截图是物理像素，缩放到逻辑尺寸后再拼接
+            if part.dimensions() != (local.width, local.height) {
+                part = imageops::resize(&part, local.width, local.height, FilterType::Triangle);
+            }
+
+            imageops::replace(&mut image, &part, offset_x as i64, offset_y as i64);
+        }
+
+        Ok(image)
+    }
+
     pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
-        self.impl_monitor.capture_region(x, y, width, height)
+        catch_panics(|| self.impl_monitor.capture_region(x, y, width, height))
+    }
+
+    /// Read the color of a few pixels, in coordinates relative to the monitor's top-left corner,
+    /// without capturing the whole monitor. Nearby points are read with one small capture,
+    /// scattered points with one single-pixel capture each.
+    pub fn sample_pixels(&self, points: &[Point]) -> XCapResult<Vec<Rgba<u8>>> {
+        let Some(region) = bounding_rect(points) else {
+            return Ok(Vec::new());
+        };
+
+        let bounds = Rect::new(0, 0, self.width()?, self.height()?);
+        if !bounds.contains_rect(region) {
+            return Err(XCapError::InvalidCaptureRegion(format!(
+                "Points {region:?} are outside monitor bounds {bounds:?}"
+            )));
+        }
+
+        // 高分屏上截图是物理像素，按比例换算到截图中的坐标
+        let sample = |image: &RgbaImage, region: Rect, point: &Point| {
+            let x = (point.x - region.x) as u64 * image.width() as u64 / region.width as u64;
+            let y = (point.y - region.y) as u64 * image.height() as u64 / region.height as u64;
+            *image.get_pixel(x as u32, y as u32)
+        };
+
+        if region.width as u64 * region.height as u64 <= SAMPLE_BATCH_AREA {
+            let image = self.impl_monitor.capture_region(
+                region.x as u32,
+                region.y as u32,
+                region.width,
+                region.height,
+            )?;
+
+            return Ok(points
+                .iter()
+                .map(|point| sample(&image, region, point))
+                .collect());
+        }
+
+        points
+            .iter()
+            .map(|point| {
+                let region = Rect::new(point.x, point.y, 1, 1);
+                let image =
+                    self.impl_monitor
+                        .capture_region(point.x as u32, point.y as u32, 1, 1)?;
+
+                Ok(sample(&image, region, point))
+            })
+            .collect()
+    }
+
+    /// Capture the `region` of the monitor, in coordinates relative to the monitor's top-left
+    /// corner. Returns the image together with the region that was actually captured, which
+    /// differs from `region` only in [`RegionMode::Clamp`].
+    pub fn capture_region_with_mode(
+        &self,
+        region: Rect,
+        mode: RegionMode,
+    ) -> XCapResult<(RgbaImage, Rect)> {
+        let bounds = Rect::new(0, 0, self.width()?, self.height()?);
+
+        let region = match mode {
+            RegionMode::Strict if bounds.contains_rect(region) => region,
+            RegionMode::Clamp => match bounds.intersection(region) {
+                Some(region) => region,
+                None => {
+                    return Err(XCapError::InvalidCaptureRegion(format!(
+                        "Region {region:?} does not overlap monitor bounds {bounds:?}"
+                    )));
+                }
+            },
+            RegionMode::Strict => {
+                return Err(XCapError::InvalidCaptureRegion(format!(
+                    "Region {region:?} is outside monitor bounds {bounds:?}"
+                )));
+            }
+        };
+
+        let image = self.impl_monitor.capture_region(
+            region.x as u32,
+            region.y as u32,
+            region.width,
+            region.height,
+        )?;
+
+        Ok((image, region))
     }
 
     pub fn video_recorder(&self) -> XCapResult<(VideoRecorder, Receiver<Frame>)> {
-        let (impl_video_recorder, sx) = self.impl_monitor.video_recorder()?;
+        self.video_recorder_with_config(&RecorderConfig::default())
+    }
 
-        Ok((VideoRecorder::new(impl_video_recorder), sx))
+    /// Create a video recorder for the monitor, applying the options in `config`.
+    pub fn video_recorder_with_config(
+        &self,
+        config: &RecorderConfig,
+    ) -> XCapResult<(VideoRecorder, Receiver<Frame>)> {
+        let health = Arc::new(RecorderHealth::new(config.stats_interval));
+        let (shared_recorder, sx) =
+            catch_panics(|| SharedRecorder::new(&self.impl_monitor, config, health.clone()))?;
+
+        Ok((VideoRecorder::new(shared_recorder, health), sx))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::XCapError;
-
     use super::*;
 
+    #[test]
+    fn test_first_of_mirror_groups() {
+        // 1 和 3 互为镜像，2 是独立的显示器
+        let groups = vec![vec![1, 3], vec![2], vec![3, 1]];
+        assert_eq!(first_of_mirror_groups(&groups), vec![0, 1]);
+
+        assert!(first_of_mirror_groups(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_match_unique_key() {
+        let keys = vec![
+            MonitorKeys {
+                serial: None,
+                uuid: Some("1".to_string()),
+                id: Some(2),
+            },
+            MonitorKeys {
+                serial: Some("ABC123".to_string()),
+                uuid: Some("UUID-B".to_string()),
+                id: Some(1),
+            },
+        ];
+
+        assert_eq!(
+            match_unique_key(&keys, "ABC123-UUID-B"),
+            Some((1, UniqueKeyMatch::Composite))
+        );
+        assert_eq!(
+            match_unique_key(&keys, "NO_SERIAL-1"),
+            Some((0, UniqueKeyMatch::Composite))
+        );
+        assert_eq!(
+            match_unique_key(&keys, "ABC123"),
+            Some((1, UniqueKeyMatch::Serial))
+        );
+        // UUID 比显示器 ID 优先
+        assert_eq!(
+            match_unique_key(&keys, "1"),
+            Some((0, UniqueKeyMatch::Uuid))
+        );
+        assert_eq!(match_unique_key(&keys, "2"), Some((0, UniqueKeyMatch::Id)));
+        assert_eq!(match_unique_key(&keys, "NO_SERIAL-NO_UUID"), None);
+    }
+
     #[test]
     fn test_capture_region_out_of_bounds() {
         let monitors = Monitor::all().unwrap();
@@ -200,4 +913,26 @@ mod tests {
             _ => panic!("Expected InvalidCaptureRegion error"),
         }
     }
+
+    #[test]
+    fn test_capture_region_clamp() {
+        let monitors = Monitor::all().unwrap();
+        let monitor = &monitors[0];
+
+        let width = monitor.width().unwrap();
+        let region = Rect::new(width as i32 / 2, -10, width, 20);
+
+        let (image, captured) = monitor
+            .capture_region_with_mode(region, RegionMode::Clamp)
+            .unwrap();
+
+        assert_eq!(
+            captured,
+            Rect::new(width as i32 / 2, 0, width - width / 2, 10)
+        );
+        assert_eq!(image.width(), captured.width);
+
+        let result = monitor.capture_region_with_mode(region, RegionMode::Strict);
+        assert!(matches!(result, Err(XCapError::InvalidCaptureRegion(_))));
+    }
 }
@@ -0,0 +1,234 @@
+use crate::error::{XCapError, XCapResult};
+
+// EDID 头部 (00 FF FF FF FF FF FF 00)
+const EDID_HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+// 4 个 18 字节的描述符块从字节 54 开始
+const DESCRIPTOR_OFFSET: usize = 54;
+const DESCRIPTOR_SERIAL: u8 = 0xFF;
+const DESCRIPTOR_NAME: u8 = 0xFC;
+
+/// When a display was manufactured, see [`MonitorIdentity::manufacture_date`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ManufactureDate {
+    /// The year, e.g. 2020.
+    pub year: u16,
+    /// The week of the year from 1 to 54, `None` when only the year is known.
+    pub week: Option<u8>,
+}
+
+/// Who made a display and which one it is, as reported by its EDID. Returned by
+/// [`Monitor::identity`](crate::Monitor::identity) with the same parsing on every platform.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MonitorIdentity {
+    /// The three-letter PNP vendor id, e.g. `DEL` or `APP`.
+    pub vendor: String,
+    /// The vendor's product code.
+    pub product: u16,
+    /// The serial number string, or the numeric serial number when the display has no serial
+    /// string. `None` when the display reports neither.
+    pub serial: Option<String>,
+    /// The model name, e.g. `DELL U2720Q`.
+    pub name: Option<String>,
+    /// When the display was made, `None` when the EDID only gives a model year.
+    pub manufacture_date: Option<ManufactureDate>,
+}
+
+/// 解析后的 EDID，保留数字序列号用于生成 UUID
+#[derive(Debug, Clone)]
+pub(crate) struct Edid {
+    pub identity: MonitorIdentity,
+    #[cfg(any(target_os = "linux", test))]
+    pub serial_number: u32,
+}
+
+/// 把 EDID 中大端序的制造商 ID 转换为三个字母，每个字母 5 位，1 表示 A
+pub(crate) fn pnp_vendor_id(manufacturer_id: u16) -> String {
+    [10, 5, 0]
+        .iter()
+        .map(|shift| (((manufacturer_id >> shift) & 0x1F) as u8 + b'A' - 1) as char)
+        .collect()
+}
+
+/// 读取描述符中的文本，以换行结束，不足 13 字节时用空格填充
+fn descriptor_text(text: &[u8]) -> Option<String> {
+    let text: String = text
+        .iter()
+        .take_while(|&&b| b != b'\n' && b != 0)
+        .filter(|b| b.is_ascii_graphic() || **b == b' ')
+        .map(|&b| b as char)
+        .collect();
+    let text = text.trim();
+
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// 遍历基本块中的 4 个显示器描述符，返回描述符类型和文本部分
+/// 前 3 个字节为 0 的是显示器描述符，第 4 个字节是类型，数据不足 128 字节时只返回完整的描述符
+fn descriptors(edid: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    edid.get(DESCRIPTOR_OFFSET..DESCRIPTOR_OFFSET + 4 * 18)
+        .or_else(|| edid.get(DESCRIPTOR_OFFSET..))
+        .unwrap_or_default()
+        .chunks_exact(18)
+        .filter_map(|descriptor| match descriptor {
+            [0, 0, 0, tag, _, text @ ..] => Some((*tag, text)),
+            _ => None,
+        })
+}
+
+/// 读取 EDID 中的型号名称，不校验头部，用于只需要名称的场景
+#[cfg(any(target_os = "linux", feature = "fuzzing", test))]
+pub(crate) fn edid_name(edid: &[u8]) -> Option<String> {
+    descriptors(edid)
+        .filter(|(tag, _)| *tag == DESCRIPTOR_NAME)
+        .find_map(|(_, text)| descriptor_text(text))
+}
+
+/// 解析 WMI 中以 uint8 数组保存的字符串，例如 WmiMonitorID 的 SerialNumberID，忽略填充的 0
+#[cfg(any(target_os = "windows", feature = "fuzzing", test))]
+pub(crate) fn wmi_string(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .filter(|&&b| b != 0)
+        .map(|&b| b as char)
+        .collect()
+}
+
+/// 解析 EDID 基本块，显示器上报的 EDID 可能被截断或损坏，任何输入都不能 panic
+/// EDID 格式参考: https://en.wikipedia.org/wiki/Extended_Display_Identification_Data
+pub(crate) fn parse_edid(edid: &[u8]) -> XCapResult<Edid> {
+    let Some(base) = edid
+        .get(..128)
+        .and_then(|base| <&[u8; 128]>::try_from(base).ok())
+    else {
+        return Err(XCapError::new("EDID data too short"));
+    };
+
+    if base[0..8] != EDID_HEADER {
+        return Err(XCapError::new("Invalid EDID header"));
+    }
+
+    let vendor = pnp_vendor_id(u16::from_be_bytes([base[8], base[9]]));
+    let product = u16::from_le_bytes([base[10], base[11]]);
+    let serial_number = u32::from_le_bytes([base[12], base[13], base[14], base[15]]);
+
+    // 第 16 字节为 0 表示不提供周数，0xFF 表示第 17 字节是型号年份而不是生产日期
+    let manufacture_date = match base[16] {
+        0xFF => None,
+        week => Some(ManufactureDate {
+            year: 1990 + base[17] as u16,
+            week: (week != 0).then_some(week),
+        }),
+    };
+
+    let mut serial = None;
+    let mut name = None;
+    // 同一类型有多个描述符时使用第一个有效的
+    for (tag, text) in descriptors(base) {
+        match tag {
+            DESCRIPTOR_SERIAL => serial = serial.or_else(|| descriptor_text(text)),
+            DESCRIPTOR_NAME => name = name.or_else(|| descriptor_text(text)),
+            _ => {}
+        }
+    }
+
+    let serial = serial.or_else(|| (serial_number != 0).then(|| serial_number.to_string()));
+
+    Ok(Edid {
+        identity: MonitorIdentity {
+            vendor,
+            product,
+            serial,
+            name,
+            manufacture_date,
+        },
+        #[cfg(any(target_os = "linux", test))]
+        serial_number,
+    })
+}
+
+impl MonitorIdentity {
+    /// Parses the identity from a raw EDID, e.g. one read from `/sys/class/drm/*/edid`. Only the
+    /// 128-byte base block is used; truncated or corrupt data returns an error instead of panicking.
+    pub fn from_edid(edid: &[u8]) -> XCapResult<MonitorIdentity> {
+        Ok(parse_edid(edid)?.identity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edid() -> Vec<u8> {
+        let mut edid = vec![0; 128];
+        edid[0..8].copy_from_slice(&EDID_HEADER);
+        // DEL
+        edid[8..10].copy_from_slice(&[0x10, 0xAC]);
+        edid[10..12].copy_from_slice(&0xA0F4u16.to_le_bytes());
+        edid[12..16].copy_from_slice(&0x4C38_4A42u32.to_le_bytes());
+        edid[16] = 12;
+        edid[17] = 30;
+
+        edid[54..72].copy_from_slice(b"\0\0\0\xFC\0DELL U2720Q\n ");
+        edid[72..90].copy_from_slice(b"\0\0\0\xFF\0ABC123\n      ");
+        edid
+    }
+
+    #[test]
+    fn test_parse_edid() {
+        let edid = parse_edid(&edid()).unwrap();
+
+        assert_eq!(edid.serial_number, 0x4C38_4A42);
+        assert_eq!(
+            edid.identity,
+            MonitorIdentity {
+                vendor: "DEL".to_string(),
+                product: 0xA0F4,
+                serial: Some("ABC123".to_string()),
+                name: Some("DELL U2720Q".to_string()),
+                manufacture_date: Some(ManufactureDate {
+                    year: 2020,
+                    week: Some(12),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_edid_fallbacks() {
+        let mut data = edid();
+        // 没有序列号描述符时使用数字序列号，只有型号年份时没有生产日期
+        data[72..90].fill(0);
+        data[16] = 0xFF;
+
+        let identity = parse_edid(&data).unwrap().identity;
+        assert_eq!(identity.serial, Some(0x4C38_4A42u32.to_string()));
+        assert_eq!(identity.manufacture_date, None);
+
+        data[0] = 0xFF;
+        assert!(parse_edid(&data).is_err());
+    }
+
+    #[test]
+    fn test_parse_edid_malformed() {
+        let data = edid();
+        // 任意长度的截断数据都只返回错误
+        for len in 0..data.len() {
+            assert!(parse_edid(&data[..len]).is_err());
+            let _ = edid_name(&data[..len]);
+        }
+        assert_eq!(edid_name(&data[..72]), Some("DELL U2720Q".to_string()));
+        assert_eq!(edid_name(&data[..71]), None);
+
+        // 描述符中没有换行和有效字符的垃圾数据
+        let mut garbage = data.clone();
+        garbage[54..126].fill(0);
+        garbage[57] = DESCRIPTOR_NAME;
+        garbage[59..72].fill(0xFF);
+        garbage.extend_from_slice(&[0xAB; 300]);
+        let identity = MonitorIdentity::from_edid(&garbage).unwrap();
+        assert_eq!(identity.name, None);
+
+        assert_eq!(wmi_string(b"ABC123\0\0\0"), "ABC123");
+        assert_eq!(wmi_string(&[]), "");
+    }
+}
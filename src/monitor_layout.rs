@@ -0,0 +1,208 @@
+use crate::{Monitor, error::XCapResult, geometry::Rect};
+
+/// One monitor of a [`MonitorLayout`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayoutMonitor {
+    /// The key to find the monitor again with, see [`Monitor::unique_key`].
+    pub unique_key: String,
+    /// The monitor's bounds in global coordinates.
+    pub rect: Rect,
+    /// The monitor's scale factor.
+    pub scale_factor: f32,
+    /// Whether the monitor is the primary monitor.
+    pub is_primary: bool,
+}
+
+/// A change between two [`MonitorLayout`]s, see [`MonitorLayout::diff`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LayoutChange {
+    /// A monitor was connected.
+    Added(LayoutMonitor),
+    /// A monitor was disconnected.
+    Removed(LayoutMonitor),
+    /// A monitor was moved or its resolution changed.
+    BoundsChanged {
+        unique_key: String,
+        old: Rect,
+        new: Rect,
+    },
+    /// A monitor's scale factor changed.
+    ScaleFactorChanged {
+        unique_key: String,
+        old: f32,
+        new: f32,
+    },
+    /// Another monitor became the primary monitor. `None` when no monitor was primary.
+    PrimaryChanged {
+        old: Option<String>,
+        new: Option<String>,
+    },
+}
+
+/// The arrangement of all monitors at one point in time. Save it, e.g. before a laptop is
+/// undocked, and [`diff`](MonitorLayout::diff) it against a later layout to find which capture
+/// targets have to be restored.
+///
+/// ```no_run
+/// use xcap::MonitorLayout;
+///
+/// let before = MonitorLayout::capture().unwrap();
+/// // ...the laptop is docked
+/// let after = MonitorLayout::capture().unwrap();
+///
+/// for change in after.diff(&before) {
+///     println!("{change:?}");
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorLayout {
+    /// The monitors in [`Monitor::all`] order.
+    pub monitors: Vec<LayoutMonitor>,
+}
+
+impl MonitorLayout {
+    /// Read the current layout of all monitors.
+    pub fn capture() -> XCapResult<MonitorLayout> {
+        let monitors = Monitor::all()?
+            .iter()
+            .map(|monitor| {
+                Ok(LayoutMonitor {
+                    unique_key: monitor.unique_key()?,
+                    rect: monitor.bounds()?,
+                    scale_factor: monitor.scale_factor()?,
+                    is_primary: monitor.is_primary()?,
+                })
+            })
+            .collect::<XCapResult<Vec<LayoutMonitor>>>()?;
+
+        Ok(MonitorLayout { monitors })
+    }
+
+    /// The monitor with the key, see [`Monitor::unique_key`].
+    pub fn get(&self, unique_key: &str) -> Option<&LayoutMonitor> {
+        self.monitors
+            .iter()
+            .find(|monitor| monitor.unique_key == unique_key)
+    }
+
+    /// The primary monitor.
+    pub fn primary(&self) -> Option<&LayoutMonitor> {
+        self.monitors.iter().find(|monitor| monitor.is_primary)
+    }
+
+    /// The changes from `old` to this layout. Monitors are matched by their unique key; removed
+    /// monitors come first, then added monitors, then changes to the monitors in both layouts,
+    /// then a change of the primary monitor.
+    pub fn diff(&self, old: &MonitorLayout) -> Vec<LayoutChange> {
+        let mut changes = Vec::new();
+
+        for monitor in &old.monitors {
+            if self.get(&monitor.unique_key).is_none() {
+                changes.push(LayoutChange::Removed(monitor.clone()));
+            }
+        }
+
+        for monitor in &self.monitors {
+            if old.get(&monitor.unique_key).is_none() {
+                changes.push(LayoutChange::Added(monitor.clone()));
+            }
+        }
+
+        for monitor in &self.monitors {
+            let Some(old_monitor) = old.get(&monitor.unique_key) else {
+                continue;
+            };
+
+            if old_monitor.rect != monitor.rect {
+                changes.push(LayoutChange::BoundsChanged {
+                    unique_key: monitor.unique_key.clone(),
+                    old: old_monitor.rect,
+                    new: monitor.rect,
+                });
+            }
+
+            if old_monitor.scale_factor != monitor.scale_factor {
+                changes.push(LayoutChange::ScaleFactorChanged {
+                    unique_key: monitor.unique_key.clone(),
+                    old: old_monitor.scale_factor,
+                    new: monitor.scale_factor,
+                });
+            }
+        }
+
+        let old_primary = old.primary().map(|monitor| monitor.unique_key.clone());
+        let new_primary = self.primary().map(|monitor| monitor.unique_key.clone());
+        if old_primary != new_primary {
+            changes.push(LayoutChange::PrimaryChanged {
+                old: old_primary,
+                new: new_primary,
+            });
+        }
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(unique_key: &str, x: i32, scale_factor: f32, is_primary: bool) -> LayoutMonitor {
+        LayoutMonitor {
+            unique_key: unique_key.to_string(),
+            rect: Rect::new(x, 0, 1920, 1080),
+            scale_factor,
+            is_primary,
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        // 笔记本接入扩展坞：外接显示器成为主显示器，内置显示器移到右侧
+        let undocked = MonitorLayout {
+            monitors: vec![monitor("BUILTIN", 0, 2.0, true)],
+        };
+        let docked = MonitorLayout {
+            monitors: vec![
+                monitor("BUILTIN", 1920, 1.0, false),
+                monitor("EXTERNAL", 0, 1.0, true),
+            ],
+        };
+
+        assert_eq!(
+            docked.diff(&undocked),
+            vec![
+                LayoutChange::Added(monitor("EXTERNAL", 0, 1.0, true)),
+                LayoutChange::BoundsChanged {
+                    unique_key: "BUILTIN".to_string(),
+                    old: Rect::new(0, 0, 1920, 1080),
+                    new: Rect::new(1920, 0, 1920, 1080),
+                },
+                LayoutChange::ScaleFactorChanged {
+                    unique_key: "BUILTIN".to_string(),
+                    old: 2.0,
+                    new: 1.0,
+                },
+                LayoutChange::PrimaryChanged {
+                    old: Some("BUILTIN".to_string()),
+                    new: Some("EXTERNAL".to_string()),
+                },
+            ]
+        );
+
+        assert!(docked.diff(&docked).is_empty());
+        assert_eq!(
+            MonitorLayout::default().diff(&undocked),
+            vec![
+                LayoutChange::Removed(monitor("BUILTIN", 0, 2.0, true)),
+                LayoutChange::PrimaryChanged {
+                    old: Some("BUILTIN".to_string()),
+                    new: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{Monitor, error::XCapResult, geometry::Rect};
+
+// 轮询显示器配置的间隔
+const MONITOR_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 显示器中会影响截图的配置
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct MonitorState {
+    bounds: Rect,
+    scale_factor: f32,
+    rotation: f32,
+    frequency: f32,
+}
+
+impl MonitorState {
+    fn new(monitor: &Monitor) -> XCapResult<MonitorState> {
+        Ok(MonitorState {
+            bounds: monitor.bounds()?,
+            scale_factor: monitor.scale_factor()?,
+            rotation: monitor.rotation()?,
+            frequency: monitor.frequency()?,
+        })
+    }
+}
+
+/// Watches a monitor for mode, scale and position changes, see [`Monitor::on_change`].
+///
+/// Dropping the watcher stops its thread.
+#[derive(Debug)]
+pub struct MonitorWatcher {
+    stopped: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl MonitorWatcher {
+    pub(crate) fn new<F>(monitor: &Monitor, mut callback: F) -> XCapResult<MonitorWatcher>
+    where
+        F: FnMut(&Monitor) + Send + 'static,
+    {
+        // Windows 上 HMONITOR 不能跨线程传递，并且分辨率变化后可能失效，
+        // 所以在线程中每次按 unique_key 重新查找显示器
+        let unique_key = monitor.unique_key()?;
+        let mut previous = MonitorState::new(monitor)?;
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let stopped = stopped.clone();
+            thread::spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    thread::sleep(MONITOR_POLL_INTERVAL);
+
+                    // 显示器断开时找不到，重新连接后继续比较
+                    let monitor = match Monitor::from_unique_key(unique_key.clone()) {
+                        Ok(monitor) => monitor,
+                        Err(err) => {
+                            log::debug!("from_unique_key failed: {err:?}");
+                            continue;
+                        }
+                    };
+
+                    let current = match MonitorState::new(&monitor) {
+                        Ok(current) => current,
+                        Err(err) => {
+                            log::debug!("get monitor state failed: {err:?}");
+                            continue;
+                        }
+                    };
+
+                    if current != previous {
+                        previous = current;
+                        callback(&monitor);
+                    }
+                }
+            })
+        };
+
+        Ok(MonitorWatcher {
+            stopped,
+            worker: Some(worker),
+        })
+    }
+
+    /// Stop watching and wait for the watcher thread to exit.
+    pub fn stop(mut self) {
+        self.stop_worker();
+    }
+
+    fn stop_worker(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for MonitorWatcher {
+    fn drop(&mut self) {
+        self.stop_worker();
+    }
+}
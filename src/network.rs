@@ -0,0 +1,393 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+#[cfg(feature = "compression")]
+use crate::compression::{self, Codec};
+use crate::{
+    XCapError, XCapResult, clock,
+    diff::diff,
+    geometry::Rect,
+    video_recorder::{Frame, FrameView},
+};
+
+// 消息头部：魔数、版本、类型、编码、宽、高、时间戳、矩形数量、原始长度、负载长度
+const MAGIC: &[u8; 4] = b"XCNF";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 1 + 1 + 1 + 4 + 4 + 8 + 4 + 4 + 4;
+const KIND_FULL: u8 = 0;
+const KIND_DELTA: u8 = 1;
+const CODEC_NONE: u8 = 0;
+// 拒绝超过 8K RGBA 的消息，防止异常的数据导致分配过多的内存
+const MAX_PAYLOAD_LEN: usize = 7680 * 4320 * 4 + 4096 * 16;
+
+/// Ships recorder frames to a remote viewer, see [`TcpFrameSender`] for the reference
+/// implementation.
+///
+/// Implement it to send frames over another transport, e.g. NDI, WebRTC or a WebSocket, so
+/// screen sharing code can be written against the trait and pick the transport at runtime.
+pub trait NetworkFrameSender: Send {
+    /// Send `frame` to the viewer, blocking until it has been handed to the transport.
+    fn send_frame(&mut self, frame: &Frame) -> XCapResult<()>;
+}
+
+/// 把帧编码为消息，开启增量编码时只发送和上一帧相比变化的矩形
+#[derive(Debug, Default)]
+pub(crate) struct FrameEncoder {
+    pub(crate) delta: bool,
+    #[cfg(feature = "compression")]
+    pub(crate) codec: Option<Codec>,
+    previous: Option<Frame>,
+}
+
+impl FrameEncoder {
+    /// 下一帧发送完整的帧
+    pub(crate) fn request_keyframe(&mut self) {
+        self.previous = None;
+    }
+
+    pub(crate) fn encode(&mut self, frame: &Frame) -> XCapResult<Vec<u8>> {
+        let (width, height) = (frame.width(), frame.height());
+        // 尺寸变化后接收端无法应用增量，发送完整的帧
+        let rects = match (&self.previous, self.delta) {
+            (Some(previous), true) if previous.width() == width && previous.height() == height => {
+                Some(diff(previous, frame))
+            }
+            _ => None,
+        };
+
+        let (kind, rects) = match rects {
+            Some(rects) => (KIND_DELTA, rects),
+            None => (KIND_FULL, vec![Rect::new(0, 0, width, height)]),
+        };
+        let mut raw = Vec::new();
+        for rect in &rects {
+            if kind == KIND_DELTA {
+                for value in [rect.x as u32, rect.y as u32, rect.width, rect.height] {
+                    raw.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            let start = rect.x as usize * 4;
+            let end = start + rect.width as usize * 4;
+            for y in rect.y as usize..rect.y as usize + rect.height as usize {
+                raw.extend_from_slice(&frame.data()[y * frame.stride() + start..][..end - start]);
+            }
+        }
+
+        let raw_len = raw.len();
+        #[cfg(feature = "compression")]
+        let (codec, payload) = match self.codec {
+            Some(codec) => (
+                codec.to_byte() + 1,
+                compression::compress_bytes(codec, &raw)?,
+            ),
+            None => (CODEC_NONE, raw),
+        };
+        #[cfg(not(feature = "compression"))]
+        let (codec, payload) = (CODEC_NONE, raw);
+
+        let mut message = Vec::with_capacity(HEADER_LEN + payload.len());
+        message.extend_from_slice(MAGIC);
+        message.extend_from_slice(&[VERSION, kind, codec]);
+        message.extend_from_slice(&width.to_le_bytes());
+        message.extend_from_slice(&height.to_le_bytes());
+        message.extend_from_slice(&clock::to_nanos(frame.timestamp()).to_le_bytes());
+        message.extend_from_slice(&(rects.len() as u32).to_le_bytes());
+        message.extend_from_slice(&(raw_len as u32).to_le_bytes());
+        message.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        message.extend_from_slice(&payload);
+
+        if self.delta {
+            self.previous = Some(frame.clone());
+        }
+
+        Ok(message)
+    }
+}
+
+/// 消息是否是完整的帧，解码时不依赖之前的消息
+pub(crate) fn is_keyframe(message: &[u8]) -> bool {
+    message.get(5) == Some(&KIND_FULL)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap_or_default())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap_or_default())
+}
+
+fn invalid_message() -> XCapError {
+    XCapError::new("Invalid network frame message")
+}
+
+fn read_error(err: io::Error) -> XCapError {
+    XCapError::new(format!("Receive frame failed: {err}"))
+}
+
+fn connect_error(err: io::Error) -> XCapError {
+    XCapError::new(format!("Connect failed: {err}"))
+}
+
+/// 读取一条消息并应用到当前帧上，增量消息需要先收到完整的帧
+#[derive(Debug, Default)]
+pub(crate) struct FrameDecoder {
+    current: Option<Frame>,
+}
+
+impl FrameDecoder {
+    pub(crate) fn decode<R: Read>(&mut self, reader: &mut R) -> XCapResult<Frame> {
+        let mut header = [0; HEADER_LEN];
+        reader.read_exact(&mut header).map_err(read_error)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid_message());
+        }
+        if header[4] != VERSION {
+            return Err(XCapError::new(format!(
+                "Unsupported network frame version {}",
+                header[4]
+            )));
+        }
+
+        let (kind, codec) = (header[5], header[6]);
+        let width = read_u32(&header, 7);
+        let height = read_u32(&header, 11);
+        let timestamp = clock::from_nanos(read_u64(&header, 15));
+        let rect_count = read_u32(&header, 23) as usize;
+        let raw_len = read_u32(&header, 27) as usize;
+        let payload_len = read_u32(&header, 31) as usize;
+        if raw_len > MAX_PAYLOAD_LEN || payload_len > MAX_PAYLOAD_LEN {
+            return Err(invalid_message());
+        }
+
+        let mut payload = vec![0; payload_len];
+        reader.read_exact(&mut payload).map_err(read_error)?;
+        let raw = match codec {
+            CODEC_NONE => payload,
+            #[cfg(feature = "compression")]
+            codec => {
+                compression::decompress_bytes(Codec::from_byte(codec - 1)?, &payload, raw_len)?
+            }
+            #[cfg(not(feature = "compression"))]
+            _ => {
+                return Err(XCapError::new(
+                    "Compressed frames need the compression feature",
+                ));
+            }
+        };
+        if raw.len() != raw_len {
+            return Err(invalid_message());
+        }
+
+        let frame = match kind {
+            KIND_FULL if raw.len() == width as usize * height as usize * 4 => {
+                Frame::with_stride(width, height, width as usize * 4, raw, timestamp)
+            }
+            KIND_DELTA => {
+                let mut frame = self
+                    .current
+                    .take()
+                    .filter(|frame| frame.width() == width && frame.height() == height)
+                    .ok_or(XCapError::new("Delta frame without a previous frame"))?;
+                apply_rects(&mut frame.view_mut(), &raw, rect_count)?;
+
+                Frame::with_stride(width, height, frame.stride(), frame.into_data(), timestamp)
+            }
+            _ => return Err(invalid_message()),
+        };
+        self.current = Some(frame.clone());
+
+        Ok(frame)
+    }
+}
+
+/// 把增量消息中的矩形复制到帧中，矩形超出帧或数据不完整时返回错误
+fn apply_rects(view: &mut FrameView, mut raw: &[u8], rect_count: usize) -> XCapResult<()> {
+    let (width, height, stride) = (view.width(), view.height(), view.stride());
+    for _ in 0..rect_count {
+        let values = raw.get(..16).ok_or_else(invalid_message)?;
+        let rect = Rect::new(
+            read_u32(values, 0) as i32,
+            read_u32(values, 4) as i32,
+            read_u32(values, 8),
+            read_u32(values, 12),
+        );
+        raw = &raw[16..];
+
+        let (x, y) = (rect.x as u32 as u64, rect.y as u32 as u64);
+        if x + rect.width as u64 > width as u64 || y + rect.height as u64 > height as u64 {
+            return Err(invalid_message());
+        }
+
+        let row_len = rect.width as usize * 4;
+        let len = row_len * rect.height as usize;
+        let pixels = raw.get(..len).ok_or_else(invalid_message)?;
+        for (row, src) in pixels.chunks_exact(row_len.max(1)).enumerate() {
+            let start = (y as usize + row) * stride + x as usize * 4;
+            view.data_mut()[start..start + row_len].copy_from_slice(src);
+        }
+        raw = &raw[len..];
+    }
+
+    Ok(())
+}
+
+/// A [`NetworkFrameSender`] writing frames to a TCP connection, read back with
+/// [`TcpFrameReceiver`]. Both ends are plain xcap, so this is all a simple LAN screen share
+/// needs.
+///
+/// Frames are sent whole by default. With [`TcpFrameSender::delta`] only the rectangles that
+/// changed since the previous frame are sent, see [`diff`](crate::diff()), which cuts the
+/// bandwidth of a mostly static desktop to a fraction. With the `compression` feature the
+/// messages can also be compressed, see [`TcpFrameSender::codec`].
+///
+/// ```no_run
+/// use std::net::TcpListener;
+///
+/// use xcap::{Monitor, NetworkFrameSender, TcpFrameSender};
+///
+/// let listener = TcpListener::bind("0.0.0.0:7878").unwrap();
+/// let (stream, _) = listener.accept().unwrap();
+/// let mut sender = TcpFrameSender::from_stream(stream).unwrap().delta(true);
+///
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let (recorder, rx) = monitor.video_recorder().unwrap();
+/// recorder.start().unwrap();
+/// for frame in rx {
+///     sender.send_frame(&frame).unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TcpFrameSender {
+    stream: TcpStream,
+    encoder: FrameEncoder,
+}
+
+impl TcpFrameSender {
+    /// Connect to a viewer listening on `addr`.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> XCapResult<TcpFrameSender> {
+        TcpFrameSender::from_stream(TcpStream::connect(addr).map_err(connect_error)?)
+    }
+
+    /// Send frames over an established connection, e.g. one accepted from a viewer.
+    pub fn from_stream(stream: TcpStream) -> XCapResult<TcpFrameSender> {
+        // 帧已经是完整的消息，不需要等待合并
+        stream
+            .set_nodelay(true)
+            .map_err(|err| XCapError::new(format!("Set TCP_NODELAY failed: {err}")))?;
+
+        Ok(TcpFrameSender {
+            stream,
+            encoder: FrameEncoder::default(),
+        })
+    }
+
+    /// Send only the rectangles that changed since the previous frame. Defaults to `false`.
+    pub fn delta(mut self, delta: bool) -> TcpFrameSender {
+        self.encoder.delta = delta;
+        self.encoder.request_keyframe();
+        self
+    }
+
+    /// Send the next frame whole even with [`TcpFrameSender::delta`], e.g. when a viewer asks
+    /// for a fresh picture.
+    pub fn request_keyframe(&mut self) {
+        self.encoder.request_keyframe();
+    }
+
+    /// Compress messages with `codec`, `None` sends them uncompressed. Defaults to `None`.
+    /// Requires the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn codec(mut self, codec: Option<Codec>) -> TcpFrameSender {
+        self.encoder.codec = codec;
+        self
+    }
+}
+
+impl NetworkFrameSender for TcpFrameSender {
+    fn send_frame(&mut self, frame: &Frame) -> XCapResult<()> {
+        let message = self.encoder.encode(frame)?;
+        if let Err(err) = self.stream.write_all(&message) {
+            // 消息可能只写了一部分，下一帧发送完整的帧，接收端仍然会因为数据错乱而失败
+            self.encoder.request_keyframe();
+            return Err(XCapError::new(format!("Send frame failed: {err}")));
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the frames sent by a [`TcpFrameSender`], restoring whole frames from delta messages.
+///
+/// ```no_run
+/// use xcap::TcpFrameReceiver;
+///
+/// let mut receiver = TcpFrameReceiver::connect("192.168.1.20:7878").unwrap();
+/// loop {
+///     let frame = receiver.recv().unwrap();
+///     println!("{}x{}", frame.width(), frame.height());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TcpFrameReceiver {
+    stream: TcpStream,
+    decoder: FrameDecoder,
+}
+
+impl TcpFrameReceiver {
+    /// Connect to a sender listening on `addr`.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> XCapResult<TcpFrameReceiver> {
+        Ok(TcpFrameReceiver::from_stream(
+            TcpStream::connect(addr).map_err(connect_error)?,
+        ))
+    }
+
+    /// Read frames from an established connection, e.g. one accepted from a sender.
+    pub fn from_stream(stream: TcpStream) -> TcpFrameReceiver {
+        TcpFrameReceiver {
+            stream,
+            decoder: FrameDecoder::default(),
+        }
+    }
+
+    /// Block until the next frame arrives. The timestamp is only meaningful when both ends
+    /// share the same [`clock::epoch`].
+    pub fn recv(&mut self) -> XCapResult<Frame> {
+        self.decoder.decode(&mut self.stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_round_trip() {
+        let mut encoder = FrameEncoder {
+            delta: true,
+            ..Default::default()
+        };
+        let mut decoder = FrameDecoder::default();
+
+        let first = Frame::new(40, 40, vec![1; 40 * 40 * 4]);
+        let mut raw = vec![1; 40 * 40 * 4];
+        raw[(35 * 40 + 35) * 4] = 9;
+        let second = Frame::new(40, 40, raw);
+
+        let full = encoder.encode(&first).unwrap();
+        let delta = encoder.encode(&second).unwrap();
+        // 只有右下角的块发生了变化
+        assert!(delta.len() < full.len());
+
+        let mut messages = [full, delta].concat();
+        let mut reader = &messages[..];
+        assert_eq!(decoder.decode(&mut reader).unwrap().data(), first.data());
+        assert_eq!(decoder.decode(&mut reader).unwrap().data(), second.data());
+
+        // 没有完整的帧时不能应用增量
+        messages.drain(..HEADER_LEN + 40 * 40 * 4);
+        assert!(FrameDecoder::default().decode(&mut &messages[..]).is_err());
+    }
+}
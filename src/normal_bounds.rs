@@ -0,0 +1,43 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use crate::geometry::Rect;
+
+// X11 和 macOS 没有提供窗口还原后的位置，记录每个窗口最近一次处于正常状态时的位置
+static NORMAL_BOUNDS: LazyLock<Mutex<HashMap<u32, Rect>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 窗口处于正常状态时记录并返回当前位置，最大化或全屏时返回最近一次记录的位置，
+/// 没有记录过时返回 None
+pub(crate) fn track_normal_bounds(window_id: u32, bounds: Rect, is_normal: bool) -> Option<Rect> {
+    let mut normal_bounds = NORMAL_BOUNDS.lock().ok()?;
+
+    if is_normal {
+        normal_bounds.insert(window_id, bounds);
+        return Some(bounds);
+    }
+
+    normal_bounds.get(&window_id).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_normal_bounds() {
+        // 使用不会和真实窗口冲突的 id
+        let window_id = u32::MAX;
+        let normal = Rect::new(10, 20, 300, 200);
+        let maximized = Rect::new(0, 0, 1920, 1080);
+
+        assert_eq!(track_normal_bounds(window_id, maximized, false), None);
+        assert_eq!(track_normal_bounds(window_id, normal, true), Some(normal));
+        assert_eq!(
+            track_normal_bounds(window_id, maximized, false),
+            Some(normal)
+        );
+    }
+}
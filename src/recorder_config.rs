@@ -0,0 +1,403 @@
+use std::time::Duration;
+
+#[cfg(any(target_os = "macos", target_os = "linux", test))]
+use crate::Config;
+use crate::{
+    Redactor,
+    geometry::Rect,
+    video_recorder::{FrameHook, FrameView},
+};
+
+/// How the recorder paces the frames it delivers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FramePacing {
+    /// Deliver frames as soon as the backend produces them.
+    #[default]
+    FreeRunning,
+    /// Deliver at most one frame per display refresh, aligned with the display's vsync
+    /// (CVDisplayLink on macOS, DXGI vblank waits on Windows and PipeWire framerate negotiation
+    /// on Wayland). X11 offers no vblank signal to screen capture, so there frames are paced by
+    /// a clock at the refresh rate and are not aligned with vblank. Displays with a variable
+    /// refresh rate are paced at the top of their
+    /// [`refresh_rate_range`](crate::Monitor::refresh_rate_range).
+    Vsync,
+}
+
+/// What a recorder does when its stream fails, e.g. after a resolution change or when the
+/// monitor disconnects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecoveryPolicy {
+    /// Re-create the platform stream and emit
+    /// [`RecorderEvent::StreamRestarted`](crate::RecorderEvent::StreamRestarted).
+    #[default]
+    Restart,
+    /// Stop delivering frames after emitting [`RecorderEvent::Error`](crate::RecorderEvent::Error).
+    Stop,
+}
+
+// 低功耗模式下的帧率
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+pub(crate) const LOW_POWER_FRAME_RATE: f32 = 5.0;
+
+/// Trade-off between frame rate and power usage, aimed at always-on recorders such as activity
+/// trackers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PowerProfile {
+    /// Capture at the rate selected by [`FramePacing`].
+    #[default]
+    Performance,
+    /// Capture at most 5 frames per second and skip frames identical to the previous one. The
+    /// backend's own frame rate is lowered where it supports it (AVCaptureScreenInput frame
+    /// duration on macOS, PipeWire framerate negotiation on Wayland), so no work is done for
+    /// frames that would be thrown away.
+    LowPower,
+    /// [`PowerProfile::LowPower`] when running on battery when the recorder is created,
+    /// [`PowerProfile::Performance`] otherwise.
+    Auto,
+}
+
+impl PowerProfile {
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+    pub(crate) fn is_low_power<F>(self, is_on_battery: F) -> bool
+    where
+        F: FnOnce() -> bool,
+    {
+        match self {
+            PowerProfile::Performance => false,
+            PowerProfile::LowPower => true,
+            PowerProfile::Auto => is_on_battery(),
+        }
+    }
+}
+
+/// Options applied to a video recorder.
+///
+/// ```no_run
+/// use xcap::{FramePacing, Monitor, RecorderConfig};
+///
+/// let config = RecorderConfig::new().pacing(FramePacing::Vsync);
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let (video_recorder, sx) = monitor.video_recorder_with_config(&config).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    pub(crate) pacing: FramePacing,
+    pub(crate) stats_interval: Duration,
+    pub(crate) recovery: RecoveryPolicy,
+    pub(crate) pause_when_idle: bool,
+    pub(crate) power_profile: PowerProfile,
+    pub(crate) max_fps: Option<f32>,
+    pub(crate) timelapse: Option<Duration>,
+    pub(crate) scale: f32,
+    pub(crate) show_cursor: Option<bool>,
+    pub(crate) follow_window: Option<u32>,
+    // 只录制显示器中的这个区域，显示器内的坐标，由 CaptureTarget::MonitorRegion 设置
+    pub(crate) region: Option<Rect>,
+    pub(crate) encoder_surface: bool,
+    pub(crate) redactor: Option<Redactor>,
+    pub(crate) frame_hook: Option<FrameHook>,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        RecorderConfig {
+            pacing: FramePacing::default(),
+            stats_interval: Duration::from_secs(1),
+            recovery: RecoveryPolicy::default(),
+            pause_when_idle: false,
+            power_profile: PowerProfile::default(),
+            max_fps: None,
+            timelapse: None,
+            scale: 1.0,
+            show_cursor: None,
+            follow_window: None,
+            region: None,
+            encoder_surface: false,
+            redactor: None,
+            frame_hook: None,
+        }
+    }
+}
+
+impl RecorderConfig {
+    pub fn new() -> RecorderConfig {
+        RecorderConfig::default()
+    }
+
+    /// How often [`RecorderEvent::Stats`](crate::RecorderEvent::Stats) is emitted, defaults to one second.
+    pub fn stats_interval(mut self, stats_interval: Duration) -> RecorderConfig {
+        self.stats_interval = stats_interval;
+        self
+    }
+
+    /// How frames are paced, defaults to [`FramePacing::FreeRunning`].
+    pub fn pacing(mut self, pacing: FramePacing) -> RecorderConfig {
+        self.pacing = pacing;
+        self
+    }
+
+    /// What to do when the stream fails, defaults to [`RecoveryPolicy::Restart`].
+    pub fn recovery(mut self, recovery: RecoveryPolicy) -> RecorderConfig {
+        self.recovery = recovery;
+        self
+    }
+
+    /// Stop capturing while the display is asleep, the screensaver is running or the session is
+    /// locked, emitting [`RecorderEvent::Paused`](crate::RecorderEvent::Paused) and
+    /// [`RecorderEvent::Resumed`](crate::RecorderEvent::Resumed). Disabled by default.
+    pub fn pause_when_idle(mut self, pause_when_idle: bool) -> RecorderConfig {
+        self.pause_when_idle = pause_when_idle;
+        self
+    }
+
+    /// Power profile, defaults to [`PowerProfile::Performance`]. A low power profile takes
+    /// precedence over [`FramePacing::Vsync`].
+    pub fn power_profile(mut self, power_profile: PowerProfile) -> RecorderConfig {
+        self.power_profile = power_profile;
+        self
+    }
+
+    /// Deliver at most `max_fps` frames per second, unlimited by default. The limit is applied
+    /// inside the capture pipeline: the backend's own frame rate is lowered where it supports it
+    /// (AVCaptureScreenInput frame duration on macOS, PipeWire framerate negotiation on Wayland)
+    /// and the capture loop waits between frames elsewhere, so skipped frames are never copied
+    /// or converted. Takes precedence over [`FramePacing::Vsync`].
+    pub fn max_fps(mut self, max_fps: f32) -> RecorderConfig {
+        self.max_fps = (max_fps > 0.0).then_some(max_fps);
+        self
+    }
+
+    /// Timelapse mode: deliver one frame every `interval`, skipping the frames in between the
+    /// same way as [`RecorderConfig::max_fps`]. Frames keep their capture timestamps. When both
+    /// are set the longer interval wins.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use xcap::{Monitor, RecorderConfig};
+    ///
+    /// let config = RecorderConfig::new().timelapse(Duration::from_secs(10));
+    /// let monitor = Monitor::all().unwrap().remove(0);
+    /// let (video_recorder, sx) = monitor.video_recorder_with_config(&config).unwrap();
+    /// ```
+    pub fn timelapse(mut self, interval: Duration) -> RecorderConfig {
+        self.timelapse = Some(interval);
+        self
+    }
+
+    /// Scale delivered frames down by `scale`, from 0.0 (exclusive) to 1.0, defaults to 1.0.
+    /// AVCaptureScreenInput scales the frames itself on macOS; other backends resize each frame
+    /// after it is captured. Values outside the range are treated as 1.0.
+    pub fn scale(mut self, scale: f32) -> RecorderConfig {
+        self.scale = if scale > 0.0 && scale < 1.0 {
+            scale
+        } else {
+            1.0
+        };
+        self
+    }
+
+    /// Whether the recording includes the mouse cursor where the backend can choose
+    /// (AVFoundation on macOS, the screencast portal on Wayland), defaults to
+    /// [`ConfigBuilder::show_cursor`](crate::ConfigBuilder::show_cursor).
+    pub fn show_cursor(mut self, show_cursor: bool) -> RecorderConfig {
+        self.show_cursor = Some(show_cursor);
+        self
+    }
+
+    /// Crop each frame to the window with id `window_id` (see [`Window::id`](crate::Window::id)),
+    /// following it as it moves or resizes. The whole monitor is still captured, so this gives
+    /// a stable window recording on backends without window streams, such as X11. The window's
+    /// position is polled every 100ms; frames are skipped while it is minimized, closed or off
+    /// the recorded monitor. Creating the recorder fails if the window does not exist.
+    ///
+    /// ```no_run
+    /// use xcap::{Monitor, RecorderConfig, Window};
+    ///
+    /// let window = Window::all().unwrap().remove(0);
+    /// let monitor = window.current_monitor().unwrap();
+    /// let config = RecorderConfig::new().follow_window(window.id().unwrap());
+    /// let (video_recorder, sx) = monitor.video_recorder_with_config(&config).unwrap();
+    /// ```
+    pub fn follow_window(mut self, window_id: u32) -> RecorderConfig {
+        self.follow_window = Some(window_id);
+        self
+    }
+
+    /// Also copy each frame into a texture shared with external encoders on Windows, see
+    /// [`Frame::encoder_surface`](crate::Frame::encoder_surface). Costs one GPU copy per frame,
+    /// disabled by default. macOS frames always carry their IOSurface, so this only matters on
+    /// Windows.
+    pub fn encoder_surface(mut self, encoder_surface: bool) -> RecorderConfig {
+        self.encoder_surface = encoder_surface;
+        self
+    }
+
+    /// Hide the regions of `redactor` in every frame before it is delivered, see [`Redactor`].
+    /// Recorders with a redactor do not share their platform stream with other recorders, and
+    /// their frames carry no native buffers or encoder surfaces, which would still hold the
+    /// unredacted pixels.
+    pub fn redactor(mut self, redactor: Redactor) -> RecorderConfig {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// 把每一帧裁剪到显示器中的 region
+    pub(crate) fn with_region(mut self, region: Rect) -> RecorderConfig {
+        self.region = Some(region);
+        self
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "linux", test))]
+    pub(crate) fn shows_cursor(&self) -> bool {
+        self.show_cursor
+            .unwrap_or_else(|| Config::get().show_cursor())
+    }
+
+    /// 帧之间的最短间隔，取低功耗模式、max_fps 和 timelapse 中最长的一个
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+    pub(crate) fn frame_interval(&self, low_power: bool) -> Option<Duration> {
+        let low_power_interval =
+            low_power.then(|| Duration::from_secs_f64(1.0 / LOW_POWER_FRAME_RATE as f64));
+        let max_fps_interval = self
+            .max_fps
+            .map(|max_fps| Duration::from_secs_f64(1.0 / max_fps as f64));
+
+        [low_power_interval, max_fps_interval, self.timelapse]
+            .into_iter()
+            .flatten()
+            .max()
+    }
+
+    /// Run `hook` on every delivered frame right after its pixels are converted to RGBA, while
+    /// they are still in cache, e.g. to stamp a timestamp or watermark without another pass over
+    /// the frame. The hook runs on the recorder's capture thread and should be quick.
+    ///
+    /// ```no_run
+    /// use xcap::{Monitor, RecorderConfig, image::Rgba};
+    ///
+    /// let config = RecorderConfig::new().frame_hook(|view| {
+    ///     for x in 0..view.width().min(100) {
+    ///         view.put_pixel(x, 0, Rgba([255, 0, 0, 255]));
+    ///     }
+    /// });
+    /// let monitor = Monitor::all().unwrap().remove(0);
+    /// let (video_recorder, sx) = monitor.video_recorder_with_config(&config).unwrap();
+    /// ```
+    pub fn frame_hook<F>(mut self, hook: F) -> RecorderConfig
+    where
+        F: FnMut(&mut FrameView) + Send + 'static,
+    {
+        self.frame_hook = Some(FrameHook::new(hook));
+        self
+    }
+}
+
+/// Settings to change on a running recorder, see
+/// [`VideoRecorder::reconfigure`](crate::VideoRecorder::reconfigure). Settings that are not set
+/// keep their current value.
+///
+/// ```no_run
+/// use xcap::{Monitor, RecorderUpdate};
+///
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let (video_recorder, sx) = monitor.video_recorder().unwrap();
+/// video_recorder.start().unwrap();
+///
+/// video_recorder
+///     .reconfigure(&RecorderUpdate::new().max_fps(10.0).scale(0.5))
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RecorderUpdate {
+    max_fps: Option<f32>,
+    scale: Option<f32>,
+    show_cursor: Option<bool>,
+}
+
+impl RecorderUpdate {
+    pub fn new() -> RecorderUpdate {
+        RecorderUpdate::default()
+    }
+
+    /// See [`RecorderConfig::max_fps`]. 0.0 removes the limit.
+    pub fn max_fps(mut self, max_fps: f32) -> RecorderUpdate {
+        self.max_fps = Some(max_fps);
+        self
+    }
+
+    /// See [`RecorderConfig::scale`].
+    pub fn scale(mut self, scale: f32) -> RecorderUpdate {
+        self.scale = Some(scale);
+        self
+    }
+
+    /// See [`RecorderConfig::show_cursor`]. Only AVFoundation on macOS can switch the cursor on
+    /// a running stream; the screencast portal on Wayland keeps the cursor mode the recorder was
+    /// created with.
+    pub fn show_cursor(mut self, show_cursor: bool) -> RecorderUpdate {
+        self.show_cursor = Some(show_cursor);
+        self
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+    pub(crate) fn apply(&self, mut config: RecorderConfig) -> RecorderConfig {
+        if let Some(max_fps) = self.max_fps {
+            config = config.max_fps(max_fps);
+        }
+        if let Some(scale) = self.scale {
+            config = config.scale(scale);
+        }
+        if let Some(show_cursor) = self.show_cursor {
+            config = config.show_cursor(show_cursor);
+        }
+
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_interval() {
+        assert_eq!(RecorderConfig::new().frame_interval(false), None);
+        assert_eq!(
+            RecorderConfig::new().frame_interval(true),
+            Some(Duration::from_millis(200))
+        );
+
+        let config = RecorderConfig::new()
+            .max_fps(2.0)
+            .timelapse(Duration::from_millis(100));
+        assert_eq!(
+            config.frame_interval(false),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            config.frame_interval(true),
+            Some(Duration::from_millis(500))
+        );
+
+        assert_eq!(
+            RecorderConfig::new().max_fps(0.0).frame_interval(false),
+            None
+        );
+    }
+
+    #[test]
+    fn test_recorder_update_apply() {
+        let config = RecorderConfig::new().max_fps(2.0).scale(0.5);
+
+        // 没有设置的项保持不变
+        let config = RecorderUpdate::new().show_cursor(false).apply(config);
+        assert_eq!(config.max_fps, Some(2.0));
+        assert_eq!(config.scale, 0.5);
+        assert!(!config.shows_cursor());
+
+        let config = RecorderUpdate::new().max_fps(0.0).scale(2.0).apply(config);
+        assert_eq!(config.max_fps, None);
+        assert_eq!(config.scale, 1.0);
+    }
+}
@@ -0,0 +1,615 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+    thread,
+    time::{Duration, Instant},
+};
+
+use image::Rgba;
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+use crate::video_recorder::Frame;
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+use crate::{Monitor, RecorderConfig};
+use crate::{
+    Window, error::XCapResult, geometry::Rect, video_recorder::FrameView,
+    window_crop::window_bounds,
+};
+
+// 轮询窗口位置的间隔
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// 重新枚举窗口匹配模式的间隔，枚举窗口比查询位置慢得多
+const PATTERN_SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How a redacted region is hidden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedactionStyle {
+    /// Paint the region opaque black.
+    #[default]
+    Black,
+    /// Paint the region with a color.
+    Fill(Rgba<u8>),
+    /// Blur the region by replacing each block of the given size in pixels with its average
+    /// color. Blocks of 16 pixels or more make text unreadable.
+    Pixelate(u32),
+}
+
+/// Identifies a region added to a [`Redactor`], to remove it later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RedactionId(u64);
+
+#[derive(Debug, Clone)]
+enum Target {
+    Rect(Rect),
+    Window(u32),
+    // 小写的通配符模式，匹配应用名称或标题
+    Pattern(Vec<char>),
+}
+
+#[derive(Debug)]
+struct Region {
+    id: RedactionId,
+    target: Target,
+    style: RedactionStyle,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_id: u64,
+    regions: Vec<Region>,
+    // 窗口最近一次的位置，最小化或关闭时为 None
+    windows: HashMap<u32, Option<Rect>>,
+    // 每个模式当前匹配的窗口
+    matches: HashMap<RedactionId, Vec<u32>>,
+    polling: bool,
+}
+
+impl State {
+    fn has_patterns(&self) -> bool {
+        self.regions
+            .iter()
+            .any(|region| matches!(region.target, Target::Pattern(_)))
+    }
+
+    /// 需要轮询位置的窗口，包括模式匹配到的窗口
+    fn window_ids(&self) -> Vec<u32> {
+        let mut window_ids: Vec<u32> = self
+            .regions
+            .iter()
+            .filter_map(|region| match region.target {
+                Target::Window(window_id) => Some(window_id),
+                _ => None,
+            })
+            .chain(self.matches.values().flatten().copied())
+            .collect();
+        window_ids.sort_unstable();
+        window_ids.dedup();
+
+        window_ids
+    }
+
+    /// 移除区域之后清理不再需要的窗口
+    fn retain_windows(&mut self) {
+        let region_ids: Vec<RedactionId> = self.regions.iter().map(|region| region.id).collect();
+        self.matches.retain(|id, _| region_ids.contains(id));
+
+        let window_ids = self.window_ids();
+        self.windows
+            .retain(|window_id, _| window_ids.contains(window_id));
+    }
+
+    /// 按窗口的应用名称和标题更新每个模式匹配的窗口
+    fn update_matches(&mut self, windows: &[(u32, String, String)]) {
+        for region in &self.regions {
+            let Target::Pattern(pattern) = &region.target else {
+                continue;
+            };
+
+            let window_ids = windows
+                .iter()
+                .filter(|(_, app_name, title)| {
+                    glob_match(pattern, app_name) || glob_match(pattern, title)
+                })
+                .map(|(window_id, _, _)| *window_id)
+                .collect();
+            self.matches.insert(region.id, window_ids);
+        }
+    }
+
+    /// 区域当前在全局坐标中的位置
+    fn rects(&self) -> Vec<(Rect, RedactionStyle)> {
+        let mut rects = Vec::new();
+
+        for region in &self.regions {
+            let window_ids = match &region.target {
+                Target::Rect(rect) => {
+                    rects.push((*rect, region.style));
+                    continue;
+                }
+                Target::Window(window_id) => std::slice::from_ref(window_id),
+                Target::Pattern(_) => self
+                    .matches
+                    .get(&region.id)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default(),
+            };
+
+            rects.extend(
+                window_ids
+                    .iter()
+                    .filter_map(|window_id| *self.windows.get(window_id)?)
+                    .map(|rect| (rect, region.style)),
+            );
+        }
+
+        rects
+    }
+}
+
+/// 不区分大小写的通配符匹配，`*` 匹配任意个字符，`?` 匹配一个字符，pattern 已经是小写
+fn glob_match(pattern: &[char], text: &str) -> bool {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 * 的位置和它匹配到的文本位置，失配时让 * 多匹配一个字符
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// 窗口的 id、应用名称和标题，用于匹配模式
+fn window_names(windows: &[Window]) -> Vec<(u32, String, String)> {
+    windows
+        .iter()
+        .filter_map(|window| {
+            Some((
+                window.id().ok()?,
+                window.app_name().unwrap_or_default(),
+                window.title().unwrap_or_default(),
+            ))
+        })
+        .collect()
+}
+
+/// 轮询所有被遮挡的窗口的位置，并定期重新匹配模式，没有窗口和模式或 Redactor 被释放后退出
+fn poll_windows(state: Weak<Mutex<State>>) {
+    thread::spawn(move || {
+        // Windows 上 HWND 不能跨线程传递，在线程中按 id 重新查找窗口
+        let mut windows: HashMap<u32, Window> = HashMap::new();
+        let mut last_scan = Instant::now();
+
+        loop {
+            thread::sleep(WINDOW_POLL_INTERVAL);
+
+            let Some(state) = state.upgrade() else {
+                break;
+            };
+
+            // 没有跨平台的窗口创建和标题变化事件，定期枚举窗口重新匹配
+            let has_patterns = match state.lock() {
+                Ok(state) => state.has_patterns(),
+                Err(_) => break,
+            };
+            if has_patterns && last_scan.elapsed() >= PATTERN_SCAN_INTERVAL {
+                last_scan = Instant::now();
+                match Window::all() {
+                    Ok(all_windows) => {
+                        let names = window_names(&all_windows);
+                        if let Ok(mut state) = state.lock() {
+                            state.update_matches(&names);
+                        }
+                    }
+                    Err(err) => log::debug!("list windows for redaction failed: {err:?}"),
+                }
+            }
+
+            let window_ids = match state.lock() {
+                Ok(mut state) => {
+                    let window_ids = state.window_ids();
+                    if window_ids.is_empty() && !state.has_patterns() {
+                        state.polling = false;
+                        break;
+                    }
+                    window_ids
+                }
+                Err(_) => break,
+            };
+
+            windows.retain(|window_id, _| window_ids.contains(window_id));
+            let bounds: Vec<(u32, Option<Rect>)> = window_ids
+                .into_iter()
+                .map(|window_id| {
+                    if !windows.contains_key(&window_id)
+                        && let Ok(window) = Window::from_id(window_id)
+                    {
+                        windows.insert(window_id, window);
+                    }
+
+                    let bounds = match windows.get(&window_id).map(window_bounds) {
+                        Some(Ok(bounds)) => bounds,
+                        Some(Err(err)) => {
+                            log::debug!("get redacted window bounds failed: {err:?}");
+                            // 窗口可能已经关闭，下次重新查找
+                            windows.remove(&window_id);
+                            None
+                        }
+                        None => None,
+                    };
+
+                    (window_id, bounds)
+                })
+                .collect();
+
+            if let Ok(mut state) = state.lock() {
+                state.windows.extend(bounds);
+                state.retain_windows();
+            }
+        }
+    });
+}
+
+/// Hides rectangles and windows in every frame of the recorders it is attached to with
+/// [`RecorderConfig::redactor`], before the frame leaves the capture thread, so sensitive
+/// content never reaches the receiver, the
+/// [`frame_hook`](crate::RecorderConfig::frame_hook) or an encoder. Monitor screenshots are
+/// redacted with [`CaptureConfig::redactor`](crate::CaptureConfig::redactor).
+///
+/// Regions can be added and removed while recording and apply from the next frame. Clones
+/// share the same regions. Window positions are polled every 100 ms, so a window that moves
+/// quickly can be partly visible for a few frames.
+///
+/// ```no_run
+/// use xcap::{Monitor, Rect, RecorderConfig, RedactionStyle, Redactor, Window};
+///
+/// let redactor = Redactor::new();
+/// redactor
+///     .add_rect(Rect::new(0, 0, 400, 40), RedactionStyle::Black)
+///     .unwrap();
+///
+/// let password_manager = Window::all().unwrap().remove(0);
+/// redactor
+///     .add_window(password_manager.id().unwrap(), RedactionStyle::Pixelate(24))
+///     .unwrap();
+///
+/// redactor
+///     .add_pattern("*bank*", RedactionStyle::Black)
+///     .unwrap();
+///
+/// let config = RecorderConfig::new().redactor(redactor.clone());
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let (video_recorder, rx) = monitor.video_recorder_with_config(&config).unwrap();
+/// video_recorder.start().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Redactor(Arc<Mutex<State>>);
+
+impl Redactor {
+    pub fn new() -> Redactor {
+        Redactor::default()
+    }
+
+    fn add(&self, target: Target, style: RedactionStyle) -> XCapResult<RedactionId> {
+        let mut state = self.0.lock()?;
+        let id = RedactionId(state.next_id);
+        state.next_id += 1;
+        state.regions.push(Region { id, target, style });
+
+        Ok(id)
+    }
+
+    /// Hide `rect`, in global screen coordinates like [`Monitor::x`] and [`Window::x`].
+    pub fn add_rect(&self, rect: Rect, style: RedactionStyle) -> XCapResult<RedactionId> {
+        self.add(Target::Rect(rect), style)
+    }
+
+    /// Hide the window with the id, see [`Window::id`], following it as it moves. Nothing is
+    /// hidden while it is minimized. Fails if there is no such window.
+    pub fn add_window(&self, window_id: u32, style: RedactionStyle) -> XCapResult<RedactionId> {
+        // 先取得窗口当前的位置，添加之后的第一帧就已经遮挡
+        let bounds = window_bounds(&Window::from_id(window_id)?)?;
+        let id = self.add(Target::Window(window_id), style)?;
+
+        let mut state = self.0.lock()?;
+        state.windows.insert(window_id, bounds);
+        self.start_polling(&mut state);
+
+        Ok(id)
+    }
+
+    /// Hide every window whose app name or title matches `pattern`, following them as they
+    /// move. `*` matches any run of characters and `?` a single character, ignoring case, e.g.
+    /// `"1Password"` or `"*bank*"`. The whole app name or title must match.
+    ///
+    /// Windows are listed again every second, so windows opened or renamed later, e.g. a
+    /// browser tab switching to a bank, are hidden within a second.
+    pub fn add_pattern(&self, pattern: &str, style: RedactionStyle) -> XCapResult<RedactionId> {
+        let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+        // 先匹配当前的窗口，添加之后的第一帧就已经遮挡
+        let windows = Window::all()?;
+        let names = window_names(&windows);
+        let id = self.add(Target::Pattern(pattern), style)?;
+
+        let mut state = self.0.lock()?;
+        state.update_matches(&names);
+        let matched = state.matches.get(&id).cloned().unwrap_or_default();
+        for window in &windows {
+            if let Ok(window_id) = window.id()
+                && matched.contains(&window_id)
+            {
+                let bounds = window_bounds(window).ok().flatten();
+                state.windows.insert(window_id, bounds);
+            }
+        }
+        self.start_polling(&mut state);
+
+        Ok(id)
+    }
+
+    fn start_polling(&self, state: &mut State) {
+        if !state.polling {
+            state.polling = true;
+            poll_windows(Arc::downgrade(&self.0));
+        }
+    }
+
+    /// Stop hiding a region. Unknown ids are ignored.
+    pub fn remove(&self, id: RedactionId) -> XCapResult<()> {
+        let mut state = self.0.lock()?;
+        state.regions.retain(|region| region.id != id);
+        state.retain_windows();
+
+        Ok(())
+    }
+
+    /// Stop hiding all regions.
+    pub fn clear(&self) -> XCapResult<()> {
+        let mut state = self.0.lock()?;
+        state.regions.clear();
+        state.windows.clear();
+        state.matches.clear();
+
+        Ok(())
+    }
+}
+
+/// 把全局坐标的区域换算成帧中的像素区域，向外取整，边缘的像素也被遮挡
+fn cover_rect(monitor: Rect, rect: Rect, width: u32, height: u32) -> Option<Rect> {
+    let visible = monitor.intersection(rect)?;
+    let scale_x = width as f64 / monitor.width as f64;
+    let scale_y = height as f64 / monitor.height as f64;
+
+    let x = (visible.x - monitor.x) as f64;
+    let y = (visible.y - monitor.y) as f64;
+    let left = (x * scale_x).floor() as u32;
+    let top = (y * scale_y).floor() as u32;
+    let right = (((x + visible.width as f64) * scale_x).ceil() as u32).min(width);
+    let bottom = (((y + visible.height as f64) * scale_y).ceil() as u32).min(height);
+
+    if right <= left || bottom <= top {
+        return None;
+    }
+
+    Some(Rect::new(
+        left as i32,
+        top as i32,
+        right - left,
+        bottom - top,
+    ))
+}
+
+fn fill(view: &mut FrameView, rect: Rect, color: Rgba<u8>) {
+    let (left, right) = (
+        rect.x as usize * 4,
+        (rect.x as usize + rect.width as usize) * 4,
+    );
+
+    for y in rect.y as u32..rect.y as u32 + rect.height {
+        if let Some(row) = view.row_mut(y) {
+            for pixel in row[left..right].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&color.0);
+            }
+        }
+    }
+}
+
+fn pixelate(view: &mut FrameView, rect: Rect, block_size: u32) {
+    let block_size = block_size.max(1);
+    let (left, top) = (rect.x as u32, rect.y as u32);
+    let (right, bottom) = (left + rect.width, top + rect.height);
+
+    for block_top in (top..bottom).step_by(block_size as usize) {
+        let block_bottom = (block_top + block_size).min(bottom);
+
+        for block_left in (left..right).step_by(block_size as usize) {
+            let block_right = (block_left + block_size).min(right);
+            let (start, end) = (block_left as usize * 4, block_right as usize * 4);
+
+            let mut sum = [0u64; 4];
+            let mut count = 0;
+            for y in block_top..block_bottom {
+                if let Some(row) = view.row_mut(y) {
+                    for pixel in row[start..end].chunks_exact(4) {
+                        for (sum, value) in sum.iter_mut().zip(pixel) {
+                            *sum += *value as u64;
+                        }
+                        count += 1;
+                    }
+                }
+            }
+            if count == 0 {
+                continue;
+            }
+
+            let average = sum.map(|sum| (sum / count) as u8);
+            fill(
+                view,
+                Rect::new(
+                    block_left as i32,
+                    block_top as i32,
+                    block_right - block_left,
+                    block_bottom - block_top,
+                ),
+                Rgba(average),
+            );
+        }
+    }
+}
+
+/// 录制器中应用 Redactor 的部分，记录显示器的位置用于换算坐标
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+#[derive(Debug, Clone)]
+pub(crate) struct Redaction {
+    redactor: Redactor,
+    monitor: Rect,
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+impl Redaction {
+    /// 没有设置 Redactor 时返回 None
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+    pub fn from_config(
+        config: &RecorderConfig,
+        monitor: &Monitor,
+    ) -> XCapResult<Option<Redaction>> {
+        match &config.redactor {
+            Some(redactor) => Ok(Some(Redaction {
+                redactor: redactor.clone(),
+                monitor: monitor.bounds()?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// 遮挡帧中的区域，帧必须是整个显示器的画面
+    pub fn apply(&self, frame: &mut Frame) {
+        self.redactor.redact(&mut frame.view_mut(), self.monitor);
+    }
+}
+
+impl Redactor {
+    /// 遮挡显示器 monitor 的画面中的区域
+    pub(crate) fn redact(&self, view: &mut FrameView, monitor: Rect) {
+        let (width, height) = (view.width(), view.height());
+
+        // 无法读取区域时遮挡整个画面，不发送可能没有遮挡的内容
+        let rects = match self.0.lock() {
+            Ok(state) => state.rects(),
+            Err(_) => {
+                fill(view, Rect::new(0, 0, width, height), Rgba([0, 0, 0, 255]));
+                return;
+            }
+        };
+
+        for (rect, style) in rects {
+            let Some(rect) = cover_rect(monitor, rect, width, height) else {
+                continue;
+            };
+
+            match style {
+                RedactionStyle::Black => fill(view, rect, Rgba([0, 0, 0, 255])),
+                RedactionStyle::Fill(color) => fill(view, rect, color),
+                RedactionStyle::Pixelate(block_size) => pixelate(view, rect, block_size),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cover_rect() {
+        let monitor = Rect::new(-100, 0, 100, 50);
+
+        // 1.5 倍缩放的显示器，区域的边缘向外取整
+        assert_eq!(
+            cover_rect(monitor, Rect::new(-99, 1, 3, 3), 150, 75),
+            Some(Rect::new(1, 1, 5, 5))
+        );
+        assert_eq!(cover_rect(monitor, Rect::new(0, 0, 40, 20), 150, 75), None);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        let pattern = |pattern: &str| pattern.to_lowercase().chars().collect::<Vec<char>>();
+
+        assert!(glob_match(&pattern("1Password"), "1password"));
+        assert!(!glob_match(&pattern("1Password"), "1Password 7"));
+        assert!(glob_match(&pattern("*bank*"), "My Bank - Online Banking"));
+        assert!(glob_match(&pattern("*bank"), "bankbank"));
+        assert!(!glob_match(&pattern("*bank"), "bank account"));
+        assert!(glob_match(&pattern("te?t"), "Test"));
+        assert!(glob_match(&pattern("*"), ""));
+    }
+
+    #[test]
+    fn test_pattern_rects() {
+        let mut state = State::default();
+        state.regions.push(Region {
+            id: RedactionId(0),
+            target: Target::Pattern("*bank*".chars().collect()),
+            style: RedactionStyle::Black,
+        });
+        state.update_matches(&[
+            (1, "Firefox".into(), "My Bank".into()),
+            (2, "Firefox".into(), "News".into()),
+            (3, "Banking".into(), "".into()),
+        ]);
+        assert_eq!(state.window_ids(), [1, 3]);
+
+        // 还没有取得位置和已经最小化的窗口不遮挡
+        state.windows.insert(1, Some(Rect::new(0, 0, 10, 10)));
+        state.windows.insert(3, None);
+        assert_eq!(
+            state.rects(),
+            [(Rect::new(0, 0, 10, 10), RedactionStyle::Black)]
+        );
+    }
+
+    #[test]
+    fn test_redaction_apply() {
+        let redactor = Redactor::new();
+        redactor
+            .add_rect(Rect::new(0, 0, 2, 1), RedactionStyle::Black)
+            .unwrap();
+        let pixelated = redactor
+            .add_rect(Rect::new(0, 1, 2, 1), RedactionStyle::Pixelate(2))
+            .unwrap();
+        let redaction = Redaction {
+            redactor: redactor.clone(),
+            monitor: Rect::new(0, 0, 3, 2),
+        };
+
+        // 3x2 像素，每行有 4 字节填充
+        let data = (0..32).map(|value| value * 2).collect();
+        let mut frame = Frame::with_stride(3, 2, 16, data, Instant::now());
+        redaction.apply(&mut frame);
+
+        assert_eq!(
+            frame.data(),
+            [
+                0, 0, 0, 255, 0, 0, 0, 255, 16, 18, 20, 22, 24, 26, 28, 30, //
+                36, 38, 40, 42, 36, 38, 40, 42, 48, 50, 52, 54, 56, 58, 60, 62,
+            ]
+        );
+
+        redactor.remove(pixelated).unwrap();
+        assert_eq!(redactor.0.lock().unwrap().rects().len(), 1);
+    }
+}
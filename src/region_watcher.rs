@@ -0,0 +1,158 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use image::RgbaImage;
+
+use crate::{
+    Monitor,
+    error::{XCapError, XCapResult},
+    geometry::Rect,
+};
+
+// 轮询区域的间隔
+const REGION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 两张同样大小的截图中不同像素所占的比例，尺寸不同时视为全部变化
+fn changed_ratio(previous: &RgbaImage, current: &RgbaImage) -> f32 {
+    if previous.dimensions() != current.dimensions() {
+        return 1.0;
+    }
+
+    let total = current.width() as usize * current.height() as usize;
+    if total == 0 {
+        return 0.0;
+    }
+
+    let changed = previous
+        .as_raw()
+        .chunks_exact(4)
+        .zip(current.as_raw().chunks_exact(4))
+        .filter(|(a, b)| a != b)
+        .count();
+
+    changed as f32 / total as f32
+}
+
+/// Watches a region of a monitor and calls back when its pixels change.
+///
+/// Dropping the watcher stops its thread.
+#[derive(Debug)]
+pub struct RegionWatcher {
+    stopped: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl RegionWatcher {
+    /// Watch `region`, in coordinates relative to the monitor's top-left corner, and call
+    /// `callback` with the new pixels whenever more than `threshold` (0.0 to 1.0) of them
+    /// changed since the last callback. The region is captured every 100ms.
+    ///
+    /// ```no_run
+    /// use xcap::{Monitor, Rect, RegionWatcher};
+    ///
+    /// let monitor = Monitor::from_point(100, 100).unwrap();
+    /// let _watcher = RegionWatcher::new(&monitor, Rect::new(0, 0, 200, 50), 0.01, |image| {
+    ///     println!("region changed: {}x{}", image.width(), image.height());
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn new<F>(
+        monitor: &Monitor,
+        region: Rect,
+        threshold: f32,
+        mut callback: F,
+    ) -> XCapResult<RegionWatcher>
+    where
+        F: FnMut(&RgbaImage) + Send + 'static,
+    {
+        let bounds = monitor.bounds()?;
+        let local_bounds = Rect::new(0, 0, bounds.width, bounds.height);
+        if !local_bounds.contains_rect(region) {
+            return Err(XCapError::InvalidCaptureRegion(format!(
+                "Region {region:?} is outside monitor bounds {local_bounds:?}"
+            )));
+        }
+
+        // Windows 上 HMONITOR 不能跨线程传递，所以换算成全局坐标，在线程中按坐标截图
+        let global_region = Rect::new(
+            bounds.x + region.x,
+            bounds.y + region.y,
+            region.width,
+            region.height,
+        );
+        let threshold = threshold.clamp(0.0, 1.0);
+        let mut previous = Monitor::capture_screen_region(global_region)?;
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let stopped = stopped.clone();
+            thread::spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    thread::sleep(REGION_POLL_INTERVAL);
+
+                    let current = match Monitor::capture_screen_region(global_region) {
+                        Ok(current) => current,
+                        Err(err) => {
+                            log::debug!("capture_screen_region failed: {err:?}");
+                            continue;
+                        }
+                    };
+
+                    if changed_ratio(&previous, &current) > threshold {
+                        callback(&current);
+                        previous = current;
+                    }
+                }
+            })
+        };
+
+        Ok(RegionWatcher {
+            stopped,
+            worker: Some(worker),
+        })
+    }
+
+    /// Stop watching and wait for the watcher thread to exit.
+    pub fn stop(mut self) {
+        self.stop_worker();
+    }
+
+    fn stop_worker(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for RegionWatcher {
+    fn drop(&mut self) {
+        self.stop_worker();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn test_changed_ratio() {
+        let previous = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]));
+        let mut current = previous.clone();
+        assert_eq!(changed_ratio(&previous, &current), 0.0);
+
+        current.put_pixel(1, 1, Rgba([255, 0, 0, 255]));
+        assert_eq!(changed_ratio(&previous, &current), 0.25);
+
+        assert_eq!(changed_ratio(&previous, &RgbaImage::new(1, 1)), 1.0);
+    }
+}
@@ -0,0 +1,465 @@
+use std::{
+    io::{self, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, RecvTimeoutError, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+#[cfg(feature = "compression")]
+use crate::Codec;
+use crate::{
+    Monitor, MonitorInfo, NetworkFrameSender, PixelFormat, RecorderConfig, RecorderUpdate,
+    TcpFrameReceiver, TcpFrameSender, XCapError, XCapResult, geometry::Rect, video_recorder::Frame,
+};
+
+// 握手消息：魔数、版本、像素格式、传输的显示器 id、显示器数量，然后是每个显示器的信息
+const MAGIC: &[u8; 4] = b"XCRV";
+const VERSION: u8 = 1;
+// 客户端的请求：类型和两个 u32 参数
+const REQUEST_LEN: usize = 9;
+const REQUEST_KEYFRAME: u8 = 0;
+const REQUEST_RESIZE: u8 = 1;
+// 画面静止时也定期处理客户端的请求
+const FRAME_WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    Keyframe,
+    Resize { width: u32, height: u32 },
+}
+
+impl Request {
+    fn to_bytes(self) -> [u8; REQUEST_LEN] {
+        let (kind, width, height) = match self {
+            Request::Keyframe => (REQUEST_KEYFRAME, 0, 0),
+            Request::Resize { width, height } => (REQUEST_RESIZE, width, height),
+        };
+
+        let mut bytes = [0; REQUEST_LEN];
+        bytes[0] = kind;
+        bytes[1..5].copy_from_slice(&width.to_le_bytes());
+        bytes[5..9].copy_from_slice(&height.to_le_bytes());
+        bytes
+    }
+
+    fn read<R: Read>(reader: &mut R) -> io::Result<Request> {
+        let mut bytes = [0; REQUEST_LEN];
+        reader.read_exact(&mut bytes)?;
+        let width = u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]);
+        let height = u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]);
+
+        match bytes[0] {
+            REQUEST_KEYFRAME => Ok(Request::Keyframe),
+            REQUEST_RESIZE => Ok(Request::Resize { width, height }),
+            kind => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown remote view request {kind}"),
+            )),
+        }
+    }
+}
+
+/// 握手时服务端发送的信息
+#[derive(Debug, Clone, PartialEq)]
+struct Hello {
+    pixel_format: PixelFormat,
+    monitor_id: u32,
+    monitors: Vec<MonitorInfo>,
+}
+
+fn write_str(out: &mut Vec<u8>, value: &str) {
+    let bytes = &value.as_bytes()[..value.len().min(u16::MAX as usize)];
+    out.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_str<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = u16::from_le_bytes(read_array(reader)?);
+    let mut bytes = vec![0; len as usize];
+    reader.read_exact(&mut bytes)?;
+
+    String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+impl Hello {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.push(match self.pixel_format {
+            PixelFormat::Rgba8 => 0,
+        });
+        out.extend_from_slice(&self.monitor_id.to_le_bytes());
+        out.extend_from_slice(&(self.monitors.len() as u16).to_le_bytes());
+
+        for monitor in &self.monitors {
+            out.extend_from_slice(&monitor.id.to_le_bytes());
+            write_str(&mut out, &monitor.name);
+            write_str(&mut out, &monitor.unique_key);
+            out.extend_from_slice(&monitor.bounds.x.to_le_bytes());
+            out.extend_from_slice(&monitor.bounds.y.to_le_bytes());
+            out.extend_from_slice(&monitor.bounds.width.to_le_bytes());
+            out.extend_from_slice(&monitor.bounds.height.to_le_bytes());
+            out.extend_from_slice(&monitor.scale_factor.to_le_bytes());
+            out.extend_from_slice(&monitor.rotation.to_le_bytes());
+            out.extend_from_slice(&monitor.frequency.to_le_bytes());
+            out.push(monitor.is_primary as u8);
+        }
+
+        out
+    }
+
+    fn read<R: Read>(reader: &mut R) -> XCapResult<Hello> {
+        let invalid =
+            |err: io::Error| XCapError::new(format!("Read remote view handshake failed: {err}"));
+
+        let header: [u8; 12] = read_array(reader).map_err(invalid)?;
+        if &header[..4] != MAGIC {
+            return Err(XCapError::new("Not a remote view server"));
+        }
+        if header[4] != VERSION {
+            return Err(XCapError::new(format!(
+                "Unsupported remote view version {}",
+                header[4]
+            )));
+        }
+        let pixel_format = match header[5] {
+            0 => PixelFormat::Rgba8,
+            format => {
+                return Err(XCapError::new(format!("Unknown pixel format {format}")));
+            }
+        };
+        let monitor_id = u32::from_le_bytes([header[6], header[7], header[8], header[9]]);
+        let count = u16::from_le_bytes([header[10], header[11]]);
+
+        let mut monitors = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut read_monitor = || -> io::Result<MonitorInfo> {
+                Ok(MonitorInfo {
+                    id: u32::from_le_bytes(read_array(reader)?),
+                    name: read_str(reader)?,
+                    unique_key: read_str(reader)?,
+                    bounds: Rect::new(
+                        i32::from_le_bytes(read_array(reader)?),
+                        i32::from_le_bytes(read_array(reader)?),
+                        u32::from_le_bytes(read_array(reader)?),
+                        u32::from_le_bytes(read_array(reader)?),
+                    ),
+                    scale_factor: f32::from_le_bytes(read_array(reader)?),
+                    rotation: f32::from_le_bytes(read_array(reader)?),
+                    frequency: f32::from_le_bytes(read_array(reader)?),
+                    is_primary: read_array::<_, 1>(reader)?[0] != 0,
+                })
+            };
+            monitors.push(read_monitor().map_err(invalid)?);
+        }
+
+        Ok(Hello {
+            pixel_format,
+            monitor_id,
+            monitors,
+        })
+    }
+}
+
+/// 让帧放进 width x height 的缩放比例，不放大，0 表示使用原始尺寸
+fn fit_scale(native: (u32, u32), width: u32, height: u32) -> f32 {
+    if width == 0 || height == 0 || native.0 == 0 || native.1 == 0 {
+        return 1.0;
+    }
+
+    (width as f32 / native.0 as f32)
+        .min(height as f32 / native.1 as f32)
+        .min(1.0)
+}
+
+/// The server side of a minimal remote viewing protocol, streaming one monitor to a
+/// [`RemoteViewClient`] over TCP. Requires the `remote-view` feature.
+///
+/// On connect the server sends the monitor layout and pixel format, then a keyframe followed by
+/// delta frames, see [`TcpFrameSender`]. The client can ask for a keyframe and for a smaller
+/// frame size at any time, which the server applies with
+/// [`VideoRecorder::reconfigure`](crate::VideoRecorder::reconfigure). There is no
+/// authentication or encryption, so only use it on trusted networks or through a tunnel.
+///
+/// ```no_run
+/// use xcap::{Monitor, RemoteViewServer};
+///
+/// let server = RemoteViewServer::bind("0.0.0.0:5910").unwrap();
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// loop {
+///     if let Err(err) = server.serve(&monitor) {
+///         eprintln!("viewer disconnected: {err}");
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RemoteViewServer {
+    listener: TcpListener,
+    config: RecorderConfig,
+    #[cfg(feature = "compression")]
+    codec: Option<Codec>,
+}
+
+impl RemoteViewServer {
+    /// Listen for viewers on `addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> XCapResult<RemoteViewServer> {
+        let listener = TcpListener::bind(addr)
+            .map_err(|err| XCapError::new(format!("Bind remote view server failed: {err}")))?;
+
+        Ok(RemoteViewServer {
+            listener,
+            config: RecorderConfig::default(),
+            #[cfg(feature = "compression")]
+            codec: None,
+        })
+    }
+
+    /// The address the server listens on, e.g. to find the port after binding port 0.
+    pub fn local_addr(&self) -> XCapResult<std::net::SocketAddr> {
+        self.listener
+            .local_addr()
+            .map_err(|err| XCapError::new(format!("Get local address failed: {err}")))
+    }
+
+    /// The settings of the recorder created for each viewer. Its scale is the largest the
+    /// viewer receives.
+    pub fn config(mut self, config: RecorderConfig) -> RemoteViewServer {
+        self.config = config;
+        self
+    }
+
+    /// Compress frames with `codec`, see [`TcpFrameSender::codec`]. Requires the `compression`
+    /// feature.
+    #[cfg(feature = "compression")]
+    pub fn codec(mut self, codec: Option<Codec>) -> RemoteViewServer {
+        self.codec = codec;
+        self
+    }
+
+    /// Wait for a viewer and stream `monitor` to it until it disconnects. Call it again to
+    /// serve the next viewer.
+    pub fn serve(&self, monitor: &Monitor) -> XCapResult<()> {
+        let (stream, _) = self
+            .listener
+            .accept()
+            .map_err(|err| XCapError::new(format!("Accept viewer failed: {err}")))?;
+        let clone_stream = || {
+            stream
+                .try_clone()
+                .map_err(|err| XCapError::new(format!("Clone viewer connection failed: {err}")))
+        };
+        let mut control = clone_stream()?;
+        let connection = clone_stream()?;
+
+        let monitors = Monitor::all()?
+            .iter()
+            .map(MonitorInfo::new)
+            .collect::<XCapResult<Vec<MonitorInfo>>>()?;
+        let hello = Hello {
+            pixel_format: PixelFormat::Rgba8,
+            monitor_id: monitor.id()?,
+            monitors,
+        };
+        (&stream)
+            .write_all(&hello.to_bytes())
+            .map_err(|err| XCapError::new(format!("Send remote view handshake failed: {err}")))?;
+
+        let sender = TcpFrameSender::from_stream(stream)?.delta(true);
+        #[cfg(feature = "compression")]
+        let sender = sender.codec(self.codec);
+
+        // 读取客户端请求的线程，连接关闭时退出
+        let (request_tx, request_rx) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(request) = Request::read(&mut control) {
+                if request_tx.send(request).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let result = self.stream_frames(monitor, sender, request_rx);
+        // 让读取请求的线程退出
+        let _ = connection.shutdown(Shutdown::Both);
+
+        result
+    }
+
+    fn stream_frames(
+        &self,
+        monitor: &Monitor,
+        mut sender: TcpFrameSender,
+        requests: mpsc::Receiver<Request>,
+    ) -> XCapResult<()> {
+        let (recorder, rx) = monitor.video_recorder_with_config(&self.config)?;
+        recorder.start()?;
+
+        // 按配置的缩放比例换算出的原始尺寸，收到第一帧后确定
+        let mut native_size = None;
+        let mut requested_size = None;
+
+        loop {
+            loop {
+                match requests.try_recv() {
+                    Ok(Request::Keyframe) => sender.request_keyframe(),
+                    Ok(Request::Resize { width, height }) => requested_size = Some((width, height)),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return recorder.stop(),
+                }
+            }
+
+            if let (Some(native), Some((width, height))) = (native_size, requested_size) {
+                let scale = fit_scale(native, width, height) * self.config.scale;
+                recorder.reconfigure(&RecorderUpdate::new().scale(scale))?;
+                requested_size = None;
+            }
+
+            let frame: Frame = match rx.recv_timeout(FRAME_WAIT_TIMEOUT) {
+                Ok(frame) => frame,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(XCapError::new("Recorder stopped"));
+                }
+            };
+            native_size.get_or_insert((
+                (frame.width() as f32 / self.config.scale).round() as u32,
+                (frame.height() as f32 / self.config.scale).round() as u32,
+            ));
+
+            if let Err(err) = sender.send_frame(&frame) {
+                recorder.stop()?;
+                return Err(err);
+            }
+        }
+    }
+}
+
+/// The viewer side of the protocol served by [`RemoteViewServer`]. Requires the `remote-view`
+/// feature.
+///
+/// ```no_run
+/// use xcap::RemoteViewClient;
+///
+/// let mut client = RemoteViewClient::connect("192.168.1.20:5910").unwrap();
+/// println!("viewing monitor {} of {:?}", client.monitor_id(), client.monitors());
+///
+/// // Fit the frames in a 1280x720 window
+/// client.request_size(1280, 720).unwrap();
+/// loop {
+///     let frame = client.recv().unwrap();
+///     println!("{}x{}", frame.width(), frame.height());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RemoteViewClient {
+    control: TcpStream,
+    receiver: TcpFrameReceiver,
+    hello: Hello,
+}
+
+impl RemoteViewClient {
+    /// Connect to a server and read its handshake.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> XCapResult<RemoteViewClient> {
+        let mut stream = TcpStream::connect(addr)
+            .map_err(|err| XCapError::new(format!("Connect failed: {err}")))?;
+        let hello = Hello::read(&mut stream)?;
+        let control = stream
+            .try_clone()
+            .map_err(|err| XCapError::new(format!("Clone connection failed: {err}")))?;
+
+        Ok(RemoteViewClient {
+            control,
+            receiver: TcpFrameReceiver::from_stream(stream),
+            hello,
+        })
+    }
+
+    /// The pixel format of the frames.
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.hello.pixel_format
+    }
+
+    /// The id of the monitor being streamed, one of [`RemoteViewClient::monitors`].
+    pub fn monitor_id(&self) -> u32 {
+        self.hello.monitor_id
+    }
+
+    /// The server's monitor layout when the connection was made.
+    pub fn monitors(&self) -> &[MonitorInfo] {
+        &self.hello.monitors
+    }
+
+    /// Block until the next frame arrives.
+    pub fn recv(&mut self) -> XCapResult<Frame> {
+        self.receiver.recv()
+    }
+
+    /// Ask for the next frame to be sent whole.
+    pub fn request_keyframe(&mut self) -> XCapResult<()> {
+        self.send(Request::Keyframe)
+    }
+
+    /// Ask for frames that fit in `width` x `height`, keeping the aspect ratio. Frames are never
+    /// scaled up, and 0 for either asks for the full size again. The server applies it from a
+    /// later frame, so check the size of each received frame.
+    pub fn request_size(&mut self, width: u32, height: u32) -> XCapResult<()> {
+        self.send(Request::Resize { width, height })
+    }
+
+    fn send(&mut self, request: Request) -> XCapResult<()> {
+        self.control
+            .write_all(&request.to_bytes())
+            .map_err(|err| XCapError::new(format!("Send request failed: {err}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hello_round_trip() {
+        let hello = Hello {
+            pixel_format: PixelFormat::Rgba8,
+            monitor_id: 2,
+            monitors: vec![MonitorInfo {
+                id: 2,
+                name: "DELL U2720Q".to_string(),
+                unique_key: "DEL-41A8-1234".to_string(),
+                bounds: Rect::new(-1920, 0, 1920, 1080),
+                scale_factor: 2.0,
+                rotation: 0.0,
+                frequency: 60.0,
+                is_primary: false,
+            }],
+        };
+
+        let bytes = hello.to_bytes();
+        assert_eq!(Hello::read(&mut &bytes[..]).unwrap(), hello);
+        assert!(Hello::read(&mut &bytes[..8]).is_err());
+
+        let request = Request::Resize {
+            width: 1280,
+            height: 720,
+        };
+        assert_eq!(
+            Request::read(&mut &request.to_bytes()[..]).unwrap(),
+            request
+        );
+    }
+
+    #[test]
+    fn test_fit_scale() {
+        assert_eq!(fit_scale((3840, 2160), 1920, 1920), 0.5);
+        assert_eq!(fit_scale((1920, 1080), 3840, 2160), 1.0);
+        assert_eq!(fit_scale((1920, 1080), 0, 0), 1.0);
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex, mpsc::Receiver},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "compression")]
+use crate::Codec;
+use crate::{
+    XCapError, XCapResult,
+    network::{FrameDecoder, FrameEncoder, is_keyframe},
+    video_recorder::Frame,
+};
+
+// 默认最多占用 512 MiB 内存
+const DEFAULT_MAX_BYTES: usize = 512 * 1024 * 1024;
+const DEFAULT_KEYFRAME_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 缓冲区中的一帧，增量帧只保存变化的区域
+#[derive(Debug)]
+struct Entry {
+    timestamp: Instant,
+    keyframe: bool,
+    message: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    duration: Duration,
+    max_bytes: usize,
+    keyframe_interval: Duration,
+    encoder: FrameEncoder,
+    entries: VecDeque<Entry>,
+    bytes: usize,
+    last_keyframe: Option<Instant>,
+}
+
+impl Inner {
+    fn push(&mut self, frame: &Frame) -> XCapResult<()> {
+        let timestamp = frame.timestamp();
+        let keyframe_due = self.last_keyframe.is_none_or(|last_keyframe| {
+            timestamp.saturating_duration_since(last_keyframe) >= self.keyframe_interval
+        });
+        if keyframe_due {
+            self.encoder.request_keyframe();
+        }
+
+        let message = self.encoder.encode(frame)?;
+        let keyframe = is_keyframe(&message);
+        if keyframe {
+            self.last_keyframe = Some(timestamp);
+        }
+        self.bytes += message.len();
+        self.entries.push_back(Entry {
+            timestamp,
+            keyframe,
+            message,
+        });
+
+        self.evict(timestamp);
+
+        Ok(())
+    }
+
+    /// 按关键帧为单位从头部丢弃，保证第一帧总是关键帧
+    fn evict(&mut self, newest: Instant) {
+        loop {
+            let Some(next_keyframe) = self
+                .entries
+                .iter()
+                .skip(1)
+                .position(|entry| entry.keyframe)
+                .map(|index| index + 1)
+            else {
+                // 只有一组帧时无法丢弃，超出内存预算时让下一帧成为关键帧
+                if self.bytes > self.max_bytes {
+                    self.encoder.request_keyframe();
+                }
+                return;
+            };
+
+            // 丢弃后剩下的帧仍然覆盖要保留的时长，或者超出了内存预算
+            let remaining = newest.saturating_duration_since(self.entries[next_keyframe].timestamp);
+            if remaining < self.duration && self.bytes <= self.max_bytes {
+                return;
+            }
+
+            for entry in self.entries.drain(..next_keyframe) {
+                self.bytes -= entry.message.len();
+            }
+        }
+    }
+
+    /// 覆盖最后 last 时长的帧，从不晚于起始时间的最后一个关键帧开始
+    fn clip(&self, last: Duration) -> impl Iterator<Item = &Entry> {
+        let start = self
+            .entries
+            .back()
+            .and_then(|newest| newest.timestamp.checked_sub(last))
+            .and_then(|start| {
+                self.entries
+                    .iter()
+                    .rposition(|entry| entry.keyframe && entry.timestamp <= start)
+            })
+            .unwrap_or(0);
+
+        self.entries.iter().skip(start)
+    }
+}
+
+/// Keeps the last few seconds of recorder frames in memory for instant replay, so a clip of
+/// what just happened can be saved on demand.
+///
+/// Frames are stored as a keyframe every
+/// [`ReplayBuffer::keyframe_interval`] followed by the regions that changed since the previous
+/// frame, see [`TcpFrameSender::delta`](crate::TcpFrameSender::delta), which keeps a mostly
+/// static desktop small. Old frames are dropped a whole keyframe interval at a time, so clips
+/// always start at a keyframe, and sooner than [`ReplayBuffer::new`]'s duration when the
+/// buffer would grow past [`ReplayBuffer::max_bytes`].
+///
+/// The buffer is a handle, clones share the same frames, so one can be fed by the recording
+/// thread while another saves clips.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use xcap::{Monitor, ReplayBuffer};
+///
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let (recorder, rx) = monitor.video_recorder().unwrap();
+/// recorder.start().unwrap();
+///
+/// let replay = ReplayBuffer::new(Duration::from_secs(60));
+/// replay.feed(rx);
+///
+/// // Later, e.g. on a hotkey
+/// replay.save("clip.xcnf", Duration::from_secs(30)).unwrap();
+/// let frames = ReplayBuffer::load("clip.xcnf").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReplayBuffer {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl ReplayBuffer {
+    /// A buffer keeping at least the last `duration` of frames, up to 512 MiB.
+    pub fn new(duration: Duration) -> ReplayBuffer {
+        ReplayBuffer {
+            inner: Arc::new(Mutex::new(Inner {
+                duration,
+                max_bytes: DEFAULT_MAX_BYTES,
+                keyframe_interval: DEFAULT_KEYFRAME_INTERVAL,
+                encoder: {
+                    let mut encoder = FrameEncoder::default();
+                    encoder.delta = true;
+                    encoder
+                },
+                entries: VecDeque::new(),
+                bytes: 0,
+                last_keyframe: None,
+            })),
+        }
+    }
+
+    /// 修改设置，锁只会在其他线程 push 时 panic 后中毒，这时设置已经没有意义
+    fn configure<F: FnOnce(&mut Inner)>(self, f: F) -> ReplayBuffer {
+        if let Ok(mut inner) = self.inner.lock() {
+            f(&mut inner);
+        }
+        self
+    }
+
+    /// The most memory the stored frames may use, in bytes. Defaults to 512 MiB.
+    pub fn max_bytes(self, max_bytes: usize) -> ReplayBuffer {
+        self.configure(|inner| inner.max_bytes = max_bytes)
+    }
+
+    /// How often a whole frame is stored. Shorter intervals make clips start closer to the
+    /// requested time, longer ones use less memory. Defaults to 2 seconds.
+    pub fn keyframe_interval(self, keyframe_interval: Duration) -> ReplayBuffer {
+        self.configure(|inner| inner.keyframe_interval = keyframe_interval)
+    }
+
+    /// Compress stored frames with `codec`, trading CPU time for memory. Defaults to `None`.
+    /// Requires the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn codec(self, codec: Option<Codec>) -> ReplayBuffer {
+        // 每条消息记录了自己的编码，已经保存的帧不受影响
+        self.configure(|inner| inner.encoder.codec = codec)
+    }
+
+    /// Add a frame, dropping the oldest frames that are no longer needed.
+    pub fn push(&self, frame: &Frame) -> XCapResult<()> {
+        self.inner.lock()?.push(frame)
+    }
+
+    /// Push every frame received from `rx` on a background thread, until the recorder stops.
+    pub fn feed(&self, rx: Receiver<Frame>) -> JoinHandle<()> {
+        let replay = self.clone();
+        thread::spawn(move || {
+            for frame in rx {
+                if let Err(err) = replay.push(&frame) {
+                    log::error!("push frame to replay buffer failed: {err:?}");
+                    break;
+                }
+            }
+        })
+    }
+
+    /// The number of bytes the stored frames use.
+    pub fn bytes(&self) -> XCapResult<usize> {
+        Ok(self.inner.lock()?.bytes)
+    }
+
+    /// The time between the oldest and newest stored frame.
+    pub fn buffered(&self) -> XCapResult<Duration> {
+        let inner = self.inner.lock()?;
+        Ok(match (inner.entries.front(), inner.entries.back()) {
+            (Some(oldest), Some(newest)) => {
+                newest.timestamp.saturating_duration_since(oldest.timestamp)
+            }
+            _ => Duration::ZERO,
+        })
+    }
+
+    /// Remove all stored frames.
+    pub fn clear(&self) -> XCapResult<()> {
+        let mut inner = self.inner.lock()?;
+        inner.entries.clear();
+        inner.bytes = 0;
+        inner.last_keyframe = None;
+        inner.encoder.request_keyframe();
+
+        Ok(())
+    }
+
+    /// The frames of the last `last`, starting at the keyframe at or before it. Decoding every
+    /// frame takes memory, prefer [`ReplayBuffer::save`] for long clips.
+    pub fn frames(&self, last: Duration) -> XCapResult<Vec<Frame>> {
+        let inner = self.inner.lock()?;
+        let mut decoder = FrameDecoder::default();
+
+        inner
+            .clip(last)
+            .map(|entry| decoder.decode(&mut &entry.message[..]))
+            .collect()
+    }
+
+    /// Write the frames of the last `last` to `path`, read them back with
+    /// [`ReplayBuffer::load`]. The frames are written as stored, without decoding them.
+    pub fn save<P: AsRef<Path>>(&self, path: P, last: Duration) -> XCapResult<()> {
+        // 复制出消息后再写文件，不阻塞录制线程
+        let messages: Vec<Vec<u8>> = self
+            .inner
+            .lock()?
+            .clip(last)
+            .map(|entry| entry.message.clone())
+            .collect();
+
+        let write = || -> std::io::Result<()> {
+            let mut writer = BufWriter::new(File::create(path.as_ref())?);
+            for message in &messages {
+                writer.write_all(message)?;
+            }
+            writer.flush()
+        };
+        write().map_err(|err| XCapError::new(format!("Save replay clip failed: {err}")))
+    }
+
+    /// Read the frames of a clip written by [`ReplayBuffer::save`].
+    pub fn load<P: AsRef<Path>>(path: P) -> XCapResult<Vec<Frame>> {
+        let bytes = fs::read(path.as_ref())
+            .map_err(|err| XCapError::new(format!("Read replay clip failed: {err}")))?;
+        let mut decoder = FrameDecoder::default();
+        let mut reader = &bytes[..];
+
+        let mut frames = Vec::new();
+        while !reader.is_empty() {
+            frames.push(decoder.decode(&mut reader)?);
+        }
+
+        Ok(frames)
+    }
+
+    /// Encode the frames of the last `last` to a WebM file at `path`, see
+    /// [`WebmEncoder`](crate::WebmEncoder). All frames must have the same size. Requires the
+    /// `webm` feature.
+    #[cfg(feature = "webm")]
+    pub fn save_webm<P: AsRef<Path>>(&self, path: P, last: Duration) -> XCapResult<()> {
+        let frames = self.frames(last)?;
+        let Some(first) = frames.first() else {
+            return Err(XCapError::new("Replay buffer is empty"));
+        };
+
+        let file = File::create(path.as_ref())
+            .map_err(|err| XCapError::new(format!("Create WebM file failed: {err}")))?;
+        let mut encoder =
+            crate::WebmEncoder::builder(BufWriter::new(file), first.width(), first.height())
+                .build()?;
+        for frame in &frames {
+            encoder.encode(frame)?;
+        }
+        encoder
+            .finish()?
+            .flush()
+            .map_err(|err| XCapError::new(format!("Write WebM file failed: {err}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(value: u8, timestamp: Instant) -> Frame {
+        Frame::with_stride(2, 2, 8, vec![value; 16], timestamp)
+    }
+
+    #[test]
+    fn test_replay_buffer() {
+        let replay =
+            ReplayBuffer::new(Duration::from_secs(3)).keyframe_interval(Duration::from_secs(2));
+        let start = Instant::now();
+        for second in 0..10 {
+            replay
+                .push(&frame(second as u8, start + Duration::from_secs(second)))
+                .unwrap();
+        }
+
+        // 关键帧在 0、2、4、6、8 秒，保留 3 秒需要从第 6 秒开始
+        let frames = replay.frames(Duration::from_secs(60)).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].data()[0], 6);
+        assert_eq!(frames[3].data()[0], 9);
+
+        // 最后 1 秒从第 8 秒的关键帧开始
+        let clip = replay.frames(Duration::from_secs(1)).unwrap();
+        assert_eq!(clip.len(), 2);
+        assert_eq!(clip[0].data()[0], 8);
+
+        // 超出内存预算时只保留最后一组帧
+        let replay = replay.max_bytes(0);
+        replay
+            .push(&frame(10, start + Duration::from_secs(10)))
+            .unwrap();
+        let frames = replay.frames(Duration::from_secs(60)).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data()[0], 10);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    thread,
+    time::Duration,
+};
+
+use image::RgbaImage;
+
+use crate::error::{XCapError, XCapResult};
+
+// 滚动后等待窗口重绘完成的时间
+const SCROLL_SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+/// 两帧之间的滚动：内容向上移动的行数，以及底部固定不动的行数（状态栏等）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Scroll {
+    shift: usize,
+    footer: usize,
+}
+
+/// 每一行像素的哈希，比较行时只需要比较哈希
+fn row_hashes(image: &RgbaImage) -> Vec<u64> {
+    image
+        .as_raw()
+        .chunks_exact(image.width().max(1) as usize * 4)
+        .map(|row| {
+            let mut hasher = DefaultHasher::new();
+            row.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+/// 查找两帧之间的滚动距离，内容没有变化或者找不到重叠部分时返回 None
+///
+/// 顶部和底部位置不变的行视为固定区域（标题栏、工具栏、状态栏），只在中间区域中查找滚动距离，
+/// 取重叠部分完全相同的最小距离，重叠部分至少占中间区域的 1/4，避免误匹配
+fn find_scroll(previous: &[u64], current: &[u64]) -> Option<Scroll> {
+    if previous.len() != current.len() || previous == current {
+        return None;
+    }
+
+    let height = previous.len();
+    let header = previous
+        .iter()
+        .zip(current)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let footer = previous
+        .iter()
+        .rev()
+        .zip(current.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let previous_band = &previous[header..height - footer];
+    let current_band = &current[header..height - footer];
+    let band_height = previous_band.len();
+    let min_overlap = (band_height / 4).max(1);
+
+    (1..band_height.saturating_sub(min_overlap) + 1)
+        .find(|&shift| previous_band[shift..] == current_band[..band_height - shift])
+        .map(|shift| Scroll { shift, footer })
+}
+
+fn extend_rows(stitched: &mut Vec<u8>, image: &RgbaImage, start: usize, end: usize) {
+    let row_len = image.width() as usize * 4;
+    stitched.extend_from_slice(&image.as_raw()[start * row_len..end * row_len]);
+}
+
+/// 截图、滚动、再截图，把每次新露出的内容拼接到一起，直到内容不再变化或者截取了 max_pages 页
+pub(crate) fn capture_scrolling<C, S>(
+    mut capture: C,
+    mut scroll: S,
+    max_pages: u32,
+) -> XCapResult<RgbaImage>
+where
+    C: FnMut() -> XCapResult<RgbaImage>,
+    S: FnMut() -> XCapResult<()>,
+{
+    let mut previous = capture()?;
+    let mut previous_hashes = row_hashes(&previous);
+    let (width, height) = previous.dimensions();
+
+    let mut stitched = Vec::new();
+    let mut footer = 0;
+
+    for _ in 1..max_pages {
+        scroll()?;
+        thread::sleep(SCROLL_SETTLE_DELAY);
+
+        let current = capture()?;
+        // 窗口大小变化后无法继续拼接
+        if current.dimensions() != (width, height) {
+            break;
+        }
+
+        let current_hashes = row_hashes(&current);
+        let Some(scroll) = find_scroll(&previous_hashes, &current_hashes) else {
+            break;
+        };
+
+        // 第一页保留顶部固定区域，之后每页只追加新露出的行，最后一页再补上底部固定区域
+        if stitched.is_empty() {
+            extend_rows(&mut stitched, &previous, 0, height as usize - scroll.footer);
+        }
+        let end = height as usize - scroll.footer;
+        extend_rows(&mut stitched, &current, end - scroll.shift, end);
+
+        footer = scroll.footer;
+        previous = current;
+        previous_hashes = current_hashes;
+    }
+
+    if stitched.is_empty() {
+        return Ok(previous);
+    }
+
+    extend_rows(
+        &mut stitched,
+        &previous,
+        height as usize - footer,
+        height as usize,
+    );
+
+    let stitched_height = (stitched.len() / (width as usize * 4)) as u32;
+    RgbaImage::from_raw(width, stitched_height, stitched)
+        .ok_or_else(|| XCapError::new("RgbaImage::from_raw failed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use image::Rgba;
+
+    use super::*;
+
+    fn row(image: &mut RgbaImage, y: u32, value: u32) {
+        let [r, g, b, _] = value.to_le_bytes();
+        for x in 0..image.width() {
+            image.put_pixel(x, y, Rgba([r, g, b, 255]));
+        }
+    }
+
+    #[test]
+    fn test_find_scroll() {
+        let previous = [1, 10, 11, 12, 13, 14, 15, 2];
+        let current = [1, 12, 13, 14, 15, 16, 17, 2];
+
+        assert_eq!(
+            find_scroll(&previous, &current),
+            Some(Scroll {
+                shift: 2,
+                footer: 1
+            })
+        );
+        assert_eq!(find_scroll(&previous, &previous), None);
+        assert_eq!(find_scroll(&previous, &[1, 20, 21, 22, 23, 24, 25, 2]), None);
+    }
+
+    #[test]
+    fn test_capture_scrolling() {
+        // 3 行标题栏 + 20 行可滚动内容 + 2 行状态栏，内容共 50 行，每次滚动 7 行
+        let offset = Cell::new(0);
+        let viewport = |offset: u32| {
+            let mut image = RgbaImage::new(4, 25);
+            for y in 0..3 {
+                row(&mut image, y, 1000 + y);
+            }
+            for y in 0..20 {
+                row(&mut image, 3 + y, offset + y);
+            }
+            for y in 0..2 {
+                row(&mut image, 23 + y, 2000 + y);
+            }
+            image
+        };
+
+        let stitched = capture_scrolling(
+            || Ok(viewport(offset.get())),
+            || {
+                offset.set((offset.get() + 7).min(30));
+                Ok(())
+            },
+            10,
+        )
+        .unwrap();
+
+        let mut expected = RgbaImage::new(4, 55);
+        for y in 0..3 {
+            row(&mut expected, y, 1000 + y);
+        }
+        for y in 0..50 {
+            row(&mut expected, 3 + y, y);
+        }
+        for y in 0..2 {
+            row(&mut expected, 53 + y, 2000 + y);
+        }
+
+        assert_eq!(stitched, expected);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    CaptureTarget, Monitor, Window, WindowLayer,
+    error::XCapResult,
+    geometry::{Point, Rect},
+    platform::impl_selector::run_selector,
+};
+
+// 按下和松开的距离不超过这个值时按点击处理
+const CLICK_DISTANCE: i32 = 4;
+
+/// A translucent full-screen overlay on which the user drags out a region or clicks a window,
+/// the first half of a snipping tool. Requires the `selector` feature.
+///
+/// Escape or the right mouse button cancels. On macOS it must be called on the main thread. On
+/// Linux it needs X11; Wayland compositors don't let clients draw over other windows, use the
+/// screenshot portal's interactive mode there instead.
+///
+/// ```no_run
+/// use xcap::Selector;
+///
+/// if let Some(target) = Selector::new().select().unwrap() {
+///     target.capture_image().unwrap().save("target/selection.png").unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Selector {
+    windows: bool,
+}
+
+impl Default for Selector {
+    fn default() -> Selector {
+        Selector { windows: true }
+    }
+}
+
+impl Selector {
+    pub fn new() -> Selector {
+        Selector::default()
+    }
+
+    /// Whether clicking picks the window under the cursor. Defaults to true. When false, clicks
+    /// are ignored and only dragged regions are returned.
+    pub fn windows(mut self, windows: bool) -> Selector {
+        self.windows = windows;
+        self
+    }
+
+    /// Show the overlay and wait until the user picks a target, `None` when they cancel.
+    pub fn select(&self) -> XCapResult<Option<CaptureTarget>> {
+        let monitors = Monitor::all()?;
+        let bounds = monitors
+            .iter()
+            .map(Monitor::bounds)
+            .collect::<XCapResult<Vec<Rect>>>()?;
+
+        // 覆盖层显示之前获取窗口列表，避免点击到覆盖层自己
+        let windows = if self.windows {
+            Window::all_with(|window| {
+                window
+                    .layer()
+                    .is_ok_and(|layer| layer == WindowLayer::Normal)
+                    && !window.is_minimized().unwrap_or(false)
+            })?
+        } else {
+            Vec::new()
+        };
+
+        let Some(outcome) = run_selector(&bounds, Selection::new(self.windows))? else {
+            return Ok(None);
+        };
+
+        match outcome {
+            Outcome::Region(rect) => {
+                let Some(index) = monitor_at(&bounds, Point::new(rect.x, rect.y)) else {
+                    return Ok(None);
+                };
+                let monitor_bounds = bounds[index];
+                let Some(rect) = monitor_bounds.intersection(rect) else {
+                    return Ok(None);
+                };
+
+                Ok(Some(CaptureTarget::MonitorRegion {
+                    monitor: monitors[index].clone(),
+                    rect: Rect::new(
+                        rect.x - monitor_bounds.x,
+                        rect.y - monitor_bounds.y,
+                        rect.width,
+                        rect.height,
+                    ),
+                }))
+            }
+            Outcome::Click(point) => Ok(windows
+                .into_iter()
+                .find(|window| {
+                    window
+                        .coordinate_space()
+                        .is_ok_and(|space| space.bounds().contains_point(point.x, point.y))
+                })
+                .map(CaptureTarget::Window)),
+        }
+    }
+}
+
+fn monitor_at(bounds: &[Rect], point: Point) -> Option<usize> {
+    bounds
+        .iter()
+        .position(|bounds| bounds.contains_point(point.x, point.y))
+}
+
+/// 选择结束时的结果，坐标都是全局坐标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    Region(Rect),
+    Click(Point),
+}
+
+/// 平台无关的选择状态，平台代码把鼠标事件转换为全局坐标后交给它，并按 rect 绘制选区
+#[derive(Debug, Clone)]
+pub(crate) struct Selection {
+    windows: bool,
+    start: Option<Point>,
+    current: Point,
+}
+
+// Android 没有选择界面，只会创建 Selection
+#[cfg_attr(target_os = "android", allow(dead_code))]
+impl Selection {
+    pub fn new(windows: bool) -> Selection {
+        Selection {
+            windows,
+            start: None,
+            current: Point::default(),
+        }
+    }
+
+    pub fn press(&mut self, point: Point) {
+        self.start = Some(point);
+        self.current = point;
+    }
+
+    pub fn drag(&mut self, point: Point) {
+        self.current = point;
+    }
+
+    /// 松开鼠标，返回 None 时继续选择
+    pub fn release(&mut self, point: Point) -> Option<Outcome> {
+        let start = self.start.take()?;
+
+        let is_click = (point.x - start.x).abs() <= CLICK_DISTANCE
+            && (point.y - start.y).abs() <= CLICK_DISTANCE;
+        if is_click {
+            return self.windows.then_some(Outcome::Click(point));
+        }
+
+        Some(Outcome::Region(span(start, point)))
+    }
+
+    /// 正在拖动的选区，按下后还没有移动超过点击距离时为 None
+    pub fn rect(&self) -> Option<Rect> {
+        let start = self.start?;
+        let rect = span(start, self.current);
+
+        (rect.width as i32 > CLICK_DISTANCE || rect.height as i32 > CLICK_DISTANCE).then_some(rect)
+    }
+}
+
+/// 两个点之间的矩形，包含终点所在的像素
+fn span(a: Point, b: Point) -> Rect {
+    let (left, right) = (a.x.min(b.x), a.x.max(b.x));
+    let (top, bottom) = (a.y.min(b.y), a.y.max(b.y));
+
+    Rect::new(
+        left,
+        top,
+        (right as i64 - left as i64 + 1) as u32,
+        (bottom as i64 - top as i64 + 1) as u32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection() {
+        let mut selection = Selection::new(true);
+        assert_eq!(selection.release(Point::new(0, 0)), None);
+
+        // 反向拖动
+        selection.press(Point::new(100, 50));
+        selection.drag(Point::new(102, 51));
+        assert_eq!(selection.rect(), None);
+        selection.drag(Point::new(-10, 20));
+        assert_eq!(selection.rect(), Some(Rect::new(-10, 20, 111, 31)));
+        assert_eq!(
+            selection.release(Point::new(-10, 20)),
+            Some(Outcome::Region(Rect::new(-10, 20, 111, 31)))
+        );
+        assert_eq!(selection.rect(), None);
+
+        selection.press(Point::new(10, 10));
+        assert_eq!(
+            selection.release(Point::new(12, 8)),
+            Some(Outcome::Click(Point::new(12, 8)))
+        );
+
+        // 不选择窗口时忽略点击
+        let mut selection = Selection::new(false);
+        selection.press(Point::new(10, 10));
+        assert_eq!(selection.release(Point::new(10, 10)), None);
+    }
+
+    #[test]
+    fn test_monitor_at() {
+        let bounds = [Rect::new(0, 0, 1920, 1080), Rect::new(-1280, 0, 1280, 720)];
+
+        assert_eq!(monitor_at(&bounds, Point::new(-1, 0)), Some(1));
+        assert_eq!(monitor_at(&bounds, Point::new(1919, 1079)), Some(0));
+        assert_eq!(monitor_at(&bounds, Point::new(-1, 720)), None);
+    }
+}
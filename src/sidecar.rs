@@ -0,0 +1,358 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::mpsc::Receiver,
+    time::Duration,
+};
+
+use crate::{
+    ActiveTitle, TitleWatcher, Window, XCapError, XCapResult, clock, diff::diff, geometry::Rect,
+    video_recorder::Frame,
+};
+
+// 前台窗口的采样间隔
+const TITLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The encoding of a [`FrameSidecar`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SidecarFormat {
+    /// One JSON object per line.
+    #[default]
+    JsonLines,
+    /// A CBOR sequence (RFC 8742), one map per frame.
+    Cbor,
+}
+
+impl SidecarFormat {
+    /// 分段录制时 sidecar 文件的扩展名
+    #[cfg(feature = "webm")]
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            SidecarFormat::JsonLines => "jsonl",
+            SidecarFormat::Cbor => "cbor",
+        }
+    }
+}
+
+/// 一帧的元数据
+#[derive(Debug)]
+struct Record<'a> {
+    timestamp_ns: u64,
+    width: u32,
+    height: u32,
+    dirty_rects: &'a [Rect],
+    active: Option<&'a ActiveTitle>,
+}
+
+impl Record<'_> {
+    fn write_json(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(
+            format!(
+                "{{\"timestamp_ns\":{},\"width\":{},\"height\":{},\"dirty_rects\":[",
+                self.timestamp_ns, self.width, self.height
+            )
+            .as_bytes(),
+        );
+        for (index, rect) in self.dirty_rects.iter().enumerate() {
+            if index > 0 {
+                out.push(b',');
+            }
+            out.extend_from_slice(
+                format!("[{},{},{},{}]", rect.x, rect.y, rect.width, rect.height).as_bytes(),
+            );
+        }
+        out.push(b']');
+
+        match self.active {
+            Some(active) => {
+                out.extend_from_slice(b",\"app_name\":");
+                write_json_string(out, &active.app_name);
+                out.extend_from_slice(format!(",\"pid\":{},\"title\":", active.pid).as_bytes());
+                write_json_string(out, &active.title);
+            }
+            None => out.extend_from_slice(b",\"app_name\":null,\"pid\":null,\"title\":null"),
+        }
+        out.extend_from_slice(b"}\n");
+    }
+
+    fn write_cbor(&self, out: &mut Vec<u8>) {
+        write_cbor_head(out, 5, 7);
+
+        write_cbor_text(out, "timestamp_ns");
+        write_cbor_head(out, 0, self.timestamp_ns);
+        write_cbor_text(out, "width");
+        write_cbor_head(out, 0, self.width as u64);
+        write_cbor_text(out, "height");
+        write_cbor_head(out, 0, self.height as u64);
+
+        write_cbor_text(out, "dirty_rects");
+        write_cbor_head(out, 4, self.dirty_rects.len() as u64);
+        for rect in self.dirty_rects {
+            write_cbor_head(out, 4, 4);
+            write_cbor_int(out, rect.x as i64);
+            write_cbor_int(out, rect.y as i64);
+            write_cbor_head(out, 0, rect.width as u64);
+            write_cbor_head(out, 0, rect.height as u64);
+        }
+
+        write_cbor_text(out, "app_name");
+        match self.active {
+            Some(active) => write_cbor_text(out, &active.app_name),
+            None => out.push(CBOR_NULL),
+        }
+        write_cbor_text(out, "pid");
+        match self.active {
+            Some(active) => write_cbor_int(out, active.pid as i64),
+            None => out.push(CBOR_NULL),
+        }
+        write_cbor_text(out, "title");
+        match self.active {
+            Some(active) => write_cbor_text(out, &active.title),
+            None => out.push(CBOR_NULL),
+        }
+    }
+}
+
+fn write_json_string(out: &mut Vec<u8>, value: &str) {
+    out.push(b'"');
+    for char in value.chars() {
+        match char {
+            '"' => out.extend_from_slice(b"\\\""),
+            '\\' => out.extend_from_slice(b"\\\\"),
+            '\n' => out.extend_from_slice(b"\\n"),
+            '\r' => out.extend_from_slice(b"\\r"),
+            '\t' => out.extend_from_slice(b"\\t"),
+            char if (char as u32) < 0x20 => {
+                out.extend_from_slice(format!("\\u{:04x}", char as u32).as_bytes())
+            }
+            char => {
+                let mut buf = [0; 4];
+                out.extend_from_slice(char.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    out.push(b'"');
+}
+
+const CBOR_NULL: u8 = 0xf6;
+
+/// CBOR 数据项的头部，major 为主类型，参数使用最短的编码
+fn write_cbor_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+fn write_cbor_int(out: &mut Vec<u8>, value: i64) {
+    if value >= 0 {
+        write_cbor_head(out, 0, value as u64);
+    } else {
+        // 负整数编码为 -1 - n
+        write_cbor_head(out, 1, !value as u64);
+    }
+}
+
+fn write_cbor_text(out: &mut Vec<u8>, value: &str) {
+    write_cbor_head(out, 3, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// Writes per-frame metadata next to a recording, for tools that analyze user-research or
+/// support recordings: the [`Frame::timestamp`] in nanoseconds on the [`clock`] timeline, the
+/// frame size, the rectangles that changed since the previous frame (see [`diff`](crate::diff))
+/// and the focused application, process id and window title, sampled every 250 ms with
+/// [`Window::watch_active_title`]. The application fields are `null` until the first sample.
+///
+/// Each record is a map with the keys `timestamp_ns`, `width`, `height`, `dirty_rects` (an
+/// array of `[x, y, width, height]`), `app_name`, `pid` and `title`. The first frame, and
+/// frames whose size changed, report the whole frame as dirty.
+///
+/// ```no_run
+/// use xcap::{FrameSidecar, Monitor, SidecarFormat};
+///
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let (recorder, rx) = monitor.video_recorder().unwrap();
+/// let mut sidecar = FrameSidecar::create("recording.jsonl", SidecarFormat::JsonLines).unwrap();
+///
+/// recorder.start().unwrap();
+/// for frame in rx.iter().take(300) {
+///     sidecar.record(&frame).unwrap();
+/// }
+/// recorder.stop().unwrap();
+/// sidecar.finish().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FrameSidecar<W: Write> {
+    writer: W,
+    format: SidecarFormat,
+    // 上一帧的像素副本，不持有平台缓冲区
+    previous: Option<Frame>,
+    // 保持 watcher 存活，释放时停止采样线程
+    _title_watcher: TitleWatcher,
+    titles: Receiver<ActiveTitle>,
+    active: Option<ActiveTitle>,
+    buf: Vec<u8>,
+}
+
+impl FrameSidecar<BufWriter<File>> {
+    /// Create or truncate the file at `path` and write to it.
+    pub fn create(
+        path: impl AsRef<Path>,
+        format: SidecarFormat,
+    ) -> XCapResult<FrameSidecar<BufWriter<File>>> {
+        let file = File::create(path)
+            .map_err(|err| XCapError::new(format!("Create sidecar failed: {err}")))?;
+
+        FrameSidecar::new(BufWriter::new(file), format)
+    }
+}
+
+impl<W: Write> FrameSidecar<W> {
+    /// Write metadata records to `writer`.
+    pub fn new(writer: W, format: SidecarFormat) -> XCapResult<FrameSidecar<W>> {
+        let (title_watcher, titles) = Window::watch_active_title(TITLE_INTERVAL)?;
+
+        Ok(FrameSidecar {
+            writer,
+            format,
+            previous: None,
+            _title_watcher: title_watcher,
+            titles,
+            active: None,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Write the record for `frame`. Call it for every frame written to the recording, in
+    /// order.
+    pub fn record(&mut self, frame: &Frame) -> XCapResult<()> {
+        if let Some(active) = self.titles.try_iter().last() {
+            self.active = Some(active);
+        }
+
+        let dirty_rects = match &self.previous {
+            Some(previous) => diff(previous, frame),
+            None => vec![Rect::new(0, 0, frame.width(), frame.height())],
+        };
+        let record = Record {
+            timestamp_ns: clock::to_nanos(frame.timestamp()),
+            width: frame.width(),
+            height: frame.height(),
+            dirty_rects: &dirty_rects,
+            active: self.active.as_ref(),
+        };
+
+        self.buf.clear();
+        match self.format {
+            SidecarFormat::JsonLines => record.write_json(&mut self.buf),
+            SidecarFormat::Cbor => record.write_cbor(&mut self.buf),
+        }
+        self.writer
+            .write_all(&self.buf)
+            .map_err(|err| XCapError::new(format!("Write sidecar failed: {err}")))?;
+
+        self.previous = Some(Frame::with_stride(
+            frame.width(),
+            frame.height(),
+            frame.stride(),
+            frame.data().to_vec(),
+            frame.timestamp(),
+        ));
+
+        Ok(())
+    }
+
+    /// Flush the writer and return it.
+    pub fn finish(mut self) -> XCapResult<W> {
+        self.writer
+            .flush()
+            .map_err(|err| XCapError::new(format!("Write sidecar failed: {err}")))?;
+
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record<'a>(dirty_rects: &'a [Rect], active: Option<&'a ActiveTitle>) -> Record<'a> {
+        Record {
+            timestamp_ns: 1_000,
+            width: 64,
+            height: 32,
+            dirty_rects,
+            active,
+        }
+    }
+
+    #[test]
+    fn test_write_json() {
+        let active = ActiveTitle {
+            app_name: "Editor".to_string(),
+            pid: 42,
+            title: "a \"b\"\n".to_string(),
+        };
+        let rects = [Rect::new(0, -1, 32, 32)];
+
+        let mut out = Vec::new();
+        record(&rects, Some(&active)).write_json(&mut out);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"timestamp_ns\":1000,\"width\":64,\"height\":32,\"dirty_rects\":[[0,-1,32,32]],\
+             \"app_name\":\"Editor\",\"pid\":42,\"title\":\"a \\\"b\\\"\\n\"}\n"
+        );
+
+        let mut out = Vec::new();
+        record(&[], None).write_json(&mut out);
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .ends_with("\"dirty_rects\":[],\"app_name\":null,\"pid\":null,\"title\":null}\n")
+        );
+    }
+
+    #[test]
+    fn test_write_cbor() {
+        let mut out = Vec::new();
+        write_cbor_head(&mut out, 0, 23);
+        write_cbor_head(&mut out, 0, 24);
+        write_cbor_head(&mut out, 0, 1_000);
+        write_cbor_int(&mut out, -1);
+        write_cbor_int(&mut out, -500);
+        write_cbor_text(&mut out, "pid");
+        assert_eq!(
+            out,
+            [
+                0x17, 0x18, 0x18, 0x19, 0x03, 0xe8, 0x20, 0x39, 0x01, 0xf3, 0x63, b'p', b'i', b'd'
+            ]
+        );
+
+        let rects = [Rect::new(-2, 0, 1, 1)];
+        let mut out = Vec::new();
+        record(&rects, None).write_cbor(&mut out);
+        // 7 个键的 map，dirty_rects 是一个包含 [-2, 0, 1, 1] 的数组
+        assert_eq!(out[0], 0xa7);
+        let dirty_rects = [0x81, 0x84, 0x21, 0x00, 0x01, 0x01];
+        assert!(
+            out.windows(dirty_rects.len())
+                .any(|window| window == dirty_rects)
+        );
+        assert_eq!(out[out.len() - 1], CBOR_NULL);
+    }
+}
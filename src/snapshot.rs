@@ -0,0 +1,147 @@
+use std::time::SystemTime;
+
+use crate::{
+    ActiveTitle, Monitor, Window, WindowLayer,
+    error::XCapResult,
+    geometry::{Point, Rect},
+    platform::{self, impl_window::ImplWindow},
+};
+
+/// A monitor in a [`Snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorInfo {
+    pub id: u32,
+    pub name: String,
+    /// See [`Monitor::unique_key`].
+    pub unique_key: String,
+    /// The monitor's bounds in global coordinates.
+    pub bounds: Rect,
+    pub scale_factor: f32,
+    pub rotation: f32,
+    pub frequency: f32,
+    pub is_primary: bool,
+}
+
+impl MonitorInfo {
+    pub(crate) fn new(monitor: &Monitor) -> XCapResult<MonitorInfo> {
+        Ok(MonitorInfo {
+            id: monitor.id()?,
+            name: monitor.name()?,
+            unique_key: monitor.unique_key()?,
+            bounds: monitor.bounds()?,
+            scale_factor: monitor.scale_factor()?,
+            rotation: monitor.rotation()?,
+            frequency: monitor.frequency()?,
+            is_primary: monitor.is_primary()?,
+        })
+    }
+}
+
+/// A window in a [`Snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowInfo {
+    pub id: u32,
+    pub pid: u32,
+    pub app_name: String,
+    pub title: String,
+    /// The window's bounds in global coordinates.
+    pub bounds: Rect,
+    pub z: i32,
+    pub layer: WindowLayer,
+    pub is_minimized: bool,
+    pub is_maximized: bool,
+    pub is_focused: bool,
+    /// The id of the monitor the window is on, see [`Window::current_monitor`].
+    pub monitor_id: u32,
+}
+
+impl WindowInfo {
+    fn new(window: &Window) -> XCapResult<WindowInfo> {
+        Ok(WindowInfo {
+            id: window.id()?,
+            pid: window.pid()?,
+            app_name: window.app_name()?,
+            title: window.title()?,
+            bounds: Rect::new(window.x()?, window.y()?, window.width()?, window.height()?),
+            z: window.z()?,
+            layer: window.layer()?,
+            is_minimized: window.is_minimized()?,
+            is_maximized: window.is_maximized()?,
+            is_focused: window.is_focused()?,
+            monitor_id: window.current_monitor()?.id()?,
+        })
+    }
+}
+
+/// The monitors, windows, focused app and cursor position at one point in time, see
+/// [`snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    /// When the snapshot was taken.
+    pub taken_at: SystemTime,
+    /// The monitors in [`Monitor::all`] order.
+    pub monitors: Vec<MonitorInfo>,
+    /// The windows in [`Window::all`] order, front to back.
+    pub windows: Vec<WindowInfo>,
+    /// The focused app and window, `None` when it can't be determined, e.g. while switching
+    /// windows.
+    pub active: Option<ActiveTitle>,
+    /// The cursor position in global coordinates, `None` where it can't be read, e.g. on
+    /// Wayland.
+    pub cursor: Option<Point>,
+}
+
+/// Read the monitors, windows, focused app and cursor position in one call, for diagnostics
+/// and support tooling.
+///
+/// The system keeps changing while the snapshot is taken, so the lists are read back to back
+/// before any details are queried, to keep them as close in time as possible. Windows that
+/// close before their details are read are left out.
+///
+/// ```no_run
+/// let snapshot = xcap::snapshot().unwrap();
+///
+/// println!("cursor at {:?}, focused {:?}", snapshot.cursor, snapshot.active);
+/// for window in &snapshot.windows {
+///     println!("{} {:?} on monitor {}", window.title, window.bounds, window.monitor_id);
+/// }
+/// ```
+pub fn snapshot() -> XCapResult<Snapshot> {
+    let taken_at = SystemTime::now();
+    let cursor = platform::cursor_position().ok();
+    let active = ImplWindow::get_active_title()
+        .ok()
+        .map(|(app_name, pid, title)| ActiveTitle {
+            app_name,
+            pid,
+            title,
+        });
+    let monitors = Monitor::all()?;
+    let windows = Window::all()?;
+
+    let monitors = monitors
+        .iter()
+        .map(MonitorInfo::new)
+        .collect::<XCapResult<Vec<MonitorInfo>>>()?;
+    let windows = windows
+        .iter()
+        .filter_map(|window| match WindowInfo::new(window) {
+            Ok(info) => Some(info),
+            Err(err) => {
+                log::debug!("read window info failed: {err:?}");
+                None
+            }
+        })
+        .collect();
+
+    Ok(Snapshot {
+        taken_at,
+        monitors,
+        windows,
+        active,
+        cursor,
+    })
+}
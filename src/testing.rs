@@ -0,0 +1,525 @@
+//! Image comparison for visual regression tests of captured screenshots, and a
+//! [`WindowObserver`] that follows a window while the test drives its UI.
+//!
+//! ```
+//! use xcap::{
+//!     Rect,
+//!     image::{Rgba, RgbaImage},
+//!     testing::Comparison,
+//! };
+//!
+//! let expected = RgbaImage::from_pixel(4, 4, Rgba([200, 100, 0, 255]));
+//! let mut actual = expected.clone();
+//! // e.g. a clock that changes on every capture
+//! actual.put_pixel(3, 3, Rgba([0, 0, 0, 255]));
+//!
+//! Comparison::new()
+//!     .tolerance(2)
+//!     .mask(Rect::new(3, 3, 1, 1))
+//!     .assert_matches(&expected, &actual);
+//! ```
+
+use std::time::{Duration, Instant};
+
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    Window,
+    delayed_capture::sleep_until,
+    error::{XCapError, XCapResult},
+    geometry::Rect,
+};
+
+// YIQ 空间中两个颜色之间的最大距离，用于把感知阈值换算到 0.0 到 1.0
+const MAX_YIQ_DELTA: f32 = 35215.0;
+// 观察窗口时默认的截图间隔
+const DEFAULT_OBSERVE_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Metric {
+    // 每个通道允许的最大差值
+    Channel(u8),
+    // 0.0 到 1.0 的感知差异阈值
+    Perceptual(f32),
+}
+
+/// 半透明像素先和白色背景混合，再转换到 YIQ 空间
+fn to_yiq(pixel: Rgba<u8>) -> [f32; 3] {
+    let alpha = pixel[3] as f32 / 255.0;
+    let [r, g, b] = [0, 1, 2].map(|i| 255.0 + (pixel[i] as f32 - 255.0) * alpha);
+
+    [
+        r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2,
+        r * 0.595_978 - g * 0.274_176_1 - b * 0.321_801_9,
+        r * 0.211_470_2 - g * 0.522_617_2 + b * 0.311_147,
+    ]
+}
+
+/// 两个像素在 YIQ 空间中加权后的距离的平方，参考 pixelmatch 的实现
+fn yiq_delta(a: Rgba<u8>, b: Rgba<u8>) -> f32 {
+    let [ya, ia, qa] = to_yiq(a);
+    let [yb, ib, qb] = to_yiq(b);
+    let (y, i, q) = (ya - yb, ia - ib, qa - qb);
+
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+/// The result of [`Comparison::compare`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageDiff {
+    /// The number of pixels that differ by more than the tolerance.
+    pub differing_pixels: u64,
+    /// The number of pixels compared, excluding masked pixels.
+    pub compared_pixels: u64,
+    /// The smallest rectangle containing all differing pixels, `None` when no pixel differs.
+    pub bounds: Option<Rect>,
+    /// Whether both images have the same size. Images of different sizes are not compared and
+    /// every pixel of the actual image counts as differing.
+    pub same_size: bool,
+}
+
+impl ImageDiff {
+    /// The fraction of compared pixels that differ, from 0.0 to 1.0.
+    pub fn ratio(&self) -> f64 {
+        if self.compared_pixels == 0 {
+            return 0.0;
+        }
+
+        self.differing_pixels as f64 / self.compared_pixels as f64
+    }
+}
+
+/// How two images are compared. By default every pixel must be identical.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    metric: Metric,
+    masks: Vec<Rect>,
+    max_diff_ratio: f64,
+}
+
+impl Default for Comparison {
+    fn default() -> Comparison {
+        Comparison::new()
+    }
+}
+
+impl Comparison {
+    /// An exact comparison, see the methods below to relax it.
+    pub fn new() -> Comparison {
+        Comparison {
+            metric: Metric::Channel(0),
+            masks: Vec::new(),
+            max_diff_ratio: 0.0,
+        }
+    }
+
+    /// Let each channel, including alpha, differ by up to `tolerance`, e.g. to absorb dithering
+    /// or color management rounding.
+    pub fn tolerance(mut self, tolerance: u8) -> Comparison {
+        self.metric = Metric::Channel(tolerance);
+        self
+    }
+
+    /// Compare by perceived color difference instead of per channel, with `threshold` from 0.0
+    /// to 1.0; 0.1 is a good start. Semi-transparent pixels are blended onto white first.
+    pub fn perceptual(mut self, threshold: f32) -> Comparison {
+        self.metric = Metric::Perceptual(threshold.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Ignore the pixels in `rect`, in image pixel coordinates, e.g. a clock or a blinking
+    /// cursor. Can be called multiple times.
+    pub fn mask(mut self, rect: Rect) -> Comparison {
+        self.masks.push(rect);
+        self
+    }
+
+    /// Let up to `ratio`, from 0.0 to 1.0, of the compared pixels differ before
+    /// [`assert_matches`](Comparison::assert_matches) fails. Defaults to 0.0.
+    pub fn max_diff_ratio(mut self, ratio: f64) -> Comparison {
+        self.max_diff_ratio = ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    fn is_different(&self, a: Rgba<u8>, b: Rgba<u8>) -> bool {
+        match self.metric {
+            Metric::Channel(tolerance) => (0..4).any(|i| a[i].abs_diff(b[i]) > tolerance),
+            Metric::Perceptual(threshold) => {
+                yiq_delta(a, b) > MAX_YIQ_DELTA * threshold * threshold
+            }
+        }
+    }
+
+    /// Compare `actual` against `expected`.
+    pub fn compare(&self, expected: &RgbaImage, actual: &RgbaImage) -> ImageDiff {
+        let (width, height) = actual.dimensions();
+
+        if expected.dimensions() != (width, height) {
+            let pixels = width as u64 * height as u64;
+            return ImageDiff {
+                differing_pixels: pixels,
+                compared_pixels: pixels,
+                bounds: (pixels > 0).then(|| Rect::new(0, 0, width, height)),
+                same_size: false,
+            };
+        }
+
+        let mut differing_pixels = 0;
+        let mut compared_pixels = 0;
+        // 差异像素的包围范围 (left, top, right, bottom)
+        let mut bounds: Option<(u32, u32, u32, u32)> = None;
+
+        for (x, y, pixel) in actual.enumerate_pixels() {
+            if self
+                .masks
+                .iter()
+                .any(|mask| mask.contains_point(x as i32, y as i32))
+            {
+                continue;
+            }
+
+            compared_pixels += 1;
+            if !self.is_different(*expected.get_pixel(x, y), *pixel) {
+                continue;
+            }
+
+            differing_pixels += 1;
+            bounds = Some(match bounds {
+                Some((left, top, right, bottom)) => {
+                    (left.min(x), top.min(y), right.max(x), bottom.max(y))
+                }
+                None => (x, y, x, y),
+            });
+        }
+
+        ImageDiff {
+            differing_pixels,
+            compared_pixels,
+            bounds: bounds.map(|(left, top, right, bottom)| {
+                Rect::new(left as i32, top as i32, right - left + 1, bottom - top + 1)
+            }),
+            same_size: true,
+        }
+    }
+
+    /// Whether `actual` matches `expected` within the tolerance and the allowed ratio of
+    /// differing pixels.
+    pub fn matches(&self, expected: &RgbaImage, actual: &RgbaImage) -> bool {
+        let diff = self.compare(expected, actual);
+
+        diff.same_size && diff.ratio() <= self.max_diff_ratio
+    }
+
+    /// Panic with a description of the differences unless `actual` matches `expected`, see
+    /// [`matches`](Comparison::matches).
+    #[track_caller]
+    pub fn assert_matches(&self, expected: &RgbaImage, actual: &RgbaImage) {
+        let diff = self.compare(expected, actual);
+
+        if !diff.same_size {
+            panic!(
+                "images differ in size: expected {:?}, actual {:?}",
+                expected.dimensions(),
+                actual.dimensions()
+            );
+        }
+
+        if diff.ratio() > self.max_diff_ratio {
+            panic!(
+                "images differ: {} of {} pixels ({:.2}%, allowed {:.2}%) within {:?}",
+                diff.differing_pixels,
+                diff.compared_pixels,
+                diff.ratio() * 100.0,
+                self.max_diff_ratio * 100.0,
+                diff.bounds
+            );
+        }
+    }
+}
+
+/// A capture of the window taken by [`WindowObserver`].
+#[derive(Debug, Clone)]
+pub struct ObservedFrame {
+    pub image: RgbaImage,
+    /// When the capture was taken.
+    pub timestamp: Instant,
+    /// Whether the window changed since the previous capture. The first capture counts as
+    /// changed.
+    pub changed: bool,
+    /// How many captures in a row, this one included, have not changed.
+    pub stable_frames: u32,
+    /// How the capture differs from [`WindowObserver::baseline`], `None` without a baseline.
+    pub baseline_diff: Option<ImageDiff>,
+    /// Whether the capture matches the baseline, `None` without a baseline.
+    pub matches_baseline: Option<bool>,
+}
+
+/// 观察状态，和截图分开以便测试
+#[derive(Debug)]
+struct Observation {
+    comparison: Comparison,
+    baseline: Option<RgbaImage>,
+    changes_only: bool,
+    stop_after_stable: Option<u32>,
+    previous: Option<RgbaImage>,
+    stable_frames: u32,
+    finished: bool,
+}
+
+impl Observation {
+    fn new() -> Observation {
+        Observation {
+            comparison: Comparison::new(),
+            baseline: None,
+            changes_only: false,
+            stop_after_stable: None,
+            previous: None,
+            stable_frames: 0,
+            finished: false,
+        }
+    }
+
+    /// 处理一次截图，返回需要交给调用者的帧
+    fn observe(&mut self, image: RgbaImage, timestamp: Instant) -> Option<ObservedFrame> {
+        let changed = self
+            .previous
+            .as_ref()
+            .is_none_or(|previous| !self.comparison.matches(previous, &image));
+        self.stable_frames = if changed { 0 } else { self.stable_frames + 1 };
+
+        // 稳定帧数达到要求后结束，即使只输出变化的帧，也输出最后一帧
+        let settled = self
+            .stop_after_stable
+            .is_some_and(|frames| self.stable_frames >= frames);
+        self.finished = settled;
+        self.previous = Some(image.clone());
+
+        if self.changes_only && !changed && !settled {
+            return None;
+        }
+
+        let baseline_diff = self
+            .baseline
+            .as_ref()
+            .map(|baseline| self.comparison.compare(baseline, &image));
+        let matches_baseline = self
+            .baseline
+            .as_ref()
+            .map(|baseline| self.comparison.matches(baseline, &image));
+
+        Some(ObservedFrame {
+            image,
+            timestamp,
+            changed,
+            stable_frames: self.stable_frames,
+            baseline_diff,
+            matches_baseline,
+        })
+    }
+}
+
+/// Captures a window at a low, fixed rate and reports what changed, for UI test runners that
+/// wait for the window to settle and compare it against a baseline.
+///
+/// The observer is an iterator of captures on the calling thread. Captures are compared with
+/// the previous one using [`WindowObserver::comparison`], so dithering or a blinking cursor can
+/// be ignored. Failed captures are yielded as errors and observing continues.
+///
+/// ```no_run
+/// use xcap::{
+///     Window,
+///     image::open,
+///     testing::{Comparison, WindowObserver},
+/// };
+///
+/// let window = Window::all().unwrap().remove(0);
+/// let baseline = open("baseline.png").unwrap().to_rgba8();
+///
+/// let observer = WindowObserver::new(&window)
+///     .comparison(Comparison::new().tolerance(2))
+///     .baseline(baseline)
+///     .changes_only(true)
+///     .stop_after_stable(3);
+/// for frame in observer {
+///     let frame = frame.unwrap();
+///     println!("changed, matches baseline: {:?}", frame.matches_baseline);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct WindowObserver {
+    window: Window,
+    interval: Duration,
+    next_capture_at: Instant,
+    observation: Observation,
+}
+
+impl WindowObserver {
+    /// Observe `window`, capturing it every 200ms. Without
+    /// [`stop_after_stable`](WindowObserver::stop_after_stable) the observer never ends.
+    pub fn new(window: &Window) -> WindowObserver {
+        WindowObserver {
+            window: window.clone(),
+            interval: DEFAULT_OBSERVE_INTERVAL,
+            next_capture_at: Instant::now(),
+            observation: Observation::new(),
+        }
+    }
+
+    /// The time between captures. Defaults to 200ms.
+    pub fn interval(mut self, interval: Duration) -> WindowObserver {
+        self.interval = interval;
+        self
+    }
+
+    /// How captures are compared with the previous one and the baseline. Defaults to an exact
+    /// comparison.
+    pub fn comparison(mut self, comparison: Comparison) -> WindowObserver {
+        self.observation.comparison = comparison;
+        self
+    }
+
+    /// Compare every capture against `baseline`, see [`ObservedFrame::matches_baseline`].
+    pub fn baseline(mut self, baseline: RgbaImage) -> WindowObserver {
+        self.observation.baseline = Some(baseline);
+        self
+    }
+
+    /// Only yield captures that changed, plus the last one when stopping after stable
+    /// captures. Defaults to `false`.
+    pub fn changes_only(mut self, changes_only: bool) -> WindowObserver {
+        self.observation.changes_only = changes_only;
+        self
+    }
+
+    /// End after `frames` captures in a row without changes, yielding the last of them.
+    pub fn stop_after_stable(mut self, frames: u32) -> WindowObserver {
+        self.observation.stop_after_stable = Some(frames);
+        self
+    }
+
+    /// Capture until the observer yields a frame or ends, blocking the calling thread.
+    pub fn next_frame(&mut self) -> XCapResult<Option<ObservedFrame>> {
+        while !self.observation.finished {
+            sleep_until(self.next_capture_at);
+            let timestamp = Instant::now();
+            self.next_capture_at = timestamp + self.interval;
+
+            let image = self.window.capture_image()?;
+            if let Some(frame) = self.observation.observe(image, timestamp) {
+                return Ok(Some(frame));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Capture until the window has not changed for the configured number of stable captures,
+    /// one if unset, and return the last capture.
+    pub fn wait_until_stable(mut self) -> XCapResult<ObservedFrame> {
+        let frames = self.observation.stop_after_stable.unwrap_or(1);
+        self = self.changes_only(true).stop_after_stable(frames);
+
+        let mut last = None;
+        while let Some(frame) = self.next_frame()? {
+            last = Some(frame);
+        }
+
+        // 正常结束时一定输出了最后一帧，没有输出说明之前已经结束
+        last.ok_or_else(|| XCapError::new("Window observer has already ended"))
+    }
+}
+
+impl Iterator for WindowObserver {
+    type Item = XCapResult<ObservedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_tolerance() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 2, Rgba([103, 100, 100, 255]));
+        actual.put_pixel(2, 3, Rgba([100, 98, 100, 255]));
+
+        let diff = Comparison::new().compare(&expected, &actual);
+        assert_eq!(diff.differing_pixels, 2);
+        assert_eq!(diff.compared_pixels, 16);
+        assert_eq!(diff.bounds, Some(Rect::new(1, 2, 2, 2)));
+
+        assert!(!Comparison::new().tolerance(2).matches(&expected, &actual));
+        assert!(Comparison::new().tolerance(3).matches(&expected, &actual));
+        assert!(
+            Comparison::new()
+                .max_diff_ratio(0.125)
+                .matches(&expected, &actual)
+        );
+    }
+
+    #[test]
+    fn test_compare_mask() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(3, 0, Rgba([255, 255, 255, 255]));
+
+        let diff = Comparison::new()
+            .mask(Rect::new(2, 0, 2, 1))
+            .compare(&expected, &actual);
+        assert_eq!(diff.differing_pixels, 0);
+        assert_eq!(diff.compared_pixels, 14);
+        assert_eq!(diff.bounds, None);
+    }
+
+    #[test]
+    fn test_compare_perceptual() {
+        let expected = RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255]));
+        let slightly_off = RgbaImage::from_pixel(1, 1, Rgba([250, 250, 250, 255]));
+        let black = RgbaImage::from_pixel(1, 1, Rgba([0, 0, 0, 255]));
+        // 完全透明的像素和白色背景没有区别
+        let transparent = RgbaImage::from_pixel(1, 1, Rgba([0, 0, 0, 0]));
+
+        let comparison = Comparison::new().perceptual(0.1);
+        assert!(comparison.matches(&expected, &slightly_off));
+        assert!(!comparison.matches(&expected, &black));
+        assert!(comparison.matches(&expected, &transparent));
+    }
+
+    #[test]
+    #[should_panic(expected = "images differ in size")]
+    fn test_assert_matches_size() {
+        Comparison::new().assert_matches(&RgbaImage::new(2, 2), &RgbaImage::new(2, 3));
+    }
+
+    #[test]
+    fn test_observation() {
+        let mut observation = Observation::new();
+        observation.changes_only = true;
+        observation.stop_after_stable = Some(2);
+        observation.baseline = Some(RgbaImage::from_pixel(1, 1, Rgba([2, 2, 2, 255])));
+
+        let now = Instant::now();
+        let image = |value| RgbaImage::from_pixel(1, 1, Rgba([value, value, value, 255]));
+
+        let first = observation.observe(image(1), now).unwrap();
+        assert!(first.changed);
+        assert_eq!(first.matches_baseline, Some(false));
+
+        let second = observation.observe(image(2), now).unwrap();
+        assert!(second.changed);
+        assert_eq!(second.matches_baseline, Some(true));
+
+        // 没有变化的帧不输出，直到达到稳定帧数
+        assert!(observation.observe(image(2), now).is_none());
+        assert!(!observation.finished);
+        let last = observation.observe(image(2), now).unwrap();
+        assert!(!last.changed);
+        assert_eq!(last.stable_frames, 2);
+        assert!(observation.finished);
+    }
+}
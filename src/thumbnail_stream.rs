@@ -0,0 +1,283 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use image::RgbaImage;
+
+use crate::{
+    Monitor, Window,
+    error::{XCapError, XCapResult},
+};
+
+const DEFAULT_MAX_SIZE: u32 = 320;
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+// 等待下一次刷新时检查是否已经停止的间隔
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 按比例缩小到不超过 max_size x max_size，不放大
+pub(crate) fn thumbnail_size(width: u32, height: u32, max_size: u32) -> (u32, u32) {
+    if width <= max_size && height <= max_size {
+        return (width, height);
+    }
+
+    let scale = max_size as f64 / width.max(height) as f64;
+    let scaled = |value: u32| ((value as f64 * scale).round() as u32).clamp(1, max_size);
+
+    (scaled(width), scaled(height))
+}
+
+/// What a [`Thumbnail`] shows, identified by id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ThumbnailSource {
+    /// The monitor with the id, see [`Monitor::id`].
+    Monitor(u32),
+    /// The window with the id, see [`Window::id`].
+    Window(u32),
+}
+
+impl ThumbnailSource {
+    /// Previews of `monitor`.
+    pub fn monitor(monitor: &Monitor) -> XCapResult<ThumbnailSource> {
+        Ok(ThumbnailSource::Monitor(monitor.id()?))
+    }
+
+    /// Previews of `window`.
+    pub fn window(window: &Window) -> XCapResult<ThumbnailSource> {
+        Ok(ThumbnailSource::Window(window.id()?))
+    }
+}
+
+/// A preview sent by a [`ThumbnailStream`].
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    /// What the preview shows.
+    pub source: ThumbnailSource,
+    /// The preview, at most `max_size` pixels wide and high.
+    pub image: RgbaImage,
+    /// When the preview was captured.
+    pub timestamp: Instant,
+}
+
+/// 刷新时按 id 查找的截图目标，Windows 上 HWND 不能跨线程传递，在线程中创建
+enum Target {
+    Monitor(Monitor),
+    Window(Window),
+}
+
+impl Target {
+    fn find(source: ThumbnailSource) -> XCapResult<Target> {
+        match source {
+            ThumbnailSource::Monitor(id) => Monitor::all()?
+                .into_iter()
+                .find(|monitor| monitor.id().ok() == Some(id))
+                .map(Target::Monitor)
+                .ok_or_else(|| XCapError::new(format!("Monitor {id} not found"))),
+            ThumbnailSource::Window(id) => Window::from_id(id).map(Target::Window),
+        }
+    }
+
+    fn capture(&self, max_size: u32) -> XCapResult<RgbaImage> {
+        match self {
+            Target::Monitor(monitor) => monitor.capture_thumbnail(max_size),
+            Target::Window(window) => window.capture_thumbnail(max_size),
+        }
+    }
+}
+
+/// 刷新所有缩略图，接收端已经断开时返回 false
+fn refresh(
+    targets: &mut [(ThumbnailSource, Option<Target>)],
+    max_size: u32,
+    tx: &SyncSender<Thumbnail>,
+) -> bool {
+    for (source, target) in targets.iter_mut() {
+        if target.is_none() {
+            *target = Target::find(*source).ok();
+        }
+
+        let image = match target.as_ref().map(|target| target.capture(max_size)) {
+            Some(Ok(image)) => image,
+            Some(Err(err)) => {
+                log::debug!("capture thumbnail of {source:?} failed: {err:?}");
+                // 窗口可能已经关闭或显示器已经断开，下次重新查找
+                *target = None;
+                continue;
+            }
+            None => continue,
+        };
+
+        let thumbnail = Thumbnail {
+            source: *source,
+            image,
+            timestamp: Instant::now(),
+        };
+
+        // 接收端没有及时取走时丢弃，下次刷新会发送更新的缩略图
+        if let Err(TrySendError::Disconnected(_)) = tx.try_send(thumbnail) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Builds a [`ThumbnailStream`], see [`ThumbnailStream::builder`].
+#[derive(Debug)]
+pub struct ThumbnailStreamBuilder {
+    sources: Vec<ThumbnailSource>,
+    max_size: u32,
+    interval: Duration,
+}
+
+impl ThumbnailStreamBuilder {
+    /// Add a monitor or window to preview.
+    pub fn source(mut self, source: ThumbnailSource) -> ThumbnailStreamBuilder {
+        self.sources.push(source);
+        self
+    }
+
+    /// The largest width and height of the previews in pixels. Defaults to 320.
+    pub fn max_size(mut self, max_size: u32) -> ThumbnailStreamBuilder {
+        self.max_size = max_size;
+        self
+    }
+
+    /// How often every preview is refreshed. Defaults to one second.
+    pub fn interval(mut self, interval: Duration) -> ThumbnailStreamBuilder {
+        self.interval = interval;
+        self
+    }
+
+    /// Start refreshing, returning the stream and the receiver of the previews.
+    pub fn build(self) -> XCapResult<(ThumbnailStream, Receiver<Thumbnail>)> {
+        if self.sources.is_empty() {
+            return Err(XCapError::new("Thumbnail stream has no sources"));
+        }
+        if self.max_size == 0 {
+            return Err(XCapError::new("Thumbnail max size must not be zero"));
+        }
+
+        let ThumbnailStreamBuilder {
+            sources,
+            max_size,
+            interval,
+        } = self;
+
+        // 每个来源最多缓存一张缩略图
+        let (tx, rx) = mpsc::sync_channel(sources.len());
+        let stopped = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let stopped = stopped.clone();
+            thread::spawn(move || {
+                let mut targets: Vec<(ThumbnailSource, Option<Target>)> =
+                    sources.into_iter().map(|source| (source, None)).collect();
+
+                while !stopped.load(Ordering::Relaxed) {
+                    let started_at = Instant::now();
+                    if !refresh(&mut targets, max_size, &tx) {
+                        break;
+                    }
+
+                    while !stopped.load(Ordering::Relaxed) {
+                        let remaining = interval.saturating_sub(started_at.elapsed());
+                        if remaining.is_zero() {
+                            break;
+                        }
+
+                        thread::sleep(remaining.min(STOP_CHECK_INTERVAL));
+                    }
+                }
+            })
+        };
+
+        Ok((
+            ThumbnailStream {
+                stopped,
+                worker: Some(worker),
+            },
+            rx,
+        ))
+    }
+}
+
+/// Sends small, periodically refreshed previews of a set of monitors and windows, e.g. for a
+/// picker UI, instead of capturing each of them in full.
+///
+/// Monitors are scaled by the system where possible: GDI scales during the copy on Windows
+/// (DWM thumbnails can only be drawn into a window, not read back) and ScreenCaptureKit
+/// scales on macOS. Windows, and monitors on Linux, are captured in full and downscaled.
+///
+/// Previews the receiver has not taken yet when the next one is ready are dropped. Dropping
+/// the stream stops its thread.
+#[derive(Debug)]
+pub struct ThumbnailStream {
+    stopped: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ThumbnailStream {
+    /// Preview the sources added to the builder.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use xcap::{ThumbnailSource, ThumbnailStream, Window};
+    ///
+    /// let mut builder = ThumbnailStream::builder().interval(Duration::from_millis(500));
+    /// for window in Window::all().unwrap() {
+    ///     builder = builder.source(ThumbnailSource::window(&window).unwrap());
+    /// }
+    ///
+    /// let (_stream, rx) = builder.build().unwrap();
+    /// for thumbnail in rx.iter().take(10) {
+    ///     println!("{:?} {:?}", thumbnail.source, thumbnail.image.dimensions());
+    /// }
+    /// ```
+    pub fn builder() -> ThumbnailStreamBuilder {
+        ThumbnailStreamBuilder {
+            sources: Vec::new(),
+            max_size: DEFAULT_MAX_SIZE,
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+
+    /// Stop refreshing and wait for the stream thread to exit.
+    pub fn stop(mut self) {
+        self.stop_worker();
+    }
+
+    fn stop_worker(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for ThumbnailStream {
+    fn drop(&mut self) {
+        self.stop_worker();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_size() {
+        assert_eq!(thumbnail_size(1920, 1080, 320), (320, 180));
+        assert_eq!(thumbnail_size(1080, 1920, 320), (180, 320));
+        // 不放大小于 max_size 的图像
+        assert_eq!(thumbnail_size(200, 100, 320), (200, 100));
+        // 极窄的窗口至少保留 1 像素
+        assert_eq!(thumbnail_size(4000, 2, 320), (320, 1));
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{XCapResult, platform::impl_window::ImplWindow};
+
+/// The focused window as reported by [`Window::watch_active_title`](crate::Window::watch_active_title).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActiveTitle {
+    /// The application name.
+    pub app_name: String,
+    /// The application process id.
+    pub pid: i32,
+    /// The focused window's title.
+    pub title: String,
+}
+
+/// Watches the focused window's title, see [`Window::watch_active_title`](crate::Window::watch_active_title).
+///
+/// Dropping the watcher stops its thread.
+#[derive(Debug)]
+pub struct TitleWatcher {
+    stopped: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl TitleWatcher {
+    pub(crate) fn new(interval: Duration) -> XCapResult<(TitleWatcher, Receiver<ActiveTitle>)> {
+        let (tx, rx) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let worker = {
+            let stopped = stopped.clone();
+            thread::spawn(move || {
+                let mut last = None;
+
+                while !stopped.load(Ordering::Relaxed) {
+                    // 切换窗口的瞬间可能没有前台窗口，跳过这一次即可
+                    match ImplWindow::get_active_title() {
+                        Ok((app_name, pid, title)) => {
+                            let active_title = ActiveTitle {
+                                app_name,
+                                pid,
+                                title,
+                            };
+
+                            if last.as_ref() != Some(&active_title) {
+                                last = Some(active_title.clone());
+                                // 接收端已经释放
+                                if tx.send(active_title).is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                        Err(err) => log::debug!("get_active_title failed: {err:?}"),
+                    }
+
+                    thread::sleep(interval);
+                }
+            })
+        };
+
+        Ok((
+            TitleWatcher {
+                stopped,
+                worker: Some(worker),
+            },
+            rx,
+        ))
+    }
+
+    /// Stop watching and wait for the watcher thread to exit.
+    pub fn stop(mut self) {
+        self.stop_worker();
+    }
+
+    fn stop_worker(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for TitleWatcher {
+    fn drop(&mut self) {
+        self.stop_worker();
+    }
+}
@@ -1,24 +1,566 @@
-use std::sync::{Condvar, Mutex};
+#[cfg(any(target_os = "macos", target_os = "windows", test))]
+use std::sync::mpsc::SyncSender;
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+use std::thread::JoinHandle;
+use std::{
+    fmt,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-use crate::{XCapResult, platform::impl_video_recorder::ImplVideoRecorder};
+#[cfg(any(target_os = "linux", target_os = "windows", test))]
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
 
+use crate::{
+    Monitor, RecorderConfig, RecorderUpdate, XCapError, XCapResult,
+    annotation::Annotation,
+    capture_session::SharedRecorder,
+    clock,
+    platform::{
+        external_encoder_surface::{self, ExternalEncoderSurface},
+        impl_wake_lock::ImplWakeLock,
+    },
+};
+
+/// The pixel layout of a [`Frame`]'s planes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PixelFormat {
+    /// 8-bit RGBA in a single plane.
+    Rgba8,
+}
+
+/// One plane of a [`Frame`], stored as rows of [`Plane::stride`] bytes.
+#[derive(Debug, Clone)]
+pub struct Plane {
+    data: Vec<u8>,
+    stride: usize,
+}
+
+impl Plane {
+    /// The plane's bytes, including any padding at the end of each row.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    /// Bytes per row, which may be larger than the row's pixel data.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+}
+
+/// A frame delivered by a [`VideoRecorder`].
 #[derive(Debug, Clone)]
 pub struct Frame {
-    pub width: u32,
-    pub height: u32,
-    pub raw: Vec<u8>,
+    format: PixelFormat,
+    width: u32,
+    height: u32,
+    planes: Vec<Plane>,
+    timestamp: Instant,
+    pub(crate) annotations: Vec<Annotation>,
+    #[cfg(target_os = "macos")]
+    pub(crate) native: Option<crate::platform::frame_ext::NativeBuffers>,
+    #[cfg(target_os = "windows")]
+    pub(crate) encoder_surface: Option<ExternalEncoderSurface>,
+    #[cfg(all(target_os = "linux", feature = "egl"))]
+    pub(crate) dma_buf: Option<crate::platform::frame_ext::DmaBuf>,
 }
 
 impl Frame {
+    /// A frame of tightly packed RGBA pixels, stamped with the current time.
     pub fn new(width: u32, height: u32, raw: Vec<u8>) -> Self {
-        Self { width, height, raw }
+        Self::with_stride(width, height, width as usize * 4, raw, Instant::now())
+    }
+
+    /// 单平面的 RGBA 帧，每行 stride 字节，时间戳使用平台提供的采集时间
+    pub(crate) fn with_stride(
+        width: u32,
+        height: u32,
+        stride: usize,
+        data: Vec<u8>,
+        timestamp: Instant,
+    ) -> Self {
+        Self {
+            format: PixelFormat::Rgba8,
+            width,
+            height,
+            planes: vec![Plane { data, stride }],
+            timestamp,
+            annotations: Vec::new(),
+            #[cfg(target_os = "macos")]
+            native: None,
+            #[cfg(target_os = "windows")]
+            encoder_surface: None,
+            #[cfg(all(target_os = "linux", feature = "egl"))]
+            dma_buf: None,
+        }
+    }
+
+    /// The pixel format of [`Frame::planes`].
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+    /// The frame pixel width.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    /// The frame pixel height.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// Bytes per row of the first plane.
+    pub fn stride(&self) -> usize {
+        self.planes[0].stride
+    }
+    /// The frame's planes, one for [`PixelFormat::Rgba8`].
+    pub fn planes(&self) -> &[Plane] {
+        &self.planes
+    }
+    /// The bytes of the first plane.
+    pub fn data(&self) -> &[u8] {
+        &self.planes[0].data
+    }
+    /// When the frame was captured, on the same timeline as [`crate::clock`].
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+    /// The markers added with [`VideoRecorder::annotate`] since the previous frame delivered to
+    /// this receiver, oldest first. Each keeps its own timestamp, which is at or before the
+    /// frame's.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+    /// The GPU surface the frame was captured into, for encoders that consume it without a copy
+    /// to system memory. `None` on Linux, for frames not produced by a recorder, and on Windows
+    /// unless [`RecorderConfig::encoder_surface`](crate::RecorderConfig::encoder_surface) is
+    /// enabled. See [`ExternalEncoderSurface`] for the synchronization contract.
+    pub fn encoder_surface(&self) -> Option<ExternalEncoderSurface> {
+        external_encoder_surface::encoder_surface(self)
+    }
+    /// 第一个平面的可写视图
+    pub(crate) fn view_mut(&mut self) -> FrameView<'_> {
+        let plane = &mut self.planes[0];
+        FrameView {
+            width: self.width,
+            height: self.height,
+            stride: plane.stride,
+            data: &mut plane.data,
+            timestamp: self.timestamp,
+        }
+    }
+    /// 取出第一个平面的数据，不复制
+    pub(crate) fn into_data(mut self) -> Vec<u8> {
+        self.planes.swap_remove(0).data
+    }
+    /// Copy the frame into an [`RgbaImage`], dropping row padding. Returns `None` if the frame
+    /// is not [`PixelFormat::Rgba8`] or its data is too short.
+    pub fn to_rgba_image(&self) -> Option<RgbaImage> {
+        if self.format != PixelFormat::Rgba8 {
+            return None;
+        }
+
+        let row_len = self.width as usize * 4;
+        let mut raw = Vec::with_capacity(row_len * self.height as usize);
+        for row in self
+            .data()
+            .chunks(self.stride().max(row_len))
+            .take(self.height as usize)
+        {
+            raw.extend_from_slice(row.get(..row_len)?);
+        }
+
+        RgbaImage::from_raw(self.width, self.height, raw)
+    }
+}
+
+/// Mutable access to a frame's pixels, passed to the hook set with
+/// [`RecorderConfig::frame_hook`](crate::RecorderConfig::frame_hook).
+#[derive(Debug)]
+pub struct FrameView<'a> {
+    width: u32,
+    height: u32,
+    stride: usize,
+    data: &'a mut [u8],
+    timestamp: Instant,
+}
+
+impl<'a> FrameView<'a> {
+    /// 紧密排列的 RGBA 图像的可写视图，截图和帧共用像素处理
+    pub(crate) fn from_image(image: &'a mut RgbaImage) -> FrameView<'a> {
+        let (width, height) = image.dimensions();
+        FrameView {
+            width,
+            height,
+            stride: width as usize * 4,
+            data: image,
+            timestamp: Instant::now(),
+        }
+    }
+    /// The frame pixel width.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    /// The frame pixel height.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// Bytes per row, which may be larger than the row's pixel data.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+    /// When the frame was captured, see [`Frame::timestamp`].
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+    /// The frame's RGBA bytes, including any padding at the end of each row.
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+    /// The RGBA bytes of row `y` without padding, `None` if `y` is out of bounds.
+    pub fn row_mut(&mut self, y: u32) -> Option<&mut [u8]> {
+        if y >= self.height {
+            return None;
+        }
+
+        let start = y as usize * self.stride;
+        self.data.get_mut(start..start + self.width as usize * 4)
+    }
+    /// Set the pixel at (`x`, `y`). Pixels outside the frame are ignored, so stamps near the
+    /// edges are clipped.
+    pub fn put_pixel(&mut self, x: u32, y: u32, pixel: Rgba<u8>) {
+        if x >= self.width {
+            return;
+        }
+
+        if let Some(row) = self.row_mut(y) {
+            let offset = x as usize * 4;
+            row[offset..offset + 4].copy_from_slice(&pixel.0);
+        }
+    }
+}
+
+type FrameHookFn = dyn FnMut(&mut FrameView) + Send;
+
+/// 用户设置的帧处理回调，各平台在像素转换完成、帧发送之前调用
+#[derive(Clone)]
+pub(crate) struct FrameHook(Arc<Mutex<Box<FrameHookFn>>>);
+
+impl fmt::Debug for FrameHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameHook").finish_non_exhaustive()
+    }
+}
+
+impl FrameHook {
+    pub fn new<F>(hook: F) -> Self
+    where
+        F: FnMut(&mut FrameView) + Send + 'static,
+    {
+        FrameHook(Arc::new(Mutex::new(Box::new(hook))))
+    }
+
+    // Android 没有录制器，不会调用回调
+    #[cfg_attr(target_os = "android", allow(dead_code))]
+    pub fn apply(&self, frame: &mut Frame) {
+        // 回调 panic 后锁会中毒，之后的帧不再处理
+        let Ok(mut hook) = self.0.lock() else {
+            return;
+        };
+
+        hook(&mut frame.view_mut());
+    }
+}
+
+/// Events emitted by a video recorder on its side channel, see [`VideoRecorder::events`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecorderEvent {
+    /// Periodic health statistics, emitted every [`RecorderConfig::stats_interval`](crate::RecorderConfig::stats_interval).
+    Stats {
+        /// Frames delivered per second since the previous report.
+        achieved_fps: f32,
+        /// Frames captured by the backend that are still waiting to be handed to the receiver.
+        queue_depth: usize,
+        /// Total frames dropped since the recorder was created.
+        dropped: u64,
+    },
+    /// The monitor's resolution or layout changed while recording.
+    DisplayReconfigured,
+    /// The platform stream was re-created after a failure.
+    StreamRestarted,
+    /// The platform stream delivered no frames for `idle` although the display content changed,
+    /// and is restarted, followed by [`RecorderEvent::StreamRestarted`]. A static display is not
+    /// reported as stalled.
+    Stalled {
+        /// How long the stream was without frames.
+        idle: Duration,
+    },
+    /// The platform stream failed.
+    Error(String),
+    /// Capture paused because the display went to sleep, the screensaver started or the session
+    /// was locked, see [`RecorderConfig::pause_when_idle`](crate::RecorderConfig::pause_when_idle).
+    Paused,
+    /// Capture resumed after [`RecorderEvent::Paused`].
+    Resumed,
+    /// Settings changed by [`VideoRecorder::reconfigure`] took effect. Emitted once per stream,
+    /// right before the first frame captured with the new settings is sent; frames from
+    /// `timestamp` on use them.
+    Reconfigured {
+        /// The [`Frame::timestamp`] of the first frame with the new settings.
+        timestamp: Instant,
+    },
+}
+
+// 统计上报的最短间隔，避免间隔为 0 时计时线程空转
+const MIN_STATS_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 录制状态统计，各平台在发送帧时更新，由计时线程按间隔通过事件通道上报，
+/// 录制卡住没有帧时也会上报
+#[derive(Debug)]
+pub(crate) struct RecorderHealth {
+    events: Mutex<Option<Sender<RecorderEvent>>>,
+    // 释放时断开通道，通知计时线程退出
+    stats_timer: Mutex<Option<Sender<()>>>,
+    stats_interval: Duration,
+    // (上次上报时间, 上次上报后发送的帧数)
+    stats_window: Mutex<(Instant, u64)>,
+    // 已经发送但接收端还没有取走的帧数，通道不缓存帧，即阻塞在发送中的帧
+    queue_depth: AtomicUsize,
+    dropped: AtomicU64,
+}
+
+impl RecorderHealth {
+    pub fn new(stats_interval: Duration) -> Self {
+        Self {
+            events: Mutex::new(None),
+            stats_timer: Mutex::new(None),
+            stats_interval,
+            stats_window: Mutex::new((Instant::now(), 0)),
+            queue_depth: AtomicUsize::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    pub fn subscribe(self: &Arc<Self>) -> XCapResult<Receiver<RecorderEvent>> {
+        let (tx, rx) = mpsc::channel();
+        *self.events.lock()? = Some(tx);
+        self.start_stats_timer()?;
+
+        Ok(rx)
+    }
+    /// 启动计时线程，替换掉的发送端释放后旧的线程退出，线程只持有弱引用
+    fn start_stats_timer(self: &Arc<Self>) -> XCapResult<()> {
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        *self.stats_timer.lock()? = Some(stop_tx);
+
+        let health = Arc::downgrade(self);
+        let interval = self.stats_interval.max(MIN_STATS_INTERVAL);
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                let Some(health) = health.upgrade() else {
+                    break;
+                };
+                health.report();
+            }
+        });
+
+        Ok(())
+    }
+
+    pub fn emit(&self, event: RecorderEvent) {
+        let Ok(mut events) = self.events.lock() else {
+            return;
+        };
+
+        // 接收端已经释放，不再发送事件
+        if let Some(tx) = events.as_ref()
+            && tx.send(event).is_err()
+        {
+            *events = None;
+        }
+    }
+    /// 发送一帧，send 返回 false 表示帧被丢弃
+    pub fn deliver<F>(&self, send: F) -> bool
+    where
+        F: FnOnce() -> bool,
+    {
+        self.queue_depth.fetch_add(1, Ordering::Relaxed);
+        let is_delivered = send();
+        self.queue_depth.fetch_sub(1, Ordering::Relaxed);
+
+        if is_delivered {
+            if let Ok(mut stats_window) = self.stats_window.lock() {
+                stats_window.1 += 1;
+            }
+        } else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+
+        is_delivered
+    }
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+    pub fn dropped(&self, count: u64) {
+        self.dropped.fetch_add(count, Ordering::Relaxed);
+    }
+    /// 发送统计事件，由计时线程调用
+    fn report(&self) {
+        let stats = {
+            let Ok(mut stats_window) = self.stats_window.lock() else {
+                return;
+            };
+            let elapsed = stats_window.0.elapsed();
+            let achieved_fps = stats_window.1 as f32 / elapsed.as_secs_f32();
+            *stats_window = (Instant::now(), 0);
+
+            RecorderEvent::Stats {
+                achieved_fps,
+                queue_depth: self.queue_depth.load(Ordering::Relaxed),
+                dropped: self.dropped.load(Ordering::Relaxed),
+            }
+        };
+
+        self.emit(stats);
+    }
+}
+
+/// 跳过与上一帧完全相同的帧，用于低功耗模式和去重截图
+#[derive(Debug, Default)]
+pub(crate) struct ChangeDetector {
+    last_hash: Option<u64>,
+}
+
+impl ChangeDetector {
+    pub fn is_changed(&mut self, raw: &[u8]) -> bool {
+        let mut hasher = DefaultHasher::new();
+        raw.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        self.last_hash.replace(hash) != Some(hash)
+    }
+}
+
+/// 平台流连续多个间隔没有产生帧时判定为卡住，由调用方强制重启
+#[cfg(any(target_os = "macos", target_os = "windows", test))]
+#[derive(Debug, Clone)]
+pub(crate) struct Watchdog {
+    interval: Duration,
+    limit: u32,
+    misses: u32,
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", test))]
+impl Watchdog {
+    pub fn new(interval: Duration, limit: u32) -> Watchdog {
+        Watchdog {
+            interval,
+            limit: limit.max(1),
+            misses: 0,
+        }
+    }
+
+    /// 收到了帧
+    pub fn feed(&mut self) {
+        self.misses = 0;
+    }
+
+    /// 等待帧超时，source_updated 表示来源在上一帧之后有新的内容。
+    /// 来源本身没有变化（例如静止的桌面）时没有帧是正常的，不计入卡住
+    #[cfg(any(target_os = "windows", test))]
+    pub fn timeout(&mut self, source_updated: bool) -> Option<Duration> {
+        if !source_updated {
+            self.feed();
+            return None;
+        }
+
+        self.miss()
+    }
+
+    /// 一个间隔内没有收到帧，卡住时返回没有帧的时长并重新计数
+    pub fn miss(&mut self) -> Option<Duration> {
+        self.misses += 1;
+        if self.misses < self.limit {
+            return None;
+        }
+
+        self.misses = 0;
+        Some(self.interval * self.limit)
     }
 }
 
+// 检查显示器是否空闲的间隔
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 显示器休眠、屏保运行或锁屏时暂停录制，状态变化时发出 Paused/Resumed 事件
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+#[derive(Debug)]
+pub(crate) struct IdleGate {
+    enabled: bool,
+    // (上次检查时间, 是否空闲)
+    state: Mutex<Option<(Instant, bool)>>,
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+impl IdleGate {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            state: Mutex::new(None),
+        }
+    }
+    /// 返回是否应该暂停录制，probe 检查显示器是否空闲，按间隔调用避免频繁查询系统状态
+    pub fn poll<F>(&self, probe: F, health: &RecorderHealth) -> bool
+    where
+        F: FnOnce() -> bool,
+    {
+        if !self.enabled {
+            return false;
+        }
+
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+
+        let was_idle = match *state {
+            Some((checked_at, is_idle)) if checked_at.elapsed() < IDLE_POLL_INTERVAL => {
+                return is_idle;
+            }
+            Some((_, is_idle)) => is_idle,
+            None => false,
+        };
+
+        let is_idle = probe();
+        *state = Some((Instant::now(), is_idle));
+
+        if is_idle != was_idle {
+            health.emit(if is_idle {
+                RecorderEvent::Paused
+            } else {
+                RecorderEvent::Resumed
+            });
+        }
+
+        is_idle
+    }
+}
+
+#[derive(Debug)]
+struct WakerState {
+    parking: bool,
+    shutdown: bool,
+    // 工作线程是否停在 wait 中，或者已经退出
+    idle: bool,
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub(crate) struct RecorderWaker {
-    parking: Mutex<bool>,
+    state: Mutex<WakerState>,
     condvar: Condvar,
 }
 
@@ -26,54 +568,870 @@ impl RecorderWaker {
     #[allow(dead_code)]
     pub fn new() -> Self {
         Self {
-            parking: Mutex::new(true),
+            state: Mutex::new(WakerState {
+                parking: true,
+                shutdown: false,
+                idle: true,
+            }),
             condvar: Condvar::new(),
         }
     }
     #[allow(dead_code)]
     pub fn wake(&self) -> XCapResult<()> {
-        let mut parking = self.parking.lock()?;
-        *parking = false;
-        self.condvar.notify_one();
+        let mut state = self.state.lock()?;
+        state.parking = false;
+        self.condvar.notify_all();
 
         Ok(())
     }
     #[allow(dead_code)]
     pub fn sleep(&self) -> XCapResult<()> {
-        let mut parking = self.parking.lock()?;
-        *parking = true;
+        let mut state = self.state.lock()?;
+        state.parking = true;
+        self.condvar.notify_all();
 
         Ok(())
     }
+    /// 通知工作线程退出，wait 之后会返回 false
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+    pub fn shutdown(&self) -> XCapResult<()> {
+        let mut state = self.state.lock()?;
+        state.shutdown = true;
+        self.condvar.notify_all();
+
+        Ok(())
+    }
+    /// 暂停或关闭时，正在发送的帧需要丢弃
+    #[cfg(any(target_os = "macos", target_os = "windows", test))]
+    pub fn is_parking(&self) -> bool {
+        self.state
+            .lock()
+            .map(|state| state.parking || state.shutdown)
+            .unwrap_or(true)
+    }
+    /// 工作线程调用，阻塞到被唤醒，返回 false 表示录制器已经关闭
     #[allow(dead_code)]
-    pub fn wait(&self) -> XCapResult<()> {
-        let mut parking = self.parking.lock()?;
-        while *parking {
-            parking = self.condvar.wait(parking)?;
+    pub fn wait(&self) -> XCapResult<bool> {
+        let mut state = self.state.lock()?;
+        state.idle = true;
+        self.condvar.notify_all();
+
+        while state.parking && !state.shutdown {
+            state = self.condvar.wait(state)?;
+        }
+
+        if state.shutdown {
+            return Ok(false);
+        }
+
+        state.idle = false;
+
+        Ok(true)
+    }
+    /// 工作线程调用，等待到 deadline，期间被暂停或关闭时提前返回 false
+    #[cfg(any(target_os = "linux", target_os = "windows", test))]
+    pub fn wait_until(&self, deadline: Instant) -> XCapResult<bool> {
+        let mut state = self.state.lock()?;
+        loop {
+            if state.parking || state.shutdown {
+                return Ok(false);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(true);
+            }
+
+            state = self.condvar.wait_timeout(state, deadline - now)?.0;
+        }
+    }
+    /// 等待工作线程处理完当前帧并停下来
+    #[cfg(any(target_os = "linux", target_os = "windows", test))]
+    pub fn wait_idle(&self) -> XCapResult<()> {
+        let mut state = self.state.lock()?;
+        while !state.idle {
+            state = self.condvar.wait(state)?;
         }
 
         Ok(())
     }
+    /// 工作线程退出时调用，包括出错提前返回的情况，避免 wait_idle 一直等待
+    #[cfg(any(target_os = "linux", target_os = "windows", test))]
+    pub fn exit(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.idle = true;
+            self.condvar.notify_all();
+        }
+    }
+    /// 发送一帧，暂停或关闭时直接丢弃
+    ///
+    /// 平台录制器的接收端是 capture_session 中分发帧的线程，它只用 try_send 转发给用户，
+    /// 不会因为用户没有取走帧而阻塞，所以这里阻塞发送只会等到它处理完上一帧。
+    /// 分发线程退出时接收端被释放，send 返回 false
+    #[cfg(any(target_os = "macos", target_os = "windows", test))]
+    pub fn send<T>(&self, tx: &SyncSender<T>, value: T) -> bool {
+        if self.is_parking() {
+            return false;
+        }
+
+        tx.send(value).is_ok()
+    }
+}
+
+/// 工作线程持有，线程退出时自动调用 [`RecorderWaker::exit`]
+#[cfg(any(target_os = "linux", target_os = "windows", test))]
+pub(crate) struct WorkerGuard(pub Arc<RecorderWaker>);
+
+#[cfg(any(target_os = "linux", target_os = "windows", test))]
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        self.0.exit();
+    }
+}
+
+/// 等待工作线程结束
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+pub(crate) fn join_worker(worker: &Mutex<Option<JoinHandle<XCapResult<()>>>>) -> XCapResult<()> {
+    let Some(handle) = worker.lock()?.take() else {
+        return Ok(());
+    };
+
+    // 在工作线程内部释放录制器时不能 join 自己
+    if handle.thread().id() == thread::current().id() {
+        return Ok(());
+    }
+
+    handle
+        .join()
+        .map_err(|_| XCapError::new("Recorder worker thread panicked"))?
 }
 
+/// 按 vsync 输出时使用的刷新率。可变刷新率的显示器按最高刷新率计算，
+/// 刷新间隔变长时每个周期仍然最多输出一帧，不会因为按当前刷新率计时而丢帧
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub(crate) fn vsync_frequency(monitor: &Monitor) -> XCapResult<f32> {
+    match monitor.refresh_rate_range() {
+        Ok(range) if range.max > 0.0 => Ok(range.max),
+        _ => monitor.frequency(),
+    }
+}
+
+/// 按显示器刷新率计时的帧节拍器，用于无法获取 vsync 信号的后端
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+#[derive(Debug)]
+pub(crate) struct FramePacer {
+    interval: Duration,
+    next_frame_at: Instant,
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+impl FramePacer {
+    #[cfg(target_os = "linux")]
+    pub fn new(frequency: f32) -> Self {
+        // 部分显示器（如内建屏幕）返回的刷新率为 0，按 60Hz 处理
+        let frequency = if frequency > 0.0 { frequency } else { 60.0 };
+
+        Self::with_interval(Duration::from_secs_f32(1.0 / frequency))
+    }
+    /// 按固定间隔计时，用于 max_fps 和延时摄影
+    pub fn with_interval(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_frame_at: Instant::now(),
+        }
+    }
+    /// 等待到下一个周期，如果已经错过了若干周期，则对齐到最近的下一个周期。
+    /// 延时摄影的间隔可能很长，等待期间录制器暂停或关闭时提前返回 false
+    pub fn wait(&mut self, recorder_waker: &RecorderWaker) -> XCapResult<bool> {
+        if !recorder_waker.wait_until(self.next_frame_at)? {
+            return Ok(false);
+        }
+
+        let now = Instant::now();
+        while self.next_frame_at <= now {
+            self.next_frame_at += self.interval;
+        }
+
+        Ok(true)
+    }
+}
+
+/// 按最短间隔丢弃帧，用于只能被动接收帧的后端
+#[cfg(any(target_os = "linux", test))]
+#[derive(Debug)]
+pub(crate) struct FrameThrottle {
+    interval: Duration,
+    next_frame_at: Option<Instant>,
+}
+
+#[cfg(any(target_os = "linux", test))]
+impl FrameThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_frame_at: None,
+        }
+    }
+    /// 到达下一个周期时返回 true，落后超过一个周期时从当前时间重新计时
+    pub fn accept(&mut self, now: Instant) -> bool {
+        if self
+            .next_frame_at
+            .is_some_and(|next_frame_at| now < next_frame_at)
+        {
+            return false;
+        }
+
+        let next_frame_at = self.next_frame_at.unwrap_or(now) + self.interval;
+        self.next_frame_at = Some(if next_frame_at > now {
+            next_frame_at
+        } else {
+            now + self.interval
+        });
+
+        true
+    }
+}
+
+/// 录制过程中可以修改的设置，由 VideoRecorder::reconfigure 写入，工作线程在处理帧之前检查
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+#[derive(Debug)]
+pub(crate) struct LiveConfig {
+    config: Mutex<RecorderConfig>,
+    version: AtomicU64,
+    // 等待发出 Reconfigured 事件的切换点，内层为 None 时下一帧就使用新设置
+    switched_at: Mutex<Option<Option<Instant>>>,
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+impl LiveConfig {
+    pub fn new(config: RecorderConfig) -> Self {
+        Self {
+            config: Mutex::new(config),
+            version: AtomicU64::new(0),
+            switched_at: Mutex::new(None),
+        }
+    }
+    /// 当前的设置和版本号
+    pub fn get(&self) -> XCapResult<(u64, RecorderConfig)> {
+        let config = self.config.lock()?;
+
+        Ok((self.version.load(Ordering::Acquire), config.clone()))
+    }
+    /// 修改设置，返回修改后的设置
+    pub fn update(&self, update: &RecorderUpdate) -> XCapResult<RecorderConfig> {
+        let mut config = self.config.lock()?;
+        *config = update.apply(config.clone());
+        self.version.fetch_add(1, Ordering::AcqRel);
+
+        Ok(config.clone())
+    }
+    /// 设置在 version 之后被修改过时返回新的设置并更新 version，同时把当前时间记为切换点
+    #[cfg(any(target_os = "linux", target_os = "windows", test))]
+    pub fn poll(&self, version: &mut u64) -> Option<RecorderConfig> {
+        if self.version.load(Ordering::Acquire) == *version {
+            return None;
+        }
+
+        let config = self.config.lock().ok()?;
+        *version = self.version.load(Ordering::Acquire);
+        // 工作线程自己应用设置，之后处理的帧都使用新设置
+        self.mark_switch(None);
+
+        Some(config.clone())
+    }
+    /// 记录切换点，from 之后捕获的第一帧发送前发出 Reconfigured 事件。
+    /// 直接修改平台流的后端在修改完成后以当前时间调用，之前捕获的帧可能还在队列中
+    pub fn mark_switch(&self, from: Option<Instant>) {
+        if let Ok(mut switched_at) = self.switched_at.lock() {
+            *switched_at = Some(from);
+        }
+    }
+    /// 在发送帧之前调用
+    pub fn emit_marker(&self, timestamp: Instant, health: &RecorderHealth) {
+        let Ok(mut switched_at) = self.switched_at.lock() else {
+            return;
+        };
+
+        if let Some(from) = *switched_at
+            && from.is_none_or(|from| timestamp >= from)
+        {
+            *switched_at = None;
+            health.emit(RecorderEvent::Reconfigured { timestamp });
+        }
+    }
+}
+
+/// 按比例缩小帧，用于不能直接输出缩放画面的后端
+#[cfg(any(target_os = "linux", target_os = "windows", test))]
+pub(crate) fn scale_frame(frame: Frame, scale: f32) -> Frame {
+    let width = ((frame.width as f32 * scale).round() as u32).max(1);
+    let height = ((frame.height as f32 * scale).round() as u32).max(1);
+    let Some(image) = frame.to_rgba_image() else {
+        return frame;
+    };
+
+    let image = imageops::resize(&image, width, height, FilterType::Triangle);
+    Frame::with_stride(
+        width,
+        height,
+        width as usize * 4,
+        image.into_raw(),
+        frame.timestamp,
+    )
+}
+
+/// 录制期间阻止显示器休眠，启用并且正在录制时持有 wake lock
+#[derive(Debug, Default)]
+struct DisplayAwake {
+    enabled: bool,
+    running: bool,
+    wake_lock: Option<ImplWakeLock>,
+}
+
+impl DisplayAwake {
+    fn update(&mut self) -> XCapResult<()> {
+        if !self.enabled || !self.running {
+            self.wake_lock = None;
+        } else if self.wake_lock.is_none() {
+            self.wake_lock = Some(ImplWakeLock::new()?);
+        }
+
+        Ok(())
+    }
+}
+
+// 所有克隆共享同一组平台录制器，最后一个被释放时关闭
+#[derive(Debug)]
+struct RecorderHandle {
+    shared_recorders: Vec<SharedRecorder>,
+    display_awake: Mutex<DisplayAwake>,
+}
+
+impl RecorderHandle {
+    /// 对每个平台录制器执行操作，一个失败时其他的仍然会执行，返回第一个错误
+    fn for_each<F>(&self, f: F) -> XCapResult<()>
+    where
+        F: Fn(&SharedRecorder) -> XCapResult<()>,
+    {
+        let mut result = Ok(());
+        for shared_recorder in &self.shared_recorders {
+            let current = f(shared_recorder);
+            if result.is_ok() {
+                result = current;
+            }
+        }
+
+        result
+    }
+}
+
+impl Drop for RecorderHandle {
+    fn drop(&mut self) {
+        if let Err(err) = self.for_each(SharedRecorder::shutdown) {
+            log::error!("Failed to shut down video recorder: {err:?}");
+        }
+    }
+}
+
+// 还没有开始录制时的起点，所有帧都早于它
+const NOT_STARTED: u64 = u64::MAX;
+
+/// 把平台录制器的帧转发给用户，丢弃共同起点之前的帧
+fn forward_frames(rx: Receiver<Frame>, started_at: Arc<AtomicU64>) -> Receiver<Frame> {
+    // 不缓存帧，接收端没有取走时平台录制器和单显示器录制时一样丢帧
+    let (tx, forwarded_rx) = mpsc::sync_channel(0);
+
+    thread::spawn(move || {
+        // 被丢弃的帧上的标注留给下一帧
+        let mut annotations = Vec::new();
+        for mut frame in rx {
+            if clock::to_nanos(frame.timestamp()) < started_at.load(Ordering::Acquire) {
+                annotations.append(&mut frame.annotations);
+                continue;
+            }
+            if !annotations.is_empty() {
+                annotations.append(&mut frame.annotations);
+                frame.annotations = std::mem::take(&mut annotations);
+            }
+            if tx.send(frame).is_err() {
+                break;
+            }
+        }
+    });
+
+    forwarded_rx
+}
+
+/// Records one or more monitors, see [`Monitor::video_recorder`](crate::Monitor::video_recorder)
+/// and [`VideoRecorder::multi`].
+///
+/// Clones share the same recorder. When the last clone is dropped the platform streams are
+/// stopped and their worker threads are joined; frames already sent stay readable on the
+/// receivers until they are drained.
 #[derive(Debug, Clone)]
 pub struct VideoRecorder {
-    impl_video_recorder: ImplVideoRecorder,
+    handle: Arc<RecorderHandle>,
+    health: Arc<RecorderHealth>,
+    // 最近一次启动的时间，clock::to_nanos 表示
+    started_at: Arc<AtomicU64>,
 }
 
 impl VideoRecorder {
-    pub(crate) fn new(impl_video_recorder: ImplVideoRecorder) -> VideoRecorder {
+    pub(crate) fn new(
+        shared_recorder: SharedRecorder,
+        health: Arc<RecorderHealth>,
+    ) -> VideoRecorder {
         VideoRecorder {
-            impl_video_recorder,
+            handle: Arc::new(RecorderHandle {
+                shared_recorders: vec![shared_recorder],
+                display_awake: Mutex::default(),
+            }),
+            health,
+            started_at: Arc::new(AtomicU64::new(NOT_STARTED)),
         }
     }
+
+    /// Record several monitors at once with the default options, see
+    /// [`VideoRecorder::multi_with_config`].
+    pub fn multi(monitors: &[Monitor]) -> XCapResult<(VideoRecorder, Vec<Receiver<Frame>>)> {
+        VideoRecorder::multi_with_config(monitors, &RecorderConfig::default())
+    }
+
+    /// Record `monitors` with a single recorder, returning one frame receiver per monitor in the
+    /// same order.
+    ///
+    /// Every stream stamps frames on the [`clock`](crate::clock) timeline, so frames of
+    /// different monitors can be matched by [`Frame::timestamp`]. [`VideoRecorder::start`]
+    /// starts all streams and drops the frames captured before the last one was running, so
+    /// every stream begins at [`VideoRecorder::started_at`]. Events and statistics cover all
+    /// monitors.
+    ///
+    /// ```no_run
+    /// use xcap::{Monitor, VideoRecorder};
+    ///
+    /// let monitors = Monitor::all().unwrap();
+    /// let (recorder, receivers) = VideoRecorder::multi(&monitors).unwrap();
+    /// recorder.start().unwrap();
+    ///
+    /// let started_at = recorder.started_at().unwrap();
+    /// for (monitor, rx) in monitors.iter().zip(&receivers) {
+    ///     let frame = rx.recv().unwrap();
+    ///     println!("{:?}: {:?}", monitor.name(), frame.timestamp() - started_at);
+    /// }
+    /// ```
+    pub fn multi_with_config(
+        monitors: &[Monitor],
+        config: &RecorderConfig,
+    ) -> XCapResult<(VideoRecorder, Vec<Receiver<Frame>>)> {
+        if monitors.is_empty() {
+            return Err(XCapError::new("No monitors to record"));
+        }
+
+        let health = Arc::new(RecorderHealth::new(config.stats_interval));
+        let started_at = Arc::new(AtomicU64::new(NOT_STARTED));
+
+        let mut shared_recorders = Vec::with_capacity(monitors.len());
+        let mut receivers = Vec::with_capacity(monitors.len());
+        for monitor in monitors {
+            let (shared_recorder, rx) =
+                SharedRecorder::new(&monitor.impl_monitor, config, health.clone())?;
+            shared_recorders.push(shared_recorder);
+            receivers.push(forward_frames(rx, started_at.clone()));
+        }
+
+        let video_recorder = VideoRecorder {
+            handle: Arc::new(RecorderHandle {
+                shared_recorders,
+                display_awake: Mutex::default(),
+            }),
+            health,
+            started_at,
+        };
+
+        Ok((video_recorder, receivers))
+    }
 }
 
 impl VideoRecorder {
     pub fn start(&self) -> XCapResult<()> {
-        self.impl_video_recorder.start()
+        // 启动期间先到的帧都早于起点，全部启动后才确定起点
+        self.started_at.store(NOT_STARTED, Ordering::Release);
+        self.handle.for_each(SharedRecorder::start)?;
+        self.started_at
+            .store(clock::to_nanos(Instant::now()), Ordering::Release);
+
+        // 无法阻止休眠时录制仍然继续，通过事件通知
+        let mut display_awake = self.handle.display_awake.lock()?;
+        display_awake.running = true;
+        if let Err(err) = display_awake.update() {
+            self.health.emit(RecorderEvent::Error(format!(
+                "Failed to keep the display awake: {err}"
+            )));
+        }
+
+        Ok(())
     }
+    /// Pause recording. Returns once the platform stream has stopped and the frame that was in
+    /// flight has been delivered or discarded, so no frames are sent after this returns.
     pub fn stop(&self) -> XCapResult<()> {
-        self.impl_video_recorder.stop()
+        let result = self.handle.for_each(SharedRecorder::stop);
+
+        let mut display_awake = self.handle.display_awake.lock()?;
+        display_awake.running = false;
+        display_awake.update()?;
+
+        result
+    }
+    /// Prevent the display from dimming or sleeping while the recorder is running, e.g. during
+    /// long recordings without user input. Uses an IOPMAssertion on macOS,
+    /// `SetThreadExecutionState` on Windows and the `org.freedesktop.ScreenSaver` inhibitor on
+    /// Linux. The assertion is held from [`VideoRecorder::start`] until
+    /// [`VideoRecorder::stop`], or until the last clone of the recorder is dropped. Off by
+    /// default.
+    pub fn keep_display_awake(&self, keep_awake: bool) -> XCapResult<()> {
+        let mut display_awake = self.handle.display_awake.lock()?;
+        display_awake.enabled = keep_awake;
+
+        display_awake.update()
+    }
+    /// When the last call to [`VideoRecorder::start`] had every stream running, `None` before
+    /// the first start. Subtract it from [`Frame::timestamp`] to get presentation times that
+    /// line up across monitors.
+    pub fn started_at(&self) -> Option<Instant> {
+        match self.started_at.load(Ordering::Acquire) {
+            NOT_STARTED => None,
+            nanos => Some(clock::from_nanos(nanos)),
+        }
+    }
+    /// Change the frame rate limit, scale or cursor visibility without re-creating the platform
+    /// streams, whether or not the recorder is running. The new settings apply from the next
+    /// captured frame, which is preceded by [`RecorderEvent::Reconfigured`] on
+    /// [`VideoRecorder::events`].
+    pub fn reconfigure(&self, update: &RecorderUpdate) -> XCapResult<()> {
+        self.handle
+            .for_each(|shared_recorder| shared_recorder.reconfigure(update))
+    }
+    /// Subscribe to statistics, error and recovery events. Calling this again replaces the
+    /// previous receiver.
+    pub fn events(&self) -> XCapResult<Receiver<RecorderEvent>> {
+        self.health.subscribe()
+    }
+    /// Attach a marker to the frame stream, e.g. a click reported by a mouse hook, so sinks can
+    /// draw it over the matching frame. The marker is delivered in [`Frame::annotations`] of the
+    /// first frame captured at or after `timestamp`, on every receiver of the recorder. Use
+    /// [`Instant::now`] or the hook's event time converted with [`crate::clock`].
+    ///
+    /// Markers wait while no frames are captured, up to 1024 per receiver, dropping the oldest
+    /// beyond that.
+    ///
+    /// ```no_run
+    /// use std::time::Instant;
+    ///
+    /// use xcap::Monitor;
+    ///
+    /// let monitor = Monitor::all().unwrap().remove(0);
+    /// let (recorder, rx) = monitor.video_recorder().unwrap();
+    /// recorder.start().unwrap();
+    ///
+    /// recorder
+    ///     .annotate(Instant::now(), r#"{"click":[100,200]}"#)
+    ///     .unwrap();
+    ///
+    /// for frame in rx.iter().take(30) {
+    ///     for annotation in frame.annotations() {
+    ///         println!("{:?}", String::from_utf8_lossy(annotation.payload()));
+    ///     }
+    /// }
+    /// ```
+    pub fn annotate(&self, timestamp: Instant, payload: impl Into<Vec<u8>>) -> XCapResult<()> {
+        let payload: Arc<[u8]> = payload.into().into();
+        self.handle
+            .for_each(|shared_recorder| shared_recorder.annotate(timestamp, payload.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_to_rgba_image_with_stride() {
+        // 2x2 像素，每行有 4 字节填充
+        let data = vec![
+            1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, //
+            3, 3, 3, 3, 4, 4, 4, 4, 0, 0, 0, 0,
+        ];
+        let frame = Frame::with_stride(2, 2, 12, data, Instant::now());
+
+        assert_eq!(frame.format(), PixelFormat::Rgba8);
+        assert_eq!(frame.stride(), 12);
+        assert_eq!(frame.planes().len(), 1);
+
+        let image = frame.to_rgba_image().unwrap();
+        assert_eq!(image.get_pixel(1, 0).0, [2, 2, 2, 2]);
+        assert_eq!(image.get_pixel(0, 1).0, [3, 3, 3, 3]);
+        assert_eq!(image.into_raw().len(), 16);
+    }
+
+    #[test]
+    fn test_forward_frames_from_start() {
+        let (tx, rx) = mpsc::channel();
+        let started_at = Arc::new(AtomicU64::new(NOT_STARTED));
+        let forwarded_rx = forward_frames(rx, started_at.clone());
+
+        let before = Instant::now();
+        let start = before + Duration::from_millis(10);
+        started_at.store(clock::to_nanos(start), Ordering::Release);
+
+        // 起点之前的帧被丢弃
+        tx.send(Frame::with_stride(1, 1, 4, vec![1; 4], before))
+            .unwrap();
+        tx.send(Frame::with_stride(1, 1, 4, vec![2; 4], start))
+            .unwrap();
+        drop(tx);
+
+        let frames: Vec<Frame> = forwarded_rx.iter().collect();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data(), &[2; 4]);
+    }
+
+    #[test]
+    fn test_live_config_marker() {
+        let health = Arc::new(RecorderHealth::new(Duration::MAX));
+        let events = health.subscribe().unwrap();
+        let live_config = LiveConfig::new(RecorderConfig::new());
+
+        let (mut version, _) = live_config.get().unwrap();
+        assert!(live_config.poll(&mut version).is_none());
+
+        live_config
+            .update(&RecorderUpdate::new().scale(0.5))
+            .unwrap();
+        let config = live_config.poll(&mut version).unwrap();
+        assert_eq!(config.scale, 0.5);
+        assert!(live_config.poll(&mut version).is_none());
+
+        // 工作线程应用设置后的下一帧触发一次事件
+        let first = Instant::now();
+        live_config.emit_marker(first, &health);
+        live_config.emit_marker(Instant::now(), &health);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            RecorderEvent::Reconfigured { timestamp: first }
+        );
+        assert!(events.try_recv().is_err());
+
+        // 切换之前捕获的帧不触发事件
+        let before = Instant::now();
+        let switched_at = before + Duration::from_millis(10);
+        live_config.mark_switch(Some(switched_at));
+        live_config.emit_marker(before, &health);
+        live_config.emit_marker(switched_at, &health);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            RecorderEvent::Reconfigured {
+                timestamp: switched_at
+            }
+        );
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_scale_frame() {
+        let frame = Frame::with_stride(4, 2, 16, vec![7; 32], Instant::now());
+        let frame = scale_frame(frame, 0.5);
+
+        assert_eq!((frame.width(), frame.height()), (2, 1));
+        assert_eq!(frame.data(), &[7; 8]);
+    }
+
+    #[test]
+    fn test_frame_hook() {
+        let data = vec![0; 24];
+        let mut frame = Frame::with_stride(2, 2, 12, data, Instant::now());
+
+        let hook = FrameHook::new(|view: &mut FrameView| {
+            view.put_pixel(1, 1, Rgba([9, 9, 9, 9]));
+            // 超出范围的像素被忽略
+            view.put_pixel(2, 0, Rgba([9, 9, 9, 9]));
+            view.put_pixel(0, 2, Rgba([9, 9, 9, 9]));
+        });
+        hook.apply(&mut frame);
+
+        assert_eq!(&frame.data()[16..20], &[9, 9, 9, 9]);
+        assert_eq!(frame.data().iter().filter(|&&byte| byte == 9).count(), 4);
+    }
+
+    #[test]
+    fn test_recorder_health_stats() {
+        let health = Arc::new(RecorderHealth::new(Duration::from_millis(20)));
+        let events = health.subscribe().unwrap();
+
+        health.dropped(2);
+        assert!(!health.deliver(|| false));
+
+        // 没有帧时按间隔上报
+        match events.recv_timeout(Duration::from_secs(1)).unwrap() {
+            RecorderEvent::Stats {
+                queue_depth,
+                dropped,
+                ..
+            } => {
+                assert_eq!(queue_depth, 0);
+                assert_eq!(dropped, 3);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+
+        // 接收端没有取走的帧计入队列深度
+        let (tx, rx) = mpsc::sync_channel(0);
+        let sender = {
+            let health = health.clone();
+            thread::spawn(move || health.deliver(|| tx.send(1).is_ok()))
+        };
+        let queue_depth = events
+            .iter()
+            .find_map(|event| match event {
+                RecorderEvent::Stats { queue_depth, .. } if queue_depth > 0 => Some(queue_depth),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(queue_depth, 1);
+
+        assert_eq!(rx.recv().unwrap(), 1);
+        assert!(sender.join().unwrap());
+    }
+
+    #[test]
+    fn test_change_detector() {
+        let mut change_detector = ChangeDetector::default();
+
+        assert!(change_detector.is_changed(&[1, 2, 3, 4]));
+        assert!(!change_detector.is_changed(&[1, 2, 3, 4]));
+        assert!(change_detector.is_changed(&[4, 3, 2, 1]));
+    }
+
+    #[test]
+    fn test_watchdog() {
+        let mut watchdog = Watchdog::new(Duration::from_millis(200), 3);
+
+        assert_eq!(watchdog.miss(), None);
+        assert_eq!(watchdog.miss(), None);
+        watchdog.feed();
+        assert_eq!(watchdog.miss(), None);
+        assert_eq!(watchdog.miss(), None);
+        assert_eq!(watchdog.miss(), Some(Duration::from_millis(600)));
+        // 重启后重新计数
+        assert_eq!(watchdog.miss(), None);
+    }
+
+    #[test]
+    fn test_watchdog_idle_source() {
+        let mut watchdog = Watchdog::new(Duration::from_millis(200), 3);
+
+        // 来源没有新内容时一直超时也不算卡住
+        for _ in 0..10 {
+            assert_eq!(watchdog.timeout(false), None);
+        }
+
+        assert_eq!(watchdog.timeout(true), None);
+        assert_eq!(watchdog.timeout(true), None);
+        assert_eq!(watchdog.timeout(true), Some(Duration::from_millis(600)));
+    }
+
+    #[test]
+    fn test_idle_gate_events() {
+        let health = Arc::new(RecorderHealth::new(Duration::MAX));
+        let events = health.subscribe().unwrap();
+
+        assert!(!IdleGate::new(false).poll(|| true, &health));
+
+        let idle_gate = IdleGate::new(true);
+        assert!(idle_gate.poll(|| true, &health));
+        // 间隔内不会再次检查
+        assert!(idle_gate.poll(|| false, &health));
+
+        assert_eq!(events.try_recv().unwrap(), RecorderEvent::Paused);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_frame_throttle() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut frame_throttle = FrameThrottle::new(Duration::from_millis(40));
+
+        let accepted: Vec<u64> = [0, 16, 33, 50, 66, 83, 100, 300]
+            .into_iter()
+            .filter(|&millis| frame_throttle.accept(at(millis)))
+            .collect();
+        // 保持 40ms 的节拍，长时间没有帧后从当前时间重新计时
+        assert_eq!(accepted, vec![0, 50, 83, 300]);
+    }
+
+    #[test]
+    fn test_recorder_waker_wait_until() {
+        let recorder_waker = RecorderWaker::new();
+        // 暂停时立即返回
+        assert!(
+            !recorder_waker
+                .wait_until(Instant::now() + Duration::from_secs(60))
+                .unwrap()
+        );
+
+        recorder_waker.wake().unwrap();
+        assert!(
+            recorder_waker
+                .wait_until(Instant::now() + Duration::from_millis(5))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_recorder_waker_shutdown() {
+        let recorder_waker = Arc::new(RecorderWaker::new());
+        let worker = {
+            let recorder_waker = recorder_waker.clone();
+            thread::spawn(move || {
+                let _guard = WorkerGuard(recorder_waker.clone());
+                let mut frames = 0;
+                while recorder_waker.wait().unwrap() {
+                    frames += 1;
+                    recorder_waker.sleep().unwrap();
+                }
+                frames
+            })
+        };
+
+        recorder_waker.wake().unwrap();
+        recorder_waker.wait_idle().unwrap();
+        recorder_waker.shutdown().unwrap();
+
+        assert!(worker.join().unwrap() <= 1);
+    }
+
+    #[test]
+    fn test_recorder_waker_send_when_parked() {
+        let recorder_waker = RecorderWaker::new();
+        let (tx, _rx) = mpsc::sync_channel(0);
+
+        assert!(!recorder_waker.send(&tx, 1));
+    }
+
+    #[test]
+    fn test_recorder_waker_send_blocks_until_received() {
+        let recorder_waker = Arc::new(RecorderWaker::new());
+        recorder_waker.wake().unwrap();
+        let (tx, rx) = mpsc::sync_channel(0);
+
+        let sender = {
+            let recorder_waker = recorder_waker.clone();
+            thread::spawn(move || (recorder_waker.send(&tx, 1), recorder_waker.send(&tx, 2)))
+        };
+
+        // 接收端取走帧后发送才返回，接收端释放后发送失败
+        assert_eq!(rx.recv().unwrap(), 1);
+        drop(rx);
+        assert_eq!(sender.join().unwrap(), (true, false));
     }
 }
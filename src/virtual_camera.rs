@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use crate::{
+    error::{XCapError, XCapResult},
+    platform::impl_virtual_camera::ImplVirtualCamera,
+    video_recorder::Frame,
+};
+
+/// Something that presents recorder frames as a webcam, so conferencing apps can use a monitor
+/// or window capture as their camera.
+///
+/// [`VirtualCamera`] implements it with v4l2loopback on Linux. Elsewhere a camera can only be
+/// added by a component the app ships itself: a CoreMediaIO Camera Extension on macOS, or a
+/// media source registered with `MFCreateVirtualCamera` on Windows. Implement this trait for
+/// the code that hands frames to that component, e.g. over the extension's sink stream, so it
+/// can be used wherever a `VirtualCamera` is.
+pub trait CameraSink: Send {
+    /// Present `frame` as the camera's next image.
+    fn write_frame(&mut self, frame: &Frame) -> XCapResult<()>;
+}
+
+/// A v4l2loopback device fed with recorder frames, see [`CameraSink`]. Linux only.
+///
+/// The device has to be created first, e.g. with
+/// `modprobe v4l2loopback exclusive_caps=1 card_label="xcap"`. Frames are written in YUYV,
+/// which browsers and conferencing apps accept, and must all have the size the camera was
+/// opened with, so scale or crop them with [`RecorderConfig`](crate::RecorderConfig) first.
+///
+/// ```no_run
+/// use xcap::{CameraSink, Monitor, VirtualCamera};
+///
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let (recorder, rx) = monitor.video_recorder().unwrap();
+/// recorder.start().unwrap();
+///
+/// let frame = rx.recv().unwrap();
+/// let mut camera = VirtualCamera::open("/dev/video10", frame.width(), frame.height()).unwrap();
+/// camera.write_frame(&frame).unwrap();
+/// for frame in rx {
+///     camera.write_frame(&frame).unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct VirtualCamera {
+    impl_virtual_camera: ImplVirtualCamera,
+    width: u32,
+    height: u32,
+}
+
+impl VirtualCamera {
+    /// Open the v4l2loopback `device` and set its format to `width` x `height` pixels. The
+    /// width must be even.
+    pub fn open<P: AsRef<Path>>(device: P, width: u32, height: u32) -> XCapResult<VirtualCamera> {
+        if width == 0 || height == 0 || !width.is_multiple_of(2) {
+            return Err(XCapError::new(format!(
+                "Virtual camera size {width}x{height} must be non-empty with an even width"
+            )));
+        }
+
+        Ok(VirtualCamera {
+            impl_virtual_camera: ImplVirtualCamera::open(device.as_ref(), width, height)?,
+            width,
+            height,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl CameraSink for VirtualCamera {
+    fn write_frame(&mut self, frame: &Frame) -> XCapResult<()> {
+        if frame.width() != self.width || frame.height() != self.height {
+            return Err(XCapError::new(format!(
+                "Frame size {}x{} does not match the virtual camera size {}x{}",
+                frame.width(),
+                frame.height(),
+                self.width,
+                self.height
+            )));
+        }
+
+        let yuyv = rgba_to_yuyv(
+            frame.data(),
+            self.width as usize,
+            self.height as usize,
+            frame.stride(),
+        );
+        self.impl_virtual_camera.write(&yuyv)
+    }
+}
+
+/// 按 BT.709 limited range 把 RGBA 转换为 YUYV，色度取水平相邻两个像素的平均值，width 必须是偶数
+fn rgba_to_yuyv(data: &[u8], width: usize, height: usize, stride: usize) -> Vec<u8> {
+    let mut yuyv = Vec::with_capacity(width * height * 2);
+    let luma = |r: i32, g: i32, b: i32| (((47 * r + 157 * g + 16 * b + 128) >> 8) + 16) as u8;
+
+    for y in 0..height {
+        let row = &data[y * stride..y * stride + width * 4];
+        for pair in row.chunks_exact(8) {
+            let (r0, g0, b0) = (pair[0] as i32, pair[1] as i32, pair[2] as i32);
+            let (r1, g1, b1) = (pair[4] as i32, pair[5] as i32, pair[6] as i32);
+            let (r, g, b) = ((r0 + r1) / 2, (g0 + g1) / 2, (b0 + b1) / 2);
+
+            let u = (((-26 * r - 87 * g + 112 * b + 128) >> 8) + 128).clamp(0, 255) as u8;
+            let v = (((112 * r - 102 * g - 10 * b + 128) >> 8) + 128).clamp(0, 255) as u8;
+            yuyv.extend_from_slice(&[luma(r0, g0, b0), u, luma(r1, g1, b1), v]);
+        }
+    }
+
+    yuyv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgba_to_yuyv() {
+        // 一行白色和黑色像素，每行有 4 字节填充
+        let data = [255, 255, 255, 255, 0, 0, 0, 255, 9, 9, 9, 9];
+        assert_eq!(rgba_to_yuyv(&data, 2, 1, 12), [235, 128, 16, 128]);
+    }
+}
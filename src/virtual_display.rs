@@ -0,0 +1,40 @@
+use crate::{Monitor, error::XCapResult, platform::impl_virtual_display::ImplVirtualDisplay};
+
+/// A headless display for tests and CI, so capture paths can be tested on machines without a
+/// screen. The display is removed again when the `VirtualDisplay` is dropped.
+///
+/// - On macOS the display is created with `CGVirtualDisplay`, a private CoreGraphics API, so
+///   this is only meant for tests and must not ship in applications.
+/// - On Windows displays can only be added by a driver. Install an indirect display driver and
+///   set `XCAP_VIRTUAL_DISPLAY_ADD` and `XCAP_VIRTUAL_DISPLAY_REMOVE` to the commands that add
+///   and remove its display; `{width}` and `{height}` in the commands are replaced with the
+///   display size. The commands are run with `cmd /C`.
+/// - On Linux an `Xvfb` server is started and all X11 connections of xcap are made to it. It
+///   must be created before any other xcap call, only one can be created per process, and the
+///   Xorg backend has to be used.
+///
+/// ```no_run
+/// use xcap::VirtualDisplay;
+///
+/// let display = VirtualDisplay::new(1280, 720).unwrap();
+/// let image = display.monitor().unwrap().capture_image().unwrap();
+/// assert_eq!(image.width(), 1280);
+/// ```
+#[derive(Debug)]
+pub struct VirtualDisplay {
+    impl_virtual_display: ImplVirtualDisplay,
+}
+
+impl VirtualDisplay {
+    /// Create a display of `width` x `height` pixels and wait until it is online.
+    pub fn new(width: u32, height: u32) -> XCapResult<VirtualDisplay> {
+        Ok(VirtualDisplay {
+            impl_virtual_display: ImplVirtualDisplay::new(width, height)?,
+        })
+    }
+
+    /// The monitor of the virtual display.
+    pub fn monitor(&self) -> XCapResult<Monitor> {
+        Ok(Monitor::new(self.impl_virtual_display.monitor()?))
+    }
+}
@@ -0,0 +1,528 @@
+use std::{collections::VecDeque, io::Write, time::Instant};
+
+use rav1e::prelude::{
+    ColorDescription, ColorPrimaries, Config, Context, EncoderConfig, EncoderStatus, FrameType,
+    MatrixCoefficients, Packet, Rational, SpeedSettings, TransferCharacteristics,
+};
+
+use crate::{XCapError, XCapResult, video_recorder::Frame};
+
+// EBML 和 Matroska 元素 ID
+const EBML: u32 = 0x1A45_DFA3;
+const EBML_VERSION: u32 = 0x4286;
+const EBML_READ_VERSION: u32 = 0x42F7;
+const EBML_MAX_ID_LENGTH: u32 = 0x42F2;
+const EBML_MAX_SIZE_LENGTH: u32 = 0x42F3;
+const DOC_TYPE: u32 = 0x4282;
+const DOC_TYPE_VERSION: u32 = 0x4287;
+const DOC_TYPE_READ_VERSION: u32 = 0x4285;
+const SEGMENT: u32 = 0x1853_8067;
+const INFO: u32 = 0x1549_A966;
+const TIMECODE_SCALE: u32 = 0x2A_D7B1;
+const MUXING_APP: u32 = 0x4D80;
+const WRITING_APP: u32 = 0x5741;
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const TRACK_UID: u32 = 0x73C5;
+const TRACK_TYPE: u32 = 0x83;
+const FLAG_LACING: u32 = 0x9C;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const CLUSTER: u32 = 0x1F43_B675;
+const TIMECODE: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+
+// 时间戳以毫秒为单位
+const TIMECODE_SCALE_NS: u64 = 1_000_000;
+// 未知大小的 Segment，写入时不需要回填长度，输出可以是不能 seek 的流
+const UNKNOWN_SIZE: [u8; 8] = [0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+const TRACK: u8 = 1;
+// SimpleBlock 的相对时间戳是 i16，超出前开始新的 Cluster
+const MAX_CLUSTER_DURATION_MS: u64 = 5_000;
+
+const DEFAULT_SPEED: u8 = 10;
+const DEFAULT_FPS: u32 = 30;
+const DEFAULT_KEYFRAME_INTERVAL: u64 = 240;
+
+fn write_id(buf: &mut Vec<u8>, id: u32) {
+    let bytes = id.to_be_bytes();
+    let skip = bytes.iter().take_while(|byte| **byte == 0).count();
+    buf.extend_from_slice(&bytes[skip..]);
+}
+
+/// 长度编码为最短的 EBML 变长整数，全 1 保留给未知大小
+fn write_size(buf: &mut Vec<u8>, size: u64) {
+    let len = (1..=8).find(|len| size < (1 << (7 * len)) - 1).unwrap_or(8);
+    let marked = size | (1 << (7 * len));
+    buf.extend_from_slice(&marked.to_be_bytes()[8 - len..]);
+}
+
+fn write_element(buf: &mut Vec<u8>, id: u32, payload: &[u8]) {
+    write_id(buf, id);
+    write_size(buf, payload.len() as u64);
+    buf.extend_from_slice(payload);
+}
+
+fn write_uint(buf: &mut Vec<u8>, id: u32, value: u64) {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|byte| **byte == 0).count().min(7);
+    write_element(buf, id, &bytes[skip..]);
+}
+
+fn header(width: u32, height: u32, codec_private: &[u8]) -> Vec<u8> {
+    let mut ebml = Vec::new();
+    write_uint(&mut ebml, EBML_VERSION, 1);
+    write_uint(&mut ebml, EBML_READ_VERSION, 1);
+    write_uint(&mut ebml, EBML_MAX_ID_LENGTH, 4);
+    write_uint(&mut ebml, EBML_MAX_SIZE_LENGTH, 8);
+    write_element(&mut ebml, DOC_TYPE, b"webm");
+    write_uint(&mut ebml, DOC_TYPE_VERSION, 4);
+    write_uint(&mut ebml, DOC_TYPE_READ_VERSION, 2);
+
+    let mut info = Vec::new();
+    write_uint(&mut info, TIMECODE_SCALE, TIMECODE_SCALE_NS);
+    write_element(&mut info, MUXING_APP, b"xcap");
+    write_element(&mut info, WRITING_APP, b"xcap");
+
+    let mut video = Vec::new();
+    write_uint(&mut video, PIXEL_WIDTH, width as u64);
+    write_uint(&mut video, PIXEL_HEIGHT, height as u64);
+
+    let mut track_entry = Vec::new();
+    write_uint(&mut track_entry, TRACK_NUMBER, TRACK as u64);
+    write_uint(&mut track_entry, TRACK_UID, 1);
+    // 1 表示视频轨道
+    write_uint(&mut track_entry, TRACK_TYPE, 1);
+    write_uint(&mut track_entry, FLAG_LACING, 0);
+    write_element(&mut track_entry, CODEC_ID, b"V_AV1");
+    write_element(&mut track_entry, CODEC_PRIVATE, codec_private);
+    write_element(&mut track_entry, VIDEO, &video);
+
+    let mut tracks = Vec::new();
+    write_element(&mut tracks, TRACK_ENTRY, &track_entry);
+
+    let mut buf = Vec::new();
+    write_element(&mut buf, EBML, &ebml);
+    write_id(&mut buf, SEGMENT);
+    buf.extend_from_slice(&UNKNOWN_SIZE);
+    write_element(&mut buf, INFO, &info);
+    write_element(&mut buf, TRACKS, &tracks);
+
+    buf
+}
+
+/// 缓存当前 Cluster 的 SimpleBlock，Cluster 结束时一次写入，长度已知
+#[derive(Debug, Default)]
+struct Cluster {
+    timecode: u64,
+    blocks: Vec<u8>,
+}
+
+impl Cluster {
+    fn push(&mut self, timecode: u64, keyframe: bool, data: &[u8]) {
+        let mut block = Vec::with_capacity(data.len() + 4);
+        write_size(&mut block, TRACK as u64);
+        block.extend_from_slice(&((timecode - self.timecode) as i16).to_be_bytes());
+        block.push(if keyframe { 0x80 } else { 0 });
+        block.extend_from_slice(data);
+
+        write_element(&mut self.blocks, SIMPLE_BLOCK, &block);
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(self.blocks.len() + 9);
+        write_uint(&mut payload, TIMECODE, self.timecode);
+        payload.extend_from_slice(&self.blocks);
+
+        let mut buf = Vec::with_capacity(payload.len() + 12);
+        write_element(&mut buf, CLUSTER, &payload);
+
+        buf
+    }
+}
+
+/// 按 BT.709 limited range 把 RGBA 转换为 I420，色度取 2x2 像素的平均值
+pub(crate) fn rgba_to_i420(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let chroma_width = width.div_ceil(2);
+    let chroma_height = height.div_ceil(2);
+    let mut y_plane = vec![0; width * height];
+    let mut u_plane = vec![0; chroma_width * chroma_height];
+    let mut v_plane = vec![0; chroma_width * chroma_height];
+
+    let pixel = |x: usize, y: usize| {
+        let offset = y * stride + x * 4;
+        (
+            data[offset] as i32,
+            data[offset + 1] as i32,
+            data[offset + 2] as i32,
+        )
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            let (r, g, b) = pixel(x, y);
+            y_plane[y * width + x] = (((47 * r + 157 * g + 16 * b + 128) >> 8) + 16) as u8;
+        }
+    }
+
+    for cy in 0..chroma_height {
+        for cx in 0..chroma_width {
+            let (mut r, mut g, mut b, mut count) = (0, 0, 0, 0);
+            for y in cy * 2..(cy * 2 + 2).min(height) {
+                for x in cx * 2..(cx * 2 + 2).min(width) {
+                    let (pr, pg, pb) = pixel(x, y);
+                    r += pr;
+                    g += pg;
+                    b += pb;
+                    count += 1;
+                }
+            }
+            let (r, g, b) = (r / count, g / count, b / count);
+
+            let index = cy * chroma_width + cx;
+            u_plane[index] = (((-26 * r - 87 * g + 112 * b + 128) >> 8) + 128).clamp(0, 255) as u8;
+            v_plane[index] = (((112 * r - 102 * g - 10 * b + 128) >> 8) + 128).clamp(0, 255) as u8;
+        }
+    }
+
+    (y_plane, u_plane, v_plane)
+}
+
+/// 编码参数，分段录制时每个分段使用相同的参数创建新的编码器
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EncodeOptions {
+    pub speed: u8,
+    pub bitrate: Option<u32>,
+    pub fps: u32,
+    pub keyframe_interval: u64,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {
+            speed: DEFAULT_SPEED,
+            bitrate: None,
+            fps: DEFAULT_FPS,
+            keyframe_interval: DEFAULT_KEYFRAME_INTERVAL,
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// 写入 WebM 头部，返回从关键帧开始的编码器
+    pub fn start<W: Write>(
+        &self,
+        mut writer: W,
+        width: u32,
+        height: u32,
+    ) -> XCapResult<WebmEncoder<W>> {
+        if width == 0 || height == 0 {
+            return Err(XCapError::new("WebM video size must not be zero"));
+        }
+
+        let encoder_config = EncoderConfig {
+            width: width as usize,
+            height: height as usize,
+            time_base: Rational::new(1, self.fps as u64),
+            bitrate: self
+                .bitrate
+                .map_or(0, |bitrate| (bitrate as i32).saturating_mul(1000)),
+            max_key_frame_interval: self.keyframe_interval,
+            // 不重排帧，编码后的帧按输入顺序输出，延迟最低
+            low_latency: true,
+            speed_settings: SpeedSettings::from_preset(self.speed),
+            color_description: Some(ColorDescription {
+                color_primaries: ColorPrimaries::BT709,
+                transfer_characteristics: TransferCharacteristics::SRGB,
+                matrix_coefficients: MatrixCoefficients::BT709,
+            }),
+            ..Default::default()
+        };
+        let context = Config::new()
+            .with_encoder_config(encoder_config)
+            .new_context()
+            .map_err(|err| XCapError::new(format!("rav1e config invalid: {err}")))?;
+
+        writer
+            .write_all(&header(width, height, &context.container_sequence_header()))
+            .map_err(|err| XCapError::new(format!("Write WebM header failed: {err}")))?;
+
+        Ok(WebmEncoder {
+            writer,
+            context,
+            width,
+            height,
+            start: None,
+            timecodes: VecDeque::new(),
+            cluster: None,
+        })
+    }
+}
+
+/// Builds a [`WebmEncoder`], see [`WebmEncoder::builder`].
+#[derive(Debug)]
+pub struct WebmEncoderBuilder<W: Write> {
+    writer: W,
+    width: u32,
+    height: u32,
+    options: EncodeOptions,
+}
+
+impl<W: Write> WebmEncoderBuilder<W> {
+    /// The rav1e speed preset from 0 (slowest, smallest) to 10 (fastest). Defaults to 10,
+    /// which keeps up with screen content in real time on most machines.
+    pub fn speed(mut self, speed: u8) -> WebmEncoderBuilder<W> {
+        self.options.speed = speed.min(10);
+        self
+    }
+
+    /// The target bitrate in kilobits per second. Defaults to constant quality.
+    pub fn bitrate(mut self, bitrate: u32) -> WebmEncoderBuilder<W> {
+        self.options.bitrate = Some(bitrate);
+        self
+    }
+
+    /// The expected frame rate, used by rate control. Frame timestamps in the file come from
+    /// [`Frame::timestamp`], so frames do not need to arrive at this rate. Defaults to 30.
+    pub fn fps(mut self, fps: u32) -> WebmEncoderBuilder<W> {
+        self.options.fps = fps.max(1);
+        self
+    }
+
+    /// The largest number of frames between keyframes, where players can start and seek.
+    /// Defaults to 240.
+    pub fn keyframe_interval(mut self, keyframe_interval: u64) -> WebmEncoderBuilder<W> {
+        self.options.keyframe_interval = keyframe_interval.max(1);
+        self
+    }
+
+    /// Write the WebM header and start encoding.
+    pub fn build(self) -> XCapResult<WebmEncoder<W>> {
+        self.options.start(self.writer, self.width, self.height)
+    }
+}
+
+/// Encodes recorder frames to AV1 in a WebM file with the pure Rust rav1e encoder, for
+/// machines without hardware encoders and products that avoid MP4 licensing. Requires the
+/// `webm` feature.
+///
+/// Software AV1 encoding is CPU heavy; record at a reduced
+/// [`RecorderConfig::scale`](crate::RecorderConfig::scale) or
+/// [`RecorderConfig::max_fps`](crate::RecorderConfig::max_fps) for long recordings.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use xcap::{Monitor, WebmEncoder};
+///
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let (recorder, rx) = monitor.video_recorder().unwrap();
+/// recorder.start().unwrap();
+///
+/// let frame = rx.recv().unwrap();
+/// let file = File::create("recording.webm").unwrap();
+/// let mut encoder = WebmEncoder::builder(file, frame.width(), frame.height())
+///     .build()
+///     .unwrap();
+/// encoder.encode(&frame).unwrap();
+///
+/// for frame in rx.iter().take(150) {
+///     encoder.encode(&frame).unwrap();
+/// }
+/// recorder.stop().unwrap();
+/// encoder.finish().unwrap();
+/// ```
+pub struct WebmEncoder<W: Write> {
+    writer: W,
+    context: Context<u8>,
+    width: u32,
+    height: u32,
+    start: Option<Instant>,
+    // 等待编码输出的帧的时间戳，按输入顺序排列
+    timecodes: VecDeque<(u64, u64)>,
+    cluster: Option<Cluster>,
+}
+
+impl<W: Write> std::fmt::Debug for WebmEncoder<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebmEncoder")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("start", &self.start)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: Write> WebmEncoder<W> {
+    /// Configure a `width` x `height` video written to `writer`. The writer does not need to
+    /// support seeking, so it can be a socket or a pipe as well as a file.
+    pub fn builder(writer: W, width: u32, height: u32) -> WebmEncoderBuilder<W> {
+        WebmEncoderBuilder {
+            writer,
+            width,
+            height,
+            options: EncodeOptions::default(),
+        }
+    }
+
+    /// Encode `frame`, which must have the size the encoder was built with. Encoded frames are
+    /// written as soon as rav1e outputs them.
+    pub fn encode(&mut self, frame: &Frame) -> XCapResult<()> {
+        if frame.width() != self.width || frame.height() != self.height {
+            return Err(XCapError::new(format!(
+                "Frame size {}x{} does not match the WebM video size {}x{}",
+                frame.width(),
+                frame.height(),
+                self.width,
+                self.height
+            )));
+        }
+
+        let start = *self.start.get_or_insert(frame.timestamp());
+        let timecode = frame
+            .timestamp()
+            .saturating_duration_since(start)
+            .as_millis() as u64;
+
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (y_plane, u_plane, v_plane) = rgba_to_i420(frame.data(), width, height, frame.stride());
+
+        let mut input = self.context.new_frame();
+        input.planes[0].copy_from_raw_u8(&y_plane, width, 1);
+        input.planes[1].copy_from_raw_u8(&u_plane, width.div_ceil(2), 1);
+        input.planes[2].copy_from_raw_u8(&v_plane, width.div_ceil(2), 1);
+
+        let frameno = self.timecodes.back().map_or(0, |(frameno, _)| frameno + 1);
+        // 帧的时间戳可能因为时钟换算而回退，保持单调
+        let timecode = self
+            .timecodes
+            .back()
+            .map_or(timecode, |(_, last)| timecode.max(*last));
+        self.timecodes.push_back((frameno, timecode));
+
+        self.context
+            .send_frame(input)
+            .map_err(|err| XCapError::new(format!("rav1e send frame failed: {err}")))?;
+
+        self.drain()
+    }
+
+    /// Flush the frames still in the encoder and return the writer.
+    pub fn finish(mut self) -> XCapResult<W> {
+        self.context.flush();
+        self.drain()?;
+        self.write_cluster()?;
+        self.writer
+            .flush()
+            .map_err(|err| XCapError::new(format!("Write WebM failed: {err}")))?;
+
+        Ok(self.writer)
+    }
+
+    fn drain(&mut self) -> XCapResult<()> {
+        loop {
+            match self.context.receive_packet() {
+                Ok(packet) => self.write_packet(&packet)?,
+                Err(EncoderStatus::Encoded) => continue,
+                Err(EncoderStatus::NeedMoreData | EncoderStatus::LimitReached) => break Ok(()),
+                Err(err) => break Err(XCapError::new(format!("rav1e encode failed: {err}"))),
+            }
+        }
+    }
+
+    fn write_packet(&mut self, packet: &Packet<u8>) -> XCapResult<()> {
+        while let Some((frameno, _)) = self.timecodes.front()
+            && *frameno < packet.input_frameno
+        {
+            self.timecodes.pop_front();
+        }
+        let timecode = match self.timecodes.pop_front() {
+            Some((_, timecode)) => timecode,
+            None => return Err(XCapError::new("rav1e output an unknown frame")),
+        };
+        let keyframe = packet.frame_type == FrameType::KEY;
+
+        // 关键帧开始新的 Cluster，方便播放器从 Cluster 开头解码
+        let starts_cluster = self.cluster.as_ref().is_none_or(|cluster| {
+            keyframe || timecode - cluster.timecode > MAX_CLUSTER_DURATION_MS
+        });
+        if starts_cluster {
+            self.write_cluster()?;
+            self.cluster = Some(Cluster {
+                timecode,
+                blocks: Vec::new(),
+            });
+        }
+
+        if let Some(cluster) = self.cluster.as_mut() {
+            cluster.push(timecode, keyframe, &packet.data);
+        }
+
+        Ok(())
+    }
+
+    fn write_cluster(&mut self) -> XCapResult<()> {
+        if let Some(cluster) = self.cluster.take() {
+            self.writer
+                .write_all(&cluster.to_bytes())
+                .map_err(|err| XCapError::new(format!("Write WebM failed: {err}")))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_size() {
+        let encode = |size| {
+            let mut buf = Vec::new();
+            write_size(&mut buf, size);
+            buf
+        };
+
+        assert_eq!(encode(0), [0x80]);
+        assert_eq!(encode(126), [0xFE]);
+        // 127 在 1 字节中是全 1，表示未知大小，需要 2 字节
+        assert_eq!(encode(127), [0x40, 0x7F]);
+        assert_eq!(encode(0x3FFE), [0x7F, 0xFE]);
+        assert_eq!(encode(0x3FFF), [0x20, 0x3F, 0xFF]);
+    }
+
+    #[test]
+    fn test_write_uint() {
+        let mut buf = Vec::new();
+        write_uint(&mut buf, TIMECODE_SCALE, TIMECODE_SCALE_NS);
+        assert_eq!(buf, [0x2A, 0xD7, 0xB1, 0x83, 0x0F, 0x42, 0x40]);
+
+        let mut buf = Vec::new();
+        write_uint(&mut buf, TIMECODE, 0);
+        assert_eq!(buf, [0xE7, 0x81, 0x00]);
+    }
+
+    #[test]
+    fn test_rgba_to_i420() {
+        // 3x1 的图像：白色、黑色、红色，色度平面向上取整为 2x1
+        let data = [255, 255, 255, 255, 0, 0, 0, 255, 255, 0, 0, 255];
+        let (y_plane, u_plane, v_plane) = rgba_to_i420(&data, 3, 1, 12);
+
+        assert_eq!(y_plane, [235, 16, 63]);
+        assert_eq!(u_plane.len(), 2);
+        assert_eq!(v_plane.len(), 2);
+        // 白色和黑色平均后是灰色，没有色度
+        assert_eq!((u_plane[0], v_plane[0]), (128, 128));
+        assert_eq!((u_plane[1], v_plane[1]), (102, 240));
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{BufWriter, ErrorKind},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    XCapError, XCapResult,
+    sidecar::{FrameSidecar, SidecarFormat},
+    video_recorder::Frame,
+    webm::{EncodeOptions, WebmEncoder},
+};
+
+const SEGMENT_PREFIX: &str = "segment-";
+const SEGMENT_EXTENSION: &str = "webm";
+// 正在写入的分段使用临时扩展名，完成后重命名，目录中的 .webm 文件总是完整的
+const PARTIAL_EXTENSION: &str = "webm.part";
+const DEFAULT_SEGMENT_DURATION: Duration = Duration::from_secs(30);
+
+fn segment_path(dir: &Path, index: u64, extension: &str) -> PathBuf {
+    dir.join(format!("{SEGMENT_PREFIX}{index:06}.{extension}"))
+}
+
+fn partial_extension(extension: &str) -> String {
+    format!("{extension}.part")
+}
+
+/// 目录中已有的完整分段，按编号排序
+fn existing_segments(dir: &Path) -> XCapResult<Vec<u64>> {
+    let entries = fs::read_dir(dir)
+        .map_err(|err| XCapError::new(format!("Read segment directory failed: {err}")))?;
+
+    let mut indices: Vec<u64> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            file_name
+                .to_str()?
+                .strip_prefix(SEGMENT_PREFIX)?
+                .strip_suffix(&format!(".{SEGMENT_EXTENSION}"))?
+                .parse()
+                .ok()
+        })
+        .collect();
+    indices.sort_unstable();
+
+    Ok(indices)
+}
+
+#[derive(Debug)]
+struct Segment {
+    index: u64,
+    start: Instant,
+    encoder: WebmEncoder<BufWriter<File>>,
+    sidecar: Option<FrameSidecar<BufWriter<File>>>,
+}
+
+/// Builds a [`SegmentedWebmEncoder`], see [`SegmentedWebmEncoder::builder`].
+#[derive(Debug, Clone)]
+pub struct SegmentedWebmEncoderBuilder {
+    dir: PathBuf,
+    width: u32,
+    height: u32,
+    options: EncodeOptions,
+    segment_duration: Duration,
+    max_segments: Option<usize>,
+    sidecar: Option<SidecarFormat>,
+}
+
+impl SegmentedWebmEncoderBuilder {
+    /// How long each file is, measured by frame timestamps. Defaults to 30 seconds.
+    pub fn segment_duration(mut self, segment_duration: Duration) -> SegmentedWebmEncoderBuilder {
+        self.segment_duration = segment_duration;
+        self
+    }
+
+    /// How many finished files to keep, deleting the oldest when a segment finishes. Defaults to
+    /// keeping all of them.
+    pub fn max_segments(mut self, max_segments: usize) -> SegmentedWebmEncoderBuilder {
+        self.max_segments = Some(max_segments.max(1));
+        self
+    }
+
+    /// Write a [`FrameSidecar`] with per-frame metadata next to each segment, e.g.
+    /// `segment-000000.jsonl`, which is renamed and deleted together with the segment.
+    pub fn sidecar(mut self, format: SidecarFormat) -> SegmentedWebmEncoderBuilder {
+        self.sidecar = Some(format);
+        self
+    }
+
+    /// See [`WebmEncoderBuilder::speed`](crate::WebmEncoderBuilder::speed).
+    pub fn speed(mut self, speed: u8) -> SegmentedWebmEncoderBuilder {
+        self.options.speed = speed.min(10);
+        self
+    }
+
+    /// See [`WebmEncoderBuilder::bitrate`](crate::WebmEncoderBuilder::bitrate).
+    pub fn bitrate(mut self, bitrate: u32) -> SegmentedWebmEncoderBuilder {
+        self.options.bitrate = Some(bitrate);
+        self
+    }
+
+    /// See [`WebmEncoderBuilder::fps`](crate::WebmEncoderBuilder::fps).
+    pub fn fps(mut self, fps: u32) -> SegmentedWebmEncoderBuilder {
+        self.options.fps = fps.max(1);
+        self
+    }
+
+    /// See [`WebmEncoderBuilder::keyframe_interval`](crate::WebmEncoderBuilder::keyframe_interval).
+    pub fn keyframe_interval(mut self, keyframe_interval: u64) -> SegmentedWebmEncoderBuilder {
+        self.options.keyframe_interval = keyframe_interval.max(1);
+        self
+    }
+
+    /// Create the directory if needed and start encoding. Segments already in the directory,
+    /// e.g. from an earlier run, count towards [`max_segments`](Self::max_segments) and new
+    /// segments are numbered after them.
+    pub fn build(self) -> XCapResult<SegmentedWebmEncoder> {
+        if self.width == 0 || self.height == 0 {
+            return Err(XCapError::new("WebM video size must not be zero"));
+        }
+        if self.segment_duration.is_zero() {
+            return Err(XCapError::new("Segment duration must not be zero"));
+        }
+
+        fs::create_dir_all(&self.dir)
+            .map_err(|err| XCapError::new(format!("Create segment directory failed: {err}")))?;
+        let finished: VecDeque<u64> = existing_segments(&self.dir)?.into();
+        let next_index = finished.back().map_or(0, |index| index + 1);
+
+        let mut encoder = SegmentedWebmEncoder {
+            dir: self.dir,
+            width: self.width,
+            height: self.height,
+            options: self.options,
+            segment_duration: self.segment_duration,
+            max_segments: self.max_segments,
+            sidecar: self.sidecar,
+            finished,
+            next_index,
+            segment: None,
+        };
+        encoder.prune();
+
+        Ok(encoder)
+    }
+}
+
+/// Encodes recorder frames into a directory of consecutive WebM files of a fixed duration,
+/// optionally keeping only the newest ones, e.g. "the last 5 minutes in 30-second files" for
+/// dashcam-style desktop recording. Requires the `webm` feature.
+///
+/// Files are named `segment-000000.webm`, `segment-000001.webm` and so on. The segment being
+/// written has a `.webm.part` extension and is renamed when it is complete, so every `.webm`
+/// file in the directory can be copied or uploaded at any time. Each segment starts with a
+/// keyframe and plays on its own.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use xcap::{Monitor, SegmentedWebmEncoder};
+///
+/// let monitor = Monitor::all().unwrap().remove(0);
+/// let (recorder, rx) = monitor.video_recorder().unwrap();
+/// recorder.start().unwrap();
+///
+/// let frame = rx.recv().unwrap();
+/// let mut encoder = SegmentedWebmEncoder::builder("recordings", frame.width(), frame.height())
+///     .segment_duration(Duration::from_secs(30))
+///     .max_segments(10)
+///     .build()
+///     .unwrap();
+///
+/// for frame in rx.iter().take(3000) {
+///     encoder.encode(&frame).unwrap();
+/// }
+/// recorder.stop().unwrap();
+/// println!("{:?}", encoder.finish().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct SegmentedWebmEncoder {
+    dir: PathBuf,
+    width: u32,
+    height: u32,
+    options: EncodeOptions,
+    segment_duration: Duration,
+    max_segments: Option<usize>,
+    sidecar: Option<SidecarFormat>,
+    // 已经完成的分段编号，从旧到新
+    finished: VecDeque<u64>,
+    next_index: u64,
+    segment: Option<Segment>,
+}
+
+impl SegmentedWebmEncoder {
+    /// Configure `width` x `height` segments written to `dir`.
+    pub fn builder(dir: impl AsRef<Path>, width: u32, height: u32) -> SegmentedWebmEncoderBuilder {
+        SegmentedWebmEncoderBuilder {
+            dir: dir.as_ref().to_path_buf(),
+            width,
+            height,
+            options: EncodeOptions::default(),
+            segment_duration: DEFAULT_SEGMENT_DURATION,
+            max_segments: None,
+            sidecar: None,
+        }
+    }
+
+    /// Encode `frame`, finishing the current segment and starting the next one first when the
+    /// frame is [`segment_duration`](SegmentedWebmEncoderBuilder::segment_duration) or more
+    /// after the segment's first frame.
+    pub fn encode(&mut self, frame: &Frame) -> XCapResult<()> {
+        let rollover = self.segment.as_ref().is_some_and(|segment| {
+            frame.timestamp().saturating_duration_since(segment.start) >= self.segment_duration
+        });
+        if rollover {
+            self.finish_segment()?;
+        }
+
+        if self.segment.is_none() {
+            self.segment = Some(self.start_segment(frame.timestamp())?);
+        }
+
+        let Some(segment) = self.segment.as_mut() else {
+            return Ok(());
+        };
+
+        segment.encoder.encode(frame)?;
+        if let Some(sidecar) = segment.sidecar.as_mut() {
+            sidecar.record(frame)?;
+        }
+
+        Ok(())
+    }
+
+    /// The finished segments in the directory, oldest first. The segment being written is not
+    /// included.
+    pub fn segments(&self) -> Vec<PathBuf> {
+        self.finished
+            .iter()
+            .map(|index| segment_path(&self.dir, *index, SEGMENT_EXTENSION))
+            .collect()
+    }
+
+    /// Finish the segment being written and return all finished segments, oldest first.
+    pub fn finish(mut self) -> XCapResult<Vec<PathBuf>> {
+        self.finish_segment()?;
+
+        Ok(self.segments())
+    }
+
+    /// 新的分段从关键帧开始，可以单独播放
+    fn start_segment(&mut self, start: Instant) -> XCapResult<Segment> {
+        let index = self.next_index;
+        let file = File::create(segment_path(&self.dir, index, PARTIAL_EXTENSION))
+            .map_err(|err| XCapError::new(format!("Create segment failed: {err}")))?;
+        let encoder = self
+            .options
+            .start(BufWriter::new(file), self.width, self.height)?;
+        let sidecar = match self.sidecar {
+            Some(format) => Some(FrameSidecar::create(
+                segment_path(&self.dir, index, &partial_extension(format.extension())),
+                format,
+            )?),
+            None => None,
+        };
+        self.next_index += 1;
+
+        Ok(Segment {
+            index,
+            start,
+            encoder,
+            sidecar,
+        })
+    }
+
+    /// 编码器写完后把临时文件重命名为正式文件，重命名是原子的
+    fn finish_segment(&mut self) -> XCapResult<()> {
+        let Some(segment) = self.segment.take() else {
+            return Ok(());
+        };
+
+        let writer = segment.encoder.finish()?;
+        let file = writer
+            .into_inner()
+            .map_err(|err| XCapError::new(format!("Write segment failed: {err}")))?;
+        file.sync_all()
+            .map_err(|err| XCapError::new(format!("Write segment failed: {err}")))?;
+        drop(file);
+
+        fs::rename(
+            segment_path(&self.dir, segment.index, PARTIAL_EXTENSION),
+            segment_path(&self.dir, segment.index, SEGMENT_EXTENSION),
+        )
+        .map_err(|err| XCapError::new(format!("Rename segment failed: {err}")))?;
+
+        if let (Some(sidecar), Some(format)) = (segment.sidecar, self.sidecar) {
+            sidecar.finish()?;
+            fs::rename(
+                segment_path(
+                    &self.dir,
+                    segment.index,
+                    &partial_extension(format.extension()),
+                ),
+                segment_path(&self.dir, segment.index, format.extension()),
+            )
+            .map_err(|err| XCapError::new(format!("Rename sidecar failed: {err}")))?;
+        }
+        self.finished.push_back(segment.index);
+        self.prune();
+
+        Ok(())
+    }
+
+    /// 删除超出数量的旧分段
+    fn prune(&mut self) {
+        let Some(max_segments) = self.max_segments else {
+            return;
+        };
+
+        while self.finished.len() > max_segments {
+            let Some(index) = self.finished.pop_front() else {
+                break;
+            };
+
+            let path = segment_path(&self.dir, index, SEGMENT_EXTENSION);
+            if let Err(err) = fs::remove_file(&path) {
+                log::error!("Remove segment {path:?} failed: {err}");
+            }
+
+            // 之前的运行可能没有写 sidecar
+            if let Some(format) = self.sidecar {
+                let path = segment_path(&self.dir, index, format.extension());
+                if let Err(err) = fs::remove_file(&path)
+                    && err.kind() != ErrorKind::NotFound
+                {
+                    log::error!("Remove sidecar {path:?} failed: {err}");
+                }
+            }
+        }
+    }
+}
+
+impl Drop for SegmentedWebmEncoder {
+    fn drop(&mut self) {
+        if let Err(err) = self.finish_segment() {
+            log::error!("Finish segment failed: {err:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_existing_segments() {
+        let dir = std::env::temp_dir().join(format!("xcap-segments-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "segment-000002.webm",
+            "segment-000010.webm",
+            "segment-000011.webm.part",
+            "other.webm",
+        ] {
+            File::create(dir.join(name)).unwrap();
+        }
+
+        assert_eq!(existing_segments(&dir).unwrap(), [2, 10]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
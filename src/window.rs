@@ -1,6 +1,55 @@
-use image::RgbaImage;
+use std::{sync::mpsc::Receiver, time::Duration};
 
-use crate::{Monitor, error::XCapResult, platform::impl_window::ImplWindow};
+use image::{
+    RgbaImage,
+    imageops::{self, FilterType},
+};
+
+use crate::{
+    CaptureConfig, CaptureReport, CoordinateSpace, Monitor,
+    delayed_capture::{capture_after, capture_when_stable},
+    encode::{encode_jpeg, encode_png},
+    error::{XCapError, XCapResult, catch_panics},
+    geometry::{Rect, union_rect},
+    platform::impl_window::ImplWindow,
+    scroll_capture::capture_scrolling,
+    thumbnail_stream::thumbnail_size,
+    title_watcher::{ActiveTitle, TitleWatcher},
+    window_watcher::WindowWatcher,
+};
+
+/// The role of a window in the window stack, used to tell application windows apart from
+/// tooltips, menus, overlays and shell surfaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WindowLayer {
+    /// The desktop background and desktop icons.
+    Desktop,
+    /// Regular application windows and dialogs.
+    Normal,
+    /// The Dock, taskbar or a panel.
+    Dock,
+    /// The system menu bar.
+    MenuBar,
+    /// Menu bar extras and system tray items.
+    StatusItem,
+    /// Popup, dropdown and context menus.
+    Popup,
+    /// Tooltips and help tags.
+    Tooltip,
+    /// Floating, always-on-top and notification windows.
+    Overlay,
+}
+
+/// How [`Window::get_active_info`] keeps track of the frontmost application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActiveInfoMode {
+    /// Updated from app activation notifications, which on macOS are only delivered while the
+    /// host runs the main RunLoop.
+    Notification,
+    /// The frontmost application is queried on every call.
+    Polling,
+}
 
 #[derive(Debug, Clone)]
 pub struct Window {
@@ -11,24 +60,79 @@ impl Window {
     pub(crate) fn new(impl_window: ImplWindow) -> Window {
         Window { impl_window }
     }
+
+    /// 按 id 查找窗口，用于在其他线程中重新获取窗口
+    pub(crate) fn from_id(id: u32) -> XCapResult<Window> {
+        Window::all()?
+            .into_iter()
+            .find(|window| window.id().ok() == Some(id))
+            .ok_or(XCapError::new("Window not found"))
+    }
 }
 
 impl Window {
     /// List all windows, sorted by z coordinate.
     pub fn all() -> XCapResult<Vec<Window>> {
-        let windows = ImplWindow::all()?
+        let windows = catch_panics(ImplWindow::all)?
             .iter()
             .map(|impl_window| Window::new(impl_window.clone()))
             .collect();
 
         Ok(windows)
     }
+
+    /// List all windows for which `filter` returns `true`, sorted by z coordinate.
+    ///
+    /// ```no_run
+    /// use xcap::{Window, WindowLayer};
+    ///
+    /// let windows = Window::all_with(|window| {
+    ///     window.layer().is_ok_and(|layer| layer == WindowLayer::Normal)
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn all_with<F>(filter: F) -> XCapResult<Vec<Window>>
+    where
+        F: Fn(&Window) -> bool,
+    {
+        let windows = Window::all()?.into_iter().filter(filter).collect();
+
+        Ok(windows)
+    }
     /// 获取当前活动应用的信息
     ///
     /// 返回：(应用名称, 进程 ID, 显示序列号)
+    ///
+    /// macOS 上需要在主线程注册通知，从其他线程调用时宿主程序必须运行主线程的 RunLoop，
+    /// 否则返回错误
     pub async fn get_active_info() -> XCapResult<(String, i32, String)> {
         ImplWindow::get_active_info().await
     }
+    /// Which mode [`Window::get_active_info`] is currently using. On macOS this switches from
+    /// [`ActiveInfoMode::Polling`] to [`ActiveInfoMode::Notification`] once the main RunLoop has
+    /// been observed running; other platforms always poll.
+    pub fn active_info_mode() -> ActiveInfoMode {
+        ImplWindow::active_info_mode()
+    }
+    /// Watch the focused window, sending an [`ActiveTitle`] whenever the focused application or
+    /// its window title changes, e.g. when switching browser tabs. Only the focused window is
+    /// queried every `interval`, so sub-second intervals are cheap. On macOS the title is empty
+    /// without the screen recording permission.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use xcap::Window;
+    ///
+    /// let (_watcher, rx) = Window::watch_active_title(Duration::from_millis(250)).unwrap();
+    /// for active_title in rx {
+    ///     println!("{}: {}", active_title.app_name, active_title.title);
+    /// }
+    /// ```
+    pub fn watch_active_title(
+        interval: Duration,
+    ) -> XCapResult<(TitleWatcher, Receiver<ActiveTitle>)> {
+        TitleWatcher::new(interval)
+    }
 }
 
 impl Window {
@@ -80,14 +184,235 @@ impl Window {
     pub fn is_maximized(&self) -> XCapResult<bool> {
         self.impl_window.is_maximized()
     }
+    /// The bounds the window has when it is neither minimized nor maximized, i.e. the size and
+    /// position it restores to, in the same coordinates as [`Window::x`] and [`Window::width`].
+    /// Windows reads them from the window placement. X11 and macOS don't expose them, so the
+    /// bounds returned by the last call made while the window was not maximized or fullscreen
+    /// are used, falling back to the current bounds.
+    pub fn normal_bounds(&self) -> XCapResult<Rect> {
+        self.impl_window.normal_bounds()
+    }
+    /// Converters between global, window-local and captured-image coordinates, see
+    /// [`CoordinateSpace`]. The result is a snapshot and does not follow later moves.
+    pub fn coordinate_space(&self) -> XCapResult<CoordinateSpace> {
+        let bounds = Rect::new(self.x()?, self.y()?, self.width()?, self.height()?);
+
+        Ok(CoordinateSpace::new(
+            bounds,
+            self.impl_window.pixel_ratio()? as f64,
+        ))
+    }
     /// The window is focused.
     pub fn is_focused(&self) -> XCapResult<bool> {
         self.impl_window.is_focused()
     }
+    /// The window layer, derived from CGWindowLayer on macOS, extended window styles and class
+    /// on Windows and `_NET_WM_WINDOW_TYPE` on X11.
+    pub fn layer(&self) -> XCapResult<WindowLayer> {
+        self.impl_window.layer()
+    }
+    /// The window that owns this one, e.g. the main window of a dialog: the owner window on
+    /// Windows and `WM_TRANSIENT_FOR` on X11. macOS doesn't expose ownership, so panels, popups
+    /// and other windows above the normal level belong to the frontmost normal window of the
+    /// same application. `None` for top-level windows and owners not listed by [`Window::all`].
+    pub fn parent(&self) -> XCapResult<Option<Window>> {
+        let Some(parent_id) = self.impl_window.parent_id()? else {
+            return Ok(None);
+        };
+
+        Ok(Window::all()?
+            .into_iter()
+            .find(|window| window.id().ok() == Some(parent_id)))
+    }
+    /// The windows owned by this one, see [`Window::parent`], sorted by z coordinate. Pass them
+    /// with the window to [`Window::capture_group`] to capture a window with its dialogs.
+    pub fn children(&self) -> XCapResult<Vec<Window>> {
+        let id = self.id()?;
+
+        Window::all_with(|window| window.impl_window.parent_id().ok().flatten() == Some(id))
+    }
 }
 
 impl Window {
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
-        self.impl_window.capture_image()
+        catch_panics(|| self.impl_window.capture_image())
+    }
+
+    /// Capture a downscaled image of the window that fits within `max_size` x `max_size`,
+    /// keeping the aspect ratio.
+    pub fn capture_thumbnail(&self, max_size: u32) -> XCapResult<RgbaImage> {
+        let image = self.impl_window.capture_image()?;
+        let (width, height) = thumbnail_size(image.width(), image.height(), max_size);
+
+        Ok(imageops::thumbnail(&image, width, height))
+    }
+
+    /// Capture a region of the window, in coordinates relative to the window's top-left corner.
+    /// On Linux and macOS only the region's pixels are read; on Windows the window is captured
+    /// and then cropped.
+    pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
+        let region = Rect::new(x as i32, y as i32, width, height);
+        let bounds = Rect::new(0, 0, self.width()?, self.height()?);
+        if !bounds.contains_rect(region) {
+            return Err(XCapError::InvalidCaptureRegion(format!(
+                "Region {region:?} is outside window bounds {bounds:?}"
+            )));
+        }
+
+        catch_panics(|| self.impl_window.capture_region(x, y, width, height))
+    }
+
+    /// Experimental: capture a "long screenshot" of a scrollable window. The window is captured,
+    /// scrolled down by `scroll_step` wheel clicks with injected input, and captured again;
+    /// the newly revealed rows of each page are stitched below the previous ones until the
+    /// content stops moving or `max_pages` pages were captured. Fixed toolbars and status bars
+    /// appear once.
+    ///
+    /// Scrolling moves the mouse pointer over the window. On macOS it needs the Accessibility
+    /// permission; on Linux it needs X11 with the XTest extension. Animated content or sticky
+    /// elements inside the scrolled area can end the capture early.
+    pub fn capture_scrolling(&self, scroll_step: u32, max_pages: u32) -> XCapResult<RgbaImage> {
+        capture_scrolling(
+            || self.impl_window.capture_image(),
+            || self.impl_window.scroll(scroll_step.max(1)),
+            max_pages,
+        )
+    }
+
+    /// Call `callback` with the changed areas, in the coordinates of the window's captured image,
+    /// whenever the window's content changes, so it only needs to be re-captured when dirty.
+    /// On X11 changes are reported by the Damage extension and on Windows by the DXGI dirty
+    /// rectangles of the window's monitor; elsewhere the window is captured every 200ms and
+    /// compared with the previous capture.
+    ///
+    /// ```no_run
+    /// use xcap::Window;
+    ///
+    /// let window = Window::all().unwrap().remove(0);
+    /// let _watcher = window
+    ///     .watch(|rects| println!("{} areas changed", rects.len()))
+    ///     .unwrap();
+    /// ```
+    pub fn watch<F>(&self, callback: F) -> XCapResult<WindowWatcher>
+    where
+        F: FnMut(&[Rect]) + Send + 'static,
+    {
+        WindowWatcher::new(self, callback)
+    }
+
+    /// Cheaply check whether the window can be captured right now, without capturing. Besides
+    /// the checks of [`Monitor::can_capture`], this returns `false` when the window no longer
+    /// exists or its owner excluded it from capture: `kCGWindowSharingState` of none on macOS,
+    /// a display affinity set with `SetWindowDisplayAffinity` on Windows.
+    pub fn can_capture(&self) -> XCapResult<bool> {
+        self.impl_window.can_capture()
+    }
+
+    /// Capture the window encoded as PNG, e.g. for an HTTP screenshot endpoint. The pixels are
+    /// encoded straight from the capture buffer.
+    pub fn capture_png(&self) -> XCapResult<Vec<u8>> {
+        encode_png(&self.impl_window.capture_image()?)
+    }
+
+    /// Capture the window encoded as JPEG with `quality` from 1 to 100. The alpha channel is
+    /// dropped while encoding, without copying the pixels.
+    pub fn capture_jpeg(&self, quality: u8) -> XCapResult<Vec<u8>> {
+        encode_jpeg(&self.impl_window.capture_image()?, quality)
+    }
+
+    /// Capture `windows` into one image covering their combined bounds, each drawn at its screen
+    /// position in z-order, so overlapping windows cover each other as on screen. Everything
+    /// else, including other windows in between, is left transparent.
+    pub fn capture_group(windows: &[Window]) -> XCapResult<RgbaImage> {
+        let mut layers = windows
+            .iter()
+            .map(|window| {
+                let bounds = Rect::new(window.x()?, window.y()?, window.width()?, window.height()?);
+                Ok((window.z()?, bounds, window))
+            })
+            .collect::<XCapResult<Vec<_>>>()?;
+
+        let bounds = layers
+            .iter()
+            .map(|(_, bounds, _)| *bounds)
+            .collect::<Vec<_>>();
+        let Some(group_bounds) = union_rect(&bounds) else {
+            return Err(XCapError::new("No windows to capture"));
+        };
+
+        // z 越大越靠前，从后往前绘制
+        layers.sort_by_key(|(z, _, _)| *z);
+
+        let mut image = RgbaImage::new(group_bounds.width, group_bounds.height);
+        for (_, bounds, window) in layers {
+            let mut layer = window.capture_image()?;
+
+            // 高分屏上截图是物理像素，缩放到逻辑尺寸后再合成
+            if layer.dimensions() != (bounds.width, bounds.height) {
+                layer = imageops::resize(&layer, bounds.width, bounds.height, FilterType::Triangle);
+            }
+
+            imageops::overlay(
+                &mut image,
+                &layer,
+                bounds.x as i64 - group_bounds.x as i64,
+                bounds.y as i64 - group_bounds.y as i64,
+            );
+        }
+
+        Ok(image)
+    }
+
+    /// Capture image of the window, applying the options in `config`.
+    pub fn capture_image_with_config(&self, config: &CaptureConfig) -> XCapResult<RgbaImage> {
+        let (image, _) = self.capture_image_with_report(config)?;
+
+        Ok(image)
+    }
+
+    /// Capture image of the window like
+    /// [`capture_image_with_config`](Window::capture_image_with_config), also returning which backend took it and whether it fell back from another one.
+    pub fn capture_image_with_report(
+        &self,
+        config: &CaptureConfig,
+    ) -> XCapResult<(RgbaImage, CaptureReport)> {
+        let (image, report) = config.capture(|| catch_panics(|| self.impl_window.capture_image()));
+        let mut image = image?;
+        config.apply(&mut image);
+
+        Ok((image, report))
+    }
+
+    /// Capture image of the window after `delay`, blocking the calling thread.
+    pub fn capture_after(&self, delay: Duration) -> XCapResult<RgbaImage> {
+        capture_after(delay, None, || self.capture_image())
+    }
+
+    /// Capture image of the window once its content has stopped changing for 200ms, e.g. so a
+    /// UI test does not take a screenshot in the middle of an animation. Changes are taken from
+    /// X11 Damage events on X11 and DXGI dirty rectangles on Windows, so the window is captured
+    /// once it is stable. Elsewhere the window is captured every 50ms and compared with the
+    /// previous capture. If it is still changing after `timeout` the last capture is returned.
+    pub fn capture_when_stable(&self, timeout: Duration) -> XCapResult<RgbaImage> {
+        capture_when_stable(self.id()?, timeout, || self.capture_image())
+    }
+
+    /// Capture image of the window once the monitor it is on has presented its next frame, see
+    /// [`Monitor::capture_on_next_present`].
+    pub fn capture_on_next_present(&self) -> XCapResult<RgbaImage> {
+        self.current_monitor()?.impl_monitor.wait_for_present()?;
+
+        self.capture_image()
+    }
+
+    /// Capture image of the window after `delay`, then wait for the focused window to change
+    /// and settle before capturing. If focus does not change within `timeout` the capture is
+    /// taken anyway.
+    pub fn capture_after_focus_change(
+        &self,
+        delay: Duration,
+        timeout: Duration,
+    ) -> XCapResult<RgbaImage> {
+        capture_after(delay, Some(timeout), || self.capture_image())
     }
 }
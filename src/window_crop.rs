@@ -0,0 +1,231 @@
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+};
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+use std::{thread, time::Duration};
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+use crate::{Monitor, RecorderConfig};
+use crate::{Window, error::XCapResult, geometry::Rect, video_recorder::Frame};
+
+// 轮询窗口位置的间隔
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 窗口当前的位置，最小化时为 None
+pub(crate) fn window_bounds(window: &Window) -> XCapResult<Option<Rect>> {
+    if window.is_minimized()? {
+        return Ok(None);
+    }
+
+    Ok(Some(Rect::new(
+        window.x()?,
+        window.y()?,
+        window.width()?,
+        window.height()?,
+    )))
+}
+
+/// 把窗口在显示器上的部分换算成帧中的像素区域，monitor 和 window 都是全局坐标，
+/// 帧的像素尺寸可能和显示器的逻辑尺寸不同
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+fn crop_rect(monitor: Rect, window: Rect, width: u32, height: u32) -> Option<Rect> {
+    let visible = monitor.intersection(window)?;
+    let scale_x = width as f64 / monitor.width as f64;
+    let scale_y = height as f64 / monitor.height as f64;
+
+    let x = (visible.x - monitor.x) as f64;
+    let y = (visible.y - monitor.y) as f64;
+    let left = (x * scale_x).round() as u32;
+    let top = (y * scale_y).round() as u32;
+    let right = (((x + visible.width as f64) * scale_x).round() as u32).min(width);
+    let bottom = (((y + visible.height as f64) * scale_y).round() as u32).min(height);
+
+    if right <= left || bottom <= top {
+        return None;
+    }
+
+    Some(Rect::new(
+        left as i32,
+        top as i32,
+        right - left,
+        bottom - top,
+    ))
+}
+
+/// 复制帧中 rect 所在的像素
+pub(crate) fn crop_frame(frame: &Frame, rect: Rect) -> Option<Frame> {
+    let row_len = rect.width as usize * 4;
+    let mut raw = Vec::with_capacity(row_len * rect.height as usize);
+
+    for y in rect.y as usize..rect.y as usize + rect.height as usize {
+        let start = y * frame.stride() + rect.x as usize * 4;
+        raw.extend_from_slice(frame.data().get(start..start + row_len)?);
+    }
+
+    Some(Frame::with_stride(
+        rect.width,
+        rect.height,
+        row_len,
+        raw,
+        frame.timestamp(),
+    ))
+}
+
+/// 把显示器录制的帧裁剪到跟随的窗口，窗口的位置由后台线程轮询，
+/// 捕获线程只读取最近一次的结果
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+#[derive(Debug)]
+pub(crate) struct WindowCrop {
+    monitor: Rect,
+    window: Arc<Mutex<Option<Rect>>>,
+    stopped: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+impl WindowCrop {
+    /// 没有设置跟随窗口和录制区域时返回 None
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+    pub fn from_config(
+        config: &RecorderConfig,
+        monitor: &Monitor,
+    ) -> XCapResult<Option<WindowCrop>> {
+        match (config.follow_window, config.region) {
+            (Some(window_id), _) => Ok(Some(WindowCrop::new(window_id, monitor.bounds()?)?)),
+            (None, Some(region)) => Ok(Some(WindowCrop::fixed(monitor.bounds()?, region))),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// 裁剪到固定的区域，region 是显示器内的坐标，不需要轮询线程
+    fn fixed(monitor: Rect, region: Rect) -> WindowCrop {
+        let region = Rect::new(
+            monitor.x + region.x,
+            monitor.y + region.y,
+            region.width,
+            region.height,
+        );
+
+        WindowCrop {
+            monitor,
+            window: Arc::new(Mutex::new(Some(region))),
+            stopped: Arc::new(AtomicBool::new(false)),
+            worker: None,
+        }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+    fn new(window_id: u32, monitor: Rect) -> XCapResult<WindowCrop> {
+        // 窗口不存在时创建录制器失败
+        let window = Arc::new(Mutex::new(window_bounds(&Window::from_id(window_id)?)?));
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let stopped = stopped.clone();
+            let window = window.clone();
+            thread::spawn(move || {
+                // Windows 上 HWND 不能跨线程传递，在线程中按 id 重新查找窗口
+                let mut current = None;
+
+                while !stopped.load(Ordering::Relaxed) {
+                    thread::sleep(WINDOW_POLL_INTERVAL);
+
+                    if current.is_none() {
+                        current = Window::from_id(window_id).ok();
+                    }
+
+                    let bounds = match current.as_ref().map(window_bounds) {
+                        Some(Ok(bounds)) => bounds,
+                        Some(Err(err)) => {
+                            log::debug!("get followed window bounds failed: {err:?}");
+                            // 窗口可能已经关闭，下次重新查找
+                            current = None;
+                            None
+                        }
+                        None => None,
+                    };
+
+                    if let Ok(mut window) = window.lock() {
+                        *window = bounds;
+                    }
+                }
+            })
+        };
+
+        Ok(WindowCrop {
+            monitor,
+            window,
+            stopped,
+            worker: Some(worker),
+        })
+    }
+
+    /// 裁剪出窗口所在的区域，窗口最小化、关闭或不在这个显示器上时返回 None
+    pub fn crop(&self, frame: &Frame) -> Option<Frame> {
+        let window = (*self.window.lock().ok()?)?;
+        let rect = crop_rect(self.monitor, window, frame.width(), frame.height())?;
+
+        crop_frame(frame, rect)
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux", test))]
+impl Drop for WindowCrop {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn test_crop_rect() {
+        let monitor = Rect::new(-100, 0, 100, 50);
+
+        // 2 倍缩放的显示器，窗口的一部分在显示器外
+        assert_eq!(
+            crop_rect(monitor, Rect::new(-20, 10, 40, 20), 200, 100),
+            Some(Rect::new(160, 20, 40, 40))
+        );
+        assert_eq!(crop_rect(monitor, Rect::new(0, 0, 40, 20), 200, 100), None);
+    }
+
+    #[test]
+    fn test_crop_frame() {
+        // 3x2 像素，每行有 4 字节填充
+        let data = (0..32).collect();
+        let frame = Frame::with_stride(3, 2, 16, data, Instant::now());
+
+        let cropped = crop_frame(&frame, Rect::new(1, 1, 2, 1)).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (2, 1));
+        assert_eq!(cropped.data(), &(20..28).collect::<Vec<u8>>()[..]);
+
+        assert!(crop_frame(&frame, Rect::new(2, 1, 3, 1)).is_none());
+    }
+
+    #[test]
+    fn test_fixed_crop() {
+        let data = (0..32).collect();
+        let frame = Frame::with_stride(3, 2, 16, data, Instant::now());
+
+        // region 是显示器内的坐标
+        let crop = WindowCrop::fixed(Rect::new(-3, 0, 3, 2), Rect::new(1, 1, 2, 1));
+        let cropped = crop.crop(&frame).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (2, 1));
+        assert_eq!(cropped.data(), &(20..28).collect::<Vec<u8>>()[..]);
+    }
+}
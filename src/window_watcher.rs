@@ -0,0 +1,115 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    Window, diff::diff, error::XCapResult, geometry::Rect, platform::impl_window::ImplWindowDamage,
+    video_recorder::Frame,
+};
+
+// 没有原生通知时轮询窗口截图的间隔
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_millis(200);
+// 等待原生通知的超时时间，超时后检查监听是否已经停止
+const DAMAGE_WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// 截取窗口并转换为 Frame，用于和上一次截图比较
+fn capture_frame(window: &Window) -> XCapResult<Frame> {
+    let image = window.capture_image()?;
+    let (width, height) = image.dimensions();
+
+    Ok(Frame::new(width, height, image.into_raw()))
+}
+
+/// Watches a window's content and calls back with the changed areas, see [`Window::watch`].
+///
+/// Dropping the watcher stops its thread.
+#[derive(Debug)]
+pub struct WindowWatcher {
+    stopped: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl WindowWatcher {
+    pub(crate) fn new<F>(window: &Window, mut callback: F) -> XCapResult<WindowWatcher>
+    where
+        F: FnMut(&[Rect]) + Send + 'static,
+    {
+        let window_id = window.id()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let worker = match ImplWindowDamage::new(window_id) {
+            // X11 上使用 Damage 扩展，Windows 上使用桌面复制的脏矩形，窗口内容变化时由系统通知
+            Ok(mut damage) => {
+                let stopped = stopped.clone();
+                thread::spawn(move || {
+                    while !stopped.load(Ordering::Relaxed) {
+                        match damage.wait(DAMAGE_WAIT_TIMEOUT) {
+                            Ok(rects) if !rects.is_empty() => callback(&rects),
+                            Ok(_) => {}
+                            Err(err) => {
+                                log::debug!("wait for window damage failed: {err:?}");
+                                break;
+                            }
+                        }
+                    }
+                })
+            }
+            // 其他平台轮询截图，和上一次截图比较得到变化的区域
+            Err(_) => {
+                let mut previous = capture_frame(window)?;
+                let stopped = stopped.clone();
+                // Windows 上 HWND 不能跨线程传递，在线程中按 id 重新查找窗口
+                thread::spawn(move || {
+                    while !stopped.load(Ordering::Relaxed) {
+                        thread::sleep(WINDOW_POLL_INTERVAL);
+
+                        let current =
+                            Window::from_id(window_id).and_then(|window| capture_frame(&window));
+                        let current = match current {
+                            Ok(current) => current,
+                            Err(err) => {
+                                log::debug!("capture window failed: {err:?}");
+                                continue;
+                            }
+                        };
+
+                        let rects = diff(&previous, &current);
+                        if !rects.is_empty() {
+                            callback(&rects);
+                        }
+                        previous = current;
+                    }
+                })
+            }
+        };
+
+        Ok(WindowWatcher {
+            stopped,
+            worker: Some(worker),
+        })
+    }
+
+    /// Stop watching and wait for the watcher thread to exit.
+    pub fn stop(mut self) {
+        self.stop_worker();
+    }
+
+    fn stop_worker(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for WindowWatcher {
+    fn drop(&mut self) {
+        self.stop_worker();
+    }
+}
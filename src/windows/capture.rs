@@ -1,4 +1,4 @@
-use std::{ffi::c_void, mem};
+use std::{ffi::c_void, mem, time::Instant};
 
 use image::{DynamicImage, RgbaImage};
 use scopeguard::guard;
@@ -8,16 +8,19 @@ use windows::Win32::{
         Dwm::DwmIsCompositionEnabled,
         Gdi::{
             BITMAP, BITMAPINFO, BITMAPINFOHEADER, BitBlt, CreateCompatibleBitmap,
-            CreateCompatibleDC, DIB_RGB_COLORS, DeleteDC, DeleteObject, GetCurrentObject,
-            GetDIBits, GetObjectW, GetWindowDC, HBITMAP, HDC, OBJ_BITMAP, ReleaseDC, SRCCOPY,
-            SelectObject,
+            CreateCompatibleDC, DIB_RGB_COLORS, DeleteDC, DeleteObject, GetCurrentObject, GetDC,
+            GetDIBits, GetObjectW, GetWindowDC, HALFTONE, HBITMAP, HDC, OBJ_BITMAP, PaintDesktop,
+            ReleaseDC, SRCCOPY, SelectObject, SetBrushOrgEx, SetStretchBltMode, SetViewportOrgEx,
+            StretchBlt,
         },
     },
     Storage::Xps::{PRINT_WINDOW_FLAGS, PrintWindow},
-    UI::WindowsAndMessaging::GetDesktopWindow,
 };
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    Config, Stage,
+    error::{XCapError, XCapResult},
+};
 
 use super::utils::{bgra_to_rgba_image, get_os_major_version, get_window_info};
 
@@ -27,6 +30,7 @@ fn to_rgba_image(
     width: i32,
     height: i32,
 ) -> XCapResult<RgbaImage> {
+    let started_at = Instant::now();
     let buffer_size = width * height * 4;
     let mut bitmap_info = BITMAPINFO {
         bmiHeader: BITMAPINFOHEADER {
@@ -61,7 +65,10 @@ fn to_rgba_image(
         }
     };
 
-    bgra_to_rgba_image(width as u32, height as u32, buffer)
+    let image = bgra_to_rgba_image(width as u32, height as u32, buffer);
+    Config::get().record_stage(Stage::Conversion, started_at.elapsed());
+
+    image
 }
 
 fn delete_bitmap_object(val: HBITMAP) {
@@ -77,9 +84,10 @@ fn delete_bitmap_object(val: HBITMAP) {
 #[allow(unused)]
 pub fn capture_monitor(x: i32, y: i32, width: i32, height: i32) -> XCapResult<RgbaImage> {
     unsafe {
-        let hwnd = GetDesktopWindow();
-        let scope_guard_hdc_desktop_window = guard(GetWindowDC(Some(hwnd)), |val| {
-            if ReleaseDC(Some(hwnd), val) != 1 {
+        // 桌面窗口只覆盖主显示器，主显示器左侧或上方的显示器坐标为负数，
+        // 所以使用整个虚拟屏幕的 DC，它的原点同样是主显示器的左上角
+        let scope_guard_hdc_desktop_window = guard(GetDC(None), |val| {
+            if ReleaseDC(None, val) != 1 {
                 log::error!("ReleaseDC({:?}) failed: {:?}", val, GetLastError());
             }
         });
@@ -106,6 +114,7 @@ pub fn capture_monitor(x: i32, y: i32, width: i32, height: i32) -> XCapResult<Rg
         // 拷贝原始图像到内存
         // 这里不需要缩放图片，所以直接使用BitBlt
         // 如需要缩放，则使用 StretchBlt
+        let started_at = Instant::now();
         BitBlt(
             *scope_guard_mem,
             0,
@@ -117,11 +126,113 @@ pub fn capture_monitor(x: i32, y: i32, width: i32, height: i32) -> XCapResult<Rg
             y,
             SRCCOPY,
         )?;
+        Config::get().record_stage(Stage::FirstFrame, started_at.elapsed());
+
+        to_rgba_image(*scope_guard_mem, *scope_guard_h_bitmap, width, height)
+    }
+}
+
+/// 把显示器区域的桌面壁纸绘制到图像中，不包括桌面图标和窗口
+pub fn capture_wallpaper(x: i32, y: i32, width: i32, height: i32) -> XCapResult<RgbaImage> {
+    unsafe {
+        let scope_guard_hdc_desktop_window = guard(GetDC(None), |val| {
+            if ReleaseDC(None, val) != 1 {
+                log::error!("ReleaseDC({:?}) failed: {:?}", val, GetLastError());
+            }
+        });
+
+        let scope_guard_mem = guard(
+            CreateCompatibleDC(Some(*scope_guard_hdc_desktop_window)),
+            |val| {
+                if !DeleteDC(val).as_bool() {
+                    log::error!("DeleteDC({:?}) failed: {:?}", val, GetLastError());
+                }
+            },
+        );
+
+        let scope_guard_h_bitmap = guard(
+            CreateCompatibleBitmap(*scope_guard_hdc_desktop_window, width, height),
+            delete_bitmap_object,
+        );
+
+        SelectObject(*scope_guard_mem, (*scope_guard_h_bitmap).into());
+
+        // PaintDesktop 按虚拟屏幕坐标绘制壁纸，移动原点让显示器的左上角对应位图的原点
+        if !SetViewportOrgEx(*scope_guard_mem, -x, -y, None).as_bool() {
+            return Err(XCapError::new("SetViewportOrgEx failed"));
+        }
+        if !PaintDesktop(*scope_guard_mem).as_bool() {
+            return Err(XCapError::new("PaintDesktop failed"));
+        }
 
         to_rgba_image(*scope_guard_mem, *scope_guard_h_bitmap, width, height)
     }
 }
 
+/// 截取显示器并在 GDI 中直接缩放到 dst_width x dst_height，不会在内存中生成完整尺寸的图像
+/// DWM 缩略图只能绘制到调用方的窗口中，无法读取像素，所以缩略图使用 StretchBlt
+pub fn capture_monitor_scaled(
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    dst_width: i32,
+    dst_height: i32,
+) -> XCapResult<RgbaImage> {
+    unsafe {
+        let scope_guard_hdc_desktop_window = guard(GetDC(None), |val| {
+            if ReleaseDC(None, val) != 1 {
+                log::error!("ReleaseDC({:?}) failed: {:?}", val, GetLastError());
+            }
+        });
+
+        let scope_guard_mem = guard(
+            CreateCompatibleDC(Some(*scope_guard_hdc_desktop_window)),
+            |val| {
+                if !DeleteDC(val).as_bool() {
+                    log::error!("DeleteDC({:?}) failed: {:?}", val, GetLastError());
+                }
+            },
+        );
+
+        let scope_guard_h_bitmap = guard(
+            CreateCompatibleBitmap(*scope_guard_hdc_desktop_window, dst_width, dst_height),
+            delete_bitmap_object,
+        );
+
+        SelectObject(*scope_guard_mem, (*scope_guard_h_bitmap).into());
+
+        // HALFTONE 模式缩小时对像素取平均，设置后需要重置画刷原点
+        // https://learn.microsoft.com/zh-cn/windows/win32/api/wingdi/nf-wingdi-setstretchbltmode
+        SetStretchBltMode(*scope_guard_mem, HALFTONE);
+        SetBrushOrgEx(*scope_guard_mem, 0, 0, None).ok()?;
+
+        let started_at = Instant::now();
+        StretchBlt(
+            *scope_guard_mem,
+            0,
+            0,
+            dst_width,
+            dst_height,
+            Some(*scope_guard_hdc_desktop_window),
+            x,
+            y,
+            width,
+            height,
+            SRCCOPY,
+        )
+        .ok()?;
+        Config::get().record_stage(Stage::FirstFrame, started_at.elapsed());
+
+        to_rgba_image(
+            *scope_guard_mem,
+            *scope_guard_h_bitmap,
+            dst_width,
+            dst_height,
+        )
+    }
+}
+
 #[allow(unused)]
 pub fn capture_window(hwnd: HWND, scale_factor: f32) -> XCapResult<RgbaImage> {
     let window_info = get_window_info(hwnd)?;
@@ -170,6 +281,7 @@ pub fn capture_window(hwnd: HWND, scale_factor: f32) -> XCapResult<RgbaImage> {
 
         let previous_object = SelectObject(*scope_guard_hdc_mem, (*scope_guard_h_bitmap).into());
 
+        let started_at = Instant::now();
         let mut is_success = false;
 
         // https://webrtc.googlesource.com/src.git/+/refs/heads/main/modules/desktop_capture/win/window_capturer_win_gdi.cc#301
@@ -201,6 +313,7 @@ pub fn capture_window(hwnd: HWND, scale_factor: f32) -> XCapResult<RgbaImage> {
         }
 
         SelectObject(*scope_guard_hdc_mem, previous_object);
+        Config::get().record_stage(Stage::FirstFrame, started_at.elapsed());
 
         let image = to_rgba_image(*scope_guard_hdc_mem, *scope_guard_h_bitmap, width, height)?;
 
@@ -231,6 +344,21 @@ mod tests {
         assert_eq!(image.height(), 100);
     }
 
+    #[test]
+    fn test_capture_monitor_negative_origin() {
+        // 主显示器左侧或上方的显示器
+        for monitor in crate::Monitor::all().unwrap() {
+            let (x, y) = (monitor.x().unwrap(), monitor.y().unwrap());
+            if x >= 0 && y >= 0 {
+                continue;
+            }
+
+            let image = capture_monitor(x, y, 100, 100).unwrap();
+            assert_eq!(image.width(), 100);
+            assert_eq!(image.height(), 100);
+        }
+    }
+
     #[test]
     fn test_capture_window() {
         unsafe {
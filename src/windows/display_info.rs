@@ -1,7 +1,7 @@
 //! Windows 显示器信息获取工具
 //!
-//! 本模块提供了 Windows 平台的显示器 UUID 和序列号获取功能。
-//! 使用 WMI (Windows Management Instrumentation)。
+//! 本模块提供了 Windows 平台的显示器 UUID、序列号和厂商型号信息获取功能。
+//! 使用 WMI (Windows Management Instrumentation) 和注册表中的 EDID。
 //!
 //! # 兼容性
 //!
@@ -9,9 +9,11 @@
 //! - ✅ 使用 WMI 从 EDID 提取信息
 //! - ✅ 支持多显示器环境
 
+use widestring::U16CString;
 use windows::{
-    core::{BSTR, HSTRING},
+    core::{w, BSTR, HSTRING},
     Win32::{
+        Devices::Display::DISPLAYCONFIG_TARGET_DEVICE_NAME,
         Graphics::Gdi::HMONITOR,
         System::{
             Com::{
@@ -22,6 +24,7 @@ use windows::{
                 SafeArrayAccessData, SafeArrayGetLBound, SafeArrayGetUBound,
                 SafeArrayUnaccessData,
             },
+            Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_BINARY},
             Variant::{VARIANT, VT_ARRAY, VT_UI1},
             Wmi::{
                 IWbemClassObject, IWbemLocator, IWbemServices, WbemLocator,
@@ -31,7 +34,10 @@ use windows::{
     },
 };
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    error::{XCapError, XCapResult},
+    monitor_identity::{parse_edid, pnp_vendor_id, wmi_string, MonitorIdentity},
+};
 
 /// 从 WMI 获取显示器信息
 /// 使用 WmiMonitorID 类来获取 EDID 信息
@@ -141,6 +147,72 @@ pub fn get_display_serial_from_wmi(_h_monitor: HMONITOR) -> XCapResult<String> {
     }
 }
 
+/// 从 WMI 获取内置显示器的亮度，WmiMonitorBrightness 只包含支持调节亮度的内置显示器
+pub fn get_display_brightness_from_wmi() -> XCapResult<f32> {
+    unsafe {
+        CoInitializeEx(None, COINIT_MULTITHREADED).ok()?;
+
+        let _com_guard = scopeguard::guard((), |_| {
+            CoUninitialize();
+        });
+
+        let locator: IWbemLocator = CoCreateInstance(&WbemLocator, None, CLSCTX_INPROC_SERVER)?;
+
+        let namespace = BSTR::from("root\\wmi");
+        let services: IWbemServices =
+            locator.ConnectServer(&namespace, &BSTR::default(), &BSTR::default(), &BSTR::default(), 0, &BSTR::default(), None)?;
+
+        let query = BSTR::from("SELECT CurrentBrightness FROM WmiMonitorBrightness WHERE Active = TRUE");
+        let query_language = BSTR::from("WQL");
+
+        let enumerator = services.ExecQuery(
+            &query_language,
+            &query,
+            WBEM_FLAG_FORWARD_ONLY | WBEM_FLAG_RETURN_IMMEDIATELY,
+            None,
+        )?;
+
+        let mut objects = [None; 1];
+        let mut returned = 0u32;
+
+        while enumerator
+            .Next(WBEM_INFINITE, &mut objects, &mut returned)
+            .is_ok()
+            && returned > 0
+        {
+            if let Some(obj) = &objects[0] {
+                // CurrentBrightness 是 0 到 100 的百分比
+                if let Some(brightness) = get_u8_property(obj, "CurrentBrightness")? {
+                    return Ok((brightness as f32 / 100.0).clamp(0.0, 1.0));
+                }
+            }
+        }
+
+        Err(XCapError::NotSupported)
+    }
+}
+
+/// 从 WMI 对象中提取 uint8 属性
+unsafe fn get_u8_property(
+    obj: &IWbemClassObject,
+    property_name: &str,
+) -> XCapResult<Option<u8>> {
+    let prop_name = HSTRING::from(property_name);
+    let mut value = VARIANT::default();
+
+    obj.Get(&prop_name, 0, &mut value, None, None)?;
+
+    // 和 get_string_property 一样直接读取 VARIANT 的内存布局，vt 在偏移 0，bVal 在偏移 8
+    let variant_ptr = &value as *const VARIANT as *const u8;
+    let vt = *(variant_ptr as *const u16);
+
+    if vt != VT_UI1.0 as u16 {
+        return Ok(None);
+    }
+
+    Ok(Some(*variant_ptr.add(8)))
+}
+
 /// 从 WMI 对象中提取字符串属性
 unsafe fn get_string_property(
     obj: &IWbemClassObject,
@@ -170,18 +242,21 @@ unsafe fn get_string_property(
     let lower_bound = SafeArrayGetLBound(parray, 1)?;
     let upper_bound = SafeArrayGetUBound(parray, 1)?;
 
-    let count = (upper_bound - lower_bound + 1) as usize;
+    // 空数组的上界比下界小 1，损坏的数组上界可能更小，不能直接转换为 usize
+    let count = usize::try_from(i64::from(upper_bound) - i64::from(lower_bound) + 1).unwrap_or(0);
+    if count == 0 {
+        return Ok(String::new());
+    }
 
     // Use SafeArrayAccessData for efficient direct memory access
     let mut data_ptr: *mut std::ffi::c_void = std::ptr::null_mut();
     SafeArrayAccessData(parray, &mut data_ptr)?;
 
-    let bytes = std::slice::from_raw_parts(data_ptr as *const u8, count);
-    let result: String = bytes
-        .iter()
-        .filter(|&&b| b != 0)
-        .map(|&b| b as char)
-        .collect();
+    let result = if data_ptr.is_null() {
+        String::new()
+    } else {
+        wmi_string(std::slice::from_raw_parts(data_ptr as *const u8, count))
+    };
 
     SafeArrayUnaccessData(parray)?;
 
@@ -217,3 +292,83 @@ pub fn get_display_serial_number(h_monitor: HMONITOR) -> XCapResult<String> {
     ))
 }
 
+
+/// 从显示器的设备路径得到注册表中设备参数的路径
+/// 设备路径格式为 \\?\DISPLAY#DEL4321#5&1a2b3c4d&0&UID4353#{e6f07b5f-ee97-4a90-b076-33f57bf4eaa7}
+fn get_device_parameters_key(device_path: &str) -> Option<String> {
+    let mut parts = device_path.split('#');
+    parts.next()?;
+    let hardware_id = parts.next()?;
+    let instance_id = parts.next()?;
+
+    Some(format!(
+        r"SYSTEM\CurrentControlSet\Enum\DISPLAY\{hardware_id}\{instance_id}\Device Parameters"
+    ))
+}
+
+/// 读取注册表中保存的显示器 EDID
+fn get_edid_from_registry(device_path: &str) -> XCapResult<Vec<u8>> {
+    let key = get_device_parameters_key(device_path)
+        .ok_or_else(|| XCapError::new("Invalid monitor device path"))?;
+    let key = HSTRING::from(key);
+
+    unsafe {
+        // 第一次调用获取数据长度
+        let mut len = 0u32;
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            &key,
+            w!("EDID"),
+            RRF_RT_REG_BINARY,
+            None,
+            None,
+            Some(&mut len),
+        )
+        .ok()?;
+
+        let mut edid = vec![0u8; len as usize];
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            &key,
+            w!("EDID"),
+            RRF_RT_REG_BINARY,
+            None,
+            Some(edid.as_mut_ptr().cast()),
+            Some(&mut len),
+        )
+        .ok()?;
+        edid.truncate(len as usize);
+
+        Ok(edid)
+    }
+}
+
+/// 获取显示器的厂商、型号和序列号
+/// 优先解析注册表中的 EDID，读取失败时使用 DisplayConfig 提供的厂商和型号
+pub fn get_display_identity(
+    target: &DISPLAYCONFIG_TARGET_DEVICE_NAME,
+) -> XCapResult<MonitorIdentity> {
+    let device_path = U16CString::from_vec_truncate(target.monitorDevicePath).to_string()?;
+
+    match get_edid_from_registry(&device_path).and_then(|edid| parse_edid(&edid)) {
+        Ok(edid) => return Ok(edid.identity),
+        Err(err) => log::debug!("read monitor EDID from registry failed: {err:?}"),
+    }
+
+    // flags 的第 3 位为 edidIdsValid
+    let edid_ids_valid = unsafe { target.flags.Anonymous.value } & 0x4 != 0;
+    if !edid_ids_valid {
+        return Err(XCapError::new("Monitor EDID not available"));
+    }
+
+    let name = U16CString::from_vec_truncate(target.monitorFriendlyDeviceName).to_string()?;
+
+    Ok(MonitorIdentity {
+        // DisplayConfig 返回的制造商 ID 和 EDID 中的字节序相反
+        vendor: pnp_vendor_id(target.edidManufactureId.swap_bytes()),
+        product: target.edidProductCodeId,
+        serial: None,
+        name: (!name.is_empty()).then_some(name),
+        manufacture_date: None,
+    })
+}
@@ -0,0 +1,273 @@
+use std::{ffi::c_void, sync::Arc};
+
+use windows::{
+    Win32::{
+        Foundation::{CloseHandle, GENERIC_ALL, HANDLE},
+        Graphics::{
+            Direct3D11::{
+                D3D11_BIND_RENDER_TARGET, D3D11_BIND_SHADER_RESOURCE, D3D11_FENCE_FLAG_SHARED,
+                D3D11_RESOURCE_MISC_SHARED, D3D11_RESOURCE_MISC_SHARED_NTHANDLE,
+                D3D11_TEXTURE2D_DESC, D3D11_USAGE_DEFAULT, ID3D11Device, ID3D11Device5,
+                ID3D11DeviceContext, ID3D11DeviceContext4, ID3D11Fence, ID3D11Texture2D,
+            },
+            Dxgi::{
+                Common::{DXGI_FORMAT, DXGI_SAMPLE_DESC},
+                DXGI_SHARED_RESOURCE_READ, DXGI_SHARED_RESOURCE_WRITE, IDXGIResource1,
+            },
+        },
+    },
+    core::{Interface, PCWSTR},
+};
+
+use crate::{
+    Frame,
+    error::{XCapError, XCapResult},
+};
+
+// 同时被帧持有的共享纹理上限，全部被持有时新的帧不带共享纹理
+const MAX_SURFACES: usize = 4;
+
+/// NT 句柄在最后一个引用释放时关闭
+///
+/// 内核句柄在整个进程中有效，和创建它的线程无关，保存为整数后自动满足 Send 和 Sync，
+/// 句柄引用的共享资源在句柄关闭之前一直有效，即使录制线程已经释放了对应的 D3D11 对象
+#[derive(Debug)]
+struct SharedHandle(usize);
+
+impl SharedHandle {
+    fn new(handle: HANDLE) -> SharedHandle {
+        SharedHandle(handle.0 as usize)
+    }
+
+    fn handle(&self) -> HANDLE {
+        HANDLE(self.0 as *mut c_void)
+    }
+}
+
+impl Drop for SharedHandle {
+    fn drop(&mut self) {
+        if let Err(err) = unsafe { CloseHandle(self.handle()) } {
+            log::error!("CloseHandle({:?}) failed: {:?}", self.handle(), err);
+        }
+    }
+}
+
+/// 帧持有的共享纹理，只包含句柄和描述
+#[derive(Debug)]
+struct SharedTexture {
+    handle: SharedHandle,
+    desc: D3D11_TEXTURE2D_DESC,
+}
+
+/// 录制线程中的 fence，D3D11 对象不离开录制线程
+#[derive(Debug)]
+struct PooledFence {
+    fence: ID3D11Fence,
+    handle: Arc<SharedHandle>,
+}
+
+/// 录制线程中的共享纹理，shared 被帧持有时纹理不会被写入
+#[derive(Debug)]
+struct PooledTexture {
+    texture: ID3D11Texture2D,
+    shared: Arc<SharedTexture>,
+}
+
+/// The GPU texture a recorder frame was captured into, shared with other devices and
+/// processes so an external encoder, e.g. NVENC or Media Foundation, can read it without a
+/// copy to system memory. See [`Frame::encoder_surface`] and
+/// [`RecorderConfig::encoder_surface`](crate::RecorderConfig::encoder_surface).
+///
+/// Synchronization contract:
+///
+/// 1. Open [`texture_handle`](ExternalEncoderSurface::texture_handle) with
+///    `ID3D11Device1::OpenSharedResource1` or `ID3D12Device::OpenSharedHandle`, and
+///    [`fence_handle`](ExternalEncoderSurface::fence_handle) with `ID3D11Device5::OpenSharedFence`
+///    or `ID3D12Device::OpenSharedHandle`. A recorder reuses a few textures and a single fence,
+///    so cache the opened objects by handle.
+/// 2. Make the encoder's GPU queue wait until the fence reaches
+///    [`fence_value`](ExternalEncoderSurface::fence_value) before reading the texture, e.g. with
+///    `ID3D11DeviceContext4::Wait` or `ID3D12CommandQueue::Wait`.
+/// 3. Keep the surface, or the frame it came from, alive until the encoder's reads have
+///    completed on the GPU. The recorder only writes to textures no frame holds anymore.
+///
+/// The texture holds the whole monitor in BGRA as delivered by Desktop Duplication, before
+/// [`RecorderConfig::scale`](crate::RecorderConfig::scale) and
+/// [`RecorderConfig::frame_hook`](crate::RecorderConfig::frame_hook). Cropped and scaled frames
+/// have no surface.
+#[derive(Debug, Clone)]
+pub struct ExternalEncoderSurface {
+    texture: Arc<SharedTexture>,
+    fence: Arc<SharedHandle>,
+    fence_value: u64,
+}
+
+impl ExternalEncoderSurface {
+    /// The NT handle of the shared texture, valid while the surface is alive.
+    pub fn texture_handle(&self) -> HANDLE {
+        self.texture.handle.handle()
+    }
+
+    /// The NT handle of the shared fence, valid while the surface is alive.
+    pub fn fence_handle(&self) -> HANDLE {
+        self.fence.handle()
+    }
+
+    /// The fence value signaled once the frame has been copied into the texture.
+    pub fn fence_value(&self) -> u64 {
+        self.fence_value
+    }
+
+    /// The texture width in pixels.
+    pub fn width(&self) -> u32 {
+        self.texture.desc.Width
+    }
+
+    /// The texture height in pixels.
+    pub fn height(&self) -> u32 {
+        self.texture.desc.Height
+    }
+
+    /// The texture format, `DXGI_FORMAT_B8G8R8A8_UNORM` for Desktop Duplication.
+    pub fn format(&self) -> DXGI_FORMAT {
+        self.texture.desc.Format
+    }
+}
+
+/// 录制线程中复用的共享纹理，设备重建后全部丢弃
+#[derive(Debug, Default)]
+pub(crate) struct SurfacePool {
+    device: Option<ID3D11Device>,
+    fence: Option<PooledFence>,
+    fence_value: u64,
+    textures: Vec<PooledTexture>,
+}
+
+impl SurfacePool {
+    fn create_fence(d3d_device: &ID3D11Device) -> XCapResult<PooledFence> {
+        unsafe {
+            let fence: ID3D11Fence = d3d_device
+                .cast::<ID3D11Device5>()?
+                .CreateFence(0, D3D11_FENCE_FLAG_SHARED)?;
+            let handle = fence.CreateSharedHandle(None, GENERIC_ALL.0, PCWSTR::null())?;
+
+            Ok(PooledFence {
+                fence,
+                handle: Arc::new(SharedHandle::new(handle)),
+            })
+        }
+    }
+
+    fn create_texture(
+        d3d_device: &ID3D11Device,
+        source_desc: &D3D11_TEXTURE2D_DESC,
+    ) -> XCapResult<PooledTexture> {
+        unsafe {
+            let desc = D3D11_TEXTURE2D_DESC {
+                Width: source_desc.Width,
+                Height: source_desc.Height,
+                MipLevels: 1,
+                ArraySize: 1,
+                Format: source_desc.Format,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Usage: D3D11_USAGE_DEFAULT,
+                BindFlags: (D3D11_BIND_SHADER_RESOURCE.0 | D3D11_BIND_RENDER_TARGET.0) as u32,
+                CPUAccessFlags: 0,
+                MiscFlags: (D3D11_RESOURCE_MISC_SHARED.0 | D3D11_RESOURCE_MISC_SHARED_NTHANDLE.0)
+                    as u32,
+            };
+
+            let mut texture = None;
+            d3d_device.CreateTexture2D(&desc, None, Some(&mut texture))?;
+            let texture: ID3D11Texture2D =
+                texture.ok_or(XCapError::new("CreateTexture2D failed"))?;
+
+            let handle = texture.cast::<IDXGIResource1>()?.CreateSharedHandle(
+                None,
+                DXGI_SHARED_RESOURCE_READ | DXGI_SHARED_RESOURCE_WRITE,
+                PCWSTR::null(),
+            )?;
+
+            Ok(PooledTexture {
+                texture,
+                shared: Arc::new(SharedTexture {
+                    handle: SharedHandle::new(handle),
+                    desc,
+                }),
+            })
+        }
+    }
+
+    /// 把桌面复制的纹理复制到没有被帧持有的共享纹理中，并在复制完成后发出 fence 信号
+    pub fn copy(
+        &mut self,
+        d3d_device: &ID3D11Device,
+        d3d_context: &ID3D11DeviceContext,
+        source_texture: &ID3D11Texture2D,
+    ) -> XCapResult<Option<ExternalEncoderSurface>> {
+        if self.device.as_ref() != Some(d3d_device) {
+            *self = SurfacePool {
+                device: Some(d3d_device.clone()),
+                ..Default::default()
+            };
+        }
+
+        let mut source_desc = D3D11_TEXTURE2D_DESC::default();
+        unsafe { source_texture.GetDesc(&mut source_desc) };
+
+        // 分辨率变化后旧的纹理不能再使用
+        self.textures.retain(|texture| {
+            texture.shared.desc.Width == source_desc.Width
+                && texture.shared.desc.Height == source_desc.Height
+        });
+
+        let index = match self
+            .textures
+            .iter()
+            .position(|texture| Arc::strong_count(&texture.shared) == 1)
+        {
+            Some(index) => index,
+            None if self.textures.len() < MAX_SURFACES => {
+                let texture = SurfacePool::create_texture(d3d_device, &source_desc)?;
+                self.textures.push(texture);
+                self.textures.len() - 1
+            }
+            None => {
+                log::debug!("all encoder surfaces are held by frames, skipping");
+                return Ok(None);
+            }
+        };
+        let texture = &self.textures[index];
+
+        let fence = match self.fence.take() {
+            Some(fence) => fence,
+            None => SurfacePool::create_fence(d3d_device)?,
+        };
+        let fence = self.fence.insert(fence);
+
+        self.fence_value += 1;
+        unsafe {
+            d3d_context.CopyResource(
+                Some(&texture.texture.cast()?),
+                Some(&source_texture.cast()?),
+            );
+            d3d_context
+                .cast::<ID3D11DeviceContext4>()?
+                .Signal(&fence.fence, self.fence_value)?;
+            d3d_context.Flush();
+        }
+
+        Ok(Some(ExternalEncoderSurface {
+            texture: texture.shared.clone(),
+            fence: fence.handle.clone(),
+            fence_value: self.fence_value,
+        }))
+    }
+}
+
+pub(crate) fn encoder_surface(frame: &Frame) -> Option<ExternalEncoderSurface> {
+    frame.encoder_surface.clone()
+}
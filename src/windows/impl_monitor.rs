@@ -1,33 +1,69 @@
-use std::{mem, ptr, sync::mpsc::Receiver};
+use std::{
+    mem, ptr,
+    sync::{Arc, mpsc::Receiver},
+};
 
 use image::RgbaImage;
 use scopeguard::guard;
 use widestring::U16CString;
+#[cfg(feature = "ddc")]
+use windows::Win32::Devices::Display::{
+    CapabilitiesRequestAndCapabilitiesReply, GetCapabilitiesStringLength,
+};
 use windows::{
     Win32::{
-        Devices::Display::DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL,
-        Foundation::{GetLastError, LPARAM, POINT, RECT, TRUE},
-        Graphics::Gdi::{
-            CreateDCW, DESKTOPHORZRES, DEVMODEW, DMDO_90, DMDO_180, DMDO_270, DMDO_DEFAULT,
-            DeleteDC, ENUM_CURRENT_SETTINGS, EnumDisplayMonitors, EnumDisplaySettingsW,
-            GetDeviceCaps, GetMonitorInfoW, HDC, HMONITOR, HORZRES, MONITOR_DEFAULTTONULL,
-            MONITORINFO, MONITORINFOEXW, MonitorFromPoint,
+        Devices::Display::{
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL, DestroyPhysicalMonitors,
+            GetNumberOfPhysicalMonitorsFromHMONITOR, GetPhysicalMonitorsFromHMONITOR,
+            GetVCPFeatureAndVCPFeatureReply, PHYSICAL_MONITOR,
+        },
+        Foundation::{GetLastError, HANDLE, LPARAM, POINT, RECT, TRUE},
+        Graphics::{
+            Dwm::DwmFlush,
+            Dxgi::{
+                Common::{
+                    DXGI_COLOR_SPACE_CUSTOM, DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709,
+                    DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709,
+                    DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P2020,
+                    DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+                    DXGI_COLOR_SPACE_RGB_STUDIO_G22_NONE_P709,
+                    DXGI_COLOR_SPACE_RGB_STUDIO_G22_NONE_P2020,
+                    DXGI_COLOR_SPACE_RGB_STUDIO_G24_NONE_P709,
+                    DXGI_COLOR_SPACE_RGB_STUDIO_G24_NONE_P2020,
+                    DXGI_COLOR_SPACE_RGB_STUDIO_G2084_NONE_P2020,
+                },
+                CreateDXGIFactory1, DXGI_OUTPUT_DESC1, IDXGIFactory1, IDXGIOutput6,
+            },
+            Gdi::{
+                CreateDCW, DESKTOPHORZRES, DEVMODEW, DMDO_90, DMDO_180, DMDO_270, DMDO_DEFAULT,
+                DeleteDC, ENUM_CURRENT_SETTINGS, EnumDisplayMonitors, EnumDisplaySettingsW,
+                GetDeviceCaps, GetMonitorInfoW, HDC, HMONITOR, HORZRES, MONITOR_DEFAULTTONULL,
+                MONITORINFO, MONITORINFOEXW, MonitorFromPoint,
+            },
         },
         System::{LibraryLoader::GetProcAddress, Threading::GetCurrentProcess},
         UI::WindowsAndMessaging::MONITORINFOF_PRIMARY,
     },
-    core::{BOOL, HRESULT, PCWSTR, s, w},
+    core::{BOOL, HRESULT, Interface, PCWSTR, s, w},
 };
 
 use crate::{
+    CaptureConfig, MonitorIdentity, PixelEncoding, PowerState, RecorderConfig, RefreshRateRange,
+    delayed_capture::wait_refresh_periods,
     error::{XCapError, XCapResult},
-    video_recorder::Frame,
+    geometry::{Point, Rect},
+    layer_compose::{LayerWindow, compose_without},
+    video_recorder::{Frame, RecorderHealth},
 };
 
 use super::{
-    capture::capture_monitor,
+    capture::{capture_monitor, capture_monitor_scaled, capture_wallpaper},
     impl_video_recorder::ImplVideoRecorder,
-    utils::{get_monitor_config, get_process_is_dpi_awareness, load_library},
+    impl_window::ImplWindow,
+    utils::{
+        get_monitor_config, get_monitor_target_count, get_process_is_dpi_awareness,
+        get_window_info, is_input_desktop_accessible, last_error, load_library,
+    },
 };
 
 // A 函数与 W 函数区别
@@ -78,6 +114,116 @@ fn get_dev_mode_w(h_monitor: HMONITOR) -> XCapResult<DEVMODEW> {
     Ok(dev_mode_w)
 }
 
+// DDC/CI 中表示显示器电源模式的 VCP 代码
+const VCP_POWER_MODE: u8 = 0xD6;
+// VCP 亮度设置
+const VCP_LUMINANCE: u8 = 0x10;
+
+// 通过 DDC/CI 读取显示器的电源模式，显示器不支持 DDC/CI 时返回错误
+/// 获取显示器对应的 DXGI 输出的描述，Windows 10 1703 之前没有 IDXGIOutput6
+fn get_output_desc1(h_monitor: HMONITOR) -> XCapResult<DXGI_OUTPUT_DESC1> {
+    unsafe {
+        let factory = CreateDXGIFactory1::<IDXGIFactory1>()?;
+
+        // 没有更多适配器或输出时返回 DXGI_ERROR_NOT_FOUND
+        let mut adapter_index = 0;
+        while let Ok(adapter) = factory.EnumAdapters1(adapter_index) {
+            adapter_index += 1;
+
+            let mut output_index = 0;
+            while let Ok(output) = adapter.EnumOutputs(output_index) {
+                output_index += 1;
+
+                if output.GetDesc()?.Monitor == h_monitor {
+                    let output6 = output.cast::<IDXGIOutput6>()?;
+                    return Ok(output6.GetDesc1()?);
+                }
+            }
+        }
+
+        Err(XCapError::new("Get DXGI output failed"))
+    }
+}
+
+/// 打开 HMONITOR 的第一个物理显示器，用于 DDC/CI 通信
+fn with_physical_monitor<T, F>(h_monitor: HMONITOR, f: F) -> XCapResult<T>
+where
+    F: FnOnce(HANDLE) -> XCapResult<T>,
+{
+    unsafe {
+        let mut number_of_physical_monitors = 0;
+        GetNumberOfPhysicalMonitorsFromHMONITOR(h_monitor, &mut number_of_physical_monitors)?;
+
+        let mut physical_monitors =
+            vec![PHYSICAL_MONITOR::default(); number_of_physical_monitors as usize];
+        GetPhysicalMonitorsFromHMONITOR(h_monitor, &mut physical_monitors)?;
+
+        let physical_monitors = guard(physical_monitors, |physical_monitors| {
+            let _ = DestroyPhysicalMonitors(&physical_monitors);
+        });
+
+        let physical_monitor = physical_monitors
+            .first()
+            .ok_or(XCapError::new("Not found physical monitor"))?;
+
+        f(physical_monitor.hPhysicalMonitor)
+    }
+}
+
+/// 通过 DDC/CI 读取显示器的 VCP 设置，返回当前值和最大值
+fn get_vcp_feature(h_monitor: HMONITOR, code: u8) -> XCapResult<(u32, u32)> {
+    with_physical_monitor(h_monitor, |physical_monitor| unsafe {
+        let mut current_value = 0;
+        let mut maximum_value = 0;
+        if GetVCPFeatureAndVCPFeatureReply(
+            physical_monitor,
+            code,
+            None,
+            &mut current_value,
+            Some(&mut maximum_value as *mut u32),
+        ) == 0
+        {
+            return Err(last_error("GetVCPFeatureAndVCPFeatureReply failed"));
+        }
+
+        Ok((current_value, maximum_value))
+    })
+}
+
+/// 读取显示器的 MCCS 能力字符串
+#[cfg(feature = "ddc")]
+fn get_capabilities_string(h_monitor: HMONITOR) -> XCapResult<String> {
+    with_physical_monitor(h_monitor, |physical_monitor| unsafe {
+        let mut len = 0;
+        if GetCapabilitiesStringLength(physical_monitor, &mut len) == 0 {
+            return Err(last_error("GetCapabilitiesStringLength failed"));
+        }
+
+        let mut capabilities = vec![0u8; len as usize];
+        if CapabilitiesRequestAndCapabilitiesReply(physical_monitor, &mut capabilities) == 0 {
+            return Err(last_error("CapabilitiesRequestAndCapabilitiesReply failed"));
+        }
+
+        Ok(String::from_utf8_lossy(&capabilities)
+            .trim_end_matches('\0')
+            .to_string())
+    })
+}
+
+fn get_power_state(h_monitor: HMONITOR) -> XCapResult<PowerState> {
+    let (current_value, _) = get_vcp_feature(h_monitor, VCP_POWER_MODE)?;
+
+    // 1 开启，2 待机，3 挂起，4 关闭，5 通过电源键关闭
+    let power_state = match current_value {
+        2 => PowerState::Standby,
+        3 => PowerState::Suspend,
+        4 | 5 => PowerState::Off,
+        _ => PowerState::On,
+    };
+
+    Ok(power_state)
+}
+
 // 定义 GetDpiForMonitor 函数的类型
 type GetDpiForMonitor = unsafe extern "system" fn(
     h_monitor: HMONITOR,
@@ -186,38 +332,6 @@ impl ImplMonitor {
 
         Ok(ImplMonitor::new(h_monitor))
     }
-
-    pub fn from_unique_key(unique_key: String) -> XCapResult<ImplMonitor> {
-        let monitors = ImplMonitor::all()?;
-
-        for monitor in monitors {
-            // 1. 优先检查序列号（硬件属性，最可靠）
-            if let Ok(serial) = monitor.serial_number() {
-                if !serial.is_empty() && serial == unique_key {
-                    return Ok(monitor);
-                }
-            }
-
-            // 2. 检查 UUID
-            if let Ok(uuid) = monitor.uuid() {
-                if uuid == unique_key {
-                    return Ok(monitor);
-                }
-            }
-
-            // 3. 最后检查显示器 ID
-            if let Ok(id) = monitor.id() {
-                if id.to_string() == unique_key {
-                    return Ok(monitor);
-                }
-            }
-        }
-
-        Err(XCapError::new(format!(
-            "Monitor with unique_key '{}' not found",
-            unique_key
-        )))
-    }
 }
 
 impl ImplMonitor {
@@ -285,11 +399,54 @@ impl ImplMonitor {
         get_scale_factor(self.h_monitor)
     }
 
+    /// 进程开启了 DPI 感知，坐标和截图都是物理像素
+    pub fn pixel_ratio(&self) -> XCapResult<f32> {
+        Ok(1.0)
+    }
+
     pub fn frequency(&self) -> XCapResult<f32> {
         let dev_mode_w = get_dev_mode_w(self.h_monitor)?;
         Ok(dev_mode_w.dmDisplayFrequency as f32)
     }
 
+    pub fn refresh_rate_range(&self) -> XCapResult<RefreshRateRange> {
+        // Windows 没有公开可变刷新率的状态和范围
+        Err(XCapError::NotSupported)
+    }
+
+    pub fn bit_depth(&self) -> XCapResult<u32> {
+        if let Ok(output_desc1) = get_output_desc1(self.h_monitor) {
+            return Ok(output_desc1.BitsPerColor);
+        }
+
+        // 没有 IDXGIOutput6 时按显示模式的每像素位数计算，32 位色是每个通道 8 位
+        let dev_mode_w = get_dev_mode_w(self.h_monitor)?;
+        Ok(dev_mode_w.dmBitsPerPel.min(24) / 3)
+    }
+
+    pub fn pixel_encoding(&self) -> XCapResult<PixelEncoding> {
+        // 没有 IDXGIOutput6 的系统不支持 HDR 和 YCbCr 输出
+        let Ok(output_desc1) = get_output_desc1(self.h_monitor) else {
+            return Ok(PixelEncoding::Rgb);
+        };
+
+        let pixel_encoding = match output_desc1.ColorSpace {
+            DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709 => PixelEncoding::RgbFloat,
+            DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709
+            | DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P2020
+            | DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020
+            | DXGI_COLOR_SPACE_RGB_STUDIO_G22_NONE_P709
+            | DXGI_COLOR_SPACE_RGB_STUDIO_G22_NONE_P2020
+            | DXGI_COLOR_SPACE_RGB_STUDIO_G24_NONE_P709
+            | DXGI_COLOR_SPACE_RGB_STUDIO_G24_NONE_P2020
+            | DXGI_COLOR_SPACE_RGB_STUDIO_G2084_NONE_P2020
+            | DXGI_COLOR_SPACE_CUSTOM => PixelEncoding::Rgb,
+            _ => PixelEncoding::YCbCr,
+        };
+
+        Ok(pixel_encoding)
+    }
+
     pub fn is_primary(&self) -> XCapResult<bool> {
         let monitor_info_ex_w = get_monitor_info_ex_w(self.h_monitor)?;
         Ok(monitor_info_ex_w.monitorInfo.dwFlags == MONITORINFOF_PRIMARY)
@@ -302,6 +459,43 @@ impl ImplMonitor {
         Ok(config.outputTechnology == DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL)
     }
 
+    pub fn power_state(&self) -> XCapResult<PowerState> {
+        get_power_state(self.h_monitor)
+    }
+
+    pub fn brightness(&self) -> XCapResult<f32> {
+        let result = get_vcp_feature(self.h_monitor, VCP_LUMINANCE);
+        if let Ok((current_value, maximum_value)) = result
+            && maximum_value > 0
+        {
+            return Ok((current_value as f32 / maximum_value as f32).clamp(0.0, 1.0));
+        }
+
+        // 笔记本的内置屏幕一般不支持 DDC/CI，亮度由 WMI 提供
+        if self.is_builtin()? {
+            return super::display_info::get_display_brightness_from_wmi();
+        }
+
+        Err(result
+            .err()
+            .unwrap_or(XCapError::new("Invalid brightness range")))
+    }
+
+    pub fn can_capture(&self) -> XCapResult<bool> {
+        Ok(is_input_desktop_accessible())
+    }
+
+    pub fn is_mirrored(&self) -> XCapResult<bool> {
+        let monitor_info_ex_w = get_monitor_info_ex_w(self.h_monitor)?;
+
+        Ok(get_monitor_target_count(monitor_info_ex_w)? > 1)
+    }
+
+    pub fn mirror_group(&self) -> XCapResult<Vec<ImplMonitor>> {
+        // 复制模式下的显示器共用同一个 HMONITOR，EnumDisplayMonitors 只返回一次
+        Ok(vec![self.clone()])
+    }
+
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
         let x = self.x()?;
         let y = self.y()?;
@@ -315,6 +509,69 @@ impl ImplMonitor {
         self.capture_image()
     }
 
+    pub fn wait_for_present(&self) -> XCapResult<()> {
+        // DwmFlush 等待 DWM 完成下一次合成，合成被禁用时（Windows 7 经典主题）按刷新率等待
+        if unsafe { DwmFlush() }.is_err() {
+            wait_refresh_periods(self.frequency()?);
+        }
+
+        Ok(())
+    }
+
+    pub fn capture_thumbnail(&self, width: u32, height: u32) -> XCapResult<RgbaImage> {
+        let x = self.x()?;
+        let y = self.y()?;
+        let monitor_width = self.width()?;
+        let monitor_height = self.height()?;
+
+        capture_monitor_scaled(
+            x,
+            y,
+            monitor_width as i32,
+            monitor_height as i32,
+            width as i32,
+            height as i32,
+        )
+    }
+
+    pub fn capture_image_with_config(&self, config: &CaptureConfig) -> XCapResult<RgbaImage> {
+        let mut image = self.capture_image()?;
+        if !config.excludes_system_windows() {
+            return Ok(image);
+        }
+
+        // GDI 截图不能按窗口过滤，被排除的窗口用下面的窗口和壁纸重新合成
+        let x = self.x()?;
+        let y = self.y()?;
+        let mut impl_windows = Vec::new();
+        let mut layer_windows = Vec::new();
+        // EnumWindows 按 Z 顺序从最顶层的窗口开始遍历
+        for impl_window in ImplWindow::all()? {
+            if impl_window.is_minimized()? {
+                continue;
+            }
+
+            let rect = impl_window.frame_bounds()?;
+            let rc_window = get_window_info(impl_window.hwnd())?.rcWindow;
+            layer_windows.push(LayerWindow {
+                rect: Rect::new(rect.x - x, rect.y - y, rect.width, rect.height),
+                image_origin: Point::new(rc_window.left - x, rc_window.top - y),
+                excluded: config.excludes_layer(impl_window.layer()?),
+            });
+            impl_windows.push(impl_window);
+        }
+
+        compose_without(
+            &mut image,
+            &layer_windows,
+            config.exclude_desktop_icons,
+            |index| impl_windows[index].capture_image(),
+            || capture_wallpaper(x, y, self.width()? as i32, self.height()? as i32),
+        )?;
+
+        Ok(image)
+    }
+
     pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
         // Validate region bounds
         let monitor_x = self.x()?;
@@ -322,11 +579,8 @@ impl ImplMonitor {
         let monitor_width = self.width()?;
         let monitor_height = self.height()?;
 
-        if width > monitor_width
-            || height > monitor_height
-            || x + width > monitor_width
-            || y + height > monitor_height
-        {
+        let region = Rect::new(x as i32, y as i32, width, height);
+        if !Rect::new(0, 0, monitor_width, monitor_height).contains_rect(region) {
             return Err(XCapError::InvalidCaptureRegion(format!(
                 "Region ({x}, {y}, {width}, {height}) is outside monitor bounds ({monitor_x}, {monitor_y}, {monitor_width}, {monitor_height})"
             )));
@@ -339,8 +593,12 @@ impl ImplMonitor {
         capture_monitor(abs_x, abs_y, width as i32, height as i32)
     }
 
-    pub fn video_recorder(&self) -> XCapResult<(ImplVideoRecorder, Receiver<Frame>)> {
-        ImplVideoRecorder::new(self.h_monitor)
+    pub fn video_recorder(
+        &self,
+        config: &RecorderConfig,
+        health: Arc<RecorderHealth>,
+    ) -> XCapResult<(ImplVideoRecorder, Receiver<Frame>)> {
+        ImplVideoRecorder::new(self.h_monitor, config, health)
     }
 
     /// 获取显示器的 UUID
@@ -354,4 +612,25 @@ impl ImplMonitor {
     pub fn serial_number(&self) -> XCapResult<String> {
         super::display_info::get_display_serial_number(self.h_monitor)
     }
+
+    /// 获取显示器的厂商、型号和序列号
+    /// 解析注册表中的 EDID
+    pub fn identity(&self) -> XCapResult<MonitorIdentity> {
+        let monitor_info_ex_w = get_monitor_info_ex_w(self.h_monitor)?;
+        let config = get_monitor_config(monitor_info_ex_w)?;
+
+        super::display_info::get_display_identity(&config)
+    }
+
+    #[cfg(feature = "ddc")]
+    pub fn ddc_capabilities(&self) -> XCapResult<String> {
+        get_capabilities_string(self.h_monitor)
+    }
+
+    #[cfg(feature = "ddc")]
+    pub fn vcp_feature(&self, code: u8) -> XCapResult<(u16, u16)> {
+        let (current_value, maximum_value) = get_vcp_feature(self.h_monitor, code)?;
+
+        Ok((current_value as u16, maximum_value as u16))
+    }
 }
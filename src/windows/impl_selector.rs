@@ -0,0 +1,200 @@
+use std::cell::RefCell;
+
+use scopeguard::guard;
+use windows::{
+    Win32::{
+        Foundation::{COLORREF, HWND, LPARAM, LRESULT, RECT, WPARAM},
+        Graphics::Gdi::{
+            BLACK_BRUSH, BeginPaint, CreateSolidBrush, DeleteObject, EndPaint, FillRect, FrameRect,
+            GetStockObject, HBRUSH, InvalidateRect, PAINTSTRUCT, WHITE_BRUSH,
+        },
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Input::KeyboardAndMouse::{ReleaseCapture, SetCapture, VK_ESCAPE},
+            WindowsAndMessaging::{
+                CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW,
+                IDC_CROSS, LWA_ALPHA, LoadCursorW, MSG, RegisterClassW, SW_SHOW,
+                SetForegroundWindow, SetLayeredWindowAttributes, ShowWindow, TranslateMessage,
+                WM_KEYDOWN, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MOUSEMOVE, WM_PAINT, WM_RBUTTONUP,
+                WNDCLASSW, WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_POPUP,
+            },
+        },
+    },
+    core::w,
+};
+
+use crate::{
+    error::XCapResult,
+    geometry::{Point, Rect, union_rect},
+    selector::{Outcome, Selection},
+};
+
+// 覆盖层的不透明度，选区绘制为白色，看起来比周围更亮
+const OVERLAY_ALPHA: u8 = 96;
+// 选区边框的颜色，COLORREF 是 0x00BBGGRR
+const BORDER_COLOR: COLORREF = COLORREF(0x00D7_7800);
+
+struct State {
+    // 覆盖层左上角的全局坐标
+    origin: Point,
+    selection: Selection,
+    // 外层 None 表示还在选择，内层 None 表示取消
+    outcome: Option<Option<Outcome>>,
+}
+
+thread_local! {
+    static STATE: RefCell<Option<State>> = const { RefCell::new(None) };
+}
+
+/// 创建覆盖整个虚拟桌面的分层窗口，在当前线程上运行消息循环直到选择结束
+pub(crate) fn run_selector(bounds: &[Rect], selection: Selection) -> XCapResult<Option<Outcome>> {
+    let Some(desktop) = union_rect(bounds) else {
+        return Ok(None);
+    };
+
+    unsafe {
+        let instance = GetModuleHandleW(None)?;
+        let class_name = w!("XCapSelector");
+        let window_class = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance.into(),
+            hCursor: LoadCursorW(None, IDC_CROSS)?,
+            lpszClassName: class_name,
+            ..Default::default()
+        };
+        // 再次选择时类已经注册过，注册失败可以忽略
+        RegisterClassW(&window_class);
+
+        STATE.with_borrow_mut(|state| {
+            *state = Some(State {
+                origin: Point::new(desktop.x, desktop.y),
+                selection,
+                outcome: None,
+            })
+        });
+        let _state_guard = guard((), |_| STATE.with_borrow_mut(|state| *state = None));
+
+        let hwnd = CreateWindowExW(
+            WS_EX_LAYERED | WS_EX_TOPMOST | WS_EX_TOOLWINDOW,
+            class_name,
+            w!(""),
+            WS_POPUP,
+            desktop.x,
+            desktop.y,
+            desktop.width as i32,
+            desktop.height as i32,
+            None,
+            None,
+            Some(instance.into()),
+            None,
+        )?;
+        let _hwnd_guard = guard(hwnd, |hwnd| {
+            if let Err(err) = DestroyWindow(hwnd) {
+                log::error!("DestroyWindow failed: {err:?}");
+            }
+        });
+
+        SetLayeredWindowAttributes(hwnd, COLORREF(0), OVERLAY_ALPHA, LWA_ALPHA)?;
+        let _ = ShowWindow(hwnd, SW_SHOW);
+        let _ = SetForegroundWindow(hwnd);
+
+        let mut msg = MSG::default();
+        loop {
+            if let Some(outcome) = STATE.with_borrow_mut(|state| state.as_mut()?.outcome.take()) {
+                return Ok(outcome);
+            }
+
+            // 收到 WM_QUIT 或出错时按取消处理
+            if GetMessageW(&mut msg, None, 0, 0).0 <= 0 {
+                return Ok(None);
+            }
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
+
+fn with_state<T>(f: impl FnOnce(&mut State) -> T) -> Option<T> {
+    STATE.with_borrow_mut(|state| state.as_mut().map(f))
+}
+
+/// lParam 的低 16 位和高 16 位是有符号的客户区坐标
+fn event_point(state: &State, lparam: LPARAM) -> Point {
+    let x = (lparam.0 & 0xFFFF) as i16 as i32;
+    let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+
+    Point::new(state.origin.x + x, state.origin.y + y)
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_LBUTTONDOWN => {
+                SetCapture(hwnd);
+                with_state(|state| state.selection.press(event_point(state, lparam)));
+            }
+            WM_MOUSEMOVE => {
+                with_state(|state| state.selection.drag(event_point(state, lparam)));
+                let _ = InvalidateRect(Some(hwnd), None, false);
+            }
+            WM_LBUTTONUP => {
+                let _ = ReleaseCapture();
+                with_state(|state| {
+                    if let Some(outcome) = state.selection.release(event_point(state, lparam)) {
+                        state.outcome = Some(Some(outcome));
+                    }
+                });
+                let _ = InvalidateRect(Some(hwnd), None, false);
+            }
+            WM_RBUTTONUP => {
+                with_state(|state| state.outcome = Some(None));
+            }
+            WM_KEYDOWN if wparam.0 == VK_ESCAPE.0 as usize => {
+                with_state(|state| state.outcome = Some(None));
+            }
+            WM_PAINT => paint(hwnd),
+            _ => return DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+
+        LRESULT(0)
+    }
+}
+
+unsafe fn paint(hwnd: HWND) {
+    unsafe {
+        let mut paint_struct = PAINTSTRUCT::default();
+        let hdc = BeginPaint(hwnd, &mut paint_struct);
+
+        FillRect(
+            hdc,
+            &paint_struct.rcPaint,
+            HBRUSH(GetStockObject(BLACK_BRUSH).0),
+        );
+
+        let rect = with_state(|state| {
+            let rect = state.selection.rect()?;
+            Some(RECT {
+                left: rect.x - state.origin.x,
+                top: rect.y - state.origin.y,
+                right: rect.x - state.origin.x + rect.width as i32,
+                bottom: rect.y - state.origin.y + rect.height as i32,
+            })
+        })
+        .flatten();
+
+        if let Some(rect) = rect {
+            FillRect(hdc, &rect, HBRUSH(GetStockObject(WHITE_BRUSH).0));
+
+            let border = CreateSolidBrush(BORDER_COLOR);
+            FrameRect(hdc, &rect, border);
+            let _ = DeleteObject(border.into());
+        }
+
+        let _ = EndPaint(hwnd, &paint_struct);
+    }
+}
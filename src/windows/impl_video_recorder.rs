@@ -1,15 +1,16 @@
 use std::{
-    slice,
+    mem, slice,
     sync::{
-        Arc,
+        Arc, Mutex,
         mpsc::{Receiver, SyncSender, sync_channel},
     },
-    thread,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use windows::{
     Win32::{
-        Foundation::HMODULE,
+        Foundation::{HMODULE, HWND},
         Graphics::{
             Direct3D::D3D_DRIVER_TYPE_HARDWARE,
             Direct3D11::{
@@ -18,9 +19,10 @@ use windows::{
                 D3D11_SDK_VERSION, D3D11_TEXTURE2D_DESC, D3D11_USAGE_STAGING, D3D11CreateDevice,
                 ID3D11Device, ID3D11DeviceContext, ID3D11Resource, ID3D11Texture2D,
             },
+            Dwm::{DWM_TIMING_INFO, DwmGetCompositionTimingInfo},
             Dxgi::{
-                DXGI_ERROR_WAIT_TIMEOUT, DXGI_OUTDUPL_FRAME_INFO, IDXGIDevice, IDXGIOutput1,
-                IDXGIOutputDuplication, IDXGIResource,
+                DXGI_ERROR_ACCESS_LOST, DXGI_ERROR_WAIT_TIMEOUT, DXGI_OUTDUPL_FRAME_INFO,
+                DXGI_OUTPUT_DESC, IDXGIDevice, IDXGIOutput1, IDXGIOutputDuplication, IDXGIResource,
             },
             Gdi::HMONITOR,
         },
@@ -29,16 +31,50 @@ use windows::{
 };
 
 use crate::{
-    XCapError, XCapResult,
-    video_recorder::{Frame, RecorderWaker},
+    FramePacing, Monitor, RecorderConfig, RecorderUpdate, RecoveryPolicy, XCapError, XCapResult,
+    clock,
+    redaction::Redaction,
+    video_recorder::{
+        ChangeDetector, Frame, FrameHook, FramePacer, IdleGate, LiveConfig, RecorderEvent,
+        RecorderHealth, RecorderWaker, Watchdog, WorkerGuard, join_worker, scale_frame,
+    },
+    window_crop::WindowCrop,
+};
+
+use super::{
+    external_encoder_surface::SurfacePool,
+    impl_monitor::ImplMonitor,
+    utils::{bgra_to_rgba, is_display_idle, is_on_battery},
 };
 
-use super::utils::bgra_to_rgba;
+// 重建桌面复制会话的重试次数和间隔
+const RECOVERY_ATTEMPTS: u32 = 10;
+const RECOVERY_INTERVAL: Duration = Duration::from_millis(500);
+// 获取帧的超时时间，单位毫秒
+const ACQUIRE_TIMEOUT: u32 = 200;
+// DWM 合成了新画面但连续这么多次获取帧超时后重建桌面复制会话
+const STALL_TIMEOUTS: u32 = 50;
+
+/// DWM 在 last_present_time（QPC 时间）之后是否合成过新的画面。
+/// 桌面静止时 DWM 不合成，桌面复制也不会产生帧，这时超时不算卡住
+fn is_composed_after(last_present_time: i64) -> bool {
+    let mut timing_info = DWM_TIMING_INFO {
+        cbSize: mem::size_of::<DWM_TIMING_INFO>() as u32,
+        ..Default::default()
+    };
+
+    // Windows 8.1 之后只能查询整个桌面的合成时间，获取失败时按有新画面处理
+    match unsafe { DwmGetCompositionTimingInfo(HWND::default(), &mut timing_info) } {
+        Ok(()) => timing_info.qpcCompose as i64 > last_present_time,
+        Err(_) => true,
+    }
+}
 
 pub fn texture_to_frame(
     d3d_device: &ID3D11Device,
     d3d_context: &ID3D11DeviceContext,
     source_texture: ID3D11Texture2D,
+    timestamp: Instant,
 ) -> XCapResult<Frame> {
     unsafe {
         let mut source_desc = D3D11_TEXTURE2D_DESC::default();
@@ -74,103 +110,314 @@ pub fn texture_to_frame(
 
         d3d_context.Unmap(Some(&resource), 0);
 
-        Ok(Frame::new(
+        // 每行 RowPitch 字节，可能比 Width * 4 大
+        Ok(Frame::with_stride(
             source_desc.Width,
             source_desc.Height,
+            mapped.RowPitch as usize,
             bgra_to_rgba(bgra.to_owned()),
+            timestamp,
         ))
     }
 }
 
+// 桌面复制会话及其依赖的设备
+pub(super) struct OutputDuplication {
+    pub d3d_device: ID3D11Device,
+    pub d3d_context: ID3D11DeviceContext,
+    pub output: IDXGIOutput1,
+    pub duplication: IDXGIOutputDuplication,
+    pub device_name: [u16; 32],
+}
+
+pub(super) fn duplicate_output<F>(is_target: F) -> XCapResult<OutputDuplication>
+where
+    F: Fn(&DXGI_OUTPUT_DESC) -> bool,
+{
+    unsafe {
+        let mut d3d_device = None;
+        D3D11CreateDevice(
+            None,
+            D3D_DRIVER_TYPE_HARDWARE,
+            HMODULE::default(),
+            D3D11_CREATE_DEVICE_BGRA_SUPPORT | D3D11_CREATE_DEVICE_SINGLETHREADED,
+            None,
+            D3D11_SDK_VERSION,
+            Some(&mut d3d_device),
+            None,
+            None,
+        )?;
+
+        let d3d_device = d3d_device.ok_or(XCapError::new("Call D3D11CreateDevice failed"))?;
+        let dxgi_device = d3d_device.cast::<IDXGIDevice>()?;
+        let d3d_context = d3d_device.GetImmediateContext()?;
+
+        let adapter = dxgi_device.GetAdapter()?;
+
+        let mut output_index = 0;
+        loop {
+            let output = adapter.EnumOutputs(output_index)?;
+            output_index += 1;
+            let output_desc = output.GetDesc()?;
+
+            if is_target(&output_desc) {
+                let output1 = output.cast::<IDXGIOutput1>()?;
+                let duplication = output1.DuplicateOutput(&dxgi_device)?;
+
+                return Ok(OutputDuplication {
+                    d3d_device,
+                    d3d_context,
+                    output: output1,
+                    duplication,
+                    device_name: output_desc.DeviceName,
+                });
+            }
+        }
+    }
+}
+
+/// 重新创建桌面复制会话，显示器重新连接后 HMONITOR 会变化，所以按设备名查找
+fn recreate_duplication(device_name: [u16; 32]) -> XCapResult<OutputDuplication> {
+    let mut last_error = XCapError::new("Recreate output duplication failed");
+
+    for _ in 0..RECOVERY_ATTEMPTS {
+        match duplicate_output(|output_desc| output_desc.DeviceName == device_name) {
+            Ok(output_duplication) => return Ok(output_duplication),
+            Err(err) => last_error = err,
+        }
+
+        // 显示模式切换需要一点时间才能完成
+        thread::sleep(RECOVERY_INTERVAL);
+    }
+
+    Err(last_error)
+}
+
 #[derive(Debug, Clone)]
 pub struct ImplVideoRecorder {
     d3d_device: ID3D11Device,
     d3d_context: ID3D11DeviceContext,
+    output: IDXGIOutput1,
     duplication: IDXGIOutputDuplication,
+    device_name: [u16; 32],
+    pacing: FramePacing,
+    low_power: bool,
+    live_config: Arc<LiveConfig>,
+    window_crop: Option<Arc<WindowCrop>>,
+    redaction: Option<Redaction>,
+    recovery: RecoveryPolicy,
+    pause_when_idle: bool,
+    encoder_surface: bool,
+    frame_hook: Option<FrameHook>,
     recorder_waker: Arc<RecorderWaker>,
+    health: Arc<RecorderHealth>,
     tx: SyncSender<Frame>,
+    worker: Arc<Mutex<Option<JoinHandle<XCapResult<()>>>>>,
 }
 
 impl ImplVideoRecorder {
-    pub fn new(h_monitor: HMONITOR) -> XCapResult<(Self, Receiver<Frame>)> {
-        unsafe {
-            let mut d3d_device = None;
-            D3D11CreateDevice(
-                None,
-                D3D_DRIVER_TYPE_HARDWARE,
-                HMODULE::default(),
-                D3D11_CREATE_DEVICE_BGRA_SUPPORT | D3D11_CREATE_DEVICE_SINGLETHREADED,
-                None,
-                D3D11_SDK_VERSION,
-                Some(&mut d3d_device),
-                None,
-                None,
-            )?;
-
-            let d3d_device = d3d_device.ok_or(XCapError::new("Call D3D11CreateDevice failed"))?;
-            let dxgi_device = d3d_device.cast::<IDXGIDevice>()?;
-            let d3d_context = d3d_device.GetImmediateContext()?;
-
-            let adapter = dxgi_device.GetAdapter()?;
-
-            let mut output_index = 0;
-            loop {
-                let output = adapter.EnumOutputs(output_index)?;
-                output_index += 1;
-                let output_desc = output.GetDesc()?;
+    pub fn new(
+        h_monitor: HMONITOR,
+        config: &RecorderConfig,
+        health: Arc<RecorderHealth>,
+    ) -> XCapResult<(Self, Receiver<Frame>)> {
+        let output_duplication = duplicate_output(|output_desc| output_desc.Monitor == h_monitor)?;
 
-                let output1 = output.cast::<IDXGIOutput1>()?;
-                let duplication = output1.DuplicateOutput(&dxgi_device)?;
+        let monitor = Monitor::new(ImplMonitor::new(h_monitor));
+        let window_crop = WindowCrop::from_config(config, &monitor)?.map(Arc::new);
+        let redaction = Redaction::from_config(config, &monitor)?;
 
-                if output_desc.Monitor == h_monitor {
-                    let (tx, sx) = sync_channel(0);
-                    let s = Self {
-                        d3d_device,
-                        d3d_context,
-                        duplication,
-                        recorder_waker: Arc::new(RecorderWaker::new()),
-                        tx,
-                    };
-                    s.on_frame()?;
-                    return Ok((s, sx));
-                }
-            }
-        }
+        let (tx, sx) = sync_channel(0);
+        let low_power = config.power_profile.is_low_power(is_on_battery);
+        let s = Self {
+            d3d_device: output_duplication.d3d_device,
+            d3d_context: output_duplication.d3d_context,
+            output: output_duplication.output,
+            duplication: output_duplication.duplication,
+            device_name: output_duplication.device_name,
+            pacing: config.pacing,
+            low_power,
+            live_config: Arc::new(LiveConfig::new(config.clone())),
+            window_crop,
+            redaction,
+            recovery: config.recovery,
+            pause_when_idle: config.pause_when_idle,
+            encoder_surface: config.encoder_surface,
+            frame_hook: config.frame_hook.clone(),
+            recorder_waker: Arc::new(RecorderWaker::new()),
+            health,
+            tx,
+            worker: Arc::default(),
+        };
+        s.on_frame()?;
+
+        Ok((s, sx))
     }
 
     pub fn on_frame(&self) -> XCapResult<()> {
-        let duplication = self.duplication.clone();
-        let d3d_device = self.d3d_device.clone();
-        let d3d_context = self.d3d_context.clone();
+        let mut output = self.output.clone();
+        let mut duplication = self.duplication.clone();
+        let device_name = self.device_name;
+        let pacing = self.pacing;
+        let low_power = self.low_power;
+        let live_config = self.live_config.clone();
+        let window_crop = self.window_crop.clone();
+        let redaction = self.redaction.clone();
+        let recovery = self.recovery;
+        let mut d3d_device = self.d3d_device.clone();
+        let mut d3d_context = self.d3d_context.clone();
+        let idle_gate = IdleGate::new(self.pause_when_idle);
+        let encoder_surface = self.encoder_surface;
+        let frame_hook = self.frame_hook.clone();
         let recorder_waker = self.recorder_waker.clone();
+        let health = self.health.clone();
         let tx = self.tx.clone();
 
-        thread::spawn(move || {
+        let worker = thread::spawn(move || {
+            let _guard = WorkerGuard(recorder_waker.clone());
+            let (mut version, config) = live_config.get()?;
+            let mut frame_interval = config.frame_interval(low_power);
+            let mut scale = config.scale;
+            // 限制帧率时在获取帧之前等待，期间合成的帧由 DXGI 丢弃，不会被复制
+            let mut frame_pacer = frame_interval.map(FramePacer::with_interval);
+            let mut change_detector = ChangeDetector::default();
+            let mut surface_pool = SurfacePool::default();
+            let mut watchdog = Watchdog::new(
+                Duration::from_millis(ACQUIRE_TIMEOUT as u64),
+                STALL_TIMEOUTS,
+            );
+            // 上一帧的呈现时间，用于判断超时期间桌面是否有新的画面
+            let mut last_present_time = 0;
+
             loop {
-                recorder_waker.wait()?;
+                if !recorder_waker.wait()? {
+                    break Ok(());
+                }
+
+                // 设置被修改后重建节拍器，桌面复制会话不受影响，从下一帧开始生效
+                if let Some(config) = live_config.poll(&mut version) {
+                    frame_interval = config.frame_interval(low_power);
+                    frame_pacer = frame_interval.map(FramePacer::with_interval);
+                    scale = config.scale;
+                }
+
+                // 锁屏后桌面复制会失效，空闲期间不获取帧
+                if idle_gate.poll(is_display_idle, &health) {
+                    thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+
+                if let Some(frame_pacer) = frame_pacer.as_mut() {
+                    // 等待期间被暂停，回到 wait 中等待下一次 start
+                    if !frame_pacer.wait(&recorder_waker)? {
+                        continue;
+                    }
+                } else if pacing == FramePacing::Vsync {
+                    // 等待下一次垂直同步，保证每个刷新周期最多输出一帧
+                    unsafe { output.WaitForVBlank()? };
+                }
 
                 let mut frame_info = DXGI_OUTDUPL_FRAME_INFO::default();
                 let mut resource: Option<IDXGIResource> = None;
                 unsafe {
-                    match duplication.AcquireNextFrame(200, &mut frame_info, &mut resource) {
+                    match duplication.AcquireNextFrame(
+                        ACQUIRE_TIMEOUT,
+                        &mut frame_info,
+                        &mut resource,
+                    ) {
                         Err(err) => {
                             // 尝试释放当前帧，不然不能获取到下一帧数据
                             let _ = duplication.ReleaseFrame();
-                            if err.code() != DXGI_ERROR_WAIT_TIMEOUT {
+                            if err.code() == DXGI_ERROR_WAIT_TIMEOUT {
+                                // 桌面复制偶尔会卡住，DWM 合成了新画面却一直超时直到重建，重建后会先返回当前画面
+                                let Some(idle) =
+                                    watchdog.timeout(is_composed_after(last_present_time))
+                                else {
+                                    continue;
+                                };
+                                health.emit(RecorderEvent::Stalled { idle });
+                            } else if err.code() == DXGI_ERROR_ACCESS_LOST {
+                                // 分辨率、旋转等显示模式变化会导致桌面复制失效
+                                health.emit(RecorderEvent::DisplayReconfigured);
+                            } else {
+                                health.emit(RecorderEvent::Error(err.to_string()));
+                            }
+
+                            if recovery == RecoveryPolicy::Stop {
                                 break Err::<(), XCapError>(XCapError::new(
                                     "DXGI_ERROR_UNSUPPORTED",
                                 ));
                             }
+
+                            match recreate_duplication(device_name) {
+                                Ok(output_duplication) => {
+                                    d3d_device = output_duplication.d3d_device;
+                                    d3d_context = output_duplication.d3d_context;
+                                    output = output_duplication.output;
+                                    duplication = output_duplication.duplication;
+                                    watchdog.feed();
+                                    health.emit(RecorderEvent::StreamRestarted);
+                                }
+                                Err(err) => {
+                                    health.emit(RecorderEvent::Error(err.to_string()));
+                                    break Err(err);
+                                }
+                            }
                         }
                         _ => {
+                            watchdog.feed();
                             // 如何确定 AcquireNextFrame 执行成功
                             if frame_info.LastPresentTime != 0 {
+                                last_present_time = frame_info.LastPresentTime;
                                 let resource =
                                     resource.ok_or(XCapError::new("AcquireNextFrame failed"))?;
                                 let source_texture = resource.cast::<ID3D11Texture2D>()?;
-                                let frame =
-                                    texture_to_frame(&d3d_device, &d3d_context, source_texture)?;
-                                let _ = tx.send(frame);
+                                // 共享纹理复制失败时仍然发送帧，只是不附带共享纹理
+                                // 共享纹理中是没有遮挡的画面，设置了 redactor 时不复制
+                                let surface = if encoder_surface && redaction.is_none() {
+                                    surface_pool
+                                        .copy(&d3d_device, &d3d_context, &source_texture)
+                                        .unwrap_or_else(|err| {
+                                            log::debug!("copy encoder surface failed: {err:?}");
+                                            None
+                                        })
+                                } else {
+                                    None
+                                };
+                                // LastPresentTime 是桌面图像呈现时的 QPC 时间
+                                let mut frame = texture_to_frame(
+                                    &d3d_device,
+                                    &d3d_context,
+                                    source_texture,
+                                    clock::from_qpc(frame_info.LastPresentTime),
+                                )?;
+                                frame.encoder_surface = surface;
+                                if let Some(redaction) = &redaction {
+                                    redaction.apply(&mut frame);
+                                }
+                                // AccumulatedFrames 为上次获取后合成的帧数，多出的部分没有被捕获
+                                // 低功耗模式和限制帧率时是主动降低帧率，不算丢帧
+                                if frame_interval.is_none() && frame_info.AccumulatedFrames > 1 {
+                                    health.dropped(frame_info.AccumulatedFrames as u64 - 1);
+                                }
+                                // 跟随的窗口不在显示器上时不发送
+                                let frame = match &window_crop {
+                                    Some(window_crop) => window_crop.crop(&frame),
+                                    None => Some(frame),
+                                };
+                                // 应用重新呈现相同内容时 DXGI 也会返回新帧
+                                if let Some(mut frame) = frame
+                                    && (!low_power || change_detector.is_changed(frame.data()))
+                                {
+                                    if scale < 1.0 {
+                                        frame = scale_frame(frame, scale);
+                                    }
+                                    if let Some(frame_hook) = &frame_hook {
+                                        frame_hook.apply(&mut frame);
+                                    }
+                                    live_config.emit_marker(frame.timestamp(), &health);
+                                    health.deliver(|| recorder_waker.send(&tx, frame));
+                                }
                             }
 
                             // 最后释放帧，不然获取不到当前帧的数据
@@ -180,6 +427,7 @@ impl ImplVideoRecorder {
                 }
             }
         });
+        *self.worker.lock()? = Some(worker);
 
         Ok(())
     }
@@ -190,7 +438,20 @@ impl ImplVideoRecorder {
     }
     pub fn stop(&self) -> XCapResult<()> {
         self.recorder_waker.sleep()?;
+        self.recorder_waker.wait_idle()?;
 
         Ok(())
     }
+    /// 由工作线程在获取下一帧之前应用，鼠标指针不在桌面复制的画面中，忽略 show_cursor
+    pub fn reconfigure(&self, update: &RecorderUpdate) -> XCapResult<()> {
+        self.live_config.update(update)?;
+
+        Ok(())
+    }
+    /// 结束工作线程，线程退出时释放桌面复制会话
+    pub fn shutdown(&self) -> XCapResult<()> {
+        self.recorder_waker.shutdown()?;
+
+        join_worker(&self.worker)
+    }
 }
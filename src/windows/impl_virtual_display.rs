@@ -0,0 +1,111 @@
+use std::{
+    env,
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::error::{XCapError, XCapResult};
+
+use super::impl_monitor::ImplMonitor;
+
+// Windows 没有不依赖驱动创建显示器的接口，由间接显示驱动提供的命令创建和移除显示器
+const ADD_COMMAND_VAR: &str = "XCAP_VIRTUAL_DISPLAY_ADD";
+const REMOVE_COMMAND_VAR: &str = "XCAP_VIRTUAL_DISPLAY_REMOVE";
+// 等待新显示器出现的超时时间
+const MONITOR_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn get_command(var: &str) -> XCapResult<String> {
+    env::var(var).map_err(|_| {
+        XCapError::new(format!(
+            "Virtual displays on Windows need an indirect display driver, set {ADD_COMMAND_VAR} \
+             and {REMOVE_COMMAND_VAR} to the commands that add and remove its display"
+        ))
+    })
+}
+
+/// 通过 cmd 运行命令，{width} 和 {height} 替换为显示器的大小
+fn run_command(command: &str, width: u32, height: u32) -> XCapResult<()> {
+    let command = command
+        .replace("{width}", &width.to_string())
+        .replace("{height}", &height.to_string());
+
+    let status = Command::new("cmd")
+        .args(["/C", &command])
+        .status()
+        .map_err(|err| XCapError::new(format!("Run `{command}` failed: {err}")))?;
+
+    if !status.success() {
+        return Err(XCapError::new(format!("`{command}` exited with {status}")));
+    }
+
+    Ok(())
+}
+
+fn get_monitor_ids() -> XCapResult<Vec<u32>> {
+    ImplMonitor::all()?
+        .iter()
+        .map(|monitor| monitor.id())
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct ImplVirtualDisplay {
+    // HMONITOR 不能跨线程传递，保存 id 再按 id 查找
+    monitor_id: u32,
+    remove_command: String,
+    width: u32,
+    height: u32,
+}
+
+impl ImplVirtualDisplay {
+    pub fn new(width: u32, height: u32) -> XCapResult<ImplVirtualDisplay> {
+        let add_command = get_command(ADD_COMMAND_VAR)?;
+        let remove_command = get_command(REMOVE_COMMAND_VAR)?;
+
+        let existing = get_monitor_ids()?;
+        run_command(&add_command, width, height)?;
+
+        // 等待驱动添加的显示器出现
+        let start = Instant::now();
+        loop {
+            let added = get_monitor_ids()?
+                .into_iter()
+                .find(|id| !existing.contains(id));
+
+            if let Some(monitor_id) = added {
+                return Ok(ImplVirtualDisplay {
+                    monitor_id,
+                    remove_command,
+                    width,
+                    height,
+                });
+            }
+
+            if start.elapsed() > MONITOR_WAIT_TIMEOUT {
+                let _ = run_command(&remove_command, width, height);
+                return Err(XCapError::new("Virtual display did not appear in time"));
+            }
+
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    pub fn monitor(&self) -> XCapResult<ImplMonitor> {
+        for monitor in ImplMonitor::all()? {
+            if monitor.id()? == self.monitor_id {
+                return Ok(monitor);
+            }
+        }
+
+        Err(XCapError::new("Virtual display was removed"))
+    }
+}
+
+impl Drop for ImplVirtualDisplay {
+    fn drop(&mut self) {
+        if let Err(err) = run_command(&self.remove_command, self.width, self.height) {
+            log::error!("remove virtual display failed: {err:?}");
+        }
+    }
+}
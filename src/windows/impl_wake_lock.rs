@@ -0,0 +1,58 @@
+use std::{
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+};
+
+use windows::Win32::System::Power::{
+    ES_CONTINUOUS, ES_DISPLAY_REQUIRED, ES_SYSTEM_REQUIRED, SetThreadExecutionState,
+};
+
+use crate::error::{XCapError, XCapResult};
+
+/// SetThreadExecutionState 的状态属于调用线程，在单独的线程中设置，线程退出前清除
+#[derive(Debug)]
+pub(crate) struct ImplWakeLock {
+    release: Option<Sender<()>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ImplWakeLock {
+    pub fn new() -> XCapResult<ImplWakeLock> {
+        let (result_tx, result_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+
+        let worker = thread::spawn(move || {
+            let previous = unsafe {
+                SetThreadExecutionState(ES_CONTINUOUS | ES_DISPLAY_REQUIRED | ES_SYSTEM_REQUIRED)
+            };
+            let _ = result_tx.send(previous.0 != 0);
+            if previous.0 == 0 {
+                return;
+            }
+
+            // 发送端被释放时返回
+            let _ = release_rx.recv();
+            unsafe { SetThreadExecutionState(ES_CONTINUOUS) };
+        });
+
+        if !result_rx.recv().unwrap_or(false) {
+            let _ = worker.join();
+            return Err(XCapError::new("SetThreadExecutionState failed"));
+        }
+
+        Ok(ImplWakeLock {
+            release: Some(release_tx),
+            worker: Some(worker),
+        })
+    }
+}
+
+impl Drop for ImplWakeLock {
+    fn drop(&mut self) {
+        self.release.take();
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
@@ -1,14 +1,26 @@
 use core::slice;
-use std::{ffi::c_void, mem, ptr};
+use std::{
+    ffi::c_void,
+    mem, ptr,
+    time::{Duration, Instant},
+};
 
-use image::RgbaImage;
+use image::{RgbaImage, imageops};
 use widestring::U16CString;
 use windows::{
     Win32::{
         Foundation::{GetLastError, HANDLE, HWND, LPARAM, MAX_PATH, RECT, TRUE},
         Graphics::{
             Dwm::{DWMWA_CLOAKED, DWMWA_EXTENDED_FRAME_BOUNDS, DwmGetWindowAttribute},
-            Gdi::{IsRectEmpty, MONITOR_DEFAULTTONEAREST, MonitorFromWindow},
+            Dxgi::{
+                Common::{DXGI_MODE_ROTATION_IDENTITY, DXGI_MODE_ROTATION_UNSPECIFIED},
+                DXGI_ERROR_WAIT_TIMEOUT, DXGI_OUTDUPL_FRAME_INFO, DXGI_OUTDUPL_MOVE_RECT,
+                IDXGIOutputDuplication, IDXGIResource,
+            },
+            Gdi::{
+                GetMonitorInfoW, IsRectEmpty, MONITOR_DEFAULTTONEAREST, MONITORINFO,
+                MonitorFromWindow,
+            },
         },
         Storage::FileSystem::{GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW},
         System::{
@@ -17,31 +29,45 @@ use windows::{
                 GetCurrentProcess, GetCurrentProcessId, PROCESS_QUERY_LIMITED_INFORMATION,
             },
         },
-        UI::WindowsAndMessaging::{
-            EnumWindows, GWL_EXSTYLE, GetClassNameW, GetForegroundWindow, GetWindowLongPtrW,
-            GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId, IsIconic, IsWindow,
-            IsWindowVisible, IsZoomed, WINDOW_EX_STYLE, WS_EX_TOOLWINDOW,
+        UI::{
+            Input::KeyboardAndMouse::{
+                INPUT, INPUT_0, INPUT_MOUSE, MOUSEEVENTF_WHEEL, MOUSEINPUT, SendInput,
+            },
+            WindowsAndMessaging::{
+                AdjustWindowRectEx, EnumWindows, GW_OWNER, GWL_EXSTYLE, GWL_STYLE, GetClassNameW,
+                GetForegroundWindow, GetMenu, GetWindow, GetWindowDisplayAffinity,
+                GetWindowLongPtrW, GetWindowPlacement, GetWindowTextLengthW, GetWindowTextW,
+                GetWindowThreadProcessId, IsIconic, IsWindow, IsWindowVisible, IsZoomed,
+                SetCursorPos, WDA_NONE, WHEEL_DELTA, WINDOW_EX_STYLE, WINDOW_STYLE,
+                WINDOWPLACEMENT, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+                WS_EX_TRANSPARENT,
+            },
         },
     },
     core::{BOOL, HSTRING, PCWSTR},
 };
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    ActiveInfoMode, Rect, WindowLayer,
+    error::{XCapError, XCapResult},
+};
 
 use super::{
     capture::capture_window,
     impl_monitor::ImplMonitor,
-    utils::{get_process_is_dpi_awareness, get_window_info, open_process},
+    impl_video_recorder::duplicate_output,
+    utils::{
+        get_process_is_dpi_awareness, get_window_info, is_input_desktop_accessible, last_error,
+        open_process,
+    },
 };
 
 #[derive(Debug, Clone)]
 pub(crate) struct ImplWindow {
-    pub hwnd: HWND,
+    // 窗口句柄在整个会话中有效，和创建它的线程无关，保存为整数后 ImplWindow 自动满足 Send 和 Sync
+    handle: usize,
 }
 
-unsafe impl Send for ImplWindow {}
-unsafe impl Sync for ImplWindow {}
-
 fn is_window_cloaked(hwnd: HWND) -> bool {
     unsafe {
         let mut cloaked = 0u32;
@@ -62,6 +88,50 @@ fn is_window_cloaked(hwnd: HWND) -> bool {
     }
 }
 
+fn get_class_name(hwnd: HWND) -> XCapResult<String> {
+    unsafe {
+        let mut lp_class_name = [0u16; MAX_PATH as usize];
+        let lp_class_name_length = GetClassNameW(hwnd, &mut lp_class_name) as usize;
+        if lp_class_name_length < 1 {
+            return Err(XCapError::new("GetClassNameW failed"));
+        }
+
+        let class_name =
+            U16CString::from_vec_truncate(&lp_class_name[0..lp_class_name_length]).to_string()?;
+
+        Ok(class_name)
+    }
+}
+
+// 根据窗口类名和扩展样式判断窗口层级
+fn get_window_layer(class_name: &str, ex_style: WINDOW_EX_STYLE) -> WindowLayer {
+    match class_name {
+        "Progman" | "WorkerW" => return WindowLayer::Desktop,
+        "Shell_TrayWnd" | "Shell_SecondaryTrayWnd" => return WindowLayer::Dock,
+        "NotifyIconOverflowWindow" | "TopLevelWindowForOverflowXamlIsland" => {
+            return WindowLayer::StatusItem;
+        }
+        "tooltips_class32" | "Xaml_WindowedPopupClass" => return WindowLayer::Tooltip,
+        // 菜单窗口的系统类名
+        "#32768" => return WindowLayer::Popup,
+        _ => {}
+    }
+
+    let is_topmost = ex_style.contains(WS_EX_TOPMOST);
+    let is_tool_window = ex_style.contains(WS_EX_TOOLWINDOW);
+    let is_click_through = ex_style.contains(WS_EX_TRANSPARENT);
+
+    if is_topmost && (is_tool_window || is_click_through || ex_style.contains(WS_EX_NOACTIVATE)) {
+        return WindowLayer::Overlay;
+    }
+
+    if is_tool_window {
+        return WindowLayer::Popup;
+    }
+
+    WindowLayer::Normal
+}
+
 // https://webrtc.googlesource.com/src.git/+/refs/heads/main/modules/desktop_capture/win/window_capture_utils.cc#52
 fn is_valid_window(hwnd: HWND) -> bool {
     unsafe {
@@ -77,15 +147,7 @@ fn is_valid_window(hwnd: HWND) -> bool {
         //   return TRUE;
         // }
 
-        let mut lp_class_name = [0u16; MAX_PATH as usize];
-        let lp_class_name_length = GetClassNameW(hwnd, &mut lp_class_name) as usize;
-        if lp_class_name_length < 1 {
-            return false;
-        }
-
-        let class_name = U16CString::from_vec_truncate(&lp_class_name[0..lp_class_name_length])
-            .to_string()
-            .unwrap_or_default();
+        let class_name = get_class_name(hwnd).unwrap_or_default();
         if class_name.is_empty() {
             return false;
         }
@@ -320,7 +382,13 @@ fn get_app_name(pid: u32) -> XCapResult<String> {
 
 impl ImplWindow {
     fn new(hwnd: HWND) -> ImplWindow {
-        ImplWindow { hwnd }
+        ImplWindow {
+            handle: hwnd.0 as usize,
+        }
+    }
+
+    pub fn hwnd(&self) -> HWND {
+        HWND(self.handle as *mut c_void)
     }
 
     pub fn all() -> XCapResult<Vec<ImplWindow>> {
@@ -381,15 +449,46 @@ impl ImplWindow {
             Ok((app_name, pid as i32, display_serial))
         }
     }
+
+    pub fn active_info_mode() -> ActiveInfoMode {
+        ActiveInfoMode::Polling
+    }
+
+    pub fn focused_id() -> XCapResult<u32> {
+        let foreground_window = unsafe { GetForegroundWindow() };
+
+        if foreground_window.0.is_null() {
+            return Err(XCapError::new("Failed to get foreground window"));
+        }
+
+        Ok(foreground_window.0 as usize as u32)
+    }
+
+    /// 获取前台窗口的标题，只查询前台窗口，不枚举所有窗口
+    ///
+    /// 返回：(应用名称, 进程 ID, 窗口标题)
+    pub fn get_active_title() -> XCapResult<(String, i32, String)> {
+        let foreground_window = unsafe { GetForegroundWindow() };
+
+        if foreground_window.0.is_null() {
+            return Err(XCapError::new("Failed to get foreground window"));
+        }
+
+        let pid = get_window_pid(foreground_window);
+        let app_name = get_app_name(pid).unwrap_or_else(|_| "Unknown".to_string());
+        let title = get_window_title(foreground_window)?;
+
+        Ok((app_name, pid as i32, title))
+    }
 }
 
 impl ImplWindow {
     pub fn id(&self) -> XCapResult<u32> {
-        Ok(self.hwnd.0 as usize as u32)
+        Ok(self.handle as u32)
     }
 
     pub fn pid(&self) -> XCapResult<u32> {
-        let pid = get_window_pid(self.hwnd);
+        let pid = get_window_pid(self.hwnd());
         Ok(pid)
     }
 
@@ -398,22 +497,27 @@ impl ImplWindow {
     }
 
     pub fn title(&self) -> XCapResult<String> {
-        get_window_title(self.hwnd)
+        get_window_title(self.hwnd())
     }
 
     pub fn current_monitor(&self) -> XCapResult<ImplMonitor> {
-        let h_monitor = unsafe { MonitorFromWindow(self.hwnd, MONITOR_DEFAULTTONEAREST) };
+        let h_monitor = unsafe { MonitorFromWindow(self.hwnd(), MONITOR_DEFAULTTONEAREST) };
 
         Ok(ImplMonitor::new(h_monitor))
     }
 
+    /// 窗口坐标和截图都是物理像素
+    pub fn pixel_ratio(&self) -> XCapResult<f32> {
+        Ok(1.0)
+    }
+
     pub fn x(&self) -> XCapResult<i32> {
-        let window_info = get_window_info(self.hwnd)?;
+        let window_info = get_window_info(self.hwnd())?;
         Ok(window_info.rcClient.left)
     }
 
     pub fn y(&self) -> XCapResult<i32> {
-        let window_info = get_window_info(self.hwnd)?;
+        let window_info = get_window_info(self.hwnd())?;
         Ok(window_info.rcClient.top)
     }
 
@@ -429,7 +533,7 @@ impl ImplWindow {
         let mut z = hwnds.len() as i32;
         for &hwnd in hwnds.iter() {
             z -= 1;
-            if self.hwnd == hwnd {
+            if self.hwnd() == hwnd {
                 break;
             }
         }
@@ -438,25 +542,114 @@ impl ImplWindow {
     }
 
     pub fn width(&self) -> XCapResult<u32> {
-        let window_info = get_window_info(self.hwnd)?;
+        let window_info = get_window_info(self.hwnd())?;
         Ok((window_info.rcClient.right - window_info.rcClient.left) as u32)
     }
 
     pub fn height(&self) -> XCapResult<u32> {
-        let window_info = get_window_info(self.hwnd)?;
+        let window_info = get_window_info(self.hwnd())?;
         Ok((window_info.rcClient.bottom - window_info.rcClient.top) as u32)
     }
 
     pub fn is_minimized(&self) -> XCapResult<bool> {
-        unsafe { Ok(IsIconic(self.hwnd).as_bool()) }
+        unsafe { Ok(IsIconic(self.hwnd()).as_bool()) }
     }
 
     pub fn is_maximized(&self) -> XCapResult<bool> {
-        unsafe { Ok(IsZoomed(self.hwnd).as_bool()) }
+        unsafe { Ok(IsZoomed(self.hwnd()).as_bool()) }
+    }
+
+    pub fn normal_bounds(&self) -> XCapResult<Rect> {
+        let mut placement = WINDOWPLACEMENT {
+            length: mem::size_of::<WINDOWPLACEMENT>() as u32,
+            ..WINDOWPLACEMENT::default()
+        };
+
+        unsafe {
+            GetWindowPlacement(self.hwnd(), &mut placement)?;
+
+            let style = WINDOW_STYLE(GetWindowLongPtrW(self.hwnd(), GWL_STYLE) as u32);
+            let ex_style = WINDOW_EX_STYLE(GetWindowLongPtrW(self.hwnd(), GWL_EXSTYLE) as u32);
+
+            // 除了工具窗口，rcNormalPosition 是相对于工作区的坐标，需要加上任务栏占用的偏移
+            let mut rect = placement.rcNormalPosition;
+            if !ex_style.contains(WS_EX_TOOLWINDOW) {
+                let h_monitor = MonitorFromWindow(self.hwnd(), MONITOR_DEFAULTTONEAREST);
+                let mut monitor_info = MONITORINFO {
+                    cbSize: mem::size_of::<MONITORINFO>() as u32,
+                    ..MONITORINFO::default()
+                };
+                GetMonitorInfoW(h_monitor, &mut monitor_info).ok()?;
+
+                let offset_x = monitor_info.rcWork.left - monitor_info.rcMonitor.left;
+                let offset_y = monitor_info.rcWork.top - monitor_info.rcMonitor.top;
+                rect.left += offset_x;
+                rect.right += offset_x;
+                rect.top += offset_y;
+                rect.bottom += offset_y;
+            }
+
+            // rcNormalPosition 包含边框，x/y/width/height 是客户区，按窗口样式计算边框大小后去掉，
+            // 最小化时 rcClient 为空，不能直接用当前的边框
+            let mut frame = RECT::default();
+            AdjustWindowRectEx(
+                &mut frame,
+                style,
+                !GetMenu(self.hwnd()).is_invalid(),
+                ex_style,
+            )?;
+
+            let left = rect.left - frame.left;
+            let top = rect.top - frame.top;
+            let right = (rect.right - frame.right).max(left);
+            let bottom = (rect.bottom - frame.bottom).max(top);
+
+            Ok(Rect::new(
+                left,
+                top,
+                (right - left) as u32,
+                (bottom - top) as u32,
+            ))
+        }
     }
 
     pub fn is_focused(&self) -> XCapResult<bool> {
-        unsafe { Ok(GetForegroundWindow() == self.hwnd) }
+        unsafe { Ok(GetForegroundWindow() == self.hwnd()) }
+    }
+
+    pub fn layer(&self) -> XCapResult<WindowLayer> {
+        let class_name = get_class_name(self.hwnd())?;
+        let ex_style =
+            unsafe { WINDOW_EX_STYLE(GetWindowLongPtrW(self.hwnd(), GWL_EXSTYLE) as u32) };
+
+        Ok(get_window_layer(&class_name, ex_style))
+    }
+
+    /// 窗口可见的区域，不包括 Windows 10 之后不可见的调整大小边框
+    pub(super) fn frame_bounds(&self) -> XCapResult<Rect> {
+        let mut rect = RECT::default();
+        unsafe {
+            DwmGetWindowAttribute(
+                self.hwnd(),
+                DWMWA_EXTENDED_FRAME_BOUNDS,
+                &mut rect as *mut RECT as *mut c_void,
+                mem::size_of::<RECT>() as u32,
+            )?;
+        }
+
+        Ok(Rect::new(
+            rect.left,
+            rect.top,
+            (rect.right - rect.left) as u32,
+            (rect.bottom - rect.top) as u32,
+        ))
+    }
+
+    pub fn parent_id(&self) -> XCapResult<Option<u32>> {
+        // 对话框、弹出窗口由所有者窗口拥有，没有所有者时 GetWindow 返回错误
+        let owner = unsafe { GetWindow(self.hwnd(), GW_OWNER) };
+
+        Ok(owner.ok().map(|owner| owner.0 as usize as u32))
     }
 
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
@@ -474,6 +667,211 @@ impl ImplWindow {
             self.current_monitor()?.scale_factor()?
         };
 
-        capture_window(self.hwnd, scale_factor)
+        capture_window(self.hwnd(), scale_factor)
+    }
+
+    pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
+        // PrintWindow 只能绘制整个窗口，截取整个窗口后裁剪
+        let image = self.capture_image()?;
+
+        // 截图可能按显示器缩放比例放大过，区域坐标需要同样换算
+        let scale = image.width() as f32 / self.width()?.max(1) as f32;
+        let crop_x = ((x as f32 * scale).round() as u32).min(image.width());
+        let crop_y = ((y as f32 * scale).round() as u32).min(image.height());
+        let crop_width = ((width as f32 * scale).round() as u32).min(image.width() - crop_x);
+        let crop_height = ((height as f32 * scale).round() as u32).min(image.height() - crop_y);
+
+        Ok(imageops::crop_imm(&image, crop_x, crop_y, crop_width, crop_height).to_image())
+    }
+
+    pub fn can_capture(&self) -> XCapResult<bool> {
+        if !is_input_desktop_accessible() || !is_valid_window(self.hwnd()) {
+            return Ok(false);
+        }
+
+        // 设置了显示亲和性的窗口截图是黑色的，或者被排除在截图之外
+        let mut affinity = 0;
+        unsafe { GetWindowDisplayAffinity(self.hwnd(), &mut affinity)? };
+
+        Ok(affinity == WDA_NONE.0)
+    }
+
+    pub fn scroll(&self, clicks: u32) -> XCapResult<()> {
+        let rc_client = get_window_info(self.hwnd())?.rcClient;
+
+        // 滚轮消息发送给指针下的窗口，先把指针移动到窗口客户区中心
+        unsafe {
+            SetCursorPos(
+                (rc_client.left + rc_client.right) / 2,
+                (rc_client.top + rc_client.bottom) / 2,
+            )?;
+        }
+
+        // 负数表示向下滚动
+        let input = INPUT {
+            r#type: INPUT_MOUSE,
+            Anonymous: INPUT_0 {
+                mi: MOUSEINPUT {
+                    mouseData: (-(WHEEL_DELTA as i32)) as u32,
+                    dwFlags: MOUSEEVENTF_WHEEL,
+                    ..Default::default()
+                },
+            },
+        };
+        let inputs = vec![input; clicks as usize];
+
+        unsafe {
+            let sent = SendInput(&inputs, mem::size_of::<INPUT>() as i32);
+            if sent as usize != inputs.len() {
+                return Err(last_error("SendInput failed"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 使用 DXGI 桌面复制的脏矩形监听窗口内容的变化，脏矩形覆盖窗口所在的整个显示器，
+/// 只保留和窗口相交的部分
+pub(crate) struct ImplWindowDamage {
+    impl_window: ImplWindow,
+    duplication: IDXGIOutputDuplication,
+    // 显示器左上角的桌面坐标，脏矩形相对于显示器
+    output_x: i32,
+    output_y: i32,
+    // 创建后的第一帧包含整个桌面，不是窗口的变化
+    is_first_frame: bool,
+}
+
+impl ImplWindowDamage {
+    pub fn new(window_id: u32) -> XCapResult<ImplWindowDamage> {
+        let impl_window = ImplWindow::new(HWND(window_id as usize as *mut c_void));
+        let h_monitor = unsafe { MonitorFromWindow(impl_window.hwnd(), MONITOR_DEFAULTTONEAREST) };
+
+        let output_duplication = duplicate_output(|output_desc| output_desc.Monitor == h_monitor)?;
+        let output_desc = unsafe { output_duplication.output.GetDesc()? };
+
+        // 旋转的显示器上脏矩形是旋转前的坐标，交给调用方比较截图
+        if !matches!(
+            output_desc.Rotation,
+            DXGI_MODE_ROTATION_IDENTITY | DXGI_MODE_ROTATION_UNSPECIFIED
+        ) {
+            return Err(XCapError::NotSupported);
+        }
+
+        Ok(ImplWindowDamage {
+            impl_window,
+            duplication: output_duplication.duplication,
+            output_x: output_desc.DesktopCoordinates.left,
+            output_y: output_desc.DesktopCoordinates.top,
+            is_first_frame: true,
+        })
+    }
+
+    /// 等待窗口内容变化，返回变化的区域，超时没有变化时返回空列表
+    pub fn wait(&mut self, timeout: Duration) -> XCapResult<Vec<Rect>> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let mut frame_info = DXGI_OUTDUPL_FRAME_INFO::default();
+            let mut resource: Option<IDXGIResource> = None;
+
+            let acquired = unsafe {
+                self.duplication.AcquireNextFrame(
+                    remaining.as_millis() as u32,
+                    &mut frame_info,
+                    &mut resource,
+                )
+            };
+            match acquired {
+                Err(err) if err.code() == DXGI_ERROR_WAIT_TIMEOUT => return Ok(Vec::new()),
+                Err(err) => return Err(err.into()),
+                Ok(()) => {}
+            }
+
+            let dirty_rects = self.frame_rects(frame_info.TotalMetadataBufferSize);
+            unsafe { self.duplication.ReleaseFrame()? };
+
+            let rects = self.window_rects(dirty_rects?)?;
+            if mem::take(&mut self.is_first_frame) {
+                continue;
+            }
+
+            // 只有鼠标移动或者变化在窗口外时继续等待
+            if !rects.is_empty() || Instant::now() >= deadline {
+                return Ok(rects);
+            }
+        }
+    }
+
+    /// 读取当前帧的脏矩形和移动矩形的目标区域，坐标相对于显示器
+    fn frame_rects(&self, metadata_size: u32) -> XCapResult<Vec<RECT>> {
+        if metadata_size == 0 {
+            return Ok(Vec::new());
+        }
+
+        // 两种矩形都保存在大小为 metadata_size 的元数据中
+        let mut size = 0;
+        let mut dirty_rects =
+            vec![RECT::default(); metadata_size as usize / mem::size_of::<RECT>()];
+        unsafe {
+            self.duplication.GetFrameDirtyRects(
+                mem::size_of_val(dirty_rects.as_slice()) as u32,
+                dirty_rects.as_mut_ptr(),
+                &mut size,
+            )?;
+        }
+        dirty_rects.truncate(size as usize / mem::size_of::<RECT>());
+
+        let mut move_rects = vec![
+            DXGI_OUTDUPL_MOVE_RECT::default();
+            metadata_size as usize / mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>()
+        ];
+        unsafe {
+            self.duplication.GetFrameMoveRects(
+                mem::size_of_val(move_rects.as_slice()) as u32,
+                move_rects.as_mut_ptr(),
+                &mut size,
+            )?;
+        }
+        move_rects.truncate(size as usize / mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>());
+
+        dirty_rects.extend(move_rects.iter().map(|move_rect| move_rect.DestinationRect));
+
+        Ok(dirty_rects)
+    }
+
+    /// 把显示器坐标的矩形裁剪到窗口内，转换为相对窗口左上角的坐标
+    fn window_rects(&self, rects: Vec<RECT>) -> XCapResult<Vec<Rect>> {
+        let window_x = self.impl_window.x()?;
+        let window_y = self.impl_window.y()?;
+        let window = Rect::new(
+            window_x,
+            window_y,
+            self.impl_window.width()?,
+            self.impl_window.height()?,
+        );
+
+        Ok(rects
+            .iter()
+            .filter_map(|rect| {
+                let rect = Rect::new(
+                    self.output_x + rect.left,
+                    self.output_y + rect.top,
+                    (rect.right - rect.left).max(0) as u32,
+                    (rect.bottom - rect.top).max(0) as u32,
+                );
+                window.intersection(rect)
+            })
+            .map(|rect| {
+                Rect::new(
+                    rect.x - window_x,
+                    rect.y - window_y,
+                    rect.width,
+                    rect.height,
+                )
+            })
+            .collect())
     }
 }
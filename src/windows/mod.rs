@@ -1,7 +1,38 @@
 mod capture;
 mod display_info;
+pub mod external_encoder_surface;
 mod utils;
 
 pub mod impl_monitor;
+#[cfg(feature = "selector")]
+pub mod impl_selector;
 pub mod impl_video_recorder;
+#[cfg(feature = "virtual-camera")]
+pub mod impl_virtual_camera;
+#[cfg(feature = "virtual-display")]
+pub mod impl_virtual_display;
+pub mod impl_wake_lock;
 pub mod impl_window;
+pub mod window_preview;
+
+use windows::Win32::{Foundation::POINT, UI::WindowsAndMessaging::GetCursorPos};
+
+use crate::{Backend, BackendInfo, error::XCapResult, geometry::Point};
+
+/// 没有需要主动释放的全局资源，录制线程随 VideoRecorder 一起停止
+pub(crate) fn shutdown() -> XCapResult<()> {
+    Ok(())
+}
+
+/// 只有 GDI 一种截图方式
+pub(crate) fn backend_info() -> BackendInfo {
+    BackendInfo::new(Backend::Auto, false)
+}
+
+/// 鼠标指针的全局坐标，与显示器坐标一样是物理像素
+pub(crate) fn cursor_position() -> XCapResult<Point> {
+    let mut point = POINT::default();
+    unsafe { GetCursorPos(&mut point)? };
+
+    Ok(Point::new(point.x, point.y))
+}
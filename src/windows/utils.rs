@@ -1,4 +1,4 @@
-use std::mem;
+use std::{ffi::c_void, mem};
 
 use image::RgbaImage;
 use scopeguard::{ScopeGuard, guard};
@@ -16,15 +16,35 @@ use windows::{
         Graphics::Gdi::MONITORINFOEXW,
         System::{
             LibraryLoader::{GetProcAddress, LoadLibraryW},
+            Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS},
             Registry::{HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ, RegGetValueW},
+            StationsAndDesktops::{
+                CloseDesktop, DESKTOP_CONTROL_FLAGS, DESKTOP_SWITCHDESKTOP, OpenInputDesktop,
+            },
             Threading::{OpenProcess, PROCESS_ACCESS_RIGHTS},
         },
-        UI::WindowsAndMessaging::{GetWindowInfo, WINDOWINFO},
+        UI::WindowsAndMessaging::{
+            GetWindowInfo, SPI_GETSCREENSAVERRUNNING, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+            SystemParametersInfoW, WINDOWINFO,
+        },
     },
-    core::{HRESULT, PCWSTR, s, w},
+    core::{BOOL, HRESULT, PCWSTR, s, w},
+};
+
+use crate::{
+    XCapError, conversion,
+    error::{PlatformError, XCapResult},
 };
 
-use crate::{XCapError, error::XCapResult};
+/// GetLastError 转换为 HRESULT，保留错误码，用户反馈问题时可以据此定位原因
+pub(super) fn last_error(message: &str) -> XCapError {
+    let hresult = unsafe { GetLastError() }.to_hresult();
+
+    XCapError::platform(
+        message,
+        PlatformError::new("HRESULT", hresult.0 as i64, hresult.message()),
+    )
+}
 
 pub(super) fn get_build_number() -> u32 {
     unsafe {
@@ -70,12 +90,14 @@ pub(super) fn get_os_major_version() -> u8 {
 }
 
 pub(super) fn bgra_to_rgba(mut buffer: Vec<u8>) -> Vec<u8> {
-    let is_old_version = get_os_major_version() < 8;
-    for src in buffer.chunks_exact_mut(4) {
-        src.swap(0, 2);
-        // fix https://github.com/nashaofu/xcap/issues/92#issuecomment-1910014951
-        if src[3] == 0 && is_old_version {
-            src[3] = 255;
+    conversion::bgra_to_rgba_in_place(&mut buffer);
+
+    // fix https://github.com/nashaofu/xcap/issues/92#issuecomment-1910014951
+    if get_os_major_version() < 8 {
+        for pixel in buffer.chunks_exact_mut(4) {
+            if pixel[3] == 0 {
+                pixel[3] = 255;
+            }
         }
     }
 
@@ -123,10 +145,7 @@ pub(super) fn load_library(
         let hmodule = LoadLibraryW(lib_filename)?;
 
         if hmodule.is_invalid() {
-            return Err(XCapError::new(format!(
-                "LoadLibraryW error {:?}",
-                GetLastError()
-            )));
+            return Err(last_error("LoadLibraryW failed"));
         }
 
         let scope_guard_hmodule = guard(hmodule, |val| {
@@ -148,10 +167,7 @@ pub(super) fn open_process(
         let handle = OpenProcess(dw_desired_access, b_inherit_handle, dw_process_id)?;
 
         if handle.is_invalid() {
-            return Err(XCapError::new(format!(
-                "OpenProcess error {:?}",
-                GetLastError()
-            )));
+            return Err(last_error("OpenProcess failed"));
         }
 
         let scope_guard_handle = guard(handle, |val| {
@@ -164,9 +180,8 @@ pub(super) fn open_process(
     }
 }
 
-pub(super) fn get_monitor_config(
-    monitor_info_ex_w: MONITORINFOEXW,
-) -> XCapResult<DISPLAYCONFIG_TARGET_DEVICE_NAME> {
+// 查询当前所有活动的显示路径，每个路径对应一个源（桌面区域）到一个目标（物理显示器）
+fn query_active_paths() -> XCapResult<Vec<DISPLAYCONFIG_PATH_INFO>> {
     unsafe {
         let mut number_of_paths = 0;
         let mut number_of_modes = 0;
@@ -190,44 +205,67 @@ pub(super) fn get_monitor_config(
         )
         .ok()?;
 
-        for path in paths {
-            let mut source = DISPLAYCONFIG_SOURCE_DEVICE_NAME {
-                header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
-                    r#type: DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
-                    size: mem::size_of::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>() as u32,
-                    adapterId: path.sourceInfo.adapterId,
-                    id: path.sourceInfo.id,
-                },
-                ..DISPLAYCONFIG_SOURCE_DEVICE_NAME::default()
-            };
+        paths.truncate(number_of_paths as usize);
 
-            if DisplayConfigGetDeviceInfo(&mut source.header) != 0 {
-                continue;
-            }
+        Ok(paths)
+    }
+}
 
-            if source.viewGdiDeviceName != monitor_info_ex_w.szDevice {
-                continue;
-            }
+// 判断路径的源是否为该显示器
+fn is_monitor_path(path: &DISPLAYCONFIG_PATH_INFO, monitor_info_ex_w: &MONITORINFOEXW) -> bool {
+    let mut source = DISPLAYCONFIG_SOURCE_DEVICE_NAME {
+        header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
+            r#type: DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+            size: mem::size_of::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>() as u32,
+            adapterId: path.sourceInfo.adapterId,
+            id: path.sourceInfo.id,
+        },
+        ..DISPLAYCONFIG_SOURCE_DEVICE_NAME::default()
+    };
 
-            let mut target = DISPLAYCONFIG_TARGET_DEVICE_NAME {
-                header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
-                    r#type: DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
-                    size: mem::size_of::<DISPLAYCONFIG_TARGET_DEVICE_NAME>() as u32,
-                    adapterId: path.sourceInfo.adapterId,
-                    id: path.targetInfo.id,
-                },
-                ..DISPLAYCONFIG_TARGET_DEVICE_NAME::default()
-            };
+    if unsafe { DisplayConfigGetDeviceInfo(&mut source.header) } != 0 {
+        return false;
+    }
 
-            if DisplayConfigGetDeviceInfo(&mut target.header) != 0 {
-                continue;
-            }
+    source.viewGdiDeviceName == monitor_info_ex_w.szDevice
+}
 
-            return Ok(target);
+pub(super) fn get_monitor_config(
+    monitor_info_ex_w: MONITORINFOEXW,
+) -> XCapResult<DISPLAYCONFIG_TARGET_DEVICE_NAME> {
+    for path in query_active_paths()? {
+        if !is_monitor_path(&path, &monitor_info_ex_w) {
+            continue;
         }
 
-        Err(XCapError::new("Get monitor name failed"))
+        let mut target = DISPLAYCONFIG_TARGET_DEVICE_NAME {
+            header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
+                r#type: DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
+                size: mem::size_of::<DISPLAYCONFIG_TARGET_DEVICE_NAME>() as u32,
+                adapterId: path.sourceInfo.adapterId,
+                id: path.targetInfo.id,
+            },
+            ..DISPLAYCONFIG_TARGET_DEVICE_NAME::default()
+        };
+
+        if unsafe { DisplayConfigGetDeviceInfo(&mut target.header) } != 0 {
+            continue;
+        }
+
+        return Ok(target);
     }
+
+    Err(XCapError::new("Get monitor name failed"))
+}
+
+/// 显示器对应的物理显示器数量，复制模式下多个目标共用同一个源，大于 1 说明处于镜像状态
+pub(super) fn get_monitor_target_count(monitor_info_ex_w: MONITORINFOEXW) -> XCapResult<usize> {
+    let count = query_active_paths()?
+        .iter()
+        .filter(|path| is_monitor_path(path, &monitor_info_ex_w))
+        .count();
+
+    Ok(count)
 }
 
 pub fn get_window_info(hwnd: HWND) -> XCapResult<WINDOWINFO> {
@@ -372,3 +410,44 @@ mod tests {
         }
     }
 }
+
+/// 屏保正在运行或者会话已锁定时返回 true
+pub(super) fn is_display_idle() -> bool {
+    unsafe {
+        let mut is_screensaver_running = BOOL::default();
+        let result = SystemParametersInfoW(
+            SPI_GETSCREENSAVERRUNNING,
+            0,
+            Some(&mut is_screensaver_running as *mut BOOL as *mut c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        );
+
+        if result.is_ok() && is_screensaver_running.as_bool() {
+            return true;
+        }
+    }
+
+    !is_input_desktop_accessible()
+}
+
+/// 锁屏或者 UAC 提示时输入桌面切换到了安全桌面（Winlogon），普通进程无法打开，也无法截图
+pub(super) fn is_input_desktop_accessible() -> bool {
+    unsafe {
+        match OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), false, DESKTOP_SWITCHDESKTOP) {
+            Ok(desktop) => {
+                let _ = CloseDesktop(desktop);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+pub(super) fn is_on_battery() -> bool {
+    unsafe {
+        let mut power_status = SYSTEM_POWER_STATUS::default();
+
+        // ACLineStatus: 0 为电池供电，1 为交流电源，255 为未知
+        GetSystemPowerStatus(&mut power_status).is_ok() && power_status.ACLineStatus == 0
+    }
+}
@@ -0,0 +1,142 @@
+use windows::Win32::{
+    Foundation::{HWND, RECT},
+    Graphics::Dwm::{
+        DWM_THUMBNAIL_PROPERTIES, DWM_TNP_OPACITY, DWM_TNP_RECTDESTINATION,
+        DWM_TNP_SOURCECLIENTAREAONLY, DWM_TNP_VISIBLE, DwmQueryThumbnailSourceSize,
+        DwmRegisterThumbnail, DwmUnregisterThumbnail, DwmUpdateThumbnailProperties,
+    },
+};
+
+use crate::{
+    Window,
+    error::{XCapError, XCapResult},
+    geometry::Rect,
+};
+
+/// A live preview of a window, drawn by the desktop window manager into a window of the
+/// calling process without copying any pixels through it, see [`WindowExt::preview`].
+///
+/// The preview follows the source window's content until it is dropped. Its pixels cannot be
+/// read back; use [`ThumbnailStream`](crate::ThumbnailStream) when they are needed.
+#[derive(Debug)]
+pub struct WindowPreview {
+    thumbnail: isize,
+    properties: DWM_THUMBNAIL_PROPERTIES,
+}
+
+impl WindowPreview {
+    fn new(destination: HWND, source: HWND, rect: Rect) -> XCapResult<WindowPreview> {
+        // 目标窗口必须是当前进程的顶层窗口，否则 DWM 返回 E_INVALIDARG
+        let thumbnail = unsafe { DwmRegisterThumbnail(destination, source)? };
+
+        let mut preview = WindowPreview {
+            thumbnail,
+            properties: DWM_THUMBNAIL_PROPERTIES {
+                dwFlags: DWM_TNP_RECTDESTINATION
+                    | DWM_TNP_OPACITY
+                    | DWM_TNP_VISIBLE
+                    | DWM_TNP_SOURCECLIENTAREAONLY,
+                rcDestination: to_win_rect(rect),
+                opacity: 255,
+                fVisible: true.into(),
+                fSourceClientAreaOnly: false.into(),
+                ..Default::default()
+            },
+        };
+        preview.update()?;
+
+        Ok(preview)
+    }
+
+    /// DWM 只在属性更新时重新绘制，每次修改都提交全部属性
+    fn update(&mut self) -> XCapResult<()> {
+        unsafe { DwmUpdateThumbnailProperties(self.thumbnail, &self.properties)? };
+
+        Ok(())
+    }
+
+    /// The size of the source window in pixels, to keep the aspect ratio when choosing the
+    /// destination rectangle.
+    pub fn source_size(&self) -> XCapResult<(u32, u32)> {
+        let size = unsafe { DwmQueryThumbnailSourceSize(self.thumbnail)? };
+
+        Ok((size.cx.max(0) as u32, size.cy.max(0) as u32))
+    }
+
+    /// Move the preview to `rect`, in client coordinates of the destination window. The source
+    /// is scaled to fill it.
+    pub fn set_rect(&mut self, rect: Rect) -> XCapResult<()> {
+        if rect.width == 0 || rect.height == 0 {
+            return Err(XCapError::new("Preview rect must not be empty"));
+        }
+
+        self.properties.rcDestination = to_win_rect(rect);
+        self.update()
+    }
+
+    /// Opacity from 0 to 255. Defaults to 255.
+    pub fn set_opacity(&mut self, opacity: u8) -> XCapResult<()> {
+        self.properties.opacity = opacity;
+        self.update()
+    }
+
+    /// Show or hide the preview without unregistering it. Defaults to visible.
+    pub fn set_visible(&mut self, visible: bool) -> XCapResult<()> {
+        self.properties.fVisible = visible.into();
+        self.update()
+    }
+
+    /// Only draw the source window's client area, without its frame. Defaults to false.
+    pub fn set_client_area_only(&mut self, client_area_only: bool) -> XCapResult<()> {
+        self.properties.fSourceClientAreaOnly = client_area_only.into();
+        self.update()
+    }
+}
+
+impl Drop for WindowPreview {
+    fn drop(&mut self) {
+        if let Err(err) = unsafe { DwmUnregisterThumbnail(self.thumbnail) } {
+            log::error!("DwmUnregisterThumbnail failed: {err:?}");
+        }
+    }
+}
+
+fn to_win_rect(rect: Rect) -> RECT {
+    RECT {
+        left: rect.x,
+        top: rect.y,
+        right: rect.x + rect.width as i32,
+        bottom: rect.y + rect.height as i32,
+    }
+}
+
+/// Windows-only live window previews for pickers and task switchers that only display the
+/// window, e.g. while hovering over it.
+///
+/// ```no_run
+/// use windows::Win32::Foundation::HWND;
+/// use xcap::{Rect, Window, WindowExt};
+///
+/// // a top-level window created by the application, e.g. with winit
+/// let picker: HWND = todo!();
+///
+/// let window = Window::all().unwrap().remove(0);
+/// let mut preview = window.preview(picker, Rect::new(10, 10, 320, 180)).unwrap();
+/// preview.set_opacity(200).unwrap();
+/// ```
+pub trait WindowExt {
+    /// Draw a live preview of the window into `rect`, in client coordinates of `destination`,
+    /// until the returned [`WindowPreview`] is dropped. `destination` must be a top-level
+    /// window of the calling process.
+    fn preview(&self, destination: HWND, rect: Rect) -> XCapResult<WindowPreview>;
+}
+
+impl WindowExt for Window {
+    fn preview(&self, destination: HWND, rect: Rect) -> XCapResult<WindowPreview> {
+        if rect.width == 0 || rect.height == 0 {
+            return Err(XCapError::new("Preview rect must not be empty"));
+        }
+
+        WindowPreview::new(destination, self.impl_window.hwnd(), rect)
+    }
+}
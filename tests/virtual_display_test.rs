@@ -0,0 +1,19 @@
+// 在虚拟显示器上测试截图，需要开启 virtual-display feature：
+// cargo test --features virtual-display --test virtual_display_test
+
+#[cfg(feature = "virtual-display")]
+mod tests {
+    use xcap::VirtualDisplay;
+
+    #[test]
+    fn test_capture_virtual_display() {
+        let display = VirtualDisplay::new(640, 480).unwrap();
+        let monitor = display.monitor().unwrap();
+
+        assert_eq!(monitor.width().unwrap(), 640);
+        assert_eq!(monitor.height().unwrap(), 480);
+
+        let image = monitor.capture_image().unwrap();
+        assert_eq!(image.dimensions(), (640, 480));
+    }
+}